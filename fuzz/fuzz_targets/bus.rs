@@ -0,0 +1,71 @@
+// Bus accesses across the 24-bit address space, with no cartridge or a
+// LoROM/HiROM cartridge built from the input. Run with:
+//
+//   cargo fuzz run bus
+//
+// The first byte picks the cartridge; the rest is a list of 5-byte
+// operations: kind, 24-bit address (little endian), value.
+#![no_main]
+
+use ccsnes::cartridge::Cartridge;
+use ccsnes::memory::Bus;
+use libfuzzer_sys::fuzz_target;
+
+fn cartridge(kind: u8, data: &[u8]) -> Option<Cartridge> {
+    let (size, header, mapper) = match kind % 3 {
+        1 => (0x8000, 0x7FC0, 0x20),
+        2 => (0x10000, 0xFFC0, 0x21),
+        _ => return None,
+    };
+    let mut rom = vec![0; size];
+    for (byte, value) in rom.iter_mut().zip(data.iter().cycle()) {
+        *byte = *value;
+    }
+    rom[header..header + 21].copy_from_slice(b"FUZZ                 ");
+    rom[header + 0x15] = mapper;
+    rom[header + 0x17] = if mapper == 0x20 { 5 } else { 6 };
+    rom[header + 0x18] = data.first().map_or(0, |size| size % 8);
+    rom[header + 0x1C..header + 0x20].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00]);
+    Cartridge::load(&rom).ok()
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&kind, ops)) = data.split_first() else { return };
+    let mut cartridge = cartridge(kind, ops);
+    let mut bus = Bus::new();
+    if let Some(cartridge) = cartridge.as_mut() {
+        bus.install_cartridge(cartridge);
+    }
+
+    for op in ops.chunks_exact(5) {
+        let address = u32::from_le_bytes([op[1], op[2], op[3], 0]);
+        let value = op[4];
+        match op[0] % 6 {
+            0 => {
+                bus.read8(address);
+            }
+            1 => bus.write8(address, value),
+            2 => {
+                bus.read16(address);
+            }
+            3 => bus.write16(address, u16::from_le_bytes([value, op[3]])),
+            4 => {
+                bus.read24(address | 0xFF000000);
+            }
+            _ => {
+                // A write through one mirror must read back through the others
+                bus.write8(address, value);
+                let bank = address >> 16;
+                let offset = address & 0xFFFF;
+                match bank {
+                    0x7E | 0x7F => assert_eq!(bus.read8(address), value, "write to ${:06X}", address),
+                    0x00..=0x3F | 0x80..=0xBF if offset < 0x2000 => {
+                        assert_eq!(bus.read8(0x7E0000 | offset), value, "write to ${:06X}", address);
+                        assert_eq!(bus.read8(0x800000 | offset), value, "write to ${:06X}", address);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+});
@@ -0,0 +1,115 @@
+// Integration point for achievement runtimes (rcheevos) and custom
+// achievement scripts. A hook names the addresses it watches once; after
+// every frame they are read in one batch, without side effects, and handed
+// over together with the previous frame's values. Resets, state loads and
+// ROM changes are reported so hit counts can be reset.
+use crate::memory::Bus;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    // A cartridge was inserted (after its reset)
+    RomLoaded,
+    // The cartridge was taken out
+    RomUnloaded,
+    Reset,
+    // Memory jumped to a save state's contents
+    StateLoaded,
+}
+
+// Values of the watched addresses, in the order they were registered
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryPoll {
+    addresses: Vec<u32>,
+    values: Vec<u8>,
+    previous: Vec<u8>,
+}
+
+impl MemoryPoll {
+    pub fn new(addresses: Vec<u32>) -> Self {
+        let len = addresses.len();
+        Self { addresses, values: vec![0; len], previous: vec![0; len] }
+    }
+
+    pub fn addresses(&self) -> &[u32] {
+        &self.addresses
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    // Read every address, keeping the last values as previous
+    pub fn poll(&mut self, bus: &Bus) {
+        std::mem::swap(&mut self.values, &mut self.previous);
+        for (value, &address) in self.values.iter_mut().zip(&self.addresses) {
+            *value = bus.peek8(address);
+        }
+    }
+
+    // Read every address into both current and previous, so nothing
+    // appears to change across a jump (state load, reset)
+    pub fn prime(&mut self, bus: &Bus) {
+        self.poll(bus);
+        self.previous.copy_from_slice(&self.values);
+    }
+
+    pub fn values(&self) -> &[u8] {
+        &self.values
+    }
+
+    pub fn previous(&self) -> &[u8] {
+        &self.previous
+    }
+
+    pub fn value(&self, index: usize) -> Option<u8> {
+        self.values.get(index).copied()
+    }
+
+    // Little-endian 16-bit value from two consecutive entries
+    pub fn value16(&self, index: usize) -> Option<u16> {
+        Some(u16::from_le_bytes([self.value(index)?, self.value(index + 1)?]))
+    }
+
+    // Indexes whose value differs from the previous frame
+    pub fn changed(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.values.len()).filter(|&i| self.values[i] != self.previous[i])
+    }
+}
+
+// Read-only access for lookups outside the polled list
+pub struct MemoryPeek<'a> {
+    bus: &'a Bus,
+}
+
+impl<'a> MemoryPeek<'a> {
+    pub fn new(bus: &'a Bus) -> Self {
+        Self { bus }
+    }
+
+    pub fn read8(&self, address: u32) -> u8 {
+        self.bus.peek8(address)
+    }
+
+    // Fill `buffer` from consecutive addresses (wrapping within the bank)
+    pub fn read_block(&self, address: u32, buffer: &mut [u8]) {
+        let bank = address & 0xFF0000;
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.bus.peek8(bank | (address as u16).wrapping_add(i as u16) as u32);
+        }
+    }
+}
+
+pub trait AchievementHook: Send {
+    // Bus addresses polled after every frame ($7E0000-$7FFFFF for WRAM,
+    // the cartridge's SRAM banks for saves)
+    fn addresses(&self) -> Vec<u32>;
+
+    // After each frame, with `frame` counted by the PPU
+    fn on_frame(&mut self, frame: u64, poll: &MemoryPoll, memory: &MemoryPeek);
+
+    fn on_event(&mut self, _event: HookEvent) {}
+}
@@ -2,6 +2,19 @@
 
 use crate::savestate::{DspState, ChannelState};
 
+// How voices are resampled between BRR samples. Gaussian is what the
+// hardware does, and dulls the highs; linear is the cleaner modern mix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interpolation {
+    #[default]
+    Gaussian,
+    Linear,
+}
+
+// Spread of the gaussian kernel in samples, matching the hardware table's
+// weights (the neighbours of a sample get about 0.29 of its weight)
+const GAUSSIAN_SIGMA: f32 = 0.63;
+
 pub struct Dsp {
     // 8 audio channels
     channels: [AudioChannel; 8],
@@ -12,8 +25,18 @@ pub struct Dsp {
     echo_volume_left: u8,
     echo_volume_right: u8,
     
+    // Everything written to $00-$7F, read back as written. Save states
+    // only keep the decoded fields above, so after a load this holds
+    // those and the rest reads as zero.
+    registers: [u8; 128],
+    
     // Sample rate counter
     sample_counter: u32,
+    
+    // Mixing preferences rather than hardware state, so resets and state
+    // loads keep them
+    interpolation: Interpolation,
+    voice_gain: [f32; 8],
 }
 
 #[derive(Clone, Copy)]
@@ -53,7 +76,10 @@ impl Dsp {
             main_volume_right: 0,
             echo_volume_left: 0,
             echo_volume_right: 0,
+            registers: [0; 128],
             sample_counter: 0,
+            interpolation: Interpolation::default(),
+            voice_gain: [1.0; 8],
         }
     }
 
@@ -63,6 +89,7 @@ impl Dsp {
         self.main_volume_right = 0;
         self.echo_volume_left = 0;
         self.echo_volume_right = 0;
+        self.registers = [0; 128];
         self.sample_counter = 0;
     }
 
@@ -81,7 +108,59 @@ impl Dsp {
         phase.sin() * amplitude
     }
 
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+    
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+    }
+    
+    pub fn voice_gain(&self, voice: usize) -> f32 {
+        self.voice_gain.get(voice).copied().unwrap_or(0.0)
+    }
+    
+    // Scale one voice in the mix: 1.0 plays it as the game set it, 0.0
+    // silences it, up to 2.0 brings it forward
+    pub fn set_voice_gain(&mut self, voice: usize, gain: f32) {
+        if let Some(slot) = self.voice_gain.get_mut(voice) {
+            *slot = if gain.is_nan() { 0.0 } else { gain.clamp(0.0, 2.0) };
+        }
+    }
+    
+    // Resample a voice at `fraction` (0-4095, the low 12 bits of its pitch
+    // counter) between the second and third of four consecutive samples,
+    // with its gain applied. Voices aren't decoded yet (step() still plays
+    // a test tone), so this is where the mixer will resample them.
+    pub fn interpolate(&self, voice: usize, samples: [i16; 4], fraction: u16) -> i16 {
+        let t = (fraction & 0x0FFF) as f32 / 4096.0;
+        let weights = match self.interpolation {
+            Interpolation::Gaussian => {
+                let weights = [t + 1.0, t, 1.0 - t, 2.0 - t]
+                    .map(|distance| (-distance * distance / (2.0 * GAUSSIAN_SIGMA * GAUSSIAN_SIGMA)).exp());
+                let total: f32 = weights.iter().sum();
+                weights.map(|weight| weight / total)
+            }
+            Interpolation::Linear => [0.0, 1.0 - t, t, 0.0],
+        };
+        let mixed: f32 = samples.iter().zip(weights).map(|(&sample, weight)| sample as f32 * weight).sum();
+        (mixed * self.voice_gain(voice)).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+    }
+
     pub fn write_register(&mut self, address: u8, value: u8) {
+        // $80-$FF mirror $00-$7F for reads only
+        if address >= 0x80 {
+            return;
+        }
+        self.registers[address as usize] = value;
+        match address {
+            0x0C => self.main_volume_left = value,
+            0x1C => self.main_volume_right = value,
+            0x2C => self.echo_volume_left = value,
+            0x3C => self.echo_volume_right = value,
+            _ => {}
+        }
+        
         // TODO: Implement DSP register writes
         let channel = (address >> 4) & 0x07;
         let register = address & 0x0F;
@@ -114,13 +193,18 @@ impl Dsp {
             match register {
                 0x8 => (self.channels[channel as usize].envelope & 0xFF) as u8,
                 0x9 => ((self.channels[channel as usize].envelope >> 8) & 0xFF) as u8,
-                _ => 0,
+                _ => self.registers[(address & 0x7F) as usize],
             }
         } else {
             0
         }
     }
     
+    // The register file as the SPC700 reads it through $F3
+    pub fn registers(&self) -> [u8; 128] {
+        std::array::from_fn(|address| self.read_register(address as u8))
+    }
+    
     // Save state functionality
     pub fn save_state(&self) -> DspState {
         let channel_states: Vec<ChannelState> = self.channels.iter().map(|ch| {
@@ -163,5 +247,24 @@ impl Dsp {
         self.echo_volume_left = state.echo_volume_left;
         self.echo_volume_right = state.echo_volume_right;
         self.sample_counter = state.sample_counter;
+        
+        self.registers = [0; 128];
+        for (i, ch) in self.channels.iter().enumerate() {
+            let base = i * 0x10;
+            self.registers[base..base + 8].copy_from_slice(&[
+                ch.volume_left,
+                ch.volume_right,
+                ch.pitch as u8,
+                (ch.pitch >> 8) as u8,
+                ch.source_number,
+                ch.adsr as u8,
+                (ch.adsr >> 8) as u8,
+                ch.gain,
+            ]);
+        }
+        self.registers[0x0C] = self.main_volume_left;
+        self.registers[0x1C] = self.main_volume_right;
+        self.registers[0x2C] = self.echo_volume_left;
+        self.registers[0x3C] = self.echo_volume_right;
     }
 }
\ No newline at end of file
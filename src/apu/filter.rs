@@ -0,0 +1,39 @@
+// The analog stage after the DSP. Real consoles roll off the highs on the
+// way to the output jack, which (with gaussian interpolation) is much of
+// the "muffled" SNES sound; a first-order low-pass is close enough.
+use std::f32::consts::PI;
+
+// Where the roll-off sits by default; lower is more muffled
+pub const DEFAULT_CUTOFF_HZ: u32 = 8_000;
+
+// Rate the DSP produces samples at
+const SAMPLE_RATE: f32 = 32_000.0;
+
+#[derive(Debug, Clone)]
+pub struct LowPass {
+    cutoff_hz: u32,
+    coefficient: f32,
+    last: f32,
+}
+
+impl LowPass {
+    // Cutoffs are limited to 20 Hz up to the Nyquist frequency
+    pub fn new(cutoff_hz: u32) -> Self {
+        let cutoff_hz = cutoff_hz.clamp(20, SAMPLE_RATE as u32 / 2);
+        let coefficient = 1.0 - (-2.0 * PI * cutoff_hz as f32 / SAMPLE_RATE).exp();
+        Self { cutoff_hz, coefficient, last: 0.0 }
+    }
+
+    pub fn cutoff_hz(&self) -> u32 {
+        self.cutoff_hz
+    }
+
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.last += self.coefficient * (sample - self.last);
+        self.last
+    }
+
+    pub fn reset(&mut self) {
+        self.last = 0.0;
+    }
+}
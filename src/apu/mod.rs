@@ -1,25 +1,84 @@
 pub mod spc700;
 pub mod dsp;
+pub mod filter;
+pub mod reconcile;
+pub mod spc_file;
+pub mod volume;
 mod spc700_instructions;
 
 use self::spc700::Spc700;
 use self::dsp::Dsp;
+use self::filter::LowPass;
+use crate::debug::apu_ports::{PortSide, PortTrace};
+use crate::frame_hash::{FrameHasher, FNV_OFFSET_BASIS};
 use crate::savestate::ApuState;
+use crate::{EmulatorError, Result};
+
+// Most CPU cycles the APU may fall behind before it catches up on its own,
+// about one scanline; keeps audio generation spread across the frame
+pub const MAX_PENDING_CYCLES: u64 = 256;
+
+// Range of APU clock ratios accepted by set_clock_ratio; 1.0 is the stock
+// console, and some late revisions and clones run a little off it
+pub const MIN_CLOCK_RATIO: f64 = 0.5;
+pub const MAX_CLOCK_RATIO: f64 = 2.0;
+
+// Clock ratios are kept in 16.16 fixed point, so scheduling rounds the
+// same way on every target
+const CLOCK_RATIO_ONE: u32 = 1 << 16;
 
 pub struct Apu {
     spc700: Spc700,
     dsp: Dsp,
     audio_buffer: Vec<f32>,
-    dsp_address: u8,
+    // CPU cycles not yet run on the APU. The CPU only sees the APU through
+    // the ports, so it runs in batches: when a port is accessed, at the end
+    // of a frame, or when too far behind.
+    pending_cycles: u64,
+    max_pending: u64,
+    // APU cycles per CPU cycle in 16.16 fixed point, and the fraction of a
+    // cycle carried between batches
+    clock_ratio: u32,
+    clock_fraction: u32,
+    port_trace: PortTrace,
+    // Boot shortcuts (see Quirks::apu_fast_boot and apu_fake_ack)
+    fast_boot: bool,
+    fake_ack: bool,
+    // Values returned to the CPU in fake-ack mode
+    ack_ports: [u8; 4],
+    // CPU port reads since power-on and the port last read, for the desync
+    // watchdog to spot a CPU polling the APU
+    cpu_reads: u64,
+    last_read: u8,
+    // Output low-pass, None for the DSP's output as is
+    output_filter: Option<LowPass>,
+    // Samples generated since the last end_frame, and the hash of those of
+    // the frame before (see frame_hash)
+    audio_hasher: FrameHasher,
+    frame_audio_hash: u64,
 }
 
+const IPL_READY_PORTS: [u8; 4] = [0xAA, 0xBB, 0, 0];
+
 impl Apu {
     pub fn new() -> Self {
         Self {
             spc700: Spc700::new(),
             dsp: Dsp::new(),
             audio_buffer: Vec::new(),
-            dsp_address: 0,
+            pending_cycles: 0,
+            max_pending: MAX_PENDING_CYCLES,
+            clock_ratio: CLOCK_RATIO_ONE,
+            clock_fraction: 0,
+            port_trace: PortTrace::new(),
+            fast_boot: false,
+            fake_ack: false,
+            ack_ports: IPL_READY_PORTS,
+            cpu_reads: 0,
+            last_read: 0,
+            output_filter: None,
+            audio_hasher: FrameHasher::new(),
+            frame_audio_hash: FNV_OFFSET_BASIS,
         }
     }
 
@@ -27,21 +86,120 @@ impl Apu {
         self.spc700.reset();
         self.dsp.reset();
         self.audio_buffer.clear();
-        self.dsp_address = 0;
+        self.audio_hasher = FrameHasher::new();
+        self.frame_audio_hash = FNV_OFFSET_BASIS;
+        self.pending_cycles = 0;
+        self.clock_fraction = 0;
+        self.ack_ports = IPL_READY_PORTS;
+        if let Some(filter) = self.output_filter.as_mut() {
+            filter.reset();
+        }
+        if self.fast_boot {
+            self.spc700.skip_ipl();
+        }
+    }
+
+    // Power cycle: unlike reset, audio RAM starts over too, so no sound
+    // program of the previous game is left behind
+    pub fn power_on(&mut self) {
+        self.spc700 = Spc700::new();
+        self.cpu_reads = 0;
+        self.reset();
+    }
+
+    // Takes effect at the next reset, or now if the APU hasn't run yet
+    pub fn set_fast_boot(&mut self, enabled: bool) {
+        self.fast_boot = enabled;
+        if enabled && self.spc700.cycles() == 0 && self.pending_cycles == 0 {
+            self.spc700.skip_ipl();
+        }
+    }
+
+    pub fn set_fake_ack(&mut self, enabled: bool) {
+        self.fake_ack = enabled;
+    }
+
+    // Run the output through a low-pass at `cutoff_hz`, like the console's
+    // analog stage, or leave it unfiltered with None
+    pub fn set_output_filter(&mut self, cutoff_hz: Option<u32>) {
+        self.output_filter = cutoff_hz.map(LowPass::new);
+    }
+
+    pub fn output_filter(&self) -> Option<u32> {
+        self.output_filter.as_ref().map(LowPass::cutoff_hz)
+    }
+
+    // Run the APU `ratio` times as fast relative to the CPU as the stock
+    // console does, for matching hardware variants and testing how timing
+    // sensitive a game's sound code is. Sound pitch follows the clock.
+    // Kept across resets.
+    pub fn set_clock_ratio(&mut self, ratio: f64) -> Result<()> {
+        if !(MIN_CLOCK_RATIO..=MAX_CLOCK_RATIO).contains(&ratio) {
+            return Err(EmulatorError::config(format!(
+                "APU clock ratio {} is out of range ({} to {})",
+                ratio, MIN_CLOCK_RATIO, MAX_CLOCK_RATIO
+            )));
+        }
+        self.clock_ratio = (ratio * CLOCK_RATIO_ONE as f64).round() as u32;
+        self.clock_fraction = 0;
+        Ok(())
+    }
+    
+    pub fn clock_ratio(&self) -> f64 {
+        self.clock_ratio as f64 / CLOCK_RATIO_ONE as f64
+    }
+    
+    // Owe the APU `cycles` CPU cycles, scaled by the clock ratio, running
+    // them once the batch is full
+    pub fn schedule(&mut self, cycles: u32) {
+        let scaled = cycles as u64 * self.clock_ratio as u64 + self.clock_fraction as u64;
+        self.clock_fraction = (scaled % CLOCK_RATIO_ONE as u64) as u32;
+        self.pending_cycles += scaled / CLOCK_RATIO_ONE as u64;
+        if self.pending_cycles >= self.max_pending {
+            self.catch_up();
+        }
+    }
+
+    // Run every pending cycle; call before anything observes the APU
+    pub fn catch_up(&mut self) {
+        for _ in 0..std::mem::take(&mut self.pending_cycles) {
+            self.step();
+        }
+    }
+
+    pub fn pending_cycles(&self) -> u64 {
+        self.pending_cycles
+    }
+
+    // Batch size; 1 runs the APU in lockstep with the CPU
+    pub fn set_max_pending(&mut self, cycles: u64) {
+        self.max_pending = cycles.max(1);
+        if self.pending_cycles >= self.max_pending {
+            self.catch_up();
+        }
     }
 
     pub fn step(&mut self) {
-        // Connect SPC700 to DSP through I/O ports
-        self.connect_dsp();
-        
         // Execute one SPC700 instruction
+        let pc = self.spc700.pc();
         self.spc700.step();
         
+        // Connect SPC700 to DSP through I/O ports
+        self.connect_dsp();
+        let (reads, writes) = self.spc700.take_port_accesses();
+        if self.port_trace.is_enabled() && (reads | writes) != 0 {
+            self.trace_spc_ports(pc, reads, writes);
+        }
+        
         // Generate audio samples (32kHz output rate)
         // The APU runs at 1.024 MHz, so we generate a sample every 32 cycles
         if self.spc700.cycles % 32 == 0 {
-            let sample = self.dsp.step();
+            let mut sample = self.dsp.step();
+            if let Some(filter) = self.output_filter.as_mut() {
+                sample = filter.process(sample);
+            }
             self.audio_buffer.push(sample);
+            self.audio_hasher.update_sample(sample);
             
             // Keep buffer from growing too large
             if self.audio_buffer.len() > 4096 {
@@ -50,38 +208,107 @@ impl Apu {
         }
     }
     
-    fn connect_dsp(&mut self) {
-        // Handle DSP register access through SPC700 I/O ports
-        let dsp_addr_write = self.spc700.read8(0x00F2);
-        let dsp_data_write = self.spc700.read8(0x00F3);
-        
-        // Update DSP address
-        if dsp_addr_write != self.dsp_address {
-            self.dsp_address = dsp_addr_write;
+    fn trace_spc_ports(&mut self, pc: u16, reads: u8, writes: u8) {
+        let cycle = self.spc700.cycles();
+        for port in 0..4u8 {
+            if reads & (1 << port) != 0 {
+                // What the CPU last wrote, which is what the SPC700 saw
+                let value = self.spc700.port_from_cpu(port as usize);
+                self.port_trace.record(PortSide::Spc, false, port, value, cycle, pc);
+            }
+            if writes & (1 << port) != 0 {
+                let value = self.spc700.read_port(port as usize);
+                self.port_trace.record(PortSide::Spc, true, port, value, cycle, pc);
+            }
         }
-        
-        // Handle DSP data write
-        if self.spc700.read8(0x00F3) != dsp_data_write {
-            self.dsp.write_register(self.dsp_address, dsp_data_write);
+    }
+
+    pub fn port_trace(&self) -> &PortTrace {
+        &self.port_trace
+    }
+
+    pub fn port_trace_mut(&mut self) -> &mut PortTrace {
+        &mut self.port_trace
+    }
+
+    fn connect_dsp(&mut self) {
+        // Pass on $F3 writes, then latch the selected register for $F3 reads
+        for (address, value) in self.spc700.take_dsp_writes() {
+            self.dsp.write_register(address, value);
         }
-        
-        // Handle DSP data read
-        let dsp_data = self.dsp.read_register(self.dsp_address);
-        self.spc700.write8(0x00F3, dsp_data);
+        let dsp_data = self.dsp.read_register(self.spc700.dsp_address());
+        self.spc700.set_dsp_data(dsp_data);
+    }
+
+    pub fn spc700(&self) -> &Spc700 {
+        &self.spc700
+    }
+
+    pub fn spc700_mut(&mut self) -> &mut Spc700 {
+        &mut self.spc700
+    }
+
+    pub fn dsp(&self) -> &Dsp {
+        &self.dsp
+    }
+
+    pub fn dsp_mut(&mut self) -> &mut Dsp {
+        &mut self.dsp
     }
 
     pub fn get_audio_samples(&mut self) -> Vec<f32> {
+        self.catch_up();
         let samples = self.audio_buffer.clone();
         self.audio_buffer.clear();
         samples
     }
     
-    // Communication ports with main CPU
-    pub fn read_port(&self, port: usize) -> u8 {
-        self.spc700.read_port(port)
+    // Communication ports with main CPU; the APU catches up first so the
+    // CPU sees (and is seen by) the APU exactly where it would be in lockstep
+    pub fn read_port(&mut self, port: usize) -> u8 {
+        self.catch_up();
+        let value = if self.fake_ack { self.ack_ports[port & 3] } else { self.spc700.read_port(port) };
+        self.cpu_reads += 1;
+        self.last_read = (port & 3) as u8;
+        if self.port_trace.is_enabled() {
+            self.port_trace.record(PortSide::Cpu, false, port as u8, value, self.spc700.cycles(), 0);
+        }
+        value
+    }
+    
+    // Close the frame's audio hash; call once the frame's APU time has run
+    pub fn end_frame(&mut self) {
+        self.frame_audio_hash = std::mem::take(&mut self.audio_hasher).finish();
+    }
+    
+    // Hash of the samples generated during the last finished frame
+    pub fn frame_audio_hash(&self) -> u64 {
+        self.frame_audio_hash
+    }
+    
+    pub fn cpu_reads(&self) -> u64 {
+        self.cpu_reads
+    }
+    
+    // Port (0-3) the CPU read last
+    pub fn last_read_port(&self) -> u8 {
+        self.last_read
+    }
+    
+    // What the CPU wrote to the ports and what it reads back, without
+    // catching up or counting as an access
+    pub fn peek_ports(&self) -> ([u8; 4], [u8; 4]) {
+        let from_cpu = std::array::from_fn(|port| self.spc700.port_from_cpu(port));
+        let to_cpu = if self.fake_ack { self.ack_ports } else { std::array::from_fn(|port| self.spc700.read_port(port)) };
+        (from_cpu, to_cpu)
     }
     
     pub fn write_port(&mut self, port: usize, value: u8) {
+        self.catch_up();
+        if self.port_trace.is_enabled() {
+            self.port_trace.record(PortSide::Cpu, true, port as u8, value, self.spc700.cycles(), 0);
+        }
+        self.ack_ports[port & 3] = value;
         self.spc700.write_port(port, value)
     }
     
@@ -91,6 +318,7 @@ impl Apu {
             spc700: self.spc700.save_state(),
             dsp: self.dsp.save_state(),
             audio_buffer: self.audio_buffer.clone(),
+            pending_cycles: self.pending_cycles,
         }
     }
     
@@ -98,5 +326,9 @@ impl Apu {
         self.spc700.load_state(&state.spc700);
         self.dsp.load_state(&state.dsp);
         self.audio_buffer = state.audio_buffer.clone();
+        self.audio_hasher = FrameHasher::new();
+        self.frame_audio_hash = FNV_OFFSET_BASIS;
+        self.pending_cycles = state.pending_cycles;
+        self.connect_dsp();
     }
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+// Keeps the host audio stream continuous when emulated audio jumps (state
+// loads, rewind, netplay rollback). Instead of clearing the output queue,
+// which underruns and clicks, the samples after a jump are crossfaded into
+// what is already queued; block sizes are resampled slightly so the queue
+// drifts back to its target fill level after a load changes it.
+//
+// Samples are interleaved stereo, as produced by Apu::get_audio_samples.
+use std::collections::VecDeque;
+
+// Crossfade length in stereo frames (8ms at 32 kHz)
+pub const FADE_FRAMES: usize = 256;
+
+// Largest rate change used to pull the queue back to its target (0.5%)
+pub const MAX_CORRECTION: f64 = 0.005;
+
+#[derive(Debug, Clone, Default)]
+pub struct AudioReconciler {
+    // Crossfade the next block into the queue
+    pending_fade: bool,
+    // Fractional resampling position carried between blocks
+    phase: f64,
+    // Last frame of the previous block, for interpolating across blocks
+    last_frame: [f32; 2],
+}
+
+impl AudioReconciler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The next block does not follow on from the previous one
+    pub fn discontinuity(&mut self) {
+        self.pending_fade = true;
+    }
+
+    // Append a block to an output queue, steering the queue towards
+    // `target_len` samples (0 disables rate correction)
+    pub fn queue(&mut self, queue: &mut VecDeque<f32>, samples: &[f32], target_len: usize) {
+        if samples.len() < 2 {
+            return;
+        }
+        let ratio = if target_len == 0 {
+            1.0
+        } else {
+            let error = (queue.len() as f64 - target_len as f64) / target_len as f64;
+            1.0 + error.clamp(-1.0, 1.0) * MAX_CORRECTION
+        };
+        let block = if self.pending_fade {
+            // A new stream starts here, so don't interpolate from the old
+            // one; the next block continues after this block's last frame
+            self.phase = 1.0;
+            samples.to_vec()
+        } else {
+            self.resample(samples, ratio)
+        };
+
+        if std::mem::take(&mut self.pending_fade) {
+            crossfade(queue, &block);
+        } else {
+            queue.extend(block.iter().copied());
+        }
+        if let [.., left, right] = samples {
+            self.last_frame = [*left, *right];
+        }
+    }
+
+    // Linear resampling by `ratio` (above 1.0 produces fewer frames)
+    fn resample(&mut self, samples: &[f32], ratio: f64) -> Vec<f32> {
+        let frames = samples.len() / 2;
+        let frame = |i: isize| -> [f32; 2] {
+            if i < 0 {
+                self.last_frame
+            } else {
+                let i = i as usize * 2;
+                [samples[i], samples[i + 1]]
+            }
+        };
+        let mut output = Vec::with_capacity(samples.len() + 4);
+        // Positions are relative to the block, starting one frame back at
+        // the previous block's last frame
+        let mut position = self.phase - 1.0;
+        while position < frames as f64 - 1.0 {
+            let index = position.floor();
+            let t = (position - index) as f32;
+            let (a, b) = (frame(index as isize), frame(index as isize + 1));
+            output.push(a[0] + (b[0] - a[0]) * t);
+            output.push(a[1] + (b[1] - a[1]) * t);
+            position += ratio;
+        }
+        self.phase = position - (frames as f64 - 1.0);
+        output
+    }
+}
+
+// Blend the queue's tail into the head of the new block. With too little
+// queued, fade in from the queue's last value (silence when empty).
+fn crossfade(queue: &mut VecDeque<f32>, block: &[f32]) {
+    let queued_frames = queue.len() / 2;
+    let fade = FADE_FRAMES.min(queued_frames).min(block.len() / 2);
+    if fade == 0 {
+        let from = match queue.len() {
+            0 | 1 => [0.0, 0.0],
+            len => [queue[len - 2], queue[len - 1]],
+        };
+        let ramp = FADE_FRAMES.min(block.len() / 2);
+        for (i, pair) in block.chunks_exact(2).enumerate() {
+            let t = if i < ramp { (i + 1) as f32 / (ramp + 1) as f32 } else { 1.0 };
+            queue.push_back(from[0] + (pair[0] - from[0]) * t);
+            queue.push_back(from[1] + (pair[1] - from[1]) * t);
+        }
+        return;
+    }
+
+    let start = queue.len() - fade * 2;
+    for i in 0..fade * 2 {
+        let t = (i / 2 + 1) as f32 / (fade + 1) as f32;
+        let old = queue[start + i];
+        queue[start + i] = old + (block[i] - old) * t;
+    }
+    queue.extend(block[fade * 2..].iter().copied());
+}
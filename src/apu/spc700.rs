@@ -1,6 +1,35 @@
 // SPC700 CPU (8-bit processor for audio)
 
 use crate::savestate::Spc700State;
+use std::cell::Cell;
+use std::fmt;
+
+// Snapshot of the SPC700 registers for debugging
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spc700Registers {
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub pc: u16,
+    pub psw: u8,
+}
+
+impl fmt::Display for Spc700Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags: String = "NVPBHIZC".chars().enumerate()
+            .map(|(i, c)| if self.psw & (0x80 >> i) != 0 { c } else { c.to_ascii_lowercase() })
+            .collect();
+        write!(
+            f,
+            "A:{:02X} X:{:02X} Y:{:02X} SP:{:02X} PC:{:04X} PSW:{:02X} [{}]",
+            self.a, self.x, self.y, self.sp, self.pc, self.psw, flags
+        )
+    }
+}
+
+// CMP $F4, #$CC in the IPL ROM: the loop waiting for the CPU
+const IPL_WAIT_PC: u16 = 0xFFCF;
 
 pub struct Spc700 {
     // CPU registers
@@ -21,6 +50,15 @@ pub struct Spc700 {
     port_in: [u8; 4],
     port_out: [u8; 4],
     
+    // Ports read and written since take_port_accesses, bit n for port n
+    port_reads: Cell<u8>,
+    port_writes: u8,
+    
+    // DSP registers written through $F2/$F3 since take_dsp_writes, as
+    // (register, value), and what a read of $F3 returns
+    dsp_writes: Vec<(u8, u8)>,
+    dsp_data: u8,
+    
     // Timers
     timer_enable: u8,
     timer_target: [u8; 3],
@@ -43,6 +81,10 @@ impl Spc700 {
             ipl_rom_enable: true,
             port_in: [0; 4],
             port_out: [0; 4],
+            port_reads: Cell::new(0),
+            port_writes: 0,
+            dsp_writes: Vec::new(),
+            dsp_data: 0,
             timer_enable: 0,
             timer_target: [0; 3],
             timer_counter: [0; 3],
@@ -65,6 +107,8 @@ impl Spc700 {
         self.ipl_rom_enable = true;
         self.port_in = [0; 4];
         self.port_out = [0; 4];
+        self.dsp_writes.clear();
+        self.dsp_data = 0;
         self.timer_enable = 0;
         self.timer_target = [0; 3];
         self.timer_counter = [0; 3];
@@ -76,6 +120,17 @@ impl Spc700 {
         self.cycles = 0;
     }
 
+    // State the IPL ROM reaches after clearing the zero page and
+    // announcing itself: waiting for $CC in port 0
+    pub fn skip_ipl(&mut self) {
+        self.a = 0;
+        self.x = 0;
+        self.sp = 0xEF;
+        self.pc = IPL_WAIT_PC;
+        self.ram[0x0000..0x00F0].fill(0);
+        self.port_in = [0xAA, 0xBB, 0, 0];
+    }
+
     pub fn step(&mut self) {
         // Execute one instruction
         self.execute_instruction();
@@ -145,12 +200,13 @@ impl Spc700 {
                 if self.timer_enable & 0x04 != 0 { value |= 0x04; }
                 value
             }
-            0x00F2 => 0,  // DSP address (handled by DSP)
-            0x00F3 => 0,  // DSP data (handled by DSP)
-            0x00F4 => self.port_out[0],  // Port 0
-            0x00F5 => self.port_out[1],  // Port 1
-            0x00F6 => self.port_out[2],  // Port 2
-            0x00F7 => self.port_out[3],  // Port 3
+            0x00F2 => self.ram[address as usize],  // DSP address
+            0x00F3 => self.dsp_data,  // DSP data (latched by the APU)
+            0x00F4..=0x00F7 => {
+                let port = (address - 0x00F4) as usize;
+                self.port_reads.set(self.port_reads.get() | 1 << port);
+                self.port_out[port]
+            }
             0x00F8 => self.ram[address as usize],  // RAM mirror
             0x00F9 => self.ram[address as usize],  // RAM mirror
             0x00FA => self.timer_target[0],
@@ -188,12 +244,13 @@ impl Spc700 {
                 if value & 0x02 != 0 { self.timer_output[1] = 0; self.timer_counter[1] = 0; }
                 if value & 0x04 != 0 { self.timer_output[2] = 0; self.timer_counter[2] = 0; }
             }
-            0x00F2 => {} // DSP address (handled by DSP)
-            0x00F3 => {} // DSP data (handled by DSP)
-            0x00F4 => self.port_in[0] = value,  // Port 0
-            0x00F5 => self.port_in[1] = value,  // Port 1
-            0x00F6 => self.port_in[2] = value,  // Port 2
-            0x00F7 => self.port_in[3] = value,  // Port 3
+            0x00F2 => self.ram[address as usize] = value,  // DSP address
+            0x00F3 => self.dsp_writes.push((self.ram[0x00F2], value)),  // DSP data
+            0x00F4..=0x00F7 => {
+                let port = (address - 0x00F4) as usize;
+                self.port_writes |= 1 << port;
+                self.port_in[port] = value;
+            }
             0x00F8 => self.ram[address as usize] = value,  // RAM
             0x00F9 => self.ram[address as usize] = value,  // RAM
             0x00FA => self.timer_target[0] = value,
@@ -242,6 +299,69 @@ impl Spc700 {
         }
     }
     
+    pub fn registers(&self) -> Spc700Registers {
+        Spc700Registers {
+            a: self.a,
+            x: self.x,
+            y: self.y,
+            sp: self.sp,
+            pc: self.pc,
+            psw: self.psw,
+        }
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    // Raw 64KB APU RAM, without I/O register side effects
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+    
+    // Ports $F4-$F7 read and written since the last call, as bit masks
+    pub(crate) fn take_port_accesses(&mut self) -> (u8, u8) {
+        (self.port_reads.take(), std::mem::take(&mut self.port_writes))
+    }
+    
+    // DSP register writes since the last call, in order
+    pub(crate) fn take_dsp_writes(&mut self) -> Vec<(u8, u8)> {
+        std::mem::take(&mut self.dsp_writes)
+    }
+    
+    // The DSP register selected through $F2
+    pub fn dsp_address(&self) -> u8 {
+        self.ram[0x00F2]
+    }
+    
+    pub(crate) fn set_dsp_data(&mut self, value: u8) {
+        self.dsp_data = value;
+    }
+    
+    // $F0-$FF as a .spc file stores them: the control register, the DSP
+    // address, the ports as the SPC700 reads them, and the timers
+    pub fn io_registers(&self) -> [u8; 16] {
+        let mut registers = [0; 16];
+        registers[0x1] = (self.ipl_rom_enable as u8) << 7 | self.timer_enable;
+        registers[0x2] = self.ram[0x00F2];
+        registers[0x3] = self.dsp_data;
+        registers[0x4..0x8].copy_from_slice(&self.port_out);
+        registers[0x8] = self.ram[0x00F8];
+        registers[0x9] = self.ram[0x00F9];
+        registers[0xA..0xD].copy_from_slice(&self.timer_target);
+        registers[0xD..0x10].copy_from_slice(&self.timer_output);
+        registers
+    }
+    
+    // Value the CPU last wrote to a port, without counting as an SPC700 read
+    pub(crate) fn port_from_cpu(&self, port: usize) -> u8 {
+        self.port_out[port & 3]
+    }
+    
     // Communication with main CPU
     pub fn read_port(&self, port: usize) -> u8 {
         if port < 4 {
@@ -289,6 +409,7 @@ impl Spc700 {
         self.ipl_rom_enable = state.ipl_rom_enable;
         self.port_in = state.port_in;
         self.port_out = state.port_out;
+        self.dsp_writes.clear();
         self.timer_enable = state.timer_enable;
         self.timer_target = state.timer_target;
         self.timer_counter = state.timer_counter;
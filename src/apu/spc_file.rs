@@ -0,0 +1,84 @@
+// .spc music files: a snapshot of the sound hardware (SPC700 registers,
+// the 64KB of audio RAM and the DSP registers) that SPC players resume
+// from, plus an ID666 tag block. Laid out as version 0.30 with text tags,
+// which every player reads.
+use crate::apu::Apu;
+
+pub const SPC_FILE_SIZE: usize = 0x10200;
+
+const SIGNATURE: &[u8; 33] = b"SNES-SPC700 Sound File Data v0.30";
+const HAS_ID666: u8 = 26;
+const MINOR_VERSION: u8 = 30;
+
+const RAM_OFFSET: usize = 0x100;
+const DSP_OFFSET: usize = 0x10100;
+// RAM hidden under the IPL ROM at $FFC0-$FFFF
+const EXTRA_RAM_OFFSET: usize = 0x101C0;
+
+// ID666 fields; text is cut to fit and padded with zeros
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpcTags {
+    pub song_title: String,
+    pub game_title: String,
+    pub dumper: String,
+    pub comments: String,
+    pub artist: String,
+    // How long players run the song before fading out
+    pub play_seconds: u32,
+    pub fade_ms: u32,
+}
+
+impl SpcTags {
+    // Tags naming only the game, for a capture from a running ROM
+    pub fn for_game(title: &str) -> Self {
+        Self { game_title: title.trim().to_string(), ..Self::default() }
+    }
+}
+
+impl Default for SpcTags {
+    fn default() -> Self {
+        Self {
+            song_title: String::new(),
+            game_title: String::new(),
+            dumper: String::new(),
+            comments: String::new(),
+            artist: String::new(),
+            play_seconds: 180,
+            fade_ms: 10_000,
+        }
+    }
+}
+
+// Build a .spc file from the APU as it is now; run any pending APU cycles
+// first (Apu::catch_up) so it matches what the CPU has seen
+pub fn export(apu: &Apu, tags: &SpcTags) -> Vec<u8> {
+    let spc = apu.spc700();
+    let registers = spc.registers();
+    let mut file = vec![0; SPC_FILE_SIZE];
+
+    file[..SIGNATURE.len()].copy_from_slice(SIGNATURE);
+    file[0x21..0x25].copy_from_slice(&[26, 26, HAS_ID666, MINOR_VERSION]);
+    file[0x25..0x27].copy_from_slice(&registers.pc.to_le_bytes());
+    file[0x27..0x2C].copy_from_slice(&[registers.a, registers.x, registers.y, registers.psw, registers.sp]);
+
+    put_text(&mut file[0x2E..0x4E], &tags.song_title);
+    put_text(&mut file[0x4E..0x6E], &tags.game_title);
+    put_text(&mut file[0x6E..0x7E], &tags.dumper);
+    put_text(&mut file[0x7E..0x9E], &tags.comments);
+    put_text(&mut file[0xA9..0xAC], &tags.play_seconds.min(999).to_string());
+    put_text(&mut file[0xAC..0xB1], &tags.fade_ms.min(99_999).to_string());
+    put_text(&mut file[0xB1..0xD1], &tags.artist);
+
+    let ram = &mut file[RAM_OFFSET..RAM_OFFSET + 0x10000];
+    ram.copy_from_slice(spc.ram());
+    ram[0xF0..0x100].copy_from_slice(&spc.io_registers());
+    file[DSP_OFFSET..DSP_OFFSET + 128].copy_from_slice(&apu.dsp().registers());
+    file[EXTRA_RAM_OFFSET..].copy_from_slice(&spc.ram()[0xFFC0..]);
+    file
+}
+
+fn put_text(field: &mut [u8], text: &str) {
+    let bytes: Vec<u8> = text.chars().map(|c| if c.is_ascii() && !c.is_ascii_control() { c as u8 } else { b'?' }).collect();
+    let len = bytes.len().min(field.len());
+    field[..len].copy_from_slice(&bytes[..len]);
+}
@@ -0,0 +1,74 @@
+// Output volume, applied to the samples themselves rather than left to the
+// host mixer, so every frontend (and anything recording the samples) hears
+// the same level
+use crate::config::AudioConfig;
+
+// Loudest setting, twice the DSP's own output; louder samples are clipped
+pub const MAX_VOLUME: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume {
+    level: f32,
+    muted: bool,
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Self { level: 1.0, muted: false }
+    }
+}
+
+impl Volume {
+    // `level` from 0.0 (silent) to MAX_VOLUME, 1.0 leaving samples as they are
+    pub fn new(level: f32) -> Self {
+        let mut volume = Self::default();
+        volume.set_level(level);
+        volume
+    }
+
+    // master_volume is in percent
+    pub fn from_config(config: &AudioConfig) -> Self {
+        Self { muted: config.muted, ..Self::new(config.master_volume as f32 / 100.0) }
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    // NaN counts as silence
+    pub fn set_level(&mut self, level: f32) {
+        self.level = if level.is_nan() { 0.0 } else { level.clamp(0.0, MAX_VOLUME) };
+    }
+
+    // The level in percent, as stored in the config
+    pub fn percent(&self) -> u8 {
+        (self.level * 100.0).round() as u8
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    // Muting keeps the level for unmuting
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn gain(&self) -> f32 {
+        if self.muted {
+            0.0
+        } else {
+            self.level
+        }
+    }
+
+    pub fn apply(&self, samples: &mut [f32]) {
+        let gain = self.gain();
+        if gain == 1.0 {
+            return;
+        }
+        for sample in samples {
+            *sample = (*sample * gain).clamp(-1.0, 1.0);
+        }
+    }
+}
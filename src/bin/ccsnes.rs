@@ -1,9 +1,25 @@
 // CCSNES CLI - Command line interface for the SNES emulator
-use clap::{Parser, Subcommand};
-use ccsnes::{Emulator, config::Config};
-use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand};
+use ccsnes::{Emulator, config::{Config, SyncMode}};
+use ccsnes::cartridge::integrity::RomDatabase;
+use ccsnes::cartridge::{verify, RomImage};
+use ccsnes::cheats::CheatList;
+use ccsnes::debug::frame_diff::{self, FrameDump};
+use ccsnes::debug::apu_ports::PortFilter;
+use ccsnes::debug::spans;
+use ccsnes::input::{InputScript, InputSource};
+use ccsnes::library::RomLibrary;
+use ccsnes::memory::mappers::MapperType;
+use ccsnes::movie::{self, Movie};
+use ccsnes::profile::GameProfile;
+use ccsnes::quirks::Quirks;
+use ccsnes::paths::{GameId, GamePaths};
+use ccsnes::savestate::SaveState;
+use ccsnes::session::SessionBundle;
+use ccsnes::sram::{self, ConvertOptions, SaveFormat};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
-use log::{info, error};
+use log::{info, warn, error};
 
 #[derive(Parser)]
 #[command(name = "ccsnes")]
@@ -36,10 +52,130 @@ struct Cli {
     #[arg(long)]
     show_fps: bool,
     
+    /// Graph emulation time, render time and audio buffer fill on screen
+    #[arg(long)]
+    show_frame_time: bool,
+    
+    /// Measure and display input-to-screen latency
+    #[arg(long)]
+    measure_latency: bool,
+    
+    /// Accept player 2 input over TCP on this address, e.g. 0.0.0.0:7755
+    #[arg(long, value_name = "ADDR")]
+    remote_input: Option<String>,
+    
+    /// Serve the JSON-RPC API for external tools on this address, e.g. 127.0.0.1:7756
+    #[arg(long, value_name = "ADDR")]
+    tool_server: Option<String>,
+    
+    /// What paces emulation: video (60 Hz timer), audio (the output queue) or free (unthrottled)
+    #[arg(long, value_name = "MODE", value_parser = parse_sync_mode)]
+    sync: Option<SyncMode>,
+    
+    /// Run the APU at RATIO times the stock console's clock (0.5-2.0), to match hardware variants or test timing sensitivity
+    #[arg(long, value_name = "RATIO")]
+    apu_clock: Option<f64>,
+    
+    /// Show the buttons held on each pad for the frame on screen
+    #[arg(long)]
+    show_input: bool,
+    
+    /// Warn about suspicious game behaviour: uninitialized WRAM reads, ROM
+    /// writes, video memory access outside blank, stack overflow, masked IRQs
+    #[arg(long)]
+    lint: bool,
+    
+    /// Browse and launch ROMs from a directory
+    #[arg(long, value_name = "DIR")]
+    library: Option<PathBuf>,
+    
+    /// Run the ROM without a window or audio
+    #[arg(long)]
+    headless: bool,
+    
+    /// Score the ROM as LoROM and as HiROM, report which mapper is used and why, then exit
+    #[arg(long)]
+    verify_rom: bool,
+    
+    /// Use this mapper instead of the one named by the header (lorom, hirom)
+    #[arg(long, value_name = "MAPPER", value_parser = parse_mapper)]
+    force_mapper: Option<MapperType>,
+    
+    #[command(flatten)]
+    headless_options: HeadlessOptions,
+    
+    #[command(flatten)]
+    watch_options: WatchOptions,
+    
+    /// Write CPU/PPU/DMA/present timing spans as a chrome://tracing JSON file
+    /// (needs a build with the `profiling` feature)
+    #[arg(long, value_name = "FILE")]
+    profile_json: Option<PathBuf>,
+    
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+// Reloading the ROM while it runs, for homebrew development
+#[derive(Args)]
+struct WatchOptions {
+    /// Reload the ROM whenever its file changes, e.g. after reassembling it
+    #[arg(long)]
+    watch: bool,
+    
+    /// Save state to load after each --watch reload
+    #[arg(long, value_name = "FILE", requires = "watch")]
+    watch_state: Option<PathBuf>,
+    
+    /// Start each --watch reload with blank SRAM instead of the running game's
+    #[arg(long, requires = "watch")]
+    watch_fresh_sram: bool,
+}
+
+// Inputs and outputs of a --headless run
+#[derive(Args)]
+struct HeadlessOptions {
+    /// Input script for --headless, one `frame: buttons` entry per line
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    input_script: Option<PathBuf>,
+    
+    /// Movie to replay with --headless
+    #[arg(long, value_name = "FILE", requires = "headless", conflicts_with = "input_script")]
+    movie: Option<PathBuf>,
+    
+    /// Frames to run with --headless (default: to the end of the inputs, or 600)
+    #[arg(long, value_name = "N", requires = "headless")]
+    frames: Option<u64>,
+    
+    /// Write the last frame as a PPM image after --headless
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    screenshot: Option<PathBuf>,
+    
+    /// Write the music playing at the end of --headless as a .spc file
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    export_spc: Option<PathBuf>,
+    
+    /// Write a save state after --headless
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    save_state: Option<PathBuf>,
+    
+    /// Record the inputs played by --headless as a movie
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    record_movie: Option<PathBuf>,
+    
+    /// Log CPU and SPC700 accesses to the APU ports ($2140-$2143) during --headless
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    apu_port_trace: Option<PathBuf>,
+    
+    /// Only log matching port accesses, e.g. "0,1,spc,write"
+    #[arg(long, value_name = "TERMS", requires = "apu_port_trace", value_parser = PortFilter::parse)]
+    apu_port_filter: Option<PortFilter>,
+    
+    /// Collect the results a test ROM reports through WDM $F0-$F2 during --headless, and fail if any test failed
+    #[arg(long, requires = "headless")]
+    wdm_tests: bool,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Run the emulator with a ROM
@@ -58,6 +194,66 @@ enum Commands {
         /// ROM file to analyze
         rom: PathBuf,
     },
+    /// Print the resolved cartridge memory map
+    Map {
+        /// ROM file to analyze
+        rom: PathBuf,
+    },
+    /// List audio backends and output devices
+    AudioDevices,
+    /// Inspect or convert save states
+    State {
+        #[command(subcommand)]
+        action: StateCommand,
+    },
+    /// Convert SRAM saves between this emulator, other emulators and flashcarts
+    Save {
+        #[command(subcommand)]
+        action: SaveCommand,
+    },
+    /// Bundle a game's SRAM, save state slots, movie, cheats and profile into one file
+    ExportSession {
+        /// ROM whose files to bundle
+        rom: PathBuf,
+        /// Bundle to write (default: <game>.ccsession)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Put the files of a bundle from export-session back in a game's save locations
+    ImportSession {
+        /// ROM to restore the files for
+        rom: PathBuf,
+        /// Bundle written by export-session
+        bundle: PathBuf,
+        /// Replace files that already exist instead of keeping them
+        #[arg(long)]
+        overwrite: bool,
+        /// Restore a bundle made for a different ROM dump (e.g. before patching)
+        #[arg(long)]
+        any_rom: bool,
+    },
+    /// Compare video output frame by frame and stop at the first divergence
+    Diff {
+        /// ROM file to run
+        rom: PathBuf,
+        /// Frame dump to compare against (default: a second instance
+        /// with the default quirks)
+        #[arg(long, value_name = "DUMP")]
+        against: Option<PathBuf>,
+        /// Record a frame dump instead of comparing
+        #[arg(long, value_name = "DUMP", conflicts_with = "against")]
+        record: Option<PathBuf>,
+        /// Flip a quirk (e.g. dram_refresh_stall) on the compared instance;
+        /// repeat for several
+        #[arg(long = "quirk", value_name = "NAME")]
+        quirks: Vec<String>,
+        /// Number of frames to run
+        #[arg(short, long, default_value = "600")]
+        frames: u64,
+        /// Write a PPM marking the differing pixels of the first divergence
+        #[arg(long, value_name = "FILE")]
+        highlight: Option<PathBuf>,
+    },
     /// Benchmark emulation performance
     Bench {
         /// ROM file to benchmark
@@ -68,6 +264,54 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum StateCommand {
+    /// Write a save state as pretty-printed JSON
+    Export {
+        /// Save state file
+        state: PathBuf,
+        /// Output file (defaults to stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Convert an exported JSON file back into a save state
+    Import {
+        /// JSON file produced by `state export`
+        json: PathBuf,
+        /// Save state file to write
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum SaveCommand {
+    /// Pad or truncate a save to the game's SRAM size and write it as raw
+    /// SRAM or in this emulator's format
+    Convert {
+        /// Save file (.srm, .sav, ...) in either format
+        input: PathBuf,
+        /// File to write
+        #[arg(short, long)]
+        output: PathBuf,
+        /// ROM whose header gives the SRAM size
+        #[arg(long, conflicts_with = "size")]
+        rom: Option<PathBuf>,
+        /// SRAM size in bytes ("8192", "8k", "0x2000"); default keeps the input size
+        #[arg(long, value_parser = parse_size)]
+        size: Option<usize>,
+        /// Output format: raw (other emulators, flashcarts) or ccsnes
+        #[arg(long, default_value = "raw", value_parser = parse_save_format)]
+        format: SaveFormat,
+        /// Swap the bytes of each 16-bit word (saves from some coprocessor boards)
+        #[arg(long)]
+        swap_bytes: bool,
+        /// Byte used to pad short saves
+        #[arg(long, default_value = "0", value_parser = parse_size)]
+        fill: usize,
+    },
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logger
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
@@ -75,7 +319,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command line arguments
     let cli = Cli::parse();
     
-    // Load or create configuration
+    // Load or create configuration (the file is watched for changes while running)
+    let config_path = cli.config.clone().unwrap_or_else(Config::default_path);
     let mut config = if let Some(config_path) = cli.config {
         Config::load_from_file(config_path)?
     } else {
@@ -89,48 +334,127 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     config.video.fullscreen = cli.fullscreen;
     config.audio.enabled = !cli.no_audio;
     config.debug.show_fps = cli.show_fps;
+    config.debug.show_frame_time |= cli.show_frame_time;
+    config.debug.input_latency |= cli.measure_latency;
+    config.debug.show_input |= cli.show_input;
+    config.debug.lint |= cli.lint;
+    if let Some(address) = cli.remote_input.clone() {
+        config.input.remote_listen = address;
+    }
+    if let Some(address) = cli.tool_server.clone() {
+        config.debug.tool_server = address;
+    }
+    if let Some(mode) = cli.sync {
+        config.emulation.sync_mode = mode;
+    }
+    if let Some(ratio) = cli.apu_clock {
+        config.emulation.apu_clock_ratio = ratio;
+    }
     
     // Create directories if needed
     config.create_directories()?;
     
+    if cli.profile_json.is_some() {
+        if spans::available() {
+            spans::start_recording();
+        } else {
+            warn!("--profile-json needs a build with the `profiling` feature; no trace will be written");
+        }
+    }
+    
     // Handle commands
     match cli.command {
         Some(Commands::Run { rom }) => {
-            run_emulator(&rom, &config)?;
+            run_emulator(&rom, cli.force_mapper, &cli.watch_options, &config, &config_path)?;
         }
         Some(Commands::Test { rom }) => {
             run_tests(rom.as_ref())?;
         }
         Some(Commands::Info { rom }) => {
-            show_rom_info(&rom)?;
+            show_rom_info(&rom, cli.force_mapper, &config.paths.rom_database)?;
+        }
+        Some(Commands::Map { rom }) => {
+            show_memory_map(&rom, cli.force_mapper)?;
+        }
+        Some(Commands::AudioDevices) => {
+            list_audio_devices()?;
+        }
+        Some(Commands::State { action }) => {
+            run_state_command(action)?;
+        }
+        Some(Commands::Save { action }) => {
+            run_save_command(action)?;
+        }
+        Some(Commands::ExportSession { rom, output }) => {
+            export_session(&rom, output, &config)?;
+        }
+        Some(Commands::ImportSession { rom, bundle, overwrite, any_rom }) => {
+            import_session(&rom, &bundle, overwrite, any_rom, &config)?;
+        }
+        Some(Commands::Diff { rom, against, record, quirks, frames, highlight }) => {
+            run_frame_diff(&rom, against.as_deref(), record.as_deref(), &quirks, frames, highlight.as_deref())?;
         }
         Some(Commands::Bench { rom, frames }) => {
             benchmark_emulator(&rom, frames)?;
         }
         None => {
             // No subcommand, check if ROM was provided as positional argument
-            if let Some(rom) = cli.rom {
-                run_emulator(&rom, &config)?;
+            if cli.verify_rom {
+                let rom = cli.rom.as_ref().ok_or("--verify-rom needs a ROM")?;
+                verify_rom(rom, cli.force_mapper)?;
+            } else if cli.headless {
+                let rom = cli.rom.as_ref().ok_or("--headless needs a ROM")?;
+                run_headless(rom, cli.force_mapper, &config, &cli.headless_options)?;
+            } else if let Some(dir) = cli.library {
+                run_library(&dir, &config, &config_path)?;
+            } else if let Some(rom) = cli.rom {
+                run_emulator(&rom, cli.force_mapper, &cli.watch_options, &config, &config_path)?;
             } else {
-                eprintln!("No ROM file specified. Use --help for usage information.");
-                std::process::exit(1);
+                run_without_rom(&cli.watch_options, &config, &config_path)?;
             }
         }
     }
     
+    if let (Some(path), Some(trace)) = (cli.profile_json.as_ref(), spans::stop_recording()) {
+        trace.save(path)?;
+        info!("Wrote {} spans to {}", trace.events.len(), path.display());
+        if trace.dropped > 0 {
+            warn!("{} spans were dropped after the first {}", trace.dropped, spans::MAX_SPANS);
+        }
+    }
+    
     Ok(())
 }
 
-fn run_emulator(rom_path: &PathBuf, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+// The ROM file's bytes, memory-mapped when the config asks for it
+fn read_rom(rom_path: &Path, config: &Config) -> Result<RomImage, Box<dyn std::error::Error>> {
+    if config.emulation.mmap_rom {
+        return Ok(RomImage::map(rom_path)?);
+    }
+    Ok(std::fs::read(rom_path)?.into())
+}
+
+fn run_emulator(
+    rom_path: &PathBuf,
+    mapper: Option<MapperType>,
+    watch: &WatchOptions,
+    config: &Config,
+    config_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting CCSNES emulator...");
     info!("Loading ROM: {:?}", rom_path);
     
-    // Load ROM file
-    let rom_data = std::fs::read(rom_path)?;
+    // Load ROM file; the game id hashes it before the cartridge takes it.
+    // A watched ROM is rewritten by the assembler, so it is never mapped.
+    let rom_data = if watch.watch { std::fs::read(rom_path)?.into() } else { read_rom(rom_path, config)? };
+    let game_id = GameId::from_rom_path(rom_path, &rom_data);
     
     // Create emulator
-    let mut emulator = Emulator::new()?;
-    emulator.load_rom(&rom_data)?;
+    let mut emulator = Emulator::with_memory_init(config.emulation.memory_init())?;
+    emulator.set_render_mode(config.emulation.ppu_renderer);
+    emulator.set_apu_clock_ratio(config.emulation.apu_clock_ratio)?;
+    emulator.set_lint(config.debug.lint);
+    emulator.load_rom_image(rom_data, mapper)?;
     
     // Get ROM info
     if let Some(rom_info) = emulator.get_rom_info() {
@@ -140,30 +464,56 @@ fn run_emulator(rom_path: &PathBuf, config: &Config) -> Result<(), Box<dyn std::
         info!("ROM Size: {} KB", rom_info.rom_size / 1024);
     }
     
+    // Resolve per-game save locations, moving files out of the flat layout
+    let game_paths = GamePaths::prepare(&config.paths, &game_id)?;
+    
     // Check for SRAM file
-    let sram_path = config.paths.sram_dir.join(
-        rom_path.file_stem().unwrap().to_string_lossy().to_string() + ".srm"
-    );
+    let sram_path = game_paths.sram_path();
     
     if sram_path.exists() {
         info!("Loading SRAM from: {:?}", sram_path);
-        let sram_data = std::fs::read(&sram_path)?;
+        let sram_data = ccsnes::sram::read_sram(sram_path)?;
         emulator.load_sram(&sram_data)?;
     }
     
+    // Cheats are frozen memory locations
+    let cheats = CheatList::load(game_paths.cheats_path())?;
+    let installed = emulator.apply_cheats(&cheats)?;
+    if installed > 0 {
+        info!("Enabled {} cheats from {:?}", installed, game_paths.cheats_path());
+    }
+    
+    // Per-game hardware quirks
+    let profile = GameProfile::load(game_paths.profile_path())?;
+    emulator.set_quirks(profile.quirks);
+    emulator.set_enhancements(profile.enhancements);
+    emulator.input_mut().rumble_mut().set_config(profile.rumble);
+    emulator.set_peripherals(&profile.peripherals);
+    
     #[cfg(not(target_arch = "wasm32"))] {
         // Create frontend
         let mut frontend = ccsnes::frontend::native::NativeFrontend::new(config.video.scale, false)?;
+        frontend.apply_config(config)?;
+        if let Err(e) = frontend.set_input_profile(profile.input_profile.as_deref()) {
+            warn!("{}", e);
+        }
+        watch_config(&mut frontend, config_path);
+        frontend.set_game_paths(game_paths.clone());
+        frontend.set_rom_path(rom_path);
+        if watch.watch {
+            info!("Watching {:?} for changes", rom_path);
+            frontend.watch_rom(ccsnes::frontend::native::rom_watch::RomWatchOptions {
+                keep_sram: !watch.watch_fresh_sram,
+                state: watch.watch_state.clone(),
+            });
+        }
         
-        // Run emulation loop
+        // Run emulation loop (SRAM is written by the frontend)
         frontend.run(emulator)?;
-        
-        // TODO: Handle SRAM saving after emulation ends
-        // This requires either modifying the frontend to return the emulator
-        // or handling SRAM saving within the frontend itself
     }
     
     #[cfg(target_arch = "wasm32")] {
+        let _ = config_path;
         error!("Native frontend not available in WebAssembly build");
     }
     
@@ -171,6 +521,54 @@ fn run_emulator(rom_path: &PathBuf, config: &Config) -> Result<(), Box<dyn std::
     Ok(())
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn watch_config(frontend: &mut ccsnes::frontend::native::NativeFrontend, config_path: &Path) {
+    if let Err(e) = frontend.watch_config(config_path) {
+        log::warn!("Not watching config {:?}: {}", config_path, e);
+    }
+}
+
+fn run_library(dir: &Path, config: &Config, config_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    info!("Scanning ROM library: {:?}", dir);
+    let library = RomLibrary::scan(dir, Some(&config.paths.library_cache))?;
+    
+    #[cfg(not(target_arch = "wasm32"))] {
+        let mut frontend = ccsnes::frontend::native::NativeFrontend::new(config.video.scale, false)?;
+        frontend.apply_config(config)?;
+        watch_config(&mut frontend, config_path);
+        frontend.run_library(library)?;
+    }
+    
+    #[cfg(target_arch = "wasm32")] {
+        let _ = (library, config_path);
+        error!("Native frontend not available in WebAssembly build");
+    }
+    
+    Ok(())
+}
+
+// No ROM on the command line: ask with a file dialog, or open an empty
+// window that accepts a dropped ROM
+fn run_without_rom(watch: &WatchOptions, config: &Config, config_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(not(target_arch = "wasm32"))] {
+        if let Some(rom) = ccsnes::frontend::native::dialog::pick_rom() {
+            return run_emulator(&rom, None, watch, config, config_path);
+        }
+        info!("No ROM selected; drop a ROM file onto the window to start");
+        let mut frontend = ccsnes::frontend::native::NativeFrontend::new(config.video.scale, false)?;
+        frontend.apply_config(config)?;
+        watch_config(&mut frontend, config_path);
+        frontend.run_empty()?;
+    }
+    
+    #[cfg(target_arch = "wasm32")] {
+        let _ = (watch, config, config_path);
+        error!("Native frontend not available in WebAssembly build");
+    }
+    
+    Ok(())
+}
+
 fn run_tests(test_rom: Option<&PathBuf>) -> Result<(), Box<dyn std::error::Error>> {
     info!("Running emulator tests...");
     
@@ -215,12 +613,12 @@ fn run_tests(test_rom: Option<&PathBuf>) -> Result<(), Box<dyn std::error::Error
     Ok(())
 }
 
-fn show_rom_info(rom_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+fn show_rom_info(rom_path: &PathBuf, mapper: Option<MapperType>, database_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
     let rom_data = std::fs::read(rom_path)?;
     
     // Create temporary emulator just to load ROM
     let mut emulator = Emulator::new()?;
-    emulator.load_rom(&rom_data)?;
+    emulator.load_rom_as(&rom_data, mapper)?;
     
     if let Some(info) = emulator.get_rom_info() {
         println!("ROM Information:");
@@ -233,6 +631,25 @@ fn show_rom_info(rom_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
         println!("Region: {:?}", info.region);
         println!("Version: {}", info.version);
         println!("Coprocessor: {:?}", info.coprocessor);
+        
+        let mut warnings = emulator.rom_warnings().to_vec();
+        let database = RomDatabase::load(database_path)?;
+        if let Some(cartridge) = emulator.cartridge() {
+            warnings.extend(database.check(&cartridge.rom_data));
+        }
+        if database.is_empty() {
+            println!("ROM database: none at {:?} (header checks only)", database_path);
+        } else {
+            println!("ROM database: {} entries", database.len());
+        }
+        if warnings.is_empty() {
+            println!("Integrity: OK");
+        } else {
+            println!("Integrity warnings:");
+            for warning in &warnings {
+                println!("  - {}", warning);
+            }
+        }
     } else {
         error!("Failed to read ROM information");
     }
@@ -240,6 +657,350 @@ fn show_rom_info(rom_path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+fn run_state_command(action: StateCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        StateCommand::Export { state, output } => {
+            let json = SaveState::load_from_file(&state.to_string_lossy())?.to_json()?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json)?;
+                    info!("Exported {:?} to {:?}", state, path);
+                }
+                None => println!("{}", json),
+            }
+        }
+        StateCommand::Import { json, output } => {
+            let state = SaveState::from_json(&std::fs::read_to_string(&json)?)?;
+            state.save_to_file(&output.to_string_lossy())?;
+            info!("Imported {:?} to {:?}", json, output);
+        }
+    }
+    Ok(())
+}
+
+fn run_save_command(action: SaveCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        SaveCommand::Convert { input, output, rom, size, format, swap_bytes, fill } => {
+            let fill = u8::try_from(fill).map_err(|_| "--fill must be a byte (0-255)")?;
+            let size = match (rom, size) {
+                (Some(rom), _) => {
+                    let cartridge = ccsnes::cartridge::Cartridge::load(&std::fs::read(&rom)?)?;
+                    if cartridge.header.sram_size == 0 {
+                        return Err(format!("{:?} declares no SRAM", rom).into());
+                    }
+                    if cartridge.has_coprocessor() && !swap_bytes {
+                        info!(
+                            "{:?} games are saved byte-swapped by some flashcarts; add --swap-bytes if the game doesn't find its save",
+                            cartridge.get_coprocessor()
+                        );
+                    }
+                    cartridge.header.sram_size
+                }
+                (None, Some(size)) => size,
+                (None, None) => sram::read_sram(&input)?.len(),
+            };
+            let options = ConvertOptions { size, swap_bytes, fill };
+            let report = sram::convert_save(&input, &output, &options, format)?;
+            if report.dropped_data {
+                warn!("Truncating to {} bytes dropped data beyond the game's SRAM", size);
+            }
+            info!(
+                "Converted {:?} ({} bytes{}) to {:?} ({} bytes, {:?})",
+                input,
+                report.input_len,
+                if report.had_footer { " + checksum footer" } else { "" },
+                output,
+                report.output_len,
+                format,
+            );
+        }
+    }
+    Ok(())
+}
+
+fn export_session(rom: &Path, output: Option<PathBuf>, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let game_id = GameId::from_rom_path(rom, &std::fs::read(rom)?);
+    let paths = GamePaths::prepare(&config.paths, &game_id)?;
+    let bundle = SessionBundle::collect(&game_id, &paths)?;
+    if bundle.files.is_empty() {
+        return Err(format!("No saves, states, cheats or profile found for {}", game_id.dir_name()).into());
+    }
+    let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.ccsession", game_id.name)));
+    bundle.save(&output)?;
+    let entries: Vec<String> = bundle.files.iter().map(|(entry, _)| entry.to_string()).collect();
+    info!("Wrote {} ({}) to {:?}", game_id.dir_name(), entries.join(", "), output);
+    Ok(())
+}
+
+fn import_session(rom: &Path, bundle: &Path, overwrite: bool, any_rom: bool, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let game_id = GameId::from_rom_path(rom, &std::fs::read(rom)?);
+    let bundle = SessionBundle::load(bundle)?;
+    if !bundle.matches(&game_id) && !any_rom {
+        return Err(format!(
+            "The bundle is for {}-{:08X}, not {}; pass --any-rom to restore it anyway",
+            bundle.game,
+            bundle.crc32,
+            game_id.dir_name()
+        )
+        .into());
+    }
+    let paths = GamePaths::prepare(&config.paths, &game_id)?;
+    let report = bundle.restore(&paths, overwrite)?;
+    for (entry, path) in &report.written {
+        info!("Restored {} to {:?}", entry, path);
+    }
+    for (entry, path) in &report.kept {
+        warn!("Kept the existing {} at {:?}; pass --overwrite to replace it", entry, path);
+    }
+    Ok(())
+}
+
+// Byte count in decimal, hex (0x) or with a k suffix
+fn parse_size(text: &str) -> Result<usize, String> {
+    let text = text.trim();
+    let parsed = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        usize::from_str_radix(hex, 16).ok()
+    } else if let Some(kb) = text.strip_suffix(['k', 'K']) {
+        kb.parse::<usize>().ok().and_then(|kb| kb.checked_mul(1024))
+    } else {
+        text.parse().ok()
+    };
+    parsed.ok_or_else(|| format!("invalid size '{}'", text))
+}
+
+fn parse_save_format(name: &str) -> Result<SaveFormat, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "raw" => Ok(SaveFormat::Raw),
+        "ccsnes" => Ok(SaveFormat::Ccsnes),
+        _ => Err(format!("unknown save format '{}' (expected raw or ccsnes)", name)),
+    }
+}
+
+fn show_memory_map(rom_path: &PathBuf, mapper: Option<MapperType>) -> Result<(), Box<dyn std::error::Error>> {
+    let rom_data = std::fs::read(rom_path)?;
+    let cartridge = ccsnes::cartridge::Cartridge::load_as(&rom_data, mapper)?;
+    
+    println!("Memory Map:");
+    println!("===========");
+    println!("File: {:?}", rom_path);
+    println!("Mapper: {} ({:?})", cartridge.mapper.name(), cartridge.get_mapper_type());
+    println!("ROM Size: {} KB", cartridge.get_rom_size() / 1024);
+    println!("SRAM Size: {} KB", cartridge.get_sram_size() / 1024);
+    println!();
+    
+    for region in cartridge.mapper.describe_regions() {
+        println!("{}", region);
+    }
+    
+    Ok(())
+}
+
+fn verify_rom(rom_path: &Path, mapper: Option<MapperType>) -> Result<(), Box<dyn std::error::Error>> {
+    let rom_data = std::fs::read(rom_path)?;
+    println!("Mapper Check:");
+    println!("=============");
+    println!("File: {:?}", rom_path);
+    println!("{}", verify::verify_mapper(&rom_data, mapper));
+    Ok(())
+}
+
+fn parse_mapper(name: &str) -> Result<MapperType, String> {
+    match MapperType::from_name(name) {
+        Some(mapper @ (MapperType::LoROM | MapperType::HiROM)) => Ok(mapper),
+        _ => Err(format!("unsupported mapper '{}' (expected lorom or hirom)", name)),
+    }
+}
+
+fn parse_sync_mode(name: &str) -> Result<SyncMode, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "video" => Ok(SyncMode::Video),
+        "audio" => Ok(SyncMode::Audio),
+        "free" => Ok(SyncMode::Free),
+        _ => Err(format!("unknown sync mode '{}' (expected video, audio or free)", name)),
+    }
+}
+
+fn list_audio_devices() -> Result<(), Box<dyn std::error::Error>> {
+    use ccsnes::frontend::native::audio::AudioPlayer;
+    
+    println!("Audio Devices:");
+    println!("==============");
+    for backend in AudioPlayer::available_backends() {
+        println!("{}:", backend);
+        match AudioPlayer::output_device_names(Some(backend)) {
+            Ok(devices) => {
+                for device in devices {
+                    println!("  {}", device);
+                }
+            }
+            Err(e) => println!("  (unavailable: {})", e),
+        }
+    }
+    
+    Ok(())
+}
+
+fn run_frame_diff(
+    rom_path: &Path,
+    against: Option<&Path>,
+    record: Option<&Path>,
+    quirk_names: &[String],
+    frames: u64,
+    highlight: Option<&Path>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut quirks = Quirks::default();
+    for name in quirk_names {
+        if !quirks.toggle(name) {
+            return Err(format!("Unknown quirk '{}'", name).into());
+        }
+    }
+    // Two default instances would always match
+    if against.is_none() && record.is_none() && quirk_names.is_empty() {
+        return Err("Nothing to compare: pass --against DUMP or a --quirk to flip".into());
+    }
+
+    let rom_data = std::fs::read(rom_path)?;
+    let new_instance = |quirks: Quirks| -> ccsnes::Result<Emulator> {
+        let mut emulator = Emulator::new()?;
+        emulator.set_quirks(quirks);
+        emulator.load_rom(&rom_data)?;
+        Ok(emulator)
+    };
+
+    let mut emulator = new_instance(quirks)?;
+    if let Some(path) = record {
+        let dump = FrameDump::record(&mut emulator, frames)?;
+        dump.save(path)?;
+        println!("Recorded {} frames to {}", dump.len(), path.display());
+        return Ok(());
+    }
+
+    // Keep the frame the other side produced so it can be highlighted
+    let (diff, expected, compared) = match against {
+        Some(path) => {
+            let dump = FrameDump::load(path)?;
+            let diff = frame_diff::run_against_dump(&mut emulator, &dump)?;
+            let expected = diff.as_ref().map(|d| dump.frames()[d.frame as usize].clone());
+            (diff, expected, dump.len() as u64)
+        }
+        None => {
+            let mut other = new_instance(Quirks::default())?;
+            let diff = frame_diff::run_side_by_side(&mut other, &mut emulator, frames)?;
+            (diff, Some(other.get_video_buffer().to_vec()), frames)
+        }
+    };
+
+    match diff {
+        Some(diff) => {
+            println!("Divergence at {}", diff);
+            if let (Some(path), Some(expected)) = (highlight, expected) {
+                let image = frame_diff::highlight(emulator.get_video_buffer(), &expected);
+                std::fs::write(path, frame_diff::to_ppm(&image))?;
+                println!("Highlight written to {}", path.display());
+            }
+            std::process::exit(1);
+        }
+        None => println!("No differences in {} frames", compared),
+    }
+    Ok(())
+}
+
+// Run without a frontend, feeding inputs from a script or movie, then write
+// the requested outputs. Per-game profile settings apply; SRAM and cheats
+// don't, so runs are reproducible.
+fn run_headless(rom_path: &Path, mapper: Option<MapperType>, config: &Config, options: &HeadlessOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let rom_data = read_rom(rom_path, config)?;
+    let game_id = GameId::from_rom_path(rom_path, &rom_data);
+    let mut emulator = Emulator::with_memory_init(config.emulation.memory_init())?;
+    emulator.set_render_mode(config.emulation.ppu_renderer);
+    emulator.set_apu_clock_ratio(config.emulation.apu_clock_ratio)?;
+    emulator.set_lint(config.debug.lint);
+    emulator.load_rom_image(rom_data, mapper)?;
+    
+    let game_paths = GamePaths::new(&config.paths, &game_id);
+    let profile = GameProfile::load(game_paths.profile_path())?;
+    emulator.set_quirks(profile.quirks);
+    emulator.set_enhancements(profile.enhancements);
+    emulator.set_peripherals(&profile.peripherals);
+    
+    let mut from_state = false;
+    let inputs: Box<dyn InputSource> = match (&options.input_script, &options.movie) {
+        (Some(path), _) => Box::new(InputScript::load(path)?),
+        (None, Some(path)) => {
+            let movie = Movie::load(path)?;
+            // Power-on with the memory the movie was recorded with, or
+            // the state it was recorded from
+            movie.start_playback(&mut emulator)?;
+            from_state = movie.starts_from_state();
+            Box::new(movie)
+        }
+        (None, None) => Box::new(InputScript::new()),
+    };
+    let frames = options.frames.unwrap_or(match inputs.frame_count() {
+        0 => 600,
+        count => count,
+    });
+    
+    if options.apu_port_trace.is_some() {
+        let trace = emulator.apu_mut().port_trace_mut();
+        trace.set_filter(options.apu_port_filter.unwrap_or_default());
+        trace.set_enabled(true);
+    }
+    
+    if options.wdm_tests {
+        emulator.cpu.wdm_mut().set_test_reports(true);
+    }
+    
+    // A recording starts where the inputs do
+    let mut recording = match &options.record_movie {
+        Some(_) if from_state => Some(Movie::start_recording(&mut emulator)?),
+        Some(_) => Some(Movie::for_emulator(&emulator)),
+        None => None,
+    };
+    let start = Instant::now();
+    movie::play(&mut emulator, inputs.as_ref(), frames, recording.as_mut())?;
+    println!("Ran {} frames in {:.2?}", frames, start.elapsed());
+    println!("Frame hash {:016x}, audio hash {:016x}", emulator.frame_hash(), emulator.audio_hash());
+    if let Some(linter) = emulator.bus().linter() {
+        println!("{} lint findings ({} more not kept)", linter.lints().len(), linter.suppressed());
+    }
+    
+    if let Some(path) = &options.screenshot {
+        let frame = emulator.widescreen_frame();
+        std::fs::write(path, frame_diff::to_ppm_with_width(frame.pixels, frame.width))?;
+        println!("Screenshot written to {}", path.display());
+    }
+    if let Some(path) = &options.export_spc {
+        emulator.export_spc_to_file(&path.to_string_lossy())?;
+        println!("SPC written to {}", path.display());
+    }
+    if let Some(path) = &options.save_state {
+        emulator.save_state_to_file(&path.to_string_lossy())?;
+        println!("Save state written to {}", path.display());
+    }
+    if let (Some(path), Some(recording)) = (&options.record_movie, recording) {
+        recording.save(path)?;
+        println!("Movie of {} frames written to {}", recording.len(), path.display());
+    }
+    if let Some(path) = &options.apu_port_trace {
+        let trace = emulator.apu().port_trace();
+        trace.save(path)?;
+        println!("{} APU port accesses written to {}", trace.len(), path.display());
+    }
+    if options.wdm_tests {
+        let wdm = emulator.cpu.wdm();
+        for report in wdm.test_reports() {
+            println!("{}", report);
+        }
+        match wdm.tests_passed() {
+            Some(true) => println!("All {} WDM test reports passed", wdm.test_reports().len()),
+            Some(false) => return Err("the test ROM reported failures".into()),
+            None => return Err("the test ROM reported no results".into()),
+        }
+    }
+    Ok(())
+}
+
 fn benchmark_emulator(rom_path: &PathBuf, frames: u64) -> Result<(), Box<dyn std::error::Error>> {
     info!("Benchmarking emulator performance...");
     info!("ROM: {:?}", rom_path);
@@ -0,0 +1,442 @@
+// Built-in self test, run when the emulator starts without a ROM: scrolling
+// color bars, a box per pad 1 button that lights up while it is held, and
+// a beeping 440 Hz tone. It checks video, audio and input setup without a
+// commercial ROM, and gives frontends something to smoke-test with.
+//
+// The ROM is a 32 KB LoROM homebrew assembled here at build time, so the
+// listing below is its source. The CPU polls HVBJOY instead of taking NMIs:
+//   - power-on: clears the PPU registers and work bytes, uploads the sound
+//     program through the IPL ROM, fills VRAM, CGRAM and OAM in forced blank
+//   - each vblank: scrolls BG1, rewrites the 12 button sprites from the
+//     pads read last frame, sets APU port 0 to the beep state and reads the
+//     pads once the auto-joypad read is done
+//   - the SPC700 sets up voice 0 on a square wave and keys it on and off
+//     as port 0 changes
+use crate::Emulator;
+use std::collections::HashMap;
+
+// Header title, which is how frontends recognise the self test
+pub const TITLE: &str = "CCSNES SELF TEST";
+
+const ROM_SIZE: usize = 0x8000;
+const HEADER: usize = 0x7FC0;
+
+// Work bytes in the direct page
+const PAD_LOW: u8 = 0x00;
+const PAD_HIGH: u8 = 0x01;
+const SCRATCH: u8 = 0x02;
+const FRAME: u8 = 0x04;
+
+// Where the sound program goes in APU RAM
+const SPC_ORIGIN: u16 = 0x0200;
+
+// Bar colors (BGR555), left to right
+const BAR_COLORS: [u16; 8] = [0x7FFF, 0x03FF, 0x7FE0, 0x03E0, 0x7C1F, 0x001F, 0x7C00, 0x0000];
+const RELEASED_COLOR: u16 = 0x294A;
+const PRESSED_COLOR: u16 = 0x03E0;
+
+// Button boxes: x, y and the JOY1L/JOY1H bits they show
+const BUTTONS: [(u8, u8, u8, u8); 12] = [
+    (56, 172, 0x20, 0x00),  // L
+    (192, 172, 0x10, 0x00), // R
+    (56, 184, 0x00, 0x08),  // Up
+    (46, 194, 0x00, 0x02),  // Left
+    (66, 194, 0x00, 0x01),  // Right
+    (56, 204, 0x00, 0x04),  // Down
+    (108, 196, 0x00, 0x20), // Select
+    (140, 196, 0x00, 0x10), // Start
+    (192, 184, 0x40, 0x00), // X
+    (182, 194, 0x00, 0x40), // Y
+    (202, 194, 0x80, 0x00), // A
+    (192, 204, 0x00, 0x80), // B
+];
+
+// Bars fill the tilemap rows above the button boxes
+const BAR_ROWS: u16 = 20;
+
+// Sprite attributes: priority 3, palette 0 or 1
+const RELEASED_ATTRIBUTES: u8 = 0x30;
+const PRESSED_ATTRIBUTES: u8 = 0x32;
+
+// DSP register writes setting up voice 0
+const DSP_SETUP: [(u8, u8); 17] = [
+    (0x6C, 0x20), // FLG: unmuted, echo writes off
+    (0x0C, 0x60), // MVOL
+    (0x1C, 0x60),
+    (0x2C, 0x00), // EVOL
+    (0x3C, 0x00),
+    (0x2D, 0x00), // PMON, NON, EON off
+    (0x3D, 0x00),
+    (0x4D, 0x00),
+    (0x5D, 0x03), // DIR at $0300
+    (0x00, 0x40), // V0VOL
+    (0x01, 0x40),
+    (0x02, 0x85), // V0PITCH $0385: 440 Hz for a 16-sample period
+    (0x03, 0x03),
+    (0x04, 0x00), // V0SRCN
+    (0x05, 0x00), // V0ADSR1: use GAIN
+    (0x07, 0x50), // V0GAIN: direct
+    (0x5C, 0x00), // KOF
+];
+
+// The self-test ROM image
+pub fn build() -> Vec<u8> {
+    let spc = spc_program();
+    let mut asm = Assembler::new(0x8000);
+    cpu_program(&mut asm, spc.len());
+
+    asm.label("spc_program");
+    asm.bytes(&spc);
+    asm.label("bar_palette");
+    for color in BAR_COLORS {
+        asm.bytes(&[0, 0]);
+        asm.bytes(&color.to_le_bytes());
+        asm.bytes(&[0; 4]);
+    }
+    asm.label("sprite_palette");
+    for color in [RELEASED_COLOR, PRESSED_COLOR] {
+        asm.bytes(&[0, 0]);
+        asm.bytes(&color.to_le_bytes());
+        asm.bytes(&[0; 28]);
+    }
+    // Tile 0 is empty, tile 1 solid color 1 (2bpp)
+    asm.label("bg_tiles");
+    asm.bytes(&[0; 16]);
+    for _ in 0..8 {
+        asm.bytes(&[0xFF, 0x00]);
+    }
+    // A 7x7 box in color 1 (4bpp)
+    asm.label("sprite_tile");
+    for row in 0..8 {
+        asm.bytes(&[if row < 7 { 0xFE } else { 0 }, 0]);
+    }
+    asm.bytes(&[0; 16]);
+    asm.label("buttons");
+    for (x, y, low, high) in BUTTONS {
+        asm.bytes(&[x, y, low, high]);
+    }
+    asm.label("rti");
+    asm.bytes(&[0x40]); // RTI
+
+    let rti = asm.address("rti");
+    let mut rom = asm.finish();
+    assert!(rom.len() <= HEADER, "self-test program overlaps the header");
+    rom.resize(ROM_SIZE, 0);
+
+    let title = format!("{:<21}", TITLE);
+    rom[HEADER..HEADER + 21].copy_from_slice(title.as_bytes());
+    rom[HEADER + 0x15] = 0x20; // LoROM
+    rom[HEADER + 0x17] = 0x05; // 32 KB
+    rom[HEADER + 0x19] = 0x01; // North America
+    // Native COP, BRK, NMI and IRQ, emulation COP, NMI and IRQ/BRK
+    for vector in [0x7FE4, 0x7FE6, 0x7FEA, 0x7FEE, 0x7FF4, 0x7FFA, 0x7FFE] {
+        rom[vector..vector + 2].copy_from_slice(&rti.to_le_bytes());
+    }
+    rom[0x7FFC..0x7FFE].copy_from_slice(&0x8000u16.to_le_bytes());
+
+    // Checksum over the image with the complement in place
+    rom[HEADER + 0x1C..HEADER + 0x20].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00]);
+    let checksum = rom.iter().fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+    rom[HEADER + 0x1C..HEADER + 0x1E].copy_from_slice(&(!checksum).to_le_bytes());
+    rom[HEADER + 0x1E..HEADER + 0x20].copy_from_slice(&checksum.to_le_bytes());
+    rom
+}
+
+// Whether the emulator is running the self test
+pub fn is_loaded(emulator: &Emulator) -> bool {
+    emulator.get_rom_info().is_some_and(|info| info.title.trim_end() == TITLE)
+}
+
+// 65816 program; A is 8-bit and X/Y 16-bit throughout
+fn cpu_program(asm: &mut Assembler, spc_len: usize) {
+    asm.bytes(&[0x78]); // SEI
+    asm.bytes(&[0x18, 0xFB]); // CLC; XCE
+    asm.bytes(&[0xC2, 0x10]); // REP #$10
+    asm.bytes(&[0xE2, 0x20]); // SEP #$20
+    asm.bytes(&[0xA2, 0xFF, 0x1F, 0x9A]); // LDX #$1FFF; TXS
+    asm.bytes(&[0xA9, 0x80, 0x8D, 0x00, 0x21]); // LDA #$80; STA INIDISP (forced blank)
+    asm.bytes(&[0x9C, 0x00, 0x42]); // STZ NMITIMEN
+
+    // Zero $2101-$2133 and the work bytes
+    asm.bytes(&[0xA2, 0x01, 0x00]); // LDX #$0001
+    asm.label("clear_ppu");
+    asm.bytes(&[0x9E, 0x00, 0x21]); // STZ $2100,X
+    asm.bytes(&[0xE8, 0xE0, 0x34, 0x00]); // INX; CPX #$0034
+    asm.branch(0xD0, "clear_ppu"); // BNE
+    asm.bytes(&[0xA2, 0x0F, 0x00]); // LDX #$000F
+    asm.label("clear_work");
+    asm.bytes(&[0x9E, 0x00, 0x00, 0xCA]); // STZ $0000,X; DEX
+    asm.branch(0x10, "clear_work"); // BPL
+
+    upload_spc(asm, spc_len);
+
+    // Tilemap at VRAM $0000: bar rows of tile 1, palette = column / 4
+    asm.bytes(&[0xA9, 0x80, 0x8D, 0x15, 0x21]); // LDA #$80; STA VMAIN
+    asm.bytes(&[0x9C, 0x16, 0x21, 0x9C, 0x17, 0x21]); // STZ VMADDL; STZ VMADDH
+    asm.bytes(&[0xA2, 0x00, 0x00]); // LDX #0
+    asm.label("tilemap");
+    let bar_words = (BAR_ROWS * 32).to_le_bytes();
+    asm.bytes(&[0xE0, bar_words[0], bar_words[1]]); // CPX #bar_words
+    asm.branch(0xB0, "tilemap_blank"); // BCS
+    asm.bytes(&[0xA9, 0x01, 0x8D, 0x18, 0x21]); // LDA #$01; STA VMDATAL
+    asm.bytes(&[0x8A, 0x29, 0x1C, 0x8D, 0x19, 0x21]); // TXA; AND #$1C; STA VMDATAH
+    asm.branch(0x80, "tilemap_next"); // BRA
+    asm.label("tilemap_blank");
+    asm.bytes(&[0x9C, 0x18, 0x21, 0x9C, 0x19, 0x21]); // STZ VMDATAL; STZ VMDATAH
+    asm.label("tilemap_next");
+    asm.bytes(&[0xE8, 0xE0, 0x00, 0x04]); // INX; CPX #$0400
+    asm.branch(0xD0, "tilemap"); // BNE
+
+    // BG1 tiles at $1000, sprite tiles at $2000
+    set_vram_address(asm, 0x1000);
+    copy_table(asm, "bg_tiles", 32, &[0x2118, 0x2119]);
+    set_vram_address(asm, 0x2000);
+    copy_table(asm, "sprite_tile", 32, &[0x2118, 0x2119]);
+
+    // Palettes: BG 0-7 and sprite 0-1
+    asm.bytes(&[0x9C, 0x21, 0x21]); // STZ CGADD
+    copy_table(asm, "bar_palette", 64, &[0x2122]);
+    asm.bytes(&[0xA9, 0x80, 0x8D, 0x21, 0x21]); // LDA #$80; STA CGADD
+    copy_table(asm, "sprite_palette", 64, &[0x2122]);
+
+    // Every sprite below the screen, small, until the main loop places them
+    asm.bytes(&[0x9C, 0x02, 0x21, 0x9C, 0x03, 0x21]); // STZ OAMADDL; STZ OAMADDH
+    asm.bytes(&[0xA2, 0x80, 0x00]); // LDX #128
+    asm.label("clear_oam");
+    asm.bytes(&[0x9C, 0x04, 0x21]); // STZ OAMDATA (x)
+    asm.bytes(&[0xA9, 0xF0, 0x8D, 0x04, 0x21]); // LDA #$F0; STA OAMDATA (y)
+    asm.bytes(&[0x9C, 0x04, 0x21, 0x9C, 0x04, 0x21]); // STZ OAMDATA (tile, attributes)
+    asm.bytes(&[0xCA]); // DEX
+    asm.branch(0xD0, "clear_oam"); // BNE
+    asm.bytes(&[0xA2, 0x20, 0x00]); // LDX #32
+    asm.label("clear_oam_high");
+    asm.bytes(&[0x9C, 0x04, 0x21, 0xCA]); // STZ OAMDATA; DEX
+    asm.branch(0xD0, "clear_oam_high"); // BNE
+
+    // Mode 0 with BG1 and sprites
+    asm.bytes(&[0xA9, 0x01, 0x8D, 0x0B, 0x21]); // LDA #$01; STA BG12NBA
+    asm.bytes(&[0xA9, 0x01, 0x8D, 0x01, 0x21]); // LDA #$01; STA OBSEL
+    asm.bytes(&[0xA9, 0x11, 0x8D, 0x2C, 0x21]); // LDA #$11; STA TM
+    asm.bytes(&[0xA9, 0x01, 0x8D, 0x00, 0x42]); // LDA #$01; STA NMITIMEN (auto-joypad)
+    asm.bytes(&[0xA9, 0x0F, 0x8D, 0x00, 0x21]); // LDA #$0F; STA INIDISP
+
+    asm.label("main");
+    asm.label("in_vblank");
+    asm.bytes(&[0xAD, 0x12, 0x42]); // LDA HVBJOY
+    asm.branch(0x30, "in_vblank"); // BMI
+    asm.label("wait_vblank");
+    asm.bytes(&[0xAD, 0x12, 0x42]); // LDA HVBJOY
+    asm.branch(0x10, "wait_vblank"); // BPL
+
+    asm.bytes(&[0xA5, FRAME, 0x8D, 0x0D, 0x21, 0x9C, 0x0D, 0x21]); // LDA frame; STA BG1HOFS; STZ BG1HOFS
+
+    asm.bytes(&[0x9C, 0x02, 0x21, 0x9C, 0x03, 0x21]); // STZ OAMADDL; STZ OAMADDH
+    asm.bytes(&[0xA2, 0x00, 0x00]); // LDX #0
+    asm.label("button");
+    asm.absolute(0xBD, "buttons", 0); // LDA buttons,X
+    asm.bytes(&[0x8D, 0x04, 0x21]); // STA OAMDATA (x)
+    asm.absolute(0xBD, "buttons", 1); // LDA buttons+1,X
+    asm.bytes(&[0x8D, 0x04, 0x21]); // STA OAMDATA (y)
+    asm.bytes(&[0x9C, 0x04, 0x21]); // STZ OAMDATA (tile 0)
+    asm.bytes(&[0xA5, PAD_LOW]); // LDA pad_low
+    asm.absolute(0x3D, "buttons", 2); // AND buttons+2,X
+    asm.bytes(&[0x85, SCRATCH, 0xA5, PAD_HIGH]); // STA scratch; LDA pad_high
+    asm.absolute(0x3D, "buttons", 3); // AND buttons+3,X
+    asm.bytes(&[0x05, SCRATCH]); // ORA scratch
+    asm.branch(0xF0, "released"); // BEQ
+    asm.bytes(&[0xA9, PRESSED_ATTRIBUTES]); // LDA #pressed
+    asm.branch(0x80, "attributes"); // BRA
+    asm.label("released");
+    asm.bytes(&[0xA9, RELEASED_ATTRIBUTES]); // LDA #released
+    asm.label("attributes");
+    asm.bytes(&[0x8D, 0x04, 0x21]); // STA OAMDATA
+    asm.bytes(&[0xE8, 0xE8, 0xE8, 0xE8]); // INX x4
+    asm.bytes(&[0xE0, BUTTONS.len() as u8 * 4, 0x00]); // CPX #entries
+    asm.branch(0xD0, "button"); // BNE
+
+    // Tone on for 32 frames, off for 32
+    asm.bytes(&[0xA5, FRAME, 0x4A, 0x4A, 0x4A, 0x4A, 0x4A]); // LDA frame; LSR A x5
+    asm.bytes(&[0x29, 0x01, 0x8D, 0x40, 0x21]); // AND #$01; STA APUIO0
+    asm.bytes(&[0xE6, FRAME]); // INC frame
+
+    asm.label("wait_joypad");
+    asm.bytes(&[0xAD, 0x12, 0x42, 0x29, 0x01]); // LDA HVBJOY; AND #$01
+    asm.branch(0xD0, "wait_joypad"); // BNE
+    asm.bytes(&[0xAD, 0x18, 0x42, 0x85, PAD_LOW]); // LDA JOY1L; STA pad_low
+    asm.bytes(&[0xAD, 0x19, 0x42, 0x85, PAD_HIGH]); // LDA JOY1H; STA pad_high
+    asm.absolute(0x4C, "main", 0); // JMP main
+}
+
+// Hand the sound program to the IPL ROM and start it. An APU that never
+// answers the handshake only costs the tone: the test carries on silent.
+fn upload_spc(asm: &mut Assembler, len: usize) {
+    let origin = SPC_ORIGIN.to_le_bytes();
+    asm.bytes(&[0xA9, 0xAA]); // LDA #$AA
+    wait_port0_or_skip(asm, "no_sound");
+    asm.bytes(&[0xA9, origin[0], 0x8D, 0x42, 0x21]); // LDA #<origin; STA APUIO2
+    asm.bytes(&[0xA9, origin[1], 0x8D, 0x43, 0x21]); // LDA #>origin; STA APUIO3
+    asm.bytes(&[0xA9, 0x01, 0x8D, 0x41, 0x21]); // LDA #$01; STA APUIO1 (transfer)
+    asm.bytes(&[0xA9, 0xCC, 0x8D, 0x40, 0x21]); // LDA #$CC; STA APUIO0
+    wait_port0_or_skip(asm, "no_sound");
+
+    asm.bytes(&[0xA2, 0x00, 0x00]); // LDX #0
+    asm.label("upload");
+    asm.absolute(0xBD, "spc_program", 0); // LDA spc_program,X
+    asm.bytes(&[0x8D, 0x41, 0x21]); // STA APUIO1
+    asm.bytes(&[0x8A, 0x8D, 0x40, 0x21]); // TXA; STA APUIO0 (index)
+    asm.label("wait_byte");
+    asm.bytes(&[0xCD, 0x40, 0x21]); // CMP APUIO0
+    asm.branch(0xD0, "wait_byte"); // BNE
+    let len = (len as u16).to_le_bytes();
+    asm.bytes(&[0xE8, 0xE0, len[0], len[1]]); // INX; CPX #len
+    asm.branch(0xD0, "upload"); // BNE
+
+    asm.bytes(&[0x9C, 0x41, 0x21]); // STZ APUIO1 (start)
+    asm.bytes(&[0xA9, origin[0], 0x8D, 0x42, 0x21]); // LDA #<origin; STA APUIO2
+    asm.bytes(&[0xA9, origin[1], 0x8D, 0x43, 0x21]); // LDA #>origin; STA APUIO3
+    asm.bytes(&[0x8A, 0x1A, 0x8D, 0x40, 0x21]); // TXA; INC A; STA APUIO0
+    asm.label("wait_start");
+    asm.bytes(&[0xCD, 0x40, 0x21]); // CMP APUIO0
+    asm.branch(0xD0, "wait_start"); // BNE
+    asm.label("no_sound");
+}
+
+// Poll APU port 0 until it reads A, giving up after a few frames
+fn wait_port0_or_skip(asm: &mut Assembler, skip: &str) {
+    let poll = asm.unique_label();
+    let answered = asm.unique_label();
+    asm.bytes(&[0xA0, 0x00, 0x40]); // LDY #$4000
+    asm.label(&poll);
+    asm.bytes(&[0xCD, 0x40, 0x21]); // CMP APUIO0
+    asm.branch(0xF0, &answered); // BEQ
+    asm.bytes(&[0x88]); // DEY
+    asm.branch(0xD0, &poll); // BNE
+    asm.absolute(0x4C, skip, 0); // JMP skip
+    asm.label(&answered);
+}
+
+fn set_vram_address(asm: &mut Assembler, address: u16) {
+    let [low, high] = address.to_le_bytes();
+    asm.bytes(&[0xA9, low, 0x8D, 0x16, 0x21]); // LDA #<address; STA VMADDL
+    asm.bytes(&[0xA9, high, 0x8D, 0x17, 0x21]); // LDA #>address; STA VMADDH
+}
+
+// Write `len` bytes of a table to the ports in turn, one byte each
+fn copy_table(asm: &mut Assembler, table: &'static str, len: u16, ports: &[u16]) {
+    let label = asm.unique_label();
+    asm.bytes(&[0xA2, 0x00, 0x00]); // LDX #0
+    asm.label(&label);
+    for (offset, port) in ports.iter().enumerate() {
+        asm.absolute(0xBD, table, offset as u16); // LDA table+offset,X
+        let [low, high] = port.to_le_bytes();
+        asm.bytes(&[0x8D, low, high]); // STA port
+    }
+    for _ in ports {
+        asm.bytes(&[0xE8]); // INX
+    }
+    let [low, high] = len.to_le_bytes();
+    asm.bytes(&[0xE0, low, high]); // CPX #len
+    asm.branch(0xD0, &label); // BNE
+}
+
+// SPC700 program at $0200, followed by the sample directory at $0300 and
+// the square wave
+fn spc_program() -> Vec<u8> {
+    let mut asm = Assembler::new(SPC_ORIGIN);
+    for (register, value) in DSP_SETUP {
+        asm.bytes(&[0x8F, register, 0xF2]); // MOV $F2,#register
+        asm.bytes(&[0x8F, value, 0xF3]); // MOV $F3,#value
+    }
+    asm.label("loop");
+    asm.bytes(&[0xE4, 0xF4, 0x28, 0x01]); // MOV A,$F4; AND A,#$01
+    asm.bytes(&[0x64, 0x10]); // CMP A,$10 (last state)
+    asm.branch(0xF0, "loop"); // BEQ
+    asm.bytes(&[0xC4, 0x10]); // MOV $10,A
+    asm.bytes(&[0x8F, 0x5C, 0xF2, 0x48, 0x01, 0xC4, 0xF3]); // MOV $F2,#KOF; EOR A,#$01; MOV $F3,A
+    asm.bytes(&[0x48, 0x01, 0x8F, 0x4C, 0xF2, 0xC4, 0xF3]); // EOR A,#$01; MOV $F2,#KON; MOV $F3,A
+    asm.branch(0x2F, "loop"); // BRA
+    let mut program = asm.finish();
+    assert!(program.len() <= 0x100, "sound program overlaps its sample directory");
+    program.resize(0x100, 0);
+
+    // Directory entry 0: start and loop point
+    program.extend_from_slice(&[0x04, 0x03, 0x04, 0x03]);
+    // One looping BRR block, range 11: eight samples of 7, eight of -7
+    program.extend_from_slice(&[0xB3, 0x77, 0x77, 0x77, 0x77, 0x99, 0x99, 0x99, 0x99]);
+    program
+}
+
+// Just enough of an assembler for the listing above: bytes, labels, and
+// branches and absolute operands that refer to labels defined later
+struct Assembler {
+    origin: u16,
+    code: Vec<u8>,
+    labels: HashMap<String, u16>,
+    fixups: Vec<(usize, String, Fixup)>,
+    next_label: usize,
+}
+
+enum Fixup {
+    Relative,
+    Absolute(u16),
+}
+
+impl Assembler {
+    fn new(origin: u16) -> Self {
+        Self { origin, code: Vec::new(), labels: HashMap::new(), fixups: Vec::new(), next_label: 0 }
+    }
+
+    fn pc(&self) -> u16 {
+        self.origin + self.code.len() as u16
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn label(&mut self, name: &str) {
+        let previous = self.labels.insert(name.to_string(), self.pc());
+        assert!(previous.is_none(), "label {} defined twice", name);
+    }
+
+    fn unique_label(&mut self) -> String {
+        self.next_label += 1;
+        format!("@{}", self.next_label)
+    }
+
+    fn address(&self, name: &str) -> u16 {
+        self.labels[name]
+    }
+
+    // Opcode and a 16-bit address, `offset` bytes past the label
+    fn absolute(&mut self, opcode: u8, target: &str, offset: u16) {
+        self.code.push(opcode);
+        self.fixups.push((self.code.len(), target.to_string(), Fixup::Absolute(offset)));
+        self.code.extend_from_slice(&[0, 0]);
+    }
+
+    // Opcode and an 8-bit displacement from the next instruction
+    fn branch(&mut self, opcode: u8, target: &str) {
+        self.code.push(opcode);
+        self.fixups.push((self.code.len(), target.to_string(), Fixup::Relative));
+        self.code.push(0);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        for (at, target, fixup) in std::mem::take(&mut self.fixups) {
+            let address = self.address(&target);
+            match fixup {
+                Fixup::Relative => {
+                    let next = self.origin as i32 + at as i32 + 1;
+                    let displacement = address as i32 - next;
+                    assert!((-128..=127).contains(&displacement), "branch to {} out of range", target);
+                    self.code[at] = displacement as i8 as u8;
+                }
+                Fixup::Absolute(offset) => {
+                    self.code[at..at + 2].copy_from_slice(&address.wrapping_add(offset).to_le_bytes());
+                }
+            }
+        }
+        self.code
+    }
+}
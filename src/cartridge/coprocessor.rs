@@ -0,0 +1,195 @@
+// Cartridge coprocessors (SA-1, SuperFX, the DSPs, ...) plug into the
+// console through one trait rather than each being wired in by hand. A
+// chip claims windows of CPU addresses for its registers, which it answers
+// in place of the cartridge mapping; it is clocked with the master cycles
+// that passed after every instruction, DMA and HDMA transfer; it can hold
+// the cartridge IRQ line; and its state goes into save states as a chunk
+// of bytes stored under its name.
+use crate::savestate::CoprocessorState;
+use crate::{EmulatorError, Result};
+use std::cell::RefCell;
+use std::ops::RangeInclusive;
+
+// CPU addresses a chip answers at: `addresses` within each of `banks`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusWindow {
+    pub banks: RangeInclusive<u8>,
+    pub addresses: RangeInclusive<u16>,
+}
+
+impl BusWindow {
+    pub fn new(banks: RangeInclusive<u8>, addresses: RangeInclusive<u16>) -> Self {
+        Self { banks, addresses }
+    }
+
+    // The same addresses in the system banks $00-$3F and their $80-$BF
+    // mirror, where most chips put their registers
+    pub fn system(addresses: RangeInclusive<u16>) -> [Self; 2] {
+        [Self::new(0x00..=0x3F, addresses.clone()), Self::new(0x80..=0xBF, addresses)]
+    }
+
+    pub fn contains(&self, address: u32) -> bool {
+        self.banks.contains(&((address >> 16) as u8)) && self.addresses.contains(&(address as u16))
+    }
+}
+
+pub trait Coprocessor: Send {
+    // Short name for logs and the key of its save state chunk; unique among
+    // the chips attached together
+    fn name(&self) -> &str;
+
+    // Addresses the chip answers at instead of the cartridge; asked once,
+    // when it is attached
+    fn windows(&self) -> Vec<BusWindow>;
+
+    // A CPU or DMA access inside one of the windows
+    fn read(&mut self, address: u32) -> u8;
+    fn write(&mut self, address: u32, value: u8);
+
+    // What a read would return, without its side effects, for debuggers;
+    // open bus unless the chip says otherwise
+    fn peek(&self, address: u32) -> u8 {
+        let _ = address;
+        0
+    }
+
+    // Run for `master_cycles` of the console's master clock; chips with
+    // their own oscillator divide it down themselves
+    fn run(&mut self, master_cycles: u64);
+
+    // Level of the chip's IRQ output; the CPU takes an IRQ while it's set
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // Console reset; a chip is expected to come up powered on
+    fn reset(&mut self);
+
+    // The chip's whole state in a layout of its own choosing, and back
+    fn save_state(&self) -> Vec<u8>;
+    fn load_state(&mut self, data: &[u8]) -> Result<()>;
+}
+
+struct Attached {
+    windows: Vec<BusWindow>,
+    // Reads can change a chip's state (status flags, FIFOs), and the bus
+    // reads through &self
+    chip: RefCell<Box<dyn Coprocessor>>,
+}
+
+// The chips on the cartridge, owned by the bus. With none attached every
+// access goes straight to the cartridge mapping.
+#[derive(Default)]
+pub struct Coprocessors {
+    chips: Vec<Attached>,
+}
+
+impl Coprocessors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attach(&mut self, chip: Box<dyn Coprocessor>) -> Result<()> {
+        if self.names().iter().any(|name| name == chip.name()) {
+            return Err(EmulatorError::config(format!("A coprocessor named '{}' is already attached", chip.name())));
+        }
+        self.chips.push(Attached { windows: chip.windows(), chip: RefCell::new(chip) });
+        Ok(())
+    }
+
+    // Take every chip off the bus, as when the cartridge comes out
+    pub fn clear(&mut self) {
+        self.chips.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chips.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chips.len()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.chips.iter().map(|attached| attached.chip.borrow().name().to_string()).collect()
+    }
+
+    // The first chip attached whose window holds `address`
+    fn claimant(&self, address: u32) -> Option<&Attached> {
+        self.chips.iter().find(|attached| attached.windows.iter().any(|window| window.contains(address)))
+    }
+
+    pub fn claims(&self, address: u32) -> bool {
+        self.claimant(address).is_some()
+    }
+
+    // The chip's answer, or None when no chip claims `address`
+    pub fn read(&self, address: u32) -> Option<u8> {
+        self.claimant(address).map(|attached| attached.chip.borrow_mut().read(address))
+    }
+
+    // The chip's answer to a debugger, see Coprocessor::peek
+    pub fn peek(&self, address: u32) -> Option<u8> {
+        self.claimant(address).map(|attached| attached.chip.borrow().peek(address))
+    }
+
+    // Whether a chip took the write
+    pub fn write(&self, address: u32, value: u8) -> bool {
+        match self.claimant(address) {
+            Some(attached) => {
+                attached.chip.borrow_mut().write(address, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn run(&mut self, master_cycles: u64) {
+        for attached in &mut self.chips {
+            attached.chip.get_mut().run(master_cycles);
+        }
+    }
+
+    // The cartridge IRQ line, pulled by any chip
+    pub fn irq(&self) -> bool {
+        self.chips.iter().any(|attached| attached.chip.borrow().irq())
+    }
+
+    pub fn reset(&mut self) {
+        for attached in &mut self.chips {
+            attached.chip.get_mut().reset();
+        }
+    }
+
+    pub fn save_state(&self) -> Vec<CoprocessorState> {
+        self.chips
+            .iter()
+            .map(|attached| {
+                let chip = attached.chip.borrow();
+                CoprocessorState { name: chip.name().to_string(), data: chip.save_state() }
+            })
+            .collect()
+    }
+
+    // Load one chunk into each chip. The state must have been saved with
+    // the same chips attached; if not, nothing is loaded.
+    pub fn load_state(&mut self, states: &[CoprocessorState]) -> Result<()> {
+        let names = self.names();
+        if let Some(extra) = states.iter().find(|state| !names.contains(&state.name)) {
+            return Err(EmulatorError::save_state(format!(
+                "Save state has a '{}' coprocessor, which isn't attached",
+                extra.name
+            )));
+        }
+        if let Some(missing) = names.iter().find(|name| !states.iter().any(|state| &state.name == *name)) {
+            return Err(EmulatorError::save_state(format!("Save state has nothing for the '{}' coprocessor", missing)));
+        }
+        for attached in &mut self.chips {
+            let chip = attached.chip.get_mut();
+            if let Some(state) = states.iter().find(|state| state.name == chip.name()) {
+                chip.load_state(&state.data)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -64,8 +64,30 @@ pub enum CoprocessorType {
 
 impl CartridgeHeader {
     pub fn parse(rom_data: &[u8]) -> Result<Self> {
-        // Try to detect header location (LoROM vs HiROM)
-        let header_offset = Self::detect_header_offset(rom_data)?;
+        Self::parse_with_sum(rom_data, Self::byte_sum(rom_data))
+    }
+
+    // Sum of all bytes, as used by the header checksum
+    pub fn byte_sum(rom_data: &[u8]) -> u32 {
+        rom_data.iter().fold(0u32, |sum, &byte| sum.wrapping_add(byte as u32))
+    }
+
+    // Parse with the byte sum already known (e.g. accumulated while the ROM
+    // was streamed in), so no pass over the whole image is needed
+    pub fn parse_with_sum(rom_data: &[u8], byte_sum: u32) -> Result<Self> {
+        Self::parse_as(rom_data, byte_sum, None)
+    }
+
+    // Parse with the header read from where `mapper` puts it and that
+    // mapper used regardless of the header's mapper byte (--force-mapper).
+    // None detects both as usual.
+    pub fn parse_as(rom_data: &[u8], byte_sum: u32, mapper: Option<MapperType>) -> Result<Self> {
+        let header_offset = match mapper {
+            Some(mapper) => Self::header_offset_for(mapper)
+                .ok_or_else(|| EmulatorError::InvalidMapperType(format!("{:?}", mapper)))?,
+            // Try to detect header location (LoROM vs HiROM)
+            None => Self::detect_header_offset(rom_data)?,
+        };
         log::debug!("Detected header offset: 0x{:X}", header_offset);
         
         if rom_data.len() < header_offset + 0x30 {
@@ -82,7 +104,7 @@ impl CartridgeHeader {
 
         // Parse mapper type (offset 0x15)
         let mapper_byte = header_data[0x15];
-        let mapper_type = MapperType::from_header_byte(mapper_byte);
+        let mapper_type = mapper.unwrap_or_else(|| MapperType::from_header_byte(mapper_byte));
 
         // Parse coprocessor type (offset 0x16)
         let coprocessor_byte = header_data[0x16];
@@ -114,7 +136,7 @@ impl CartridgeHeader {
         let checksum = u16::from_le_bytes([header_data[0x1E], header_data[0x1F]]);
 
         // Validate checksum
-        if !Self::validate_checksum(rom_data, checksum, complement) {
+        if !Self::validate_checksum(rom_data.len(), byte_sum, checksum, complement) {
             log::warn!("ROM checksum validation failed");
         }
 
@@ -131,6 +153,16 @@ impl CartridgeHeader {
         })
     }
 
+    // Header location in a ROM without copier header, for the mappers
+    // create_mapper supports
+    pub fn header_offset_for(mapper: MapperType) -> Option<usize> {
+        match mapper {
+            MapperType::LoROM => Some(0x7FC0),
+            MapperType::HiROM => Some(0xFFC0),
+            _ => None,
+        }
+    }
+
     fn detect_header_offset(rom_data: &[u8]) -> Result<usize> {
         // Check if ROM has a 512-byte copier header
         let has_copier_header = (rom_data.len() % 1024) == 512;
@@ -178,7 +210,7 @@ impl CartridgeHeader {
         }
     }
 
-    fn is_valid_header(header_data: &[u8]) -> bool {
+    pub(crate) fn is_valid_header(header_data: &[u8]) -> bool {
         // Check if the header looks valid by examining key fields
         if header_data.len() < 0x30 {
             return false;
@@ -243,20 +275,15 @@ impl CartridgeHeader {
         }
     }
 
-    fn validate_checksum(rom_data: &[u8], checksum: u16, complement: u16) -> bool {
+    pub(crate) fn validate_checksum(rom_size: usize, byte_sum: u32, checksum: u16, complement: u16) -> bool {
         // Basic checksum validation
         if checksum != (!complement & 0xFFFF) {
             return false;
         }
 
-        // Calculate actual ROM checksum
-        let mut calculated_checksum = 0u32;
-        for &byte in rom_data.iter() {
-            calculated_checksum = calculated_checksum.wrapping_add(byte as u32);
-        }
+        let mut calculated_checksum = byte_sum;
 
         // Handle different ROM sizes
-        let rom_size = rom_data.len();
         let power_of_two_size = rom_size.next_power_of_two();
         
         if power_of_two_size > rom_size {
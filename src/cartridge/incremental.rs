@@ -0,0 +1,67 @@
+// Chunked ROM loading. Data is appended piece by piece (e.g. between browser
+// frames) while the checksum sum is accumulated, so finishing the load only
+// parses the header instead of walking the whole image again.
+use super::{Cartridge, CartridgeHeader};
+use crate::Result;
+
+const COPIER_HEADER_SIZE: usize = 512;
+
+#[derive(Debug, Default)]
+pub struct RomLoader {
+    data: Vec<u8>,
+    expected_size: Option<usize>,
+    byte_sum: u32,
+    // Sum of the first 512 bytes, dropped if they turn out to be a copier header
+    prefix_sum: u32,
+}
+
+impl RomLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_expected_size(size: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(size),
+            expected_size: Some(size),
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) {
+        let offset = self.data.len();
+        if offset < COPIER_HEADER_SIZE {
+            let prefix = &chunk[..chunk.len().min(COPIER_HEADER_SIZE - offset)];
+            self.prefix_sum = self.prefix_sum.wrapping_add(CartridgeHeader::byte_sum(prefix));
+        }
+        self.byte_sum = self.byte_sum.wrapping_add(CartridgeHeader::byte_sum(chunk));
+        self.data.extend_from_slice(chunk);
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    // Fraction of the expected size received so far (0.0 when unknown)
+    pub fn progress(&self) -> f64 {
+        match self.expected_size {
+            Some(0) => 1.0,
+            Some(size) => (self.data.len() as f64 / size as f64).min(1.0),
+            None => 0.0,
+        }
+    }
+
+    pub fn finish(mut self) -> Result<Cartridge> {
+        let mut byte_sum = self.byte_sum;
+        if self.data.len() % 1024 == COPIER_HEADER_SIZE {
+            log::info!("Removing 512-byte copier header");
+            self.data.drain(..COPIER_HEADER_SIZE);
+            byte_sum = byte_sum.wrapping_sub(self.prefix_sum);
+        }
+        Cartridge::from_image(self.data, byte_sum)
+    }
+}
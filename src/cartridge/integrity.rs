@@ -0,0 +1,211 @@
+// Signs that a ROM file isn't a clean dump, from the header's checksum and
+// size fields and, when one is installed, a DAT file of known dumps (the
+// No-Intro/Logiqx XML format). Many "emulator bugs" turn out to be bad
+// ROMs, so loading warns about these instead of failing quietly later.
+use crate::cartridge::{Cartridge, CartridgeHeader};
+use crate::Result;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityWarning {
+    // The checksum and its complement don't add up to $FFFF
+    ChecksumComplement { checksum: u16, complement: u16 },
+    // The bytes don't sum to the header's checksum
+    ChecksumMismatch { header: u16, computed: u16 },
+    // At most half the size the header declares
+    Underdump { header_size: usize, file_size: usize },
+    // Bigger than the header declares
+    Overdump { header_size: usize, file_size: usize },
+    // A database is installed and doesn't know this ROM
+    NotInDatabase { crc32: u32 },
+    // The database knows this ROM under a name flagging it as bad
+    MarkedInDatabase { name: String },
+}
+
+impl IntegrityWarning {
+    // Short form for on-screen notices
+    pub fn summary(&self) -> &'static str {
+        match self {
+            IntegrityWarning::ChecksumComplement { .. } => "ROM header checksum is damaged",
+            IntegrityWarning::ChecksumMismatch { .. } => "ROM checksum mismatch",
+            IntegrityWarning::Underdump { .. } => "Underdump: ROM is too small",
+            IntegrityWarning::Overdump { .. } => "Overdump: ROM is too large",
+            IntegrityWarning::NotInDatabase { .. } => "ROM not in the ROM database",
+            IntegrityWarning::MarkedInDatabase { .. } => "Known bad dump",
+        }
+    }
+}
+
+impl fmt::Display for IntegrityWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityWarning::ChecksumComplement { checksum, complement } => write!(
+                f,
+                "header checksum ${:04X} and complement ${:04X} don't add up to $FFFF: the header was edited or damaged",
+                checksum, complement
+            ),
+            IntegrityWarning::ChecksumMismatch { header, computed } => write!(
+                f,
+                "header checksum is ${:04X} but the ROM sums to ${:04X}: a bad dump, or modified (hack, translation or patch)",
+                header, computed
+            ),
+            IntegrityWarning::Underdump { header_size, file_size } => write!(
+                f,
+                "header says {} KB but the file has only {} KB: an underdump missing part of the ROM",
+                header_size / 1024,
+                file_size / 1024
+            ),
+            IntegrityWarning::Overdump { header_size, file_size } => write!(
+                f,
+                "file has {} KB but the header says {} KB: an overdump, the extra data may be garbage",
+                file_size / 1024,
+                header_size / 1024
+            ),
+            IntegrityWarning::NotInDatabase { crc32 } => write!(
+                f,
+                "CRC32 {:08X} is not in the ROM database: a bad dump, a hack or translation, or a dump the database doesn't list",
+                crc32
+            ),
+            IntegrityWarning::MarkedInDatabase { name } => write!(f, "the ROM database lists this ROM as \"{}\"", name),
+        }
+    }
+}
+
+// Checks that need only the ROM itself
+pub fn check_cartridge(cartridge: &Cartridge) -> Vec<IntegrityWarning> {
+    let header = &cartridge.header;
+    let rom = &cartridge.rom_data[..];
+    let mut warnings = Vec::new();
+
+    if header.checksum != !header.complement {
+        warnings.push(IntegrityWarning::ChecksumComplement { checksum: header.checksum, complement: header.complement });
+    }
+    // The loader's own check pads with $FF; hardware-style tools mirror the
+    // tail of odd sizes. Either matching is a clean sum.
+    let computed = (if rom.len().is_power_of_two() { cartridge.byte_sum() } else { mirrored_sum(rom) }) as u16;
+    let padded_matches = CartridgeHeader::validate_checksum(rom.len(), cartridge.byte_sum(), header.checksum, !header.checksum);
+    if computed != header.checksum && !padded_matches {
+        warnings.push(IntegrityWarning::ChecksumMismatch { header: header.checksum, computed });
+    }
+
+    // Sizes between half and all of the declared size are normal: the
+    // header rounds up to a power of two
+    if rom.len() <= header.rom_size / 2 {
+        warnings.push(IntegrityWarning::Underdump { header_size: header.rom_size, file_size: rom.len() });
+    } else if rom.len() > header.rom_size {
+        warnings.push(IntegrityWarning::Overdump { header_size: header.rom_size, file_size: rom.len() });
+    }
+    warnings
+}
+
+// Byte sum with a size that isn't a power of two made up by repeating the
+// tail, as the checksum is computed for such carts
+pub fn mirrored_sum(rom: &[u8]) -> u32 {
+    if rom.is_empty() {
+        return 0;
+    }
+    let base = if rom.len().is_power_of_two() { rom.len() } else { rom.len().next_power_of_two() / 2 };
+    let head = CartridgeHeader::byte_sum(&rom[..base]);
+    let tail = &rom[base..];
+    if tail.is_empty() {
+        return head;
+    }
+    let repeats = (base / tail.len().next_power_of_two()) as u32;
+    head.wrapping_add(mirrored_sum(tail).wrapping_mul(repeats))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatEntry {
+    pub name: String,
+    pub size: usize,
+    pub crc32: u32,
+}
+
+// Known dumps from a DAT file; dumps are matched by the CRC32 of the ROM
+// without copier header
+#[derive(Debug, Clone, Default)]
+pub struct RomDatabase {
+    entries: Vec<DatEntry>,
+}
+
+// Name tags for dumps that aren't clean, as GoodTools-style sets use them
+const BAD_TAGS: &[&str] = &["[b", "[o", "[BadDump]"];
+
+impl RomDatabase {
+    // A missing file is an empty database, which checks nothing
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Every <rom name=".." size=".." crc=".."/> element; anything else in
+    // the file is skipped
+    pub fn parse(text: &str) -> Self {
+        let entries = text
+            .split("<rom ")
+            .skip(1)
+            .filter_map(|element| {
+                let element = &element[..element.find('>')?];
+                Some(DatEntry {
+                    name: unescape(attribute(element, "name")?),
+                    size: attribute(element, "size")?.parse().ok()?,
+                    crc32: u32::from_str_radix(attribute(element, "crc")?, 16).ok()?,
+                })
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn find(&self, crc32: u32, size: usize) -> Option<&DatEntry> {
+        self.entries.iter().find(|entry| entry.crc32 == crc32 && entry.size == size)
+    }
+
+    // What the database says about a ROM (without copier header); nothing
+    // for a clean dump or an empty database
+    pub fn check(&self, rom: &[u8]) -> Option<IntegrityWarning> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut crc = flate2::Crc::new();
+        crc.update(rom);
+        match self.find(crc.sum(), rom.len()) {
+            None => Some(IntegrityWarning::NotInDatabase { crc32: crc.sum() }),
+            Some(entry) if BAD_TAGS.iter().any(|tag| entry.name.contains(tag)) => {
+                Some(IntegrityWarning::MarkedInDatabase { name: entry.name.clone() })
+            }
+            Some(_) => None,
+        }
+    }
+}
+
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let key = format!("{}=\"", name);
+    let start = element
+        .match_indices(&key)
+        .map(|(at, _)| at)
+        .find(|&at| at == 0 || element[..at].ends_with(char::is_whitespace))?
+        + key.len();
+    let len = element[start..].find('"')?;
+    Some(&element[start..start + len])
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -1,22 +1,49 @@
-use crate::cartridge::CartridgeHeader;
-use crate::memory::mappers::{create_mapper, Mapper};
+use crate::cartridge::{CartridgeHeader, RomImage};
+use crate::memory::mappers::{create_mapper, Mapper, MapperType};
 use crate::{Result, EmulatorError};
 use log::info;
 
 pub struct Cartridge {
     pub header: CartridgeHeader,
-    pub rom_data: Vec<u8>,
+    pub rom_data: RomImage,
     pub sram: Vec<u8>,
     pub mapper: Box<dyn Mapper>,
+    // Number of bus writes that reached SRAM
+    sram_writes: u64,
+    // Sum of the ROM's bytes, for integrity checks
+    byte_sum: u32,
 }
 
 impl Cartridge {
     pub fn load(rom_data: &[u8]) -> Result<Self> {
+        Self::load_as(rom_data, None)
+    }
+    
+    // Load with the given mapper instead of the detected one
+    pub fn load_as(rom_data: &[u8], mapper: Option<MapperType>) -> Result<Self> {
         // Remove copier header if present
         let clean_rom_data = Self::remove_copier_header(rom_data);
-        
+        let byte_sum = CartridgeHeader::byte_sum(&clean_rom_data);
+        Self::from_image_as(clean_rom_data, byte_sum, mapper)
+    }
+    
+    // Load from a ROM file's bytes without copying them, e.g. a mapped file
+    pub fn from_rom_image_as(rom_image: RomImage, mapper: Option<MapperType>) -> Result<Self> {
+        let clean_rom_data = rom_image.without_copier_header();
+        let byte_sum = CartridgeHeader::byte_sum(&clean_rom_data);
+        Self::from_image_as(clean_rom_data, byte_sum, mapper)
+    }
+    
+    // Build a cartridge from a ROM image without copier header whose byte
+    // sum is already known
+    pub fn from_image(clean_rom_data: impl Into<RomImage>, byte_sum: u32) -> Result<Self> {
+        Self::from_image_as(clean_rom_data, byte_sum, None)
+    }
+    
+    pub fn from_image_as(clean_rom_data: impl Into<RomImage>, byte_sum: u32, mapper: Option<MapperType>) -> Result<Self> {
+        let clean_rom_data = clean_rom_data.into();
         // Parse header
-        let header = CartridgeHeader::parse(&clean_rom_data)?;
+        let header = CartridgeHeader::parse_as(&clean_rom_data, byte_sum, mapper)?;
         
         info!("Loaded cartridge:");
         info!("{}", header);
@@ -41,6 +68,8 @@ impl Cartridge {
             rom_data: clean_rom_data,
             sram,
             mapper,
+            sram_writes: 0,
+            byte_sum,
         })
     }
 
@@ -68,6 +97,7 @@ impl Cartridge {
         if let Some(sram_offset) = self.mapper.map_sram_address(address) {
             if sram_offset < self.sram.len() {
                 self.sram[sram_offset] = value;
+                self.sram_writes += 1;
             }
         }
         // ROM writes are ignored
@@ -87,6 +117,14 @@ impl Cartridge {
         Ok(())
     }
 
+    pub fn byte_sum(&self) -> u32 {
+        self.byte_sum
+    }
+
+    pub fn sram_write_count(&self) -> u64 {
+        self.sram_writes
+    }
+
     pub fn save_sram(&self) -> Vec<u8> {
         self.sram.clone()
     }
@@ -101,7 +139,7 @@ impl Cartridge {
         self.has_sram()
     }
 
-    fn remove_copier_header(rom_data: &[u8]) -> Vec<u8> {
+    pub(crate) fn remove_copier_header(rom_data: &[u8]) -> Vec<u8> {
         // Check if ROM has a 512-byte copier header
         if (rom_data.len() % 1024) == 512 {
             info!("Removing 512-byte copier header");
@@ -1,5 +1,13 @@
 pub mod header;
 pub mod loader;
+pub mod incremental;
+pub mod integrity;
+pub mod patch;
+pub mod rom_image;
+pub mod verify;
+pub mod coprocessor;
 
 pub use header::CartridgeHeader;
-pub use loader::Cartridge;
\ No newline at end of file
+pub use loader::Cartridge;
+pub use incremental::RomLoader;
+pub use rom_image::RomImage;
\ No newline at end of file
@@ -0,0 +1,199 @@
+// IPS and BPS ROM patches (translations, hacks, bug fixes). Patches are
+// applied to the ROM image before it is parsed, so the result loads like
+// any other dump.
+use crate::{EmulatorError, Result};
+use std::path::Path;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const BPS_MAGIC: &[u8] = b"BPS1";
+// Source, target and patch CRC32s
+const BPS_FOOTER_SIZE: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchFormat {
+    Ips,
+    Bps,
+}
+
+impl PatchFormat {
+    // Identify a patch by its magic bytes
+    pub fn detect(patch: &[u8]) -> Option<Self> {
+        if patch.starts_with(IPS_MAGIC) {
+            Some(PatchFormat::Ips)
+        } else if patch.starts_with(BPS_MAGIC) {
+            Some(PatchFormat::Bps)
+        } else {
+            None
+        }
+    }
+
+    // Identify a patch file by its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        if ext.eq_ignore_ascii_case("ips") {
+            Some(PatchFormat::Ips)
+        } else if ext.eq_ignore_ascii_case("bps") {
+            Some(PatchFormat::Bps)
+        } else {
+            None
+        }
+    }
+}
+
+// Apply an IPS or BPS patch, returning the patched ROM
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    match PatchFormat::detect(patch) {
+        Some(PatchFormat::Ips) => apply_ips(rom, patch),
+        Some(PatchFormat::Bps) => apply_bps(rom, patch),
+        None => Err(EmulatorError::rom_load("Unrecognised patch format (expected IPS or BPS)")),
+    }
+}
+
+fn truncated() -> EmulatorError {
+    EmulatorError::rom_load("Patch file is truncated")
+}
+
+pub fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    if !patch.starts_with(IPS_MAGIC) {
+        return Err(EmulatorError::rom_load("Not an IPS patch"));
+    }
+    let mut output = rom.to_vec();
+    let mut pos = IPS_MAGIC.len();
+    let mut read = |len: usize| -> Result<&[u8]> {
+        let bytes = patch.get(pos..pos + len).ok_or_else(truncated)?;
+        pos += len;
+        Ok(bytes)
+    };
+
+    loop {
+        let offset = read(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = (offset[0] as usize) << 16 | (offset[1] as usize) << 8 | offset[2] as usize;
+        let size = read(2)?;
+        let size = (size[0] as usize) << 8 | size[1] as usize;
+
+        // A zero size marks a run-length record
+        let data = if size == 0 {
+            let run = read(3)?;
+            vec![run[2]; (run[0] as usize) << 8 | run[1] as usize]
+        } else {
+            read(size)?.to_vec()
+        };
+        if output.len() < offset + data.len() {
+            output.resize(offset + data.len(), 0);
+        }
+        output[offset..offset + data.len()].copy_from_slice(&data);
+    }
+
+    // Optional truncation extension after the EOF marker
+    if let Ok(length) = read(3) {
+        output.truncate((length[0] as usize) << 16 | (length[1] as usize) << 8 | length[2] as usize);
+    }
+    Ok(output)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+pub fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>> {
+    if !patch.starts_with(BPS_MAGIC) || patch.len() < BPS_MAGIC.len() + BPS_FOOTER_SIZE {
+        return Err(EmulatorError::rom_load("Not a BPS patch"));
+    }
+    let footer = &patch[patch.len() - BPS_FOOTER_SIZE..];
+    let footer_crc = |n: usize| u32::from_le_bytes([footer[n], footer[n + 1], footer[n + 2], footer[n + 3]]);
+    if crc32(&patch[..patch.len() - 4]) != footer_crc(8) {
+        return Err(EmulatorError::rom_load("BPS patch is corrupt (checksum mismatch)"));
+    }
+    if crc32(rom) != footer_crc(0) {
+        return Err(EmulatorError::rom_load("BPS patch was made for a different ROM (source checksum mismatch)"));
+    }
+
+    let actions = &patch[..patch.len() - BPS_FOOTER_SIZE];
+    let mut pos = BPS_MAGIC.len();
+    let source_size = read_number(actions, &mut pos)?;
+    let target_size = read_number(actions, &mut pos)?;
+    let metadata_size = read_number(actions, &mut pos)?;
+    if source_size != rom.len() {
+        return Err(EmulatorError::rom_load("BPS patch was made for a different ROM (size mismatch)"));
+    }
+    pos = pos.checked_add(metadata_size).ok_or_else(truncated)?;
+
+    // The size comes from the patch; don't trust it for the allocation
+    let mut target = Vec::with_capacity(target_size.min(rom.len().saturating_add(actions.len())));
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    while pos < actions.len() {
+        let command = read_number(actions, &mut pos)?;
+        let length = (command >> 2) + 1;
+        // TargetCopy can repeat its own output forever; nothing may run
+        // past the size the patch promised
+        if length > target_size - target.len() {
+            return Err(truncated());
+        }
+        let span = |start: usize| start.checked_add(length).map(|end| start..end).ok_or_else(truncated);
+        match command & 3 {
+            // SourceRead: copy from the same position in the source
+            0 => {
+                let start = target.len();
+                target.extend_from_slice(rom.get(span(start)?).ok_or_else(truncated)?);
+            }
+            // TargetRead: literal bytes from the patch
+            1 => {
+                target.extend_from_slice(actions.get(span(pos)?).ok_or_else(truncated)?);
+                pos += length;
+            }
+            // SourceCopy: copy from a movable position in the source
+            2 => {
+                source_offset = relative_offset(source_offset, read_number(actions, &mut pos)?)?;
+                target.extend_from_slice(rom.get(span(source_offset)?).ok_or_else(truncated)?);
+                source_offset += length;
+            }
+            // TargetCopy: copy already written output (may overlap)
+            _ => {
+                target_offset = relative_offset(target_offset, read_number(actions, &mut pos)?)?;
+                for _ in 0..length {
+                    let byte = *target.get(target_offset).ok_or_else(truncated)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size || crc32(&target) != footer_crc(4) {
+        return Err(EmulatorError::rom_load("BPS patch produced an unexpected result (target checksum mismatch)"));
+    }
+    Ok(target)
+}
+
+// BPS variable-length number
+fn read_number(data: &[u8], pos: &mut usize) -> Result<usize> {
+    let mut value = 0usize;
+    let mut shift = 1usize;
+    loop {
+        let byte = *data.get(*pos).ok_or_else(truncated)?;
+        *pos += 1;
+        // A crafted patch can run the number past usize
+        let digit = ((byte & 0x7F) as usize).checked_mul(shift).ok_or_else(truncated)?;
+        value = value.checked_add(digit).ok_or_else(truncated)?;
+        if byte & 0x80 != 0 {
+            return Ok(value);
+        }
+        // checked_shl only fails for shifts past the width, so multiply
+        shift = shift.checked_mul(0x80).ok_or_else(truncated)?;
+        value = value.checked_add(shift).ok_or_else(truncated)?;
+    }
+}
+
+// Signed relative offset; bit 0 of the encoded value is the sign
+fn relative_offset(offset: usize, encoded: usize) -> Result<usize> {
+    let delta = encoded >> 1;
+    let moved = if encoded & 1 != 0 { offset.checked_sub(delta) } else { offset.checked_add(delta) };
+    moved.ok_or_else(truncated)
+}
@@ -0,0 +1,95 @@
+// ROM bytes behind a cartridge: either owned, or (native builds) a
+// read-only memory map of the ROM file, so large ROMs are neither read up
+// front nor held twice. A copier header is skipped by offset rather than
+// copying the rest of the image.
+#[cfg(not(target_arch = "wasm32"))]
+use crate::Result;
+use std::fmt;
+use std::ops::Deref;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+
+// Copier headers are 512 bytes on top of a multiple of 1 KiB
+const COPIER_HEADER_SIZE: usize = 512;
+
+#[derive(Clone)]
+pub enum RomImage {
+    Owned(Vec<u8>),
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped { map: Arc<memmap2::Mmap>, start: usize },
+}
+
+impl RomImage {
+    // Map `path` read-only. The file must not change while it's mapped;
+    // ROM files aren't written to while a game runs.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn map(path: &Path) -> Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the map is read-only and only read through slices; see above
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Self::Mapped { map: Arc::new(map), start: 0 })
+    }
+
+    pub fn is_mapped(&self) -> bool {
+        !matches!(self, Self::Owned(_))
+    }
+
+    pub fn has_copier_header(&self) -> bool {
+        self.len() % 1024 == COPIER_HEADER_SIZE
+    }
+
+    pub fn without_copier_header(self) -> Self {
+        if !self.has_copier_header() {
+            return self;
+        }
+        log::info!("Removing 512-byte copier header");
+        match self {
+            Self::Owned(mut data) => {
+                data.drain(..COPIER_HEADER_SIZE);
+                Self::Owned(data)
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Mapped { map, start } => Self::Mapped { map, start: start + COPIER_HEADER_SIZE },
+        }
+    }
+}
+
+impl Deref for RomImage {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(data) => data,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Mapped { map, start } => &map[*start..],
+        }
+    }
+}
+
+impl From<Vec<u8>> for RomImage {
+    fn from(data: Vec<u8>) -> Self {
+        Self::Owned(data)
+    }
+}
+
+impl PartialEq for RomImage {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl PartialEq<Vec<u8>> for RomImage {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        **self == other[..]
+    }
+}
+
+// The bytes are usually megabytes; show the size and backing instead
+impl fmt::Debug for RomImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.is_mapped() { "mapped" } else { "owned" };
+        write!(f, "RomImage({} bytes, {})", self.len(), kind)
+    }
+}
@@ -0,0 +1,179 @@
+// Mapper diagnosis for --verify-rom: the ROM is read as both LoROM and
+// HiROM, each interpretation is scored on its header and reset vector, and
+// the report says which one the loader picks and why.
+use crate::cartridge::loader::Cartridge;
+use crate::cartridge::CartridgeHeader;
+use crate::memory::mappers::{create_mapper, MapperType};
+use std::fmt;
+
+// Opcodes games commonly start with: SEI, CLC, SEP, REP, XCE, JML, JMP,
+// STZ, LDA/LDX/LDY immediate, PHK, SEC
+const BOOT_OPCODES: &[u8] = &[0x78, 0x18, 0xE2, 0xC2, 0xFB, 0x5C, 0x4C, 0x9C, 0xA9, 0xA2, 0xA0, 0x4B, 0x38];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapperCandidate {
+    pub mapper: MapperType,
+    pub header_offset: usize,
+    // Title, size fields and layout byte look like a header
+    pub header_plausible: bool,
+    pub mapper_byte: u8,
+    // The header's mapper byte names this layout
+    pub mapper_byte_matches: bool,
+    // Checksum and complement add up to $FFFF
+    pub complement_matches: bool,
+    // Checksum equals the sum of the ROM's bytes
+    pub checksum_matches: bool,
+    // Emulation-mode reset vector
+    pub reset_vector: u16,
+    // The vector points into ROM, at a byte that looks like a first instruction
+    pub reset_in_rom: bool,
+    pub reset_opcode: Option<u8>,
+    pub score: i32,
+}
+
+impl MapperCandidate {
+    fn evaluate(rom: &[u8], mapper: MapperType) -> Option<Self> {
+        let header_offset = CartridgeHeader::header_offset_for(mapper)?;
+        let header = rom.get(header_offset..header_offset + 0x40)?;
+
+        let mapper_byte = header[0x15];
+        let named = MapperType::from_header_byte(mapper_byte);
+        let mapper_byte_matches = match mapper {
+            MapperType::LoROM => matches!(named, MapperType::LoROM | MapperType::ExLoROM | MapperType::SA1),
+            _ => matches!(named, MapperType::HiROM | MapperType::ExHiROM),
+        };
+        let complement = u16::from_le_bytes([header[0x1C], header[0x1D]]);
+        let checksum = u16::from_le_bytes([header[0x1E], header[0x1F]]);
+        let byte_sum = CartridgeHeader::byte_sum(rom);
+
+        let reset_vector = u16::from_le_bytes([header[0x3C], header[0x3D]]);
+        let reset_opcode = create_mapper(mapper, rom.len(), 0)
+            .ok()
+            .filter(|_| reset_vector >= 0x8000)
+            .and_then(|map| map.map_address(reset_vector as u32))
+            .and_then(|offset| rom.get(offset).copied());
+
+        let mut candidate = Self {
+            mapper,
+            header_offset,
+            header_plausible: CartridgeHeader::is_valid_header(header),
+            mapper_byte,
+            mapper_byte_matches,
+            complement_matches: checksum == !complement,
+            checksum_matches: CartridgeHeader::validate_checksum(rom.len(), byte_sum, checksum, complement),
+            reset_vector,
+            reset_in_rom: reset_opcode.is_some(),
+            reset_opcode,
+            score: 0,
+        };
+        candidate.score = candidate.compute_score();
+        Some(candidate)
+    }
+
+    fn compute_score(&self) -> i32 {
+        let mut score = 0;
+        score += if self.header_plausible { 2 } else { 0 };
+        score += if self.mapper_byte_matches { 2 } else { 0 };
+        score += if self.complement_matches { 2 } else { 0 };
+        // A matching checksum is hard to hit by accident
+        score += if self.checksum_matches { 4 } else { 0 };
+        score += if self.reset_in_rom { 2 } else { 0 };
+        score += match self.reset_opcode {
+            Some(opcode) if BOOT_OPCODES.contains(&opcode) => 1,
+            // BRK or erased ROM
+            Some(0x00) | Some(0xFF) => -1,
+            _ => 0,
+        };
+        score
+    }
+}
+
+impl fmt::Display for MapperCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mark = |ok: bool| if ok { "yes" } else { "no" };
+        writeln!(f, "{:?} (header at ${:04X}): score {}", self.mapper, self.header_offset, self.score)?;
+        writeln!(f, "  header fields plausible: {}", mark(self.header_plausible))?;
+        writeln!(
+            f,
+            "  mapper byte ${:02X} ({:?}) matches: {}",
+            self.mapper_byte,
+            MapperType::from_header_byte(self.mapper_byte),
+            mark(self.mapper_byte_matches)
+        )?;
+        writeln!(f, "  checksum complement valid: {}", mark(self.complement_matches))?;
+        writeln!(f, "  checksum matches ROM: {}", mark(self.checksum_matches))?;
+        match self.reset_opcode {
+            Some(opcode) => write!(f, "  reset vector ${:04X} -> opcode ${:02X}", self.reset_vector, opcode),
+            None => write!(f, "  reset vector ${:04X} is outside ROM", self.reset_vector),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MapperReport {
+    // Interpretations that fit the ROM (too small a ROM has no HiROM header)
+    pub candidates: Vec<MapperCandidate>,
+    // What loading picks: header detection, or the forced mapper
+    pub chosen: Option<MapperType>,
+    pub forced: Option<MapperType>,
+    // Highest scoring interpretation
+    pub best: Option<MapperType>,
+}
+
+impl MapperReport {
+    // The loader's choice disagrees with the scores
+    pub fn is_suspicious(&self) -> bool {
+        matches!((self.chosen, self.best), (Some(chosen), Some(best)) if !same_layout(chosen, best))
+    }
+
+    pub fn candidate(&self, mapper: MapperType) -> Option<&MapperCandidate> {
+        self.candidates.iter().find(|candidate| candidate.mapper == mapper)
+    }
+}
+
+fn same_layout(chosen: MapperType, candidate: MapperType) -> bool {
+    match candidate {
+        MapperType::LoROM => matches!(chosen, MapperType::LoROM | MapperType::ExLoROM | MapperType::SA1),
+        _ => matches!(chosen, MapperType::HiROM | MapperType::ExHiROM),
+    }
+}
+
+impl fmt::Display for MapperReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for candidate in &self.candidates {
+            writeln!(f, "{}", candidate)?;
+        }
+        match (self.forced, self.chosen) {
+            (Some(forced), _) => writeln!(f, "Chosen: {:?} (forced with --force-mapper)", forced)?,
+            (None, Some(chosen)) => writeln!(f, "Chosen: {:?} (from the detected header's mapper byte)", chosen)?,
+            (None, None) => writeln!(f, "Chosen: none (no header could be read)")?,
+        }
+        match self.best {
+            Some(best) if self.is_suspicious() => write!(
+                f,
+                "Warning: {:?} scores higher; if the game fails to boot try --force-mapper {}",
+                best,
+                format!("{:?}", best).to_ascii_lowercase()
+            ),
+            Some(best) => write!(f, "Best match: {:?}", best),
+            None => write!(f, "Best match: none"),
+        }
+    }
+}
+
+// Score both interpretations of a ROM (copier header allowed)
+pub fn verify_mapper(rom_data: &[u8], forced: Option<MapperType>) -> MapperReport {
+    let rom = Cartridge::remove_copier_header(rom_data);
+    let candidates: Vec<MapperCandidate> = [MapperType::LoROM, MapperType::HiROM]
+        .into_iter()
+        .filter_map(|mapper| MapperCandidate::evaluate(&rom, mapper))
+        .collect();
+    // Ties go to LoROM, the first candidate
+    let best = candidates
+        .iter()
+        .rev()
+        .max_by_key(|candidate| candidate.score)
+        .map(|candidate| candidate.mapper);
+    let chosen = forced.or_else(|| CartridgeHeader::parse(&rom).ok().map(|header| header.mapper_type));
+    MapperReport { candidates, chosen, forced, best }
+}
@@ -0,0 +1,100 @@
+// Per-game cheat list stored as TOML. Each cheat is a Pro Action Replay
+// style code (AAAAAAVV, or AAAAAA:VV) whose byte is frozen on the bus while
+// the cheat is enabled.
+use crate::memory::Bus;
+use crate::{EmulatorError, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheatCode {
+    pub address: u32,
+    pub value: u8,
+}
+
+impl CheatCode {
+    pub fn parse(code: &str) -> Result<Self> {
+        let digits: String = code.chars().filter(|c| !matches!(c, ':' | '-' | ' ')).collect();
+        if digits.len() != 8 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(EmulatorError::config(format!("Invalid cheat code '{}', expected AAAAAAVV", code)));
+        }
+        let raw = u32::from_str_radix(&digits, 16).unwrap();
+        Ok(Self { address: raw >> 8, value: raw as u8 })
+    }
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cheat {
+    pub name: String,
+    pub code: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CheatList {
+    #[serde(default, rename = "cheat")]
+    pub cheats: Vec<Cheat>,
+}
+
+impl CheatList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A missing file is an empty list
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => {
+                let list: CheatList = toml::from_str(&contents)?;
+                for cheat in &list.cheats {
+                    CheatCode::parse(&cheat.code)?;
+                }
+                Ok(list)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, name: &str, code: &str) -> Result<()> {
+        CheatCode::parse(code)?;
+        self.cheats.push(Cheat { name: name.to_string(), code: code.to_string(), enabled: true });
+        Ok(())
+    }
+
+    // Enable or disable the named cheat; returns false if there is none
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for cheat in self.cheats.iter_mut().filter(|c| c.name == name) {
+            cheat.enabled = enabled;
+            found = true;
+        }
+        found
+    }
+
+    // Replace the bus freezes with the enabled cheats; returns how many were installed
+    pub fn apply(&self, bus: &mut Bus) -> Result<usize> {
+        bus.clear_freezes();
+        let mut installed = 0;
+        for cheat in self.cheats.iter().filter(|c| c.enabled) {
+            let code = CheatCode::parse(&cheat.code)?;
+            bus.freeze(code.address, code.value)?;
+            installed += 1;
+        }
+        Ok(installed)
+    }
+}
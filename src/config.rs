@@ -1,10 +1,21 @@
 // Configuration system for the SNES emulator
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use crate::Result;
+use std::time::SystemTime;
+use crate::{EmulatorError, Result};
+use crate::input::controller::{
+    BUTTON_A, BUTTON_B, BUTTON_X, BUTTON_Y,
+    BUTTON_L, BUTTON_R, BUTTON_START, BUTTON_SELECT,
+    BUTTON_UP, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT
+};
+use crate::input::conflicts::{self, InputConflict};
+use crate::input::HotkeyAction;
+use crate::memory::{MemoryInit, MemoryPattern};
+use crate::ppu::RenderMode;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     // Video settings
     pub video: VideoConfig,
@@ -15,6 +26,10 @@ pub struct Config {
     // Input settings
     pub input: InputConfig,
     
+    // Emulator hotkeys
+    #[serde(default)]
+    pub hotkeys: HotkeyConfig,
+    
     // Emulation settings
     pub emulation: EmulationConfig,
     
@@ -25,7 +40,7 @@ pub struct Config {
     pub debug: DebugConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VideoConfig {
     // Window scale (1-4)
     pub scale: u32,
@@ -47,13 +62,21 @@ pub struct VideoConfig {
     
     // CRT filter enable
     pub crt_filter: bool,
+    
+    // Average each frame with the previous one (30Hz flicker transparency)
+    #[serde(default)]
+    pub frame_blend: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AudioConfig {
-    // Master volume (0-100)
+    // Master volume in percent (0-200)
     pub master_volume: u8,
     
+    // Silence output, keeping master_volume for unmuting
+    pub muted: bool,
+    
     // Sample rate (32000, 44100, 48000)
     pub sample_rate: u32,
     
@@ -63,11 +86,31 @@ pub struct AudioConfig {
     // Enable audio
     pub enabled: bool,
     
-    // Low-pass filter
+    // Low-pass filter modelling the console's analog output, and where it
+    // rolls off
     pub low_pass_filter: bool,
+    pub low_pass_cutoff_hz: u32,
+    
+    // Gaussian interpolation as on hardware; false resamples linearly
+    pub gaussian_interpolation: bool,
+    
+    // Gain of each of the eight DSP voices (0.0-2.0)
+    pub voice_gain: [f32; 8],
+    
+    // Audio host backend name (native only, None = system default)
+    pub backend: Option<String>,
+    
+    // Output device name (native only, None = system default)
+    pub device: Option<String>,
+    
+    // Target output latency in milliseconds (0 = backend default)
+    pub latency_ms: u32,
+    
+    // Exclusive device access where supported
+    pub exclusive_mode: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InputConfig {
     // Controller mappings for player 1
     pub player1: ControllerMapping,
@@ -77,9 +120,36 @@ pub struct InputConfig {
     
     // Turbo button speed (frames between presses)
     pub turbo_speed: u8,
+    
+    // Named sets of binding overrides, picked per game by the game
+    // profile's input_profile or switched at runtime
+    #[serde(default)]
+    pub profiles: BTreeMap<String, InputProfile>,
+    
+    // Profile in effect and the mappings it produces; runtime only
+    #[serde(skip)]
+    active_profile: Option<(String, [ControllerMapping; 2])>,
+    
+    // Address to accept remote input on over TCP ("0.0.0.0:7755"); empty
+    // turns it off. See input::remote.
+    #[serde(default)]
+    pub remote_listen: String,
+    
+    // Player the remote client controls, 1 or 2
+    #[serde(default = "default_remote_player")]
+    pub remote_player: u8,
+}
+
+// Bindings that differ from the base mappings, by button name:
+// `[input.profiles.shoulder_swap.player1]` with `l = "W"` and `r = "Q"`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputProfile {
+    pub player1: BTreeMap<String, String>,
+    pub player2: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ControllerMapping {
     // D-Pad
     pub up: String,
@@ -102,7 +172,56 @@ pub struct ControllerMapping {
     pub start: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Hotkey bindings: a key name with optional modifiers ("F5", "Ctrl+R") or a
+// controller combo ("Pad:Select+L"). An empty string leaves the action unbound.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotkeyConfig {
+    // Save states
+    pub save_state: String,
+    pub load_state: String,
+    pub next_slot: String,
+    pub previous_slot: String,
+    
+    // Speed control
+    pub rewind: String,
+    pub fast_forward: String,
+    
+    // System
+    pub screenshot: String,
+    pub export_spc: String,
+    pub reset: String,
+    pub quit: String,
+    
+    // Layer toggles
+    pub toggle_bg1: String,
+    pub toggle_bg2: String,
+    pub toggle_bg3: String,
+    pub toggle_bg4: String,
+    pub toggle_obj: String,
+    
+    // Video filters
+    pub toggle_frame_blend: String,
+    
+    // Input profiles
+    pub next_input_profile: String,
+    
+    // Pacing
+    pub cycle_sync_mode: String,
+    
+    // Menu with resume, reset, state slots, filter and volume
+    pub pause_menu: String,
+    
+    // Volume
+    pub volume_up: String,
+    pub volume_down: String,
+    pub toggle_mute: String,
+    
+    // Start or stop recording a movie from wherever the game is
+    pub toggle_recording: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EmulationConfig {
     // Region (NTSC/PAL)
     pub region: Region,
@@ -119,8 +238,46 @@ pub struct EmulationConfig {
     // SRAM save interval (seconds)
     pub sram_save_interval: u32,
     
+    // Frames without SRAM writes before changes are written to disk
+    #[serde(default = "default_sram_idle_frames")]
+    pub sram_idle_frames: u32,
+    
     // Run ahead frames (for input lag reduction)
     pub run_ahead_frames: u8,
+    
+    // Save slots kept uncompressed in memory for instant loads
+    #[serde(default = "default_hot_slots")]
+    pub hot_slots: usize,
+    
+    // Power-on WRAM/VRAM fill: zero, ones, stripes or random
+    #[serde(default)]
+    pub memory_init: MemoryPattern,
+    
+    // Seed for the random fill; unset picks a new one each run
+    #[serde(default)]
+    pub memory_seed: Option<u64>,
+    
+    // PPU renderer: scanline (fast) or dot (per-dot, for mid-line effects)
+    #[serde(default)]
+    pub ppu_renderer: RenderMode,
+    
+    // Memory-map ROM files instead of reading them into memory
+    #[serde(default)]
+    pub mmap_rom: bool,
+    
+    // What paces emulation: the video timer, the audio queue, or nothing
+    #[serde(default)]
+    pub sync_mode: SyncMode,
+    
+    // APU clock relative to the stock console's (1.0); 0.5 to 2.0
+    #[serde(default = "default_apu_clock_ratio")]
+    pub apu_clock_ratio: f64,
+}
+
+impl EmulationConfig {
+    pub fn memory_init(&self) -> MemoryInit {
+        MemoryInit::resolve(self.memory_init, self.memory_seed)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
@@ -130,7 +287,31 @@ pub enum Region {
     Auto,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMode {
+    // 60 Hz timer, with vsync lining presents up with the display
+    #[default]
+    Video,
+    // Run frames as the audio output drains its queue
+    Audio,
+    // As fast as possible, for benchmarking
+    Free,
+}
+
+impl SyncMode {
+    // Video, audio, free, then back to video
+    pub fn next(self) -> Self {
+        match self {
+            Self::Video => Self::Audio,
+            Self::Audio => Self::Free,
+            Self::Free => Self::Video,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PathConfig {
     // ROM directory
     pub rom_dir: PathBuf,
@@ -146,9 +327,22 @@ pub struct PathConfig {
     
     // BIOS/firmware directory
     pub bios_dir: PathBuf,
+    
+    // Root for per-game save/state/screenshot directories
+    pub data_root: PathBuf,
+    
+    // Store user data under <data_root>/<name>-<crc32>/ instead of the flat directories
+    pub per_game_dirs: bool,
+    
+    // Cached ROM metadata for the library browser
+    pub library_cache: PathBuf,
+    
+    // DAT file of known good dumps (No-Intro XML) for bad dump warnings;
+    // without one only the header is checked
+    pub rom_database: PathBuf,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DebugConfig {
     // Show FPS counter
     pub show_fps: bool,
@@ -167,6 +361,23 @@ pub struct DebugConfig {
     
     // Performance profiling
     pub profiling: bool,
+    
+    // Measure input-to-display latency and show it on screen
+    #[serde(default)]
+    pub input_latency: bool,
+    
+    // Show both pads' buttons for the frame on screen
+    #[serde(default)]
+    pub show_input: bool,
+    
+    // Address of the JSON-RPC server for external tools
+    // ("127.0.0.1:7756"); empty turns it off. See debug::tool_server.
+    #[serde(default)]
+    pub tool_server: String,
+    
+    // Log suspicious game behaviour (debug::lint)
+    #[serde(default)]
+    pub lint: bool,
 }
 
 impl Default for Config {
@@ -175,6 +386,7 @@ impl Default for Config {
             video: VideoConfig::default(),
             audio: AudioConfig::default(),
             input: InputConfig::default(),
+            hotkeys: HotkeyConfig::default(),
             emulation: EmulationConfig::default(),
             paths: PathConfig::default(),
             debug: DebugConfig::default(),
@@ -192,6 +404,7 @@ impl Default for VideoConfig {
             integer_scaling: true,
             scanline_intensity: 0,
             crt_filter: false,
+            frame_blend: false,
         }
     }
 }
@@ -200,10 +413,18 @@ impl Default for AudioConfig {
     fn default() -> Self {
         Self {
             master_volume: 80,
+            muted: false,
             sample_rate: 48000,
             buffer_size: 512,
             enabled: true,
             low_pass_filter: true,
+            low_pass_cutoff_hz: crate::apu::filter::DEFAULT_CUTOFF_HZ,
+            gaussian_interpolation: true,
+            voice_gain: [1.0; 8],
+            backend: None,
+            device: None,
+            latency_ms: 64,
+            exclusive_mode: false,
         }
     }
 }
@@ -214,6 +435,10 @@ impl Default for InputConfig {
             player1: ControllerMapping::default_player1(),
             player2: ControllerMapping::default_player2(),
             turbo_speed: 6,
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            remote_listen: String::new(),
+            remote_player: default_remote_player(),
         }
     }
 }
@@ -236,6 +461,54 @@ impl ControllerMapping {
         }
     }
     
+    // Key names paired with the controller button they press
+    pub fn bindings(&self) -> [(&str, u16); 12] {
+        [
+            (&self.up, BUTTON_UP),
+            (&self.down, BUTTON_DOWN),
+            (&self.left, BUTTON_LEFT),
+            (&self.right, BUTTON_RIGHT),
+            (&self.a, BUTTON_A),
+            (&self.b, BUTTON_B),
+            (&self.x, BUTTON_X),
+            (&self.y, BUTTON_Y),
+            (&self.l, BUTTON_L),
+            (&self.r, BUTTON_R),
+            (&self.select, BUTTON_SELECT),
+            (&self.start, BUTTON_START),
+        ]
+    }
+    
+    // Buttons bound to a host key name (case-insensitive)
+    pub fn buttons_for_key(&self, key: &str) -> u16 {
+        self.bindings()
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(key))
+            .fold(0, |buttons, (_, button)| buttons | button)
+    }
+    
+    // Bind a host key name to the button named like its field ("a",
+    // "start", "up"; case-insensitive)
+    pub fn set_binding(&mut self, button: &str, key: &str) -> Result<()> {
+        let slot = match button.to_ascii_lowercase().as_str() {
+            "up" => &mut self.up,
+            "down" => &mut self.down,
+            "left" => &mut self.left,
+            "right" => &mut self.right,
+            "a" => &mut self.a,
+            "b" => &mut self.b,
+            "x" => &mut self.x,
+            "y" => &mut self.y,
+            "l" => &mut self.l,
+            "r" => &mut self.r,
+            "select" => &mut self.select,
+            "start" => &mut self.start,
+            _ => return Err(EmulatorError::config(format!("Unknown controller button '{}'", button))),
+        };
+        *slot = key.to_string();
+        Ok(())
+    }
+    
     pub fn default_player2() -> Self {
         Self {
             up: "I".to_string(),
@@ -254,6 +527,156 @@ impl ControllerMapping {
     }
 }
 
+impl InputConfig {
+    // Buttons a host key presses on each controller port
+    pub fn buttons_for_key(&self, key: &str) -> [u16; 2] {
+        let [player1, player2] = self.mappings();
+        [player1.buttons_for_key(key), player2.buttons_for_key(key)]
+    }
+    
+    // Mappings in effect: the base ones with the active profile applied
+    pub fn mappings(&self) -> [&ControllerMapping; 2] {
+        match &self.active_profile {
+            Some((_, [player1, player2])) => [player1, player2],
+            None => [&self.player1, &self.player2],
+        }
+    }
+    
+    // Bind a key to a button on `player`'s controller (0 or 1)
+    pub fn set_key_binding(&mut self, player: u8, button: &str, key: &str) -> Result<()> {
+        match player {
+            0 => self.player1.set_binding(button, key)?,
+            1 => self.player2.set_binding(button, key)?,
+            _ => return Err(EmulatorError::config(format!("No controller for player {}", player + 1))),
+        }
+        // Profiles override the changed base mapping
+        let active = self.active_profile().map(str::to_string);
+        self.set_active_profile(active.as_deref())
+    }
+    
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_ref().map(|(name, _)| name.as_str())
+    }
+    
+    // Switch to the named profile, or back to the base mappings with None
+    pub fn set_active_profile(&mut self, name: Option<&str>) -> Result<()> {
+        let Some(name) = name else {
+            self.active_profile = None;
+            return Ok(());
+        };
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            EmulatorError::config(format!("Unknown input profile '{}'", name))
+        })?;
+        let mut mappings = [self.player1.clone(), self.player2.clone()];
+        for (mapping, overrides) in mappings.iter_mut().zip([&profile.player1, &profile.player2]) {
+            for (button, key) in overrides {
+                mapping.set_binding(button, key).map_err(|e| {
+                    EmulatorError::config(format!("Input profile '{}': {}", name, e))
+                })?;
+            }
+        }
+        self.active_profile = Some((name.to_string(), mappings));
+        Ok(())
+    }
+    
+    // Duplicate bindings, buttons that can't be pressed and keys hotkeys
+    // take from the game, for the base mappings and every profile; empty
+    // when all is well. Key names are the native frontend's (see
+    // input::conflicts::check for other hosts).
+    pub fn validate(&self, hotkeys: &HotkeyConfig) -> Vec<InputConflict> {
+        conflicts::check(self, hotkeys, conflicts::is_key_name)
+    }
+    
+    // Step to the next profile in name order, then back to the base
+    // mappings. Returns the profile now active.
+    pub fn cycle_profile(&mut self) -> Result<Option<&str>> {
+        let next = match self.active_profile() {
+            None => self.profiles.keys().next(),
+            Some(active) => self.profiles.keys().find(|name| name.as_str() > active),
+        }
+        .cloned();
+        self.set_active_profile(next.as_deref())?;
+        Ok(self.active_profile())
+    }
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            save_state: "F5".to_string(),
+            load_state: "F8".to_string(),
+            next_slot: "F7".to_string(),
+            previous_slot: "F6".to_string(),
+            rewind: "Backspace".to_string(),
+            fast_forward: "Tab".to_string(),
+            screenshot: "F12".to_string(),
+            export_spc: "Shift+F12".to_string(),
+            reset: "Ctrl+R".to_string(),
+            quit: "Escape".to_string(),
+            toggle_bg1: "1".to_string(),
+            toggle_bg2: "2".to_string(),
+            toggle_bg3: "3".to_string(),
+            toggle_bg4: "4".to_string(),
+            toggle_obj: "5".to_string(),
+            toggle_frame_blend: "F9".to_string(),
+            next_input_profile: "F10".to_string(),
+            cycle_sync_mode: "F11".to_string(),
+            pause_menu: "P".to_string(),
+            volume_up: "Equal".to_string(),
+            volume_down: "Minus".to_string(),
+            toggle_mute: "M".to_string(),
+            toggle_recording: "Ctrl+M".to_string(),
+        }
+    }
+}
+
+impl HotkeyConfig {
+    // Binding strings paired with the action they trigger
+    pub fn bindings(&self) -> [(HotkeyAction, &str); 23] {
+        [
+            (HotkeyAction::SaveState, &self.save_state),
+            (HotkeyAction::LoadState, &self.load_state),
+            (HotkeyAction::NextSlot, &self.next_slot),
+            (HotkeyAction::PreviousSlot, &self.previous_slot),
+            (HotkeyAction::Rewind, &self.rewind),
+            (HotkeyAction::FastForward, &self.fast_forward),
+            (HotkeyAction::Screenshot, &self.screenshot),
+            (HotkeyAction::ExportSpc, &self.export_spc),
+            (HotkeyAction::Reset, &self.reset),
+            (HotkeyAction::Quit, &self.quit),
+            (HotkeyAction::ToggleBg1, &self.toggle_bg1),
+            (HotkeyAction::ToggleBg2, &self.toggle_bg2),
+            (HotkeyAction::ToggleBg3, &self.toggle_bg3),
+            (HotkeyAction::ToggleBg4, &self.toggle_bg4),
+            (HotkeyAction::ToggleObj, &self.toggle_obj),
+            (HotkeyAction::ToggleFrameBlend, &self.toggle_frame_blend),
+            (HotkeyAction::NextInputProfile, &self.next_input_profile),
+            (HotkeyAction::CycleSyncMode, &self.cycle_sync_mode),
+            (HotkeyAction::PauseMenu, &self.pause_menu),
+            (HotkeyAction::VolumeUp, &self.volume_up),
+            (HotkeyAction::VolumeDown, &self.volume_down),
+            (HotkeyAction::ToggleMute, &self.toggle_mute),
+            (HotkeyAction::ToggleRecording, &self.toggle_recording),
+        ]
+    }
+}
+
+fn default_sram_idle_frames() -> u32 {
+    30
+}
+
+fn default_remote_player() -> u8 {
+    2
+}
+
+fn default_hot_slots() -> usize {
+    4
+}
+
+fn default_apu_clock_ratio() -> f64 {
+    1.0
+}
+
 impl Default for EmulationConfig {
     fn default() -> Self {
         Self {
@@ -262,7 +685,15 @@ impl Default for EmulationConfig {
             rewind_buffer_frames: 600, // 10 seconds at 60fps
             auto_save_sram: true,
             sram_save_interval: 10,
+            sram_idle_frames: default_sram_idle_frames(),
             run_ahead_frames: 0,
+            hot_slots: default_hot_slots(),
+            memory_init: MemoryPattern::default(),
+            memory_seed: None,
+            ppu_renderer: RenderMode::Scanline,
+            mmap_rom: false,
+            sync_mode: SyncMode::Video,
+            apu_clock_ratio: default_apu_clock_ratio(),
         }
     }
 }
@@ -278,6 +709,10 @@ impl Default for PathConfig {
             sram_dir: base.join("sram"),
             screenshot_dir: base.join("screenshots"),
             bios_dir: base.join("bios"),
+            data_root: base.join("games"),
+            per_game_dirs: true,
+            library_cache: base.join("library.toml"),
+            rom_database: base.join("snes.dat"),
         }
     }
 }
@@ -291,6 +726,10 @@ impl Default for DebugConfig {
             ppu_layer_debug: false,
             memory_trace: false,
             profiling: false,
+            input_latency: false,
+            show_input: false,
+            tool_server: String::new(),
+            lint: false,
         }
     }
 }
@@ -352,6 +791,151 @@ impl Config {
         fs::create_dir_all(&self.paths.sram_dir)?;
         fs::create_dir_all(&self.paths.screenshot_dir)?;
         fs::create_dir_all(&self.paths.bios_dir)?;
+        if self.paths.per_game_dirs {
+            fs::create_dir_all(&self.paths.data_root)?;
+        }
+        Ok(())
+    }
+}
+// Sections that differ between two configurations
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConfigChanges {
+    pub video: bool,
+    pub audio: bool,
+    pub input: bool,
+    pub hotkeys: bool,
+    pub emulation: bool,
+    pub paths: bool,
+    pub debug: bool,
+}
+
+impl ConfigChanges {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Config {
+    // Which sections differ from `new`
+    pub fn changes(&self, new: &Config) -> ConfigChanges {
+        ConfigChanges {
+            video: self.video != new.video,
+            audio: self.audio != new.audio,
+            input: self.input != new.input,
+            hotkeys: self.hotkeys != new.hotkeys,
+            emulation: self.emulation != new.emulation,
+            paths: self.paths != new.paths,
+            debug: self.debug != new.debug,
+        }
+    }
+    
+    // Changed settings that cannot be applied to a running emulator and only
+    // take effect after a reset or restart
+    pub fn reset_required(&self, new: &Config) -> Vec<&'static str> {
+        let checks = [
+            ("video.scale", self.video.scale != new.video.scale),
+            ("video.fullscreen", self.video.fullscreen != new.video.fullscreen),
+            ("audio.sample_rate", self.audio.sample_rate != new.audio.sample_rate),
+            ("emulation.region", self.emulation.region != new.emulation.region),
+            ("emulation.rewind_buffer_frames", self.emulation.rewind_buffer_frames != new.emulation.rewind_buffer_frames),
+            ("emulation.run_ahead_frames", self.emulation.run_ahead_frames != new.emulation.run_ahead_frames),
+            ("paths", self.paths != new.paths),
+            ("debug.cpu_trace", self.debug.cpu_trace != new.debug.cpu_trace),
+            ("debug.memory_trace", self.debug.memory_trace != new.debug.memory_trace),
+        ];
+        checks.iter().filter(|(_, changed)| *changed).map(|(name, _)| *name).collect()
+    }
+    
+    // Apply a partial JSON configuration on top of this one, e.g.
+    // {"audio": {"latency_ms": 32}}. Unknown keys are rejected.
+    pub fn merge_json(&self, json: &str) -> Result<Config> {
+        let patch: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| EmulatorError::config(format!("Invalid config JSON: {}", e)))?;
+        let mut merged = serde_json::to_value(self)
+            .map_err(|e| EmulatorError::config(e.to_string()))?;
+        merge_value(&mut merged, patch)?;
+        serde_json::from_value(merged)
+            .map_err(|e| EmulatorError::config(format!("Invalid config JSON: {}", e)))
+    }
+}
+
+fn merge_value(target: &mut serde_json::Value, patch: serde_json::Value) -> Result<()> {
+    match (target, patch) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                let entry = target.get_mut(&key)
+                    .ok_or_else(|| EmulatorError::config(format!("Unknown config key '{}'", key)))?;
+                merge_value(entry, value)?;
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+    Ok(())
+}
+
+// A configuration reloaded from disk
+#[derive(Debug, Clone)]
+pub struct ConfigUpdate {
+    pub config: Config,
+    pub changes: ConfigChanges,
+    pub reset_required: Vec<&'static str>,
+}
+
+// Reloads the config file when its modification time changes
+pub struct ConfigWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    current: Config,
+}
+
+impl ConfigWatcher {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        Ok(Self {
+            modified: modified_time(&path),
+            current: Config::load_from_file(&path)?,
+            path,
+        })
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    // Configuration as last read from disk
+    pub fn current(&self) -> &Config {
+        &self.current
+    }
+    
+    // Write `config` to the file, e.g. for a setting changed in the
+    // frontend; poll doesn't report it back as an update
+    pub fn save(&mut self, config: Config) -> Result<()> {
+        config.save_to_file(&self.path)?;
+        self.modified = modified_time(&self.path);
+        self.current = config;
         Ok(())
     }
-}
\ No newline at end of file
+    
+    // Check the file; returns the new configuration if it changed. A file
+    // that fails to parse is reported once and retried on the next save.
+    pub fn poll(&mut self) -> Result<Option<ConfigUpdate>> {
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return Ok(None);
+        }
+        self.modified = modified;
+        
+        let config = Config::load_from_file(&self.path)?;
+        let changes = self.current.changes(&config);
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        let reset_required = self.current.reset_required(&config);
+        self.current = config.clone();
+        Ok(Some(ConfigUpdate { config, changes, reset_required }))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -1,4 +1,4 @@
-use crate::memory::Bus;
+use crate::cpu::bus::Bus65816;
 use crate::cpu::registers::CpuRegisters;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -49,7 +49,7 @@ pub struct AddressingResult {
 }
 
 impl AddressingMode {
-    pub fn resolve(&self, cpu: &mut CpuRegisters, bus: &mut Bus) -> AddressingResult {
+    pub fn resolve(&self, cpu: &mut CpuRegisters, bus: &mut impl Bus65816) -> AddressingResult {
         match self {
             AddressingMode::Implied => {
                 AddressingResult {
@@ -69,34 +69,14 @@ impl AddressingMode {
                 }
             }
 
-            AddressingMode::Immediate => {
-                let pc = cpu.pc;
-                let value = if cpu.memory_width() {
-                    // 8-bit immediate
-                    let val = bus.read8(pc) as u16;
-                    cpu.increment_pc(1);
-                    val
-                } else {
-                    // 16-bit immediate
-                    let val = bus.read16(pc);
-                    cpu.increment_pc(2);
-                    val
-                };
-                
-                AddressingResult {
-                    address: pc,
-                    value,
-                    cycles: 0,
-                    crossed_page: false,
-                }
-            }
+            AddressingMode::Immediate => Self::resolve_immediate(cpu, bus, cpu.memory_width()),
 
             AddressingMode::DirectPage => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let address = (cpu.d + offset) as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -112,11 +92,11 @@ impl AddressingMode {
             }
 
             AddressingMode::DirectPageX => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let address = (cpu.d + offset + cpu.get_x()) as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -132,11 +112,11 @@ impl AddressingMode {
             }
 
             AddressingMode::DirectPageY => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let address = (cpu.d + offset + cpu.get_y()) as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -152,12 +132,12 @@ impl AddressingMode {
             }
 
             AddressingMode::DirectPageIndirect => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let pointer_addr = (cpu.d + offset) as u32;
                 let address = bus.read16(pointer_addr) as u32 | ((cpu.db as u32) << 16);
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -173,12 +153,12 @@ impl AddressingMode {
             }
 
             AddressingMode::DirectPageIndirectX => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let pointer_addr = (cpu.d + offset + cpu.get_x()) as u32;
                 let address = bus.read16(pointer_addr) as u32 | ((cpu.db as u32) << 16);
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -194,13 +174,13 @@ impl AddressingMode {
             }
 
             AddressingMode::DirectPageIndirectY => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let pointer_addr = (cpu.d + offset) as u32;
                 let base_address = bus.read16(pointer_addr) as u32 | ((cpu.db as u32) << 16);
                 let address = base_address + cpu.get_y() as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -218,12 +198,12 @@ impl AddressingMode {
             }
 
             AddressingMode::DirectPageIndirectLong => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let pointer_addr = (cpu.d + offset) as u32;
                 let address = bus.read24(pointer_addr);
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -239,13 +219,13 @@ impl AddressingMode {
             }
 
             AddressingMode::DirectPageIndirectLongY => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let pointer_addr = (cpu.d + offset) as u32;
                 let base_address = bus.read24(pointer_addr);
                 let address = base_address + cpu.get_y() as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -264,7 +244,7 @@ impl AddressingMode {
                 let address = bus.read16(cpu.pc) as u32 | ((cpu.db as u32) << 16);
                 cpu.increment_pc(2);
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -282,7 +262,7 @@ impl AddressingMode {
                 cpu.increment_pc(2);
                 let address = base_address + cpu.get_x() as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -303,7 +283,7 @@ impl AddressingMode {
                 cpu.increment_pc(2);
                 let address = base_address + cpu.get_y() as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -324,7 +304,7 @@ impl AddressingMode {
                 cpu.increment_pc(2);
                 let address = bus.read16(pointer_addr) as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -343,7 +323,7 @@ impl AddressingMode {
                 let pointer_addr = base_pointer + cpu.get_x() as u32;
                 let address = bus.read16(pointer_addr) as u32 | ((cpu.get_pc_bank() as u32) << 16);
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -360,7 +340,7 @@ impl AddressingMode {
                 let address = bus.read24(cpu.pc);
                 cpu.increment_pc(3);
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -378,7 +358,7 @@ impl AddressingMode {
                 cpu.increment_pc(3);
                 let address = base_address + cpu.get_x() as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -396,7 +376,7 @@ impl AddressingMode {
                 cpu.increment_pc(2);
                 let address = bus.read24(pointer_addr);
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -410,11 +390,11 @@ impl AddressingMode {
             }
 
             AddressingMode::StackRelative => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let address = (cpu.s + offset) as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -428,13 +408,13 @@ impl AddressingMode {
             }
 
             AddressingMode::StackRelativeIndirectY => {
-                let offset = bus.read8(cpu.pc) as u16;
+                let offset = bus.read(cpu.pc) as u16;
                 cpu.increment_pc(1);
                 let pointer_addr = (cpu.s + offset) as u32;
                 let base_address = bus.read16(pointer_addr) as u32 | ((cpu.db as u32) << 16);
                 let address = base_address + cpu.get_y() as u32;
                 let value = if cpu.memory_width() {
-                    bus.read8(address) as u16
+                    bus.read(address) as u16
                 } else {
                     bus.read16(address)
                 };
@@ -449,8 +429,8 @@ impl AddressingMode {
 
             AddressingMode::BlockMove => {
                 // For MVP/MVN instructions - operands are source and destination banks
-                let src_bank = bus.read8(cpu.pc) as u16;
-                let dst_bank = bus.read8(cpu.pc + 1) as u16;
+                let src_bank = bus.read(cpu.pc) as u16;
+                let dst_bank = bus.read(cpu.pc + 1) as u16;
                 cpu.increment_pc(2);
                 
                 AddressingResult {
@@ -462,7 +442,7 @@ impl AddressingMode {
             }
 
             AddressingMode::Relative => {
-                let offset = bus.read8(cpu.pc) as i8 as i32;
+                let offset = bus.read(cpu.pc) as i8 as i32;
                 cpu.increment_pc(1);
                 let address = (cpu.pc as i32 + offset) as u32 & 0xFFFFFF;
                 
@@ -489,7 +469,29 @@ impl AddressingMode {
         }
     }
 
-    pub fn write_result(&self, cpu: &mut CpuRegisters, bus: &mut Bus, result: &AddressingResult, value: u16) {
+    // Immediate operand of an explicit size. Which size an instruction takes
+    // is decided by Instruction::uses_memory_width/uses_index_width.
+    pub fn resolve_immediate(cpu: &mut CpuRegisters, bus: &mut impl Bus65816, eight_bit: bool) -> AddressingResult {
+        let pc = cpu.pc;
+        let value = if eight_bit {
+            let val = bus.read(pc) as u16;
+            cpu.increment_pc(1);
+            val
+        } else {
+            let val = bus.read16(pc);
+            cpu.increment_pc(2);
+            val
+        };
+
+        AddressingResult {
+            address: pc,
+            value,
+            cycles: 0,
+            crossed_page: false,
+        }
+    }
+
+    pub fn write_result(&self, cpu: &mut CpuRegisters, bus: &mut impl Bus65816, result: &AddressingResult, value: u16) {
         match self {
             AddressingMode::Accumulator => {
                 cpu.set_a(value);
@@ -497,7 +499,7 @@ impl AddressingMode {
             
             _ => {
                 if cpu.memory_width() {
-                    bus.write8(result.address, (value & 0xFF) as u8);
+                    bus.write(result.address, (value & 0xFF) as u8);
                 } else {
                     bus.write16(result.address, value);
                 }
@@ -0,0 +1,26 @@
+// What the 65816 core needs from the machine around it: byte reads and
+// writes on a 24-bit address bus. The S-CPU runs on memory::Bus; a second
+// core (the SA-1's) or a test harness can bring its own.
+pub trait Bus65816 {
+    fn read(&mut self, address: u32) -> u8;
+    fn write(&mut self, address: u32, value: u8);
+
+    // Wider accesses are byte accesses at consecutive addresses, low byte
+    // first; the implementation wraps the address to 24 bits
+    fn read16(&mut self, address: u32) -> u16 {
+        let low = self.read(address) as u16;
+        let high = self.read(address.wrapping_add(1)) as u16;
+        low | (high << 8)
+    }
+
+    fn read24(&mut self, address: u32) -> u32 {
+        let low = self.read16(address) as u32;
+        let high = self.read(address.wrapping_add(2)) as u32;
+        low | (high << 16)
+    }
+
+    fn write16(&mut self, address: u32, value: u16) {
+        self.write(address, (value & 0xFF) as u8);
+        self.write(address.wrapping_add(1), (value >> 8) as u8);
+    }
+}
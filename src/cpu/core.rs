@@ -1,13 +1,54 @@
-use crate::memory::Bus;
+use crate::cpu::bus::Bus65816;
 use crate::Result;
 use crate::cpu::registers::CpuRegisters;
 use crate::cpu::decode_table::decode_opcode_fast;
 use crate::cpu::execute::execute_instruction;
+use crate::cpu::wdm::WdmHooks;
 use crate::savestate::CpuState;
 
+// The console's IRQ sources, which share the CPU's one IRQ line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IrqSource {
+    // H/V timer ($4200, HTIME/VTIME)
+    Timer,
+    Ppu,
+    // A chip on the cartridge
+    Cartridge,
+}
+
+impl IrqSource {
+    pub const ALL: [IrqSource; 3] = [IrqSource::Timer, IrqSource::Ppu, IrqSource::Cartridge];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|source| source.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IrqSource::Timer => "timer",
+            IrqSource::Ppu => "ppu",
+            IrqSource::Cartridge => "cartridge",
+        }
+    }
+
+    fn mask(self) -> u8 {
+        match self {
+            IrqSource::Timer => 0x01,
+            IrqSource::Ppu => 0x02,
+            IrqSource::Cartridge => 0x04,
+        }
+    }
+}
+
 pub struct Cpu {
     pub registers: CpuRegisters,
     pub cycles: u64,
+    // Survive resets, like breakpoints
+    wdm: WdmHooks,
+    // Interrupts raised by hand (assert_nmi, assert_irq) for tests and
+    // scripts: an NMI not yet taken, and IRQ sources held (IrqSource masks)
+    asserted_nmi: bool,
+    asserted_irqs: u8,
 }
 
 impl Cpu {
@@ -15,10 +56,13 @@ impl Cpu {
         Self {
             registers: CpuRegisters::new(),
             cycles: 0,
+            wdm: WdmHooks::new(),
+            asserted_nmi: false,
+            asserted_irqs: 0,
         }
     }
 
-    pub fn reset(&mut self, bus: &mut Bus) -> Result<()> {
+    pub fn reset(&mut self, bus: &mut impl Bus65816) -> Result<()> {
         // Read reset vector from $FFFC-$FFFD
         let reset_vector = bus.read16(0xFFFC);
         
@@ -32,13 +76,15 @@ impl Cpu {
         self.cycles = 0;
         self.registers.halt = false;
         self.registers.waiting_for_interrupt = false;
+        self.asserted_nmi = false;
+        self.asserted_irqs = 0;
         
         log::info!("CPU Reset - PC: ${:04X}, S: ${:04X}", reset_vector, self.registers.s);
         
         Ok(())
     }
 
-    pub fn step(&mut self, bus: &mut Bus) -> Result<u32> {
+    pub fn step(&mut self, bus: &mut impl Bus65816) -> Result<u32> {
         if self.registers.halt || self.registers.waiting_for_interrupt {
             // CPU is halted, just consume 1 cycle
             self.cycles += 1;
@@ -46,7 +92,8 @@ impl Cpu {
         }
         
         // Fetch opcode
-        let opcode = bus.read8(self.registers.pc);
+        let pc = self.registers.pc;
+        let opcode = bus.read(pc);
         self.registers.increment_pc(1);
         
         // Decode instruction using optimized lookup table
@@ -55,6 +102,10 @@ impl Cpu {
             let cycles = execute_instruction(&mut self.registers, bus, &info)?;
             self.cycles += cycles as u64;
             
+            if let Some(operand) = self.registers.wdm_operand.take() {
+                self.wdm.dispatch(operand, pc, &mut self.registers, bus);
+            }
+            
             Ok(cycles)
         } else {
             // Unknown opcode - treat as NOP
@@ -64,7 +115,7 @@ impl Cpu {
         }
     }
 
-    pub fn trigger_nmi(&mut self, bus: &mut Bus) -> Result<()> {
+    pub fn trigger_nmi(&mut self, bus: &mut impl Bus65816) -> Result<()> {
         if self.registers.waiting_for_interrupt {
             self.registers.waiting_for_interrupt = false;
         }
@@ -76,11 +127,13 @@ impl Cpu {
         }
         self.registers.push_16(bus, self.registers.get_pc_offset());
         
-        // Push processor status
-        self.registers.push_8(bus, self.registers.p);
+        // Push processor status, with B clear in emulation mode so a
+        // shared IRQ/BRK handler can tell them apart
+        self.registers.push_8(bus, self.registers.interrupt_status());
         
-        // Set interrupt disable flag
+        // Set interrupt disable flag and leave decimal mode
         self.registers.set_irq_disable(true);
+        self.registers.set_decimal(false);
         
         // Jump to NMI vector
         let nmi_vector = if self.registers.emulation_mode {
@@ -97,7 +150,7 @@ impl Cpu {
         Ok(())
     }
 
-    pub fn trigger_irq(&mut self, bus: &mut Bus) -> Result<()> {
+    pub fn trigger_irq(&mut self, bus: &mut impl Bus65816) -> Result<()> {
         // IRQ is ignored if interrupt disable flag is set
         if self.registers.irq_disable() {
             return Ok(());
@@ -114,11 +167,13 @@ impl Cpu {
         }
         self.registers.push_16(bus, self.registers.get_pc_offset());
         
-        // Push processor status
-        self.registers.push_8(bus, self.registers.p);
+        // Push processor status, with B clear in emulation mode so a
+        // shared IRQ/BRK handler can tell them apart
+        self.registers.push_8(bus, self.registers.interrupt_status());
         
-        // Set interrupt disable flag
+        // Set interrupt disable flag and leave decimal mode
         self.registers.set_irq_disable(true);
+        self.registers.set_decimal(false);
         
         // Jump to IRQ vector
         let irq_vector = if self.registers.emulation_mode {
@@ -135,6 +190,55 @@ impl Cpu {
         Ok(())
     }
     
+    // Raise an NMI as the start of vblank does. The emulator takes it after
+    // the current instruction, like any NMI; it isn't kept in save states.
+    pub fn assert_nmi(&mut self) {
+        self.asserted_nmi = true;
+    }
+
+    // Hold the IRQ line for `source` until release_irq, as the hardware
+    // does; the handler runs again after each RTI while it's held. Meant
+    // for tests and scripts, and not kept in save states.
+    pub fn assert_irq(&mut self, source: IrqSource) {
+        self.asserted_irqs |= source.mask();
+    }
+
+    pub fn release_irq(&mut self, source: IrqSource) {
+        self.asserted_irqs &= !source.mask();
+    }
+
+    pub fn is_irq_asserted(&self, source: IrqSource) -> bool {
+        (self.asserted_irqs & source.mask()) != 0
+    }
+
+    // The asserted NMI, which is taken once
+    pub fn take_asserted_nmi(&mut self) -> bool {
+        std::mem::take(&mut self.asserted_nmi)
+    }
+
+    // For harnesses running a Cpu on their own bus, between steps: take an
+    // asserted NMI, or an asserted IRQ unless interrupts are disabled.
+    // Returns whether the CPU entered a handler.
+    pub fn service_asserted(&mut self, bus: &mut impl Bus65816) -> Result<bool> {
+        if self.take_asserted_nmi() {
+            self.trigger_nmi(bus)?;
+            return Ok(true);
+        }
+        if self.asserted_irqs != 0 && !self.registers.irq_disable() {
+            self.trigger_irq(bus)?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    pub fn wdm(&self) -> &WdmHooks {
+        &self.wdm
+    }
+
+    pub fn wdm_mut(&mut self) -> &mut WdmHooks {
+        &mut self.wdm
+    }
+
     pub fn get_registers(&self) -> &CpuRegisters {
         &self.registers
     }
@@ -164,6 +268,8 @@ impl Cpu {
             waiting_for_interrupt: self.registers.waiting_for_interrupt,
             nmi_pending: false, // TODO: Track NMI state
             irq_pending: false, // TODO: Track IRQ state
+            // Emulator::save_state fills in the line it tracks
+            nmi_line: false,
         }
     }
     
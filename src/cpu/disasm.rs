@@ -0,0 +1,245 @@
+// 65C816 disassembler shared by the debugger and external tools. Decoding
+// uses the same opcode table as the CPU core, so lengths and base cycle
+// counts always match what the emulator executes.
+use crate::cpu::addressing::AddressingMode;
+use crate::cpu::decode_table::decode_opcode_fast;
+use crate::cpu::instructions::Instruction;
+use crate::cpu::registers::{FLAG_INDEX_WIDTH, FLAG_MEMORY_WIDTH};
+use crate::memory::Bus;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisasmInstruction {
+    pub address: u32,
+    pub instruction: Instruction,
+    pub mode: AddressingMode,
+    // Opcode followed by the operand bytes
+    pub bytes: Vec<u8>,
+    // Base cycle count from the decode table
+    pub cycles: u8,
+    // Resolved destination of branches and direct jumps/calls
+    pub target: Option<u32>,
+}
+
+impl DisasmInstruction {
+    pub fn opcode(&self) -> u8 {
+        self.bytes[0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn mnemonic(&self) -> String {
+        format!("{:?}", self.instruction)
+    }
+
+    // Little-endian operand value (0 for instructions without operands)
+    pub fn operand(&self) -> u32 {
+        self.bytes[1..].iter().rev().fold(0, |value, &b| (value << 8) | b as u32)
+    }
+
+    // Address of the following instruction (the PC wraps within its bank)
+    pub fn next_address(&self) -> u32 {
+        (self.address & 0xFF0000) | (self.address as u16).wrapping_add(self.len() as u16) as u32
+    }
+
+    // Operand in assembler syntax, e.g. "#$42", "($10),Y" or "[$0004]"
+    pub fn operand_text(&self) -> String {
+        use AddressingMode::*;
+        let value = self.operand();
+        let byte = |n: usize| self.bytes.get(n).copied().unwrap_or(0);
+        match self.mode {
+            Implied if self.len() == 2 => format!("#${:02X}", value),
+            Implied => String::new(),
+            Accumulator => "A".to_string(),
+            Immediate if self.len() == 3 => format!("#${:04X}", value),
+            Immediate => format!("#${:02X}", value),
+            DirectPage => format!("${:02X}", value),
+            DirectPageX => format!("${:02X},X", value),
+            DirectPageY => format!("${:02X},Y", value),
+            DirectPageIndirect => format!("(${:02X})", value),
+            DirectPageIndirectX => format!("(${:02X},X)", value),
+            DirectPageIndirectY => format!("(${:02X}),Y", value),
+            DirectPageIndirectLong => format!("[${:02X}]", value),
+            DirectPageIndirectLongY => format!("[${:02X}],Y", value),
+            Absolute => format!("${:04X}", value),
+            AbsoluteX => format!("${:04X},X", value),
+            AbsoluteY => format!("${:04X},Y", value),
+            // JML ($DC) reads a long pointer even though it shares the mode
+            AbsoluteIndirect if self.instruction == Instruction::JML => format!("[${:04X}]", value),
+            AbsoluteIndirect => format!("(${:04X})", value),
+            AbsoluteIndirectX => format!("(${:04X},X)", value),
+            AbsoluteLong => format!("${:06X}", value),
+            AbsoluteLongX => format!("${:06X},X", value),
+            AbsoluteIndirectLong => format!("[${:04X}]", value),
+            StackRelative => format!("${:02X},S", value),
+            StackRelativeIndirectY => format!("(${:02X},S),Y", value),
+            // Encoded as destination bank, source bank; written source first
+            BlockMove => format!("${:02X},${:02X}", byte(2), byte(1)),
+            Relative | RelativeLong => format!("${:06X}", self.target.unwrap_or(0)),
+        }
+    }
+}
+
+impl fmt::Display for DisasmInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        let operand = self.operand_text();
+        write!(f, "${:06X}: {:<11} {}", self.address, bytes.join(" "), self.mnemonic())?;
+        if !operand.is_empty() {
+            write!(f, " {}", operand)?;
+        }
+        Ok(())
+    }
+}
+
+fn operand_length(instruction: Instruction, mode: AddressingMode, memory_8bit: bool, index_8bit: bool) -> usize {
+    use AddressingMode::*;
+    match mode {
+        // BRK carries a signature byte
+        Implied if instruction == Instruction::BRK => 1,
+        Implied | Accumulator => 0,
+        Immediate if instruction.uses_memory_width() => if memory_8bit { 1 } else { 2 },
+        Immediate if instruction.uses_index_width() => if index_8bit { 1 } else { 2 },
+        Immediate => 1,
+        DirectPage | DirectPageX | DirectPageY | DirectPageIndirect | DirectPageIndirectX
+        | DirectPageIndirectY | DirectPageIndirectLong | DirectPageIndirectLongY
+        | StackRelative | StackRelativeIndirectY | Relative => 1,
+        Absolute | AbsoluteX | AbsoluteY | AbsoluteIndirect | AbsoluteIndirectX
+        | AbsoluteIndirectLong | BlockMove | RelativeLong => 2,
+        AbsoluteLong | AbsoluteLongX => 3,
+    }
+}
+
+// Decode one instruction at `address`. `memory_8bit` and `index_8bit` give
+// the M and X flags in effect, which decide the size of immediate operands.
+pub fn decode<F: Fn(u32) -> u8>(read: F, address: u32, memory_8bit: bool, index_8bit: bool) -> DisasmInstruction {
+    let bank = address & 0xFF0000;
+    let fetch = |offset: u16| read(bank | (address as u16).wrapping_add(offset) as u32);
+
+    let opcode = fetch(0);
+    let info = decode_opcode_fast(opcode).expect("decode table covers all opcodes");
+    let length = 1 + operand_length(info.instruction, info.addressing_mode, memory_8bit, index_8bit);
+    let bytes: Vec<u8> = (0..length as u16).map(fetch).collect();
+
+    let mut instruction = DisasmInstruction {
+        address,
+        instruction: info.instruction,
+        mode: info.addressing_mode,
+        bytes,
+        cycles: info.base_cycles,
+        target: None,
+    };
+    instruction.target = branch_target(&instruction);
+    instruction
+}
+
+fn branch_target(instruction: &DisasmInstruction) -> Option<u32> {
+    use Instruction::*;
+    let bank = instruction.address & 0xFF0000;
+    let next = instruction.next_address() as u16;
+    let operand = instruction.operand();
+    match (instruction.mode, instruction.instruction) {
+        (AddressingMode::Relative, _) => Some(bank | next.wrapping_add(operand as u8 as i8 as u16) as u32),
+        (AddressingMode::RelativeLong, _) => Some(bank | next.wrapping_add(operand as u16) as u32),
+        (AddressingMode::Absolute, JMP | JSR) => Some(bank | operand),
+        (AddressingMode::AbsoluteLong, JML | JSL) => Some(operand),
+        _ => None,
+    }
+}
+
+enum Source<'a> {
+    Slice { data: &'a [u8], base: u32 },
+    Bus(&'a Bus),
+}
+
+// Walks consecutive instructions from a start address. REP/SEP update the
+// tracked M/X widths as they are passed, so 16-bit immediates decode
+// correctly after the usual mode switch at the start of a routine.
+pub struct DisasmIter<'a> {
+    source: Source<'a>,
+    address: u32,
+    memory_8bit: bool,
+    index_8bit: bool,
+}
+
+impl<'a> DisasmIter<'a> {
+    // `data[0]` lives at `base`; iteration stops when an instruction would
+    // extend past the end of the slice
+    pub fn from_slice(data: &'a [u8], base: u32, start: u32) -> Self {
+        Self::new(Source::Slice { data, base }, start)
+    }
+
+    // Reads go through the bus like CPU fetches, so I/O registers in the
+    // walked range see the reads
+    pub fn from_bus(bus: &'a Bus, start: u32) -> Self {
+        Self::new(Source::Bus(bus), start)
+    }
+
+    fn new(source: Source<'a>, start: u32) -> Self {
+        // Power-on state: 8-bit accumulator and index registers
+        Self { source, address: start & 0xFFFFFF, memory_8bit: true, index_8bit: true }
+    }
+
+    // Start with the given M/X widths instead of 8-bit/8-bit
+    pub fn with_widths(mut self, memory_8bit: bool, index_8bit: bool) -> Self {
+        self.memory_8bit = memory_8bit;
+        self.index_8bit = index_8bit;
+        self
+    }
+
+    // Start with the widths from a processor status byte
+    pub fn with_status(self, p: u8, emulation_mode: bool) -> Self {
+        self.with_widths(emulation_mode || p & FLAG_MEMORY_WIDTH != 0, emulation_mode || p & FLAG_INDEX_WIDTH != 0)
+    }
+
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    fn decode_next(&self) -> Option<DisasmInstruction> {
+        match self.source {
+            Source::Slice { data, base } => {
+                let read = |address: u32| data.get(address.wrapping_sub(base) as usize).copied().unwrap_or(0);
+                let instruction = decode(read, self.address, self.memory_8bit, self.index_8bit);
+                // Every byte must come from the slice, including bytes
+                // fetched after the PC wraps within the bank
+                let bank = self.address & 0xFF0000;
+                let complete = (0..instruction.len() as u16).all(|i| {
+                    let address = bank | (self.address as u16).wrapping_add(i) as u32;
+                    (address.wrapping_sub(base) as usize) < data.len()
+                });
+                complete.then_some(instruction)
+            }
+            Source::Bus(bus) => Some(decode(|address| bus.peek8(address), self.address, self.memory_8bit, self.index_8bit)),
+        }
+    }
+}
+
+impl Iterator for DisasmIter<'_> {
+    type Item = DisasmInstruction;
+
+    fn next(&mut self) -> Option<DisasmInstruction> {
+        let instruction = self.decode_next()?;
+        match instruction.instruction {
+            Instruction::REP => {
+                let bits = instruction.operand() as u8;
+                self.memory_8bit &= bits & FLAG_MEMORY_WIDTH == 0;
+                self.index_8bit &= bits & FLAG_INDEX_WIDTH == 0;
+            }
+            Instruction::SEP => {
+                let bits = instruction.operand() as u8;
+                self.memory_8bit |= bits & FLAG_MEMORY_WIDTH != 0;
+                self.index_8bit |= bits & FLAG_INDEX_WIDTH != 0;
+            }
+            _ => {}
+        }
+        self.address = instruction.next_address();
+        Some(instruction)
+    }
+}
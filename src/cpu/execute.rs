@@ -1,15 +1,28 @@
 use crate::cpu::instructions::{Instruction, InstructionInfo};
 use crate::cpu::addressing::AddressingMode;
-use crate::cpu::registers::CpuRegisters;
-use crate::memory::Bus;
+use crate::cpu::registers::{CpuRegisters, FLAG_BREAK};
+use crate::cpu::bus::Bus65816;
 use crate::Result;
 
 pub fn execute_instruction(
     cpu: &mut CpuRegisters,
-    bus: &mut Bus,
+    bus: &mut impl Bus65816,
     info: &InstructionInfo,
 ) -> Result<u32> {
-    let addressing_result = info.addressing_mode.resolve(cpu, bus);
+    let addressing_result = match info.addressing_mode {
+        // Index immediates follow X, and REP/SEP/COP always take one byte
+        AddressingMode::Immediate => {
+            let eight_bit = if info.instruction.uses_memory_width() {
+                cpu.memory_width()
+            } else if info.instruction.uses_index_width() {
+                cpu.index_width()
+            } else {
+                true
+            };
+            AddressingMode::resolve_immediate(cpu, bus, eight_bit)
+        }
+        mode => mode.resolve(cpu, bus),
+    };
     let mut cycles = info.base_cycles as u32 + addressing_result.cycles;
     
     match info.instruction {
@@ -118,6 +131,7 @@ pub fn execute_instruction(
                 cpu.set_memory_width(true);
                 cpu.set_index_width(true);
             }
+            cpu.clamp_index_registers();
         }
         
         Instruction::PHX => {
@@ -423,6 +437,7 @@ pub fn execute_instruction(
                 cpu.set_memory_width(true);
                 cpu.set_index_width(true);
             }
+            cpu.clamp_index_registers();
             
             let return_addr = cpu.pop_16(bus);
             if !cpu.emulation_mode {
@@ -588,10 +603,14 @@ pub fn execute_instruction(
                 cpu.push_8(bus, cpu.get_pc_bank());
             }
             cpu.push_16(bus, cpu.get_pc_offset());
-            cpu.push_8(bus, cpu.p | 0x10); // Set B flag in pushed status
+            // Bit 4 is B in emulation mode, set to tell BRK from IRQ; in
+            // native mode it's the X flag and BRK has its own vector
+            let status = if cpu.emulation_mode { cpu.p | FLAG_BREAK } else { cpu.p };
+            cpu.push_8(bus, status);
             
-            // Disable interrupts
+            // Disable interrupts and leave decimal mode
             cpu.set_irq_disable(true);
+            cpu.set_decimal(false);
             
             // Jump to interrupt vector
             let vector = if cpu.emulation_mode {
@@ -603,8 +622,8 @@ pub fn execute_instruction(
         }
         
         Instruction::COP => {
-            // Coprocessor interrupt
-            cpu.increment_pc(1); // COP is a 2-byte instruction
+            // Coprocessor interrupt; the signature byte was read as the
+            // immediate operand
             
             // Push PC and P
             if !cpu.emulation_mode {
@@ -613,8 +632,9 @@ pub fn execute_instruction(
             cpu.push_16(bus, cpu.get_pc_offset());
             cpu.push_8(bus, cpu.p);
             
-            // Disable interrupts
+            // Disable interrupts and leave decimal mode
             cpu.set_irq_disable(true);
+            cpu.set_decimal(false);
             
             // Jump to interrupt vector
             let vector = if cpu.emulation_mode {
@@ -731,8 +751,8 @@ pub fn execute_instruction(
             // Move one byte
             let src_addr = ((src_bank as u32) << 16) | (cpu.get_x() as u32);
             let dst_addr = ((dst_bank as u32) << 16) | (cpu.get_y() as u32);
-            let byte = bus.read8(src_addr);
-            bus.write8(dst_addr, byte);
+            let byte = bus.read(src_addr);
+            bus.write(dst_addr, byte);
             
             // Decrement X and Y
             cpu.set_x(cpu.get_x().wrapping_sub(1));
@@ -757,8 +777,8 @@ pub fn execute_instruction(
             // Move one byte
             let src_addr = ((src_bank as u32) << 16) | (cpu.get_x() as u32);
             let dst_addr = ((dst_bank as u32) << 16) | (cpu.get_y() as u32);
-            let byte = bus.read8(src_addr);
-            bus.write8(dst_addr, byte);
+            let byte = bus.read(src_addr);
+            bus.write(dst_addr, byte);
             
             // Increment X and Y
             cpu.set_x(cpu.get_x().wrapping_add(1));
@@ -792,13 +812,13 @@ pub fn execute_instruction(
             // Set Processor Status Bits
             let mask = addressing_result.value as u8;
             cpu.p |= mask;
+            cpu.clamp_index_registers();
         }
         
         Instruction::WDM => {
-            // William D. Mensch Jr. - Reserved instruction
-            // This is used for debugger breakpoints in some systems
-            // For now, just skip the operand byte
-            cpu.increment_pc(1);
+            // William D. Mensch Jr. - Reserved instruction. The operand byte
+            // was read as an immediate; Cpu::step hands it to WDM handlers.
+            cpu.wdm_operand = Some(addressing_result.value as u8);
         }
         
     }
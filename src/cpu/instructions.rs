@@ -121,6 +121,26 @@ pub enum Instruction {
     PER,    // Push Effective Relative Address
 }
 
+impl Instruction {
+    // Immediate operands of these instructions follow the M (accumulator)
+    // or X (index) width flags; all other immediates are one byte
+    pub fn uses_memory_width(self) -> bool {
+        use Instruction::*;
+        matches!(self, ADC | AND | BIT | CMP | EOR | LDA | ORA | SBC)
+    }
+
+    pub fn uses_index_width(self) -> bool {
+        use Instruction::*;
+        matches!(self, CPX | CPY | LDX | LDY)
+    }
+
+    // Writes its operand without using the old value
+    pub fn is_store(self) -> bool {
+        use Instruction::*;
+        matches!(self, STA | STX | STY | STZ)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct InstructionInfo {
     pub instruction: Instruction,
@@ -1,9 +1,14 @@
+pub mod bus;
 pub mod core;
 pub mod instructions;
 pub mod addressing;
 pub mod registers;
 pub mod execute;
 pub mod decode_table;
+pub mod disasm;
+pub mod wdm;
 
-pub use core::Cpu;
-pub use registers::CpuRegisters;
\ No newline at end of file
+pub use bus::Bus65816;
+pub use core::{Cpu, IrqSource};
+pub use registers::CpuRegisters;
+pub use wdm::{TestOutcome, TestReport, WdmHandler, WdmHooks};
\ No newline at end of file
@@ -1,3 +1,4 @@
+use crate::cpu::bus::Bus65816;
 use std::fmt;
 
 #[derive(Debug, Clone)]
@@ -24,6 +25,8 @@ pub struct CpuRegisters {
     // CPU state flags
     pub halt: bool,
     pub waiting_for_interrupt: bool,
+    // Operand of a WDM just executed, for Cpu::step to dispatch
+    pub wdm_operand: Option<u8>,
 }
 
 // Processor status flags (P register)
@@ -32,6 +35,7 @@ pub const FLAG_ZERO: u8         = 0x02; // Z - Zero
 pub const FLAG_IRQ_DISABLE: u8  = 0x04; // I - IRQ Disable
 pub const FLAG_DECIMAL: u8      = 0x08; // D - Decimal mode
 pub const FLAG_INDEX_WIDTH: u8  = 0x10; // X - Index register width (0=16bit, 1=8bit)
+pub const FLAG_BREAK: u8        = 0x10; // B - Break, in emulation-mode pushed status only
 pub const FLAG_MEMORY_WIDTH: u8 = 0x20; // M - Memory/Accumulator width (0=16bit, 1=8bit)
 pub const FLAG_OVERFLOW: u8     = 0x40; // V - Overflow
 pub const FLAG_NEGATIVE: u8     = 0x80; // N - Negative
@@ -50,6 +54,7 @@ impl CpuRegisters {
             emulation_mode: true, // Start in 6502 emulation mode
             halt: false,
             waiting_for_interrupt: false,
+            wdm_operand: None,
         }
     }
 
@@ -174,8 +179,8 @@ impl CpuRegisters {
     }
 
     // Stack operations
-    pub fn push_8(&mut self, bus: &mut crate::memory::Bus, value: u8) {
-        bus.write8(self.s as u32, value);
+    pub fn push_8(&mut self, bus: &mut (impl Bus65816 + ?Sized), value: u8) {
+        bus.write(self.s as u32, value);
         self.s = self.s.wrapping_sub(1);
         if self.emulation_mode {
             // In emulation mode, stack wraps within page 1
@@ -183,21 +188,21 @@ impl CpuRegisters {
         }
     }
 
-    pub fn pop_8(&mut self, bus: &mut crate::memory::Bus) -> u8 {
+    pub fn pop_8(&mut self, bus: &mut (impl Bus65816 + ?Sized)) -> u8 {
         self.s = self.s.wrapping_add(1);
         if self.emulation_mode {
             // In emulation mode, stack wraps within page 1
             self.s = (self.s & 0xFF) | 0x0100;
         }
-        bus.read8(self.s as u32)
+        bus.read(self.s as u32)
     }
 
-    pub fn push_16(&mut self, bus: &mut crate::memory::Bus, value: u16) {
+    pub fn push_16(&mut self, bus: &mut (impl Bus65816 + ?Sized), value: u16) {
         self.push_8(bus, (value >> 8) as u8);   // High byte first
         self.push_8(bus, (value & 0xFF) as u8); // Low byte second
     }
 
-    pub fn pop_16(&mut self, bus: &mut crate::memory::Bus) -> u16 {
+    pub fn pop_16(&mut self, bus: &mut (impl Bus65816 + ?Sized)) -> u16 {
         let low = self.pop_8(bus) as u16;       // Low byte first
         let high = self.pop_8(bus) as u16;      // High byte second
         (high << 8) | low
@@ -236,6 +241,20 @@ impl CpuRegisters {
         // Set flags for 6502 compatibility
         self.set_memory_width(true);  // 8-bit accumulator
         self.set_index_width(true);   // 8-bit index registers
+        self.clamp_index_registers();
+    }
+
+    // 8-bit index registers lose their high bytes
+    pub fn clamp_index_registers(&mut self) {
+        if self.index_width() {
+            self.x &= 0xFF;
+            self.y &= 0xFF;
+        }
+    }
+
+    // Status byte pushed by a hardware interrupt
+    pub fn interrupt_status(&self) -> u8 {
+        if self.emulation_mode { self.p & !FLAG_BREAK } else { self.p }
     }
 
     // Get effective address width for current addressing mode
@@ -0,0 +1,127 @@
+// WDM ($42) is a two-byte no-op on real hardware, which makes its operand
+// a free channel from ROM code to the emulator. Test harnesses and
+// practice-ROM tools register a handler per operand byte; unclaimed
+// operands stay no-ops. A built-in set of operands lets test ROMs report
+// results without agreeing on a memory location first.
+use crate::cpu::registers::CpuRegisters;
+use crate::cpu::bus::Bus65816;
+use std::collections::HashMap;
+use std::fmt;
+
+// The test passed
+pub const WDM_PASS: u8 = 0xF0;
+// The test failed, with a code in A
+pub const WDM_FAIL: u8 = 0xF1;
+// Fail unless A equals X (compared at the current widths)
+pub const WDM_ASSERT_EQ: u8 = 0xF2;
+
+pub trait WdmHandler: Send {
+    // Runs after the WDM, with PC already past the operand
+    fn on_wdm(&mut self, operand: u8, cpu: &mut CpuRegisters, bus: &mut dyn Bus65816);
+}
+
+impl<F: FnMut(u8, &mut CpuRegisters, &mut dyn Bus65816) + Send> WdmHandler for F {
+    fn on_wdm(&mut self, operand: u8, cpu: &mut CpuRegisters, bus: &mut dyn Bus65816) {
+        self(operand, cpu, bus)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestOutcome {
+    Pass,
+    Fail { code: u16 },
+    NotEqual { a: u16, x: u16 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TestReport {
+    // Address of the WDM instruction
+    pub pc: u32,
+    pub outcome: TestOutcome,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> bool {
+        self.outcome == TestOutcome::Pass
+    }
+}
+
+impl fmt::Display for TestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "${:02X}:{:04X} ", self.pc >> 16, self.pc & 0xFFFF)?;
+        match self.outcome {
+            TestOutcome::Pass => write!(f, "pass"),
+            TestOutcome::Fail { code } => write!(f, "FAIL code ${:04X}", code),
+            TestOutcome::NotEqual { a, x } => write!(f, "FAIL A=${:04X} X=${:04X}", a, x),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct WdmHooks {
+    handlers: HashMap<u8, Box<dyn WdmHandler>>,
+    // Reports from the built-in test operands, None while they're off
+    reports: Option<Vec<TestReport>>,
+}
+
+impl WdmHooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Replaces any handler already registered for `operand`. Handlers take
+    // precedence over the built-in test operands.
+    pub fn set_handler(&mut self, operand: u8, handler: Box<dyn WdmHandler>) {
+        self.handlers.insert(operand, handler);
+    }
+
+    pub fn remove_handler(&mut self, operand: u8) -> Option<Box<dyn WdmHandler>> {
+        self.handlers.remove(&operand)
+    }
+
+    pub fn has_handler(&self, operand: u8) -> bool {
+        self.handlers.contains_key(&operand)
+    }
+
+    // Start or stop collecting WDM_PASS/WDM_FAIL/WDM_ASSERT_EQ reports;
+    // turning them on clears earlier reports
+    pub fn set_test_reports(&mut self, enabled: bool) {
+        self.reports = enabled.then(Vec::new);
+    }
+
+    pub fn test_reports(&self) -> &[TestReport] {
+        self.reports.as_deref().unwrap_or(&[])
+    }
+
+    pub fn take_test_reports(&mut self) -> Vec<TestReport> {
+        self.reports.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    // None until the ROM reports something, then false if anything failed
+    pub fn tests_passed(&self) -> Option<bool> {
+        let reports = self.test_reports();
+        (!reports.is_empty()).then(|| reports.iter().all(TestReport::passed))
+    }
+
+    pub fn dispatch(&mut self, operand: u8, pc: u32, cpu: &mut CpuRegisters, bus: &mut dyn Bus65816) {
+        if let Some(handler) = self.handlers.get_mut(&operand) {
+            handler.on_wdm(operand, cpu, bus);
+            return;
+        }
+        let Some(reports) = self.reports.as_mut() else {
+            return;
+        };
+        let outcome = match operand {
+            WDM_PASS => TestOutcome::Pass,
+            WDM_FAIL => TestOutcome::Fail { code: cpu.get_a() },
+            WDM_ASSERT_EQ if cpu.get_a() == cpu.get_x() => TestOutcome::Pass,
+            WDM_ASSERT_EQ => TestOutcome::NotEqual { a: cpu.get_a(), x: cpu.get_x() },
+            _ => return,
+        };
+        let report = TestReport { pc, outcome };
+        if !report.passed() {
+            log::warn!("Test ROM reported {}", report);
+        }
+        reports.push(report);
+    }
+}
@@ -0,0 +1,239 @@
+// Log of the CPU<->APU handshake through $2140-$2143 (the SPC700 sees them
+// as $F4-$F7). A game that hangs at boot is usually waiting on a port
+// value the other side never writes; this shows who wrote what, when.
+// Repeated identical reads from a polling loop collapse into one entry.
+use crate::Result;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::Path;
+
+// Entries kept; older ones are dropped first
+pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortSide {
+    Cpu,
+    Spc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortAccess {
+    pub side: PortSide,
+    pub write: bool,
+    pub port: u8,
+    pub value: u8,
+    // Master cycles since reset when the CPU access happened, or when the
+    // CPU last synced with the APU for SPC700 accesses
+    pub cpu_cycle: u64,
+    pub spc_cycle: u64,
+    // 24-bit CPU address or 16-bit SPC700 address of the instruction
+    pub pc: u32,
+    // Further identical reads right after this one
+    pub repeats: u32,
+}
+
+impl fmt::Display for PortAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (side, pc, register) = match self.side {
+            PortSide::Cpu => ("CPU", format!("${:02X}:{:04X}", self.pc >> 16, self.pc & 0xFFFF), format!("$214{}", self.port)),
+            PortSide::Spc => ("SPC", format!("${:04X}   ", self.pc), format!("$F{}  ", self.port + 4)),
+        };
+        let direction = if self.write { "write" } else { "read " };
+        write!(
+            f,
+            "cpu {:>10}  spc {:>10}  {} {}  {} {} = ${:02X}",
+            self.cpu_cycle, self.spc_cycle, side, pc, direction, register, self.value
+        )?;
+        if self.repeats > 0 {
+            write!(f, "  (x{})", self.repeats + 1)?;
+        }
+        Ok(())
+    }
+}
+
+// Which accesses are logged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortFilter {
+    // Bit n selects port n
+    pub ports: u8,
+    pub cpu: bool,
+    pub spc: bool,
+    pub reads: bool,
+    pub writes: bool,
+}
+
+impl Default for PortFilter {
+    fn default() -> Self {
+        Self { ports: 0x0F, cpu: true, spc: true, reads: true, writes: true }
+    }
+}
+
+impl PortFilter {
+    // Comma-separated terms: port numbers 0-3, `cpu` or `spc`, `read` or
+    // `write`. Omitted groups match everything, so "0,1,write" logs writes
+    // to ports 0 and 1 from either side.
+    pub fn parse(spec: &str) -> std::result::Result<Self, String> {
+        let (mut ports, mut cpu, mut spc, mut reads, mut writes) = (0u8, false, false, false, false);
+        for term in spec.split(',').map(str::trim).filter(|term| !term.is_empty()) {
+            match term.to_ascii_lowercase().as_str() {
+                "cpu" => cpu = true,
+                "spc" | "apu" => spc = true,
+                "read" | "reads" => reads = true,
+                "write" | "writes" => writes = true,
+                port => match port.parse::<u8>() {
+                    Ok(port) if port < 4 => ports |= 1 << port,
+                    _ => return Err(format!("unknown APU port filter term '{}'", term)),
+                },
+            }
+        }
+        Ok(Self {
+            ports: if ports == 0 { 0x0F } else { ports },
+            cpu: cpu || !spc,
+            spc: spc || !cpu,
+            reads: reads || !writes,
+            writes: writes || !reads,
+        })
+    }
+
+    pub fn matches(&self, side: PortSide, write: bool, port: u8) -> bool {
+        let side_ok = match side {
+            PortSide::Cpu => self.cpu,
+            PortSide::Spc => self.spc,
+        };
+        let direction_ok = if write { self.writes } else { self.reads };
+        side_ok && direction_ok && self.ports & (1 << (port & 3)) != 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PortTrace {
+    enabled: bool,
+    filter: PortFilter,
+    entries: VecDeque<PortAccess>,
+    max_entries: usize,
+    // Entries pushed out by max_entries
+    dropped: u64,
+    // Position of the CPU instruction running, set by the emulator
+    cpu_cycle: u64,
+    cpu_pc: u32,
+}
+
+impl Default for PortTrace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PortTrace {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            filter: PortFilter::default(),
+            entries: VecDeque::new(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            dropped: 0,
+            cpu_cycle: 0,
+            cpu_pc: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn filter(&self) -> PortFilter {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: PortFilter) {
+        self.filter = filter;
+    }
+
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries.max(1);
+        self.trim();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    pub fn set_cpu_position(&mut self, cycle: u64, pc: u32) {
+        self.cpu_cycle = cycle;
+        self.cpu_pc = pc;
+    }
+
+    pub fn record(&mut self, side: PortSide, write: bool, port: u8, value: u8, spc_cycle: u64, spc_pc: u16) {
+        if !self.enabled || !self.filter.matches(side, write, port) {
+            return;
+        }
+        let pc = match side {
+            PortSide::Cpu => self.cpu_pc,
+            PortSide::Spc => spc_pc as u32,
+        };
+        if let Some(last) = self.entries.back_mut() {
+            if !write && !last.write && last.side == side && last.port == port && last.value == value && last.pc == pc {
+                last.repeats += 1;
+                return;
+            }
+        }
+        self.entries.push_back(PortAccess {
+            side,
+            write,
+            port,
+            value,
+            cpu_cycle: self.cpu_cycle,
+            spc_cycle,
+            pc,
+            repeats: 0,
+        });
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    // Oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &PortAccess> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    // One line per entry, oldest first
+    pub fn dump(&self) -> String {
+        let mut output = String::new();
+        if self.dropped > 0 {
+            let _ = writeln!(output, "({} earlier accesses dropped)", self.dropped);
+        }
+        for entry in &self.entries {
+            let _ = writeln!(output, "{}", entry);
+        }
+        output
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.dump())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,751 @@
+// The interactive debugger: breakpoints on both processors, stepping,
+// watches with history, traces and the profiler, disassembly, memory and
+// graphics dumps, and the command line the frontends drive it through.
+// Built with the `debugger` feature.
+use crate::apu::spc700::Spc700;
+use crate::cpu::disasm::DisasmIter;
+use crate::cpu::Cpu;
+use crate::emulator::Emulator;
+use crate::memory::freeze::FreezeTarget;
+use crate::memory::Bus;
+use crate::ppu::Ppu;
+use crate::{EmulatorError, Result};
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+use super::breakpoints::BreakpointManager;
+use super::interrupts::{InterruptEvent, InterruptKind};
+use super::profiler::Profiler;
+use super::trace::Tracer;
+use super::watch_history::{self, WatchHistory};
+use super::watchdog::DesyncReport;
+use super::{graphics, heatmap, palette, sheets, spc700};
+
+// Processor targeted by stepping, register view, disassembly and dumps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugContext {
+    #[default]
+    Cpu,
+    Spc700,
+}
+
+// Debugger state
+pub struct Debugger {
+    // Breakpoint management
+    pub breakpoints: BreakpointManager,
+    
+    // SPC700 breakpoints (APU RAM addresses)
+    pub spc_breakpoints: BreakpointManager,
+    
+    // Active debugger context
+    context: DebugContext,
+    
+    // Execution trace
+    pub tracer: Tracer,
+    
+    // Performance profiler
+    pub profiler: Profiler,
+    
+    // Debugger state
+    pub enabled: bool,
+    pub single_step: bool,
+    pub break_on_next: bool,
+    
+    // Break when the desync watchdog fires (see check_desync)
+    pub break_on_desync: bool,
+    // Frame of the last desync report seen, so each stall breaks once
+    desync_seen: Option<u64>,
+    
+    // Interrupt kinds to break on (see check_interrupts), and the event
+    // behind the pending break
+    interrupt_breaks: HashSet<InterruptKind>,
+    interrupt_hit: Option<InterruptEvent>,
+    
+    // Watch variables
+    watches: Vec<Watch>,
+    // Frames of history each watch keeps
+    history_frames: usize,
+    
+    // Command history
+    command_history: VecDeque<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub name: String,
+    pub address: u32,
+    pub size: WatchSize,
+    pub format: WatchFormat,
+    // Value at the end of each recorded frame (see record_watches)
+    pub history: WatchHistory,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchSize {
+    Byte,
+    Word,
+    Long,
+}
+
+impl WatchSize {
+    pub fn bytes(self) -> u32 {
+        match self {
+            WatchSize::Byte => 1,
+            WatchSize::Word => 2,
+            WatchSize::Long => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum WatchFormat {
+    Hex,
+    Decimal,
+    Binary,
+    Ascii,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self {
+            breakpoints: BreakpointManager::new(),
+            spc_breakpoints: BreakpointManager::new(),
+            context: DebugContext::Cpu,
+            tracer: Tracer::new(),
+            profiler: Profiler::new(),
+            enabled: false,
+            single_step: false,
+            break_on_next: false,
+            break_on_desync: false,
+            desync_seen: None,
+            interrupt_breaks: HashSet::new(),
+            interrupt_hit: None,
+            watches: Vec::new(),
+            history_frames: watch_history::DEFAULT_HISTORY_FRAMES,
+            command_history: VecDeque::with_capacity(100),
+        }
+    }
+    
+    pub fn context(&self) -> DebugContext {
+        self.context
+    }
+    
+    // Switch the processor that stepping and inspection commands target
+    pub fn set_context(&mut self, context: DebugContext) {
+        self.context = context;
+    }
+    
+    // Check if we should break execution
+    pub fn should_break(&self, cpu: &Cpu) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        
+        if self.context == DebugContext::Cpu && (self.single_step || self.break_on_next) {
+            return true;
+        }
+        
+        self.breakpoints.check_breakpoint(cpu.registers.pc)
+    }
+    
+    // Check if the SPC700 should break before its next instruction
+    pub fn should_break_spc(&self, spc: &Spc700) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        
+        if self.context == DebugContext::Spc700 && (self.single_step || self.break_on_next) {
+            return true;
+        }
+        
+        self.spc_breakpoints.check_breakpoint(spc.pc() as u32)
+    }
+    
+    // Execute one instruction on the processor of the active context
+    pub fn step(&mut self, emulator: &mut Emulator) -> Result<()> {
+        match self.context {
+            DebugContext::Cpu => emulator.step(),
+            DebugContext::Spc700 => {
+                emulator.apu_mut().catch_up();
+                emulator.apu_mut().step();
+                Ok(())
+            }
+        }
+    }
+    
+    // Run a text command and return what to print. Supported:
+    //   dumpvram|dumpcgram|dumpoam <file>
+    //   restorevram|restorecgram|restoreoam <file>
+    //   exportpal <file.pal|file.png>
+    //   importpal <file> [lock], unlockpal
+    //   exporttiles <bg1-4|obj> <file.png> [palette]
+    //   exportsprites <file.png>
+    //   heatmap on [frames] [vram], heatmap off, heatmap clear
+    //   heatmap [wram|vram] [count]
+    //   breakon [nmi|irq|brk|cop|rti|all ...], breakon off [kinds ...]
+    //   desync
+    pub fn execute_command(&mut self, emulator: &mut Emulator, line: &str) -> Result<String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default().to_ascii_lowercase();
+        let argument = words.next();
+        let usage = |command: &str| EmulatorError::input(format!("Usage: {} <file>", command));
+
+        match command.as_str() {
+            "history" => {
+                let name = argument.ok_or_else(|| EmulatorError::input("Usage: history <watch> [width]"))?;
+                let width = match words.next() {
+                    Some(width) => width.parse().map_err(|_| EmulatorError::input(format!("Bad graph width '{}'", width)))?,
+                    None => 64,
+                };
+                return self.format_watch_history(name, width);
+            }
+            "exportpal" => {
+                let path = std::path::Path::new(argument.ok_or_else(|| usage(&command))?);
+                let bytes = palette::export(emulator.ppu(), path)?;
+                return Ok(format!("Wrote palette ({} bytes) to {}", bytes, path.display()));
+            }
+            "importpal" => {
+                let path = std::path::Path::new(argument.ok_or_else(|| usage(&command))?);
+                let lock = words.next().is_some_and(|word| word.eq_ignore_ascii_case("lock"));
+                let colors = palette::import(emulator.ppu_mut(), path, lock)?;
+                let locked = if lock { ", locked against game writes" } else { "" };
+                return Ok(format!("Imported {} colors from {}{}", colors, path.display(), locked));
+            }
+            "exporttiles" => {
+                let tiles_usage = || EmulatorError::input("Usage: exporttiles <bg1-4|obj> <file.png> [palette]");
+                let name = argument.ok_or_else(tiles_usage)?;
+                let layer = sheets::TileLayer::from_name(name)
+                    .ok_or_else(|| EmulatorError::input(format!("Unknown tile layer '{}' (expected bg1-bg4 or obj)", name)))?;
+                let path = std::path::Path::new(words.next().ok_or_else(tiles_usage)?);
+                let palette = match words.next() {
+                    Some(palette) => palette
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|&palette| palette < 8)
+                        .ok_or_else(|| EmulatorError::input(format!("Bad palette '{}' (expected 0-7)", palette)))?,
+                    None => 0,
+                };
+                let tiles = sheets::export_tiles(emulator.ppu(), layer, palette, path)?;
+                return Ok(format!("Wrote {} {} tiles with palette {} to {}", tiles, layer.name(), palette, path.display()));
+            }
+            "exportsprites" => {
+                let path = std::path::Path::new(argument.ok_or_else(|| usage(&command))?);
+                let placed = sheets::export_sprites(emulator.ppu(), path)?;
+                let mut out = format!("Wrote the frame with {} visible sprites to {}", placed.len(), path.display());
+                for sprite in placed {
+                    let _ = write!(
+                        out,
+                        "\n  ${:02X} at ({}, {}) {}x{} tile ${:03X} palette {} priority {}",
+                        sprite.index, sprite.x, sprite.y, sprite.width, sprite.height, sprite.tile, sprite.palette, sprite.priority
+                    );
+                }
+                return Ok(out);
+            }
+            "heatmap" => return self.heatmap_command(emulator, argument, words.collect()),
+            "breakon" => return self.breakon_command(emulator, argument.into_iter().chain(words).collect()),
+            "desync" => {
+                return Ok(match emulator.desync() {
+                    Some(report) => report.to_string(),
+                    None if emulator.desync_watchdog().is_none() => "Desync watchdog is off".to_string(),
+                    None => "No CPU/APU desync detected".to_string(),
+                });
+            }
+            "unlockpal" => {
+                emulator.ppu_mut().set_palette_locked(false);
+                return Ok("Palette unlocked".to_string());
+            }
+            _ => {}
+        }
+
+        let (restoring, memory) = match command.strip_prefix("dump") {
+            Some(name) => (false, graphics::GraphicsMemory::from_name(name)),
+            None => (true, command.strip_prefix("restore").and_then(graphics::GraphicsMemory::from_name)),
+        };
+        let Some(memory) = memory else {
+            return Err(EmulatorError::input(format!("Unknown debugger command '{}'", command)));
+        };
+        let Some(path) = argument.map(std::path::Path::new) else {
+            return Err(usage(&command));
+        };
+
+        if restoring {
+            let bytes = graphics::restore(emulator.ppu_mut(), memory, path)?;
+            Ok(format!("Restored {} bytes of {} from {}", bytes, memory.name(), path.display()))
+        } else {
+            let bytes = graphics::dump(emulator.ppu(), memory, path)?;
+            Ok(format!("Wrote {} bytes of {} to {}", bytes, memory.name(), path.display()))
+        }
+    }
+    
+    fn heatmap_command(&mut self, emulator: &mut Emulator, argument: Option<&str>, options: Vec<&str>) -> Result<String> {
+        let bad_count = |text: &str| EmulatorError::input(format!("Bad count '{}'", text));
+        match argument.map(str::to_ascii_lowercase).as_deref() {
+            Some("on") => {
+                let mut frames = heatmap::DEFAULT_WINDOW_FRAMES;
+                let mut vram = false;
+                for word in options {
+                    if word.eq_ignore_ascii_case("vram") {
+                        vram = true;
+                    } else {
+                        frames = word.parse().map_err(|_| bad_count(word))?;
+                    }
+                }
+                emulator.start_heatmap(frames, vram);
+                let window = if frames == 0 { "until cleared".to_string() } else { format!("over {} frame windows", frames) };
+                let spaces = if vram { "WRAM and VRAM" } else { "WRAM" };
+                Ok(format!("Counting {} accesses {}", spaces, window))
+            }
+            Some("off") => {
+                emulator.stop_heatmap();
+                Ok("Heatmap off".to_string())
+            }
+            Some("clear") => {
+                emulator.clear_heatmap();
+                Ok("Heatmap cleared".to_string())
+            }
+            space => {
+                let space = match space {
+                    None => heatmap::HeatmapSpace::Wram,
+                    Some(name) => heatmap::HeatmapSpace::from_name(name)
+                        .ok_or_else(|| EmulatorError::input("Usage: heatmap on [frames] [vram] | off | clear | [wram|vram] [count]"))?,
+                };
+                let count = match options.first() {
+                    Some(count) => count.parse().map_err(|_| bad_count(count))?,
+                    None => 16,
+                };
+                let counting = emulator.heatmap().ok_or_else(|| EmulatorError::input("Heatmap is off; start it with 'heatmap on'"))?;
+                let window = counting.latest();
+                let counts = window.counts(space)
+                    .ok_or_else(|| EmulatorError::input("VRAM isn't being counted; start with 'heatmap on [frames] vram'"))?;
+                let base = if space == heatmap::HeatmapSpace::Wram { 0x7E0000 } else { 0 };
+                let mut out = window.to_string();
+                for hot in counts.hottest(count) {
+                    let _ = write!(out, "\n  ${:06X}  {} reads, {} writes", base + hot.address, hot.reads, hot.writes);
+                }
+                Ok(out)
+            }
+        }
+    }
+    
+    fn breakon_command(&mut self, emulator: &mut Emulator, words: Vec<&str>) -> Result<String> {
+        let (enable, names) = match words.split_first() {
+            Some((first, rest)) if first.eq_ignore_ascii_case("off") => (false, rest),
+            _ => (true, &words[..]),
+        };
+        let mut kinds = Vec::new();
+        for &name in names {
+            if name.eq_ignore_ascii_case("all") {
+                kinds.extend(InterruptKind::ALL);
+            } else {
+                let kind = InterruptKind::from_name(name).ok_or_else(|| {
+                    EmulatorError::input(format!("Unknown interrupt '{}' (expected nmi, irq, brk, cop, rti or all)", name))
+                })?;
+                kinds.push(kind);
+            }
+        }
+        // A bare "off" turns them all off
+        if !enable && kinds.is_empty() {
+            kinds.extend(InterruptKind::ALL);
+        }
+        for kind in kinds {
+            self.set_interrupt_break(emulator, kind, enable);
+        }
+        
+        let breaks = self.interrupt_breaks();
+        if breaks.is_empty() {
+            return Ok("Not breaking on interrupts".to_string());
+        }
+        let names: Vec<&str> = breaks.iter().map(|kind| kind.name()).collect();
+        Ok(format!("Breaking on {}", names.join(", ")))
+    }
+    
+    // Register view for the active context
+    pub fn registers(&self, emulator: &Emulator) -> String {
+        match self.context {
+            DebugContext::Cpu => DebugFormatter::format_cpu_state(&emulator.cpu),
+            DebugContext::Spc700 => DebugFormatter::format_spc700_state(emulator.apu().spc700()),
+        }
+    }
+    
+    // Handle debugger break
+    pub fn handle_break(&mut self, cpu: &Cpu, bus: &Bus) -> DebuggerAction {
+        if !self.enabled {
+            return DebuggerAction::Continue;
+        }
+        
+        // Reset break flags
+        self.break_on_next = false;
+        
+        // Print current state
+        println!("\n=== DEBUGGER BREAK ===");
+        println!("PC: ${:06X}", cpu.registers.pc);
+        if let Some(event) = self.interrupt_hit.take() {
+            println!("Interrupt: {}", event);
+        }
+        println!("Registers: {}", cpu.registers);
+        
+        // Print watches
+        if !self.watches.is_empty() {
+            println!("\nWatches:");
+            for watch in &self.watches {
+                let value = self.read_watch(bus, watch);
+                println!("  {}: {} {}", watch.name, value, watch.history.sparkline(32));
+            }
+        }
+        
+        // Return action (in a real implementation, this would wait for user input)
+        DebuggerAction::Continue
+    }
+    
+    // Handle an SPC700 break
+    pub fn handle_spc_break(&mut self, spc: &Spc700) -> DebuggerAction {
+        if !self.enabled {
+            return DebuggerAction::Continue;
+        }
+        
+        self.break_on_next = false;
+        
+        println!("\n=== SPC700 BREAK ===");
+        println!("Registers: {}", spc.registers());
+        println!("{}", spc700::decode(spc.ram(), spc.pc()));
+        
+        DebuggerAction::Continue
+    }
+    
+    // Add a watch
+    pub fn add_watch(&mut self, name: String, address: u32, size: WatchSize, format: WatchFormat) {
+        self.watches.push(Watch {
+            name,
+            address,
+            size,
+            format,
+            history: WatchHistory::new(self.history_frames),
+        });
+    }
+    
+    // Remove a watch
+    pub fn remove_watch(&mut self, name: &str) {
+        self.watches.retain(|w| w.name != name);
+    }
+    
+    // Freeze the bytes covered by a watch at their current value
+    pub fn lock_watch(&self, emulator: &mut Emulator, name: &str) -> Result<()> {
+        let watch = self.find_watch(name)?;
+        for i in 0..watch.size.bytes() {
+            let address = watch.address + i;
+            let value = emulator.peek8(address);
+            emulator.freeze(address, value)?;
+        }
+        Ok(())
+    }
+    
+    pub fn unlock_watch(&self, emulator: &mut Emulator, name: &str) -> Result<()> {
+        let watch = self.find_watch(name)?;
+        for i in 0..watch.size.bytes() {
+            emulator.unfreeze(watch.address + i);
+        }
+        Ok(())
+    }
+    
+    pub fn history_frames(&self) -> usize {
+        self.history_frames
+    }
+    
+    // Frames of history kept per watch, for existing watches too
+    pub fn set_history_frames(&mut self, frames: usize) {
+        self.history_frames = frames.max(1);
+        for watch in &mut self.watches {
+            watch.history.set_capacity(self.history_frames);
+        }
+    }
+    
+    // Add every watch's current value to its history; call once per frame,
+    // after it ran. Reads have no side effects on I/O registers.
+    pub fn record_watches(&mut self, bus: &Bus, frame: u64) {
+        for watch in &mut self.watches {
+            let value = peek_watch(bus, watch);
+            watch.history.push(frame, value);
+        }
+    }
+    
+    // Break into the debugger when the desync watchdog reports a new
+    // stall; call once per frame, like record_watches. Returns the report
+    // when it breaks.
+    pub fn check_desync(&mut self, emulator: &Emulator) -> Option<DesyncReport> {
+        let report = *emulator.desync()?;
+        if !self.enabled || !self.break_on_desync || self.desync_seen == Some(report.frame) {
+            return None;
+        }
+        self.desync_seen = Some(report.frame);
+        self.break_on_next = true;
+        Some(report)
+    }
+    
+    // Break when the CPU enters (or with Rti, leaves) an interrupt handler
+    // of `kind`. The emulator tracks interrupts while any are set.
+    pub fn set_interrupt_break(&mut self, emulator: &mut Emulator, kind: InterruptKind, enabled: bool) {
+        if enabled {
+            self.interrupt_breaks.insert(kind);
+        } else {
+            self.interrupt_breaks.remove(&kind);
+        }
+        emulator.set_interrupt_tracking(!self.interrupt_breaks.is_empty());
+    }
+    
+    pub fn interrupt_breaks(&self) -> Vec<InterruptKind> {
+        InterruptKind::ALL.into_iter().filter(|kind| self.interrupt_breaks.contains(kind)).collect()
+    }
+    
+    // Break into the debugger after an interrupt of a kind set with
+    // set_interrupt_break; call after each step. Returns the event when it
+    // breaks, the first one if the step had several.
+    pub fn check_interrupts(&mut self, emulator: &mut Emulator) -> Option<InterruptEvent> {
+        let events = emulator.take_interrupts();
+        if !self.enabled {
+            return None;
+        }
+        let event = events.into_iter().find(|event| self.interrupt_breaks.contains(&event.kind))?;
+        self.interrupt_hit = Some(event);
+        self.break_on_next = true;
+        Some(event)
+    }
+    
+    pub fn watch_history(&self, name: &str) -> Option<&WatchHistory> {
+        self.watches.iter().find(|w| w.name == name).map(|w| &w.history)
+    }
+    
+    pub fn clear_watch_history(&mut self) {
+        for watch in &mut self.watches {
+            watch.history.clear();
+        }
+    }
+    
+    // Range and a text graph of the newest `width` frames of a watch
+    pub fn format_watch_history(&self, name: &str, width: usize) -> Result<String> {
+        let watch = self.find_watch(name)?;
+        let history = &watch.history;
+        let (Some((min, max)), Some(latest)) = (history.range(), history.latest()) else {
+            return Ok(format!("{}: no frames recorded", name));
+        };
+        let digits = watch.size.bytes() as usize * 2;
+        Ok(format!(
+            "{}: {} frames, ${:0digits$X}-${:0digits$X}, ${:0digits$X} at frame {}\n{}",
+            name,
+            history.len(),
+            min,
+            max,
+            latest.value,
+            latest.frame,
+            history.sparkline(width),
+            digits = digits
+        ))
+    }
+    
+    fn find_watch(&self, name: &str) -> Result<&Watch> {
+        self.watches.iter().find(|w| w.name == name)
+            .ok_or_else(|| EmulatorError::memory(format!("No watch named '{}'", name)))
+    }
+    
+    // List active memory freezes
+    pub fn format_freezes(&self, bus: &Bus) -> String {
+        let mut result = String::new();
+        for freeze in bus.freezes() {
+            let target = match freeze.target {
+                FreezeTarget::Wram(offset) => format!("WRAM ${:05X}", offset),
+                FreezeTarget::Sram(offset) => format!("SRAM ${:05X}", offset),
+            };
+            writeln!(&mut result, "${:06X} = ${:02X}  ({})", freeze.address, freeze.value, target).unwrap();
+        }
+        result
+    }
+    
+    // Read watch value
+    fn read_watch(&self, bus: &Bus, watch: &Watch) -> String {
+        let value = peek_watch(bus, watch);
+        
+        match watch.format {
+            WatchFormat::Hex => match watch.size {
+                WatchSize::Byte => format!("${:02X}", value),
+                WatchSize::Word => format!("${:04X}", value),
+                WatchSize::Long => format!("${:06X}", value),
+            },
+            WatchFormat::Decimal => format!("{}", value),
+            WatchFormat::Binary => format!("{:b}", value),
+            WatchFormat::Ascii => {
+                if watch.size == WatchSize::Byte && value < 128 {
+                    format!("'{}'", value as u8 as char)
+                } else {
+                    format!("${:02X}", value)
+                }
+            }
+        }
+    }
+    
+    // Disassemble `count` instructions, starting with 8-bit A and index
+    // registers (REP/SEP along the way are followed)
+    pub fn disassemble(&self, bus: &Bus, address: u32, count: usize) -> String {
+        let mut result = String::new();
+        for instruction in DisasmIter::from_bus(bus, address).take(count) {
+            writeln!(&mut result, "{}", instruction).unwrap();
+        }
+        result
+    }
+
+    // Disassemble from the CPU's PC using its current M/X widths
+    pub fn disassemble_at_pc(&self, cpu: &Cpu, bus: &Bus, count: usize) -> String {
+        let regs = cpu.get_registers();
+        let mut result = String::new();
+        for instruction in DisasmIter::from_bus(bus, regs.pc).with_status(regs.p, regs.emulation_mode).take(count) {
+            writeln!(&mut result, "{}", instruction).unwrap();
+        }
+        result
+    }
+    
+    // Memory dump
+    pub fn memory_dump(&self, bus: &Bus, address: u32, length: usize) -> String {
+        format_dump(|addr| bus.peek8(addr), address, length, 6)
+    }
+    
+    // Disassemble SPC700 code from APU RAM
+    pub fn disassemble_spc(&self, spc: &Spc700, address: u16, count: usize) -> String {
+        let mut result = String::new();
+        for instruction in spc700::disassemble(spc.ram(), address, count) {
+            writeln!(&mut result, "{}", instruction).unwrap();
+        }
+        result
+    }
+    
+    // Dump APU RAM (addresses wrap at $FFFF)
+    pub fn memory_dump_spc(&self, spc: &Spc700, address: u16, length: usize) -> String {
+        let ram = spc.ram();
+        format_dump(|addr| ram[(addr & 0xFFFF) as usize], address as u32, length, 4)
+    }
+    
+    // Search memory
+    pub fn search_memory(&self, bus: &Bus, pattern: &[u8], start: u32, end: u32) -> Vec<u32> {
+        let mut matches = Vec::new();
+        
+        if pattern.is_empty() {
+            return matches;
+        }
+        
+        for addr in start..=end.saturating_sub(pattern.len() as u32 - 1) {
+            let mut found = true;
+            for (i, &byte) in pattern.iter().enumerate() {
+                if bus.peek8(addr + i as u32) != byte {
+                    found = false;
+                    break;
+                }
+            }
+            
+            if found {
+                matches.push(addr);
+            }
+        }
+        
+        matches
+    }
+}
+
+// Numeric value of a watch, read with peeks
+fn peek_watch(bus: &Bus, watch: &Watch) -> u32 {
+    (0..watch.size.bytes()).fold(0, |value, i| value | (bus.peek8(watch.address + i) as u32) << (8 * i))
+}
+
+// Hex + ASCII dump, 16 bytes per line, addresses printed with `width` digits
+fn format_dump(read: impl Fn(u32) -> u8, address: u32, length: usize, width: usize) -> String {
+    let mut result = String::new();
+    
+    for offset in (0..length).step_by(16) {
+        let line_address = address + offset as u32;
+        let line_address = if width == 4 { line_address & 0xFFFF } else { line_address };
+        write!(&mut result, "${:0width$X}: ", line_address, width = width).unwrap();
+        
+        // Hex bytes
+        for i in 0..16 {
+            if offset + i < length {
+                let byte = read(address + (offset + i) as u32);
+                write!(&mut result, "{:02X} ", byte).unwrap();
+            } else {
+                write!(&mut result, "   ").unwrap();
+            }
+        }
+        
+        write!(&mut result, " ").unwrap();
+        
+        // ASCII representation
+        for i in 0..16 {
+            if offset + i < length {
+                let byte = read(address + (offset + i) as u32);
+                let ch = if (0x20..0x7F).contains(&byte) {
+                    byte as char
+                } else {
+                    '.'
+                };
+                write!(&mut result, "{}", ch).unwrap();
+            }
+        }
+        
+        writeln!(&mut result).unwrap();
+    }
+    
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DebuggerAction {
+    Continue,
+    Step,
+    StepOver,
+    StepOut,
+    Break,
+    Reset,
+    Quit,
+}
+
+// Helper for formatting debug output
+pub struct DebugFormatter;
+
+impl DebugFormatter {
+    pub fn format_cpu_state(cpu: &Cpu) -> String {
+        format!(
+            "A:{:04X} X:{:04X} Y:{:04X} S:{:04X} D:{:04X} DB:{:02X} PC:{:06X} P:{:02X} [{}]",
+            cpu.registers.a,
+            cpu.registers.x,
+            cpu.registers.y,
+            cpu.registers.s,
+            cpu.registers.d,
+            cpu.registers.db,
+            cpu.registers.pc,
+            cpu.registers.p,
+            Self::format_flags(cpu.registers.p)
+        )
+    }
+    
+    fn format_flags(p: u8) -> String {
+        format!(
+            "{}{}{}{}{}{}{}{}",
+            if p & 0x80 != 0 { 'N' } else { 'n' },
+            if p & 0x40 != 0 { 'V' } else { 'v' },
+            if p & 0x20 != 0 { 'M' } else { 'm' },
+            if p & 0x10 != 0 { 'X' } else { 'x' },
+            if p & 0x08 != 0 { 'D' } else { 'd' },
+            if p & 0x04 != 0 { 'I' } else { 'i' },
+            if p & 0x02 != 0 { 'Z' } else { 'z' },
+            if p & 0x01 != 0 { 'C' } else { 'c' }
+        )
+    }
+    
+    pub fn format_spc700_state(spc: &Spc700) -> String {
+        format!("{} CYC:{}", spc.registers(), spc.cycles())
+    }
+    
+    pub fn format_ppu_state(ppu: &Ppu) -> String {
+        format!(
+            "Scanline: {} Dot: {} Frame: {} VBlank: {}",
+            ppu.get_current_scanline(),
+            ppu.get_current_dot(),
+            ppu.get_frame_count(),
+            ppu.is_in_vblank()
+        )
+    }
+}
\ No newline at end of file
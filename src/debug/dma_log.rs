@@ -0,0 +1,140 @@
+// Per-frame log of DMA and HDMA transfers
+use crate::dma::DmaMode;
+use std::fmt;
+use std::fmt::Write;
+
+// Scanlines covered by the timeline (NTSC); later lines widen it
+const TIMELINE_LINES: u16 = 262;
+// Scanlines folded into one timeline column
+const LINES_PER_COLUMN: u16 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferKind {
+    Dma,
+    Hdma,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DmaTransfer {
+    pub kind: TransferKind,
+    pub channel: u8,
+    pub mode: DmaMode,
+    pub b_to_a: bool,
+    // 24-bit A-bus address the transfer started at
+    pub source: u32,
+    // Low byte of the B-bus register ($21xx)
+    pub b_address: u8,
+    pub size: u32,
+    pub scanline: u16,
+}
+
+impl fmt::Display for DmaTransfer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            TransferKind::Dma => "DMA ",
+            TransferKind::Hdma => "HDMA",
+        };
+        let a_bus = format!("${:02X}:{:04X}", self.source >> 16, self.source & 0xFFFF);
+        let b_bus = format!("$21{:02X}", self.b_address);
+        let (from, to) = if self.b_to_a { (b_bus, a_bus) } else { (a_bus, b_bus) };
+        write!(
+            f,
+            "line {:3}  {} ch{} mode {}  {} -> {}  {} bytes",
+            self.scanline, kind, self.channel, self.mode as u8, from, to, self.size
+        )
+    }
+}
+
+// Transfers are collected for the frame in progress; end_frame() makes them
+// available through transfers() and starts a new frame
+#[derive(Debug, Default)]
+pub struct DmaLog {
+    enabled: bool,
+    current: Vec<DmaTransfer>,
+    last_frame: Vec<DmaTransfer>,
+}
+
+impl DmaLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.current.clear();
+        self.last_frame.clear();
+    }
+
+    pub fn record(&mut self, transfer: DmaTransfer) {
+        if self.enabled {
+            self.current.push(transfer);
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        if self.enabled {
+            self.last_frame = std::mem::take(&mut self.current);
+        }
+    }
+
+    // Transfers of the last completed frame
+    pub fn transfers(&self) -> &[DmaTransfer] {
+        &self.last_frame
+    }
+
+    // Transfers of the frame in progress
+    pub fn current(&self) -> &[DmaTransfer] {
+        &self.current
+    }
+
+    // One line per transfer of the last completed frame
+    pub fn dump(&self) -> String {
+        let mut output = String::new();
+        for transfer in &self.last_frame {
+            let _ = writeln!(output, "{}", transfer);
+        }
+        let total: u32 = self.last_frame.iter().map(|t| t.size).sum();
+        let _ = writeln!(output, "{} transfers, {} bytes", self.last_frame.len(), total);
+        output
+    }
+
+    // Channel-by-scanline chart of the last completed frame: 'D' marks a
+    // general DMA, 'H' an HDMA transfer and '*' both in the same column
+    pub fn timeline(&self) -> String {
+        let lines = self
+            .last_frame
+            .iter()
+            .map(|t| t.scanline + 1)
+            .max()
+            .unwrap_or(0)
+            .max(TIMELINE_LINES);
+        let columns = lines.div_ceil(LINES_PER_COLUMN) as usize;
+
+        let mut rows = vec![vec![b'.'; columns]; 8];
+        for transfer in &self.last_frame {
+            let cell = &mut rows[transfer.channel as usize & 7][(transfer.scanline / LINES_PER_COLUMN) as usize];
+            let mark = match transfer.kind {
+                TransferKind::Dma => b'D',
+                TransferKind::Hdma => b'H',
+            };
+            *cell = if *cell == b'.' || *cell == mark { mark } else { b'*' };
+        }
+
+        let mut output = String::new();
+        let _ = writeln!(output, "     line 0, {} lines per column", LINES_PER_COLUMN);
+        for (channel, row) in rows.iter().enumerate() {
+            let _ = writeln!(output, "ch{}  {}", channel, String::from_utf8_lossy(row));
+        }
+        output
+    }
+}
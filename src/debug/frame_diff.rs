@@ -0,0 +1,200 @@
+// Frame-by-frame video comparison for hunting rendering regressions
+use crate::emulator::Emulator;
+use crate::error::EmulatorError;
+use crate::Result;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fmt;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+pub const FRAME_WIDTH: usize = 256;
+pub const FRAME_HEIGHT: usize = 224;
+pub const FRAME_BYTES: usize = FRAME_WIDTH * FRAME_HEIGHT * 4;
+
+const DUMP_MAGIC: &[u8; 4] = b"CCFD";
+
+// Colour used for differing pixels in highlight images
+const HIGHLIGHT: [u8; 4] = [0xFF, 0x00, 0xFF, 0xFF];
+
+// Where two frames first diverged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameDiff {
+    pub frame: u64,
+    pub pixels: usize,
+    // First differing pixel in scan order
+    pub first: (usize, usize),
+    // Inclusive bounding box of all differing pixels (x0, y0, x1, y1)
+    pub bounds: (usize, usize, usize, usize),
+}
+
+impl fmt::Display for FrameDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frame {}: {} pixels differ, first at ({}, {}), bounds ({}, {})-({}, {})",
+            self.frame, self.pixels, self.first.0, self.first.1,
+            self.bounds.0, self.bounds.1, self.bounds.2, self.bounds.3
+        )
+    }
+}
+
+// Compare two RGBA frames, ignoring alpha. Lines only one of them has
+// (one frame drawn with overscan) all differ.
+pub fn compare_frames(frame: u64, a: &[u8], b: &[u8]) -> Option<FrameDiff> {
+    let mut diff: Option<FrameDiff> = None;
+    for i in 0..a.len().max(b.len()) / 4 {
+        let (pa, pb) = (a.get(i * 4..i * 4 + 3), b.get(i * 4..i * 4 + 3));
+        if pa == pb {
+            continue;
+        }
+        let (x, y) = (i % FRAME_WIDTH, i / FRAME_WIDTH);
+        match diff.as_mut() {
+            Some(d) => {
+                d.pixels += 1;
+                d.bounds.0 = d.bounds.0.min(x);
+                d.bounds.2 = d.bounds.2.max(x);
+                d.bounds.3 = y;
+            }
+            None => {
+                diff = Some(FrameDiff { frame, pixels: 1, first: (x, y), bounds: (x, y, x, y) });
+            }
+        }
+    }
+    diff
+}
+
+// Dimmed greyscale copy of `a` with every pixel that differs from `b` marked
+pub fn highlight(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(a.len());
+    for (pa, pb) in a.chunks_exact(4).zip(b.chunks_exact(4)) {
+        if pa[..3] == pb[..3] {
+            let luma = ((pa[0] as u32 * 77 + pa[1] as u32 * 150 + pa[2] as u32 * 29) >> 9) as u8;
+            output.extend_from_slice(&[luma, luma, luma, 0xFF]);
+        } else {
+            output.extend_from_slice(&HIGHLIGHT);
+        }
+    }
+    output
+}
+
+// Run two emulators in lockstep and stop at the first frame that differs
+pub fn run_side_by_side(a: &mut Emulator, b: &mut Emulator, frames: u64) -> Result<Option<FrameDiff>> {
+    for frame in 0..frames {
+        a.step_frame()?;
+        b.step_frame()?;
+        if let Some(diff) = compare_frames(frame, a.get_video_buffer(), b.get_video_buffer()) {
+            return Ok(Some(diff));
+        }
+    }
+    Ok(None)
+}
+
+// Run an emulator against a recorded dump and stop at the first divergence
+pub fn run_against_dump(emulator: &mut Emulator, dump: &FrameDump) -> Result<Option<FrameDiff>> {
+    for (frame, expected) in dump.frames().iter().enumerate() {
+        emulator.step_frame()?;
+        if let Some(diff) = compare_frames(frame as u64, expected, emulator.get_video_buffer()) {
+            return Ok(Some(diff));
+        }
+    }
+    Ok(None)
+}
+
+// Sequence of recorded frames, stored gzip-compressed on disk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameDump {
+    frames: Vec<Vec<u8>>,
+}
+
+impl FrameDump {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Record `frames` frames from an emulator
+    pub fn record(emulator: &mut Emulator, frames: u64) -> Result<Self> {
+        let mut dump = Self::new();
+        for _ in 0..frames {
+            emulator.step_frame()?;
+            dump.push(emulator.get_video_buffer())?;
+        }
+        Ok(dump)
+    }
+
+    pub fn push(&mut self, frame: &[u8]) -> Result<()> {
+        if frame.len() != FRAME_BYTES {
+            return Err(EmulatorError::video(format!(
+                "Frame is {} bytes, expected {}", frame.len(), FRAME_BYTES
+            )));
+        }
+        self.frames.push(frame.to_vec());
+        Ok(())
+    }
+
+    pub fn frames(&self) -> &[Vec<u8>] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut encoder = GzEncoder::new(File::create(path)?, Compression::fast());
+        encoder.write_all(DUMP_MAGIC)?;
+        encoder.write_all(&(FRAME_WIDTH as u32).to_le_bytes())?;
+        encoder.write_all(&(FRAME_HEIGHT as u32).to_le_bytes())?;
+        encoder.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+        for frame in &self.frames {
+            encoder.write_all(frame)?;
+        }
+        encoder.finish()?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut decoder = GzDecoder::new(File::open(path)?);
+        let mut header = [0u8; 16];
+        decoder.read_exact(&mut header)?;
+        if &header[0..4] != DUMP_MAGIC {
+            return Err(EmulatorError::video("Not a frame dump file"));
+        }
+
+        let field = |i: usize| u32::from_le_bytes([header[i], header[i + 1], header[i + 2], header[i + 3]]) as usize;
+        if field(4) != FRAME_WIDTH || field(8) != FRAME_HEIGHT {
+            return Err(EmulatorError::video(format!(
+                "Unsupported frame dump size {}x{}", field(4), field(8)
+            )));
+        }
+
+        let mut frames = Vec::with_capacity(field(12));
+        for _ in 0..field(12) {
+            let mut frame = vec![0; FRAME_BYTES];
+            decoder.read_exact(&mut frame)?;
+            frames.push(frame);
+        }
+        Ok(Self { frames })
+    }
+}
+
+// Encode an RGBA frame (224 or 239 lines) as a binary PPM image
+pub fn to_ppm(frame: &[u8]) -> Vec<u8> {
+    to_ppm_with_width(frame, FRAME_WIDTH)
+}
+
+// to_ppm for frames of another width, such as widescreen ones
+pub fn to_ppm_with_width(frame: &[u8], width: usize) -> Vec<u8> {
+    let height = frame.len() / (width * 4);
+    let mut data = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for pixel in frame.chunks_exact(4) {
+        data.extend_from_slice(&pixel[..3]);
+    }
+    data
+}
@@ -0,0 +1,87 @@
+// Raw dumps of the PPU's memories for tile and palette editors (YY-CHR
+// opens a VRAM dump as 4bpp SNES tiles) and for attaching graphics state
+// to bug reports. Files hold the bytes exactly as the PPU stores them:
+// VRAM little-endian words, CGRAM BGR555 colors, OAM low then high table.
+use crate::ppu::Ppu;
+use crate::{EmulatorError, Result};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsMemory {
+    Vram,
+    Cgram,
+    Oam,
+}
+
+impl GraphicsMemory {
+    pub fn size(self) -> usize {
+        match self {
+            GraphicsMemory::Vram => 0x10000,
+            GraphicsMemory::Cgram => 0x200,
+            GraphicsMemory::Oam => 544,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GraphicsMemory::Vram => "VRAM",
+            GraphicsMemory::Cgram => "CGRAM",
+            GraphicsMemory::Oam => "OAM",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "vram" => Some(GraphicsMemory::Vram),
+            "cgram" => Some(GraphicsMemory::Cgram),
+            "oam" => Some(GraphicsMemory::Oam),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn contents(self, ppu: &Ppu) -> Vec<u8> {
+        match self {
+            GraphicsMemory::Vram => ppu.get_vram().to_vec(),
+            GraphicsMemory::Cgram => ppu.get_cgram().to_vec(),
+            // Low and high table
+            GraphicsMemory::Oam => ppu.get_complete_oam_data(),
+        }
+    }
+
+    pub(crate) fn restore_contents(self, ppu: &mut Ppu, data: &[u8]) {
+        match self {
+            GraphicsMemory::Vram => ppu.set_vram(data),
+            GraphicsMemory::Cgram => ppu.set_cgram(data),
+            GraphicsMemory::Oam => ppu.set_oam(data),
+        }
+    }
+}
+
+// Write `memory` to `path`, returning the bytes written
+pub fn dump(ppu: &Ppu, memory: GraphicsMemory, path: &Path) -> Result<usize> {
+    let data = memory.contents(ppu);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, &data)?;
+    Ok(data.len())
+}
+
+// Load a dump back. OAM dumps without the high table (512 bytes) are
+// accepted and leave the high table as it is.
+pub fn restore(ppu: &mut Ppu, memory: GraphicsMemory, path: &Path) -> Result<usize> {
+    let data = fs::read(path)?;
+    let size_ok = data.len() == memory.size() || (memory == GraphicsMemory::Oam && data.len() == 512);
+    if !size_ok {
+        return Err(EmulatorError::ppu(format!(
+            "{} dumps are {} bytes, {:?} has {}",
+            memory.name(),
+            memory.size(),
+            path,
+            data.len()
+        )));
+    }
+    memory.restore_contents(ppu, &data);
+    Ok(data.len())
+}
@@ -0,0 +1,227 @@
+// Memory heatmap: how often each WRAM byte, and optionally each VRAM byte,
+// is read and written over a sampling window, for finding which addresses
+// a game actually uses. WRAM counts the CPU's and DMA's accesses; VRAM
+// counts accesses through the $2118/$2119 and $2139/$213A ports, not the
+// PPU's own fetches while drawing. Debugger peeks don't count.
+use std::fmt;
+
+pub const WRAM_SIZE: usize = 0x20000;
+pub const VRAM_SIZE: usize = 0x10000;
+
+// Frames per sampling window unless set otherwise, ten seconds at 60 fps
+pub const DEFAULT_WINDOW_FRAMES: u32 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapSpace {
+    // By offset from $7E0000
+    Wram,
+    // By byte offset, low byte of each word first
+    Vram,
+}
+
+impl HeatmapSpace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "wram" => Some(HeatmapSpace::Wram),
+            "vram" => Some(HeatmapSpace::Vram),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HeatmapSpace::Wram => "WRAM",
+            HeatmapSpace::Vram => "VRAM",
+        }
+    }
+
+    pub fn size(self) -> usize {
+        match self {
+            HeatmapSpace::Wram => WRAM_SIZE,
+            HeatmapSpace::Vram => VRAM_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotAddress {
+    pub address: u32,
+    pub reads: u32,
+    pub writes: u32,
+}
+
+impl HotAddress {
+    pub fn accesses(&self) -> u64 {
+        self.reads as u64 + self.writes as u64
+    }
+}
+
+// Read and write counts per byte; they stop at u32::MAX
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessCounts {
+    reads: Vec<u32>,
+    writes: Vec<u32>,
+}
+
+impl AccessCounts {
+    pub fn new(size: usize) -> Self {
+        Self { reads: vec![0; size], writes: vec![0; size] }
+    }
+
+    pub fn len(&self) -> usize {
+        self.reads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reads.is_empty()
+    }
+
+    pub fn read(&mut self, offset: usize) {
+        if let Some(count) = self.reads.get_mut(offset) {
+            *count = count.saturating_add(1);
+        }
+    }
+
+    pub fn write(&mut self, offset: usize) {
+        if let Some(count) = self.writes.get_mut(offset) {
+            *count = count.saturating_add(1);
+        }
+    }
+
+    pub fn reads(&self) -> &[u32] {
+        &self.reads
+    }
+
+    pub fn writes(&self) -> &[u32] {
+        &self.writes
+    }
+
+    pub fn at(&self, offset: usize) -> HotAddress {
+        HotAddress { address: offset as u32, reads: self.reads[offset], writes: self.writes[offset] }
+    }
+
+    // Bytes accessed at all
+    pub fn touched(&self) -> usize {
+        self.reads.iter().zip(&self.writes).filter(|(&reads, &writes)| reads > 0 || writes > 0).count()
+    }
+
+    // Read and write totals of each `bucket`-byte block, for drawing the
+    // map at a lower resolution
+    pub fn buckets(&self, bucket: usize) -> Vec<(u64, u64)> {
+        let sum = |counts: &[u32]| counts.iter().map(|&count| count as u64).sum::<u64>();
+        self.reads
+            .chunks(bucket.max(1))
+            .zip(self.writes.chunks(bucket.max(1)))
+            .map(|(reads, writes)| (sum(reads), sum(writes)))
+            .collect()
+    }
+
+    // The `count` most accessed addresses, busiest first
+    pub fn hottest(&self, count: usize) -> Vec<HotAddress> {
+        let mut hot: Vec<HotAddress> = (0..self.len()).map(|offset| self.at(offset)).filter(|hot| hot.accesses() > 0).collect();
+        hot.sort_by_key(|hot| (std::cmp::Reverse(hot.accesses()), hot.address));
+        hot.truncate(count);
+        hot
+    }
+}
+
+// The counts of one sampling window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeatmapWindow {
+    // Frame count when the window opened, and frames counted since
+    pub first_frame: u64,
+    pub frames: u32,
+    pub wram: AccessCounts,
+    pub vram: Option<AccessCounts>,
+}
+
+impl HeatmapWindow {
+    fn new(first_frame: u64, vram: bool) -> Self {
+        Self { first_frame, frames: 0, wram: AccessCounts::new(WRAM_SIZE), vram: vram.then(|| AccessCounts::new(VRAM_SIZE)) }
+    }
+
+    pub fn counts(&self, space: HeatmapSpace) -> Option<&AccessCounts> {
+        match space {
+            HeatmapSpace::Wram => Some(&self.wram),
+            HeatmapSpace::Vram => self.vram.as_ref(),
+        }
+    }
+}
+
+impl fmt::Display for HeatmapWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} frames from frame {}: {} WRAM bytes used", self.frames, self.first_frame, self.wram.touched())?;
+        if let Some(vram) = &self.vram {
+            write!(f, ", {} VRAM bytes", vram.touched())?;
+        }
+        Ok(())
+    }
+}
+
+// Counts accesses into a window that closes every `window_frames` frames;
+// the last closed one stays readable while the next fills. A window of 0
+// frames never closes, so counts add up until cleared.
+pub struct Heatmap {
+    window_frames: u32,
+    current: HeatmapWindow,
+    completed: Option<HeatmapWindow>,
+}
+
+impl Heatmap {
+    pub fn new(window_frames: u32, vram: bool, frame: u64) -> Self {
+        Self { window_frames, current: HeatmapWindow::new(frame, vram), completed: None }
+    }
+
+    pub fn window_frames(&self) -> u32 {
+        self.window_frames
+    }
+
+    pub fn counts_vram(&self) -> bool {
+        self.current.vram.is_some()
+    }
+
+    pub fn wram_read(&mut self, offset: usize) {
+        self.current.wram.read(offset);
+    }
+
+    pub fn wram_write(&mut self, offset: usize) {
+        self.current.wram.write(offset);
+    }
+
+    pub fn vram_read(&mut self, offset: u16) {
+        if let Some(vram) = self.current.vram.as_mut() {
+            vram.read(offset as usize);
+        }
+    }
+
+    pub fn vram_write(&mut self, offset: u16) {
+        if let Some(vram) = self.current.vram.as_mut() {
+            vram.write(offset as usize);
+        }
+    }
+
+    // After each frame, with the frame count it left
+    pub fn end_frame(&mut self, frame: u64) {
+        self.current.frames += 1;
+        if self.window_frames > 0 && self.current.frames >= self.window_frames {
+            let next = HeatmapWindow::new(frame, self.counts_vram());
+            self.completed = Some(std::mem::replace(&mut self.current, next));
+        }
+    }
+
+    // The window being filled
+    pub fn current(&self) -> &HeatmapWindow {
+        &self.current
+    }
+
+    // The last closed window, or the current one before any has closed
+    pub fn latest(&self) -> &HeatmapWindow {
+        self.completed.as_ref().unwrap_or(&self.current)
+    }
+
+    // Start counting over from `frame`
+    pub fn clear(&mut self, frame: u64) {
+        self.current = HeatmapWindow::new(frame, self.counts_vram());
+        self.completed = None;
+    }
+}
@@ -0,0 +1,149 @@
+// Interrupt tracking: each NMI and IRQ the CPU takes, each BRK and COP it
+// executes and each RTI, with what raised it and where it went, so the
+// debugger can stop at a game's NMI handler (and find its vblank logic)
+// without the handler's address being known in advance.
+use std::fmt;
+
+// Events kept between two take_events calls; older ones are dropped
+const MAX_EVENTS: usize = 256;
+// Handlers entered and not left that RTIs are matched against
+const MAX_NESTING: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InterruptKind {
+    Nmi,
+    Irq,
+    Brk,
+    Cop,
+    Rti,
+}
+
+impl InterruptKind {
+    pub const ALL: [InterruptKind; 5] =
+        [InterruptKind::Nmi, InterruptKind::Irq, InterruptKind::Brk, InterruptKind::Cop, InterruptKind::Rti];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            InterruptKind::Nmi => "NMI",
+            InterruptKind::Irq => "IRQ",
+            InterruptKind::Brk => "BRK",
+            InterruptKind::Cop => "COP",
+            InterruptKind::Rti => "RTI",
+        }
+    }
+}
+
+impl fmt::Display for InterruptKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterruptSource {
+    // The NMI at the start of vblank
+    Vblank,
+    // What was holding the IRQ line; more than one can at once
+    Irq { timer: bool, ppu: bool, cartridge: bool },
+    // The byte after a BRK or COP opcode
+    Signature(u8),
+    // RTI out of a handler of this kind, None when its entry wasn't seen
+    Return(Option<InterruptKind>),
+}
+
+impl fmt::Display for InterruptSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            InterruptSource::Vblank => f.write_str("vblank"),
+            InterruptSource::Irq { timer, ppu, cartridge } => {
+                let sources: Vec<&str> = [(timer, "H/V timer"), (ppu, "PPU"), (cartridge, "cartridge")]
+                    .into_iter()
+                    .filter_map(|(set, name)| set.then_some(name))
+                    .collect();
+                f.write_str(&sources.join(", "))
+            }
+            InterruptSource::Signature(signature) => write!(f, "#${:02X}", signature),
+            InterruptSource::Return(Some(kind)) => write!(f, "from {}", kind),
+            InterruptSource::Return(None) => f.write_str("from an unseen handler"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterruptEvent {
+    pub kind: InterruptKind,
+    pub source: InterruptSource,
+    // The instruction interrupted, or the BRK, COP or RTI itself
+    pub pc: u32,
+    // The handler entered, or where RTI returned to
+    pub target: u32,
+    pub frame: u64,
+    pub scanline: u16,
+}
+
+impl fmt::Display for InterruptEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let to = if self.kind == InterruptKind::Rti { "returning to" } else { "handler" };
+        write!(
+            f,
+            "{} ({}) at ${:06X}, {} ${:06X} (frame {}, line {})",
+            self.kind, self.source, self.pc, to, self.target, self.frame, self.scanline
+        )
+    }
+}
+
+// Collects events as the emulator runs, and pairs each RTI with the
+// handler it leaves
+#[derive(Debug, Clone, Default)]
+pub struct InterruptTracker {
+    events: Vec<InterruptEvent>,
+    handlers: Vec<InterruptKind>,
+}
+
+impl InterruptTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(&mut self, event: InterruptEvent) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.remove(0);
+        }
+        self.events.push(event);
+    }
+
+    // An NMI, IRQ, BRK or COP sent the CPU from `pc` to the handler at `target`
+    pub fn entered(&mut self, kind: InterruptKind, source: InterruptSource, pc: u32, target: u32, frame: u64, scanline: u16) {
+        if self.handlers.len() == MAX_NESTING {
+            self.handlers.remove(0);
+        }
+        self.handlers.push(kind);
+        self.push(InterruptEvent { kind, source, pc, target, frame, scanline });
+    }
+
+    // The RTI at `pc` returned to `target`
+    pub fn returned(&mut self, pc: u32, target: u32, frame: u64, scanline: u16) {
+        let source = InterruptSource::Return(self.handlers.pop());
+        self.push(InterruptEvent { kind: InterruptKind::Rti, source, pc, target, frame, scanline });
+    }
+
+    // Handlers entered and not yet returned from, innermost last
+    pub fn handlers(&self) -> &[InterruptKind] {
+        &self.handlers
+    }
+
+    // The events since the last call, oldest first
+    pub fn take_events(&mut self) -> Vec<InterruptEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    // Forget everything, after a reset or a loaded state
+    pub fn reset(&mut self) {
+        self.events.clear();
+        self.handlers.clear();
+    }
+}
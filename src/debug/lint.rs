@@ -0,0 +1,175 @@
+// Lint mode: warnings about game behaviour that works on this emulator by
+// luck but is likely a bug, for homebrew developers. Nothing here changes
+// what the game sees; findings are logged and collected for the frontend.
+use crate::ppu::ports;
+use log::warn;
+use std::collections::HashSet;
+use std::fmt;
+
+// Findings kept; later ones are only counted
+pub const MAX_LINTS: usize = 256;
+
+const WRAM_SIZE: usize = 0x20000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintKind {
+    // A WRAM byte read before anything wrote it since power-on; its value
+    // depends on the console
+    UninitializedRead,
+    // A write to cartridge ROM, which ignores it
+    RomWrite,
+    // VRAM, OAM or CGRAM port accessed while the PPU draws, which hardware
+    // ignores or corrupts (CGRAM is free during H-Blank)
+    VideoMemoryWhileDrawing,
+    // Native-mode stack pointer below $0100, into the direct page
+    StackOverflow,
+    // IRQ raised while the I flag was set, so the game never took it
+    UnservicedIrq,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lint {
+    pub kind: LintKind,
+    // Memory address, PPU port or stack pointer, depending on the kind
+    pub address: u32,
+    // Instruction at fault
+    pub pc: u32,
+    pub frame: u64,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame {} PC ${:06X}: ", self.frame, self.pc)?;
+        match self.kind {
+            LintKind::UninitializedRead => write!(f, "read of ${:06X} before anything wrote it", self.address),
+            LintKind::RomWrite => write!(f, "write to ROM at ${:06X}", self.address),
+            LintKind::VideoMemoryWhileDrawing => write!(
+                f,
+                "{} (${:04X}) accessed while the PPU is drawing",
+                ports::port(self.address as u16).name,
+                self.address
+            ),
+            LintKind::StackOverflow => write!(f, "stack pointer ${:04X} is below $0100", self.address),
+            LintKind::UnservicedIrq => write!(f, "IRQ raised with the I flag set and dropped"),
+        }
+    }
+}
+
+// Collects findings, one per kind and instruction so a loop doesn't flood
+// the log
+pub struct Linter {
+    lints: Vec<Lint>,
+    seen: HashSet<(LintKind, u32)>,
+    suppressed: u64,
+    // One bit per WRAM byte, set once it has been written
+    wram_written: Vec<u64>,
+    // Uninitialized reads by a store, which the CPU core makes of the
+    // destination before writing it; only those of other bytes count
+    store: bool,
+    store_reads: Vec<(usize, u32)>,
+    // Stack pointer was below $0100 after the last instruction
+    stack_low: bool,
+    pc: u32,
+    frame: u64,
+}
+
+impl Linter {
+    // WRAM starts out counted as written, as its history is unknown; the
+    // next power-on (init_wram) starts tracking it
+    pub fn new() -> Self {
+        Self {
+            lints: Vec::new(),
+            seen: HashSet::new(),
+            suppressed: 0,
+            wram_written: vec![u64::MAX; WRAM_SIZE / 64],
+            store: false,
+            store_reads: Vec::new(),
+            stack_low: false,
+            pc: 0,
+            frame: 0,
+        }
+    }
+
+    // Instruction about to run, whether it is a store (STA, STX, STY,
+    // STZ) and the frame it is in
+    pub fn begin_instruction(&mut self, pc: u32, store: bool, frame: u64) {
+        self.pc = pc;
+        self.store = store;
+        self.frame = frame;
+    }
+
+    // After the instruction, with the stack pointer it left
+    pub fn end_instruction(&mut self, stack_pointer: u16) {
+        for (_, address) in std::mem::take(&mut self.store_reads) {
+            self.report(LintKind::UninitializedRead, address);
+        }
+        self.store = false;
+        let stack_low = stack_pointer < 0x0100;
+        if stack_low && !self.stack_low {
+            self.report(LintKind::StackOverflow, stack_pointer as u32);
+        }
+        self.stack_low = stack_low;
+    }
+
+    pub fn lints(&self) -> &[Lint] {
+        &self.lints
+    }
+
+    // Findings beyond MAX_LINTS
+    pub fn suppressed(&self) -> u64 {
+        self.suppressed
+    }
+
+    pub fn clear(&mut self) {
+        self.lints.clear();
+        self.seen.clear();
+        self.suppressed = 0;
+    }
+
+    // Power-on: nothing in WRAM has been written yet
+    pub fn forget_wram(&mut self) {
+        self.wram_written.fill(0);
+    }
+
+    // All of WRAM came from somewhere known (a save state)
+    pub fn mark_wram_written(&mut self) {
+        self.wram_written.fill(u64::MAX);
+    }
+
+    pub fn wram_write(&mut self, offset: usize) {
+        self.wram_written[offset / 64] |= 1 << (offset % 64);
+        if self.store {
+            self.store_reads.retain(|&(read, _)| read != offset);
+        }
+    }
+
+    pub fn wram_read(&mut self, offset: usize, address: u32) {
+        if self.wram_written[offset / 64] & (1 << (offset % 64)) != 0 {
+            return;
+        }
+        if self.store {
+            self.store_reads.push((offset, address));
+        } else {
+            self.report(LintKind::UninitializedRead, address);
+        }
+    }
+
+    pub fn report(&mut self, kind: LintKind, address: u32) {
+        if !self.seen.insert((kind, self.pc)) {
+            return;
+        }
+        if self.lints.len() >= MAX_LINTS {
+            self.suppressed += 1;
+            return;
+        }
+        let lint = Lint { kind, address, pc: self.pc, frame: self.frame };
+        warn!("Lint: {}", lint);
+        self.lints.push(lint);
+    }
+}
+
+impl Default for Linter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
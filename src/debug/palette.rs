@@ -0,0 +1,99 @@
+// Palette export and import for prototyping color changes live. Exports
+// are a 768-byte RGB .pal (the layout YY-CHR and most tile editors use) or
+// a PNG swatch of the 256 colors; imports accept that .pal, JASC-PAL text
+// or a raw 512-byte CGRAM dump.
+use crate::debug::png;
+use crate::ppu::Ppu;
+use crate::{EmulatorError, Result};
+use std::path::Path;
+
+pub const COLORS: usize = 256;
+// Swatch: 16x16 colors, each SWATCH_CELL pixels square
+pub const SWATCH_CELL: usize = 8;
+pub const SWATCH_SIZE: usize = 16 * SWATCH_CELL;
+
+// BGR555 to RGB888, repeating the top bits so white stays $FF
+pub fn to_rgb(color: u16) -> [u8; 3] {
+    let expand = |c: u16| ((c << 3) | (c >> 2)) as u8;
+    [expand(color & 0x1F), expand((color >> 5) & 0x1F), expand((color >> 10) & 0x1F)]
+}
+
+pub fn from_rgb(rgb: [u8; 3]) -> u16 {
+    let [r, g, b] = rgb.map(|c| (c >> 3) as u16);
+    r | (g << 5) | (b << 10)
+}
+
+// BGR555 colors from CGRAM bytes
+pub fn colors(cgram: &[u8]) -> Vec<u16> {
+    cgram.chunks_exact(2).take(COLORS).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect()
+}
+
+pub fn to_pal(cgram: &[u8]) -> Vec<u8> {
+    colors(cgram).into_iter().flat_map(to_rgb).collect()
+}
+
+pub fn to_swatch_png(cgram: &[u8]) -> Vec<u8> {
+    let colors = colors(cgram);
+    let mut rgba = vec![0; SWATCH_SIZE * SWATCH_SIZE * 4];
+    for (i, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (i % SWATCH_SIZE, i / SWATCH_SIZE);
+        let index = (y / SWATCH_CELL) * 16 + x / SWATCH_CELL;
+        let [r, g, b] = to_rgb(colors.get(index).copied().unwrap_or(0));
+        pixel.copy_from_slice(&[r, g, b, 0xFF]);
+    }
+    png::encode_rgba(SWATCH_SIZE as u32, SWATCH_SIZE as u32, &rgba)
+}
+
+// CGRAM bytes from a .pal, JASC-PAL or raw CGRAM file. Palettes with
+// fewer than 256 colors replace the first entries only.
+pub fn parse(data: &[u8]) -> Result<Vec<u8>> {
+    let colors: Vec<u16> = if data.starts_with(b"JASC-PAL") {
+        parse_jasc(&String::from_utf8_lossy(data))?
+    } else if data.len() == COLORS * 2 {
+        return Ok(data.to_vec());
+    } else if data.len().is_multiple_of(3) && !data.is_empty() && data.len() <= COLORS * 3 {
+        data.chunks_exact(3).map(|rgb| from_rgb([rgb[0], rgb[1], rgb[2]])).collect()
+    } else {
+        return Err(EmulatorError::ppu(format!(
+            "Unrecognized palette: {} bytes (expected up to 768 bytes of RGB, JASC-PAL text or a 512-byte CGRAM dump)",
+            data.len()
+        )));
+    };
+    Ok(colors.into_iter().flat_map(u16::to_le_bytes).collect())
+}
+
+fn parse_jasc(text: &str) -> Result<Vec<u16>> {
+    let invalid = |line: &str| EmulatorError::ppu(format!("Invalid JASC-PAL line '{}'", line));
+    // Header, version and color count come first
+    text.lines()
+        .skip(3)
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .take(COLORS)
+        .map(|line| {
+            let parts: Vec<u8> = line.split_whitespace().map(|part| part.parse().map_err(|_| invalid(line))).collect::<Result<_>>()?;
+            match parts[..] {
+                [r, g, b] => Ok(from_rgb([r, g, b])),
+                _ => Err(invalid(line)),
+            }
+        })
+        .collect()
+}
+
+// Write the PPU's palette; a .png extension gives the swatch, anything
+// else the RGB .pal
+pub fn export(ppu: &Ppu, path: &Path) -> Result<usize> {
+    let is_png = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+    let data = if is_png { to_swatch_png(ppu.get_cgram()) } else { to_pal(ppu.get_cgram()) };
+    std::fs::write(path, &data)?;
+    Ok(data.len())
+}
+
+// Load a palette into CGRAM, returning the number of colors replaced.
+// With `lock`, the game's own CGRAM writes are ignored until unlocked.
+pub fn import(ppu: &mut Ppu, path: &Path, lock: bool) -> Result<usize> {
+    let cgram = parse(&std::fs::read(path)?)?;
+    ppu.set_cgram(&cgram);
+    ppu.set_palette_locked(lock);
+    Ok(cgram.len() / 2)
+}
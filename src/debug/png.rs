@@ -0,0 +1,42 @@
+// Minimal PNG writer for debug images: 8-bit RGBA, no filtering, one
+// zlib-compressed IDAT chunk.
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+// Encode `rgba` (width * height * 4 bytes, rows top to bottom)
+pub fn encode_rgba(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    assert_eq!(rgba.len(), width as usize * height as usize * 4, "RGBA buffer doesn't match the image size");
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth 8, color type 6 (RGBA), deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    // Every row starts with filter type 0 (none)
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in rgba.chunks_exact(width as usize * 4) {
+        let _ = encoder.write_all(&[0]);
+        let _ = encoder.write_all(row);
+    }
+    let data = encoder.finish().unwrap_or_default();
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &data);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let mut crc = flate2::Crc::new();
+    crc.update(kind);
+    crc.update(data);
+    png.extend_from_slice(&crc.sum().to_be_bytes());
+}
@@ -0,0 +1,323 @@
+// Annotated PNG sheets of the PPU's graphics, for documenting rendering
+// bugs and for ROM hacking: a layer's tile set laid out with a palette
+// applied and each row labelled with its first tile number, and the frame
+// with every sprite's box and OAM index drawn over it. Tiles are decoded
+// the way the hardware addresses them, so a sheet that disagrees with the
+// picture points at the renderer.
+use crate::debug::{palette, png};
+use crate::ppu::sprites;
+use crate::ppu::core::SCREEN_WIDTH;
+use crate::ppu::Ppu;
+use crate::{EmulatorError, Result};
+use std::path::Path;
+
+// Tiles per row of a tile sheet
+pub const SHEET_COLUMNS: usize = 16;
+// A tile and the grid line after it
+const CELL: usize = 9;
+// Width of the row labels: three hex digits and a gap
+const LABEL_WIDTH: usize = 3 * GLYPH_ADVANCE + 2;
+const GLYPH_ADVANCE: usize = 4;
+
+const GRID: [u8; 4] = [0x30, 0x30, 0x30, 0xFF];
+const BACKGROUND: [u8; 4] = [0x10, 0x10, 0x10, 0xFF];
+const LABEL: [u8; 4] = [0xC0, 0xC0, 0xC0, 0xFF];
+// Sprite boxes by priority, 0 to 3
+const PRIORITY_COLORS: [[u8; 4]; 4] = [
+    [0x40, 0x80, 0xFF, 0xFF],
+    [0x40, 0xE0, 0x40, 0xFF],
+    [0xFF, 0xE0, 0x20, 0xFF],
+    [0xFF, 0x40, 0x40, 0xFF],
+];
+
+// 3x5 hex digits, top row in the high bits
+const GLYPHS: [u16; 16] = [
+    0b111_101_101_101_111,
+    0b010_110_010_010_111,
+    0b111_001_111_100_111,
+    0b111_001_111_001_111,
+    0b101_101_111_001_001,
+    0b111_100_111_001_111,
+    0b111_100_111_101_111,
+    0b111_001_001_001_001,
+    0b111_101_111_101_111,
+    0b111_101_111_001_111,
+    0b010_101_111_101_101,
+    0b110_101_110_101_110,
+    0b011_100_100_100_011,
+    0b110_101_101_101_110,
+    0b111_100_111_100_111,
+    0b111_100_111_100_100,
+];
+
+// Tile set a sheet shows: a background (1-4) or the sprites
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileLayer {
+    Bg(u8),
+    Obj,
+}
+
+impl TileLayer {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bg1" => Some(TileLayer::Bg(1)),
+            "bg2" => Some(TileLayer::Bg(2)),
+            "bg3" => Some(TileLayer::Bg(3)),
+            "bg4" => Some(TileLayer::Bg(4)),
+            "obj" | "sprites" => Some(TileLayer::Obj),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            TileLayer::Bg(bg) => format!("BG{}", bg),
+            TileLayer::Obj => "OBJ".to_string(),
+        }
+    }
+}
+
+// Bits per pixel of background `bg` in BG mode `mode`; None where the mode
+// has no such background. Mode 7's BG1 is 8bpp in its own tile format.
+pub fn bg_bpp(mode: u8, bg: u8) -> Option<u8> {
+    match (mode & 0x07, bg) {
+        (0, 1..=4) => Some(2),
+        (1, 1 | 2) => Some(4),
+        (1, 3) => Some(2),
+        (2, 1 | 2) => Some(4),
+        (3, 1) => Some(8),
+        (3, 2) => Some(4),
+        (4, 1) => Some(8),
+        (4, 2) => Some(2),
+        (5, 1) => Some(4),
+        (5, 2) => Some(2),
+        (6, 1) => Some(4),
+        (7, 1) => Some(8),
+        _ => None,
+    }
+}
+
+// An RGBA image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sheet {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+impl Sheet {
+    fn new(width: usize, height: usize, fill: [u8; 4]) -> Self {
+        Self { width, height, rgba: fill.repeat(width * height) }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * self.width + x) * 4;
+        self.rgba[offset..offset + 4].try_into().unwrap()
+    }
+
+    // Out-of-bounds pixels are dropped, so shapes may hang off the edges
+    fn set(&mut self, x: i32, y: i32, color: [u8; 4]) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let offset = (y as usize * self.width + x as usize) * 4;
+        self.rgba[offset..offset + 4].copy_from_slice(&color);
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: [u8; 4]) {
+        for row in y..y + height {
+            for col in x..x + width {
+                self.set(col, row, color);
+            }
+        }
+    }
+
+    fn outline(&mut self, x: i32, y: i32, width: i32, height: i32, color: [u8; 4]) {
+        for col in x..x + width {
+            self.set(col, y, color);
+            self.set(col, y + height - 1, color);
+        }
+        for row in y..y + height {
+            self.set(x, row, color);
+            self.set(x + width - 1, row, color);
+        }
+    }
+
+    // `digits` hex digits of `value` with the top left at (x, y)
+    fn label(&mut self, x: i32, y: i32, value: usize, digits: usize, color: [u8; 4]) {
+        for digit in 0..digits {
+            let nibble = (value >> ((digits - 1 - digit) * 4)) & 0xF;
+            let glyph = GLYPHS[nibble];
+            let left = x + (digit * GLYPH_ADVANCE) as i32;
+            for bit in 0..15 {
+                if glyph & (1 << (14 - bit)) != 0 {
+                    self.set(left + bit % 3, y + bit / 3, color);
+                }
+            }
+        }
+    }
+
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode_rgba(self.width as u32, self.height as u32, &self.rgba)
+    }
+}
+
+// A layer's tile set as it is configured now, with palette `palette` (0-7)
+// applied; 8bpp tiles use the whole CGRAM and ignore it. Color 0 is left
+// transparent.
+pub fn tile_sheet(ppu: &Ppu, layer: TileLayer, palette: u8) -> Result<Sheet> {
+    let registers = &ppu.registers;
+    let mode = registers.get_bg_mode();
+    let palette = palette as usize & 0x07;
+    let (tiles, bpp, color_base): (Vec<u16>, u8, usize) = match layer {
+        TileLayer::Obj => {
+            // Tiles 256-511 sit after a gap set by the OBSEL name select
+            let base = ((registers.obsel & 0x07) as u16) << 13;
+            let second = base.wrapping_add((((registers.obsel >> 3) & 0x03) as u16 + 1) << 12);
+            let tiles = (0..512u16)
+                .map(|tile| if tile < 256 { base + (tile << 4) } else { second.wrapping_add((tile - 256) << 4) })
+                .collect();
+            (tiles, 4, 128 + palette * 16)
+        }
+        TileLayer::Bg(1) if mode == 7 => (Vec::new(), 8, 0),
+        TileLayer::Bg(bg) => {
+            let bpp = bg_bpp(mode, bg).ok_or_else(|| {
+                EmulatorError::ppu(format!("BG mode {} has no {}", mode, layer.name()))
+            })?;
+            let nibbles = if bg <= 2 { registers.bg12nba } else { registers.bg34nba };
+            let nibble = if bg % 2 == 1 { nibbles & 0x0F } else { nibbles >> 4 };
+            let base = (nibble as u16) << 12;
+            let words = bpp as u16 * 4;
+            let tiles = (0..1024u16).map(|tile| base.wrapping_add(tile.wrapping_mul(words))).collect();
+            // Mode 0 gives each background its own 32 colors
+            let color_base = match bpp {
+                2 if mode == 0 => (bg as usize - 1) * 32 + palette * 4,
+                2 => palette * 4,
+                4 => palette * 16,
+                _ => 0,
+            };
+            (tiles, bpp, color_base)
+        }
+    };
+
+    let vram = ppu.get_vram();
+    let colors = palette::colors(ppu.get_cgram());
+    let count = if tiles.is_empty() { 256 } else { tiles.len() };
+    let rows = count.div_ceil(SHEET_COLUMNS);
+    let mut sheet = Sheet::new(LABEL_WIDTH + SHEET_COLUMNS * CELL + 1, rows * CELL + 1, BACKGROUND);
+    for row in 0..rows {
+        sheet.fill_rect(LABEL_WIDTH as i32, (row * CELL) as i32, (SHEET_COLUMNS * CELL + 1) as i32, 1, GRID);
+        sheet.label(0, (row * CELL + 2) as i32, row * SHEET_COLUMNS, 3, LABEL);
+    }
+    sheet.fill_rect(LABEL_WIDTH as i32, (rows * CELL) as i32, (SHEET_COLUMNS * CELL + 1) as i32, 1, GRID);
+    for col in 0..=SHEET_COLUMNS {
+        sheet.fill_rect((LABEL_WIDTH + col * CELL) as i32, 0, 1, (rows * CELL + 1) as i32, GRID);
+    }
+
+    // Low byte of VRAM word `word` when `high` is false
+    let byte = |word: u16, high: bool| vram[((word & 0x7FFF) as usize) * 2 + high as usize];
+    for tile in 0..count {
+        let left = LABEL_WIDTH + (tile % SHEET_COLUMNS) * CELL + 1;
+        let top = (tile / SHEET_COLUMNS) * CELL + 1;
+        for y in 0..8u16 {
+            for x in 0..8u16 {
+                let index = match tiles.get(tile) {
+                    // Bitplane pairs 8 words apart, one word per row
+                    Some(&address) => (0..bpp as u16 / 2).fold(0u8, |index, pair| {
+                        let word = address.wrapping_add(y + pair * 8);
+                        let bit = |high| (byte(word, high) >> (7 - x)) & 1;
+                        index | (bit(false) << (pair * 2)) | (bit(true) << (pair * 2 + 1))
+                    }),
+                    // Mode 7: one pixel byte per word, high bytes only
+                    None => byte(tile as u16 * 64 + y * 8 + x, true),
+                };
+                let rgba = if index == 0 {
+                    [0, 0, 0, 0]
+                } else {
+                    let [r, g, b] = palette::to_rgb(colors.get(color_base + index as usize).copied().unwrap_or(0));
+                    [r, g, b, 0xFF]
+                };
+                sheet.set((left + x as usize) as i32, (top + y as usize) as i32, rgba);
+            }
+        }
+    }
+    Ok(sheet)
+}
+
+// A sprite on the frame, as the overlay draws it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlacedSprite {
+    pub index: u8,
+    // Top left on screen; sprites low on the screen wrap to the top
+    pub x: i32,
+    pub y: i32,
+    pub width: u8,
+    pub height: u8,
+    pub tile: u16,
+    pub palette: u8,
+    pub priority: u8,
+}
+
+// Sprites that show at least one pixel of the frame, in OAM order
+pub fn visible_sprites(ppu: &Ppu) -> Vec<PlacedSprite> {
+    let (small, large) = sprites::sprite_sizes(ppu.registers.obsel);
+    let height = ppu.frame_height() as i32;
+    (0..128u8)
+        .filter_map(|index| {
+            let sprite = ppu.sprite(index);
+            let (w, h) = if sprite.size { large } else { small };
+            let mut y = sprite.y as i32;
+            if y + h as i32 > 256 {
+                y -= 256;
+            }
+            let x = sprite.x as i32;
+            let visible = x + (w as i32) > 0 && x < SCREEN_WIDTH as i32 && y + (h as i32) > 0 && y < height;
+            visible.then_some(PlacedSprite {
+                index,
+                x,
+                y,
+                width: w,
+                height: h,
+                tile: sprite.tile,
+                palette: sprite.palette,
+                priority: sprite.priority,
+            })
+        })
+        .collect()
+}
+
+// The last frame with a box around every visible sprite, colored by its
+// priority (blue, green, yellow, red for 0-3) and labelled with its OAM
+// index in hex
+pub fn sprite_overlay(ppu: &Ppu) -> Sheet {
+    let mut sheet = Sheet {
+        width: SCREEN_WIDTH,
+        height: ppu.frame_height(),
+        rgba: ppu.get_frame_buffer().to_vec(),
+    };
+    let placed = visible_sprites(ppu);
+    for sprite in &placed {
+        let color = PRIORITY_COLORS[sprite.priority as usize & 0x03];
+        sheet.outline(sprite.x, sprite.y, sprite.width as i32, sprite.height as i32, color);
+    }
+    // Labels last, so no box crosses one
+    for sprite in &placed {
+        let color = PRIORITY_COLORS[sprite.priority as usize & 0x03];
+        sheet.fill_rect(sprite.x + 1, sprite.y + 1, 2 * GLYPH_ADVANCE as i32 + 1, 7, [0, 0, 0, 0xFF]);
+        sheet.label(sprite.x + 2, sprite.y + 2, sprite.index as usize, 2, color);
+    }
+    sheet
+}
+
+// Write a tile sheet as PNG, returning the number of tiles
+pub fn export_tiles(ppu: &Ppu, layer: TileLayer, palette: u8, path: &Path) -> Result<usize> {
+    let sheet = tile_sheet(ppu, layer, palette)?;
+    std::fs::write(path, sheet.to_png())?;
+    Ok((sheet.height - 1) / CELL * SHEET_COLUMNS)
+}
+
+// Write the sprite overlay as PNG, returning the sprites it shows
+pub fn export_sprites(ppu: &Ppu, path: &Path) -> Result<Vec<PlacedSprite>> {
+    std::fs::write(path, sprite_overlay(ppu).to_png())?;
+    Ok(visible_sprites(ppu))
+}
@@ -0,0 +1,150 @@
+// Timed spans around the emulator's hot paths (CPU batches, scanline
+// rendering, DMA, frontend present), exported in the Chrome trace event
+// format for chrome://tracing or Perfetto.
+//
+// Spans are only recorded in builds with the `profiling` feature, and only
+// on the thread that called start_recording; without the feature span()
+// returns an empty guard that compiles away.
+use crate::Result;
+use serde::Serialize;
+use std::path::Path;
+
+// Recording stops growing after this many spans (about 64 MB)
+pub const MAX_SPANS: usize = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SpanEvent {
+    pub name: &'static str,
+    #[serde(rename = "cat")]
+    pub category: &'static str,
+    // Start and duration in microseconds since recording started
+    pub ts: f64,
+    pub dur: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    pub events: Vec<SpanEvent>,
+    // Spans dropped after MAX_SPANS was reached
+    pub dropped: u64,
+}
+
+#[derive(Serialize)]
+struct ChromeEvent<'a> {
+    #[serde(flatten)]
+    event: &'a SpanEvent,
+    ph: &'static str,
+    pid: u32,
+    tid: u32,
+}
+
+impl Trace {
+    // {"traceEvents": [...]} with one complete ("X") event per span
+    pub fn to_chrome_json(&self) -> Result<String> {
+        let events: Vec<ChromeEvent> = self.events
+            .iter()
+            .map(|event| ChromeEvent { event, ph: "X", pid: 1, tid: 1 })
+            .collect();
+        serde_json::to_string(&serde_json::json!({
+            "traceEvents": events,
+            "displayTimeUnit": "ms",
+        }))
+        .map_err(|e| std::io::Error::from(e).into())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_chrome_json()?)?;
+        Ok(())
+    }
+}
+
+// Whether spans can be recorded in this build
+pub const fn available() -> bool {
+    cfg!(all(feature = "profiling", not(target_arch = "wasm32")))
+}
+
+#[cfg(all(feature = "profiling", not(target_arch = "wasm32")))]
+mod recorder {
+    use super::{SpanEvent, Trace, MAX_SPANS};
+    use std::cell::RefCell;
+    use std::time::Instant;
+
+    struct Recorder {
+        origin: Instant,
+        trace: Trace,
+    }
+
+    thread_local! {
+        static RECORDER: RefCell<Option<Recorder>> = const { RefCell::new(None) };
+    }
+
+    pub fn start_recording() {
+        RECORDER.with(|recorder| {
+            *recorder.borrow_mut() = Some(Recorder { origin: Instant::now(), trace: Trace::default() });
+        });
+    }
+
+    pub fn stop_recording() -> Option<Trace> {
+        RECORDER.with(|recorder| recorder.borrow_mut().take().map(|recorder| recorder.trace))
+    }
+
+    #[must_use = "the span ends when the guard is dropped"]
+    pub struct SpanGuard {
+        name: &'static str,
+        category: &'static str,
+        start: Option<Instant>,
+    }
+
+    pub fn span(name: &'static str, category: &'static str) -> SpanGuard {
+        let recording = RECORDER.with(|recorder| recorder.borrow().is_some());
+        SpanGuard { name, category, start: recording.then(Instant::now) }
+    }
+
+    impl Drop for SpanGuard {
+        fn drop(&mut self) {
+            let Some(start) = self.start else { return };
+            let end = Instant::now();
+            RECORDER.with(|recorder| {
+                if let Some(recorder) = recorder.borrow_mut().as_mut() {
+                    if recorder.trace.events.len() >= MAX_SPANS {
+                        recorder.trace.dropped += 1;
+                        return;
+                    }
+                    let micros = |instant: Instant| instant.saturating_duration_since(recorder.origin).as_secs_f64() * 1e6;
+                    recorder.trace.events.push(SpanEvent {
+                        name: self.name,
+                        category: self.category,
+                        ts: micros(start),
+                        dur: (end - start).as_secs_f64() * 1e6,
+                    });
+                }
+            });
+        }
+    }
+}
+
+#[cfg(not(all(feature = "profiling", not(target_arch = "wasm32"))))]
+mod recorder {
+    use super::Trace;
+
+    pub fn start_recording() {}
+
+    pub fn stop_recording() -> Option<Trace> {
+        None
+    }
+
+    #[must_use = "the span ends when the guard is dropped"]
+    pub struct SpanGuard;
+
+    #[inline(always)]
+    pub fn span(_name: &'static str, _category: &'static str) -> SpanGuard {
+        SpanGuard
+    }
+}
+
+// Start collecting spans on this thread, discarding any earlier recording
+pub use recorder::start_recording;
+// End the recording; None if nothing was being recorded (or the feature is off)
+pub use recorder::stop_recording;
+// Time the enclosing scope: `let _span = spans::span("dma", "dma");`
+pub use recorder::{span, SpanGuard};
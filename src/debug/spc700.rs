@@ -0,0 +1,146 @@
+// SPC700 disassembler for the APU debugger context
+use std::fmt;
+
+// Operand placeholders: {dN} direct page byte N, {iN} immediate byte N,
+// {rN} branch offset in byte N, {uN} PCALL page offset, {a12} absolute
+// word, {m12} 13-bit address with bit number
+const MNEMONICS: [&str; 256] = [
+    "NOP", "TCALL 0", "SET1 {d1}.0", "BBS {d1}.0, {r2}",
+    "OR A, {d1}", "OR A, {a12}", "OR A, (X)", "OR A, [{d1}+X]",
+    "OR A, #{i1}", "OR {d2}, {d1}", "OR1 C, {m12}", "ASL {d1}",
+    "ASL {a12}", "PUSH PSW", "TSET1 {a12}", "BRK",
+    "BPL {r1}", "TCALL 1", "CLR1 {d1}.0", "BBC {d1}.0, {r2}",
+    "OR A, {d1}+X", "OR A, {a12}+X", "OR A, {a12}+Y", "OR A, [{d1}]+Y",
+    "OR {d2}, #{i1}", "OR (X), (Y)", "DECW {d1}", "ASL {d1}+X",
+    "ASL A", "DEC X", "CMP X, {a12}", "JMP [{a12}+X]",
+    "CLRP", "TCALL 2", "SET1 {d1}.1", "BBS {d1}.1, {r2}",
+    "AND A, {d1}", "AND A, {a12}", "AND A, (X)", "AND A, [{d1}+X]",
+    "AND A, #{i1}", "AND {d2}, {d1}", "OR1 C, /{m12}", "ROL {d1}",
+    "ROL {a12}", "PUSH A", "CBNE {d1}, {r2}", "BRA {r1}",
+    "BMI {r1}", "TCALL 3", "CLR1 {d1}.1", "BBC {d1}.1, {r2}",
+    "AND A, {d1}+X", "AND A, {a12}+X", "AND A, {a12}+Y", "AND A, [{d1}]+Y",
+    "AND {d2}, #{i1}", "AND (X), (Y)", "INCW {d1}", "ROL {d1}+X",
+    "ROL A", "INC X", "CMP X, {d1}", "CALL {a12}",
+    "SETP", "TCALL 4", "SET1 {d1}.2", "BBS {d1}.2, {r2}",
+    "EOR A, {d1}", "EOR A, {a12}", "EOR A, (X)", "EOR A, [{d1}+X]",
+    "EOR A, #{i1}", "EOR {d2}, {d1}", "AND1 C, {m12}", "LSR {d1}",
+    "LSR {a12}", "PUSH X", "TCLR1 {a12}", "PCALL {u1}",
+    "BVC {r1}", "TCALL 5", "CLR1 {d1}.2", "BBC {d1}.2, {r2}",
+    "EOR A, {d1}+X", "EOR A, {a12}+X", "EOR A, {a12}+Y", "EOR A, [{d1}]+Y",
+    "EOR {d2}, #{i1}", "EOR (X), (Y)", "CMPW YA, {d1}", "LSR {d1}+X",
+    "LSR A", "MOV X, A", "CMP Y, {a12}", "JMP {a12}",
+    "CLRC", "TCALL 6", "SET1 {d1}.3", "BBS {d1}.3, {r2}",
+    "CMP A, {d1}", "CMP A, {a12}", "CMP A, (X)", "CMP A, [{d1}+X]",
+    "CMP A, #{i1}", "CMP {d2}, {d1}", "AND1 C, /{m12}", "ROR {d1}",
+    "ROR {a12}", "PUSH Y", "DBNZ {d1}, {r2}", "RET",
+    "BVS {r1}", "TCALL 7", "CLR1 {d1}.3", "BBC {d1}.3, {r2}",
+    "CMP A, {d1}+X", "CMP A, {a12}+X", "CMP A, {a12}+Y", "CMP A, [{d1}]+Y",
+    "CMP {d2}, #{i1}", "CMP (X), (Y)", "ADDW YA, {d1}", "ROR {d1}+X",
+    "ROR A", "MOV A, X", "CMP Y, {d1}", "RETI",
+    "SETC", "TCALL 8", "SET1 {d1}.4", "BBS {d1}.4, {r2}",
+    "ADC A, {d1}", "ADC A, {a12}", "ADC A, (X)", "ADC A, [{d1}+X]",
+    "ADC A, #{i1}", "ADC {d2}, {d1}", "EOR1 C, {m12}", "DEC {d1}",
+    "DEC {a12}", "MOV Y, #{i1}", "POP PSW", "MOV {d2}, #{i1}",
+    "BCC {r1}", "TCALL 9", "CLR1 {d1}.4", "BBC {d1}.4, {r2}",
+    "ADC A, {d1}+X", "ADC A, {a12}+X", "ADC A, {a12}+Y", "ADC A, [{d1}]+Y",
+    "ADC {d2}, #{i1}", "ADC (X), (Y)", "SUBW YA, {d1}", "DEC {d1}+X",
+    "DEC A", "MOV X, SP", "DIV YA, X", "XCN A",
+    "EI", "TCALL 10", "SET1 {d1}.5", "BBS {d1}.5, {r2}",
+    "SBC A, {d1}", "SBC A, {a12}", "SBC A, (X)", "SBC A, [{d1}+X]",
+    "SBC A, #{i1}", "SBC {d2}, {d1}", "MOV1 C, {m12}", "INC {d1}",
+    "INC {a12}", "CMP Y, #{i1}", "POP A", "MOV (X)+, A",
+    "BCS {r1}", "TCALL 11", "CLR1 {d1}.5", "BBC {d1}.5, {r2}",
+    "SBC A, {d1}+X", "SBC A, {a12}+X", "SBC A, {a12}+Y", "SBC A, [{d1}]+Y",
+    "SBC {d2}, #{i1}", "SBC (X), (Y)", "MOVW YA, {d1}", "INC {d1}+X",
+    "INC A", "MOV SP, X", "DAS A", "MOV A, (X)+",
+    "DI", "TCALL 12", "SET1 {d1}.6", "BBS {d1}.6, {r2}",
+    "MOV {d1}, A", "MOV {a12}, A", "MOV (X), A", "MOV [{d1}+X], A",
+    "CMP X, #{i1}", "MOV {a12}, X", "MOV1 {m12}, C", "MOV {d1}, Y",
+    "MOV {a12}, Y", "MOV X, #{i1}", "POP X", "MUL YA",
+    "BNE {r1}", "TCALL 13", "CLR1 {d1}.6", "BBC {d1}.6, {r2}",
+    "MOV {d1}+X, A", "MOV {a12}+X, A", "MOV {a12}+Y, A", "MOV [{d1}]+Y, A",
+    "MOV {d1}, X", "MOV {d1}+Y, X", "MOVW {d1}, YA", "MOV {d1}+X, Y",
+    "DEC Y", "MOV A, Y", "CBNE {d1}+X, {r2}", "DAA A",
+    "CLRV", "TCALL 14", "SET1 {d1}.7", "BBS {d1}.7, {r2}",
+    "MOV A, {d1}", "MOV A, {a12}", "MOV A, (X)", "MOV A, [{d1}+X]",
+    "MOV A, #{i1}", "MOV X, {a12}", "NOT1 {m12}", "MOV Y, {d1}",
+    "MOV Y, {a12}", "NOTC", "POP Y", "SLEEP",
+    "BEQ {r1}", "TCALL 15", "CLR1 {d1}.7", "BBC {d1}.7, {r2}",
+    "MOV A, {d1}+X", "MOV A, {a12}+X", "MOV A, {a12}+Y", "MOV A, [{d1}]+Y",
+    "MOV X, {d1}", "MOV X, {d1}+Y", "MOV {d2}, {d1}", "MOV Y, {d1}+X",
+    "INC Y", "MOV Y, A", "DBNZ Y, {r1}", "STOP",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpcInstruction {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub text: String,
+}
+
+impl SpcInstruction {
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+impl fmt::Display for SpcInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        write!(f, "${:04X}: {:<9} {}", self.address, bytes.join(" "), self.text)
+    }
+}
+
+// Length in bytes of the instruction starting with this opcode
+pub fn instruction_length(opcode: u8) -> usize {
+    let template = MNEMONICS[opcode as usize];
+    if template.contains("2}") {
+        3
+    } else if template.contains("1}") {
+        2
+    } else {
+        1
+    }
+}
+
+// Decode one instruction from 64KB of APU RAM (addresses wrap)
+pub fn decode(ram: &[u8], address: u16) -> SpcInstruction {
+    let read = |offset: u16| ram.get(address.wrapping_add(offset) as usize).copied().unwrap_or(0);
+    let opcode = read(0);
+    let length = instruction_length(opcode);
+    let bytes: Vec<u8> = (0..length as u16).map(read).collect();
+    let byte = |n: usize| bytes.get(n).copied().unwrap_or(0);
+    let word = u16::from_le_bytes([byte(1), byte(2)]);
+
+    // Branch targets are relative to the next instruction
+    let next = address.wrapping_add(length as u16);
+    let branch = |n: usize| next.wrapping_add(byte(n) as i8 as u16);
+
+    let text = MNEMONICS[opcode as usize]
+        .replace("{d1}", &format!("${:02X}", byte(1)))
+        .replace("{d2}", &format!("${:02X}", byte(2)))
+        .replace("{i1}", &format!("${:02X}", byte(1)))
+        .replace("{r1}", &format!("${:04X}", branch(1)))
+        .replace("{r2}", &format!("${:04X}", branch(2)))
+        .replace("{u1}", &format!("$FF{:02X}", byte(1)))
+        .replace("{a12}", &format!("${:04X}", word))
+        .replace("{m12}", &format!("${:04X}.{}", word & 0x1FFF, word >> 13));
+
+    SpcInstruction { address, bytes, text }
+}
+
+// Decode `count` consecutive instructions
+pub fn disassemble(ram: &[u8], address: u16, count: usize) -> Vec<SpcInstruction> {
+    let mut instructions = Vec::with_capacity(count);
+    let mut addr = address;
+    for _ in 0..count {
+        let instruction = decode(ram, addr);
+        addr = addr.wrapping_add(instruction.len() as u16);
+        instructions.push(instruction);
+    }
+    instructions
+}
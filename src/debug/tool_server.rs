@@ -0,0 +1,491 @@
+// Local JSON-RPC server for external tools (trackers, map viewers,
+// practice HUDs) that want to read and change a running game without
+// linking against the crate. Each request and response is one JSON-RPC 2.0
+// object per line over TCP:
+//
+//   {"jsonrpc":"2.0","id":1,"method":"peek","params":{"address":"0x7E0010","length":2}}
+//   {"jsonrpc":"2.0","id":1,"result":{"address":8257552,"bytes":[0,3]}}
+//
+// Methods:
+//   peek {address, length = 1, space = "bus"}    read bytes without side effects
+//   poke {address, bytes, space = "bus"}         write bytes
+//   registers                                    CPU and SPC700 registers
+//   status                                       frame count and ROM title
+//   hashes                                       frame and audio hash of the last frame (see frame_hash)
+//   save_state {path?}, load_state {path?}       a file, or one state kept in the server
+//   heatmap_start {window = 600, vram = false}   count accesses per address (see debug::heatmap)
+//   heatmap {space = "wram", bucket = 16, top = 16, current = false}, heatmap_stop
+//   subscribe {watch = [{address, length, space}]}, unsubscribe
+//   interrupts                                   interrupts taken since the last call (see debug::interrupts)
+//   assert_nmi, assert_irq {source}, release_irq {source}   raise interrupts by hand
+//
+// `interrupts` starts tracking on its first call, which returns nothing;
+// each later call returns every NMI, IRQ, BRK, COP and RTI since the one
+// before. An IRQ `source` is "timer", "ppu" or "cartridge"; an asserted one
+// holds the line until released, an asserted NMI is taken once.
+//
+// `heatmap` returns the last closed window (or the open one, with
+// `current`): read and write totals per `bucket` bytes from the start of
+// "wram" or "vram", and the `top` busiest addresses.
+//
+// Spaces are "bus" (the CPU address space; I/O registers peek as 0), "aram"
+// (SPC700 RAM), "vram", "cgram" and "oam". Addresses are numbers or hex
+// strings ("0x7E0010", "$7E0010").
+//
+// Subscribed clients get a "frame" notification after every frame with
+// the watched bytes, and `discontinuity` set when the emulator was reset or
+// a state loaded since the previous one:
+//
+//   {"jsonrpc":"2.0","method":"frame","params":{"frame":120,"discontinuity":false,"watch":[[0,3]]}}
+use crate::debug::graphics::GraphicsMemory;
+use crate::cpu::IrqSource;
+use crate::debug::heatmap::{self, HeatmapSpace};
+use crate::emulator::Emulator;
+use crate::savestate::SaveState;
+use crate::Result;
+use serde_json::{json, Value};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+// Longest request line accepted
+const MAX_REQUEST: usize = 64 * 1024;
+// Unsent output at which a client that stopped reading is dropped
+const MAX_BACKLOG: usize = 4 * 1024 * 1024;
+// Most bytes one peek returns
+const MAX_PEEK: usize = 0x10000;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const EMULATOR_ERROR: i64 = -32000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Space {
+    Bus,
+    Aram,
+    Graphics(GraphicsMemory),
+}
+
+impl Space {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "bus" => Some(Space::Bus),
+            "aram" => Some(Space::Aram),
+            other => GraphicsMemory::from_name(other).map(Space::Graphics),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Space::Bus => 0x100_0000,
+            Space::Aram => 0x10000,
+            Space::Graphics(memory) => memory.size(),
+        }
+    }
+
+    fn peek(self, emulator: &Emulator, address: u32, length: usize) -> Vec<u8> {
+        match self {
+            Space::Bus => (0..length as u32).map(|i| emulator.peek8((address + i) & 0xFF_FFFF)).collect(),
+            Space::Aram => {
+                let ram = emulator.apu().spc700().ram();
+                (0..length).map(|i| ram[(address as usize + i) & 0xFFFF]).collect()
+            }
+            Space::Graphics(memory) => {
+                let data = memory.contents(emulator.ppu());
+                (0..length).map(|i| data[(address as usize + i) % data.len()]).collect()
+            }
+        }
+    }
+
+    fn poke(self, emulator: &mut Emulator, address: u32, bytes: &[u8]) {
+        match self {
+            Space::Bus => {
+                for (i, &byte) in bytes.iter().enumerate() {
+                    emulator.write8((address + i as u32) & 0xFF_FFFF, byte);
+                }
+            }
+            Space::Aram => {
+                for (i, &byte) in bytes.iter().enumerate() {
+                    emulator.apu_mut().spc700_mut().write8((address as usize + i) as u16, byte);
+                }
+            }
+            Space::Graphics(memory) => {
+                let mut data = memory.contents(emulator.ppu());
+                let len = data.len();
+                for (i, &byte) in bytes.iter().enumerate() {
+                    data[(address as usize + i) % len] = byte;
+                }
+                memory.restore_contents(emulator.ppu_mut(), &data);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Watch {
+    space: Space,
+    address: u32,
+    length: usize,
+}
+
+struct Client {
+    stream: TcpStream,
+    peer: SocketAddr,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    // Watched ranges while subscribed to frame notifications
+    subscription: Option<Vec<Watch>>,
+}
+
+impl Client {
+    fn send(&mut self, message: &Value) {
+        self.output.extend_from_slice(message.to_string().as_bytes());
+        self.output.push(b'\n');
+    }
+
+    // Write what the socket takes; false once the client is gone
+    fn flush(&mut self) -> bool {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.output.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return false,
+            }
+        }
+        self.output.len() <= MAX_BACKLOG
+    }
+}
+
+type CallResult = std::result::Result<Value, (i64, String)>;
+
+fn invalid_params(message: impl Into<String>) -> (i64, String) {
+    (INVALID_PARAMS, message.into())
+}
+
+pub struct ToolServer {
+    listener: TcpListener,
+    clients: Vec<Client>,
+    // State kept by save_state without a path
+    saved: Option<SaveState>,
+    // Emulator audio epoch at the previous frame notification
+    epoch: Option<u64>,
+}
+
+impl ToolServer {
+    // Listen on `address`; tools are trusted with the whole machine, so
+    // keep it on 127.0.0.1 unless the network is too
+    pub fn bind(address: &str) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener, clients: Vec::new(), saved: None, epoch: None })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.len()
+    }
+
+    // Accept clients and answer their requests; never blocks. Call between
+    // frames, so pokes land before the next one runs.
+    pub fn poll(&mut self, emulator: &mut Emulator) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    if stream.set_nonblocking(true).is_ok() {
+                        let _ = stream.set_nodelay(true);
+                        log::info!("Tool connected from {}", peer);
+                        self.clients.push(Client { stream, peer, input: Vec::new(), output: Vec::new(), subscription: None });
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!("Tool server accept failed: {}", e);
+                    break;
+                }
+            }
+        }
+
+        let mut clients = std::mem::take(&mut self.clients);
+        clients.retain_mut(|client| {
+            let open = self.serve(client, emulator);
+            if !open {
+                log::info!("Tool at {} disconnected", client.peer);
+            }
+            open
+        });
+        self.clients = clients;
+    }
+
+    // Send frame notifications to subscribed clients; call after each frame
+    pub fn end_frame(&mut self, emulator: &Emulator) {
+        let epoch = emulator.audio_epoch();
+        let discontinuity = self.epoch.is_some_and(|last| last != epoch);
+        self.epoch = Some(epoch);
+        for client in self.clients.iter_mut() {
+            let Some(watches) = client.subscription.as_ref() else {
+                continue;
+            };
+            let watch: Vec<Vec<u8>> = watches.iter().map(|w| w.space.peek(emulator, w.address, w.length)).collect();
+            client.send(&json!({
+                "jsonrpc": "2.0",
+                "method": "frame",
+                "params": { "frame": emulator.get_frame_count(), "discontinuity": discontinuity, "watch": watch },
+            }));
+        }
+        self.clients.retain_mut(Client::flush);
+    }
+
+    // Read and answer what the client sent; false once it is gone
+    fn serve(&mut self, client: &mut Client, emulator: &mut Emulator) -> bool {
+        let mut open = true;
+        let mut chunk = [0; 4096];
+        loop {
+            match client.stream.read(&mut chunk) {
+                Ok(0) => {
+                    open = false;
+                    break;
+                }
+                Ok(n) => client.input.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => {
+                    open = false;
+                    break;
+                }
+            }
+        }
+        while let Some(end) = client.input.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = client.input.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if !line.trim().is_empty() {
+                if let Some(response) = self.handle(&line, &mut client.subscription, emulator) {
+                    client.send(&response);
+                }
+            }
+        }
+        if client.input.len() > MAX_REQUEST {
+            return false;
+        }
+        client.flush() && open
+    }
+
+    // One request line; None for notifications (requests without an id)
+    fn handle(&mut self, line: &str, subscription: &mut Option<Vec<Watch>>, emulator: &mut Emulator) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request.get("method").and_then(Value::as_str) else {
+            return Some(error_response(id.unwrap_or(Value::Null), INVALID_REQUEST, "missing method"));
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let result = self.call(method, &params, subscription, emulator);
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn call(&mut self, method: &str, params: &Value, subscription: &mut Option<Vec<Watch>>, emulator: &mut Emulator) -> CallResult {
+        let emulator_error = |e: crate::EmulatorError| (EMULATOR_ERROR, e.to_string());
+        match method {
+            "peek" => {
+                let watch = watch_param(params)?;
+                Ok(json!({ "address": watch.address, "bytes": watch.space.peek(emulator, watch.address, watch.length) }))
+            }
+            "poke" => {
+                let space = space_param(params)?;
+                let address = address_param(params, space)?;
+                let bytes = params.get("bytes").and_then(Value::as_array).ok_or_else(|| invalid_params("poke needs bytes"))?;
+                let bytes = bytes
+                    .iter()
+                    .map(|byte| byte.as_u64().filter(|&b| b <= 0xFF).map(|b| b as u8))
+                    .collect::<Option<Vec<u8>>>()
+                    .ok_or_else(|| invalid_params("bytes must be numbers from 0 to 255"))?;
+                space.poke(emulator, address, &bytes);
+                Ok(json!({ "written": bytes.len() }))
+            }
+            "registers" => {
+                let cpu = &emulator.cpu.registers;
+                let spc = emulator.apu().spc700().registers();
+                Ok(json!({
+                    "cpu": {
+                        "a": cpu.a, "x": cpu.x, "y": cpu.y, "s": cpu.s, "d": cpu.d,
+                        "db": cpu.db, "pc": cpu.pc, "p": cpu.p, "emulation": cpu.emulation_mode,
+                    },
+                    "spc700": { "a": spc.a, "x": spc.x, "y": spc.y, "sp": spc.sp, "pc": spc.pc, "psw": spc.psw },
+                }))
+            }
+            "status" => {
+                let title = emulator.get_rom_info().map(|info| info.title);
+                Ok(json!({ "frame": emulator.get_frame_count(), "title": title }))
+            }
+            // Hex strings, as JSON numbers lose bits past 2^53 in most clients
+            "hashes" => Ok(json!({
+                "frame": emulator.get_frame_count(),
+                "frame_hash": format!("{:016x}", emulator.frame_hash()),
+                "audio_hash": format!("{:016x}", emulator.audio_hash()),
+            })),
+            "save_state" => match path_param(params) {
+                Some(path) => {
+                    emulator.save_state_to_file(path).map_err(emulator_error)?;
+                    Ok(json!({ "path": path }))
+                }
+                None => {
+                    self.saved = Some(emulator.save_state().map_err(emulator_error)?);
+                    Ok(json!({ "saved": true }))
+                }
+            },
+            "load_state" => {
+                match path_param(params) {
+                    Some(path) => emulator.load_state_from_file(path).map_err(emulator_error)?,
+                    None => {
+                        let state = self.saved.as_ref().ok_or((EMULATOR_ERROR, "no state saved yet".to_string()))?;
+                        emulator.load_state(state).map_err(emulator_error)?;
+                    }
+                }
+                Ok(json!({ "loaded": true }))
+            }
+            "heatmap_start" => {
+                let window = match params.get("window") {
+                    None => heatmap::DEFAULT_WINDOW_FRAMES,
+                    Some(window) => window.as_u64().and_then(|frames| u32::try_from(frames).ok())
+                        .ok_or_else(|| invalid_params("window must be a frame count"))?,
+                };
+                let vram = params.get("vram").and_then(Value::as_bool).unwrap_or(false);
+                emulator.start_heatmap(window, vram);
+                Ok(json!({ "window": window, "vram": vram }))
+            }
+            "heatmap_stop" => {
+                emulator.stop_heatmap();
+                Ok(json!({ "stopped": true }))
+            }
+            "heatmap" => {
+                let space = match params.get("space").and_then(Value::as_str) {
+                    None => HeatmapSpace::Wram,
+                    Some(name) => HeatmapSpace::from_name(name).ok_or_else(|| invalid_params(format!("no heatmap for space '{}'", name)))?,
+                };
+                let count_param = |name: &str, default: u64| match params.get(name) {
+                    None => Ok(default),
+                    Some(value) => value.as_u64().filter(|&n| n >= 1).ok_or_else(|| invalid_params(format!("{} must be a positive number", name))),
+                };
+                let bucket = count_param("bucket", 16)?.min(space.size() as u64) as usize;
+                let top = count_param("top", 16)?.min(MAX_PEEK as u64) as usize;
+                let counting = emulator.heatmap().ok_or((EMULATOR_ERROR, "the heatmap is off; call heatmap_start".to_string()))?;
+                let window = if params.get("current").and_then(Value::as_bool).unwrap_or(false) { counting.current() } else { counting.latest() };
+                let counts = window.counts(space).ok_or((EMULATOR_ERROR, "VRAM isn't being counted; start with vram = true".to_string()))?;
+                let (reads, writes): (Vec<u64>, Vec<u64>) = counts.buckets(bucket).into_iter().unzip();
+                let hottest: Vec<Value> = counts.hottest(top)
+                    .iter()
+                    .map(|hot| json!({ "address": hot.address, "reads": hot.reads, "writes": hot.writes }))
+                    .collect();
+                Ok(json!({
+                    "space": space.name(),
+                    "first_frame": window.first_frame,
+                    "frames": window.frames,
+                    "bucket": bucket,
+                    "touched": counts.touched(),
+                    "reads": reads,
+                    "writes": writes,
+                    "hottest": hottest,
+                }))
+            }
+            "subscribe" => {
+                let watches = match params.get("watch") {
+                    None => Vec::new(),
+                    Some(Value::Array(list)) => list.iter().map(watch_param).collect::<std::result::Result<_, _>>()?,
+                    Some(_) => return Err(invalid_params("watch must be a list")),
+                };
+                *subscription = Some(watches);
+                Ok(json!({ "subscribed": true }))
+            }
+            "unsubscribe" => {
+                *subscription = None;
+                Ok(json!({ "subscribed": false }))
+            }
+            "interrupts" => {
+                if emulator.interrupt_tracker().is_none() {
+                    emulator.set_interrupt_tracking(true);
+                }
+                let events: Vec<Value> = emulator
+                    .take_interrupts()
+                    .iter()
+                    .map(|event| {
+                        json!({
+                            "kind": event.kind.name(), "source": event.source.to_string(), "pc": event.pc,
+                            "target": event.target, "frame": event.frame, "scanline": event.scanline,
+                        })
+                    })
+                    .collect();
+                Ok(json!({ "interrupts": events }))
+            }
+            "assert_nmi" => {
+                emulator.cpu.assert_nmi();
+                Ok(json!({ "asserted": "nmi" }))
+            }
+            "assert_irq" | "release_irq" => {
+                let name = params.get("source").and_then(Value::as_str).unwrap_or_default();
+                let source = IrqSource::from_name(name)
+                    .ok_or_else(|| invalid_params("source must be \"timer\", \"ppu\" or \"cartridge\""))?;
+                if method == "assert_irq" {
+                    emulator.cpu.assert_irq(source);
+                } else {
+                    emulator.cpu.release_irq(source);
+                }
+                Ok(json!({ "source": source.name(), "asserted": emulator.cpu.is_irq_asserted(source) }))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+        }
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn space_param(params: &Value) -> std::result::Result<Space, (i64, String)> {
+    match params.get("space").and_then(Value::as_str) {
+        None => Ok(Space::Bus),
+        Some(name) => Space::from_name(name).ok_or_else(|| invalid_params(format!("unknown space '{}'", name))),
+    }
+}
+
+fn address_param(params: &Value, space: Space) -> std::result::Result<u32, (i64, String)> {
+    let address = match params.get("address") {
+        Some(Value::Number(number)) => number.as_u64(),
+        Some(Value::String(text)) => {
+            let digits = text.trim().trim_start_matches("0x").trim_start_matches("0X").trim_start_matches('$');
+            u64::from_str_radix(digits, 16).ok()
+        }
+        _ => None,
+    };
+    address
+        .filter(|&address| address < space.size() as u64)
+        .map(|address| address as u32)
+        .ok_or_else(|| invalid_params("address must be a number or hex string inside the space"))
+}
+
+fn watch_param(params: &Value) -> std::result::Result<Watch, (i64, String)> {
+    let space = space_param(params)?;
+    let address = address_param(params, space)?;
+    let length = match params.get("length") {
+        None => 1,
+        Some(length) => length.as_u64().filter(|&n| n <= MAX_PEEK as u64).ok_or_else(|| invalid_params("length is at most 65536"))? as usize,
+    };
+    Ok(Watch { space, address, length })
+}
+
+fn path_param(params: &Value) -> Option<&str> {
+    params.get("path").and_then(Value::as_str)
+}
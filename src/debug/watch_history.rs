@@ -0,0 +1,108 @@
+// Per-frame values of a debugger watch, for plotting how something changes
+// over time (a player's X position, HP) while working out game logic.
+use std::collections::VecDeque;
+
+// Frames kept per watch unless set otherwise, ten seconds at 60 fps
+pub const DEFAULT_HISTORY_FRAMES: usize = 600;
+
+// Bars of the text graph, lowest first
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchSample {
+    pub frame: u64,
+    pub value: u32,
+}
+
+// Ring buffer of the last `capacity` samples, oldest first
+#[derive(Debug, Clone)]
+pub struct WatchHistory {
+    samples: VecDeque<WatchSample>,
+    capacity: usize,
+}
+
+impl WatchHistory {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self { samples: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Keeps the newest samples that still fit
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.samples.len() > self.capacity {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    // Record the value at the end of `frame`. A frame number at or before
+    // the newest sample means time went back (a reset or a loaded state),
+    // and the old samples no longer lead up to it, so they are dropped.
+    pub fn push(&mut self, frame: u64, value: u32) {
+        if self.latest().is_some_and(|latest| frame <= latest.frame) {
+            self.samples.clear();
+        }
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(WatchSample { frame, value });
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = WatchSample> + '_ {
+        self.samples.iter().copied()
+    }
+
+    // Samples after `frame`, for a UI that fetches only what is new
+    pub fn since(&self, frame: u64) -> impl Iterator<Item = WatchSample> + '_ {
+        self.samples().filter(move |sample| sample.frame > frame)
+    }
+
+    pub fn values(&self) -> Vec<u32> {
+        self.samples().map(|sample| sample.value).collect()
+    }
+
+    pub fn latest(&self) -> Option<WatchSample> {
+        self.samples.back().copied()
+    }
+
+    // Smallest and largest value, for scaling a graph
+    pub fn range(&self) -> Option<(u32, u32)> {
+        let min = self.samples().map(|sample| sample.value).min()?;
+        let max = self.samples().map(|sample| sample.value).max()?;
+        Some((min, max))
+    }
+
+    // The newest `width` samples as a one-line bar graph, scaled to the
+    // range of the whole history
+    pub fn sparkline(&self, width: usize) -> String {
+        let Some((min, max)) = self.range() else {
+            return String::new();
+        };
+        let span = (max - min) as u64;
+        let skip = self.samples.len().saturating_sub(width);
+        self.samples
+            .iter()
+            .skip(skip)
+            .map(|sample| {
+                let level = ((sample.value - min) as u64 * (SPARK_LEVELS.len() as u64 - 1)).checked_div(span).unwrap_or(0);
+                SPARK_LEVELS[level as usize]
+            })
+            .collect()
+    }
+}
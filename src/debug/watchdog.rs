@@ -0,0 +1,125 @@
+// Desync watchdog: the CPU and the SPC700 talk through the $2140-$2143
+// ports, and a game waiting on an answer the sound program never gives
+// (an unimplemented SPC700 opcode, a timing difference) just freezes. The
+// watchdog spots the CPU polling ports that stopped changing and names the
+// handshake, so the freeze is reported instead of looking like a hang.
+use log::warn;
+use std::fmt;
+
+// Frames of polling without an answer before the watchdog fires, three
+// seconds at 60 fps; games wait on the APU for a few frames at most
+pub const DEFAULT_DESYNC_FRAMES: u32 = 180;
+
+// Port reads in one frame that make it a polling loop rather than the odd
+// check; a tight LDA/CMP/BNE loop manages thousands
+const POLLS_PER_FRAME: u32 = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DesyncReport {
+    // Frame the watchdog fired in
+    pub frame: u64,
+    // Frames the CPU had been polling without an answer
+    pub frames: u32,
+    // Instruction polling the port, and which port (0-3)
+    pub cpu_pc: u32,
+    pub port: u8,
+    pub spc_pc: u16,
+    // Values the CPU last wrote, and the values it keeps reading
+    pub cpu_to_apu: [u8; 4],
+    pub apu_to_cpu: [u8; 4],
+}
+
+impl fmt::Display for DesyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hex = |ports: &[u8; 4]| ports.map(|value| format!("${:02X}", value)).join(" ");
+        write!(
+            f,
+            "CPU at ${:06X} has polled ${:04X} for {} frames without an answer from the APU \
+             (CPU wrote {}, reads {}); SPC700 at ${:04X}",
+            self.cpu_pc,
+            0x2140 + self.port as u16,
+            self.frames,
+            hex(&self.cpu_to_apu),
+            hex(&self.apu_to_cpu),
+            self.spc_pc
+        )
+    }
+}
+
+pub struct DesyncWatchdog {
+    threshold: u32,
+    // CPU port reads this frame and the last of them
+    polls: u32,
+    pc: u32,
+    port: u8,
+    // Ports in both directions at the end of the last frame
+    ports: Option<([u8; 4], [u8; 4])>,
+    // Consecutive frames of polling unchanged ports
+    stuck: u32,
+    // Set when the watchdog fires, until the ports move again
+    report: Option<DesyncReport>,
+}
+
+impl DesyncWatchdog {
+    pub fn new(threshold: u32) -> Self {
+        Self { threshold: threshold.max(1), polls: 0, pc: 0, port: 0, ports: None, stuck: 0, report: None }
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    // After an instruction at `pc` read `port`
+    pub fn cpu_polled(&mut self, pc: u32, port: u8) {
+        self.polls = self.polls.saturating_add(1);
+        self.pc = pc;
+        self.port = port;
+    }
+
+    // At the end of `frame`, with the ports as they stand and the SPC700's
+    // PC. Returns the report when the watchdog fires; it fires once per
+    // stall and is logged.
+    pub fn end_frame(&mut self, frame: u64, ports: ([u8; 4], [u8; 4]), spc_pc: u16) -> Option<DesyncReport> {
+        let waiting = self.polls >= POLLS_PER_FRAME && self.ports == Some(ports);
+        self.ports = Some(ports);
+        self.polls = 0;
+        if !waiting {
+            self.stuck = 0;
+            self.report = None;
+            return None;
+        }
+
+        self.stuck += 1;
+        if self.stuck < self.threshold || self.report.is_some() {
+            return None;
+        }
+        let report = DesyncReport {
+            frame,
+            frames: self.stuck,
+            cpu_pc: self.pc,
+            port: self.port,
+            spc_pc,
+            cpu_to_apu: ports.0,
+            apu_to_cpu: ports.1,
+        };
+        warn!("APU desync: {}", report);
+        self.report = Some(report);
+        self.report
+    }
+
+    // The stall in progress, if the watchdog fired for it
+    pub fn report(&self) -> Option<&DesyncReport> {
+        self.report.as_ref()
+    }
+
+    // Start over, after a reset or a loaded state
+    pub fn reset(&mut self) {
+        *self = Self::new(self.threshold);
+    }
+}
+
+impl Default for DesyncWatchdog {
+    fn default() -> Self {
+        Self::new(DEFAULT_DESYNC_FRAMES)
+    }
+}
@@ -1,4 +1,6 @@
+use crate::debug::dma_log::{DmaLog, DmaTransfer, TransferKind};
 use crate::memory::Bus;
+use crate::ppu::ports::{self, PortOwner};
 use crate::ppu::Ppu;
 use log::trace;
 
@@ -31,6 +33,28 @@ impl From<u8> for DmaMode {
     }
 }
 
+// Bytes moved per scanline by an HDMA transfer in the given mode
+fn hdma_unit_size(mode: DmaMode) -> u32 {
+    match mode {
+        DmaMode::SingleByte => 1,
+        DmaMode::TwoRegisters | DmaMode::SingleToTwoSame | DmaMode::SingleToTwoAlternating => 2,
+        _ => 4,
+    }
+}
+
+// $2140-$217F decode only the low two address bits
+fn apu_port(address: u16) -> u32 {
+    0x2140 | (address & 0x03) as u32
+}
+
+// A-bus addresses a transfer can't use: $2100-$21FF and the DMA registers
+// in the system banks
+fn a_bus_blocked(address: u32) -> bool {
+    let bank = (address >> 16) as u8;
+    let offset = address as u16;
+    matches!(bank, 0x00..=0x3F | 0x80..=0xBF) && matches!(offset, 0x2100..=0x21FF | 0x420B | 0x420C | 0x4300..=0x437F)
+}
+
 // DMA channel state
 #[derive(Debug, Clone)]
 pub struct DmaChannel {
@@ -112,6 +136,9 @@ pub struct DmaController {
     channels: [DmaChannel; 8],
     dma_enable: u8,  // $420B
     hdma_enable: u8, // $420C
+    log: DmaLog,
+    // Bytes moved by DMA and HDMA since reset
+    bytes_transferred: u64,
 }
 
 impl DmaController {
@@ -129,6 +156,8 @@ impl DmaController {
             ],
             dma_enable: 0,
             hdma_enable: 0,
+            log: DmaLog::new(),
+            bytes_transferred: 0,
         }
     }
     
@@ -138,6 +167,20 @@ impl DmaController {
         }
         self.dma_enable = 0;
         self.hdma_enable = 0;
+        self.log.clear();
+        self.bytes_transferred = 0;
+    }
+    
+    pub fn bytes_transferred(&self) -> u64 {
+        self.bytes_transferred
+    }
+    
+    pub fn log(&self) -> &DmaLog {
+        &self.log
+    }
+    
+    pub fn log_mut(&mut self) -> &mut DmaLog {
+        &mut self.log
     }
     
     // Execute DMA transfers for enabled channels
@@ -178,6 +221,18 @@ impl DmaController {
                if b_to_a { "B" } else { "A" },
                if b_to_a { "A" } else { "B" });
         
+        self.log.record(DmaTransfer {
+            kind: TransferKind::Dma,
+            channel: channel as u8,
+            mode,
+            b_to_a,
+            source: (a_bank as u32) << 16 | self.channels[channel].a_address as u32,
+            b_address,
+            size: remaining,
+            scanline: ppu.get_current_scanline(),
+        });
+        self.bytes_transferred += remaining as u64;
+        
         while remaining > 0 {
             let a_address = self.channels[channel].a_address;
             
@@ -188,11 +243,11 @@ impl DmaController {
                         let value = self.read_b_bus(bus, ppu, b_address);
                         self.write_a_bus(bus, a_bank, a_address, value);
                     } else {
-                        let value = bus.read8((a_bank as u32) << 16 | a_address as u32);
+                        let value = self.read_a_bus(bus, a_bank, a_address);
                         self.write_b_bus(bus, ppu, b_address, value);
                     }
                     
-                    self.channels[channel].a_address = (a_address as i32 + step as i32) as u16;
+                    self.channels[channel].a_address = a_address.wrapping_add_signed(step);
                     remaining -= 1;
                     cycles += 8;
                 }
@@ -205,14 +260,14 @@ impl DmaController {
                         let a_address = self.channels[channel].a_address;
                         
                         if b_to_a {
-                            let value = self.read_b_bus(bus, ppu, b_address + i);
+                            let value = self.read_b_bus(bus, ppu, b_address.wrapping_add(i));
                             self.write_a_bus(bus, a_bank, a_address, value);
                         } else {
-                            let value = bus.read8((a_bank as u32) << 16 | a_address as u32);
-                            self.write_b_bus(bus, ppu, b_address + i, value);
+                            let value = self.read_a_bus(bus, a_bank, a_address);
+                            self.write_b_bus(bus, ppu, b_address.wrapping_add(i), value);
                         }
                         
-                        self.channels[channel].a_address = (a_address as i32 + step as i32) as u16;
+                        self.channels[channel].a_address = a_address.wrapping_add_signed(step);
                         remaining -= 1;
                         cycles += 8;
                     }
@@ -223,16 +278,16 @@ impl DmaController {
                     if b_to_a {
                         let value = self.read_b_bus(bus, ppu, b_address);
                         self.write_a_bus(bus, a_bank, a_address, value);
-                        self.channels[channel].a_address = (a_address as i32 + step as i32) as u16;
+                        self.channels[channel].a_address = a_address.wrapping_add_signed(step);
                         if remaining > 1 {
                             let a_address = self.channels[channel].a_address;
                             self.write_a_bus(bus, a_bank, a_address, value);
-                            self.channels[channel].a_address = (a_address as i32 + step as i32) as u16;
+                            self.channels[channel].a_address = a_address.wrapping_add_signed(step);
                         }
                     } else {
-                        let value = bus.read8((a_bank as u32) << 16 | a_address as u32);
+                        let value = self.read_a_bus(bus, a_bank, a_address);
                         self.write_b_bus(bus, ppu, b_address, value);
-                        self.channels[channel].a_address = (a_address as i32 + step as i32) as u16;
+                        self.channels[channel].a_address = a_address.wrapping_add_signed(step);
                         if remaining > 1 {
                             self.write_b_bus(bus, ppu, b_address, value);
                         }
@@ -251,14 +306,14 @@ impl DmaController {
                             let a_address = self.channels[channel].a_address;
                             
                             if b_to_a {
-                                let value = self.read_b_bus(bus, ppu, b_address + (i & 1));
+                                let value = self.read_b_bus(bus, ppu, b_address.wrapping_add(i & 1));
                                 self.write_a_bus(bus, a_bank, a_address, value);
                             } else {
-                                let value = bus.read8((a_bank as u32) << 16 | a_address as u32);
-                                self.write_b_bus(bus, ppu, b_address + (i & 1), value);
+                                let value = self.read_a_bus(bus, a_bank, a_address);
+                                self.write_b_bus(bus, ppu, b_address.wrapping_add(i & 1), value);
                             }
                             
-                            self.channels[channel].a_address = (a_address as i32 + step as i32) as u16;
+                            self.channels[channel].a_address = a_address.wrapping_add_signed(step);
                             remaining -= 1;
                             cycles += 8;
                         }
@@ -273,14 +328,14 @@ impl DmaController {
                         let a_address = self.channels[channel].a_address;
                         
                         if b_to_a {
-                            let value = self.read_b_bus(bus, ppu, b_address + i);
+                            let value = self.read_b_bus(bus, ppu, b_address.wrapping_add(i));
                             self.write_a_bus(bus, a_bank, a_address, value);
                         } else {
-                            let value = bus.read8((a_bank as u32) << 16 | a_address as u32);
-                            self.write_b_bus(bus, ppu, b_address + i, value);
+                            let value = self.read_a_bus(bus, a_bank, a_address);
+                            self.write_b_bus(bus, ppu, b_address.wrapping_add(i), value);
                         }
                         
-                        self.channels[channel].a_address = (a_address as i32 + step as i32) as u16;
+                        self.channels[channel].a_address = a_address.wrapping_add_signed(step);
                         remaining -= 1;
                         cycles += 8;
                     }
@@ -292,11 +347,11 @@ impl DmaController {
                         let value = self.read_b_bus(bus, ppu, b_address);
                         self.write_a_bus(bus, a_bank, a_address, value);
                     } else {
-                        let value = bus.read8((a_bank as u32) << 16 | a_address as u32);
+                        let value = self.read_a_bus(bus, a_bank, a_address);
                         self.write_b_bus(bus, ppu, b_address, value);
                     }
                     
-                    self.channels[channel].a_address = (a_address as i32 + step as i32) as u16;
+                    self.channels[channel].a_address = a_address.wrapping_add_signed(step);
                     remaining -= 1;
                     cycles += 8;
                 }
@@ -327,6 +382,9 @@ impl DmaController {
     
     // Execute HDMA transfers for current scanline
     pub fn execute_hdma(&mut self, bus: &mut Bus, ppu: &mut Ppu) -> u32 {
+        if self.hdma_enable == 0 {
+            return 0;
+        }
         let mut cycles = 18; // HDMA overhead per scanline
         
         for channel_num in 0..8 {
@@ -364,13 +422,25 @@ impl DmaController {
         
         // Execute transfer for this scanline
         if hdma_repeat_mode || line_counter == 1 {
+            self.log.record(DmaTransfer {
+                kind: TransferKind::Hdma,
+                channel: channel as u8,
+                mode,
+                b_to_a,
+                source: (a_bank as u32) << 16 | a_address as u32,
+                b_address,
+                size: hdma_unit_size(mode),
+                scanline: ppu.get_current_scanline(),
+            });
+            self.bytes_transferred += hdma_unit_size(mode) as u64;
+            
             match mode {
                 DmaMode::SingleByte => {
                     if b_to_a {
                         let value = self.read_b_bus(bus, ppu, b_address);
                         self.write_a_bus(bus, a_bank, a_address, value);
                     } else {
-                        let value = bus.read8((a_bank as u32) << 16 | a_address as u32);
+                        let value = self.read_a_bus(bus, a_bank, a_address);
                         self.write_b_bus(bus, ppu, b_address, value);
                     }
                     self.channels[channel].a_address = a_address.wrapping_add(1);
@@ -381,11 +451,11 @@ impl DmaController {
                     for i in 0..2 {
                         let current_a_address = self.channels[channel].a_address;
                         if b_to_a {
-                            let value = self.read_b_bus(bus, ppu, b_address + i);
+                            let value = self.read_b_bus(bus, ppu, b_address.wrapping_add(i));
                             self.write_a_bus(bus, a_bank, current_a_address, value);
                         } else {
-                            let value = bus.read8((a_bank as u32) << 16 | current_a_address as u32);
-                            self.write_b_bus(bus, ppu, b_address + i, value);
+                            let value = self.read_a_bus(bus, a_bank, current_a_address);
+                            self.write_b_bus(bus, ppu, b_address.wrapping_add(i), value);
                         }
                         self.channels[channel].a_address = current_a_address.wrapping_add(1);
                         cycles += 8;
@@ -465,29 +535,51 @@ impl DmaController {
         }
     }
     
-    // Helper functions for B-Bus access (PPU registers)
+    // Helper functions for B-Bus access. The B address is 8 bits, so
+    // B + offset wraps within $21xx ($21FF + 1 is $2100). $2140-$217F are
+    // the four APU ports mirrored; nothing else above $213F is emulated, so
+    // those transfers read open bus (0) and writes go nowhere.
     fn read_b_bus(&self, bus: &mut Bus, ppu: &mut Ppu, address: u8) -> u8 {
+        if let Some(port) = ports::decode_b_bus(address) {
+            return match ports::port(port).owner {
+                PortOwner::Counters => bus.read8(port as u32),
+                PortOwner::Ppu => ppu.read_register(port),
+            };
+        }
         let full_address = 0x2100 + address as u16;
-        if full_address >= 0x2100 && full_address <= 0x213F {
-            ppu.read_register(full_address)
-        } else {
-            bus.read8(full_address as u32)
+        match full_address {
+            0x2140..=0x217F => bus.read8(apu_port(full_address)),
+            _ => 0,
         }
     }
     
     fn write_b_bus(&self, bus: &mut Bus, ppu: &mut Ppu, address: u8, value: u8) {
+        if let Some(port) = ports::decode_b_bus(address) {
+            ppu.write_register(port, value);
+            return;
+        }
         let full_address = 0x2100 + address as u16;
-        if full_address >= 0x2100 && full_address <= 0x213F {
-            ppu.write_register(full_address, value);
-        } else {
-            bus.write8(full_address as u32, value);
+        if (0x2140..=0x217F).contains(&full_address) {
+            bus.write8(apu_port(full_address), value);
+        }
+    }
+    
+    // Helper functions for A-Bus access (main memory). The address wraps
+    // within the bank; the bank never increments. The A bus can't reach the
+    // B bus or the DMA registers, which read open bus and ignore writes.
+    fn read_a_bus(&self, bus: &mut Bus, bank: u8, address: u16) -> u8 {
+        let full_address = (bank as u32) << 16 | address as u32;
+        if a_bus_blocked(full_address) {
+            return 0;
         }
+        bus.read8(full_address)
     }
     
-    // Helper function for A-Bus access (main memory)
     fn write_a_bus(&self, bus: &mut Bus, bank: u8, address: u16, value: u8) {
         let full_address = (bank as u32) << 16 | address as u32;
-        bus.write8(full_address, value);
+        if !a_bus_blocked(full_address) {
+            bus.write8(full_address, value);
+        }
     }
     
     // Register access
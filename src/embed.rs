@@ -0,0 +1,209 @@
+// The embedding API: what GUI frontends, libretro cores and other hosts
+// need to run games, gathered in `ccsnes::prelude`. The rest of the crate
+// is public for tools and tests but follows the emulator's internals.
+//
+// Compatibility intent: the items here only grow within a minor series
+// (and, after 1.0, a major version). To keep that possible:
+// - Button, Frame, RanCycles and AudioChunk are #[non_exhaustive]: new
+//   buttons can be added, so matches need a wildcard arm, and the structs
+//   are only produced by the emulator, so new fields don't break callers.
+// - EmulatorConfig is a builder with private fields; new options arrive as
+//   methods whose defaults keep today's behavior.
+// - Frame and AudioChunk carry their own format (size, pixel format, rate,
+//   channels) rather than leaving callers to assume it.
+// - VideoSink and AudioSink (sink.rs) only take these types, so a frontend
+//   built on them doesn't depend on anything else here.
+use crate::input::controller::*;
+use crate::memory::MemoryInit;
+use crate::metrics::MASTER_CLOCK_HZ;
+use crate::ppu::pixel::{self, PixelFormat};
+use crate::ppu::RenderMode;
+use crate::enhancements::Enhancements;
+use crate::quirks::Quirks;
+use crate::{Emulator, Result};
+
+// Output rate of the DSP, one sample per 32 SPC700 cycles
+pub const AUDIO_SAMPLE_RATE: u32 = 32_000;
+
+// Frames per second on NTSC, 262 lines of 1364 master cycles
+pub const FRAME_RATE: f64 = MASTER_CLOCK_HZ / (1364.0 * 262.0);
+
+// Options an Emulator is created with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmulatorConfig {
+    memory_init: MemoryInit,
+    render_mode: RenderMode,
+    quirks: Quirks,
+    enhancements: Enhancements,
+    frame_timing: bool,
+    apu_clock_ratio: Option<f64>,
+}
+
+impl EmulatorConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // WRAM/VRAM contents at power-on
+    pub fn memory_init(mut self, memory_init: MemoryInit) -> Self {
+        self.memory_init = memory_init;
+        self
+    }
+
+    // Per-scanline or per-dot rendering
+    pub fn render_mode(mut self, render_mode: RenderMode) -> Self {
+        self.render_mode = render_mode;
+        self
+    }
+
+    // Hardware edge cases to emulate
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.quirks = quirks;
+        self
+    }
+
+    // Rendering enhancements to enable
+    pub fn enhancements(mut self, enhancements: Enhancements) -> Self {
+        self.enhancements = enhancements;
+        self
+    }
+
+    // Collect host time per frame (see Emulator::metrics)
+    pub fn frame_timing(mut self, enabled: bool) -> Self {
+        self.frame_timing = enabled;
+        self
+    }
+
+    // APU speed relative to the stock console (see Apu::set_clock_ratio);
+    // build fails for ratios out of range
+    pub fn apu_clock_ratio(mut self, ratio: f64) -> Self {
+        self.apu_clock_ratio = Some(ratio);
+        self
+    }
+
+    pub fn build(&self) -> Result<Emulator> {
+        let mut emulator = Emulator::with_memory_init(self.memory_init)?;
+        emulator.set_render_mode(self.render_mode);
+        emulator.set_quirks(self.quirks);
+        emulator.set_enhancements(self.enhancements);
+        emulator.set_frame_timing(self.frame_timing);
+        if let Some(ratio) = self.apu_clock_ratio {
+            emulator.set_apu_clock_ratio(ratio)?;
+        }
+        Ok(emulator)
+    }
+}
+
+// A button on the standard controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Button {
+    B,
+    Y,
+    Select,
+    Start,
+    Up,
+    Down,
+    Left,
+    Right,
+    A,
+    X,
+    L,
+    R,
+}
+
+impl Button {
+    pub const ALL: [Button; 12] = [
+        Button::B, Button::Y, Button::Select, Button::Start,
+        Button::Up, Button::Down, Button::Left, Button::Right,
+        Button::A, Button::X, Button::L, Button::R,
+    ];
+
+    // The button's bit in the pad word the game reads
+    pub fn mask(self) -> u16 {
+        match self {
+            Button::B => BUTTON_B,
+            Button::Y => BUTTON_Y,
+            Button::Select => BUTTON_SELECT,
+            Button::Start => BUTTON_START,
+            Button::Up => BUTTON_UP,
+            Button::Down => BUTTON_DOWN,
+            Button::Left => BUTTON_LEFT,
+            Button::Right => BUTTON_RIGHT,
+            Button::A => BUTTON_A,
+            Button::X => BUTTON_X,
+            Button::L => BUTTON_L,
+            Button::R => BUTTON_R,
+        }
+    }
+
+    // Pad word with all of `buttons` held
+    pub fn pad_bits(buttons: &[Button]) -> u16 {
+        buttons.iter().fold(0, |bits, button| bits | button.mask())
+    }
+}
+
+// The last completed picture, borrowed from the emulator
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct Frame<'a> {
+    // Row after row with no padding
+    pub pixels: &'a [u8],
+    pub format: PixelFormat,
+    pub width: usize,
+    pub height: usize,
+    // Frames since power-on
+    pub number: u64,
+    // Emulated seconds since power-on (after step_frame, when this frame
+    // ended), for timestamping encoded video or syncing it with audio
+    pub timestamp: f64,
+}
+
+impl Frame<'_> {
+    // Bytes from one row to the next
+    pub fn pitch(&self) -> usize {
+        self.width * self.format.bytes_per_pixel()
+    }
+
+    // RGBA8 whatever the format
+    pub fn pixel(&self, x: usize, y: usize) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let size = self.format.bytes_per_pixel();
+        let offset = y * self.pitch() + x * size;
+        let mut rgba = [0; 4];
+        pixel::convert_to_rgba(self.format, self.pixels.get(offset..offset + size)?, &mut rgba);
+        Some(rgba)
+    }
+}
+
+// What one Emulator::run_cycles call did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RanCycles {
+    // Master cycles emulated, 21.477 MHz on NTSC
+    pub cycles: u64,
+    // A frame finished and the call stopped there; frame() holds it
+    pub frame_completed: bool,
+}
+
+// Audio produced since the previous chunk was taken
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct AudioChunk {
+    // Samples from -1.0 to 1.0, interleaved when channels > 1
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    // Changes when the sound stops following on from earlier chunks
+    // (reset, state load); hosts should crossfade rather than append
+    pub epoch: u64,
+}
+
+impl AudioChunk {
+    // Sample frames, i.e. samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+}
@@ -1,65 +1,365 @@
+use crate::achievements::{AchievementHook, HookEvent, MemoryPeek, MemoryPoll};
+use crate::apu::dsp::Interpolation;
 use crate::apu::Apu;
-use crate::cartridge::Cartridge;
-use crate::cpu::Cpu;
+use crate::apu::spc_file::{self, SpcTags};
+use crate::apu::volume::Volume;
+use crate::cartridge::coprocessor::{Coprocessor, Coprocessors};
+use crate::cartridge::integrity::{self, IntegrityWarning};
+use crate::cartridge::{Cartridge, RomImage};
+use crate::cheats::CheatList;
+use crate::config::AudioConfig;
+use crate::cpu::decode_table::decode_opcode_fast;
+use crate::cpu::{Cpu, IrqSource};
+use crate::debug::heatmap::Heatmap;
+use crate::debug::interrupts::{InterruptEvent, InterruptKind, InterruptSource, InterruptTracker};
+use crate::debug::lint::{Lint, LintKind};
+use crate::debug::spans;
+use crate::debug::watchdog::{DesyncReport, DesyncWatchdog};
 use crate::dma::DmaController;
-use crate::input::Input;
-use crate::memory::Bus;
-use crate::ppu::Ppu;
-use crate::savestate::SaveState;
-use crate::Result;
-use log::{debug, info};
+use crate::embed::{AudioChunk, Button, Frame, RanCycles, AUDIO_SAMPLE_RATE};
+use crate::frame_hash;
+use crate::input::{Input, LivePads, PeripheralConfig};
+use crate::memory::mappers::MapperType;
+use crate::memory::timing;
+use crate::memory::timing::MASTER_CYCLES_PER_DOT;
+use crate::memory::{Bus, MemoryInit};
+use crate::metrics::{FrameTimeHistogram, Metrics, MASTER_CLOCK_HZ};
+use crate::ppu::core::SCREEN_WIDTH;
+use crate::ppu::pixel::FRAME_BUFFER_FORMAT;
+use crate::ppu::{Ppu, RenderMode};
+use crate::enhancements::{self, Enhancements};
+use crate::quirks::Quirks;
+use crate::savestate::{PartialState, SaveState, StateEncoding, StateRecovery, StateSection};
+use crate::sink::{AudioSink, VideoSink};
+use crate::{EmulatorError, Result};
+use log::{debug, info, warn};
+use std::cell::Ref;
 
+type SramWriteCallback = Box<dyn FnMut(&[u8]) + Send>;
+
+// Dot at which the CPU is halted for DRAM refresh (master cycle 538)
+const DRAM_REFRESH_DOT: u32 = 134;
+// Length of the refresh stall: 40 master cycles
+const DRAM_REFRESH_DOTS: u32 = 10;
+const DRAM_REFRESH_CYCLES: u64 = 40;
+
+// Last dots of an instruction in which a new NMI is missed (nmi_vblank_race quirk)
+const NMI_RACE_DOTS: u32 = 2;
+
+// Components the bus links to by pointer are boxed so their addresses stay
+// fixed when the Emulator itself is moved (into a Vec, another thread, ...),
+// and private so that only the Emulator can replace them, relinking the bus
+// when it does. The bus is private too and only lent out shared: see bus().
 pub struct Emulator {
     pub cpu: Cpu,
-    pub ppu: Ppu,
-    pub apu: Apu,
+    ppu: Box<Ppu>,
+    apu: Box<Apu>,
     pub dma: DmaController,
-    pub bus: Bus,
-    pub input: Input,
-    pub cartridge: Option<Cartridge>,
+    bus: Bus,
+    input: Box<Input>,
+    cartridge: Option<Box<Cartridge>>,
     pub cycles: u64,
     pub running: bool,
     
     // Track HDMA initialization state
     hdma_init_pending: bool,
+    
+    // Hardware edge cases enabled for the loaded game
+    quirks: Quirks,
+    // Rendering enhancements enabled for it
+    enhancements: Enhancements,
+    
+    // Master cycles not yet converted into PPU dots
+    master_remainder: u64,
+    
+    // H/V timer IRQ raised and not yet delivered to the CPU
+    timer_irq: bool,
+    
+    // Number of auto-joypad reads performed so far
+    joypad_latches: u64,
+    
+    // CPU instructions executed since reset
+    instructions: u64,
+    
+    // Light gun currently pulling the I/O pin low
+    light_gun_pin: bool,
+    
+    // Level of the NMI line (RDNMI flag and NMITIMEN enable) the CPU last
+    // saw; an NMI is taken on each rising edge
+    nmi_line: bool,
+    
+    // Bumped whenever emulated audio stops following on from the samples
+    // already handed out (reset, state load)
+    audio_epoch: u64,
+    
+    // Gain applied to samples as they are handed out
+    volume: Volume,
+    
+    // Host time per step_frame, when enabled with set_frame_timing
+    frame_times: Option<FrameTimeHistogram>,
+    
+    // Called after a frame in which the game wrote to SRAM
+    sram_write_callback: Option<SramWriteCallback>,
+    // Cartridge SRAM write count when the current frame began
+    frame_sram_writes: u64,
+    
+    // Master cycles run_cycles ran past its last budget, taken off the next
+    cycle_overrun: u64,
+    
+    // WRAM/VRAM contents at power-on
+    memory_init: MemoryInit,
+    
+    // Achievement runtime polling memory after each frame
+    achievement_hook: Option<Box<dyn AchievementHook>>,
+    achievement_poll: MemoryPoll,
+    
+    // Signs of a bad or modified dump found when the ROM was loaded
+    rom_warnings: Vec<IntegrityWarning>,
+    
+    // Catches the CPU waiting forever on the APU; None when turned off
+    desync_watchdog: Option<DesyncWatchdog>,
+    
+    // Interrupts taken and returned from, while the debugger wants them
+    interrupt_tracker: Option<InterruptTracker>,
 }
 
+// SAFETY: the raw pointers in `bus` are what keeps Emulator from being Send.
+// They point into the PPU, APU, input and cartridge boxes of the same
+// Emulator. connect_bus sets them (Bus::connect is crate-private; the public
+// Bus links are unsafe, for standalone buses), the boxes are private and
+// only replaced by Emulator methods that relink, and the bus itself is only
+// lent out shared (bus()), so it can't be swapped or moved out of the
+// instance. Memory is read and written through Emulator methods instead.
+// So no pointer outlives its pointee or reaches into another instance, and
+// moving the Emulator to another thread moves everything the pointers can
+// reach along with it. The Cell/RefCell state in the bus is Send; it only
+// makes Emulator !Sync, so one instance is never used from two threads at
+// once.
+unsafe impl Send for Emulator {}
+
 impl Emulator {
     pub fn new() -> Result<Self> {
+        Self::with_memory_init(MemoryInit::default())
+    }
+
+    pub fn with_memory_init(memory_init: MemoryInit) -> Result<Self> {
         info!("Initializing SNES emulator");
         
         Ok(Self {
             cpu: Cpu::new(),
-            ppu: Ppu::new(),
-            apu: Apu::new(),
+            ppu: Box::new(Ppu::with_memory_init(memory_init)),
+            apu: Box::new(Apu::new()),
             dma: DmaController::new(),
-            bus: Bus::new(),
-            input: Input::new(),
+            bus: Bus::with_memory_init(memory_init),
+            input: Box::new(Input::new()),
             cartridge: None,
             cycles: 0,
             running: false,
             hdma_init_pending: false,
+            quirks: Quirks::default(),
+            enhancements: Enhancements::default(),
+            master_remainder: 0,
+            timer_irq: false,
+            joypad_latches: 0,
+            instructions: 0,
+            light_gun_pin: false,
+            nmi_line: false,
+            audio_epoch: 0,
+            volume: Volume::default(),
+            frame_times: None,
+            sram_write_callback: None,
+            frame_sram_writes: 0,
+            cycle_overrun: 0,
+            memory_init,
+            achievement_hook: None,
+            achievement_poll: MemoryPoll::default(),
+            rom_warnings: Vec::new(),
+            desync_watchdog: Some(DesyncWatchdog::default()),
+            interrupt_tracker: None,
         })
     }
 
+    pub fn memory_init(&self) -> MemoryInit {
+        self.memory_init
+    }
+
+    // Refill WRAM and VRAM as at power-on; inserting a cartridge does the same
+    pub fn set_memory_init(&mut self, memory_init: MemoryInit) {
+        self.memory_init = memory_init;
+        self.bus.init_wram(memory_init);
+        self.ppu.set_memory_init(memory_init);
+    }
+
     pub fn load_rom(&mut self, rom_data: &[u8]) -> Result<()> {
+        self.load_rom_as(rom_data, None)
+    }
+
+    // Load with a forced mapper instead of the one detected from the header
+    pub fn load_rom_as(&mut self, rom_data: &[u8], mapper: Option<MapperType>) -> Result<()> {
         info!("Loading ROM ({} bytes)", rom_data.len());
         
-        let cartridge = Cartridge::load(rom_data)?;
+        self.load_cartridge(Cartridge::load_as(rom_data, mapper)?)
+    }
+
+    // Load from ROM file bytes that may be memory-mapped (RomImage::map);
+    // the cartridge reads from them in place
+    pub fn load_rom_image(&mut self, rom_image: RomImage, mapper: Option<MapperType>) -> Result<()> {
+        info!("Loading ROM ({} bytes, {})", rom_image.len(), if rom_image.is_mapped() { "mapped" } else { "in memory" });
+        
+        self.load_cartridge(Cartridge::from_rom_image_as(rom_image, mapper)?)
+    }
+
+    // Insert an already parsed cartridge (see RomLoader for chunked loading),
+    // taking out the one already in
+    pub fn load_cartridge(&mut self, cartridge: Cartridge) -> Result<()> {
+        self.unload_rom();
         info!("ROM loaded: {}", cartridge.header.title);
         info!("Mapper type: {:?}", cartridge.header.mapper_type);
-        
-        self.cartridge = Some(cartridge);
-        if let Some(ref mut cartridge) = self.cartridge {
-            self.bus.install_cartridge(cartridge);
+        self.rom_warnings = integrity::check_cartridge(&cartridge);
+        for warning in &self.rom_warnings {
+            warn!("ROM integrity: {}", warning);
         }
-        self.bus.connect_input(&mut self.input);
-        self.bus.connect_apu(&mut self.apu);
+        
+        // Known games get their device; profiles can override it later
+        self.input.configure_peripherals(&PeripheralConfig::default(), &cartridge.header.title);
+        self.cartridge = Some(Box::new(cartridge));
+        self.connect_bus();
+        
+        // A new cartridge means a power cycle (reset refills VRAM)
+        self.bus.power_on(self.memory_init);
+        self.apu.power_on();
         
         self.reset()?;
+        self.notify_achievements(HookEvent::RomLoaded);
         Ok(())
     }
 
+    // Take the cartridge out and stop, so another game can go in without
+    // anything of this one carried over. SRAM written since the last frame
+    // ended goes to the SRAM write callback first. Settings (quirks,
+    // enhancements, lint mode, audio and video options) stay. Returns the
+    // cartridge, if any.
+    pub fn unload_rom(&mut self) -> Option<Cartridge> {
+        let cartridge = self.cartridge.take()?;
+        info!("ROM unloaded: {}", cartridge.header.title);
+        if cartridge.sram_write_count() != self.frame_sram_writes {
+            if let Some(callback) = self.sram_write_callback.as_mut() {
+                callback(&cartridge.sram);
+            }
+        }
+        self.bus.remove_cartridge();
+        self.running = false;
+        self.frame_sram_writes = 0;
+        self.rom_warnings.clear();
+        self.dma.reset();
+        self.dma.log_mut().clear();
+        self.bus.power_on(self.memory_init);
+        self.apu.power_on();
+        self.ppu.reset();
+        self.audio_epoch += 1;
+        self.input.rumble_mut().stop_all();
+        if let Some(watchdog) = self.desync_watchdog.as_mut() {
+            watchdog.reset();
+        }
+        self.notify_achievements(HookEvent::RomUnloaded);
+        Some(*cartridge)
+    }
+    
+    // Put a chip on the cartridge (see cartridge::coprocessor). Chips belong
+    // to the cartridge in, so attach them after loading the ROM; they come
+    // out with it.
+    pub fn attach_coprocessor(&mut self, chip: Box<dyn Coprocessor>) -> Result<()> {
+        info!("Coprocessor attached: {}", chip.name());
+        self.bus.coprocessors_mut().attach(chip)
+    }
+    
+    pub fn coprocessors(&self) -> &Coprocessors {
+        self.bus.coprocessors()
+    }
+    
+    // Point the bus at this instance's cartridge, input, APU and PPU
+    fn connect_bus(&mut self) {
+        self.bus.connect(self.cartridge.as_deref_mut(), &mut self.input, &mut self.apu, &mut self.ppu);
+    }
+    
+    // The system bus, for looking at memory and I/O state without side
+    // effects. Accesses that change anything go through the methods below,
+    // so the bus and its links never leave this instance.
+    pub fn bus(&self) -> &Bus {
+        &self.bus
+    }
+    
+    // Memory as the CPU sees it, with the side effects of a real access
+    // (APU catch-up, RDNMI acknowledge, counter latches, ...)
+    pub fn read8(&mut self, address: u32) -> u8 {
+        self.bus.read8(address)
+    }
+    
+    pub fn write8(&mut self, address: u32, value: u8) {
+        self.bus.write8(address, value);
+    }
+    
+    pub fn read16(&mut self, address: u32) -> u16 {
+        self.bus.read16(address)
+    }
+    
+    pub fn write16(&mut self, address: u32, value: u16) {
+        self.bus.write16(address, value);
+    }
+    
+    // A read without side effects, for debuggers and tools
+    pub fn peek8(&self, address: u32) -> u8 {
+        self.bus.peek8(address)
+    }
+    
+    // Hold the WRAM/SRAM byte at `address` at `value`
+    pub fn freeze(&mut self, address: u32, value: u8) -> Result<()> {
+        self.bus.freeze(address, value)
+    }
+    
+    // Returns true if a freeze was removed
+    pub fn unfreeze(&mut self, address: u32) -> bool {
+        self.bus.unfreeze(address)
+    }
+    
+    // Replace the installed freezes with the enabled cheats; returns how
+    // many were installed
+    pub fn apply_cheats(&mut self, cheats: &CheatList) -> Result<usize> {
+        cheats.apply(&mut self.bus)
+    }
+    
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+    
+    pub fn ppu_mut(&mut self) -> &mut Ppu {
+        &mut self.ppu
+    }
+    
+    pub fn apu(&self) -> &Apu {
+        &self.apu
+    }
+    
+    pub fn apu_mut(&mut self) -> &mut Apu {
+        &mut self.apu
+    }
+    
+    pub fn input(&self) -> &Input {
+        &self.input
+    }
+    
+    pub fn input_mut(&mut self) -> &mut Input {
+        &mut self.input
+    }
+    
+    pub fn cartridge(&self) -> Option<&Cartridge> {
+        self.cartridge.as_deref()
+    }
+    
+    pub fn cartridge_mut(&mut self) -> Option<&mut Cartridge> {
+        self.cartridge.as_deref_mut()
+    }
+
     pub fn reset(&mut self) -> Result<()> {
         debug!("Resetting emulator");
         
@@ -67,9 +367,30 @@ impl Emulator {
         self.ppu.reset();
         self.apu.reset();
         self.dma.reset();
+        self.bus.coprocessors_mut().reset();
         self.cycles = 0;
         self.running = true;
         self.hdma_init_pending = false;
+        self.master_remainder = 0;
+        self.timer_irq = false;
+        self.instructions = 0;
+        self.frame_sram_writes = self.sram_write_count();
+        self.cycle_overrun = 0;
+        self.light_gun_pin = false;
+        self.nmi_line = false;
+        self.bus.set_nmi_flag(false);
+        self.audio_epoch += 1;
+        if let Some(watchdog) = self.desync_watchdog.as_mut() {
+            watchdog.reset();
+        }
+        if let Some(tracker) = self.interrupt_tracker.as_mut() {
+            tracker.reset();
+        }
+        self.input.rumble_mut().stop_all();
+        if let Some(frame_times) = self.frame_times.as_mut() {
+            frame_times.clear();
+        }
+        self.notify_achievements(HookEvent::Reset);
         
         Ok(())
     }
@@ -79,12 +400,20 @@ impl Emulator {
             return Ok(());
         }
 
+        // The emulator may have been moved since the bus was connected
+        // (frontends take it by value), leaving its pointers dangling
+        self.connect_bus();
+
         // Handle DMA register writes
         let dma_enable = self.bus.read8(0x420B);
         if dma_enable != 0 {
-            // Execute DMA transfers
-            let dma_cycles = self.dma.execute_dma(&mut self.bus, &mut self.ppu);
-            self.cycles += dma_cycles as u64;
+            // Execute DMA transfers; the CPU is halted while the PPU keeps running
+            self.dma.write_register(0x420B, dma_enable);
+            let dma_cycles = {
+                let _span = spans::span("dma", "dma");
+                self.dma.execute_dma(&mut self.bus, &mut self.ppu)
+            };
+            self.advance(dma_cycles as u64, 0);
             
             // Clear DMA enable register
             self.bus.write8(0x420B, 0);
@@ -111,47 +440,245 @@ impl Emulator {
             self.dma.write_register(addr as u16, value);
         }
 
-        // Handle PPU register reads/writes through the bus
-        for addr in 0x2100..=0x213F {
-            if self.bus.ppu_register(addr) != 0 {
-                let value = self.bus.ppu_register(addr);
-                self.ppu.write_register(addr, value);
-                self.bus.set_ppu_register(addr, 0); // Clear after handling
+        // Instruction length in master cycles: each bus access costs what its
+        // region costs, remaining cycles are internal operations
+        let (dot, scanline) = (self.ppu.get_current_dot() as u16, self.ppu.get_current_scanline());
+        self.bus.set_beam_position(dot, scanline);
+        self.bus.set_field(self.ppu.field());
+        self.bus.set_vblank_start(self.ppu.vblank_start_scanline());
+        
+        // An aimed Super Scope pulls the I/O pin low while the beam passes its target
+        let pin_low = self.input.light_gun_target().is_some_and(|(x, y)| scanline == y && dot >= x);
+        if pin_low != self.light_gun_pin {
+            self.light_gun_pin = pin_low;
+            self.bus.set_io_pins_low(if pin_low { 0x80 } else { 0 });
+        }
+        if self.apu.port_trace().is_enabled() {
+            self.apu.port_trace_mut().set_cpu_position(self.cycles, self.cpu.registers.pc);
+        }
+        if self.bus.linter().is_some() {
+            let pc = self.cpu.registers.pc;
+            let store = decode_opcode_fast(self.bus.peek8(pc)).is_some_and(|info| info.instruction.is_store());
+            let frame = self.ppu.get_frame_count();
+            if let Some(linter) = self.bus.linter_mut() {
+                linter.begin_instruction(pc, store, frame);
             }
         }
-
+        self.bus.take_access_timing();
+        let pc = self.cpu.registers.pc;
+        // BRK, COP and RTI are seen by their opcode, with BRK and COP's signature
+        let opcode = self.interrupt_tracker.is_some().then(|| {
+            (self.bus.peek8(pc), self.bus.peek8((pc & 0xFF0000) | (pc.wrapping_add(1) & 0xFFFF)))
+        });
+        let port_reads = self.apu.cpu_reads();
         let cpu_cycles = self.cpu.step(&mut self.bus)?;
+        self.instructions += 1;
+        match opcode {
+            Some((0x00, signature)) => self.track_interrupt(InterruptKind::Brk, InterruptSource::Signature(signature), pc),
+            Some((0x02, signature)) => self.track_interrupt(InterruptKind::Cop, InterruptSource::Signature(signature), pc),
+            Some((0x40, _)) => {
+                let (target, frame, scanline) = (self.cpu.registers.pc, self.ppu.get_frame_count(), self.ppu.get_current_scanline());
+                if let Some(tracker) = self.interrupt_tracker.as_mut() {
+                    tracker.returned(pc, target, frame, scanline);
+                }
+            }
+            _ => {}
+        }
+        if self.apu.cpu_reads() != port_reads {
+            if let Some(watchdog) = self.desync_watchdog.as_mut() {
+                watchdog.cpu_polled(pc, self.apu.last_read_port());
+            }
+        }
+        let stack_pointer = self.cpu.registers.s;
+        if let Some(linter) = self.bus.linter_mut() {
+            linter.end_instruction(stack_pointer);
+        }
+        let (accesses, access_cycles) = self.bus.take_access_timing();
+        let master_cycles = access_cycles + cpu_cycles.saturating_sub(accesses) as u64 * timing::IO_CYCLES;
+        
+        let race_dots = if self.quirks.nmi_vblank_race { NMI_RACE_DOTS } else { 0 };
+        let nmi_late = self.advance(master_cycles, race_dots);
+        
+        self.apu.schedule(cpu_cycles);
         
-        // Track current scanline for HDMA
-        let old_scanline = self.ppu.get_current_scanline();
+        // NMI follows RDNMI: enabling it during vblank fires at once unless
+        // the flag was already read. A late NMI is seen only after the next
+        // instruction, so its edge is left for then.
+        // NMIs and IRQs asserted by hand on the CPU go the same way as the
+        // ones they stand in for
+        let nmi_line = self.bus.nmi_flag() && self.bus.nmi_enabled();
+        let vblank_nmi = !nmi_late && nmi_line && !self.nmi_line;
+        if !nmi_late {
+            self.nmi_line = nmi_line;
+        }
+        if self.cpu.take_asserted_nmi() || vblank_nmi {
+            let interrupted = self.cpu.registers.pc;
+            self.cpu.trigger_nmi(&mut self.bus)?;
+            self.track_interrupt(InterruptKind::Nmi, InterruptSource::Vblank, interrupted);
+        }
+        
+        let (ppu_irq, timer_irq, cartridge_irq) = (
+            self.ppu.irq_pending() | self.cpu.is_irq_asserted(IrqSource::Ppu),
+            std::mem::take(&mut self.timer_irq) | self.cpu.is_irq_asserted(IrqSource::Timer),
+            self.bus.coprocessors().irq() | self.cpu.is_irq_asserted(IrqSource::Cartridge),
+        );
+        if ppu_irq || timer_irq || cartridge_irq {
+            if self.cpu.registers.irq_disable() {
+                if let Some(linter) = self.bus.linter_mut() {
+                    linter.report(LintKind::UnservicedIrq, 0);
+                }
+            } else {
+                let interrupted = self.cpu.registers.pc;
+                self.cpu.trigger_irq(&mut self.bus)?;
+                let source = InterruptSource::Irq { timer: timer_irq, ppu: ppu_irq, cartridge: cartridge_irq };
+                self.track_interrupt(InterruptKind::Irq, source, interrupted);
+            }
+        }
+        
+        Ok(())
+    }
+
+    // The CPU went from `pc` into the handler it's now at
+    fn track_interrupt(&mut self, kind: InterruptKind, source: InterruptSource, pc: u32) {
+        let (target, frame, scanline) = (self.cpu.registers.pc, self.ppu.get_frame_count(), self.ppu.get_current_scanline());
+        if let Some(tracker) = self.interrupt_tracker.as_mut() {
+            tracker.entered(kind, source, pc, target, frame, scanline);
+        }
+    }
+
+    // Run the PPU and the per-dot machinery (HDMA, timer IRQ, DRAM refresh)
+    // for `master_cycles`, then the coprocessors for all the cycles that took. Returns true when vblank began within the last
+    // `race_dots` dots, too late for the CPU to see the NMI this instruction.
+    fn advance(&mut self, master_cycles: u64, race_dots: u32) -> bool {
+        let old_frame = self.ppu.get_frame_count();
+        let was_in_vblank = self.ppu.is_in_vblank();
+        let start_cycles = self.cycles;
         
-        for _ in 0..cpu_cycles * 4 {
+        // H/V timer IRQ: $4200 bits 4-5 select the mode, HTIME/VTIME the position
+        let irq_mode = (self.bus.read8(0x4200) >> 4) & 0x03;
+        let htime = u16::from_le_bytes([self.bus.read8(0x4207), self.bus.read8(0x4208)]) & 0x1FF;
+        let vtime = u16::from_le_bytes([self.bus.read8(0x4209), self.bus.read8(0x420A)]) & 0x1FF;
+        let irq_dot = if irq_mode & 0x01 != 0 { htime as u32 } else { 0 } + self.quirks.irq_late_dot as u32;
+        let mut nmi_late = false;
+        
+        self.master_remainder += master_cycles;
+        let own_dots = (self.master_remainder / MASTER_CYCLES_PER_DOT) as u32;
+        self.master_remainder %= MASTER_CYCLES_PER_DOT;
+        self.cycles += master_cycles;
+        
+        // HDMA and the refresh stall extend the run past the caller's own dots
+        let mut dots = own_dots;
+        let mut dot = 0;
+        while dot < dots {
+            let old_scanline = self.ppu.get_current_scanline();
+            let vblank_before = self.ppu.is_in_vblank();
             self.ppu.step(&mut self.bus);
             
             // Check if we crossed a scanline boundary
             let new_scanline = self.ppu.get_current_scanline();
-            if new_scanline != old_scanline && new_scanline < 224 {
-                // Execute HDMA for this scanline
-                let hdma_cycles = self.dma.execute_hdma(&mut self.bus, &mut self.ppu);
-                self.cycles += hdma_cycles as u64;
+            if new_scanline != old_scanline && (new_scanline as usize) < self.ppu.frame_height() {
+                // Execute HDMA for this scanline; the CPU waits while it runs
+                let hdma_cycles = {
+                    let _span = spans::span("hdma", "dma");
+                    self.dma.execute_hdma(&mut self.bus, &mut self.ppu) as u64
+                };
+                self.cycles += hdma_cycles;
+                self.master_remainder += hdma_cycles;
+                dots += (self.master_remainder / MASTER_CYCLES_PER_DOT) as u32;
+                self.master_remainder %= MASTER_CYCLES_PER_DOT;
+            }
+            
+            if irq_mode != 0
+                && self.ppu.get_current_dot() == irq_dot
+                && (irq_mode & 0x02 == 0 || new_scanline == vtime)
+            {
+                self.timer_irq = true;
+            }
+            
+            if vblank_before != self.ppu.is_in_vblank() {
+                self.bus.set_nmi_flag(!vblank_before);
+                if !vblank_before && dot + race_dots >= own_dots && dot < own_dots {
+                    nmi_late = true;
+                }
+            }
+            
+            // DRAM refresh halts the CPU once per scanline
+            if self.quirks.dram_refresh_stall && self.ppu.get_current_dot() == DRAM_REFRESH_DOT {
+                dots += DRAM_REFRESH_DOTS;
+                self.cycles += DRAM_REFRESH_CYCLES;
             }
+            
+            dot += 1;
         }
         
-        for _ in 0..cpu_cycles {
-            self.apu.step();
+        if self.ppu.get_frame_count() != old_frame {
+            self.dma.log_mut().end_frame();
         }
         
-        self.cycles += cpu_cycles as u64;
-        
-        if self.ppu.nmi_pending() {
-            self.cpu.trigger_nmi(&mut self.bus)?;
+        if !self.bus.coprocessors().is_empty() {
+            self.bus.coprocessors_mut().run(self.cycles - start_cycles);
         }
         
-        if self.ppu.irq_pending() {
-            self.cpu.trigger_irq(&mut self.bus)?;
+        if !was_in_vblank && self.ppu.is_in_vblank() && self.bus.auto_joypad_read() {
+            self.joypad_latches += 1;
         }
         
-        Ok(())
+        nmi_late
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.ppu.render_mode()
+    }
+
+    // Dot rendering also latches the H/V counters at the exact dot of the
+    // $2137 read or WRIO write
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.ppu.set_render_mode(mode);
+        self.bus.set_latch_at_access(mode == RenderMode::Dot);
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // Enable hardware edge cases, usually from the game's profile
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        if !quirks.is_empty() {
+            info!("Enabled quirks: {}", quirks.enabled().join(", "));
+        }
+        self.ppu.set_access_restrictions(quirks.ppu_access_restrictions);
+        self.apu.set_fast_boot(quirks.apu_fast_boot);
+        self.apu.set_fake_ack(quirks.apu_fake_ack);
+        self.quirks = quirks;
+    }
+
+    pub fn enhancements(&self) -> Enhancements {
+        self.enhancements
+    }
+
+    // Enable rendering enhancements, usually from the game's profile
+    pub fn set_enhancements(&mut self, enhancements: Enhancements) {
+        if !enhancements.is_empty() {
+            info!("Enabled enhancements: {}", enhancements.enabled().join(", "));
+        }
+        if let Some(cartridge) = &self.cartridge {
+            if enhancements.widescreen > 0 && !enhancements::widescreen_compatible(&cartridge.header.title) {
+                warn!("{} isn't on the widescreen list; its edges may show stray tiles", cartridge.header.title);
+            }
+        }
+        self.ppu.set_mode7_bilinear(enhancements.mode7_bilinear);
+        self.ppu.set_widescreen(enhancements.widescreen_margin());
+        self.enhancements = enhancements;
+    }
+
+    // APU speed relative to the stock console's CPU/APU clock ratio (see
+    // Apu::set_clock_ratio); 1.0 unless set
+    pub fn set_apu_clock_ratio(&mut self, ratio: f64) -> Result<()> {
+        self.apu.set_clock_ratio(ratio)
+    }
+
+    pub fn apu_clock_ratio(&self) -> f64 {
+        self.apu.clock_ratio()
     }
 
     pub fn step_frame(&mut self) -> Result<()> {
@@ -159,26 +686,303 @@ impl Emulator {
             return Ok(());
         }
 
+        // Instant is unavailable on wasm32; the web frontend reports frame times itself
+        #[cfg(not(target_arch = "wasm32"))]
+        let started = self.frame_times.is_some().then(std::time::Instant::now);
         let start_cycles = self.cycles;
-        const CYCLES_PER_FRAME: u64 = 357366; // NTSC: ~21.477MHz / 60fps
+        self.frame_sram_writes = self.sram_write_count();
+        const CYCLES_PER_FRAME: u64 = 1364 * 262; // NTSC: 1364 master cycles per scanline
         
+        let _frame_span = spans::span("frame", "emulator");
         while self.cycles - start_cycles < CYCLES_PER_FRAME {
-            self.step()?;
+            // One span per scanline's worth of CPU steps
+            let _cpu_span = spans::span("cpu", "cpu");
+            let scanline = self.ppu.get_current_scanline();
+            while self.cycles - start_cycles < CYCLES_PER_FRAME && self.ppu.get_current_scanline() == scanline {
+                self.step()?;
+            }
+        }
+        self.finish_frame();
+        
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(started) = started {
+            self.record_frame_time(started.elapsed().as_secs_f64() * 1000.0);
         }
         
         Ok(())
     }
 
+    // Run up to `budget` master cycles, stopping early when a frame
+    // completes, for hosts that schedule emulation themselves. Instructions
+    // and DMA transfers aren't split, so the last one can run past the
+    // budget; the overrun is taken off the next call's budget, which keeps
+    // the total in step with the cycles handed out.
+    pub fn run_cycles(&mut self, budget: u64) -> Result<RanCycles> {
+        let budget = budget.saturating_sub(std::mem::take(&mut self.cycle_overrun));
+        let start_cycles = self.cycles;
+        let start_frame = self.ppu.get_frame_count();
+        let mut frame_completed = false;
+        
+        while self.running && self.cycles - start_cycles < budget {
+            let before = self.cycles;
+            self.step()?;
+            if self.ppu.get_frame_count() != start_frame {
+                self.finish_frame();
+                frame_completed = true;
+                break;
+            }
+            if self.cycles == before {
+                break;
+            }
+        }
+        
+        let cycles = self.cycles - start_cycles;
+        self.cycle_overrun = cycles.saturating_sub(budget);
+        Ok(RanCycles { cycles, frame_completed })
+    }
+
+    // Work done once per frame: settle audio, report SRAM writes, and let
+    // input, rumble and achievements see the finished frame
+    fn finish_frame(&mut self) {
+        // Settle the APU so the frame's audio is complete
+        {
+            let _span = spans::span("apu", "apu");
+            self.apu.catch_up();
+        }
+        self.apu.end_frame();
+        
+        let sram_writes = self.sram_write_count();
+        if sram_writes != std::mem::replace(&mut self.frame_sram_writes, sram_writes) {
+            if let (Some(callback), Some(cartridge)) = (self.sram_write_callback.as_mut(), self.cartridge.as_ref()) {
+                callback(&cartridge.sram);
+            }
+        }
+        
+        if let Some(watchdog) = self.desync_watchdog.as_mut() {
+            watchdog.end_frame(self.ppu.get_frame_count(), self.apu.peek_ports(), self.apu.spc700().pc());
+        }
+        let frame = self.ppu.get_frame_count();
+        if let Some(heatmap) = self.bus.heatmap_mut() {
+            heatmap.end_frame(frame);
+        }
+        
+        let (polled_latch, software_latch) = self.bus.take_counter_polls();
+        self.input.end_frame(polled_latch, software_latch);
+        
+        // Rumble effects are timed in emulated frames
+        self.input.rumble_mut().end_frame();
+        let bus = &self.bus;
+        self.input.rumble_mut().poll_triggers(|address| bus.peek8(address));
+        
+        if let Some(hook) = self.achievement_hook.as_mut() {
+            self.achievement_poll.poll(&self.bus);
+            hook.on_frame(self.ppu.get_frame_count(), &self.achievement_poll, &MemoryPeek::new(&self.bus));
+        }
+    }
+
+    // Attach controller port devices from a game profile
+    pub fn set_peripherals(&mut self, config: &PeripheralConfig) {
+        let title = self.get_rom_info().map(|info| info.title).unwrap_or_default();
+        self.input.configure_peripherals(config, &title);
+    }
+
+    // Counters since reset, for speed displays and profiling
+    pub fn metrics(&self) -> Metrics {
+        Metrics {
+            master_cycles: self.cycles,
+            instructions: self.instructions,
+            frames: self.ppu.get_frame_count(),
+            dma_bytes: self.dma.bytes_transferred(),
+            frame_times: self.frame_times.clone(),
+        }
+    }
+
+    // Collect a histogram of host time spent per step_frame
+    pub fn set_frame_timing(&mut self, enabled: bool) {
+        if enabled != self.frame_times.is_some() {
+            self.frame_times = enabled.then(FrameTimeHistogram::new);
+        }
+    }
+
+    // Add a frame time measured by the frontend (ignored unless frame
+    // timing is enabled)
+    pub fn record_frame_time(&mut self, ms: f64) {
+        if let Some(frame_times) = self.frame_times.as_mut() {
+            frame_times.record(ms);
+        }
+    }
+
     pub fn set_controller_input(&mut self, player: u8, buttons: u16) {
         self.input.set_controller_state(player, buttons);
     }
+    
+    // Handle for updating the pads at any time, e.g. from an input thread;
+    // the state is latched when the game reads the pads rather than when
+    // step_frame starts. set_controller_input keeps working alongside it.
+    pub fn live_pads(&mut self) -> LivePads {
+        if let Some(pads) = self.input.live_pads() {
+            return pads.clone();
+        }
+        let pads = LivePads::new();
+        for player in 0..2 {
+            pads.set(player, self.input.get_controller_state(player));
+        }
+        self.input.set_live_pads(Some(pads.clone()));
+        pads
+    }
+    
+    pub fn detach_live_pads(&mut self) {
+        self.input.set_live_pads(None);
+    }
+
+    // Hold exactly `buttons` on controller `player` (0 or 1)
+    pub fn set_buttons(&mut self, player: u8, buttons: &[Button]) {
+        self.set_controller_input(player, Button::pad_bits(buttons));
+    }
+
+    // Total SRAM writes since the ROM was loaded
+    pub fn sram_write_count(&self) -> u64 {
+        self.cartridge.as_ref().map_or(0, |cartridge| cartridge.sram_write_count())
+    }
+
+    // Install an achievement runtime; its addresses are read after every
+    // frame. Replaces any hook already installed.
+    pub fn set_achievement_hook(&mut self, hook: Box<dyn AchievementHook>) {
+        self.achievement_poll = MemoryPoll::new(hook.addresses());
+        self.achievement_poll.prime(&self.bus);
+        self.achievement_hook = Some(hook);
+    }
+
+    pub fn take_achievement_hook(&mut self) -> Option<Box<dyn AchievementHook>> {
+        self.achievement_poll = MemoryPoll::default();
+        self.achievement_hook.take()
+    }
+
+    // Re-read the hook's address list, e.g. after it loaded another set
+    pub fn refresh_achievement_addresses(&mut self) {
+        if let Some(hook) = self.achievement_hook.as_ref() {
+            self.achievement_poll = MemoryPoll::new(hook.addresses());
+            self.achievement_poll.prime(&self.bus);
+        }
+    }
+
+    fn notify_achievements(&mut self, event: HookEvent) {
+        if let Some(hook) = self.achievement_hook.as_mut() {
+            self.achievement_poll.prime(&self.bus);
+            hook.on_event(event);
+        }
+    }
+
+    // Register a handler run at the end of every frame that wrote to SRAM,
+    // receiving the current SRAM contents
+    pub fn on_sram_write<F: FnMut(&[u8]) + Send + 'static>(&mut self, callback: F) {
+        self.sram_write_callback = Some(Box::new(callback));
+    }
+
+    // Counts auto-joypad reads; frontends compare it across frames to see
+    // when host input was latched by the game
+    pub fn joypad_latch_count(&self) -> u64 {
+        self.joypad_latches
+    }
 
     pub fn get_video_buffer(&self) -> &[u8] {
         self.ppu.get_frame_buffer()
     }
 
     pub fn get_audio_samples(&mut self) -> Vec<f32> {
-        self.apu.get_audio_samples()
+        let mut samples = self.apu.get_audio_samples();
+        self.volume.apply(&mut samples);
+        samples
+    }
+    
+    // Output volume from 0.0 to 2.0 (apu::volume::MAX_VOLUME), 1.0 being
+    // the DSP's own level; kept across resets and ROM loads
+    pub fn set_volume(&mut self, level: f32) {
+        self.volume.set_level(level);
+    }
+    
+    pub fn volume(&self) -> f32 {
+        self.volume.level()
+    }
+    
+    pub fn set_muted(&mut self, muted: bool) {
+        self.volume.set_muted(muted);
+    }
+    
+    pub fn is_muted(&self) -> bool {
+        self.volume.is_muted()
+    }
+    
+    // Output filter, interpolation and voice gains from the audio config;
+    // the volume is left to whoever plays the samples
+    pub fn apply_audio_config(&mut self, config: &AudioConfig) {
+        self.apu.set_output_filter(config.low_pass_filter.then_some(config.low_pass_cutoff_hz));
+        let dsp = self.apu.dsp_mut();
+        dsp.set_interpolation(if config.gaussian_interpolation { Interpolation::Gaussian } else { Interpolation::Linear });
+        for (voice, &gain) in config.voice_gain.iter().enumerate() {
+            dsp.set_voice_gain(voice, gain);
+        }
+    }
+
+    pub fn frame(&self) -> Frame<'_> {
+        Frame {
+            pixels: self.ppu.get_frame_buffer(),
+            format: FRAME_BUFFER_FORMAT,
+            width: SCREEN_WIDTH,
+            height: self.ppu.frame_height(),
+            number: self.ppu.get_frame_count(),
+            timestamp: self.cycles as f64 / MASTER_CLOCK_HZ,
+        }
+    }
+
+    // The picture with the widescreen enhancement's columns on both sides;
+    // the same as frame() while it's off
+    pub fn widescreen_frame(&mut self) -> Frame<'_> {
+        Frame {
+            width: self.ppu.widescreen_width(),
+            height: self.ppu.frame_height(),
+            number: self.ppu.get_frame_count(),
+            timestamp: self.cycles as f64 / MASTER_CLOCK_HZ,
+            format: FRAME_BUFFER_FORMAT,
+            pixels: self.ppu.get_widescreen_frame_buffer(),
+        }
+    }
+
+    // Run a frame and hand the picture and the sound made during it to
+    // a frontend's sinks
+    pub fn run_frame_to(&mut self, video: &mut dyn VideoSink, audio: &mut dyn AudioSink) -> Result<()> {
+        self.step_frame()?;
+        video.present_frame(&self.frame())?;
+        audio.queue_audio(&self.take_audio())
+    }
+
+    // Stable hash of the picture (see frame_hash for the algorithm), for
+    // harnesses that compare runs without copying frames; after step_frame
+    // it is the hash of that frame
+    pub fn frame_hash(&self) -> u64 {
+        frame_hash::hash_frame(SCREEN_WIDTH, self.ppu.frame_height(), self.ppu.get_frame_buffer())
+    }
+
+    // Stable hash of the samples the APU generated during the last finished
+    // frame, before the volume; unaffected by when the frontend takes them
+    pub fn audio_hash(&self) -> u64 {
+        self.apu.frame_audio_hash()
+    }
+
+    // Samples generated since the last call
+    pub fn take_audio(&mut self) -> AudioChunk {
+        AudioChunk {
+            samples: self.get_audio_samples(),
+            sample_rate: AUDIO_SAMPLE_RATE,
+            channels: 1,
+            epoch: self.audio_epoch,
+        }
+    }
+
+    // Compare between frames: a change means the next samples should be
+    // blended in rather than appended (see apu::reconcile)
+    pub fn audio_epoch(&self) -> u64 {
+        self.audio_epoch
     }
 
     pub fn is_running(&self) -> bool {
@@ -199,9 +1003,12 @@ impl Emulator {
         
         // Save CPU state
         state.cpu = self.cpu.save_state();
+        state.cpu.nmi_line = self.nmi_line;
         
         // Save PPU state
         state.ppu = self.ppu.save_state();
+        state.ppu.latches.counters = self.bus.counter_latch().save_state();
+        state.ppu.nmi_flag = self.bus.nmi_flag();
         
         // Save APU state
         state.apu = self.apu.save_state();
@@ -214,16 +1021,25 @@ impl Emulator {
         
         // Save emulator state
         state.cycles = self.cycles;
+        state.memory_init = self.memory_init;
+        state.coprocessors = self.bus.coprocessors().save_state();
         
         Ok(state)
     }
     
     pub fn load_state(&mut self, state: &SaveState) -> Result<()> {
+        // Coprocessors first: a state saved with other chips attached is
+        // refused before anything else changes
+        self.bus.coprocessors_mut().load_state(&state.coprocessors)?;
+        
         // Load CPU state
         self.cpu.load_state(&state.cpu);
+        self.nmi_line = state.cpu.nmi_line;
         
         // Load PPU state
         self.ppu.load_state(&state.ppu);
+        self.bus.counter_latch().load_state(&state.ppu.latches.counters);
+        self.bus.set_nmi_flag(state.ppu.nmi_flag);
         
         // Load APU state
         self.apu.load_state(&state.apu);
@@ -236,10 +1052,103 @@ impl Emulator {
         
         // Load emulator state
         self.cycles = state.cycles;
+        // Memory itself comes from the state; only later power-ons use this
+        self.memory_init = state.memory_init;
+        self.finish_state_load();
         
         Ok(())
     }
     
+    // Load what could be read of a damaged state or one from another
+    // version (see SaveState::recover). Sections that checked out are
+    // loaded and the rest reset as on power-on, except that the
+    // cartridge's SRAM is kept. Errors only when nothing could be read.
+    pub fn load_partial_state(&mut self, state: &PartialState) -> Result<StateRecovery> {
+        let mut recovery = state.recovery();
+        if recovery.recovered.is_empty() {
+            return Err(EmulatorError::save_state(format!("Nothing in the save state could be read: {}", recovery)));
+        }
+        
+        match &state.cpu {
+            Some(cpu) => {
+                self.cpu.load_state(cpu);
+                self.nmi_line = cpu.nmi_line;
+            }
+            None => {
+                self.cpu.reset(&mut self.bus)?;
+                self.nmi_line = false;
+            }
+        }
+        match &state.ppu {
+            Some(ppu) => {
+                self.ppu.load_state(ppu);
+                self.bus.counter_latch().load_state(&ppu.latches.counters);
+                self.bus.set_nmi_flag(ppu.nmi_flag);
+            }
+            None => {
+                self.ppu.reset();
+                self.bus.set_nmi_flag(false);
+            }
+        }
+        match &state.apu {
+            Some(apu) => self.apu.load_state(apu),
+            None => self.apu.reset(),
+        }
+        match state.timing {
+            Some((cycles, memory_init)) => {
+                self.cycles = cycles;
+                self.memory_init = memory_init;
+            }
+            None => self.cycles = 0,
+        }
+        match &state.memory {
+            Some(memory) => {
+                if let Err(e) = self.bus.load_memory_state(memory) {
+                    recovery.fail(StateSection::Memory, e.to_string());
+                    self.bus.init_wram(self.memory_init);
+                }
+            }
+            None => self.bus.init_wram(self.memory_init),
+        }
+        match &state.dma {
+            Some(dma) => self.dma.load_state(dma),
+            None => self.dma.reset(),
+        }
+        match &state.coprocessors {
+            Some(coprocessors) => {
+                if let Err(e) = self.bus.coprocessors_mut().load_state(coprocessors) {
+                    recovery.fail(StateSection::Coprocessors, e.to_string());
+                    self.bus.coprocessors_mut().reset();
+                }
+            }
+            None => self.bus.coprocessors_mut().reset(),
+        }
+        self.finish_state_load();
+        
+        if !recovery.is_complete() {
+            warn!("Save state partly recovered: {}", recovery);
+        }
+        Ok(recovery)
+    }
+    
+    // What every state load ends with, after the sections are in
+    fn finish_state_load(&mut self) {
+        self.cycle_overrun = 0;
+        self.ppu.set_memory_init_for_reset(self.memory_init);
+        
+        // Samples still buffered in the state were already played when it
+        // was saved; drop them and let the output blend across the jump
+        self.apu.get_audio_samples();
+        self.audio_epoch += 1;
+        if let Some(watchdog) = self.desync_watchdog.as_mut() {
+            watchdog.reset();
+        }
+        if let Some(tracker) = self.interrupt_tracker.as_mut() {
+            tracker.reset();
+        }
+        self.notify_achievements(HookEvent::StateLoaded);
+    }
+    
     pub fn save_state_to_file(&self, path: &str) -> Result<()> {
         let state = self.save_state()?;
         state.save_to_file(path)?;
@@ -254,6 +1163,29 @@ impl Emulator {
         Ok(())
     }
     
+    // Load a state file as far as it can be read; the report says what was
+    // reset instead. A whole state of this version loads as with
+    // load_state_from_file.
+    pub fn recover_state_from_file(&mut self, path: &str) -> Result<StateRecovery> {
+        let state = SaveState::recover(&std::fs::read(path)?, StateEncoding::Bincode);
+        let recovery = self.load_partial_state(&state)?;
+        info!("Save state loaded from: {}", path);
+        Ok(recovery)
+    }
+    
+    // The sound hardware as a .spc file, tagged with the ROM title
+    pub fn export_spc(&mut self) -> Vec<u8> {
+        self.apu.catch_up();
+        let title = self.get_rom_info().map(|info| info.title).unwrap_or_default();
+        spc_file::export(&self.apu, &SpcTags::for_game(&title))
+    }
+    
+    pub fn export_spc_to_file(&mut self, path: &str) -> Result<()> {
+        std::fs::write(path, self.export_spc())?;
+        info!("SPC exported to: {}", path);
+        Ok(())
+    }
+    
     // Information and stats methods
     pub fn get_rom_info(&self) -> Option<crate::cartridge::header::RomInfo> {
         if let Some(cartridge) = self.cartridge.as_ref() {
@@ -263,6 +1195,89 @@ impl Emulator {
         }
     }
     
+    // Header checksum and size problems of the loaded ROM; see
+    // integrity::RomDatabase for checking against known dumps
+    pub fn rom_warnings(&self) -> &[IntegrityWarning] {
+        &self.rom_warnings
+    }
+    
+    // Lint mode (see debug::lint); WRAM reads are checked from the next
+    // ROM load, which powers the console on
+    pub fn set_lint(&mut self, enabled: bool) {
+        self.bus.set_lint(enabled);
+    }
+    
+    pub fn lint_enabled(&self) -> bool {
+        self.bus.linter().is_some()
+    }
+    
+    // Findings so far, oldest first
+    pub fn lints(&self) -> Vec<Lint> {
+        self.bus.linter().map(|linter| linter.lints().to_vec()).unwrap_or_default()
+    }
+    
+    pub fn clear_lints(&mut self) {
+        if let Some(linter) = self.bus.linter_mut() {
+            linter.clear();
+        }
+    }
+    
+    // Count WRAM (and with `vram`, VRAM port) accesses per address in
+    // windows of `window_frames` frames, 0 for one window until cleared
+    // (see debug::heatmap). Starting again clears the counts.
+    pub fn start_heatmap(&mut self, window_frames: u32, vram: bool) {
+        self.bus.set_heatmap(Some(Heatmap::new(window_frames, vram, self.get_frame_count())));
+    }
+    
+    pub fn stop_heatmap(&mut self) {
+        self.bus.set_heatmap(None);
+    }
+    
+    pub fn heatmap(&self) -> Option<Ref<'_, Heatmap>> {
+        self.bus.heatmap()
+    }
+    
+    pub fn clear_heatmap(&mut self) {
+        let frame = self.get_frame_count();
+        if let Some(heatmap) = self.bus.heatmap_mut() {
+            heatmap.clear(frame);
+        }
+    }
+    
+    // Watch for the CPU polling APU ports that stopped answering for
+    // `frames` frames (see debug::watchdog); None turns it off. On by
+    // default with DEFAULT_DESYNC_FRAMES.
+    pub fn set_desync_watchdog(&mut self, frames: Option<u32>) {
+        self.desync_watchdog = frames.map(DesyncWatchdog::new);
+    }
+    
+    pub fn desync_watchdog(&self) -> Option<u32> {
+        self.desync_watchdog.as_ref().map(DesyncWatchdog::threshold)
+    }
+    
+    // Record each NMI, IRQ, BRK, COP and RTI (see debug::interrupts); off
+    // by default, since it looks at every opcode
+    pub fn set_interrupt_tracking(&mut self, enabled: bool) {
+        if enabled != self.interrupt_tracker.is_some() {
+            self.interrupt_tracker = enabled.then(InterruptTracker::new);
+        }
+    }
+    
+    pub fn interrupt_tracker(&self) -> Option<&InterruptTracker> {
+        self.interrupt_tracker.as_ref()
+    }
+    
+    // Interrupt events since the last call, oldest first
+    pub fn take_interrupts(&mut self) -> Vec<InterruptEvent> {
+        self.interrupt_tracker.as_mut().map(InterruptTracker::take_events).unwrap_or_default()
+    }
+    
+    // The CPU/APU stall in progress, once the watchdog fired for it; cleared
+    // when the ports move again
+    pub fn desync(&self) -> Option<&DesyncReport> {
+        self.desync_watchdog.as_ref().and_then(DesyncWatchdog::report)
+    }
+    
     pub fn get_cycle_count(&self) -> u64 {
         self.cycles
     }
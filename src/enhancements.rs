@@ -0,0 +1,62 @@
+// Opt-in enhancements: output the real console can't produce, for looks
+// rather than accuracy. Like quirks they are off by default and enabled
+// per game through its profile, since each suits some games and spoils
+// others.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Enhancements {
+    // Filter the Mode 7 plane between texels by the fractions of its
+    // texture coordinates, which smooths scaled and rotated floors
+    pub mode7_bilinear: bool,
+    
+    // Pixels drawn past each side of the 256-pixel picture, continuing the
+    // backgrounds' tilemaps and the Mode 7 plane, up to MAX_WIDESCREEN;
+    // 0 is off. Sprites, windows and color math stay within the 256.
+    pub widescreen: u16,
+}
+
+// 64 pixels a side make a 384-pixel picture, 16:9 at 224 lines with the
+// console's 8:7 pixels
+pub const MAX_WIDESCREEN: u16 = 64;
+
+// Games known to look right with widescreen, by header title prefix: Mode 7
+// racers and flyers, and scrollers that keep their tilemaps filled past the
+// screen edges
+const WIDESCREEN_GAMES: &[&str] = &[
+    "SUPER MARIOKART",
+    "F-ZERO",
+    "PILOTWINGS",
+    "SUPER MARIOWORLD",
+];
+
+// Whether a game is on the widescreen list, from its header title. Others
+// may show tiles the game never meant to be seen at the edges.
+pub fn widescreen_compatible(title: &str) -> bool {
+    let title = title.trim().to_ascii_uppercase();
+    WIDESCREEN_GAMES.iter().any(|prefix| title.starts_with(prefix))
+}
+
+impl Enhancements {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+    
+    // Pixels added on each side of the picture, limited to MAX_WIDESCREEN
+    pub fn widescreen_margin(&self) -> usize {
+        self.widescreen.min(MAX_WIDESCREEN) as usize
+    }
+
+    // Names of the enabled enhancements, for logging
+    pub fn enabled(&self) -> Vec<&'static str> {
+        [
+            ("mode7_bilinear", self.mode7_bilinear),
+            ("widescreen", self.widescreen > 0),
+        ]
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect()
+    }
+}
@@ -0,0 +1,71 @@
+// Stable hashes of the emulator's output, for test harnesses and CI
+// scripts that assert on a run without moving whole frames around.
+//
+// The algorithm is part of the API and must not change: 64-bit FNV-1a
+// (offset basis 0xCBF29CE484222325, prime 0x100000001B3, each byte XORed
+// in and then multiplied) over
+//
+//   frame: width and height as u32 little-endian, then the RGBA8 pixels
+//          top row first (Emulator::frame's buffer, no padding)
+//   audio: every sample the APU generated during the frame, before the
+//          output volume, as i16 little-endian PCM (the f32 sample times
+//          32767, rounded and clamped)
+//
+// so hashes recorded by one build or target match any other that produces
+// the same pixels and sound. Nothing here is cryptographic.
+
+pub const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+pub const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+// Incremental FNV-1a; `finish` can be called at any point
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHasher(u64);
+
+impl FrameHasher {
+    pub fn new() -> Self {
+        Self(FNV_OFFSET_BASIS)
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn update_sample(&mut self, sample: f32) {
+        self.update(&sample_to_pcm(sample).to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for FrameHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Hash of a `width` by `height` RGBA8 picture
+pub fn hash_frame(width: usize, height: usize, rgba: &[u8]) -> u64 {
+    let mut hasher = FrameHasher::new();
+    hasher.update(&(width as u32).to_le_bytes());
+    hasher.update(&(height as u32).to_le_bytes());
+    hasher.update(rgba);
+    hasher.finish()
+}
+
+// Hash of one frame's samples
+pub fn hash_audio(samples: &[f32]) -> u64 {
+    let mut hasher = FrameHasher::new();
+    for &sample in samples {
+        hasher.update_sample(sample);
+    }
+    hasher.finish()
+}
+
+// An APU sample as 16-bit PCM
+pub fn sample_to_pcm(sample: f32) -> i16 {
+    (sample * i16::MAX as f32).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+}
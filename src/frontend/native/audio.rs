@@ -1,60 +1,216 @@
 use crate::{Result, EmulatorError};
+use crate::apu::reconcile::AudioReconciler;
+use crate::apu::volume::Volume;
+use crate::config::AudioConfig;
+use crate::embed::AudioChunk;
+use crate::sink::AudioSink;
 use cpal::{traits::{DeviceTrait, HostTrait, StreamTrait}, Stream};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use log::{info, warn};
 
 const SAMPLE_RATE: u32 = 32000;
 const BUFFER_SIZE: usize = 2048;
 
+// Minimum time between attempts to reopen a lost output device
+const RECOVERY_INTERVAL: Duration = Duration::from_secs(1);
+
+// Output stream settings, usually built from AudioConfig
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioOptions {
+    // cpal host name (e.g. "ALSA", "JACK", "WASAPI"); None uses the default host
+    pub backend: Option<String>,
+
+    // Output device name; None uses the host's default device
+    pub device: Option<String>,
+
+    // Target output latency in milliseconds (0 lets the backend decide)
+    pub latency_ms: u32,
+
+    // Request exclusive device access where the backend supports it
+    pub exclusive: bool,
+}
+
+impl From<&AudioConfig> for AudioOptions {
+    fn from(config: &AudioConfig) -> Self {
+        Self {
+            backend: config.backend.clone(),
+            device: config.device.clone(),
+            latency_ms: config.latency_ms,
+            exclusive: config.exclusive_mode,
+        }
+    }
+}
+
 pub struct AudioPlayer {
     stream: Stream,
     sample_buffer: Arc<Mutex<VecDeque<f32>>>,
+    options: AudioOptions,
+
+    // Set by the stream error callback when the device disappears
+    stream_lost: Arc<AtomicBool>,
+    last_recovery: Option<Instant>,
+
+    // Queue limit in samples, derived from the requested latency
+    max_buffered: usize,
+
+    // Blends across state loads and steers the queue towards half full
+    reconciler: AudioReconciler,
+
+    // Gain applied to queued samples
+    volume: Volume,
+
+    // Epoch of the last chunk queued through AudioSink
+    epoch: Option<u64>,
 }
 
 impl AudioPlayer {
     pub fn new() -> Result<Self> {
-        let host = cpal::default_host();
-        
-        let device = host.default_output_device()
-            .ok_or_else(|| EmulatorError::AudioError("No output device available".to_string()))?;
-        
-        let config = device.default_output_config()
-            .map_err(|e| EmulatorError::AudioError(format!("Failed to get default config: {}", e)))?;
-        
+        Self::with_options(AudioOptions::default())
+    }
+
+    pub fn with_options(options: AudioOptions) -> Result<Self> {
         let sample_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(BUFFER_SIZE * 4)));
-        let buffer_clone = Arc::clone(&sample_buffer);
-        
-        let stream = match config.sample_format() {
-            cpal::SampleFormat::F32 => Self::build_stream::<f32>(&device, &config.into(), buffer_clone),
-            cpal::SampleFormat::I16 => Self::build_stream::<i16>(&device, &config.into(), buffer_clone),
-            cpal::SampleFormat::U16 => Self::build_stream::<u16>(&device, &config.into(), buffer_clone),
-            sample_format => return Err(EmulatorError::AudioError(format!("Unsupported sample format: {:?}", sample_format))),
-        }?;
-        
-        stream.play()
-            .map_err(|e| EmulatorError::AudioError(format!("Failed to play stream: {}", e)))?;
-        
+        let stream_lost = Arc::new(AtomicBool::new(false));
+
+        let stream = Self::open_stream(&options, &sample_buffer, &stream_lost)?;
+
+        // Keep roughly twice the requested latency queued at the emulator's rate
+        let latency_samples = (SAMPLE_RATE * options.latency_ms / 1000) as usize * 2;
+        let max_buffered = if options.latency_ms == 0 {
+            BUFFER_SIZE * 8
+        } else {
+            (latency_samples * 2).max(BUFFER_SIZE)
+        };
+
         Ok(Self {
             stream,
             sample_buffer,
+            options,
+            stream_lost,
+            last_recovery: None,
+            max_buffered,
+            reconciler: AudioReconciler::new(),
+            volume: Volume::default(),
+            epoch: None,
         })
     }
-    
+
+    // Names of the audio hosts compiled into this build
+    pub fn available_backends() -> Vec<&'static str> {
+        cpal::available_hosts().into_iter().map(|id| id.name()).collect()
+    }
+
+    // Names of the output devices on a backend (default backend when None)
+    pub fn output_device_names(backend: Option<&str>) -> Result<Vec<String>> {
+        let host = Self::select_host(backend)?;
+        let devices = host.output_devices()
+            .map_err(|e| EmulatorError::AudioError(format!("Failed to enumerate devices: {}", e)))?;
+        Ok(devices.filter_map(|device| device.name().ok()).collect())
+    }
+
+    fn select_host(backend: Option<&str>) -> Result<cpal::Host> {
+        let Some(name) = backend else {
+            return Ok(cpal::default_host());
+        };
+
+        let id = cpal::available_hosts()
+            .into_iter()
+            .find(|id| id.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| EmulatorError::AudioError(format!(
+                "Unknown audio backend '{}' (available: {})",
+                name,
+                Self::available_backends().join(", "),
+            )))?;
+
+        cpal::host_from_id(id)
+            .map_err(|e| EmulatorError::AudioError(format!("Audio backend '{}' unavailable: {}", name, e)))
+    }
+
+    fn select_device(host: &cpal::Host, name: Option<&str>) -> Result<cpal::Device> {
+        if let Some(name) = name {
+            let found = host.output_devices()
+                .ok()
+                .and_then(|mut devices| devices.find(|d| d.name().map(|n| n == name).unwrap_or(false)));
+
+            match found {
+                Some(device) => return Ok(device),
+                None => warn!("Audio device '{}' not found, falling back to default device", name),
+            }
+        }
+
+        host.default_output_device()
+            .ok_or_else(|| EmulatorError::AudioError("No output device available".to_string()))
+    }
+
+    fn open_stream(
+        options: &AudioOptions,
+        buffer: &Arc<Mutex<VecDeque<f32>>>,
+        lost: &Arc<AtomicBool>,
+    ) -> Result<Stream> {
+        let host = Self::select_host(options.backend.as_deref())?;
+        let device = Self::select_device(&host, options.device.as_deref())?;
+
+        let supported = device.default_output_config()
+            .map_err(|e| EmulatorError::AudioError(format!("Failed to get default config: {}", e)))?;
+
+        if options.exclusive {
+            // cpal only opens shared-mode streams
+            warn!("Exclusive audio mode is not supported by the {} backend, using shared mode", host.id().name());
+        }
+
+        let mut config: cpal::StreamConfig = supported.config();
+        if options.latency_ms > 0 {
+            let frames = config.sample_rate.0 * options.latency_ms / 1000;
+            let frames = match supported.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => frames.clamp(*min, *max),
+                cpal::SupportedBufferSize::Unknown => frames,
+            };
+            config.buffer_size = cpal::BufferSize::Fixed(frames);
+        }
+
+        info!(
+            "Audio output: {} on {} ({} Hz, {} channels)",
+            device.name().unwrap_or_else(|_| "unknown device".to_string()),
+            host.id().name(),
+            config.sample_rate.0,
+            config.channels,
+        );
+
+        let buffer_clone = Arc::clone(buffer);
+        let lost_clone = Arc::clone(lost);
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => Self::build_stream::<f32>(&device, &config, buffer_clone, lost_clone),
+            cpal::SampleFormat::I16 => Self::build_stream::<i16>(&device, &config, buffer_clone, lost_clone),
+            cpal::SampleFormat::U16 => Self::build_stream::<u16>(&device, &config, buffer_clone, lost_clone),
+            sample_format => return Err(EmulatorError::AudioError(format!("Unsupported sample format: {:?}", sample_format))),
+        }?;
+
+        stream.play()
+            .map_err(|e| EmulatorError::AudioError(format!("Failed to play stream: {}", e)))?;
+
+        lost.store(false, Ordering::Release);
+        Ok(stream)
+    }
+
     fn build_stream<T>(
         device: &cpal::Device,
         config: &cpal::StreamConfig,
         buffer: Arc<Mutex<VecDeque<f32>>>,
+        lost: Arc<AtomicBool>,
     ) -> Result<Stream>
     where
         T: cpal::Sample + cpal::SizedSample + cpal::FromSample<f32>,
     {
         let channels = config.channels as usize;
-        
+
         let stream = device.build_output_stream(
             config,
             move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
                 let mut buffer = buffer.lock().unwrap();
-                
+
                 for frame in data.chunks_mut(channels) {
                     if buffer.len() >= channels {
                         // We have stereo samples
@@ -76,38 +232,100 @@ impl AudioPlayer {
             },
             move |err| {
                 eprintln!("Audio stream error: {}", err);
+                if let cpal::StreamError::DeviceNotAvailable = err {
+                    lost.store(true, Ordering::Release);
+                }
             },
             None,
         ).map_err(|e| EmulatorError::AudioError(format!("Failed to build output stream: {}", e)))?;
-        
+
         Ok(stream)
     }
-    
+
+    // Reopen the output stream if the device disappeared. Call once per frame;
+    // returns true when a new stream was opened.
+    pub fn poll_device(&mut self) -> Result<bool> {
+        if !self.stream_lost.load(Ordering::Acquire) {
+            return Ok(false);
+        }
+
+        if let Some(last) = self.last_recovery {
+            if last.elapsed() < RECOVERY_INTERVAL {
+                return Ok(false);
+            }
+        }
+        self.last_recovery = Some(Instant::now());
+
+        warn!("Audio device lost, reopening output stream");
+        self.stream = Self::open_stream(&self.options, &self.sample_buffer, &self.stream_lost)?;
+        self.clear_buffer();
+        Ok(true)
+    }
+
+    pub fn is_device_lost(&self) -> bool {
+        self.stream_lost.load(Ordering::Acquire)
+    }
+
+    pub fn options(&self) -> &AudioOptions {
+        &self.options
+    }
+
+    // Applies from the next queued samples
+    pub fn set_volume(&mut self, volume: Volume) {
+        self.volume = volume;
+    }
+
+    pub fn volume(&self) -> Volume {
+        self.volume
+    }
+
     pub fn queue_samples(&mut self, samples: &[f32]) {
+        let mut samples = samples.to_vec();
+        self.volume.apply(&mut samples);
         let mut buffer = self.sample_buffer.lock().unwrap();
-        
-        // Don't let the buffer grow too large
-        let max_size = BUFFER_SIZE * 8;
+
+        // Don't let the buffer grow past the latency target
+        let max_size = self.max_buffered;
         if buffer.len() + samples.len() > max_size {
             // Drop old samples if buffer is getting too full
-            let to_drop = (buffer.len() + samples.len()) - max_size;
-            for _ in 0..to_drop {
-                buffer.pop_front();
-            }
+            let to_drop = ((buffer.len() + samples.len()) - max_size).min(buffer.len());
+            buffer.drain(..to_drop);
         }
-        
+
         // Queue new samples
-        for &sample in samples {
-            buffer.push_back(sample);
-        }
+        self.reconciler.queue(&mut buffer, &samples, max_size / 2);
     }
-    
+
+    // The next queued samples don't follow on from the queued ones (state
+    // load, rewind), so crossfade them in instead of clearing the queue
+    pub fn mark_discontinuity(&mut self) {
+        self.reconciler.discontinuity();
+    }
+
     pub fn clear_buffer(&mut self) {
         let mut buffer = self.sample_buffer.lock().unwrap();
         buffer.clear();
     }
-    
+
     pub fn get_buffer_size(&self) -> usize {
         self.sample_buffer.lock().unwrap().len()
     }
-}
\ No newline at end of file
+
+    // Queued samples relative to the latency limit (0.0-1.0)
+    pub fn buffer_fill(&self) -> f32 {
+        if self.max_buffered == 0 {
+            return 0.0;
+        }
+        (self.get_buffer_size() as f32 / self.max_buffered as f32).min(1.0)
+    }
+}
+
+impl AudioSink for AudioPlayer {
+    fn queue_audio(&mut self, chunk: &AudioChunk) -> Result<()> {
+        if self.epoch.replace(chunk.epoch).is_some_and(|epoch| epoch != chunk.epoch) {
+            self.mark_discontinuity();
+        }
+        self.queue_samples(&chunk.samples);
+        Ok(())
+    }
+}
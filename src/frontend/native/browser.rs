@@ -0,0 +1,133 @@
+// Launcher list for the ROM library, drawn with the OSD font
+use super::osd::{self, CHAR_ADVANCE};
+use crate::library::RomLibrary;
+use std::path::PathBuf;
+
+const WIDTH: usize = 256;
+const HEIGHT: usize = 224;
+
+const LIST_TOP: usize = 18;
+const ROW_HEIGHT: usize = 10;
+pub const VISIBLE_ROWS: usize = 19;
+
+const BACKGROUND: [u8; 4] = [0x10, 0x14, 0x28, 0xFF];
+const HIGHLIGHT: [u8; 4] = [0x30, 0x48, 0x98, 0xFF];
+const TEXT: [u8; 4] = [0xE8, 0xE8, 0xE8, 0xFF];
+const DIM: [u8; 4] = [0x88, 0x90, 0xA8, 0xFF];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrowserAction {
+    None,
+    Launch(PathBuf),
+    Quit,
+}
+
+pub struct LibraryBrowser {
+    library: RomLibrary,
+    selected: usize,
+    // First visible row
+    scroll: usize,
+    frame: Vec<u8>,
+}
+
+impl LibraryBrowser {
+    pub fn new(library: RomLibrary) -> Self {
+        Self {
+            library,
+            selected: 0,
+            scroll: 0,
+            frame: vec![0; WIDTH * HEIGHT * 4],
+        }
+    }
+
+    pub fn library(&self) -> &RomLibrary {
+        &self.library
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    // Handle a host key (named as in hotkey bindings) pressed while browsing
+    pub fn handle_key(&mut self, key: &str) -> BrowserAction {
+        match key {
+            "Up" => self.move_selection(-1),
+            "Down" => self.move_selection(1),
+            "PageUp" | "Left" => self.move_selection(-(VISIBLE_ROWS as isize)),
+            "PageDown" | "Right" => self.move_selection(VISIBLE_ROWS as isize),
+            "Home" => self.move_selection(isize::MIN / 2),
+            "End" => self.move_selection(isize::MAX / 2),
+            "Return" | "Space" => {
+                if let Some(entry) = self.library.entries().get(self.selected) {
+                    return BrowserAction::Launch(entry.path.clone());
+                }
+            }
+            "Escape" => return BrowserAction::Quit,
+            _ => {}
+        }
+        BrowserAction::None
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let count = self.library.len();
+        if count == 0 {
+            return;
+        }
+        self.selected = (self.selected as isize).saturating_add(delta).clamp(0, count as isize - 1) as usize;
+
+        // Keep the selection on screen
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + VISIBLE_ROWS {
+            self.scroll = self.selected + 1 - VISIBLE_ROWS;
+        }
+    }
+
+    // Draw the list into an RGBA8888 frame
+    pub fn render(&mut self) -> &[u8] {
+        let frame = &mut self.frame;
+        osd::fill_rect(frame, 0, 0, WIDTH, HEIGHT, BACKGROUND);
+
+        osd::draw_text(frame, 8, 4, "ROM LIBRARY", TEXT);
+        let count = format!("{}", self.library.len());
+        osd::draw_text(frame, (WIDTH - 8 - osd::text_width(&count)) as i32, 4, &count, DIM);
+        osd::fill_rect(frame, 4, 14, WIDTH - 8, 1, DIM);
+
+        let entries = self.library.entries();
+        if entries.is_empty() {
+            osd::draw_text(frame, 8, LIST_TOP as i32 + 2, "NO ROMS FOUND", DIM);
+        }
+
+        // Room for the title, leaving space for a region code on the right
+        let max_chars = (WIDTH - 16) / CHAR_ADVANCE - 5;
+        for (row, entry) in entries.iter().enumerate().skip(self.scroll).take(VISIBLE_ROWS) {
+            let y = (LIST_TOP + (row - self.scroll) * ROW_HEIGHT) as i32;
+            if row == self.selected {
+                osd::fill_rect(frame, 4, y - 1, WIDTH - 8, ROW_HEIGHT - 1, HIGHLIGHT);
+            }
+
+            let title: String = entry.title.chars().take(max_chars).collect();
+            osd::draw_text(frame, 8, y, &title, TEXT);
+
+            let region = region_code(&entry.region);
+            osd::draw_text(frame, (WIDTH - 8 - osd::text_width(region)) as i32, y, region, DIM);
+        }
+
+        osd::fill_rect(frame, 4, HEIGHT as i32 - 13, WIDTH - 8, 1, DIM);
+        osd::draw_text(frame, 8, HEIGHT as i32 - 10, "ENTER: PLAY   ESC: QUIT", DIM);
+
+        &self.frame
+    }
+}
+
+fn region_code(region: &str) -> &'static str {
+    match region {
+        "Japan" => "JPN",
+        "USA" => "USA",
+        "Europe" | "Sweden" | "Finland" | "Denmark" | "France" | "Netherlands" | "Spain" | "Germany" | "Italy" => "EUR",
+        "China" => "CHN",
+        "Indonesia" => "IDN",
+        "Korea" => "KOR",
+        _ => "---",
+    }
+}
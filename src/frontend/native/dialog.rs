@@ -0,0 +1,57 @@
+// Native "open ROM" dialog. Uses the tools every desktop already ships
+// (zenity/kdialog, AppleScript, PowerShell) so no GUI toolkit is linked in;
+// returns None when the user cancels or no dialog tool is available.
+use std::path::PathBuf;
+use std::process::Command;
+
+const DIALOG_TITLE: &str = "Open SNES ROM";
+
+pub fn pick_rom() -> Option<PathBuf> {
+    candidates().into_iter().find_map(run).flatten()
+}
+
+// Try each command until one runs; a tool that ran but returned nothing
+// means the user cancelled, so stop there
+fn run(mut command: Command) -> Option<Option<PathBuf>> {
+    let output = command.output().ok()?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some((output.status.success() && !path.is_empty()).then(|| PathBuf::from(path)))
+}
+
+#[cfg(target_os = "macos")]
+fn candidates() -> Vec<Command> {
+    let mut command = Command::new("osascript");
+    command.arg("-e").arg(format!("POSIX path of (choose file with prompt \"{}\")", DIALOG_TITLE));
+    vec![command]
+}
+
+#[cfg(target_os = "windows")]
+fn candidates() -> Vec<Command> {
+    let patterns: Vec<String> = crate::library::ROM_EXTENSIONS.iter().map(|ext| format!("*.{}", ext)).collect();
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $d = New-Object System.Windows.Forms.OpenFileDialog; \
+         $d.Title = '{}'; $d.Filter = 'SNES ROMs|{}|All files|*.*'; \
+         if ($d.ShowDialog() -eq 'OK') {{ $d.FileName }}",
+        DIALOG_TITLE,
+        patterns.join(";")
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", &script]);
+    vec![command]
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn candidates() -> Vec<Command> {
+    let patterns: Vec<String> = crate::library::ROM_EXTENSIONS.iter().map(|ext| format!("*.{}", ext)).collect();
+
+    let mut zenity = Command::new("zenity");
+    zenity.args(["--file-selection", "--title", DIALOG_TITLE])
+        .arg(format!("--file-filter=SNES ROMs | {}", patterns.join(" ")));
+
+    let mut kdialog = Command::new("kdialog");
+    kdialog.args(["--title", DIALOG_TITLE, "--getopenfilename", "."])
+        .arg(format!("SNES ROMs ({})", patterns.join(" ")));
+
+    vec![zenity, kdialog]
+}
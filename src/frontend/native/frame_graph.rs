@@ -0,0 +1,92 @@
+// Scrolling per-frame timing graph for the OSD: emulation and render time
+// as stacked bars against the 60 Hz frame budget, with the audio queue's
+// fill level traced over them. Shows where a stutter came from: a tall
+// green bar is the core, a tall amber one the GPU/present, and a falling
+// blue line an audio queue about to underrun.
+use crate::frontend::native::osd;
+use std::collections::VecDeque;
+
+// Three seconds at 60 fps, one pixel column per frame
+pub const HISTORY_FRAMES: usize = 180;
+
+pub const GRAPH_HEIGHT: usize = 40;
+
+// Time at the top of the graph: two frames, so the budget line sits halfway
+pub const GRAPH_SCALE_MS: f32 = 1000.0 / 30.0;
+pub const FRAME_BUDGET_MS: f32 = 1000.0 / 60.0;
+
+pub const EMULATION_COLOR: [u8; 4] = [0x40, 0xE0, 0x40, 0xFF];
+pub const RENDER_COLOR: [u8; 4] = [0xFF, 0xB0, 0x30, 0xFF];
+pub const AUDIO_COLOR: [u8; 4] = [0x40, 0xC0, 0xFF, 0xFF];
+const BUDGET_COLOR: [u8; 4] = [0x80, 0x80, 0x80, 0xFF];
+const BACKGROUND: [u8; 4] = [0, 0, 0, 0xFF];
+
+// Graph area, right-aligned at the top of the frame
+pub const GRAPH_X: i32 = 256 - HISTORY_FRAMES as i32 - 3;
+pub const GRAPH_Y: i32 = 3 + osd::GLYPH_HEIGHT as i32 + 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameSample {
+    pub emulation_ms: f32,
+    pub render_ms: f32,
+    // Audio queue fill, 0.0 (empty) to 1.0 (at the latency limit)
+    pub audio_fill: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameTimeGraph {
+    samples: VecDeque<FrameSample>,
+}
+
+impl FrameTimeGraph {
+    pub fn new() -> Self {
+        Self { samples: VecDeque::with_capacity(HISTORY_FRAMES) }
+    }
+
+    pub fn push(&mut self, sample: FrameSample) {
+        if self.samples.len() == HISTORY_FRAMES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    // Oldest first
+    pub fn samples(&self) -> impl Iterator<Item = &FrameSample> {
+        self.samples.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    // Draw into an RGBA frame: a legend with the latest values, then the
+    // graph with the newest frame in the rightmost column
+    pub fn draw(&self, frame: &mut [u8]) {
+        let latest = self.samples.back().copied().unwrap_or_default();
+        osd::fill_rect(frame, GRAPH_X - 2, 1, HISTORY_FRAMES + 4, osd::GLYPH_HEIGHT + GRAPH_HEIGHT + 6, BACKGROUND);
+
+        let mut x = GRAPH_X;
+        x = osd::draw_text(frame, x, 3, &format!("EMU {:.1} ", latest.emulation_ms), EMULATION_COLOR);
+        x = osd::draw_text(frame, x, 3, &format!("REN {:.1} ", latest.render_ms), RENDER_COLOR);
+        osd::draw_text(frame, x, 3, &format!("AUD {:.0}%", latest.audio_fill * 100.0), AUDIO_COLOR);
+
+        let bottom = GRAPH_Y + GRAPH_HEIGHT as i32;
+        let height = |ms: f32| ((ms / GRAPH_SCALE_MS).clamp(0.0, 1.0) * GRAPH_HEIGHT as f32).round() as i32;
+        let budget_y = bottom - height(FRAME_BUDGET_MS);
+        for col in (0..HISTORY_FRAMES as i32).step_by(2) {
+            osd::fill_rect(frame, GRAPH_X + col, budget_y, 1, 1, BUDGET_COLOR);
+        }
+
+        let first_col = GRAPH_X + (HISTORY_FRAMES - self.samples.len()) as i32;
+        for (i, sample) in self.samples.iter().enumerate() {
+            let x = first_col + i as i32;
+            let emulation = height(sample.emulation_ms);
+            let total = height(sample.emulation_ms + sample.render_ms);
+            osd::fill_rect(frame, x, bottom - emulation, 1, emulation as usize, EMULATION_COLOR);
+            osd::fill_rect(frame, x, bottom - total, 1, (total - emulation) as usize, RENDER_COLOR);
+
+            let fill = (sample.audio_fill.clamp(0.0, 1.0) * (GRAPH_HEIGHT - 1) as f32).round() as i32;
+            osd::fill_rect(frame, x, bottom - 1 - fill, 1, 1, AUDIO_COLOR);
+        }
+    }
+}
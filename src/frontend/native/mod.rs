@@ -1,31 +1,375 @@
 pub mod video;
 pub mod audio;
+pub mod osd;
+pub mod browser;
+pub mod dialog;
+pub mod frame_graph;
+pub mod pacing;
+pub mod rom_watch;
+pub mod pause_menu;
 
+use crate::apu::volume::Volume;
+use crate::boot_rom;
+use crate::cartridge::integrity::RomDatabase;
+use crate::cartridge::patch::{self, PatchFormat};
+use crate::cartridge::Cartridge;
+use crate::cheats::CheatList;
+use crate::config::{AudioConfig, Config, ConfigUpdate, ConfigWatcher, InputConfig, PathConfig, SyncMode};
+use crate::debug::frame_diff;
+#[cfg(feature = "tool-server")]
+use crate::debug::tool_server::ToolServer;
 use crate::emulator::Emulator;
+use frame_graph::{FrameSample, FrameTimeGraph};
+use crate::hot_slots::HotSlots;
+use crate::input::hotkeys::{HotkeyAction, HotkeyManager, Modifiers};
+use crate::input::{LatencyTracker, Peripheral};
+use crate::input::remote::RemoteInput;
+use crate::input::rumble::{RumbleSink, RUMBLE_PORTS};
+use crate::library::{self, RomLibrary};
+use crate::metrics::{Metrics, FRAME_RATE_HZ};
+use crate::movie::{self, Movie};
+use crate::paths::{GameId, GamePaths};
+use crate::ppu::Layer;
+use crate::profile::GameProfile;
+use crate::savestate::{SaveState, StateEncoding};
+use crate::sink::AudioSink;
+use crate::sram::{self, SramSaver};
 use crate::{Result, EmulatorError};
 use winit::{
-    event::{Event, WindowEvent, KeyEvent, ElementState},
+    event::{DeviceEvent, Event, WindowEvent, KeyEvent, ElementState, MouseButton},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{PhysicalKey, KeyCode},
     window::WindowBuilder,
 };
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
 use std::time::{Instant, Duration};
 use pollster::FutureExt;
+use browser::{BrowserAction, LibraryBrowser};
+use pause_menu::{MenuAction, PauseMenu};
+use rom_watch::{RomWatchOptions, RomWatcher};
+use video::Letterbox;
+
+// Frames between rewind snapshots
+const REWIND_INTERVAL: u32 = 5;
+pub const STATE_SLOTS: u8 = 10;
+
+// Volume change per press of the volume hotkeys
+const VOLUME_STEP: f32 = 0.1;
+
+const WINDOW_TITLE: &str = "CCSNES - Super Nintendo Emulator";
+
+// How long on-screen notices stay up
+const NOTICE_DURATION: Duration = Duration::from_secs(3);
 
 pub struct NativeFrontend {
     scale: u32,
     debug: bool,
+    audio_options: audio::AudioOptions,
+    // Master volume and mute, saved back to the watched config file
+    volume: Volume,
+    // Output filter and DSP mixing settings handed to each game
+    audio_config: AudioConfig,
+    hotkeys: HotkeyManager,
+    game_paths: GamePaths,
+    path_config: PathConfig,
+    fast_forward_speed: f32,
+    rewind_buffer_frames: u32,
+    hot_slots: usize,
+    measure_latency: bool,
+    show_fps: bool,
+    show_frame_time: bool,
+    show_input: bool,
+    auto_save_sram: bool,
+    sram_idle_frames: u32,
+    input: InputConfig,
+    // Input profile the running game's profile asked for
+    game_input_profile: Option<String>,
+    vsync: bool,
+    frame_blend: bool,
+    sync_mode: SyncMode,
+    // Config file reloaded while running
+    config_watcher: Option<ConfigWatcher>,
+    // ROM behind the emulator passed to `run`, for re-launching with a patch
+    rom_path: Option<PathBuf>,
+    // Host gamepad force feedback
+    rumble_sink: Option<Box<dyn RumbleSink>>,
+    // Player input from a TCP client, and the address it listens on
+    remote_input: Option<RemoteInput>,
+    remote_listen: String,
+    // JSON-RPC server for external tools, and the address it listens on
+    #[cfg(feature = "tool-server")]
+    tool_server: Option<ToolServer>,
+    tool_server_listen: String,
+    // Reload the running ROM when its file is rebuilt
+    rom_watch: Option<RomWatchOptions>,
+    // Lint mode for games launched from the library or a drop
+    lint: bool,
 }
 
 impl NativeFrontend {
     pub fn new(scale: u32, debug: bool) -> Result<Self> {
-        Ok(Self { scale, debug })
+        let config = Config::default();
+        Ok(Self {
+            scale,
+            debug,
+            audio_options: audio::AudioOptions::default(),
+            volume: Volume::from_config(&config.audio),
+            audio_config: config.audio,
+            hotkeys: HotkeyManager::from_config(&config.hotkeys)?,
+            game_paths: GamePaths::flat(&config.paths, "game"),
+            path_config: config.paths,
+            fast_forward_speed: config.emulation.fast_forward_speed,
+            rewind_buffer_frames: config.emulation.rewind_buffer_frames,
+            hot_slots: config.emulation.hot_slots,
+            measure_latency: config.debug.input_latency,
+            show_fps: config.debug.show_fps,
+            show_frame_time: config.debug.show_frame_time,
+            show_input: config.debug.show_input,
+            auto_save_sram: config.emulation.auto_save_sram,
+            sram_idle_frames: config.emulation.sram_idle_frames,
+            input: config.input,
+            game_input_profile: None,
+            vsync: config.video.vsync,
+            frame_blend: config.video.frame_blend,
+            sync_mode: config.emulation.sync_mode,
+            config_watcher: None,
+            rom_path: None,
+            rumble_sink: None,
+            remote_input: None,
+            remote_listen: String::new(),
+            #[cfg(feature = "tool-server")]
+            tool_server: None,
+            tool_server_listen: String::new(),
+            rom_watch: None,
+            lint: config.debug.lint,
+        })
+    }
+
+    pub fn set_audio_options(&mut self, options: audio::AudioOptions) {
+        self.audio_options = options;
+    }
+
+    // Apply audio, hotkey, path and speed settings from a configuration
+    pub fn apply_config(&mut self, config: &Config) -> Result<()> {
+        self.audio_options = (&config.audio).into();
+        self.volume = Volume::from_config(&config.audio);
+        self.audio_config = config.audio.clone();
+        self.hotkeys = HotkeyManager::from_config(&config.hotkeys)?;
+        self.path_config = config.paths.clone();
+        self.fast_forward_speed = config.emulation.fast_forward_speed;
+        self.rewind_buffer_frames = config.emulation.rewind_buffer_frames;
+        self.hot_slots = config.emulation.hot_slots;
+        self.measure_latency = config.debug.input_latency;
+        self.show_fps = config.debug.show_fps;
+        self.show_frame_time = config.debug.show_frame_time;
+        self.show_input = config.debug.show_input;
+        self.lint = config.debug.lint;
+        self.auto_save_sram = config.emulation.auto_save_sram;
+        self.sram_idle_frames = config.emulation.sram_idle_frames;
+        self.input = config.input.clone();
+        report_input_conflicts(config);
+        self.vsync = config.video.vsync;
+        self.frame_blend = config.video.frame_blend;
+        self.sync_mode = config.emulation.sync_mode;
+        self.update_remote_input();
+        self.update_tool_server(&config.debug.tool_server);
+        let profile = self.game_input_profile.take();
+        self.set_input_profile(profile.as_deref())
+    }
+
+    // Switch the keyboard bindings to a named [input.profiles] entry, or
+    // back to the base bindings with None. Later config reloads keep it.
+    pub fn set_input_profile(&mut self, name: Option<&str>) -> Result<()> {
+        self.input.set_active_profile(name)?;
+        self.game_input_profile = name.map(str::to_string);
+        Ok(())
+    }
+
+    pub fn input_profile(&self) -> Option<&str> {
+        self.input.active_profile()
+    }
+
+    // (Re)bind the remote input listener to the configured address. A bind
+    // failure is reported and leaves remote input off.
+    fn update_remote_input(&mut self) {
+        let player = self.input.remote_player.clamp(1, 2) - 1;
+        let unchanged = self.remote_listen == self.input.remote_listen
+            && self.remote_input.as_ref().is_none_or(|remote| remote.player() == player);
+        if unchanged {
+            return;
+        }
+        self.remote_listen = self.input.remote_listen.clone();
+        self.remote_input = None;
+        if self.remote_listen.is_empty() {
+            return;
+        }
+        match RemoteInput::bind(&self.remote_listen, player) {
+            Ok(remote) => {
+                println!("Accepting player {} input on {}", player + 1, self.remote_listen);
+                self.remote_input = Some(remote);
+            }
+            Err(e) => eprintln!("Remote input not available on {}: {}", self.remote_listen, e),
+        }
+    }
+
+    fn update_tool_server(&mut self, address: &str) {
+        if self.tool_server_listen == address {
+            return;
+        }
+        self.tool_server_listen = address.to_string();
+        #[cfg(feature = "tool-server")]
+        {
+            self.tool_server = None;
+            if address.is_empty() {
+                return;
+            }
+            match ToolServer::bind(address) {
+                Ok(server) => {
+                    println!("Tool server listening on {}", address);
+                    self.tool_server = Some(server);
+                }
+                Err(e) => eprintln!("Tool server not available on {}: {}", address, e),
+            }
+        }
+        #[cfg(not(feature = "tool-server"))]
+        if !address.is_empty() {
+            eprintln!("Tool server not available on {}: built without the tool-server feature", address);
+        }
+    }
+
+    pub fn sync_mode(&self) -> SyncMode {
+        self.sync_mode
+    }
+
+    // Takes effect from the next frame, including while running
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.sync_mode = mode;
+    }
+
+    // Hotkeys for settings of the frontend rather than the running game.
+    // Returns true when the key bindings changed.
+    fn frontend_hotkey(&mut self, action: HotkeyAction) -> bool {
+        match action {
+            HotkeyAction::NextInputProfile => {
+                match self.input.cycle_profile() {
+                    Ok(Some(name)) => println!("Input profile '{}'", name),
+                    Ok(None) => println!("Default input bindings"),
+                    Err(e) => eprintln!("Failed to switch input profile: {}", e),
+                }
+                return true;
+            }
+            HotkeyAction::VolumeUp | HotkeyAction::VolumeDown => {
+                let step = if action == HotkeyAction::VolumeUp { VOLUME_STEP } else { -VOLUME_STEP };
+                self.volume.set_level(self.volume.level() + step);
+                println!("Volume {}%", self.volume.percent());
+            }
+            HotkeyAction::ToggleMute => {
+                self.volume.set_muted(!self.volume.is_muted());
+                println!("Sound {}", if self.volume.is_muted() { "muted" } else { "unmuted" });
+            }
+            HotkeyAction::CycleSyncMode => {
+                self.sync_mode = self.sync_mode.next();
+                println!("Sync to {}", match self.sync_mode {
+                    SyncMode::Video => "video",
+                    SyncMode::Audio => "audio",
+                    SyncMode::Free => "nothing (free-run)",
+                });
+            }
+            _ => {}
+        }
+        false
     }
 
-    pub fn run(&mut self, mut emulator: Emulator) -> Result<()> {
+    // Reload `path` while running and apply changed settings where that is
+    // safe; anything else is reported as needing a reset
+    pub fn watch_config(&mut self, path: &Path) -> Result<()> {
+        self.config_watcher = Some(ConfigWatcher::new(path)?);
+        Ok(())
+    }
+
+    // Take over the sections of a reloaded config that changed, so
+    // later launches use them too
+    fn apply_update(&mut self, update: &ConfigUpdate) {
+        let config = &update.config;
+        if update.changes.audio {
+            self.audio_options = (&config.audio).into();
+            self.volume = Volume::from_config(&config.audio);
+            self.audio_config = config.audio.clone();
+        }
+        if update.changes.input {
+            self.input = config.input.clone();
+            self.update_remote_input();
+            let profile = self.game_input_profile.take();
+            if let Err(e) = self.set_input_profile(profile.as_deref()) {
+                eprintln!("{}", e);
+            }
+        }
+        if update.changes.video {
+            self.vsync = config.video.vsync;
+            self.frame_blend = config.video.frame_blend;
+        }
+        if update.changes.emulation {
+            self.fast_forward_speed = config.emulation.fast_forward_speed;
+            self.auto_save_sram = config.emulation.auto_save_sram;
+            self.sram_idle_frames = config.emulation.sram_idle_frames;
+            self.hot_slots = config.emulation.hot_slots;
+            self.sync_mode = config.emulation.sync_mode;
+        }
+        if update.changes.debug {
+            self.measure_latency = config.debug.input_latency;
+            self.show_fps = config.debug.show_fps;
+            self.show_frame_time = config.debug.show_frame_time;
+            self.show_input = config.debug.show_input;
+            self.update_tool_server(&config.debug.tool_server);
+        }
+    }
+
+    // Where save states and screenshots for the running game are stored
+    pub fn set_game_paths(&mut self, paths: GamePaths) {
+        self.game_paths = paths;
+    }
+
+    pub fn set_rom_path(&mut self, path: &Path) {
+        self.rom_path = Some(path.to_path_buf());
+    }
+
+    // Reload the ROM of the running game whenever its file changes
+    pub fn watch_rom(&mut self, options: RomWatchOptions) {
+        self.rom_watch = Some(options);
+    }
+
+    // Forward the game's rumble effects to the application's gamepad backend;
+    // without one they go nowhere
+    pub fn set_rumble_sink(&mut self, sink: Box<dyn RumbleSink>) {
+        self.rumble_sink = Some(sink);
+    }
+
+    pub fn run(&mut self, emulator: Emulator) -> Result<()> {
+        self.run_loop(Some(emulator), None)
+    }
+
+    // Launcher mode: pick games from a library list. Quitting a game returns
+    // to the list instead of closing the window.
+    pub fn run_library(&mut self, library: RomLibrary) -> Result<()> {
+        self.run_loop(None, Some(LibraryBrowser::new(library)))
+    }
+
+    // Open a window running the built-in self test until a ROM is dropped
+    // onto it
+    pub fn run_empty(&mut self) -> Result<()> {
+        let mut emulator = Emulator::new()?;
+        emulator.set_lint(self.lint);
+        emulator.load_rom(&boot_rom::build())?;
+        self.game_paths = GamePaths::flat(&self.path_config, "self-test");
+        self.rom_path = None;
+        self.run_loop(Some(emulator), None)
+    }
+
+    fn run_loop(&mut self, emulator: Option<Emulator>, mut browser: Option<LibraryBrowser>) -> Result<()> {
         let event_loop = EventLoop::new().unwrap();
         let window = WindowBuilder::new()
-            .with_title("CCSNES - Super Nintendo Emulator")
+            .with_title(WINDOW_TITLE)
             .with_inner_size(winit::dpi::LogicalSize::new(
                 256 * self.scale,
                 224 * self.scale,
@@ -33,110 +377,1142 @@ impl NativeFrontend {
             .with_resizable(false)
             .build(&event_loop)
             .map_err(|e| EmulatorError::VideoError(format!("Failed to create window: {}", e)))?;
-        
+
         // Initialize video and audio systems
         let mut video = video::VideoRenderer::new(&window, self.scale).block_on()?;
-        let mut audio = audio::AudioPlayer::new()?;
-        
+        let mut pacer = pacing::Pacer::new(self.sync_mode, Duration::from_secs_f64(1.0 / FRAME_RATE_HZ));
+        video.set_vsync(self.vsync && pacer.wants_vsync());
+        let mut audio = audio::AudioPlayer::with_options(self.audio_options.clone())?;
+        audio.set_volume(self.volume);
+
+        // Volume last written to the config file
+        let mut saved_volume = self.volume;
+
         // Frame timing
-        let mut last_frame = Instant::now();
-        let frame_duration = Duration::from_secs_f64(1.0 / 60.0);
         let mut fps_counter = 0;
         let mut fps_timer = Instant::now();
-        
-        // Controller state
-        let mut controller_state = 0u16;
-        
+        // Counters at the last FPS tick, for the speed readout
+        let mut last_metrics: Option<Metrics> = None;
+
+        // Controller state per port
+        let mut controller_state = [0u16; 2];
+        let mut config_watcher = self.config_watcher.take();
+        let mut rom_watcher: Option<RomWatcher> = None;
+        let mut rumble_sink = self.rumble_sink.take();
+        let mut rumble_levels = [(0.0, 0.0); RUMBLE_PORTS];
+
+        // Host mouse buttons (left, right, middle), shared by the SNES Mouse
+        // and the Super Scope
+        let mut mouse_buttons = (false, false, false);
+        let mut notice: Option<(String, Instant)> = None;
+        // Frame of the last desync report announced
+        let mut desync_shown: Option<u64> = None;
+
+        // Input latency instrumentation
+        let clock = Instant::now();
+        let now_ms = move || clock.elapsed().as_secs_f64() * 1000.0;
+        let mut latency = self.measure_latency.then(LatencyTracker::new);
+        // Frame-time graph and the duration of the last present
+        let mut frame_graph = self.show_frame_time.then(FrameTimeGraph::new);
+        let mut render_ms = 0.0;
+        // Emulator frame currently handed to the video renderer
+        let mut shown_frame = 0u64;
+        let idle_screen = idle_screen();
+
+        // Open over the running game, which is paused until it closes
+        let mut pause_menu: Option<PauseMenu> = None;
+
+        // Hotkey state
+        let mut hotkeys = std::mem::take(&mut self.hotkeys);
+        let mut modifiers = Modifiers::default();
+
+        // Running game, if any (None while the library list is shown)
+        let mut game = emulator.map(|mut emulator| {
+            emulator.apply_audio_config(&self.audio_config);
+            let session = HotkeySession::new(self, self.game_paths.clone(), self.rom_path.clone(), &emulator);
+            (emulator, session)
+        });
+        // Emulator of the last game closed, reused for the next one
+        let mut spare: Option<Emulator> = None;
+
         event_loop.run(move |event, elwt| {
             elwt.set_control_flow(ControlFlow::Poll);
 
             match event {
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::CloseRequested => {
+                        if let Some((emulator, session)) = game.as_mut() {
+                            session.close(emulator);
+                        }
                         elwt.exit();
                     }
-                    
+
                     WindowEvent::Resized(_) => {
                         // Surface is recreated each frame, so no need to handle resize
                     }
-                    
-                    WindowEvent::KeyboardInput { event: KeyEvent { physical_key: PhysicalKey::Code(keycode), state, .. }, .. } => {
-                        // Map keyboard to SNES controller
-                        let button = match keycode {
-                            KeyCode::KeyZ => Some(0x80),    // A
-                            KeyCode::KeyX => Some(0x8000),  // B  
-                            KeyCode::KeyA => Some(0x40),    // X
-                            KeyCode::KeyS => Some(0x4000),  // Y
-                            KeyCode::KeyQ => Some(0x20),    // L
-                            KeyCode::KeyW => Some(0x10),    // R
-                            KeyCode::Enter => Some(0x1000), // Start
-                            KeyCode::ShiftRight => Some(0x2000), // Select
-                            KeyCode::ArrowUp => Some(0x800),     // Up
-                            KeyCode::ArrowDown => Some(0x400),   // Down
-                            KeyCode::ArrowLeft => Some(0x200),   // Left
-                            KeyCode::ArrowRight => Some(0x100),  // Right
-                            _ => None,
+
+                    WindowEvent::ModifiersChanged(state) => {
+                        let state = state.state();
+                        modifiers = Modifiers {
+                            ctrl: state.control_key(),
+                            shift: state.shift_key(),
+                            alt: state.alt_key(),
+                        };
+                    }
+
+                    WindowEvent::KeyboardInput { event: KeyEvent { physical_key: PhysicalKey::Code(keycode), state, repeat, .. }, .. } => {
+                        let Some((emulator, session)) = game.as_mut() else {
+                            // Browsing the library
+                            let Some(browser) = browser.as_mut() else { return };
+                            if state != ElementState::Pressed {
+                                return;
+                            }
+                            match key_name(keycode).map(|name| browser.handle_key(name)) {
+                                Some(BrowserAction::Launch(path)) => match self.switch_game(&mut game, &mut spare, &path, None) {
+                                    Ok(emulator) => {
+                                        window.set_title(&game_title(emulator));
+                                        controller_state = [0; 2];
+                                        if let Some(latency) = latency.as_mut() {
+                                            latency.reset();
+                                        }
+                                        last_metrics = None;
+                                    }
+                                    Err(e) => eprintln!("Failed to load {:?}: {}", path, e),
+                                },
+                                Some(BrowserAction::Quit) => elwt.exit(),
+                                _ => {}
+                            }
+                            return;
+                        };
+
+                        let mut quit = false;
+                        let mut open_menu = false;
+
+                        // Hotkeys take precedence over game input
+                        let action = key_name(keycode).and_then(|name| hotkeys.match_key(name, modifiers));
+                        if let Some(menu) = pause_menu.as_mut() {
+                            // The menu takes every key while it is open
+                            if state == ElementState::Pressed {
+                                let action = key_name(keycode).map_or(MenuAction::None, |name| menu.handle_key(name));
+                                if let MenuAction::SetVolume(percent) = action {
+                                    self.volume.set_level(percent as f32 / 100.0);
+                                    audio.set_volume(self.volume);
+                                }
+                                if !matches!(action, MenuAction::None | MenuAction::SetFrameBlend(_) | MenuAction::SetVolume(_)) {
+                                    pause_menu = None;
+                                }
+                                quit = session.menu_action(action, emulator);
+                            }
+                        } else if let Some(action) = action {
+                            let pressed = state == ElementState::Pressed;
+                            if action == HotkeyAction::PauseMenu {
+                                open_menu = pressed && !repeat;
+                            } else if is_frontend_hotkey(action) {
+                                if pressed && !repeat && self.frontend_hotkey(action) {
+                                    // Buttons held under the old bindings would never release
+                                    controller_state = [0; 2];
+                                    for port in 0..2 {
+                                        emulator.set_controller_input(port, 0);
+                                    }
+                                }
+                            } else if !repeat && (pressed || action.is_held()) {
+                                quit = session.handle(action, pressed, emulator);
+                            }
+                        } else {
+                            // Map keyboard to SNES controllers via the input bindings
+                            let buttons = key_name(keycode).map_or([0; 2], |name| self.input.buttons_for_key(name));
+
+                            if buttons != [0; 2] {
+                                for (port, button) in buttons.into_iter().enumerate() {
+                                    match state {
+                                        ElementState::Pressed => controller_state[port] |= button,
+                                        ElementState::Released => controller_state[port] &= !button,
+                                    }
+                                    emulator.set_controller_input(port as u8, controller_state[port]);
+                                }
+                                if let Some(latency) = latency.as_mut() {
+                                    latency.input_event(emulator.get_frame_count(), now_ms());
+                                }
+
+                                // Controller combos bound to hotkeys
+                                for (action, pressed) in hotkeys.poll_pad(controller_state[0]) {
+                                    if action == HotkeyAction::PauseMenu {
+                                        open_menu |= pressed;
+                                    } else if is_frontend_hotkey(action) {
+                                        if pressed {
+                                            self.frontend_hotkey(action);
+                                        }
+                                    } else if pressed || action.is_held() {
+                                        quit |= session.handle(action, pressed, emulator);
+                                    }
+                                }
+                            }
+                        }
+
+                        if open_menu {
+                            // Keys released while the menu is open never reach the game
+                            controller_state = [0; 2];
+                            for port in 0..2 {
+                                emulator.set_controller_input(port, 0);
+                            }
+                            session.fast_forward = false;
+                            session.rewinding = false;
+                            pause_menu = Some(PauseMenu::new(session.slot, session.frame_blend, self.volume.percent()));
+                        }
+
+                        if quit {
+                            session.close(emulator);
+                            if browser.is_some() {
+                                // Back to the library list; the emulator is
+                                // kept for the next game
+                                spare = game.take().map(|(mut emulator, _)| {
+                                    emulator.unload_rom();
+                                    emulator
+                                });
+                                window.set_title(WINDOW_TITLE);
+                                audio.clear_buffer();
+                            } else {
+                                elwt.exit();
+                            }
+                        }
+                    }
+
+                    WindowEvent::DroppedFile(path) => {
+                        // ROMs replace the running game, patches re-launch it
+                        // patched and anything else is tried as a save state
+                        let target = if PatchFormat::from_path(&path).is_some() {
+                            match game.as_ref().and_then(|(_, session)| session.rom_path.clone()) {
+                                Some(rom_path) => Some((rom_path, Some(path.clone()))),
+                                None => {
+                                    eprintln!("Load a ROM before dropping the patch {:?}", path);
+                                    None
+                                }
+                            }
+                        } else if library::is_rom_file(&path) {
+                            Some((path.clone(), None))
+                        } else {
+                            match game.as_mut() {
+                                Some((emulator, _)) => match emulator.recover_state_from_file(&path.to_string_lossy()) {
+                                    Ok(recovery) if recovery.is_complete() => println!("Loaded state from {:?}", path),
+                                    Ok(recovery) => println!("Loaded state from {:?} in part: {}", path, recovery),
+                                    Err(e) => eprintln!("Failed to load state {:?}: {}", path, e),
+                                },
+                                None => eprintln!("Load a ROM before dropping the save state {:?}", path),
+                            }
+                            None
                         };
-                        
-                        if let Some(button) = button {
-                            match state {
-                                ElementState::Pressed => controller_state |= button,
-                                ElementState::Released => controller_state &= !button,
+
+                        if let Some((rom_path, patch_path)) = target {
+                            match self.switch_game(&mut game, &mut spare, &rom_path, patch_path.as_deref()) {
+                                Ok(emulator) => {
+                                    window.set_title(&game_title(emulator));
+                                    controller_state = [0; 2];
+                                    if let Some(latency) = latency.as_mut() {
+                                        latency.reset();
+                                    }
+                                    audio.clear_buffer();
+                                    last_metrics = None;
+                                }
+                                Err(e) => eprintln!("Failed to load {:?}: {}", path, e),
+                            }
+                        }
+                    }
+
+                    WindowEvent::CursorMoved { position, .. } => {
+                        if let Some((emulator, _)) = game.as_mut() {
+                            if emulator.input().device(1) == Peripheral::SuperScope {
+                                let size = window.inner_size();
+                                let frame = (256, emulator.frame().height as u32);
+                                let aim = Letterbox::fit((size.width, size.height), frame).to_frame((position.x, position.y), frame);
+                                emulator.input_mut().scope_mut().aim(aim);
+                            }
+                        }
+                    }
+
+                    WindowEvent::CursorLeft { .. } => {
+                        if let Some((emulator, _)) = game.as_mut() {
+                            emulator.input_mut().scope_mut().aim(None);
+                        }
+                    }
+
+                    WindowEvent::MouseInput { state, button, .. } => {
+                        let pressed = state == ElementState::Pressed;
+                        match button {
+                            MouseButton::Left => mouse_buttons.0 = pressed,
+                            MouseButton::Right => mouse_buttons.1 = pressed,
+                            MouseButton::Middle => mouse_buttons.2 = pressed,
+                            _ => {}
+                        }
+                        if let Some((emulator, _)) = game.as_mut() {
+                            let (left, right, middle) = mouse_buttons;
+                            for port in 0..2 {
+                                if let Some(mouse) = emulator.input_mut().mouse_mut(port) {
+                                    mouse.set_buttons(left, right);
+                                }
                             }
-                            emulator.set_controller_input(0, controller_state);
+                            emulator.input_mut().scope_mut().set_buttons(left, right, false, middle);
                         }
                     }
-                    
+
                     WindowEvent::RedrawRequested => {
                         // Present the rendered frame
+                        let render_started = Instant::now();
+                        // Lets Wayland throttle presents with frame callbacks
+                        window.pre_present_notify();
                         if let Err(e) = video.render(&window) {
                             eprintln!("Render error: {}", e);
                         }
+                        pacer.presented(Instant::now());
+                        render_ms = render_started.elapsed().as_secs_f32() * 1000.0;
+                        if let Some(latency) = latency.as_mut() {
+                            latency.presented(shown_frame, now_ms());
+                        }
                     }
-                    
+
                     _ => {}
                 },
-                
+
+                // Raw motion, so the SNES Mouse keeps moving at the window edge
+                Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta }, .. } => {
+                    if let Some((emulator, _)) = game.as_mut() {
+                        for port in 0..2 {
+                            if emulator.input().device(port) == Peripheral::Mouse {
+                                if let Some(mouse) = emulator.input_mut().mouse_mut(port) {
+                                    mouse.move_by(delta.0 as i32, delta.1 as i32);
+                                }
+                            }
+                        }
+                    }
+                }
+
                 Event::AboutToWait => {
-                    // Check if enough time has passed for next frame
+                    if pacer.mode() != self.sync_mode {
+                        pacer.set_mode(self.sync_mode);
+                        video.set_vsync(self.vsync && pacer.wants_vsync());
+                    }
+                    // Check whether the pacing master wants the next frame
                     let now = Instant::now();
-                    if now.duration_since(last_frame) >= frame_duration {
-                        last_frame = now;
-                        
-                        // Run one frame of emulation
-                        if let Err(e) = emulator.step_frame() {
-                            eprintln!("Emulation error: {}", e);
-                            elwt.exit();
-                            return;
-                        }
-                        
-                        // Update video with frame buffer
-                        video.update_frame(emulator.get_video_buffer());
-                        
-                        // Queue audio samples
-                        let samples = emulator.get_audio_samples();
-                        if !samples.is_empty() {
-                            audio.queue_samples(&samples);
+                    let due = pacer.frame_due(now, (!audio.is_device_lost()).then(|| audio.buffer_fill()));
+                    // Sleep until the next frame instead of spinning when the schedule allows
+                    elwt.set_control_flow(pacer.wake_at().map_or(ControlFlow::Poll, ControlFlow::WaitUntil));
+                    if due {
+
+                        if let (Some(menu), Some((emulator, _))) = (pause_menu.as_ref(), game.as_ref()) {
+                            video.update_frame_with(emulator.get_video_buffer(), |frame| menu.draw(frame));
+                        } else if let Some((emulator, session)) = game.as_mut() {
+                            // Pick up a rebuilt ROM once it has been written out
+                            if let (Some(options), Some(rom_path)) = (self.rom_watch.as_ref(), session.rom_path.clone()) {
+                                if rom_watcher.as_ref().is_none_or(|watcher| watcher.path() != rom_path) {
+                                    rom_watcher = Some(RomWatcher::new(&rom_path));
+                                }
+                                if rom_watcher.as_mut().is_some_and(|watcher| watcher.poll(now)) {
+                                    match session.reload_rom(emulator, options) {
+                                        Ok(()) => {
+                                            println!("Reloaded {:?}", rom_path);
+                                            notice = Some(("ROM reloaded".to_string(), now));
+                                            window.set_title(&game_title(emulator));
+                                            audio.clear_buffer();
+                                        }
+                                        Err(e) => eprintln!("Failed to reload {:?}: {}", rom_path, e),
+                                    }
+                                }
+                            }
+
+                            // Remote buttons add to the keyboard's for that player
+                            if let Some(remote) = self.remote_input.as_mut() {
+                                for event in remote.poll() {
+                                    println!("{}", event);
+                                    notice = Some((event.to_string(), now));
+                                }
+                                let port = remote.player();
+                                emulator.set_controller_input(port, controller_state[port as usize] | remote.buttons());
+                            }
+                            #[cfg(feature = "tool-server")]
+                            if let Some(server) = self.tool_server.as_mut() {
+                                server.poll(emulator);
+                            }
+
+                            // Run one frame of emulation (or step back while rewinding)
+                            let latches = emulator.joypad_latch_count();
+                            let emulation_started = Instant::now();
+                            if let Err(e) = session.run_frame(emulator) {
+                                eprintln!("Emulation error: {}", e);
+                                elwt.exit();
+                                return;
+                            }
+                            if let Some(graph) = frame_graph.as_mut() {
+                                graph.push(FrameSample {
+                                    emulation_ms: emulation_started.elapsed().as_secs_f32() * 1000.0,
+                                    render_ms,
+                                    audio_fill: audio.buffer_fill(),
+                                });
+                            }
+                            shown_frame = emulator.get_frame_count();
+                            #[cfg(feature = "tool-server")]
+                            if let Some(server) = self.tool_server.as_mut() {
+                                server.end_frame(emulator);
+                            }
+
+                            // Send changed motor levels to the host gamepads
+                            if let Some(sink) = rumble_sink.as_mut() {
+                                for (port, last) in rumble_levels.iter_mut().enumerate() {
+                                    let level = emulator.input().rumble().level(port as u8);
+                                    if level != *last {
+                                        sink.set_rumble(port as u8, level.0, level.1);
+                                        *last = level;
+                                    }
+                                }
+                            }
+
+                            if let Some(text) = session.rom_notice.take() {
+                                notice = Some((text, now));
+                            }
+
+                            // Announce devices attached by detection
+                            for (port, device) in emulator.input_mut().take_auto_attached() {
+                                let text = format!("{} detected on port {}", device, port + 1);
+                                println!("{} (set [peripherals] in the game profile to override)", text);
+                                notice = Some((text, now));
+                            }
+
+                            // A game stuck waiting on the APU says why
+                            if let Some(report) = emulator.desync().filter(|report| desync_shown != Some(report.frame)) {
+                                eprintln!("APU desync: {}", report);
+                                notice = Some(("APU not responding, see log".to_string(), now));
+                                desync_shown = Some(report.frame);
+                            }
+                            if notice.as_ref().is_some_and(|(_, since)| since.elapsed() >= NOTICE_DURATION) {
+                                notice = None;
+                            }
+
+                            // Update video with frame buffer
+                            if let Some(latency) = latency.as_mut() {
+                                if emulator.joypad_latch_count() != latches {
+                                    latency.latched(shown_frame, now_ms());
+                                }
+                            }
+                            video.set_frame_blend(session.frame_blend);
+                            // Pads as set for the frame just run, i.e. the one shown
+                            let pads = self.show_input.then(|| [0, 1].map(|port| emulator.input().get_controller_state(port)));
+                            let recorded = session.recording.as_ref().map(Movie::len);
+                            video.update_frame_with(emulator.get_video_buffer(), |frame| {
+                                if let Some(frames) = recorded {
+                                    draw_recording(frame, frames);
+                                }
+                                if let Some(pads) = pads {
+                                    draw_input(frame, pads);
+                                }
+                                if let Some(graph) = frame_graph.as_ref() {
+                                    graph.draw(frame);
+                                }
+                                if let Some(latency) = latency.as_ref() {
+                                    draw_latency(frame, latency);
+                                }
+                                if let Some((text, _)) = notice.as_ref() {
+                                    draw_notice(frame, text);
+                                }
+                            });
+
+                            // Recover from a lost output device
+                            if let Err(e) = audio.poll_device() {
+                                eprintln!("Audio recovery failed: {}", e);
+                            }
+
+                            // Queue audio samples; the player blends across state
+                            // loads and rewinds instead of clicking
+                            audio.set_volume(self.volume);
+                            let chunk = emulator.take_audio();
+                            if !chunk.samples.is_empty() && !session.rewinding {
+                                let _ = audio.queue_audio(&chunk);
+                            }
+                        } else if let Some(browser) = browser.as_mut() {
+                            video.update_frame(browser.render());
+                        } else {
+                            video.update_frame(&idle_screen);
                         }
-                        
+
                         // Request redraw
                         window.request_redraw();
-                        
+
                         // FPS counter
                         fps_counter += 1;
                         if fps_timer.elapsed() >= Duration::from_secs(1) {
-                            if self.debug {
-                                println!("FPS: {}", fps_counter);
+                            if self.debug || self.show_fps {
+                                let metrics = game.as_ref().map(|(emulator, _)| emulator.metrics());
+                                match (&metrics, &last_metrics) {
+                                    (Some(now), Some(before)) => println!(
+                                        "FPS: {} ({:.1}% speed)",
+                                        fps_counter,
+                                        now.speed_percent(before, fps_timer.elapsed().as_secs_f64())
+                                    ),
+                                    _ => println!("FPS: {}", fps_counter),
+                                }
+                                last_metrics = metrics;
+                            }
+                            if let Some(stats) = latency.as_ref().and_then(LatencyTracker::stats) {
+                                println!("{}", stats);
                             }
                             fps_counter = 0;
                             fps_timer = Instant::now();
+
+                            // Keep volume changes for the next run
+                            if self.volume != saved_volume {
+                                saved_volume = self.volume;
+                                if let Some(watcher) = config_watcher.as_mut() {
+                                    let mut config = watcher.current().clone();
+                                    config.audio.master_volume = self.volume.percent();
+                                    config.audio.muted = self.volume.is_muted();
+                                    if let Err(e) = watcher.save(config) {
+                                        eprintln!("Failed to save volume: {}", e);
+                                    }
+                                }
+                            }
+
+                            // Pick up config file edits
+                            let update = match config_watcher.as_mut().map(ConfigWatcher::poll) {
+                                Some(Ok(update)) => update,
+                                Some(Err(e)) => {
+                                    eprintln!("Failed to reload config: {}", e);
+                                    None
+                                }
+                                None => None,
+                            };
+                            if let Some(update) = update {
+                                let config = &update.config;
+                                self.apply_update(&update);
+                                saved_volume = self.volume;
+                                if update.changes.hotkeys {
+                                    match HotkeyManager::from_config(&config.hotkeys) {
+                                        Ok(manager) => hotkeys = manager,
+                                        Err(e) => eprintln!("Keeping previous hotkeys: {}", e),
+                                    }
+                                }
+                                if update.changes.input || update.changes.hotkeys {
+                                    report_input_conflicts(config);
+                                }
+                                if update.changes.input {
+                                    // Release everything so no button stays stuck under the old bindings
+                                    controller_state = [0; 2];
+                                    if let Some((emulator, _)) = game.as_mut() {
+                                        emulator.set_controller_input(0, 0);
+                                        emulator.set_controller_input(1, 0);
+                                    }
+                                }
+                                if update.changes.audio && *audio.options() != self.audio_options {
+                                    match audio::AudioPlayer::with_options(self.audio_options.clone()) {
+                                        Ok(player) => audio = player,
+                                        Err(e) => eprintln!("Keeping previous audio output: {}", e),
+                                    }
+                                }
+                                audio.set_volume(self.volume);
+                                if update.changes.audio {
+                                    if let Some((emulator, _)) = game.as_mut() {
+                                        emulator.apply_audio_config(&self.audio_config);
+                                    }
+                                }
+                                video.set_vsync(self.vsync && pacer.wants_vsync());
+                                if self.measure_latency != latency.is_some() {
+                                    latency = self.measure_latency.then(LatencyTracker::new);
+                                }
+                                if self.show_frame_time != frame_graph.is_some() {
+                                    frame_graph = self.show_frame_time.then(FrameTimeGraph::new);
+                                }
+                                if let Some((_, session)) = game.as_mut() {
+                                    session.apply_config(self);
+                                }
+                                println!("Reloaded config from {:?}", config_watcher.as_ref().map(ConfigWatcher::path));
+                                for setting in &update.reset_required {
+                                    println!("{} changed; restart to apply it", setting);
+                                }
+                            }
                         }
                     }
                 }
-                
+
                 _ => {}
             }
         }).map_err(|e| EmulatorError::VideoError(format!("Event loop error: {:?}", e)))?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    // Switch to a ROM picked from the library or dropped on the window. The
+    // running game's emulator takes the new cartridge, or the spare one
+    // kept from the last game when none runs. On failure the running game
+    // carries on.
+    fn switch_game<'a>(
+        &mut self,
+        game: &'a mut Option<(Emulator, HotkeySession)>,
+        spare: &mut Option<Emulator>,
+        rom_path: &Path,
+        patch_path: Option<&Path>,
+    ) -> Result<&'a Emulator> {
+        if let Some((emulator, session)) = game.as_mut() {
+            session.close(emulator);
+            *session = self.launch(emulator, rom_path, patch_path)?;
+        } else {
+            let mut emulator = match spare.take() {
+                Some(emulator) => emulator,
+                None => Emulator::new()?,
+            };
+            match self.launch(&mut emulator, rom_path, patch_path) {
+                Ok(session) => *game = Some((emulator, session)),
+                Err(e) => {
+                    *spare = Some(emulator);
+                    return Err(e);
+                }
+            }
+        }
+        Ok(game.as_ref().map(|(emulator, _)| emulator).expect("game launched"))
+    }
+
+    // Load a ROM into `emulator` along with its SRAM, replacing the game it
+    // runs. Every file is read before the emulator is touched, so a bad one
+    // leaves it as it was. A patched ROM gets its own save directory.
+    fn launch(&mut self, emulator: &mut Emulator, rom_path: &Path, patch_path: Option<&Path>) -> Result<HotkeySession> {
+        let mut rom_data = std::fs::read(rom_path)?;
+        if let Some(patch_path) = patch_path {
+            rom_data = patch::apply_patch(&rom_data, &std::fs::read(patch_path)?)?;
+            println!("Applied patch {:?}", patch_path);
+        }
+        let cartridge = Cartridge::load(&rom_data)?;
+        let game_id = GameId::from_rom_path(rom_path, &rom_data);
+        let paths = GamePaths::prepare(&self.path_config, &game_id)?;
+        let sram = if paths.sram_path().exists() { Some(sram::read_sram(paths.sram_path())?) } else { None };
+        let cheats = CheatList::load(paths.cheats_path())?;
+        let profile = GameProfile::load(paths.profile_path())?;
+
+        emulator.set_lint(self.lint);
+        emulator.load_cartridge(cartridge)?;
+        emulator.apply_audio_config(&self.audio_config);
+        if let Some(sram) = sram {
+            emulator.load_sram(&sram)?;
+        }
+        emulator.apply_cheats(&cheats)?;
+        emulator.set_quirks(profile.quirks);
+        emulator.set_enhancements(profile.enhancements);
+        emulator.input_mut().rumble_mut().set_config(profile.rumble);
+        emulator.set_peripherals(&profile.peripherals);
+        if let Err(e) = self.set_input_profile(profile.input_profile.as_deref()) {
+            eprintln!("{}", e);
+        }
+
+        Ok(HotkeySession::new(self, paths, Some(rom_path.to_path_buf()), emulator))
+    }
+}
+
+// Runtime state driven by hotkeys
+struct HotkeySession {
+    paths: GamePaths,
+    rom_path: Option<PathBuf>,
+    sram: SramSaver,
+    auto_save_sram: bool,
+    slot: u8,
+    // Recent save slots held in memory; files are written behind them
+    hot_slots: HotSlots,
+    fast_forward: bool,
+    fast_forward_speed: f32,
+    rewinding: bool,
+    rewind_buffer: VecDeque<SaveState>,
+    rewind_capacity: usize,
+    frames_since_snapshot: u32,
+    // Frame blending, from the config until toggled
+    frame_blend: bool,
+    // Bad dump warning to put on screen once the game is running
+    rom_notice: Option<String>,
+    // Movie being recorded, saved when recording stops
+    recording: Option<Movie>,
+}
+
+impl HotkeySession {
+    fn new(frontend: &NativeFrontend, paths: GamePaths, rom_path: Option<PathBuf>, emulator: &Emulator) -> Self {
+        Self {
+            sram: SramSaver::new(paths.sram_path().to_path_buf(), frontend.sram_idle_frames, emulator),
+            auto_save_sram: frontend.auto_save_sram,
+            paths,
+            rom_path,
+            slot: 0,
+            hot_slots: HotSlots::new(frontend.hot_slots),
+            fast_forward: false,
+            fast_forward_speed: frontend.fast_forward_speed,
+            rewinding: false,
+            rewind_buffer: VecDeque::new(),
+            rewind_capacity: (frontend.rewind_buffer_frames / REWIND_INTERVAL) as usize,
+            frames_since_snapshot: 0,
+            frame_blend: frontend.frame_blend,
+            rom_notice: rom_notice(&frontend.path_config, emulator),
+            recording: None,
+        }
+    }
+
+    // Settings that can change while a game runs
+    fn apply_config(&mut self, frontend: &NativeFrontend) {
+        self.fast_forward_speed = frontend.fast_forward_speed;
+        self.auto_save_sram = frontend.auto_save_sram;
+        self.sram.set_idle_frames(frontend.sram_idle_frames);
+        self.hot_slots.set_capacity(frontend.hot_slots);
+        self.frame_blend = frontend.frame_blend;
+    }
+
+    fn state_path(&self) -> PathBuf {
+        self.paths.state_path(self.slot)
+    }
+
+    // Snapshot into the hot slot at once; the file is written on a
+    // background thread so the frame isn't held up by compression and IO
+    fn save_state(&mut self, emulator: &Emulator) -> Result<()> {
+        let state = emulator.save_state()?;
+        if self.hot_slots.capacity() == 0 && !self.hot_slots.is_pinned(self.slot) {
+            return state.save_to_file(&self.state_path().to_string_lossy());
+        }
+        self.hot_slots.insert(self.slot, state.clone());
+        let path = self.state_path();
+        std::thread::spawn(move || {
+            // Through a temp file so overlapping saves to one slot can't interleave
+            let mut temp_name = path.as_os_str().to_owned();
+            temp_name.push(".tmp");
+            let temp_path = PathBuf::from(temp_name);
+            let written = state
+                .save_to_file(&temp_path.to_string_lossy())
+                .and_then(|()| Ok(std::fs::rename(&temp_path, &path)?));
+            if let Err(e) = written {
+                eprintln!("Failed to write state file {:?}: {}", path, e);
+            }
+        });
+        Ok(())
+    }
+
+    // From memory when the slot is hot, otherwise from disk (keeping it hot)
+    fn load_state(&mut self, emulator: &mut Emulator) -> Result<()> {
+        if self.hot_slots.load(self.slot, emulator)? {
+            return Ok(());
+        }
+        // A damaged or older slot loads as far as it can be read
+        let state = SaveState::recover(&std::fs::read(self.state_path())?, StateEncoding::Bincode);
+        let recovery = emulator.load_partial_state(&state)?;
+        if !recovery.is_complete() {
+            println!("Slot {} didn't load whole: {}", self.slot, recovery);
+        }
+        if let Some(state) = state.into_state() {
+            self.hot_slots.insert(self.slot, state);
+        }
+        Ok(())
+    }
+
+    // Returns true when the frontend should quit
+    fn handle(&mut self, action: HotkeyAction, pressed: bool, emulator: &mut Emulator) -> bool {
+        match action {
+            HotkeyAction::SaveState => match self.save_state(emulator) {
+                Ok(()) => println!("Saved state to slot {}", self.slot),
+                Err(e) => eprintln!("Failed to save state: {}", e),
+            },
+            HotkeyAction::LoadState => match self.load_state(emulator) {
+                Ok(()) => {
+                    println!("Loaded state from slot {}", self.slot);
+                    self.follow_loaded_state(emulator);
+                }
+                Err(e) => eprintln!("Failed to load state: {}", e),
+            },
+            HotkeyAction::NextSlot => {
+                self.slot = (self.slot + 1) % STATE_SLOTS;
+                println!("State slot {}", self.slot);
+            }
+            HotkeyAction::PreviousSlot => {
+                self.slot = (self.slot + STATE_SLOTS - 1) % STATE_SLOTS;
+                println!("State slot {}", self.slot);
+            }
+            HotkeyAction::Rewind => self.rewinding = pressed && self.rewind_capacity > 0,
+            HotkeyAction::FastForward => self.fast_forward = pressed,
+            HotkeyAction::Screenshot => match self.save_screenshot(emulator) {
+                Ok(path) => println!("Saved screenshot to {:?}", path),
+                Err(e) => eprintln!("Failed to save screenshot: {}", e),
+            },
+            HotkeyAction::ExportSpc => match self.export_spc(emulator) {
+                Ok(path) => println!("Saved SPC to {:?}", path),
+                Err(e) => eprintln!("Failed to export SPC: {}", e),
+            },
+            HotkeyAction::Reset => {
+                if let Err(e) = emulator.reset() {
+                    eprintln!("Reset failed: {}", e);
+                }
+                self.rewind_buffer.clear();
+                // A movie can't replay through a reset
+                self.stop_recording();
+            }
+            HotkeyAction::ToggleRecording => {
+                if self.recording.is_some() {
+                    self.stop_recording();
+                } else {
+                    match self.start_recording(emulator) {
+                        Ok(message) => println!("{}", message),
+                        Err(e) => eprintln!("Failed to start recording: {}", e),
+                    }
+                }
+            }
+            HotkeyAction::Quit => return true,
+            HotkeyAction::ToggleBg1 => toggle_layer(emulator, Layer::Bg1),
+            HotkeyAction::ToggleBg2 => toggle_layer(emulator, Layer::Bg2),
+            HotkeyAction::ToggleBg3 => toggle_layer(emulator, Layer::Bg3),
+            HotkeyAction::ToggleBg4 => toggle_layer(emulator, Layer::Bg4),
+            HotkeyAction::ToggleObj => toggle_layer(emulator, Layer::Obj),
+            HotkeyAction::ToggleFrameBlend => {
+                self.frame_blend = !self.frame_blend;
+                println!("Frame blending {}", if self.frame_blend { "enabled" } else { "disabled" });
+            }
+            // Frontend-wide settings, handled by the frontend itself
+            HotkeyAction::NextInputProfile
+            | HotkeyAction::CycleSyncMode
+            | HotkeyAction::VolumeUp
+            | HotkeyAction::VolumeDown
+            | HotkeyAction::ToggleMute
+            | HotkeyAction::PauseMenu => {}
+        }
+        false
+    }
+
+    // Carry out a pause menu choice; volume is the frontend's. Returns true
+    // when the frontend should quit.
+    fn menu_action(&mut self, action: MenuAction, emulator: &mut Emulator) -> bool {
+        match action {
+            MenuAction::Reset => return self.handle(HotkeyAction::Reset, true, emulator),
+            MenuAction::SaveState(slot) => {
+                self.slot = slot;
+                return self.handle(HotkeyAction::SaveState, true, emulator);
+            }
+            MenuAction::LoadState(slot) => {
+                self.slot = slot;
+                return self.handle(HotkeyAction::LoadState, true, emulator);
+            }
+            MenuAction::SetFrameBlend(enabled) => self.frame_blend = enabled,
+            MenuAction::Quit => return true,
+            MenuAction::None | MenuAction::Resume | MenuAction::SetVolume(_) => {}
+        }
+        false
+    }
+
+    fn run_frame(&mut self, emulator: &mut Emulator) -> Result<()> {
+        if self.rewinding {
+            if let Some(state) = self.rewind_buffer.pop_back() {
+                emulator.load_state(&state)?;
+                self.follow_loaded_state(emulator);
+                // Run the restored frame so the picture updates
+                self.record_input(emulator);
+                emulator.step_frame()?;
+            }
+            return Ok(());
+        }
+
+        let frames = if self.fast_forward { self.fast_forward_speed.max(1.0) as u32 } else { 1 };
+        for i in 0..frames {
+            self.record_input(emulator);
+            emulator.step_frame()?;
+            if self.auto_save_sram {
+                if let Err(e) = self.sram.frame(emulator) {
+                    eprintln!("Failed to write SRAM: {}", e);
+                }
+            }
+
+            // Only the last fast-forwarded frame's audio is kept
+            if i + 1 < frames {
+                emulator.get_audio_samples();
+            }
+        }
+
+        // Periodic snapshots for rewind
+        if self.rewind_capacity > 0 {
+            self.frames_since_snapshot += frames;
+            if self.frames_since_snapshot >= REWIND_INTERVAL {
+                self.frames_since_snapshot = 0;
+                if self.rewind_buffer.len() >= self.rewind_capacity {
+                    self.rewind_buffer.pop_front();
+                }
+                self.rewind_buffer.push_back(emulator.save_state()?);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Persist unsaved battery-backed SRAM for the game
+    // Swap in a rebuilt copy of the ROM. Saves stay in this game's
+    // directory, though the new ROM's checksum would name another one.
+    fn reload_rom(&mut self, emulator: &mut Emulator, options: &RomWatchOptions) -> Result<()> {
+        let rom_path = self.rom_path.as_ref().ok_or_else(|| EmulatorError::rom_load("No ROM file to reload"))?;
+        // Parsed before the running game is touched, so a bad build leaves it running
+        let cartridge = Cartridge::load(&std::fs::read(rom_path)?)?;
+        self.flush_sram(emulator);
+        let sram = options.keep_sram.then(|| emulator.get_sram()).flatten();
+
+        emulator.load_cartridge(cartridge)?;
+        if let Some(sram) = sram.filter(|sram| !sram.is_empty()) {
+            if let Err(e) = emulator.load_sram(&sram) {
+                eprintln!("SRAM not carried over: {}", e);
+            }
+        }
+        emulator.set_peripherals(&GameProfile::load(self.paths.profile_path())?.peripherals);
+        // Loading took the cheats out with the old cartridge
+        emulator.apply_cheats(&CheatList::load(self.paths.cheats_path())?)?;
+        if let Some(state) = &options.state {
+            emulator.load_state_from_file(&state.to_string_lossy())?;
+        }
+
+        // Nothing new to write yet; this just catches up with the write counter
+        self.flush_sram(emulator);
+        self.rewind_buffer.clear();
+        self.frames_since_snapshot = 0;
+        self.stop_recording();
+        Ok(())
+    }
+
+    // Start a movie from wherever the game is. The game's saved movie is
+    // appended to instead when the game is at its last frame, i.e. its
+    // final state was loaded.
+    fn start_recording(&mut self, emulator: &mut Emulator) -> Result<String> {
+        let path = self.paths.movie_path();
+        if let Ok(mut movie) = Movie::load(&path) {
+            if movie.end_frame() == emulator.get_frame_count() && movie.resume_recording(emulator).is_ok() {
+                let message = format!("Appending to the movie in {:?} after its {} frames", path, movie.len());
+                self.recording = Some(movie);
+                return Ok(message);
+            }
+        }
+        let movie = Movie::start_recording(emulator)?;
+        let replacing = if path.exists() { format!(", replacing {:?} when stopped", path) } else { String::new() };
+        let message = format!("Recording a movie from frame {}{}", movie.start_frame(), replacing);
+        self.recording = Some(movie);
+        Ok(message)
+    }
+
+    // Stop recording and write the movie, if one is being recorded
+    fn stop_recording(&mut self) {
+        let Some(movie) = self.recording.take() else {
+            return;
+        };
+        match self.save_movie(&movie) {
+            Ok(path) => println!("Saved movie of {} frames to {:?}", movie.len(), path),
+            Err(e) => eprintln!("Failed to save movie: {}", e),
+        }
+    }
+
+    fn save_movie(&self, movie: &Movie) -> Result<PathBuf> {
+        let path = self.paths.movie_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        movie.save(&path)?;
+        Ok(path)
+    }
+
+    // The pads as set for the coming frame, when recording
+    fn record_input(&mut self, emulator: &Emulator) {
+        if let Some(movie) = self.recording.as_mut() {
+            movie.record_frame(emulator);
+        }
+    }
+
+    // A state was loaded while recording: carry on from it, dropping the
+    // frames after it, or stop when it isn't from this movie
+    fn follow_loaded_state(&mut self, emulator: &Emulator) {
+        let Some(movie) = self.recording.as_mut() else {
+            return;
+        };
+        if let Err(e) = movie.resume_recording(emulator) {
+            println!("{}; stopping the recording", e);
+            self.stop_recording();
+        }
+    }
+
+    fn flush_sram(&mut self, emulator: &Emulator) {
+        if let Err(e) = self.sram.flush(emulator) {
+            eprintln!("Failed to write SRAM: {}", e);
+        }
+    }
+
+    // The game is being closed: write its SRAM, and the mouse and Super
+    // Scope settings into its profile if they changed
+    fn close(&mut self, emulator: &Emulator) {
+        self.flush_sram(emulator);
+        self.stop_recording();
+        let path = self.paths.profile_path();
+        let result = GameProfile::load(path).and_then(|mut profile| {
+            let before = profile.peripherals.clone();
+            emulator.input().store_peripheral_settings(&mut profile.peripherals);
+            if profile.peripherals == before {
+                return Ok(());
+            }
+            profile.save(path)
+        });
+        if let Err(e) = result {
+            eprintln!("Failed to save peripheral settings: {}", e);
+        }
+    }
+
+    // Write the current frame as a binary PPM image, widescreen columns
+    // included
+    fn save_screenshot(&self, emulator: &mut Emulator) -> Result<PathBuf> {
+        std::fs::create_dir_all(self.paths.screenshot_dir())?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = self.paths.screenshot_path(timestamp, "ppm");
+
+        let frame = emulator.widescreen_frame();
+        std::fs::write(&path, frame_diff::to_ppm_with_width(frame.pixels, frame.width))?;
+        Ok(path)
+    }
+
+    // Next to the screenshots, named the same way
+    fn export_spc(&self, emulator: &mut Emulator) -> Result<PathBuf> {
+        std::fs::create_dir_all(self.paths.screenshot_dir())?;
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        let path = self.paths.screenshot_path(timestamp, "spc");
+        std::fs::write(&path, emulator.export_spc())?;
+        Ok(path)
+    }
+}
+
+// Check the loaded ROM against the ROM database (the emulator has already
+// logged header problems) and sum up what was found for the OSD. The self
+// test isn't in the database, so it gets a hint instead.
+fn rom_notice(paths: &PathConfig, emulator: &Emulator) -> Option<String> {
+    if boot_rom::is_loaded(emulator) {
+        return Some("DROP A ROM FILE TO PLAY".to_string());
+    }
+    let mut warnings = emulator.rom_warnings().to_vec();
+    let cartridge = emulator.cartridge()?;
+    match RomDatabase::load(&paths.rom_database) {
+        Ok(database) => {
+            if let Some(warning) = database.check(&cartridge.rom_data) {
+                eprintln!("ROM integrity: {}", warning);
+                warnings.push(warning);
+            }
+        }
+        Err(e) => eprintln!("Failed to read the ROM database {:?}: {}", paths.rom_database, e),
+    }
+    let first = warnings.first()?;
+    Some(match warnings.len() {
+        1 => first.summary().to_string(),
+        n => format!("{} (+{} more)", first.summary(), n - 1),
+    })
+}
+
+// Print binding mistakes, which otherwise only show as dead buttons
+fn report_input_conflicts(config: &Config) {
+    for conflict in config.input.validate(&config.hotkeys) {
+        eprintln!("Input config: {}", conflict);
+    }
+}
+
+fn game_title(emulator: &Emulator) -> String {
+    let title = emulator.get_rom_info().map(|info| info.title).unwrap_or_default();
+    format!("{} - {}", WINDOW_TITLE, title)
+}
+
+// Shown while no game is loaded and there is no library list
+fn idle_screen() -> Vec<u8> {
+    let mut frame = vec![0; 256 * 224 * 4];
+    osd::fill_rect(&mut frame, 0, 0, 256, 224, [0, 0, 0, 0xFF]);
+    for (row, text) in ["DROP A ROM FILE HERE", "(PATCHES AND SAVE STATES TOO)"].iter().enumerate() {
+        let x = (256 - osd::text_width(text)) as i32 / 2;
+        osd::draw_text(&mut frame, x, 100 + row as i32 * 12, text, [0xC0, 0xC0, 0xC0, 0xFF]);
+    }
+    frame
+}
+
+// Latency readout in the bottom-left corner of the frame
+fn draw_latency(frame: &mut [u8], latency: &LatencyTracker) {
+    let text = match latency.stats() {
+        Some(stats) => format!("LAT {:.1}MS  P95 {:.1}MS", stats.avg_ms, stats.p95_ms),
+        None => "LAT --".to_string(),
+    };
+    let y = osd::height_of(frame) as i32 - osd::GLYPH_HEIGHT as i32 - 3;
+    osd::fill_rect(frame, 1, y - 2, osd::text_width(&text) + 4, osd::GLYPH_HEIGHT + 4, [0, 0, 0, 0xFF]);
+    osd::draw_text(frame, 3, y, &text, [0xFF, 0xFF, 0x60, 0xFF]);
+}
+
+fn is_frontend_hotkey(action: HotkeyAction) -> bool {
+    matches!(
+        action,
+        HotkeyAction::NextInputProfile
+            | HotkeyAction::CycleSyncMode
+            | HotkeyAction::VolumeUp
+            | HotkeyAction::VolumeDown
+            | HotkeyAction::ToggleMute
+    )
+}
+
+// Held buttons of both pads in the bottom-right corner, one row each
+fn draw_input(frame: &mut [u8], pads: [u16; 2]) {
+    for (row, buttons) in pads.into_iter().enumerate() {
+        let text = format!("P{} {}", row + 1, movie::format_buttons(buttons));
+        let x = 256 - osd::text_width(&text) as i32 - 3;
+        let y = osd::height_of(frame) as i32 - (2 - row as i32) * (osd::GLYPH_HEIGHT as i32 + 4) - 1;
+        osd::fill_rect(frame, x - 2, y - 2, osd::text_width(&text) + 4, osd::GLYPH_HEIGHT + 4, [0, 0, 0, 0xFF]);
+        osd::draw_text(frame, x, y, &text, [0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+}
+
+// Recording marker and frame count in the top-right corner
+fn draw_recording(frame: &mut [u8], frames: usize) {
+    let text = format!("REC {}", frames);
+    let x = 256 - osd::text_width(&text) as i32 - 3;
+    osd::fill_rect(frame, x - 2, 1, osd::text_width(&text) + 4, osd::GLYPH_HEIGHT + 4, [0, 0, 0, 0xFF]);
+    osd::draw_text(frame, x, 3, &text, [0xFF, 0x40, 0x40, 0xFF]);
+}
+
+// Notice in the top-left corner of the frame
+fn draw_notice(frame: &mut [u8], text: &str) {
+    osd::fill_rect(frame, 1, 1, osd::text_width(text) + 4, osd::GLYPH_HEIGHT + 4, [0, 0, 0, 0xFF]);
+    osd::draw_text(frame, 3, 3, text, [0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+fn toggle_layer(emulator: &mut Emulator, layer: Layer) {
+    let enabled = !emulator.ppu().is_layer_enabled(layer);
+    emulator.ppu_mut().set_layer_enabled(layer, enabled);
+    println!("{:?} layer {}", layer, if enabled { "enabled" } else { "disabled" });
+}
+
+// Host key names used by input and hotkey bindings in the config file
+fn key_name(keycode: KeyCode) -> Option<&'static str> {
+    let name = match keycode {
+        KeyCode::KeyA => "A", KeyCode::KeyB => "B", KeyCode::KeyC => "C", KeyCode::KeyD => "D",
+        KeyCode::KeyE => "E", KeyCode::KeyF => "F", KeyCode::KeyG => "G", KeyCode::KeyH => "H",
+        KeyCode::KeyI => "I", KeyCode::KeyJ => "J", KeyCode::KeyK => "K", KeyCode::KeyL => "L",
+        KeyCode::KeyM => "M", KeyCode::KeyN => "N", KeyCode::KeyO => "O", KeyCode::KeyP => "P",
+        KeyCode::KeyQ => "Q", KeyCode::KeyR => "R", KeyCode::KeyS => "S", KeyCode::KeyT => "T",
+        KeyCode::KeyU => "U", KeyCode::KeyV => "V", KeyCode::KeyW => "W", KeyCode::KeyX => "X",
+        KeyCode::KeyY => "Y", KeyCode::KeyZ => "Z",
+        KeyCode::Digit0 => "0", KeyCode::Digit1 => "1", KeyCode::Digit2 => "2", KeyCode::Digit3 => "3",
+        KeyCode::Digit4 => "4", KeyCode::Digit5 => "5", KeyCode::Digit6 => "6", KeyCode::Digit7 => "7",
+        KeyCode::Digit8 => "8", KeyCode::Digit9 => "9",
+        KeyCode::F1 => "F1", KeyCode::F2 => "F2", KeyCode::F3 => "F3", KeyCode::F4 => "F4",
+        KeyCode::F5 => "F5", KeyCode::F6 => "F6", KeyCode::F7 => "F7", KeyCode::F8 => "F8",
+        KeyCode::F9 => "F9", KeyCode::F10 => "F10", KeyCode::F11 => "F11", KeyCode::F12 => "F12",
+        KeyCode::ArrowUp => "Up",
+        KeyCode::ArrowDown => "Down",
+        KeyCode::ArrowLeft => "Left",
+        KeyCode::ArrowRight => "Right",
+        KeyCode::Enter => "Return",
+        KeyCode::Space => "Space",
+        KeyCode::Tab => "Tab",
+        KeyCode::Backspace => "Backspace",
+        KeyCode::Escape => "Escape",
+        KeyCode::ShiftLeft => "LShift",
+        KeyCode::ShiftRight => "RShift",
+        KeyCode::ControlLeft => "LCtrl",
+        KeyCode::ControlRight => "RCtrl",
+        KeyCode::Insert => "Insert",
+        KeyCode::Delete => "Delete",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        KeyCode::Minus => "Minus",
+        KeyCode::Equal => "Equal",
+        _ => return None,
+    };
+    Some(name)
+}
@@ -0,0 +1,147 @@
+// On-screen text drawn directly into an RGBA8888 frame, 256 pixels wide
+// and 224 or 239 (overscan) lines high
+const WIDTH: usize = 256;
+
+pub const GLYPH_WIDTH: usize = 5;
+pub const GLYPH_HEIGHT: usize = 7;
+
+// Horizontal distance between characters, including one pixel of spacing
+pub const CHAR_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+// 5x7 glyphs for ASCII $20-$5F, one byte per row with bit 4 on the left.
+// Lowercase letters are drawn with the uppercase glyphs.
+const FONT: [[u8; GLYPH_HEIGHT]; 64] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // space
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04], // !
+    [0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00], // "
+    [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A], // #
+    [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04], // $
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03], // %
+    [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D], // &
+    [0x04, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00], // '
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02], // (
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08], // )
+    [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00], // *
+    [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x06, 0x02, 0x04], // ,
+    [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C], // .
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00], // /
+    [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E], // 0
+    [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E], // 1
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F], // 2
+    [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E], // 3
+    [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02], // 4
+    [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E], // 5
+    [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E], // 6
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08], // 7
+    [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E], // 8
+    [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C], // 9
+    [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00], // :
+    [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08], // ;
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02], // <
+    [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00], // =
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08], // >
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // ?
+    [0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E], // @
+    [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11], // A
+    [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E], // B
+    [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E], // C
+    [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C], // D
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F], // E
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10], // F
+    [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F], // G
+    [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11], // H
+    [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E], // I
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C], // J
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // K
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F], // L
+    [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11], // M
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11], // N
+    [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // O
+    [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10], // P
+    [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D], // Q
+    [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11], // R
+    [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E], // S
+    [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // T
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // U
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04], // V
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A], // W
+    [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11], // X
+    [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04], // Y
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F], // Z
+    [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E], // [
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00], // \
+    [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E], // ]
+    [0x04, 0x0A, 0x11, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F], // _
+];
+
+fn glyph(c: char) -> &'static [u8; GLYPH_HEIGHT] {
+    let c = c.to_ascii_uppercase();
+    let index = match c as u32 {
+        code @ 0x20..=0x5F => code - 0x20,
+        _ => '?' as u32 - 0x20,
+    };
+    &FONT[index as usize]
+}
+
+// Width in pixels of a line of text
+pub fn text_width(text: &str) -> usize {
+    let count = text.chars().count();
+    if count == 0 {
+        0
+    } else {
+        count * CHAR_ADVANCE - 1
+    }
+}
+
+// Draw one line of text with its top-left corner at (x, y); pixels outside
+// the frame are clipped. Returns the x position after the last character.
+pub fn draw_text(frame: &mut [u8], x: i32, y: i32, text: &str, color: [u8; 4]) -> i32 {
+    let mut pen_x = x;
+    for c in text.chars() {
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (0x10 >> col) != 0 {
+                    put_pixel(frame, pen_x + col as i32, y + row as i32, color);
+                }
+            }
+        }
+        pen_x += CHAR_ADVANCE as i32;
+    }
+    pen_x
+}
+
+// Lines in a frame
+pub fn height_of(frame: &[u8]) -> usize {
+    frame.len() / (WIDTH * 4)
+}
+
+// Fill a rectangle, clipped to the frame
+pub fn fill_rect(frame: &mut [u8], x: i32, y: i32, width: usize, height: usize, color: [u8; 4]) {
+    let x0 = x.clamp(0, WIDTH as i32) as usize;
+    let frame_height = height_of(frame) as i32;
+    let y0 = y.clamp(0, frame_height) as usize;
+    let x1 = (x + width as i32).clamp(0, WIDTH as i32) as usize;
+    let y1 = (y + height as i32).clamp(0, frame_height) as usize;
+
+    for row in y0..y1 {
+        for col in x0..x1 {
+            let offset = (row * WIDTH + col) * 4;
+            if let Some(pixel) = frame.get_mut(offset..offset + 4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    }
+}
+
+fn put_pixel(frame: &mut [u8], x: i32, y: i32, color: [u8; 4]) {
+    if x < 0 || y < 0 || x as usize >= WIDTH || y as usize >= height_of(frame) {
+        return;
+    }
+    let offset = (y as usize * WIDTH + x as usize) * 4;
+    if let Some(pixel) = frame.get_mut(offset..offset + 4) {
+        pixel.copy_from_slice(&color);
+    }
+}
@@ -0,0 +1,117 @@
+// What decides when the next frame runs. Sync-to-video runs frames on a
+// schedule at the console's rate (metrics::FRAME_RATE_HZ) and lets vsync
+// line presents up with the display; sync-to-audio runs a frame whenever
+// the audio queue drops below its target, so the sound card's clock is
+// the master and audio never crackles; free-run goes as fast as the host
+// allows, for benchmarks.
+//
+// The video schedule is a series of presentation deadlines one frame time
+// apart, not a timer restarted at each frame, so wake-up lateness doesn't
+// add up into drift. Frames start early by the time they have been taking
+// from start to present, so on a variable-refresh display (which shows a
+// frame as soon as it is presented) they appear on the deadlines instead
+// of a wake-up plus however long the frame took.
+use crate::config::SyncMode;
+use std::time::{Duration, Instant};
+
+// Audio queue fill that sync-to-audio keeps topped up to; the same level
+// the reconciler steers towards
+pub const AUDIO_TARGET_FILL: f32 = 0.5;
+
+// Sync-to-audio falls back to the timer when the queue hasn't asked for a
+// frame in this many frame times (no device, or a stalled stream)
+pub const AUDIO_STALL_FRAMES: u32 = 3;
+
+// How long before a deadline the event loop wakes to wait out the rest,
+// since OS timers can fire a millisecond or more late
+pub const WAKE_MARGIN: Duration = Duration::from_millis(2);
+
+// Share of each new start-to-present time mixed into the lead
+const LEAD_SMOOTHING: f64 = 0.125;
+
+#[derive(Debug, Clone)]
+pub struct Pacer {
+    mode: SyncMode,
+    frame_duration: Duration,
+    last_frame: Instant,
+    // Deadline the next frame should be presented by
+    next_present: Instant,
+    // Smoothed time from starting a frame to presenting it, at most a
+    // quarter of a frame
+    lead: Duration,
+    // When the frame waiting to be presented was started
+    started: Option<Instant>,
+}
+
+impl Pacer {
+    pub fn new(mode: SyncMode, frame_duration: Duration) -> Self {
+        let now = Instant::now();
+        Self { mode, frame_duration, last_frame: now, next_present: now + frame_duration, lead: Duration::ZERO, started: None }
+    }
+
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration
+    }
+
+    // How early frames start ahead of their deadline
+    pub fn lead(&self) -> Duration {
+        self.lead
+    }
+
+    pub fn mode(&self) -> SyncMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: SyncMode) {
+        self.mode = mode;
+    }
+
+    // Presents should wait for vblank only when the display is the master
+    pub fn wants_vsync(&self) -> bool {
+        self.mode != SyncMode::Free
+    }
+
+    // Whether to run a frame now; `audio_fill` is the output queue's fill
+    // from 0.0 to 1.0, None without a working output (sync-to-audio then
+    // uses the timer). Marks the frame as run when it returns true.
+    pub fn frame_due(&mut self, now: Instant, audio_fill: Option<f32>) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_frame);
+        let due = match (self.mode, audio_fill) {
+            (SyncMode::Video, _) => now + self.lead >= self.next_present,
+            (SyncMode::Audio, None) => elapsed >= self.frame_duration,
+            (SyncMode::Audio, Some(fill)) => fill < AUDIO_TARGET_FILL || elapsed >= self.frame_duration * AUDIO_STALL_FRAMES,
+            (SyncMode::Free, _) => true,
+        };
+        if !due {
+            return false;
+        }
+        self.last_frame = now;
+        self.started = Some(now);
+        // Keep to the schedule, unless a frame or more behind (the window
+        // was dragged, the host stalled), where catching up would race
+        self.next_present = if self.mode == SyncMode::Video && now < self.next_present + self.frame_duration {
+            self.next_present + self.frame_duration
+        } else {
+            now + self.frame_duration
+        };
+        true
+    }
+
+    // A frame was presented at `at`; its start-to-present time tunes how
+    // early the next ones start
+    pub fn presented(&mut self, at: Instant) {
+        let Some(started) = self.started.take() else {
+            return;
+        };
+        let took = at.saturating_duration_since(started).as_secs_f64();
+        let lead = self.lead.as_secs_f64() * (1.0 - LEAD_SMOOTHING) + took * LEAD_SMOOTHING;
+        self.lead = Duration::from_secs_f64(lead).min(self.frame_duration / 4);
+    }
+
+    // When the event loop should next wake to check `frame_due`, None to
+    // keep polling (sync-to-audio and free-run)
+    pub fn wake_at(&self) -> Option<Instant> {
+        let start = self.next_present.checked_sub(self.lead)?;
+        (self.mode == SyncMode::Video).then(|| start.checked_sub(WAKE_MARGIN).unwrap_or(start))
+    }
+}
@@ -0,0 +1,172 @@
+// Pause menu drawn over the game with the OSD font, so the basics work
+// without knowing the hotkeys. The game doesn't run while it is open.
+use super::osd::{self, CHAR_ADVANCE, GLYPH_HEIGHT};
+use super::STATE_SLOTS;
+use crate::apu::volume::MAX_VOLUME;
+
+const WIDTH: usize = 256;
+
+const ROW_HEIGHT: usize = 12;
+const BOX_WIDTH: usize = 160;
+const VOLUME_STEP: u8 = 10;
+const MAX_PERCENT: u8 = (MAX_VOLUME * 100.0) as u8;
+
+const BACKGROUND: [u8; 4] = [0x10, 0x14, 0x28, 0xFF];
+const HIGHLIGHT: [u8; 4] = [0x30, 0x48, 0x98, 0xFF];
+const TEXT: [u8; 4] = [0xE8, 0xE8, 0xE8, 0xFF];
+const DIM: [u8; 4] = [0x88, 0x90, 0xA8, 0xFF];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Resume,
+    Reset,
+    SaveState,
+    LoadState,
+    Filter,
+    Volume,
+    Quit,
+}
+
+pub const ITEMS: [MenuItem; 7] = [
+    MenuItem::Resume,
+    MenuItem::Reset,
+    MenuItem::SaveState,
+    MenuItem::LoadState,
+    MenuItem::Filter,
+    MenuItem::Volume,
+    MenuItem::Quit,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    None,
+    // Close the menu and carry on
+    Resume,
+    Reset,
+    SaveState(u8),
+    LoadState(u8),
+    // Frame blending on or off
+    SetFrameBlend(bool),
+    // Master volume in percent
+    SetVolume(u8),
+    Quit,
+}
+
+pub struct PauseMenu {
+    selected: usize,
+    slot: u8,
+    frame_blend: bool,
+    volume: u8,
+}
+
+impl PauseMenu {
+    // Opened with the running game's state slot and the current settings
+    pub fn new(slot: u8, frame_blend: bool, volume: u8) -> Self {
+        Self { selected: 0, slot: slot % STATE_SLOTS, frame_blend, volume: volume.min(MAX_PERCENT) }
+    }
+
+    pub fn selected(&self) -> MenuItem {
+        ITEMS[self.selected]
+    }
+
+    pub fn slot(&self) -> u8 {
+        self.slot
+    }
+
+    pub fn frame_blend(&self) -> bool {
+        self.frame_blend
+    }
+
+    pub fn volume(&self) -> u8 {
+        self.volume
+    }
+
+    // Handle a host key (named as in hotkey bindings). Up and Down pick an
+    // item, Left and Right change its value, Return activates it and
+    // Escape resumes.
+    pub fn handle_key(&mut self, key: &str) -> MenuAction {
+        match key {
+            "Up" => self.selected = (self.selected + ITEMS.len() - 1) % ITEMS.len(),
+            "Down" => self.selected = (self.selected + 1) % ITEMS.len(),
+            "Left" => return self.adjust(false),
+            "Right" => return self.adjust(true),
+            "Return" | "Space" => return self.activate(),
+            "Escape" => return MenuAction::Resume,
+            _ => {}
+        }
+        MenuAction::None
+    }
+
+    fn adjust(&mut self, up: bool) -> MenuAction {
+        match self.selected() {
+            MenuItem::SaveState | MenuItem::LoadState => {
+                self.slot = if up { (self.slot + 1) % STATE_SLOTS } else { (self.slot + STATE_SLOTS - 1) % STATE_SLOTS };
+                MenuAction::None
+            }
+            MenuItem::Filter => {
+                self.frame_blend = !self.frame_blend;
+                MenuAction::SetFrameBlend(self.frame_blend)
+            }
+            MenuItem::Volume => {
+                self.volume = if up { (self.volume + VOLUME_STEP).min(MAX_PERCENT) } else { self.volume.saturating_sub(VOLUME_STEP) };
+                MenuAction::SetVolume(self.volume)
+            }
+            _ => MenuAction::None,
+        }
+    }
+
+    fn activate(&mut self) -> MenuAction {
+        match self.selected() {
+            MenuItem::Resume => MenuAction::Resume,
+            MenuItem::Reset => MenuAction::Reset,
+            MenuItem::SaveState => MenuAction::SaveState(self.slot),
+            MenuItem::LoadState => MenuAction::LoadState(self.slot),
+            MenuItem::Filter => self.adjust(true),
+            MenuItem::Volume => MenuAction::None,
+            MenuItem::Quit => MenuAction::Quit,
+        }
+    }
+
+    fn label(&self, item: MenuItem) -> (&'static str, String) {
+        match item {
+            MenuItem::Resume => ("RESUME", String::new()),
+            MenuItem::Reset => ("RESET", String::new()),
+            MenuItem::SaveState => ("SAVE STATE", format!("< {} >", self.slot)),
+            MenuItem::LoadState => ("LOAD STATE", format!("< {} >", self.slot)),
+            MenuItem::Filter => ("FILTER", if self.frame_blend { "BLEND" } else { "NONE" }.to_string()),
+            MenuItem::Volume => ("VOLUME", format!("{}%", self.volume)),
+            MenuItem::Quit => ("QUIT", String::new()),
+        }
+    }
+
+    // Draw over an RGBA8888 game frame, darkening the game behind it
+    pub fn draw(&self, frame: &mut [u8]) {
+        for pixel in frame.chunks_exact_mut(4) {
+            for channel in &mut pixel[..3] {
+                *channel /= 3;
+            }
+        }
+
+        let height = (ITEMS.len() + 2) * ROW_HEIGHT + 6;
+        let left = ((WIDTH - BOX_WIDTH) / 2) as i32;
+        let top = (osd::height_of(frame).saturating_sub(height) / 2) as i32;
+        osd::fill_rect(frame, left, top, BOX_WIDTH, height, BACKGROUND);
+        osd::draw_text(frame, left + 8, top + 4, "PAUSED", TEXT);
+        osd::fill_rect(frame, left + 4, top + 14, BOX_WIDTH - 8, 1, DIM);
+
+        for (row, &item) in ITEMS.iter().enumerate() {
+            let y = top + ((row + 1) * ROW_HEIGHT + 6) as i32;
+            if row == self.selected {
+                osd::fill_rect(frame, left + 4, y - 2, BOX_WIDTH - 8, GLYPH_HEIGHT + 4, HIGHLIGHT);
+            }
+            let (name, value) = self.label(item);
+            osd::draw_text(frame, left + 8, y, name, TEXT);
+            let x = left + BOX_WIDTH as i32 - 8 - osd::text_width(&value) as i32;
+            osd::draw_text(frame, x, y, &value, TEXT);
+        }
+
+        let hint = "ENTER: SELECT  ESC: BACK";
+        let x = (WIDTH - hint.len() * CHAR_ADVANCE) as i32 / 2;
+        osd::draw_text(frame, x, top + height as i32 - 10, hint, DIM);
+    }
+}
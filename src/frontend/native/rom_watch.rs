@@ -0,0 +1,73 @@
+// Reloading the ROM when it is rebuilt, for a homebrew edit-assemble-test
+// loop. Assemblers and linkers write the output in several steps, so a
+// change only counts once the file has stopped changing for a moment.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// How often the file is looked at
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+// What `--watch` does on each reload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RomWatchOptions {
+    // Carry the running game's SRAM over to the rebuilt ROM
+    pub keep_sram: bool,
+    // Save state loaded after each reload, to start testing at a known point
+    pub state: Option<PathBuf>,
+}
+
+impl Default for RomWatchOptions {
+    fn default() -> Self {
+        Self { keep_sram: true, state: None }
+    }
+}
+
+// Modification time and size, which together change on every rebuild
+type Stamp = Option<(SystemTime, u64)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[derive(Debug)]
+pub struct RomWatcher {
+    path: PathBuf,
+    // Stamp of the loaded ROM
+    loaded: Stamp,
+    // Stamp seen at the last look, while it differs from `loaded`
+    changing: Option<Stamp>,
+    last_check: Option<Instant>,
+}
+
+impl RomWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), loaded: stamp(path), changing: None, last_check: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // True once the file has changed and then looked the same for one
+    // poll interval; a missing file (mid-build) doesn't count
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if self.last_check.is_some_and(|last| now.saturating_duration_since(last) < POLL_INTERVAL) {
+            return false;
+        }
+        self.last_check = Some(now);
+        let current = stamp(&self.path);
+        if current == self.loaded || current.is_none() {
+            self.changing = None;
+            return false;
+        }
+        if self.changing != Some(current) {
+            self.changing = Some(current);
+            return false;
+        }
+        self.loaded = current;
+        self.changing = None;
+        true
+    }
+}
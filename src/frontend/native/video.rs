@@ -1,7 +1,45 @@
 use crate::{Result, EmulatorError};
-use wgpu::{self, util::DeviceExt};
+use crate::ppu::core::{OVERSCAN_HEIGHT, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::ppu::frame_blend::FrameBlender;
+use crate::ppu::pixel::{self, FRAME_BUFFER_FORMAT};
+use crate::debug::spans;
+use crate::embed::Frame;
+use crate::ppu::pixel::PixelFormat;
+use crate::sink::VideoSink;
+use wgpu::{self, util::DeviceExt, util::StagingBelt};
 use winit::window::Window;
 
+// Size of the largest RGBA8888 frame (239 lines with overscan); rows are
+// 1024 bytes, already a multiple of wgpu's 256-byte copy alignment
+const FRAME_BYTES: u64 = (SCREEN_WIDTH * OVERSCAN_HEIGHT * 4) as u64;
+
+// Where a frame goes in the window: as large as fits with square pixels,
+// centred, with black bars filling the rest
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Letterbox {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Letterbox {
+    pub fn fit(window: (u32, u32), frame: (u32, u32)) -> Self {
+        let (window_width, window_height) = (window.0.max(1) as f32, window.1.max(1) as f32);
+        let scale = (window_width / frame.0 as f32).min(window_height / frame.1 as f32);
+        let (width, height) = (frame.0 as f32 * scale, frame.1 as f32 * scale);
+        Self { x: (window_width - width) / 2.0, y: (window_height - height) / 2.0, width, height }
+    }
+
+    // Frame pixel under a window position; outside the frame on the bars
+    pub fn to_frame(&self, position: (f64, f64), frame: (u32, u32)) -> Option<(u16, u16)> {
+        let x = (position.0 - self.x as f64) * frame.0 as f64 / self.width as f64;
+        let y = (position.1 - self.y as f64) * frame.1 as f64 / self.height as f64;
+        let inside = (0.0..frame.0 as f64).contains(&x) && (0.0..frame.1 as f64).contains(&y);
+        inside.then_some((x as u16, y as u16))
+    }
+}
+
 pub struct VideoRenderer {
     instance: wgpu::Instance,
     adapter: wgpu::Adapter,
@@ -11,10 +49,23 @@ pub struct VideoRenderer {
     render_pipeline: wgpu::RenderPipeline,
     vertex_buffer: wgpu::Buffer,
     texture: wgpu::Texture,
+    // Lines of the last frame; the texture holds the tallest, this many
+    // of its rows are drawn
+    frame_height: u32,
     texture_view: wgpu::TextureView,
     sampler: wgpu::Sampler,
     bind_group: wgpu::BindGroup,
     scale: u32,
+    vsync: bool,
+    // Scratch frame the emulator output is converted into and overlays are
+    // drawn onto; uploaded on the next render
+    rgba_buffer: Vec<u8>,
+    frame_pending: bool,
+    blender: FrameBlender,
+    // Frames are copied through reused staging chunks into a GPU-side
+    // buffer, then to the texture in the same encoder as the draw
+    staging_belt: StagingBelt,
+    upload_buffer: wgpu::Buffer,
 }
 
 impl VideoRenderer {
@@ -79,8 +130,8 @@ impl VideoRenderer {
         
         // Create texture for SNES frame buffer
         let texture_size = wgpu::Extent3d {
-            width: 256,
-            height: 224,
+            width: SCREEN_WIDTH as u32,
+            height: OVERSCAN_HEIGHT as u32,
             depth_or_array_layers: 1,
         };
         
@@ -189,19 +240,17 @@ impl VideoRenderer {
         });
         
         // Create vertex buffer
-        let vertices = &[
-            Vertex { position: [-1.0, -1.0, 0.0], tex_coords: [0.0, 1.0] },
-            Vertex { position: [ 1.0, -1.0, 0.0], tex_coords: [1.0, 1.0] },
-            Vertex { position: [ 1.0,  1.0, 0.0], tex_coords: [1.0, 0.0] },
-            Vertex { position: [-1.0, -1.0, 0.0], tex_coords: [0.0, 1.0] },
-            Vertex { position: [ 1.0,  1.0, 0.0], tex_coords: [1.0, 0.0] },
-            Vertex { position: [-1.0,  1.0, 0.0], tex_coords: [0.0, 0.0] },
-        ];
-        
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(vertices),
-            usage: wgpu::BufferUsages::VERTEX,
+            contents: bytemuck::cast_slice(&quad(SCREEN_HEIGHT as u32)),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        
+        let upload_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("SNES Frame Upload Buffer"),
+            size: FRAME_BYTES,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
         });
         
         Ok(Self {
@@ -213,58 +262,101 @@ impl VideoRenderer {
             render_pipeline,
             vertex_buffer,
             texture,
+            frame_height: SCREEN_HEIGHT as u32,
             texture_view,
             sampler,
             bind_group,
             scale,
+            vsync: true,
+            rgba_buffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 4],
+            frame_pending: false,
+            blender: FrameBlender::default(),
+            staging_belt: StagingBelt::new(FRAME_BYTES),
+            upload_buffer,
         })
     }
     
+    // Where frames are drawn in a window of `size`
+    pub fn letterbox(&self, size: (u32, u32)) -> Letterbox {
+        Letterbox::fit(size, (SCREEN_WIDTH as u32, self.frame_height))
+    }
+
+    pub fn frame_height(&self) -> u32 {
+        self.frame_height
+    }
+    
+    // Takes effect on the next rendered frame
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
+    }
+    
+    pub fn frame_blend(&self) -> bool {
+        self.blender.is_enabled()
+    }
+    
+    pub fn set_frame_blend(&mut self, enabled: bool) {
+        self.blender.set_enabled(enabled);
+    }
+    
     pub fn update_frame(&mut self, frame_buffer: &[u8]) {
-        // Convert RGB565 to RGBA8888
-        let mut rgba_buffer = vec![0u8; 256 * 224 * 4];
-        for (i, chunk) in frame_buffer.chunks_exact(2).enumerate() {
-            if i >= 256 * 224 {
-                break;
-            }
-            let pixel = u16::from_le_bytes([chunk[0], chunk[1]]);
-            let r = ((pixel >> 11) & 0x1F) as u8;
-            let g = ((pixel >> 5) & 0x3F) as u8;
-            let b = (pixel & 0x1F) as u8;
-            
-            // Expand to 8-bit
-            let idx = i * 4;
-            if idx + 3 < rgba_buffer.len() {
-                rgba_buffer[idx] = (r << 3) | (r >> 2);
-                rgba_buffer[idx + 1] = (g << 2) | (g >> 4);
-                rgba_buffer[idx + 2] = (b << 3) | (b >> 2);
-                rgba_buffer[idx + 3] = 255;
-            }
+        self.update_frame_with(frame_buffer, |_| {});
+    }
+    
+    // Set the next frame to present, letting `overlay` draw OSD elements
+    // onto the converted RGBA8888 frame before it is uploaded
+    pub fn update_frame_with(&mut self, frame_buffer: &[u8], overlay: impl FnOnce(&mut [u8])) {
+        self.set_frame(FRAME_BUFFER_FORMAT, frame_buffer, overlay);
+    }
+    
+    fn set_frame(&mut self, format: PixelFormat, frame_buffer: &[u8], overlay: impl FnOnce(&mut [u8])) {
+        let height = pixel::frame_lines(format, frame_buffer.len()).min(OVERSCAN_HEIGHT);
+        if height as u32 != self.frame_height {
+            self.frame_height = height as u32;
+            self.queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&quad(self.frame_height)));
         }
-        
-        // Update texture
-        self.queue.write_texture(
+        self.rgba_buffer.resize(SCREEN_WIDTH * height * 4, 0);
+        pixel::convert_to_rgba(format, frame_buffer, &mut self.rgba_buffer);
+        self.blender.apply(&mut self.rgba_buffer);
+        overlay(&mut self.rgba_buffer);
+        self.frame_pending = true;
+    }
+    
+    // Record the upload of a pending frame into `encoder`
+    fn upload_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !std::mem::take(&mut self.frame_pending) {
+            return;
+        }
+        let Some(size) = wgpu::BufferSize::new(self.rgba_buffer.len() as u64) else {
+            return;
+        };
+        self.staging_belt
+            .write_buffer(encoder, &self.upload_buffer, 0, size, &self.device)
+            .copy_from_slice(&self.rgba_buffer);
+        encoder.copy_buffer_to_texture(
+            wgpu::ImageCopyBuffer {
+                buffer: &self.upload_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(256 * 4),
+                    rows_per_image: Some(self.frame_height),
+                },
+            },
             wgpu::ImageCopyTexture {
                 texture: &self.texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
-            &rgba_buffer,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(256 * 4),
-                rows_per_image: Some(224),
-            },
             wgpu::Extent3d {
-                width: 256,
-                height: 224,
+                width: SCREEN_WIDTH as u32,
+                height: self.frame_height,
                 depth_or_array_layers: 1,
             },
         );
     }
     
     pub fn render(&mut self, window: &Window) -> Result<()> {
+        let _span = spans::span("present", "frontend");
         // Create surface for this frame
         let surface = self.instance.create_surface(window)
             .map_err(|e| EmulatorError::VideoError(format!("Failed to create surface: {}", e)))?;
@@ -276,7 +368,7 @@ impl VideoRenderer {
             format: self.surface_format,
             width: size.width,
             height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
+            present_mode: if self.vsync { wgpu::PresentMode::Fifo } else { wgpu::PresentMode::AutoNoVsync },
             alpha_mode: wgpu::CompositeAlphaMode::Auto,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
@@ -291,6 +383,7 @@ impl VideoRenderer {
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("Render Encoder"),
         });
+        self.upload_frame(&mut encoder);
         
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -313,19 +406,47 @@ impl VideoRenderer {
                 occlusion_query_set: None,
             });
             
+            let view = self.letterbox((size.width, size.height));
+            render_pass.set_viewport(view.x, view.y, view.width, view.height, 0.0, 1.0);
             render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_bind_group(0, &self.bind_group, &[]);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             render_pass.draw(0..6, 0..1);
         }
         
+        self.staging_belt.finish();
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
         
+        // Chunks become writable again once the GPU is done with them
+        self.staging_belt.recall();
+        
+        Ok(())
+    }
+}
+
+// Uploads the frame for the next render; drawn with the window's blending
+impl VideoSink for VideoRenderer {
+    fn present_frame(&mut self, frame: &Frame) -> Result<()> {
+        let len = (frame.pitch() * frame.height).min(frame.pixels.len());
+        self.set_frame(frame.format, &frame.pixels[..len], |_| {});
         Ok(())
     }
 }
 
+// Full-viewport quad showing the top `frame_height` rows of the texture
+fn quad(frame_height: u32) -> [Vertex; 6] {
+    let bottom = frame_height as f32 / OVERSCAN_HEIGHT as f32;
+    [
+        Vertex { position: [-1.0, -1.0, 0.0], tex_coords: [0.0, bottom] },
+        Vertex { position: [ 1.0, -1.0, 0.0], tex_coords: [1.0, bottom] },
+        Vertex { position: [ 1.0,  1.0, 0.0], tex_coords: [1.0, 0.0] },
+        Vertex { position: [-1.0, -1.0, 0.0], tex_coords: [0.0, bottom] },
+        Vertex { position: [ 1.0,  1.0, 0.0], tex_coords: [1.0, 0.0] },
+        Vertex { position: [-1.0,  1.0, 0.0], tex_coords: [0.0, 0.0] },
+    ]
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct Vertex {
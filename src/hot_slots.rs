@@ -0,0 +1,140 @@
+// Save states kept in memory for instant save and load. The most recently
+// used slots are held uncompressed; older ones are dropped from memory
+// (their files stay on disk) unless pinned, so practice loops that reload
+// the same state every few seconds never wait on decompression or disk.
+use crate::emulator::Emulator;
+use crate::savestate::SaveState;
+use crate::Result;
+
+#[derive(Debug, Clone)]
+struct HotSlot {
+    slot: u8,
+    state: SaveState,
+    pinned: bool,
+    // Value of HotSlots::clock when last saved or loaded
+    last_used: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HotSlots {
+    // Unpinned slots kept in memory; 0 keeps only pinned ones
+    capacity: usize,
+    slots: Vec<HotSlot>,
+    clock: u64,
+}
+
+impl HotSlots {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, slots: Vec::new(), clock: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    // Snapshot the emulator into `slot`
+    pub fn save(&mut self, slot: u8, emulator: &Emulator) -> Result<()> {
+        self.insert(slot, emulator.save_state()?);
+        Ok(())
+    }
+
+    // Load `slot` into the emulator; false if it isn't held in memory
+    pub fn load(&mut self, slot: u8, emulator: &mut Emulator) -> Result<bool> {
+        let clock = self.tick();
+        let Some(hot) = self.slots.iter_mut().find(|hot| hot.slot == slot) else {
+            return Ok(false);
+        };
+        hot.last_used = clock;
+        emulator.load_state(&hot.state)?;
+        Ok(true)
+    }
+
+    // Store a state, e.g. one just read from disk
+    pub fn insert(&mut self, slot: u8, state: SaveState) {
+        let clock = self.tick();
+        match self.slots.iter_mut().find(|hot| hot.slot == slot) {
+            Some(hot) => {
+                hot.state = state;
+                hot.last_used = clock;
+            }
+            None => self.slots.push(HotSlot { slot, state, pinned: false, last_used: clock }),
+        }
+        self.evict();
+    }
+
+    pub fn get(&self, slot: u8) -> Option<&SaveState> {
+        self.slots.iter().find(|hot| hot.slot == slot).map(|hot| &hot.state)
+    }
+
+    pub fn contains(&self, slot: u8) -> bool {
+        self.get(slot).is_some()
+    }
+
+    // Keep `slot` in memory regardless of capacity. Returns false if the
+    // slot isn't held (save or insert it first).
+    pub fn pin(&mut self, slot: u8, pinned: bool) -> bool {
+        let Some(hot) = self.slots.iter_mut().find(|hot| hot.slot == slot) else {
+            return false;
+        };
+        hot.pinned = pinned;
+        if !pinned {
+            self.evict();
+        }
+        true
+    }
+
+    pub fn is_pinned(&self, slot: u8) -> bool {
+        self.slots.iter().any(|hot| hot.slot == slot && hot.pinned)
+    }
+
+    pub fn remove(&mut self, slot: u8) -> Option<SaveState> {
+        let index = self.slots.iter().position(|hot| hot.slot == slot)?;
+        Some(self.slots.remove(index).state)
+    }
+
+    // Slots held in memory, in slot order
+    pub fn slots(&self) -> Vec<u8> {
+        let mut slots: Vec<u8> = self.slots.iter().map(|hot| hot.slot).collect();
+        slots.sort_unstable();
+        slots
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    // Drop every state, pinned or not (after a reset or loading another game)
+    pub fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    // Drop least recently used unpinned slots beyond capacity
+    fn evict(&mut self) {
+        while self.slots.iter().filter(|hot| !hot.pinned).count() > self.capacity {
+            let oldest = self.slots
+                .iter()
+                .enumerate()
+                .filter(|(_, hot)| !hot.pinned)
+                .min_by_key(|(_, hot)| hot.last_used)
+                .map(|(index, _)| index);
+            match oldest {
+                Some(index) => self.slots.remove(index),
+                None => break,
+            };
+        }
+    }
+}
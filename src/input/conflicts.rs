@@ -0,0 +1,230 @@
+// Checks of the controller and hotkey bindings for mistakes that show up
+// only as buttons that don't work: one key on two buttons, a button on a
+// key no keyboard has, or a game button on a key a hotkey takes first.
+// The native frontend reports them at startup; GUIs can call
+// InputConfig::validate as the user edits mappings.
+use crate::config::{ControllerMapping, HotkeyConfig, InputConfig};
+use crate::input::hotkeys::{Binding, HotkeyAction, Modifiers};
+use crate::EmulatorError;
+use std::collections::HashMap;
+use std::fmt;
+
+// Key names the native frontend produces; bindings to anything else never fire
+pub const KEY_NAMES: [&str; 69] = [
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M",
+    "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z",
+    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9",
+    "F1", "F2", "F3", "F4", "F5", "F6", "F7", "F8", "F9", "F10", "F11", "F12",
+    "Up", "Down", "Left", "Right", "Return", "Space", "Tab", "Backspace", "Escape",
+    "LShift", "RShift", "LCtrl", "RCtrl", "Insert", "Delete", "Home", "End",
+    "PageUp", "PageDown", "Minus", "Equal",
+];
+
+pub fn is_key_name(key: &str) -> bool {
+    KEY_NAMES.iter().any(|name| name.eq_ignore_ascii_case(key))
+}
+
+// A controller button: player (0 or 1) and the button's mapping field name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ButtonRef {
+    pub player: u8,
+    pub button: &'static str,
+    // Input profile whose mappings it is in, None for the base mappings
+    pub profile: Option<String>,
+}
+
+impl fmt::Display for ButtonRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "player {} {}", self.player + 1, self.button)?;
+        if let Some(profile) = &self.profile {
+            write!(f, " (profile '{}')", profile)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputConflict {
+    // Two buttons on one key, which always press together
+    DuplicateBinding { key: String, first: ButtonRef, second: ButtonRef },
+    // A button on no key, or on a key name the frontend doesn't know
+    UnreachableButton { button: ButtonRef, key: String },
+    // A game button on a key a hotkey takes, so the game never sees it
+    HotkeyConflict { button: ButtonRef, key: String, action: HotkeyAction },
+    // Two hotkeys on one binding; only the later one works
+    DuplicateHotkey { binding: String, first: HotkeyAction, second: HotkeyAction },
+    // A hotkey binding that doesn't parse or names an unknown key
+    InvalidHotkey { action: HotkeyAction, binding: String, reason: String },
+    // An input profile that names an unknown button, and can't be used
+    InvalidProfile { profile: String, reason: String },
+}
+
+impl fmt::Display for InputConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputConflict::DuplicateBinding { key, first, second } => write!(
+                f,
+                "{} and {} are both bound to '{}' and press together; bind one to another key",
+                first, second, key
+            ),
+            InputConflict::UnreachableButton { button, key } if key.trim().is_empty() => {
+                write!(f, "{} has no key and can't be pressed; bind it to a key", button)
+            }
+            InputConflict::UnreachableButton { button, key } => {
+                write!(f, "{} is bound to unknown key '{}' and can't be pressed; use a key name such as 'Z' or 'Return'", button, key)
+            }
+            InputConflict::HotkeyConflict { button, key, action } => write!(
+                f,
+                "{} is bound to '{}', which the {} hotkey takes first; rebind one of them",
+                button,
+                key,
+                action.config_name()
+            ),
+            InputConflict::DuplicateHotkey { binding, first, second } => write!(
+                f,
+                "hotkeys {} and {} are both bound to '{}' and only {} works; rebind one of them",
+                first.config_name(),
+                second.config_name(),
+                binding,
+                second.config_name()
+            ),
+            InputConflict::InvalidHotkey { action, binding, reason } => {
+                write!(f, "hotkey {} = '{}' is not usable: {}", action.config_name(), binding, reason)
+            }
+            // The reason names the profile
+            InputConflict::InvalidProfile { reason, .. } => write!(f, "{}; fix or remove that binding", reason),
+        }
+    }
+}
+
+// Every problem with the bindings, checking the base mappings and each
+// input profile's. `known_key` tells the key names the host produces
+// (compared case-insensitively; hotkeys pass them lowercased).
+pub fn check(input: &InputConfig, hotkeys: &HotkeyConfig, known_key: impl Fn(&str) -> bool) -> Vec<InputConflict> {
+    let mut conflicts = Vec::new();
+
+    // Plain key hotkeys, which take a key before the controllers see it
+    let mut hotkey_keys: HashMap<String, HotkeyAction> = HashMap::new();
+    let mut seen_bindings: Vec<(Binding, HotkeyAction)> = Vec::new();
+    for (action, text) in hotkeys.bindings() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let binding = match Binding::parse(text) {
+            Ok(binding) => binding,
+            Err(e) => {
+                conflicts.push(InputConflict::InvalidHotkey { action, binding: text.to_string(), reason: e.to_string() });
+                continue;
+            }
+        };
+        if let Binding::Key { key, modifiers } = &binding {
+            if !known_key(key) {
+                conflicts.push(InputConflict::InvalidHotkey {
+                    action,
+                    binding: text.to_string(),
+                    reason: format!("unknown key '{}'", key),
+                });
+                continue;
+            }
+            if *modifiers == Modifiers::default() {
+                hotkey_keys.insert(key.clone(), action);
+            }
+        }
+        if let Some((_, first)) = seen_bindings.iter().find(|(seen, _)| *seen == binding) {
+            conflicts.push(InputConflict::DuplicateHotkey { binding: text.to_string(), first: *first, second: action });
+        }
+        seen_bindings.push((binding, action));
+    }
+
+    let mut mapping_sets = vec![(None, [input.player1.clone(), input.player2.clone()])];
+    for name in input.profiles.keys() {
+        let mut profiled = input.clone();
+        match profiled.set_active_profile(Some(name)) {
+            Ok(()) => {
+                let [player1, player2] = profiled.mappings();
+                mapping_sets.push((Some(name.clone()), [player1.clone(), player2.clone()]));
+            }
+            Err(e) => {
+                let reason = match e {
+                    EmulatorError::ConfigError(message) => message,
+                    e => e.to_string(),
+                };
+                conflicts.push(InputConflict::InvalidProfile { profile: name.clone(), reason });
+            }
+        }
+    }
+
+    for (profile, mappings) in &mapping_sets {
+        let mut keys: HashMap<String, ButtonRef> = HashMap::new();
+        for (player, mapping) in mappings.iter().enumerate() {
+            for (button, key) in named_bindings(mapping) {
+                let button = ButtonRef { player: player as u8, button, profile: profile.clone() };
+                if !known_key(key) {
+                    conflicts.push(InputConflict::UnreachableButton { button, key: key.to_string() });
+                    continue;
+                }
+                let normalized = key.trim().to_ascii_lowercase();
+                if let Some(&action) = hotkey_keys.get(&normalized) {
+                    conflicts.push(InputConflict::HotkeyConflict { button: button.clone(), key: key.to_string(), action });
+                }
+                match keys.get(&normalized) {
+                    Some(first) => conflicts.push(InputConflict::DuplicateBinding {
+                        key: key.to_string(),
+                        first: first.clone(),
+                        second: button,
+                    }),
+                    None => {
+                        keys.insert(normalized, button);
+                    }
+                }
+            }
+        }
+    }
+
+    // A profile repeats the base mappings it doesn't override; report each
+    // problem under the first mappings that have it
+    let mut unique: Vec<InputConflict> = Vec::new();
+    for conflict in conflicts {
+        if !unique.iter().any(|seen| same_problem(seen, &conflict)) {
+            unique.push(conflict);
+        }
+    }
+    unique
+}
+
+// Field names with the keys bound to them
+fn named_bindings(mapping: &ControllerMapping) -> [(&'static str, &str); 12] {
+    [
+        ("up", &mapping.up),
+        ("down", &mapping.down),
+        ("left", &mapping.left),
+        ("right", &mapping.right),
+        ("a", &mapping.a),
+        ("b", &mapping.b),
+        ("x", &mapping.x),
+        ("y", &mapping.y),
+        ("l", &mapping.l),
+        ("r", &mapping.r),
+        ("select", &mapping.select),
+        ("start", &mapping.start),
+    ]
+}
+
+// The same conflict, ignoring which mappings it was found in
+fn same_problem(a: &InputConflict, b: &InputConflict) -> bool {
+    fn strip(conflict: &InputConflict) -> InputConflict {
+        let mut conflict = conflict.clone();
+        match &mut conflict {
+            InputConflict::DuplicateBinding { first, second, .. } => {
+                first.profile = None;
+                second.profile = None;
+            }
+            InputConflict::UnreachableButton { button, .. } | InputConflict::HotkeyConflict { button, .. } => {
+                button.profile = None;
+            }
+            InputConflict::DuplicateHotkey { .. } | InputConflict::InvalidHotkey { .. } | InputConflict::InvalidProfile { .. } => {}
+        }
+        conflict
+    }
+    strip(a) == strip(b)
+}
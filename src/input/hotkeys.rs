@@ -0,0 +1,235 @@
+// Emulator hotkeys, kept separate from game controller input
+use crate::config::HotkeyConfig;
+use crate::input::controller::{
+    BUTTON_A, BUTTON_B, BUTTON_X, BUTTON_Y,
+    BUTTON_L, BUTTON_R, BUTTON_START, BUTTON_SELECT,
+    BUTTON_UP, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT,
+};
+use crate::{Result, EmulatorError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HotkeyAction {
+    SaveState,
+    LoadState,
+    NextSlot,
+    PreviousSlot,
+    Rewind,
+    FastForward,
+    Screenshot,
+    ExportSpc,
+    Reset,
+    Quit,
+    ToggleBg1,
+    ToggleBg2,
+    ToggleBg3,
+    ToggleBg4,
+    ToggleObj,
+    ToggleFrameBlend,
+    NextInputProfile,
+    CycleSyncMode,
+    PauseMenu,
+    VolumeUp,
+    VolumeDown,
+    ToggleMute,
+    ToggleRecording,
+}
+
+impl HotkeyAction {
+    // Held actions stay active until their binding is released
+    pub fn is_held(self) -> bool {
+        matches!(self, HotkeyAction::Rewind | HotkeyAction::FastForward)
+    }
+
+    // Name of the action's setting in [hotkeys]
+    pub fn config_name(self) -> &'static str {
+        match self {
+            HotkeyAction::SaveState => "save_state",
+            HotkeyAction::LoadState => "load_state",
+            HotkeyAction::NextSlot => "next_slot",
+            HotkeyAction::PreviousSlot => "previous_slot",
+            HotkeyAction::Rewind => "rewind",
+            HotkeyAction::FastForward => "fast_forward",
+            HotkeyAction::Screenshot => "screenshot",
+            HotkeyAction::ExportSpc => "export_spc",
+            HotkeyAction::Reset => "reset",
+            HotkeyAction::Quit => "quit",
+            HotkeyAction::ToggleBg1 => "toggle_bg1",
+            HotkeyAction::ToggleBg2 => "toggle_bg2",
+            HotkeyAction::ToggleBg3 => "toggle_bg3",
+            HotkeyAction::ToggleBg4 => "toggle_bg4",
+            HotkeyAction::ToggleObj => "toggle_obj",
+            HotkeyAction::ToggleFrameBlend => "toggle_frame_blend",
+            HotkeyAction::NextInputProfile => "next_input_profile",
+            HotkeyAction::CycleSyncMode => "cycle_sync_mode",
+            HotkeyAction::PauseMenu => "pause_menu",
+            HotkeyAction::VolumeUp => "volume_up",
+            HotkeyAction::VolumeDown => "volume_down",
+            HotkeyAction::ToggleMute => "toggle_mute",
+            HotkeyAction::ToggleRecording => "toggle_recording",
+        }
+    }
+}
+
+// Host modifier keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub shift: bool,
+    pub alt: bool,
+}
+
+// A parsed binding: either a host key with modifiers or a controller button combo
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    Key { key: String, modifiers: Modifiers },
+    Pad { buttons: u16 },
+}
+
+impl Binding {
+    // Parse "F5", "Ctrl+R" or "Pad:Select+L" (names are case-insensitive)
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(EmulatorError::config("Empty hotkey binding"));
+        }
+
+        if let Some(combo) = strip_prefix_ignore_case(text, "pad:") {
+            let mut buttons = 0;
+            for name in combo.split('+') {
+                buttons |= pad_button(name.trim()).ok_or_else(|| {
+                    EmulatorError::config(format!("Unknown controller button '{}' in hotkey '{}'", name, text))
+                })?;
+            }
+            return Ok(Binding::Pad { buttons });
+        }
+
+        let mut modifiers = Modifiers::default();
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or_default();
+        for part in parts {
+            match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                _ => return Err(EmulatorError::config(format!("Unknown modifier '{}' in hotkey '{}'", part, text))),
+            }
+        }
+        if key.is_empty() {
+            return Err(EmulatorError::config(format!("Missing key in hotkey '{}'", text)));
+        }
+
+        // Accept common aliases for the frontend key names
+        let key = match key.to_ascii_lowercase().as_str() {
+            "enter" => "return".to_string(),
+            "esc" => "escape".to_string(),
+            other => other.to_string(),
+        };
+
+        Ok(Binding::Key { key, modifiers })
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    match text.get(..prefix.len()) {
+        Some(head) if head.eq_ignore_ascii_case(prefix) => Some(&text[prefix.len()..]),
+        _ => None,
+    }
+}
+
+pub(crate) fn pad_button(name: &str) -> Option<u16> {
+    let button = match name.to_ascii_lowercase().as_str() {
+        "a" => BUTTON_A,
+        "b" => BUTTON_B,
+        "x" => BUTTON_X,
+        "y" => BUTTON_Y,
+        "l" => BUTTON_L,
+        "r" => BUTTON_R,
+        "start" => BUTTON_START,
+        "select" => BUTTON_SELECT,
+        "up" => BUTTON_UP,
+        "down" => BUTTON_DOWN,
+        "left" => BUTTON_LEFT,
+        "right" => BUTTON_RIGHT,
+        _ => return None,
+    };
+    Some(button)
+}
+
+pub struct HotkeyManager {
+    bindings: Vec<(Binding, HotkeyAction)>,
+    // Pad combos active on the previous poll, for edge detection
+    active_pad: Vec<HotkeyAction>,
+}
+
+impl HotkeyManager {
+    pub fn new() -> Self {
+        Self {
+            bindings: Vec::new(),
+            active_pad: Vec::new(),
+        }
+    }
+
+    pub fn from_config(config: &HotkeyConfig) -> Result<Self> {
+        let mut manager = Self::new();
+        for (action, text) in config.bindings() {
+            // Empty strings leave an action unbound
+            if text.trim().is_empty() {
+                continue;
+            }
+            manager.bind(Binding::parse(text)?, action);
+        }
+        Ok(manager)
+    }
+
+    pub fn bind(&mut self, binding: Binding, action: HotkeyAction) {
+        self.bindings.retain(|(b, _)| *b != binding);
+        self.bindings.push((binding, action));
+    }
+
+    pub fn unbind(&mut self, action: HotkeyAction) {
+        self.bindings.retain(|(_, a)| *a != action);
+    }
+
+    pub fn bindings(&self) -> &[(Binding, HotkeyAction)] {
+        &self.bindings
+    }
+
+    // Look up the action bound to a host key name with the given modifiers
+    pub fn match_key(&self, key: &str, modifiers: Modifiers) -> Option<HotkeyAction> {
+        self.bindings.iter().find_map(|(binding, action)| match binding {
+            Binding::Key { key: k, modifiers: m } if k.eq_ignore_ascii_case(key) && *m == modifiers => Some(*action),
+            _ => None,
+        })
+    }
+
+    // Feed the current controller state; returns (action, pressed) transitions
+    pub fn poll_pad(&mut self, buttons: u16) -> Vec<(HotkeyAction, bool)> {
+        let mut transitions = Vec::new();
+        let mut active = Vec::new();
+
+        for (binding, action) in &self.bindings {
+            if let Binding::Pad { buttons: combo } = binding {
+                if *combo != 0 && (buttons & combo) == *combo {
+                    active.push(*action);
+                    if !self.active_pad.contains(action) {
+                        transitions.push((*action, true));
+                    }
+                }
+            }
+        }
+        for action in &self.active_pad {
+            if !active.contains(action) {
+                transitions.push((*action, false));
+            }
+        }
+
+        self.active_pad = active;
+        transitions
+    }
+}
+
+impl Default for HotkeyManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
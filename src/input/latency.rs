@@ -0,0 +1,137 @@
+// End-to-end input latency measurement: host input event -> auto-joypad
+// latch -> presentation of the frame that latched it.
+//
+// Timestamps are milliseconds on any monotonic clock chosen by the frontend
+// (Instant on native, performance.now() on the web).
+use std::collections::VecDeque;
+use std::fmt;
+
+// Completed samples kept for statistics
+const SAMPLE_CAPACITY: usize = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencySample {
+    pub input_to_latch_ms: f64,
+    pub latch_to_present_ms: f64,
+    // Frames emulated between the input event and the latch
+    pub frames_to_latch: u64,
+}
+
+impl LatencySample {
+    pub fn total_ms(&self) -> f64 {
+        self.input_to_latch_ms + self.latch_to_present_ms
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub min_ms: f64,
+    pub avg_ms: f64,
+    pub max_ms: f64,
+    pub p95_ms: f64,
+    // Average share of the total spent before the game latched the input
+    pub avg_latch_ms: f64,
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Latency {:.1}ms avg (min {:.1}, max {:.1}, p95 {:.1}, latch {:.1}, n={})",
+            self.avg_ms, self.min_ms, self.max_ms, self.p95_ms, self.avg_latch_ms, self.samples
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingInput {
+    time_ms: f64,
+    frame: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LatchedInput {
+    input: PendingInput,
+    latch_ms: f64,
+    latch_frame: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct LatencyTracker {
+    // Host events not yet seen by an auto-joypad read
+    pending: Vec<PendingInput>,
+    // Latched events waiting for their frame to be presented
+    latched: Vec<LatchedInput>,
+    samples: VecDeque<LatencySample>,
+}
+
+impl LatencyTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A host input event changed the controller state while `frame` was
+    // the emulator's current frame
+    pub fn input_event(&mut self, frame: u64, time_ms: f64) {
+        self.pending.push(PendingInput { time_ms, frame });
+    }
+
+    // The game latched the controllers during `frame`
+    pub fn latched(&mut self, frame: u64, time_ms: f64) {
+        for input in self.pending.drain(..) {
+            self.latched.push(LatchedInput { input, latch_ms: time_ms, latch_frame: frame });
+        }
+    }
+
+    // The frame numbered `frame` reached the screen
+    pub fn presented(&mut self, frame: u64, time_ms: f64) {
+        let mut i = 0;
+        while i < self.latched.len() {
+            let entry = self.latched[i];
+            if entry.latch_frame > frame {
+                i += 1;
+                continue;
+            }
+            self.latched.swap_remove(i);
+            if self.samples.len() >= SAMPLE_CAPACITY {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(LatencySample {
+                input_to_latch_ms: (entry.latch_ms - entry.input.time_ms).max(0.0),
+                latch_to_present_ms: (time_ms - entry.latch_ms).max(0.0),
+                frames_to_latch: entry.latch_frame.saturating_sub(entry.input.frame),
+            });
+        }
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &LatencySample> {
+        self.samples.iter()
+    }
+
+    pub fn stats(&self) -> Option<LatencyStats> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut totals: Vec<f64> = self.samples.iter().map(LatencySample::total_ms).collect();
+        totals.sort_by(f64::total_cmp);
+
+        let count = totals.len();
+        let p95_index = ((count as f64 * 0.95).ceil() as usize).clamp(1, count) - 1;
+        Some(LatencyStats {
+            samples: count,
+            min_ms: totals[0],
+            avg_ms: totals.iter().sum::<f64>() / count as f64,
+            max_ms: totals[count - 1],
+            p95_ms: totals[p95_index],
+            avg_latch_ms: self.samples.iter().map(|s| s.input_to_latch_ms).sum::<f64>() / count as f64,
+        })
+    }
+
+    // Drop all measurements, e.g. after changing vsync or run-ahead
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.latched.clear();
+        self.samples.clear();
+    }
+}
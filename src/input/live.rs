@@ -0,0 +1,30 @@
+// Pad state a frontend can update at any moment, from any thread, instead
+// of once before each step_frame. The core picks it up when the game
+// latches the pads (the auto-joypad read at vblank, or a $4016 strobe), so
+// a press lands on the next read the game makes rather than the next frame
+// boundary, and games polling several times per frame see changes between
+// polls.
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct LivePads {
+    buttons: Arc<[AtomicU16; 2]>,
+}
+
+impl LivePads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Pad bits (controller::BUTTON_*) held on `player` (0 or 1)
+    pub fn set(&self, player: u8, buttons: u16) {
+        if let Some(pad) = self.buttons.get(player as usize) {
+            pad.store(buttons, Ordering::Relaxed);
+        }
+    }
+
+    pub fn get(&self, player: u8) -> u16 {
+        self.buttons.get(player as usize).map_or(0, |pad| pad.load(Ordering::Relaxed))
+    }
+}
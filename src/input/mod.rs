@@ -1,10 +1,37 @@
+pub mod conflicts;
 pub mod controller;
+pub mod hotkeys;
+pub mod latency;
+pub mod live;
+pub mod peripheral;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
+pub mod rumble;
+pub mod script;
 
 pub use controller::Controller;
+pub use hotkeys::{HotkeyAction, HotkeyManager};
+pub use latency::{LatencyStats, LatencyTracker};
+pub use live::LivePads;
+pub use peripheral::{Mouse, MouseSettings, Peripheral, PeripheralConfig, ScopeSettings, SuperScope};
+pub use rumble::{Rumble, RumbleConfig, RumbleEffect, RumbleSink, RumbleTrigger};
+pub use script::{InputScript, InputSource};
+
+use peripheral::{PeripheralDetector, Port};
 
 pub struct Input {
     controller1: Controller,
     controller2: Controller,
+    rumble: Rumble,
+    ports: [Port; 2],
+    strobe: bool,
+    // Ports whose device may still be switched by detection
+    auto_detect: [bool; 2],
+    detector: PeripheralDetector,
+    // Devices attached by detection, not yet reported to the frontend
+    auto_attached: Vec<(u8, Peripheral)>,
+    // Pads the frontend updates directly, read at each latch
+    live: Option<LivePads>,
 }
 
 impl Input {
@@ -12,10 +39,38 @@ impl Input {
         Self {
             controller1: Controller::new(),
             controller2: Controller::new(),
+            rumble: Rumble::new(),
+            ports: Default::default(),
+            strobe: false,
+            auto_detect: [true; 2],
+            detector: PeripheralDetector::default(),
+            auto_attached: Vec::new(),
+            live: None,
+        }
+    }
+
+    // Take pad state from `pads` whenever the game latches the pads, or go
+    // back to set_controller_state alone with None
+    pub fn set_live_pads(&mut self, pads: Option<LivePads>) {
+        self.live = pads;
+    }
+
+    pub fn live_pads(&self) -> Option<&LivePads> {
+        self.live.as_ref()
+    }
+
+    // Pick up the frontend's latest state just before a latch
+    fn refresh_live_pads(&mut self) {
+        if let Some(live) = self.live.as_ref() {
+            self.controller1.set_state(live.get(0));
+            self.controller2.set_state(live.get(1));
         }
     }
 
     pub fn set_controller_state(&mut self, player: u8, buttons: u16) {
+        if let Some(live) = self.live.as_ref() {
+            live.set(player, buttons);
+        }
         match player {
             0 => self.controller1.set_state(buttons),
             1 => self.controller2.set_state(buttons),
@@ -23,8 +78,22 @@ impl Input {
         }
     }
 
+    pub fn get_controller_state(&self, player: u8) -> u16 {
+        match player {
+            0 => self.controller1.get_state(),
+            1 => self.controller2.get_state(),
+            _ => 0,
+        }
+    }
+
     pub fn read_controller(&mut self, player: u8) -> u8 {
+        if player < 2 {
+            self.detector.read(player as usize, self.strobe);
+        }
         match player {
+            0 | 1 if self.ports[player as usize].device != Peripheral::Gamepad => {
+                self.ports[player as usize].read(self.strobe)
+            }
             0 => self.controller1.read(),
             1 => self.controller2.read(),
             _ => 0,
@@ -32,7 +101,115 @@ impl Input {
     }
     
     pub fn strobe_controllers(&mut self, value: bool) {
+        // The pads follow the buttons while strobed; outside of that a
+        // refresh would disturb a serial read in progress
+        if value || self.strobe {
+            self.refresh_live_pads();
+        }
         self.controller1.strobe(value);
         self.controller2.strobe(value);
+        if self.strobe && !value {
+            for port in self.ports.iter_mut() {
+                port.latch();
+            }
+        }
+        if self.strobe != value {
+            self.detector.strobe(value);
+        }
+        self.strobe = value;
+    }
+
+    // The 16 bits the auto-joypad read latches into $4218-$421B
+    pub fn auto_read(&mut self, player: u8) -> u16 {
+        if player == 0 {
+            self.detector.auto_read();
+            self.refresh_live_pads();
+        }
+        match self.ports.get_mut(player as usize) {
+            Some(port) if port.device != Peripheral::Gamepad => port.read_word(),
+            _ => self.get_controller_state(player),
+        }
+    }
+
+    pub fn device(&self, port: u8) -> Peripheral {
+        self.ports.get(port as usize).map(|port| port.device).unwrap_or_default()
+    }
+
+    pub fn attach(&mut self, port: u8, device: Peripheral) {
+        if let Some(slot) = self.ports.get_mut(port as usize) {
+            slot.device = device;
+            slot.latch();
+        }
+    }
+
+    // Attach devices from the profile, else from the title list, and
+    // leave the remaining ports to detection if enabled
+    pub fn configure_peripherals(&mut self, config: &PeripheralConfig, title: &str) {
+        let known = peripheral::known_peripheral(title);
+        for port in 0..2u8 {
+            let device = config.port(port).or(known.filter(|&(known_port, _)| known_port == port).map(|(_, device)| device));
+            self.attach(port, device.unwrap_or_default());
+            self.auto_detect[port as usize] = config.auto_detect && device.is_none();
+            let slot = &mut self.ports[port as usize];
+            slot.mouse.apply_settings(&config.mouse);
+            slot.scope.apply_settings(&config.scope);
+        }
+        self.detector = PeripheralDetector::default();
+    }
+
+    // Copy the attached devices' settings into `config` for the game
+    // profile; settings of a device not plugged in are left as they were
+    pub fn store_peripheral_settings(&self, config: &mut PeripheralConfig) {
+        if let Some(port) = self.ports.iter().find(|port| port.device == Peripheral::Mouse) {
+            config.mouse = port.mouse.settings();
+        }
+        if self.ports[1].device == Peripheral::SuperScope {
+            config.scope = self.ports[1].scope.settings();
+        }
+    }
+
+    pub fn mouse_mut(&mut self, port: u8) -> Option<&mut Mouse> {
+        self.ports.get_mut(port as usize).map(|port| &mut port.mouse)
+    }
+
+    pub fn scope_mut(&mut self) -> &mut SuperScope {
+        &mut self.ports[1].scope
+    }
+
+    // Beam position at which an aimed Super Scope on port 2 sees the beam
+    pub fn light_gun_target(&self) -> Option<(u16, u16)> {
+        match self.ports[1].device {
+            Peripheral::SuperScope => self.ports[1].scope.beam_target(),
+            _ => None,
+        }
+    }
+
+    // Called once per frame with the frame's H/V counter polling
+    pub fn end_frame(&mut self, polled_latch: bool, software_latch: bool) {
+        self.detector.end_frame(polled_latch, software_latch);
+        for port in 0..2u8 {
+            if !self.auto_detect[port as usize] {
+                continue;
+            }
+            if let Some(device) = self.detector.suggestion(port).filter(|&device| device != self.device(port)) {
+                self.attach(port, device);
+                self.auto_detect[port as usize] = false;
+                self.auto_attached.push((port, device));
+            }
+        }
+    }
+
+    // Devices attached by detection since the last call, for notifications
+    pub fn take_auto_attached(&mut self) -> Vec<(u8, Peripheral)> {
+        std::mem::take(&mut self.auto_attached)
+    }
+
+    // Force feedback for the host gamepads
+    pub fn rumble(&self) -> &Rumble {
+        &self.rumble
+    }
+
+    pub fn rumble_mut(&mut self) -> &mut Rumble {
+        &mut self.rumble
     }
 }
\ No newline at end of file
@@ -0,0 +1,332 @@
+// Devices for the controller ports besides the standard pad (SNES Mouse,
+// Super Scope), and detection of games that expect them: a small title
+// list plus the way a game polls the ports.
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Beam position at which a Super Scope aimed at pixel (0, 0) sees the
+// beam: the first visible dot and scanline
+const SCOPE_DOT_OFFSET: u16 = 22;
+const SCOPE_LINE_OFFSET: u16 = 1;
+
+// Polling patterns seen before a device is attached automatically
+const MOUSE_HITS: u32 = 3;
+const SCOPE_FRAMES: u32 = 30;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Peripheral {
+    #[default]
+    Gamepad,
+    Mouse,
+    #[serde(rename = "superscope")]
+    SuperScope,
+}
+
+impl fmt::Display for Peripheral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Peripheral::Gamepad => "Controller",
+            Peripheral::Mouse => "SNES Mouse",
+            Peripheral::SuperScope => "Super Scope",
+        })
+    }
+}
+
+// Per-game peripheral settings, stored in the game profile. A port set
+// here skips detection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeripheralConfig {
+    pub port1: Option<Peripheral>,
+    pub port2: Option<Peripheral>,
+    // Attach a device when the game polls a port the way it expects one
+    pub auto_detect: bool,
+    // Device state carried between sessions, written back when the game
+    // is closed
+    pub mouse: MouseSettings,
+    pub scope: ScopeSettings,
+}
+
+impl Default for PeripheralConfig {
+    fn default() -> Self {
+        Self {
+            port1: None,
+            port2: None,
+            auto_detect: true,
+            mouse: MouseSettings::default(),
+            scope: ScopeSettings::default(),
+        }
+    }
+}
+
+// What a mouse remembers: the sensitivity the game last cycled it to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseSettings {
+    pub sensitivity: u8,
+}
+
+// Super Scope calibration: how far the point the game sees is moved from
+// where the host cursor aims, in dots and scanlines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScopeSettings {
+    pub dot_offset: i16,
+    pub line_offset: i16,
+}
+
+impl PeripheralConfig {
+    pub fn port(&self, port: u8) -> Option<Peripheral> {
+        match port {
+            0 => self.port1,
+            1 => self.port2,
+            _ => None,
+        }
+    }
+}
+
+// Games that need a device, by header title: (title prefix, port, device)
+const KNOWN_GAMES: &[(&str, u8, Peripheral)] = &[
+    ("MARIO PAINT", 0, Peripheral::Mouse),
+    ("MARIO&WARIO", 0, Peripheral::Mouse),
+    ("SUPER SCOPE 6", 1, Peripheral::SuperScope),
+    ("BATTLE CLASH", 1, Peripheral::SuperScope),
+    ("METAL COMBAT", 1, Peripheral::SuperScope),
+    ("YOSHI'S SAFARI", 1, Peripheral::SuperScope),
+    ("X ZONE", 1, Peripheral::SuperScope),
+    ("T2 THE ARCADE GAME", 1, Peripheral::SuperScope),
+];
+
+// Device a known game expects, from its header title
+pub fn known_peripheral(title: &str) -> Option<(u8, Peripheral)> {
+    let title = title.trim().to_ascii_uppercase();
+    KNOWN_GAMES
+        .iter()
+        .find(|(prefix, _, _)| title.starts_with(prefix))
+        .map(|&(_, port, device)| (port, device))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Mouse {
+    dx: i32,
+    dy: i32,
+    left: bool,
+    right: bool,
+    sensitivity: u8,
+}
+
+impl Mouse {
+    // Host motion in mouse counts; accumulated until the game reads it
+    pub fn move_by(&mut self, dx: i32, dy: i32) {
+        self.dx = self.dx.saturating_add(dx);
+        self.dy = self.dy.saturating_add(dy);
+    }
+
+    pub fn set_buttons(&mut self, left: bool, right: bool) {
+        self.left = left;
+        self.right = right;
+    }
+
+    // 0-2, cycled by the game
+    pub fn sensitivity(&self) -> u8 {
+        self.sensitivity
+    }
+
+    fn cycle_sensitivity(&mut self) {
+        self.sensitivity = (self.sensitivity + 1) % 3;
+    }
+
+    pub fn settings(&self) -> MouseSettings {
+        MouseSettings { sensitivity: self.sensitivity }
+    }
+
+    pub fn apply_settings(&mut self, settings: &MouseSettings) {
+        self.sensitivity = settings.sensitivity % 3;
+    }
+
+    // 32-bit report, first bit in bit 31: 8 zero bits, buttons, sensitivity
+    // and ID 0001, then Y and X as direction bit plus 7-bit magnitude
+    fn latch(&mut self) -> u32 {
+        let axis = |delta: i32, negative_bit: bool| -> u32 {
+            (if negative_bit { 0x80 } else { 0 }) | delta.unsigned_abs().min(0x7F)
+        };
+        let status = (self.right as u32) << 7 | (self.left as u32) << 6 | (self.sensitivity as u32) << 4 | 0x01;
+        let report = status << 16 | axis(self.dy, self.dy < 0) << 8 | axis(self.dx, self.dx < 0);
+        self.dx = 0;
+        self.dy = 0;
+        report
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SuperScope {
+    // Screen pixel the scope points at; None when aimed off screen
+    aim: Option<(u16, u16)>,
+    fire: bool,
+    cursor: bool,
+    turbo: bool,
+    pause: bool,
+    calibration: ScopeSettings,
+}
+
+impl SuperScope {
+    pub fn aim(&mut self, position: Option<(u16, u16)>) {
+        self.aim = position.filter(|&(x, y)| x < 256 && y < 239);
+    }
+
+    pub fn aim_position(&self) -> Option<(u16, u16)> {
+        self.aim
+    }
+
+    pub fn set_buttons(&mut self, fire: bool, cursor: bool, turbo: bool, pause: bool) {
+        self.fire = fire;
+        self.cursor = cursor;
+        self.turbo = turbo;
+        self.pause = pause;
+    }
+
+    pub fn settings(&self) -> ScopeSettings {
+        self.calibration
+    }
+
+    pub fn apply_settings(&mut self, settings: &ScopeSettings) {
+        self.calibration = *settings;
+    }
+
+    // Move the calibration so a shot aimed at `target` would have been
+    // seen where the game drew it instead of at `seen`
+    pub fn calibrate(&mut self, target: (u16, u16), seen: (u16, u16)) {
+        let error = |target: u16, seen: u16| target as i16 - seen as i16;
+        self.calibration.dot_offset = self.calibration.dot_offset.saturating_add(error(target.0, seen.0));
+        self.calibration.line_offset = self.calibration.line_offset.saturating_add(error(target.1, seen.1));
+    }
+
+    // Beam position (dot, scanline) at which the scope sees the beam,
+    // after calibration
+    pub fn beam_target(&self) -> Option<(u16, u16)> {
+        let shift = |position: u16, offset: u16, calibration: i16| (position + offset).saturating_add_signed(calibration);
+        self.aim.map(|(x, y)| {
+            (shift(x, SCOPE_DOT_OFFSET, self.calibration.dot_offset), shift(y, SCOPE_LINE_OFFSET, self.calibration.line_offset))
+        })
+    }
+
+    // Fire, Cursor, Turbo, Pause, two unused bits, Offscreen, Noise, then
+    // eight ID bits, padded to the 32-bit serial register
+    fn latch(&self) -> u32 {
+        let bits = (self.fire as u32) << 7
+            | (self.cursor as u32) << 6
+            | (self.turbo as u32) << 5
+            | (self.pause as u32) << 4
+            | (self.aim.is_none() as u32) << 1;
+        (bits << 8 | 0xFF) << 16 | 0xFFFF
+    }
+}
+
+// One controller port with whatever is plugged into it. Gamepads are read
+// through Input's controllers; other devices shift out their own report.
+#[derive(Debug, Clone, Default)]
+pub struct Port {
+    pub device: Peripheral,
+    pub mouse: Mouse,
+    pub scope: SuperScope,
+    shift: u32,
+}
+
+impl Port {
+    pub(crate) fn latch(&mut self) {
+        self.shift = match self.device {
+            Peripheral::Gamepad => 0,
+            Peripheral::Mouse => self.mouse.latch(),
+            Peripheral::SuperScope => self.scope.latch(),
+        };
+    }
+
+    // Next serial bit; reading the mouse while strobed cycles its sensitivity
+    pub(crate) fn read(&mut self, strobe: bool) -> u8 {
+        if strobe {
+            if self.device == Peripheral::Mouse {
+                self.mouse.cycle_sensitivity();
+            }
+            return 0;
+        }
+        let bit = (self.shift >> 31) as u8;
+        self.shift = self.shift << 1 | 1;
+        bit
+    }
+
+    // First 16 bits, as the auto-joypad read takes them
+    pub(crate) fn read_word(&mut self) -> u16 {
+        self.latch();
+        let word = (self.shift >> 16) as u16;
+        self.shift = self.shift << 16 | 0xFFFF;
+        word
+    }
+}
+
+// Watches how the game polls the ports
+#[derive(Debug, Clone, Default)]
+pub struct PeripheralDetector {
+    // Serial reads since the last strobe or auto-joypad read
+    reads: [u32; 2],
+    strobed_reads: [u32; 2],
+    after_auto_read: bool,
+    mouse_hits: [u32; 2],
+    scope_frames: u32,
+}
+
+impl PeripheralDetector {
+    pub(crate) fn strobe(&mut self, high: bool) {
+        if !high {
+            // One read while strobed is how games cycle mouse sensitivity
+            for port in 0..2 {
+                if self.strobed_reads[port] == 1 {
+                    self.mouse_hits[port] += 1;
+                }
+            }
+        }
+        self.reads = [0; 2];
+        self.strobed_reads = [0; 2];
+        self.after_auto_read = false;
+    }
+
+    pub(crate) fn auto_read(&mut self) {
+        self.reads = [0; 2];
+        self.after_auto_read = true;
+    }
+
+    pub(crate) fn read(&mut self, port: usize, strobe: bool) {
+        if strobe {
+            self.strobed_reads[port] += 1;
+            return;
+        }
+        // A pad report is 16 bits; reading a full 32-bit report is a mouse
+        self.reads[port] += 1;
+        let report_end = if self.after_auto_read { 16 } else { 32 };
+        if self.reads[port] == report_end {
+            self.mouse_hits[port] += 1;
+        }
+    }
+
+    // Per frame: whether STAT78 was polled with the I/O pin enabled, and
+    // whether the counters were latched by software. Light gun games poll
+    // for a latch they never make themselves.
+    pub(crate) fn end_frame(&mut self, polled_latch: bool, software_latch: bool) {
+        if software_latch {
+            self.scope_frames = 0;
+        } else if polled_latch {
+            self.scope_frames += 1;
+        }
+    }
+
+    pub fn suggestion(&self, port: u8) -> Option<Peripheral> {
+        let port = port as usize;
+        if self.mouse_hits.get(port).is_some_and(|&hits| hits >= MOUSE_HITS) {
+            Some(Peripheral::Mouse)
+        } else if port == 1 && self.scope_frames >= SCOPE_FRAMES {
+            Some(Peripheral::SuperScope)
+        } else {
+            None
+        }
+    }
+}
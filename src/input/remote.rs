@@ -0,0 +1,158 @@
+// One controller driven over TCP, for a second player on another machine
+// or a script injecting input. Separate from netplay: the client sends the
+// buttons it holds and the game just reads them, with no rollback.
+//
+// The protocol is one line per change of buttons, in the names input
+// scripts use:
+//
+//   a+b+right          hold A, B and Right until the next line
+//   none               release everything (so does an empty line)
+//   0x8080             the raw pad bits (as returned by Controller)
+//
+// Bad lines get an "error: ..." reply. One client at a time; others are
+// told "busy" and closed. Disconnecting releases the buttons.
+use crate::input::hotkeys::pad_button;
+use crate::Result;
+use std::fmt;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+
+// Longest line accepted; a client sending more without a newline is dropped
+const MAX_LINE: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteEvent {
+    Connected(SocketAddr),
+    Disconnected(SocketAddr),
+    // Another client tried to connect while one was playing
+    Refused(SocketAddr),
+}
+
+impl fmt::Display for RemoteEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoteEvent::Connected(peer) => write!(f, "Remote input connected from {}", peer),
+            RemoteEvent::Disconnected(peer) => write!(f, "Remote input from {} disconnected", peer),
+            RemoteEvent::Refused(peer) => write!(f, "Remote input refused {} (already connected)", peer),
+        }
+    }
+}
+
+struct Client {
+    stream: TcpStream,
+    peer: SocketAddr,
+    pending: Vec<u8>,
+}
+
+pub struct RemoteInput {
+    listener: TcpListener,
+    client: Option<Client>,
+    player: u8,
+    buttons: u16,
+}
+
+impl RemoteInput {
+    // Listen on `address` ("0.0.0.0:7755"; port 0 picks a free one) for
+    // input to `player` (0 or 1)
+    pub fn bind(address: &str, player: u8) -> Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener, client: None, player: player.min(1), buttons: 0 })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn player(&self) -> u8 {
+        self.player
+    }
+
+    // Buttons the client holds, 0 without a client
+    pub fn buttons(&self) -> u16 {
+        self.buttons
+    }
+
+    pub fn peer(&self) -> Option<SocketAddr> {
+        self.client.as_ref().map(|client| client.peer)
+    }
+
+    // Accept a waiting client and apply the lines it sent; never blocks.
+    // Call once per frame.
+    pub fn poll(&mut self) -> Vec<RemoteEvent> {
+        let mut events = Vec::new();
+        loop {
+            match self.listener.accept() {
+                Ok((mut stream, peer)) => {
+                    if self.client.is_some() {
+                        let _ = stream.write_all(b"busy\n");
+                        events.push(RemoteEvent::Refused(peer));
+                    } else if stream.set_nonblocking(true).is_ok() {
+                        let _ = stream.set_nodelay(true);
+                        self.client = Some(Client { stream, peer, pending: Vec::new() });
+                        events.push(RemoteEvent::Connected(peer));
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!("Remote input accept failed: {}", e);
+                    break;
+                }
+            }
+        }
+
+        let Some(client) = self.client.as_mut() else {
+            return events;
+        };
+        let mut closed = false;
+        let mut chunk = [0; 512];
+        loop {
+            match client.stream.read(&mut chunk) {
+                Ok(0) => {
+                    closed = true;
+                    break;
+                }
+                Ok(n) => client.pending.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => {
+                    closed = true;
+                    break;
+                }
+            }
+        }
+        while let Some(end) = client.pending.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = client.pending.drain(..=end).collect();
+            match parse_buttons(String::from_utf8_lossy(&line).trim()) {
+                Ok(buttons) => self.buttons = buttons,
+                Err(e) => {
+                    let _ = client.stream.write_all(format!("error: {}\n", e).as_bytes());
+                }
+            }
+        }
+        if client.pending.len() > MAX_LINE {
+            let _ = client.stream.write_all(b"error: line too long\n");
+            closed = true;
+        }
+        if closed {
+            events.push(RemoteEvent::Disconnected(client.peer));
+            self.client = None;
+            self.buttons = 0;
+        }
+        events
+    }
+}
+
+// A protocol line as pad bits
+pub fn parse_buttons(line: &str) -> std::result::Result<u16, String> {
+    if let Some(hex) = line.strip_prefix("0x").or_else(|| line.strip_prefix("0X")) {
+        return u16::from_str_radix(hex, 16).map_err(|_| format!("invalid button bits '{}'", line));
+    }
+    let mut buttons = 0;
+    for name in line.split(|c: char| c == '+' || c.is_whitespace()).filter(|s| !s.is_empty()) {
+        if !name.eq_ignore_ascii_case("none") {
+            buttons |= pad_button(name).ok_or_else(|| format!("unknown button '{}'", name))?;
+        }
+    }
+    Ok(buttons)
+}
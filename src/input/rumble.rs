@@ -0,0 +1,156 @@
+// Force feedback for host gamepads. Effects are requested through the API
+// (scripts, frontends) or fired by per-game triggers that watch memory like
+// cheat codes do; frontends read the motor levels back each frame and hand
+// them to the host gamepad through a RumbleSink.
+use serde::{Deserialize, Serialize};
+
+pub const RUMBLE_PORTS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RumbleEffect {
+    // Low-frequency (heavy) and high-frequency (light) motors, 0.0-1.0
+    pub strong: f32,
+    pub weak: f32,
+    // Duration in frames; 0 plays until stopped
+    pub frames: u32,
+}
+
+// Fire an effect when `address & mask` becomes `value`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RumbleTrigger {
+    pub address: u32,
+    pub value: u8,
+    pub mask: u8,
+    pub port: u8,
+    pub strong: f32,
+    pub weak: f32,
+    pub frames: u32,
+}
+
+impl Default for RumbleTrigger {
+    fn default() -> Self {
+        Self {
+            address: 0,
+            value: 0,
+            mask: 0xFF,
+            port: 0,
+            strong: 1.0,
+            weak: 1.0,
+            frames: 10,
+        }
+    }
+}
+
+impl RumbleTrigger {
+    fn matches(&self, byte: u8) -> bool {
+        byte & self.mask == self.value & self.mask
+    }
+
+    fn effect(&self) -> RumbleEffect {
+        RumbleEffect { strong: self.strong, weak: self.weak, frames: self.frames }
+    }
+}
+
+// Per-game rumble settings, stored in the game profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RumbleConfig {
+    pub enabled: bool,
+    // Scales every effect, e.g. 0.5 for half strength
+    pub strength: f32,
+    pub triggers: Vec<RumbleTrigger>,
+}
+
+impl Default for RumbleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            strength: 1.0,
+            triggers: Vec::new(),
+        }
+    }
+}
+
+// Host side of the pass-through, implemented by the application for its
+// gamepad library (e.g. a gilrs force-feedback effect); none ships here
+pub trait RumbleSink {
+    fn set_rumble(&mut self, port: u8, strong: f32, weak: f32);
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Rumble {
+    config: RumbleConfig,
+    active: [Option<RumbleEffect>; RUMBLE_PORTS],
+    // Whether each trigger matched at the last poll, so effects fire on
+    // the change rather than every frame the condition holds
+    matched: Vec<bool>,
+}
+
+impl Rumble {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config(&self) -> &RumbleConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: RumbleConfig) {
+        self.matched = vec![false; config.triggers.len()];
+        self.config = config;
+    }
+
+    pub fn request(&mut self, port: u8, effect: RumbleEffect) {
+        if let Some(slot) = self.active.get_mut(port as usize) {
+            *slot = Some(effect);
+        }
+    }
+
+    pub fn stop(&mut self, port: u8) {
+        if let Some(slot) = self.active.get_mut(port as usize) {
+            *slot = None;
+        }
+    }
+
+    pub fn stop_all(&mut self) {
+        self.active = [None; RUMBLE_PORTS];
+    }
+
+    // Motor levels (strong, weak) to send to the host gamepad on `port`
+    pub fn level(&self, port: u8) -> (f32, f32) {
+        match self.active.get(port as usize).copied().flatten() {
+            Some(effect) if self.config.enabled => {
+                let scale = self.config.strength.clamp(0.0, 1.0);
+                ((effect.strong * scale).clamp(0.0, 1.0), (effect.weak * scale).clamp(0.0, 1.0))
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    // Check the profile triggers against memory
+    pub fn poll_triggers<F: Fn(u32) -> u8>(&mut self, read: F) {
+        for (i, trigger) in self.config.triggers.iter().enumerate() {
+            let matches = trigger.matches(read(trigger.address));
+            if matches && !self.matched[i] {
+                if let Some(slot) = self.active.get_mut(trigger.port as usize) {
+                    *slot = Some(trigger.effect());
+                }
+            }
+            self.matched[i] = matches;
+        }
+    }
+
+    // Count down timed effects; called once per emulated frame
+    pub fn end_frame(&mut self) {
+        for slot in self.active.iter_mut() {
+            if let Some(effect) = slot {
+                match effect.frames {
+                    0 => {}
+                    1 => *slot = None,
+                    _ => effect.frames -= 1,
+                }
+            }
+        }
+    }
+}
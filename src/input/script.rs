@@ -0,0 +1,109 @@
+// Scripted controller input for headless runs. One entry per line:
+//
+//   # comments run to the end of the line
+//   60: start             press Start on frame 60
+//   120-180: right+b      hold Right and B for frames 120 to 180
+//   200: p2 a             player 2 presses A
+//   240: none             an explicit release (also the default)
+//
+// Frames not named by any line have no buttons pressed; entries for the
+// same frame and player are combined.
+use crate::input::hotkeys::pad_button;
+use crate::{EmulatorError, Result};
+use std::fs;
+use std::path::Path;
+
+// Per-frame controller input, from a script or a recorded movie
+pub trait InputSource {
+    // Buttons held by `player` (0 or 1) during `frame`
+    fn buttons(&self, frame: u64, player: u8) -> u16;
+
+    // Frames covered by the inputs
+    fn frame_count(&self) -> u64;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ScriptEntry {
+    first: u64,
+    last: u64,
+    player: u8,
+    buttons: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputScript {
+    entries: Vec<ScriptEntry>,
+}
+
+impl InputScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut script = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let entry = parse_line(line)
+                .map_err(|e| EmulatorError::input(format!("Input script line {}: {}", number + 1, e)))?;
+            script.entries.push(entry);
+        }
+        Ok(script)
+    }
+
+    // Hold `buttons` for frames `first..=last`
+    pub fn hold(&mut self, first: u64, last: u64, player: u8, buttons: u16) {
+        self.entries.push(ScriptEntry { first, last, player, buttons });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl InputSource for InputScript {
+    fn buttons(&self, frame: u64, player: u8) -> u16 {
+        self.entries
+            .iter()
+            .filter(|entry| entry.player == player && (entry.first..=entry.last).contains(&frame))
+            .fold(0, |buttons, entry| buttons | entry.buttons)
+    }
+
+    fn frame_count(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.last + 1).max().unwrap_or(0)
+    }
+}
+
+fn parse_line(line: &str) -> std::result::Result<ScriptEntry, String> {
+    let (frames, input) = line.split_once(':').ok_or("expected 'frame: buttons'")?;
+    let frame = |text: &str| text.trim().parse::<u64>().map_err(|_| format!("invalid frame '{}'", text.trim()));
+    let (first, last) = match frames.split_once('-') {
+        Some((first, last)) => (frame(first)?, frame(last)?),
+        None => {
+            let single = frame(frames)?;
+            (single, single)
+        }
+    };
+    if last < first {
+        return Err(format!("frame range {}-{} ends before it starts", first, last));
+    }
+
+    let mut player = 0;
+    let mut buttons = 0;
+    for (i, name) in input.split(|c: char| c == '+' || c.is_whitespace()).filter(|s| !s.is_empty()).enumerate() {
+        match name.to_ascii_lowercase().as_str() {
+            "p1" if i == 0 => player = 0,
+            "p2" if i == 0 => player = 1,
+            "none" => {}
+            _ => buttons |= pad_button(name).ok_or_else(|| format!("unknown button '{}'", name))?,
+        }
+    }
+    Ok(ScriptEntry { first, last, player, buttons })
+}
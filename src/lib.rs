@@ -7,9 +7,27 @@ pub mod input;
 pub mod memory;
 pub mod ppu;
 pub mod savestate;
+pub mod hot_slots;
+pub mod sram;
+pub mod paths;
+pub mod session;
+pub mod library;
+pub mod cheats;
+pub mod achievements;
+pub mod profile;
+pub mod quirks;
+pub mod enhancements;
+pub mod movie;
+pub mod movie_editor;
+pub mod metrics;
 pub mod config;
 pub mod debug;
 pub mod error;
+pub mod boot_rom;
+pub mod embed;
+pub mod sink;
+pub mod frame_hash;
+pub mod prelude;
 
 #[cfg(target_arch = "wasm32")]
 pub mod wasm;
@@ -0,0 +1,177 @@
+// ROM library scanning with a metadata cache
+use crate::cartridge::{Cartridge, CartridgeHeader};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+// File extensions recognised as SNES ROM images
+pub const ROM_EXTENSIONS: [&str; 4] = ["sfc", "smc", "swc", "fig"];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub path: PathBuf,
+    pub title: String,
+    pub region: String,
+    pub mapper: String,
+    // File size and modification time (seconds since the epoch), used to
+    // decide whether a cached entry is still valid
+    pub size: u64,
+    pub modified: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LibraryCache {
+    roms: Vec<LibraryEntry>,
+}
+
+pub struct RomLibrary {
+    root: PathBuf,
+    entries: Vec<LibraryEntry>,
+    cache_hits: usize,
+}
+
+impl RomLibrary {
+    // Scan a directory tree for ROMs. Headers are only parsed for files that
+    // are new or changed since the cache was written; the cache is updated
+    // when anything changed.
+    pub fn scan(root: &Path, cache_path: Option<&Path>) -> Result<Self> {
+        let mut cached: HashMap<PathBuf, LibraryEntry> = cache_path
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| toml::from_str::<LibraryCache>(&contents).ok())
+            .map(|cache| cache.roms.into_iter().map(|e| (e.path.clone(), e)).collect())
+            .unwrap_or_default();
+        let cached_count = cached.len();
+
+        let mut entries = Vec::new();
+        let mut cache_hits = 0;
+        for path in find_roms(root)? {
+            let metadata = match fs::metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    log::warn!("Skipping {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            let size = metadata.len();
+            let modified = metadata.modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+
+            match cached.remove(&path) {
+                Some(entry) if entry.size == size && entry.modified == modified => {
+                    cache_hits += 1;
+                    entries.push(entry);
+                }
+                _ => match fs::read(&path) {
+                    Ok(data) => entries.push(Self::describe(&path, &data, size, modified)),
+                    Err(e) => log::warn!("Skipping {:?}: {}", path, e),
+                },
+            }
+        }
+
+        entries.sort_by(|a, b| {
+            a.title.to_ascii_lowercase().cmp(&b.title.to_ascii_lowercase())
+                .then_with(|| a.path.cmp(&b.path))
+        });
+
+        if let Some(cache_path) = cache_path {
+            if cache_hits != entries.len() || cache_hits != cached_count {
+                let cache = LibraryCache { roms: entries.clone() };
+                if let Some(parent) = cache_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(cache_path, toml::to_string_pretty(&cache)?)?;
+            }
+        }
+
+        log::info!("Library: {} ROMs in {:?} ({} cached)", entries.len(), root, cache_hits);
+        Ok(Self {
+            root: root.to_path_buf(),
+            entries,
+            cache_hits,
+        })
+    }
+
+    // Build an entry from ROM contents; unreadable headers fall back to the file name
+    fn describe(path: &Path, data: &[u8], size: u64, modified: u64) -> LibraryEntry {
+        let stem = path.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let (title, region, mapper) = match CartridgeHeader::parse(&Cartridge::remove_copier_header(data)) {
+            Ok(header) => {
+                let title = if header.title.is_empty() { stem } else { header.title };
+                (title, format!("{:?}", header.region), format!("{:?}", header.mapper_type))
+            }
+            Err(e) => {
+                log::warn!("Could not parse header of {:?}: {}", path, e);
+                (stem, "Unknown".to_string(), "Unknown".to_string())
+            }
+        };
+
+        LibraryEntry {
+            path: path.to_path_buf(),
+            title,
+            region,
+            mapper,
+            size,
+            modified,
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn entries(&self) -> &[LibraryEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Number of entries taken from the cache during the last scan
+    pub fn cache_hits(&self) -> usize {
+        self.cache_hits
+    }
+}
+
+pub fn is_rom_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ROM_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+        .unwrap_or(false)
+}
+
+fn find_roms(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if dir != root => {
+                log::warn!("Skipping {:?}: {}", dir, e);
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_rom_file(&path) {
+                roms.push(path);
+            }
+        }
+    }
+    Ok(roms)
+}
@@ -1,14 +1,33 @@
+use crate::cartridge::coprocessor::Coprocessors;
 use crate::cartridge::Cartridge;
+use crate::cpu::Bus65816;
+use crate::debug::graphics::GraphicsMemory;
+use crate::debug::heatmap::Heatmap;
+use crate::debug::lint::{LintKind, Linter};
 use crate::input::Input;
 use crate::apu::Apu;
+use crate::memory::freeze::{Freeze, FreezeList, FreezeTarget};
+use crate::memory::init::{MemoryInit, WRAM_STREAM};
+use crate::memory::timing;
+use crate::ppu::counters::CounterLatch;
+use crate::ppu::ports::{self, PortOwner};
+use crate::ppu::Ppu;
 use crate::savestate::MemoryState;
-use crate::Result;
+use crate::{EmulatorError, Result};
+use std::cell::{Cell, Ref, RefCell};
 
 const WRAM_SIZE: usize = 0x20000; // 128KB Work RAM
 const VRAM_SIZE: usize = 0x10000; // 64KB Video RAM
 const OAM_SIZE: usize = 0x220;    // 544 bytes OAM (Object Attribute Memory)
 const CGRAM_SIZE: usize = 0x200;  // 512 bytes Color Generator RAM
 
+// Counter accesses seen during a frame (see take_counter_polls)
+const COUNTER_POLL_STATUS: u8 = 0x01;
+const COUNTER_POLL_SOFTWARE_LATCH: u8 = 0x02;
+
+// 5A22 version in RDNMI bits 0-3
+const CPU_VERSION: u8 = 2;
+
 pub struct Bus {
     wram: Vec<u8>,       // $7E0000-$7FFFFF: Work RAM
     vram: Vec<u8>,       // PPU Video RAM
@@ -17,14 +36,17 @@ pub struct Bus {
     
     cartridge: Option<*mut Cartridge>,
     
-    // PPU registers ($2100-$213F)
+    // Chips on the cartridge, answering ahead of its mapping
+    coprocessors: Coprocessors,
+    
+    // Last byte written to each PPU port while no PPU is connected
     ppu_regs: [u8; 0x40],
     
     // APU registers ($2140-$217F)
     apu_regs: [u8; 0x40],
     
     // Controller registers ($4016-$4017, $4200-$421F)
-    controller_regs: [u8; 0x20],
+    controller_regs: [u8; 0x22],
     
     // DMA registers ($4300-$437F)
     dma_regs: [u8; 0x80],
@@ -34,38 +56,218 @@ pub struct Bus {
     
     // APU pointer
     apu: Option<*mut Apu>,
+    
+    // PPU pointer
+    ppu: Option<*mut Ppu>,
+    
+    // Bytes held at fixed values
+    freezes: FreezeList,
+    
+    // H/V counter latch and the beam position it latches from
+    counters: CounterLatch,
+    beam_position: (u16, u16),
+    // First vblank line, later with overscan
+    vblank_start: u16,
+    // Latch the counters at the dot of the access rather than the start of
+    // the instruction (dot rendering)
+    latch_at_access: bool,
+    
+    // I/O port pins pulled low by connected devices (e.g. a light gun)
+    io_pins_low: u8,
+    
+    // RDNMI bit 7: set when vblank begins, cleared by reading $4210 or
+    // when vblank ends
+    nmi_flag: Cell<bool>,
+    
+    // COUNTER_POLL_* flags for the current frame, for light gun detection
+    counter_polls: Cell<u8>,
+    
+    // Accesses and their master cycles since the last take_access_timing()
+    access_count: Cell<u32>,
+    access_cycles: Cell<u64>,
+    
+    // Lint mode findings, when enabled (see debug::lint)
+    lint: Option<RefCell<Linter>>,
+    
+    // Access counts, when enabled (see debug::heatmap)
+    heatmap: Option<RefCell<Heatmap>>,
 }
 
 impl Bus {
     pub fn new() -> Self {
-        Self {
+        Self::with_memory_init(MemoryInit::default())
+    }
+
+    pub fn with_memory_init(memory_init: MemoryInit) -> Self {
+        let mut bus = Self {
             wram: vec![0; WRAM_SIZE],
             vram: vec![0; VRAM_SIZE],
             oam: vec![0; OAM_SIZE],
             cgram: vec![0; CGRAM_SIZE],
             cartridge: None,
+            coprocessors: Coprocessors::new(),
             ppu_regs: [0; 0x40],
             apu_regs: [0; 0x40],
-            controller_regs: [0; 0x20],
+            controller_regs: Self::initial_controller_regs(),
             dma_regs: [0; 0x80],
             input: None,
             apu: None,
+            ppu: None,
+            freezes: FreezeList::new(),
+            counters: CounterLatch::new(),
+            beam_position: (0, 0),
+            vblank_start: timing::VBLANK_START_SCANLINE,
+            latch_at_access: false,
+            io_pins_low: 0,
+            nmi_flag: Cell::new(false),
+            counter_polls: Cell::new(0),
+            access_count: Cell::new(0),
+            access_cycles: Cell::new(0),
+            lint: None,
+            heatmap: None,
+        };
+        bus.init_wram(memory_init);
+        bus
+    }
+
+    // Power-on WRAM contents; reset leaves WRAM alone, as on hardware
+    pub fn init_wram(&mut self, memory_init: MemoryInit) {
+        memory_init.fill(&mut self.wram, WRAM_STREAM);
+        if let Some(linter) = self.linter_mut() {
+            linter.forget_wram();
         }
     }
 
-    pub fn install_cartridge(&mut self, cartridge: &mut Cartridge) {
-        self.cartridge = Some(cartridge as *mut Cartridge);
+    // Turn lint mode on or off; reads of uninitialized WRAM are tracked
+    // from the next power-on
+    pub fn set_lint(&mut self, enabled: bool) {
+        if enabled != self.lint.is_some() {
+            self.lint = enabled.then(|| RefCell::new(Linter::new()));
+        }
+    }
+
+    pub fn linter(&self) -> Option<Ref<'_, Linter>> {
+        self.lint.as_ref().map(RefCell::borrow)
+    }
+
+    pub fn linter_mut(&mut self) -> Option<&mut Linter> {
+        self.lint.as_mut().map(RefCell::get_mut)
+    }
+
+    // Start counting accesses into `heatmap`, or stop with None
+    pub fn set_heatmap(&mut self, heatmap: Option<Heatmap>) {
+        self.heatmap = heatmap.map(RefCell::new);
+    }
+
+    pub fn heatmap(&self) -> Option<Ref<'_, Heatmap>> {
+        self.heatmap.as_ref().map(RefCell::borrow)
+    }
+
+    pub fn heatmap_mut(&mut self) -> Option<&mut Heatmap> {
+        self.heatmap.as_mut().map(RefCell::get_mut)
+    }
+
+    fn read_wram(&self, offset: usize, address: u32) -> u8 {
+        if let Some(lint) = &self.lint {
+            lint.borrow_mut().wram_read(offset, address);
+        }
+        if let Some(heatmap) = &self.heatmap {
+            heatmap.borrow_mut().wram_read(offset);
+        }
+        self.wram[offset]
+    }
+
+    fn write_wram(&mut self, offset: usize, value: u8) {
+        self.wram[offset] = value;
+        if let Some(linter) = self.linter_mut() {
+            linter.wram_write(offset);
+        }
+        if let Some(heatmap) = self.heatmap_mut() {
+            heatmap.wram_write(offset);
+        }
+    }
+
+    fn initial_controller_regs() -> [u8; 0x22] {
+        let mut regs = [0; 0x22];
+        // WRIO powers up with every pin high
+        regs[0x01 + 2] = 0xFF;
+        regs
+    }
+
+    // Forget the cartridge before it is dropped, and its coprocessors with it
+    pub fn remove_cartridge(&mut self) {
+        self.cartridge = None;
+        self.coprocessors.clear();
     }
     
-    pub fn connect_input(&mut self, input: &mut Input) {
-        self.input = Some(input as *mut Input);
+    pub fn coprocessors(&self) -> &Coprocessors {
+        &self.coprocessors
     }
     
-    pub fn connect_apu(&mut self, apu: &mut Apu) {
+    pub fn coprocessors_mut(&mut self) -> &mut Coprocessors {
+        &mut self.coprocessors
+    }
+    
+    // Power cycle: I/O registers, frozen bytes and the counter latch as the
+    // console comes up, and WRAM refilled. Nothing of the previous game is
+    // left for the next one to see.
+    pub fn power_on(&mut self, memory_init: MemoryInit) {
+        self.ppu_regs = [0; 0x40];
+        self.apu_regs = [0; 0x40];
+        self.controller_regs = Self::initial_controller_regs();
+        self.dma_regs = [0; 0x80];
+        self.freezes.clear();
+        self.counters.reset();
+        self.io_pins_low = 0;
+        self.nmi_flag.set(false);
+        self.counter_polls.set(0);
+        self.init_wram(memory_init);
+    }
+    
+    // Point the bus at the components it forwards accesses to. The bus
+    // keeps raw pointers, so only the Emulator that owns both may do this
+    // (see the SAFETY note on its Send impl).
+    pub(crate) fn connect(&mut self, cartridge: Option<&mut Cartridge>, input: &mut Input, apu: &mut Apu, ppu: &mut Ppu) {
+        self.cartridge = cartridge.map(|cartridge| cartridge as *mut Cartridge);
+        self.input = Some(input as *mut Input);
         self.apu = Some(apu as *mut Apu);
+        self.ppu = Some(ppu as *mut Ppu);
+    }
+    
+    /// Link a standalone bus, as in tests that don't need a whole Emulator.
+    ///
+    /// # Safety
+    /// The bus keeps a raw pointer, so the cartridge must outlive every
+    /// access made through the bus and not move meanwhile.
+    pub unsafe fn install_cartridge(&mut self, cartridge: &mut Cartridge) {
+        self.cartridge = Some(cartridge as *mut Cartridge);
+    }
+    
+    /// # Safety
+    /// As for install_cartridge.
+    pub unsafe fn connect_ppu(&mut self, ppu: &mut Ppu) {
+        self.ppu = Some(ppu as *mut Ppu);
+    }
+
+    // Number of accesses and master cycles they took since the last call
+    pub fn take_access_timing(&self) -> (u32, u64) {
+        (self.access_count.take(), self.access_cycles.take())
     }
 
-    pub fn read8(&self, address: u32) -> u8 {
+    fn count_access(&self, address: u32) {
+        let fast_rom = self.controller_regs[0x0D + 2] & 0x01 != 0;
+        self.access_count.set(self.access_count.get() + 1);
+        self.access_cycles.set(self.access_cycles.get() + timing::access_cycles(address, fast_rom));
+    }
+
+    pub fn read8(&mut self, address: u32) -> u8 {
+        self.count_access(address);
+        if let Some(port) = ports::decode(address) {
+            return self.read_ppu_port(port);
+        }
+        if let Some(value) = self.coprocessors.read(address) {
+            return value;
+        }
         let bank = (address >> 16) & 0xFF;
         let addr = address & 0xFFFF;
 
@@ -74,15 +276,12 @@ impl Bus {
             0x00..=0x3F | 0x80..=0xBF => {
                 match addr {
                     // Low RAM mirror ($0000-$1FFF)
-                    0x0000..=0x1FFF => self.wram[addr as usize],
-                    
-                    // PPU registers ($2100-$213F)
-                    0x2100..=0x213F => self.read_ppu_register(addr as u16),
+                    0x0000..=0x1FFF => self.read_wram(addr as usize, address),
                     
                     // APU registers ($2140-$217F)
                     0x2140..=0x217F => {
                         if let Some(apu_ptr) = self.apu {
-                            let apu = unsafe { &*apu_ptr };
+                            let apu = unsafe { &mut *apu_ptr };
                             // Read from APU ports 0-3
                             match addr {
                                 0x2140 => apu.read_port(0),
@@ -99,6 +298,11 @@ impl Bus {
                     // Controller registers ($4016-$4017)
                     0x4016..=0x4017 => self.read_controller(addr as u16),
                     
+                    // RDIO: the I/O port as seen on the pins
+                    0x4213 => self.rdio(),
+                    0x4210 => self.read_rdnmi(),
+                    0x4212 => self.read_hvbjoy(),
+                    
                     // System registers ($4200-$421F)
                     0x4200..=0x421F => self.controller_regs[(addr - 0x4200 + 2) as usize],
                     
@@ -126,14 +330,8 @@ impl Bus {
             0x40..=0x7D => self.read_cartridge(address),
             
             // Banks $7E-$7F: Work RAM
-            0x7E => self.wram[addr as usize],
-            0x7F => {
-                if addr <= 0xFFFF {
-                    self.wram[(0x10000 + addr) as usize]
-                } else {
-                    0
-                }
-            }
+            0x7E => self.read_wram(addr as usize, address),
+            0x7F => self.read_wram(0x10000 | addr as usize, address),
             
             // Banks $C0-$FF: ROM area
             0xC0..=0xFF => self.read_cartridge(address),
@@ -143,6 +341,25 @@ impl Bus {
     }
 
     pub fn write8(&mut self, address: u32, value: u8) {
+        self.count_access(address);
+        self.write8_unfrozen(address, value);
+        if !self.freezes.is_empty() {
+            if let Some(target) = self.freeze_target(address) {
+                if let Some(frozen) = self.freezes.value_of(target) {
+                    self.write_target(target, frozen);
+                }
+            }
+        }
+    }
+
+    fn write8_unfrozen(&mut self, address: u32, value: u8) {
+        if let Some(port) = ports::decode(address) {
+            self.write_ppu_port(port, value);
+            return;
+        }
+        if self.coprocessors.write(address, value) {
+            return;
+        }
         let bank = (address >> 16) & 0xFF;
         let addr = address & 0xFFFF;
 
@@ -151,10 +368,7 @@ impl Bus {
             0x00..=0x3F | 0x80..=0xBF => {
                 match addr {
                     // Low RAM mirror ($0000-$1FFF)
-                    0x0000..=0x1FFF => self.wram[addr as usize] = value,
-                    
-                    // PPU registers ($2100-$213F)
-                    0x2100..=0x213F => self.write_ppu_register(addr as u16, value),
+                    0x0000..=0x1FFF => self.write_wram(addr as usize, value),
                     
                     // APU registers ($2140-$217F)
                     0x2140..=0x217F => {
@@ -176,6 +390,9 @@ impl Bus {
                     // Controller registers ($4016-$4017)
                     0x4016..=0x4017 => self.write_controller(addr as u16, value),
                     
+                    // WRIO: a high-to-low transition on pin 7 latches the counters
+                    0x4201 => self.set_io_port(value, self.io_pins_low),
+                    
                     // System registers ($4200-$421F)
                     0x4200..=0x421F => self.controller_regs[(addr - 0x4200 + 2) as usize] = value,
                     
@@ -206,12 +423,8 @@ impl Bus {
             }
             
             // Banks $7E-$7F: Work RAM
-            0x7E => self.wram[addr as usize] = value,
-            0x7F => {
-                if addr <= 0xFFFF {
-                    self.wram[(0x10000 + addr) as usize] = value;
-                }
-            }
+            0x7E => self.write_wram(addr as usize, value),
+            0x7F => self.write_wram(0x10000 | addr as usize, value),
             
             // Other banks - mostly ROM, but might have SRAM
             _ => {
@@ -222,23 +435,121 @@ impl Bus {
         }
     }
 
-    pub fn read16(&self, address: u32) -> u16 {
+    // Resolve a CPU address to the WRAM or SRAM byte it reaches, if any
+    pub fn freeze_target(&self, address: u32) -> Option<FreezeTarget> {
+        let bank = (address >> 16) & 0xFF;
+        let addr = address & 0xFFFF;
+        match bank {
+            0x7E | 0x7F => return Some(FreezeTarget::Wram(((bank - 0x7E) << 16 | addr) as usize)),
+            0x00..=0x3F | 0x80..=0xBF if addr < 0x2000 => return Some(FreezeTarget::Wram(addr as usize)),
+            _ => {}
+        }
+        let cartridge = unsafe { &*self.cartridge? };
+        cartridge.mapper.map_sram_address(address)
+            .filter(|&offset| offset < cartridge.sram.len())
+            .map(FreezeTarget::Sram)
+    }
+
+    fn write_target(&mut self, target: FreezeTarget, value: u8) {
+        match target {
+            FreezeTarget::Wram(offset) => self.wram[offset] = value,
+            FreezeTarget::Sram(offset) => {
+                if let Some(cartridge_ptr) = self.cartridge {
+                    let cartridge = unsafe { &mut *cartridge_ptr };
+                    cartridge.sram[offset] = value;
+                }
+            }
+        }
+    }
+
+    // Hold the WRAM/SRAM byte at `address` at `value`
+    pub fn freeze(&mut self, address: u32, value: u8) -> Result<()> {
+        let target = self.freeze_target(address).ok_or_else(|| {
+            EmulatorError::memory(format!("${:06X} is not WRAM or SRAM and cannot be frozen", address))
+        })?;
+        self.freezes.insert(Freeze { address, target, value });
+        self.write_target(target, value);
+        Ok(())
+    }
+
+    // Returns true if a freeze was removed
+    pub fn unfreeze(&mut self, address: u32) -> bool {
+        match self.freeze_target(address) {
+            Some(target) => self.freezes.remove(target),
+            None => false,
+        }
+    }
+
+    pub fn clear_freezes(&mut self) {
+        self.freezes.clear();
+    }
+
+    pub fn freezes(&self) -> &[Freeze] {
+        self.freezes.entries()
+    }
+
+    // Write every frozen value back, e.g. after memory was replaced wholesale
+    pub fn apply_freezes(&mut self) {
+        for freeze in self.freezes.entries().to_vec() {
+            self.write_target(freeze.target, freeze.value);
+        }
+    }
+
+    pub fn read16(&mut self, address: u32) -> u16 {
         let low = self.read8(address) as u16;
-        let high = self.read8(address + 1) as u16;
+        let high = self.read8(address.wrapping_add(1)) as u16;
         low | (high << 8)
     }
 
     pub fn write16(&mut self, address: u32, value: u16) {
         self.write8(address, (value & 0xFF) as u8);
-        self.write8(address + 1, (value >> 8) as u8);
+        self.write8(address.wrapping_add(1), (value >> 8) as u8);
     }
 
-    pub fn read24(&self, address: u32) -> u32 {
+    pub fn read24(&mut self, address: u32) -> u32 {
         let low = self.read16(address) as u32;
-        let high = self.read8(address + 2) as u32;
+        let high = self.read8(address.wrapping_add(2)) as u32;
         low | (high << 16)
     }
 
+    // Read memory or an I/O register as the CPU would see it, but without
+    // timing, latches, flip-flops, acknowledges or APU catch-up, for
+    // debuggers and tools watching the game. The serial controller ports,
+    // which can't be read without shifting, read as 0.
+    pub fn peek8(&self, address: u32) -> u8 {
+        if let Some(port) = ports::decode(address) {
+            return self.peek_ppu_port(port);
+        }
+        if let Some(value) = self.coprocessors.peek(address) {
+            return value;
+        }
+        let bank = (address >> 16) & 0xFF;
+        let addr = address & 0xFFFF;
+        match bank {
+            0x7E => self.wram[addr as usize],
+            0x7F => self.wram[0x10000 | addr as usize],
+            0x00..=0x3F | 0x80..=0xBF => match addr {
+                0x0000..=0x1FFF => self.wram[addr as usize],
+                0x2140..=0x217F => match self.apu {
+                    Some(apu_ptr) if addr <= 0x2143 => unsafe { (*apu_ptr).peek_ports().1[(addr - 0x2140) as usize] },
+                    Some(_) => 0,
+                    None => self.apu_regs[(addr - 0x2140) as usize],
+                },
+                0x4213 => self.rdio(),
+                0x4210 => self.peek_rdnmi(),
+                0x4212 => self.read_hvbjoy(),
+                0x4200..=0x421F => self.controller_regs[(addr - 0x4200 + 2) as usize],
+                0x4300..=0x437F => self.dma_regs[(addr - 0x4300) as usize],
+                0x2000..=0x5FFF => 0,
+                0x8000.. if self.cartridge.is_none() => {
+                    self.wram.get(0x8000 + (addr - 0x8000) as usize).copied().unwrap_or(0)
+                }
+                _ => self.read_cartridge(address),
+            },
+            _ => self.read_cartridge(address),
+        }
+    }
+
     fn read_cartridge(&self, address: u32) -> u8 {
         if let Some(cartridge_ptr) = self.cartridge {
             unsafe {
@@ -251,29 +562,215 @@ impl Bus {
     
     fn write_cartridge(&mut self, address: u32, value: u8) {
         if let Some(cartridge_ptr) = self.cartridge {
-            unsafe {
-                let cartridge = &mut *(cartridge_ptr as *mut Cartridge);
-                cartridge.write(address, value);
+            let cartridge = unsafe { &mut *cartridge_ptr };
+            cartridge.write(address, value);
+            if let Some(lint) = self.lint.as_mut() {
+                let mapper = &cartridge.mapper;
+                if mapper.map_sram_address(address).is_none() && mapper.map_address(address).is_some() {
+                    lint.get_mut().report(LintKind::RomWrite, address);
+                }
             }
         }
     }
 
-    fn read_ppu_register(&self, addr: u16) -> u8 {
-        // PPU register reads are handled by the PPU itself
-        // For now, return the cached value
-        if addr >= 0x2100 && addr <= 0x213F {
-            self.ppu_regs[(addr - 0x2100) as usize]
-        } else {
-            0
+    // Dot and scanline the PPU is at, updated by the scheduler
+    pub fn set_beam_position(&mut self, h: u16, v: u16) {
+        self.beam_position = (h, v);
+    }
+
+    // Interlace field the PPU is drawing, for STAT78
+    pub fn set_field(&mut self, field: bool) {
+        self.counters.set_field(field);
+    }
+
+    // Line the PPU starts vblank on this frame, for HVBJOY
+    pub fn set_vblank_start(&mut self, scanline: u16) {
+        self.vblank_start = scanline;
+    }
+
+    pub fn set_latch_at_access(&mut self, enabled: bool) {
+        self.latch_at_access = enabled;
+    }
+
+    pub fn latch_counters(&self) {
+        let (h, v) = if self.latch_at_access { self.access_position() } else { self.beam_position };
+        self.counters.latch(h, v);
+    }
+
+    // Beam position advanced by the dots the instruction's accesses took so far
+    fn access_position(&self) -> (u16, u16) {
+        let (mut h, mut v) = self.beam_position;
+        h += (self.access_cycles.get() / timing::MASTER_CYCLES_PER_DOT) as u16;
+        if h >= timing::DOTS_PER_SCANLINE {
+            h -= timing::DOTS_PER_SCANLINE;
+            v = (v + 1) % timing::SCANLINES_PER_FRAME;
         }
+        (h, v)
     }
 
-    fn write_ppu_register(&mut self, addr: u16, value: u8) {
-        // Cache the value for direct access
-        if addr >= 0x2100 && addr <= 0x213F {
-            self.ppu_regs[(addr - 0x2100) as usize] = value;
+    // $4212 HVBJOY: vblank (bit 7), hblank (bit 6) and auto-joypad read in
+    // progress (bit 0), as of the access itself since games poll it in
+    // tight loops
+    fn read_hvbjoy(&self) -> u8 {
+        let (h, v) = self.access_position();
+        let mut status = 0;
+        if v >= self.vblank_start {
+            status |= 0x80;
+        }
+        if h >= timing::HBLANK_START_DOT || h == 0 {
+            status |= 0x40;
+        }
+        let joypad_start = self.vblank_start as u32 * timing::DOTS_PER_SCANLINE as u32
+            + timing::AUTO_JOYPAD_START_DOT as u32;
+        let now = v as u32 * timing::DOTS_PER_SCANLINE as u32 + h as u32;
+        if self.controller_regs[2] & 0x01 != 0 && (joypad_start..joypad_start + timing::AUTO_JOYPAD_DOTS).contains(&now) {
+            status |= 0x01;
+        }
+        status
+    }
+
+    pub fn counter_latch(&self) -> &CounterLatch {
+        &self.counters
+    }
+
+    // $4210 RDNMI; reading acknowledges the vblank NMI
+    fn read_rdnmi(&self) -> u8 {
+        let flag = if self.nmi_flag.replace(false) { 0x80 } else { 0 };
+        flag | CPU_VERSION
+    }
+
+    fn peek_rdnmi(&self) -> u8 {
+        let flag = if self.nmi_flag.get() { 0x80 } else { 0 };
+        flag | CPU_VERSION
+    }
+
+    pub fn nmi_flag(&self) -> bool {
+        self.nmi_flag.get()
+    }
+
+    pub fn set_nmi_flag(&mut self, flag: bool) {
+        self.nmi_flag.set(flag);
+    }
+
+    // NMITIMEN ($4200) bit 7
+    pub fn nmi_enabled(&self) -> bool {
+        self.controller_regs[2] & 0x80 != 0
+    }
+
+    // Value last written to $4201
+    pub fn wrio(&self) -> u8 {
+        self.controller_regs[0x01 + 2]
+    }
+
+    // Pin levels: a pin reads high only if WRIO drives it high and no device pulls it low
+    pub fn rdio(&self) -> u8 {
+        self.wrio() & !self.io_pins_low
+    }
+
+    // Devices on the controller ports pull I/O pins low (light guns pull pin 7
+    // when they see the beam), which latches the counters like a WRIO write
+    pub fn set_io_pins_low(&mut self, mask: u8) {
+        self.set_io_port(self.wrio(), mask);
+    }
+
+    // Whether STAT78 was read with the I/O pin enabled, and whether $2137
+    // latched the counters, since the last call
+    pub fn take_counter_polls(&self) -> (bool, bool) {
+        let polls = self.counter_polls.take();
+        (polls & COUNTER_POLL_STATUS != 0, polls & COUNTER_POLL_SOFTWARE_LATCH != 0)
+    }
+
+    fn set_io_port(&mut self, wrio: u8, pins_low: u8) {
+        let pin_was_high = self.rdio() & 0x80 != 0;
+        self.controller_regs[0x01 + 2] = wrio;
+        self.io_pins_low = pins_low;
+        if pin_was_high && self.rdio() & 0x80 == 0 {
+            self.latch_counters();
+        }
+    }
+
+    // PPU ports ($2100-$213F in every system bank, see ppu::ports). The
+    // connected PPU answers at the time of the access; a bare bus reads
+    // back the last byte written.
+    fn read_ppu_port(&mut self, port: u16) -> u8 {
+        if ports::port(port).owner == PortOwner::Counters {
+            return self.read_counter_port(port);
+        }
+        match self.ppu {
+            Some(ppu_ptr) => {
+                self.lint_video_port(port);
+                if let (Some(heatmap), 0x2139 | 0x213A) = (&self.heatmap, port) {
+                    let offset = unsafe { (*ppu_ptr).registers.get_vram_address().byte_address() };
+                    heatmap.borrow_mut().vram_read(offset | (port - 0x2139));
+                }
+                unsafe { (*ppu_ptr).read_register(port) }
+            }
+            None => self.ppu_regs[(port - ports::FIRST_PORT) as usize],
+        }
+    }
+
+    fn peek_ppu_port(&self, port: u16) -> u8 {
+        if ports::port(port).owner == PortOwner::Counters {
+            return match port {
+                0x2137 => 0,
+                0x213C => self.counters.peek_h(),
+                0x213D => self.counters.peek_v(),
+                _ => self.counters.peek_status(),
+            };
+        }
+        match self.ppu {
+            Some(ppu_ptr) => unsafe { (*ppu_ptr).peek_register(port) },
+            None => self.ppu_regs[(port - ports::FIRST_PORT) as usize],
+        }
+    }
+
+    fn write_ppu_port(&mut self, port: u16, value: u8) {
+        match self.ppu {
+            Some(ppu_ptr) => {
+                self.lint_video_port(port);
+                if let (Some(heatmap), 0x2118 | 0x2119) = (self.heatmap.as_mut(), port) {
+                    let offset = unsafe { (*ppu_ptr).registers.get_vram_address().byte_address() };
+                    heatmap.get_mut().vram_write(offset | (port - 0x2118));
+                }
+                unsafe { (*ppu_ptr).write_register(port, value) }
+            }
+            None => self.ppu_regs[(port - ports::FIRST_PORT) as usize] = value,
+        }
+    }
+
+    // Lint data port accesses the PPU doesn't allow while it draws
+    fn lint_video_port(&self, port: u16) {
+        let (Some(lint), Some(ppu_ptr)) = (&self.lint, self.ppu) else {
+            return;
+        };
+        let Some(memory) = ports::data_port_memory(port) else {
+            return;
+        };
+        if unsafe { (*ppu_ptr).memory_busy(memory == GraphicsMemory::Cgram) } {
+            lint.borrow_mut().report(LintKind::VideoMemoryWhileDrawing, port as u32);
+        }
+    }
+
+    // H/V counter latch and PPU status
+    fn read_counter_port(&self, port: u16) -> u8 {
+        match port {
+            0x2137 => {
+                if self.wrio() & 0x80 != 0 {
+                    self.latch_counters();
+                    self.counter_polls.set(self.counter_polls.get() | COUNTER_POLL_SOFTWARE_LATCH);
+                }
+                // Open bus
+                0
+            }
+            0x213C => self.counters.read_h(),
+            0x213D => self.counters.read_v(),
+            _ => {
+                if self.wrio() & 0x80 != 0 {
+                    self.counter_polls.set(self.counter_polls.get() | COUNTER_POLL_STATUS);
+                }
+                self.counters.read_status()
+            }
         }
-        // Actual PPU register writes are handled by the PPU itself
     }
 
     // Direct memory access methods for PPU
@@ -315,7 +812,7 @@ impl Bus {
         }
     }
     
-    fn read_controller(&self, addr: u16) -> u8 {
+    fn read_controller(&mut self, addr: u16) -> u8 {
         if let Some(input_ptr) = self.input {
             unsafe {
                 let input = &mut *input_ptr;
@@ -336,6 +833,25 @@ impl Bus {
         }
     }
     
+    // Auto-joypad read at the start of V-Blank: when enabled in $4200 the
+    // controller states are latched into $4218-$421F. Returns true if the
+    // read took place.
+    pub fn auto_joypad_read(&mut self) -> bool {
+        if self.controller_regs[2] & 0x01 == 0 {
+            return false;
+        }
+        let Some(input_ptr) = self.input else { return false };
+        let input = unsafe { &mut *input_ptr };
+        for (player, offset) in [(0u8, 0x18usize), (1, 0x1A)] {
+            let buttons = input.auto_read(player);
+            self.controller_regs[offset + 2] = buttons as u8;
+            self.controller_regs[offset + 3] = (buttons >> 8) as u8;
+        }
+        // Ports 3 and 4 (multitap) are not connected
+        self.controller_regs[0x1E..0x22].fill(0);
+        true
+    }
+    
     fn write_controller(&mut self, addr: u16, value: u8) {
         match addr {
             0x4016 => {
@@ -375,6 +891,9 @@ impl Bus {
     
     pub fn load_memory_state(&mut self, state: &MemoryState) -> Result<()> {
         self.wram = state.wram.clone();
+        if let Some(linter) = self.linter_mut() {
+            linter.mark_wram_written();
+        }
         
         if let (Some(sram_data), Some(cartridge_ptr)) = (&state.sram, self.cartridge) {
             unsafe {
@@ -382,7 +901,19 @@ impl Bus {
                 cartridge.load_sram(sram_data)?;
             }
         }
+        self.apply_freezes();
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// The S-CPU's side of the bus
+impl Bus65816 for Bus {
+    fn read(&mut self, address: u32) -> u8 {
+        self.read8(address)
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        self.write8(address, value);
+    }
+}
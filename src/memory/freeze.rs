@@ -0,0 +1,65 @@
+// Memory freezes: WRAM/SRAM bytes held at a fixed value. The bus re-applies
+// a freeze after every write that lands on a frozen byte, whichever mirror
+// the write went through.
+
+// Physical location of a frozen byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FreezeTarget {
+    // Offset into the 128KB work RAM
+    Wram(usize),
+    // Offset into cartridge SRAM
+    Sram(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Freeze {
+    // CPU address the freeze was requested at
+    pub address: u32,
+    pub target: FreezeTarget,
+    pub value: u8,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FreezeList {
+    entries: Vec<Freeze>,
+}
+
+impl FreezeList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn entries(&self) -> &[Freeze] {
+        &self.entries
+    }
+
+    // Add a freeze, replacing any existing one on the same byte
+    pub fn insert(&mut self, freeze: Freeze) {
+        match self.entries.iter_mut().find(|f| f.target == freeze.target) {
+            Some(existing) => *existing = freeze,
+            None => self.entries.push(freeze),
+        }
+    }
+
+    pub fn remove(&mut self, target: FreezeTarget) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|f| f.target != target);
+        self.entries.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn value_of(&self, target: FreezeTarget) -> Option<u8> {
+        self.entries.iter().find(|f| f.target == target).map(|f| f.value)
+    }
+}
@@ -0,0 +1,87 @@
+// Power-on contents of WRAM and VRAM. Real consoles come up with whatever
+// the RAM chips settle to, and a few games read memory they never cleared,
+// so the pattern is configurable. The seed is kept with save states and
+// movies so a random fill replays identically.
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Separate random streams so WRAM and VRAM don't start out identical
+pub const WRAM_STREAM: u64 = 0x5752_414D;
+pub const VRAM_STREAM: u64 = 0x5652_414D;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoryPattern {
+    // All $00
+    #[default]
+    Zero,
+    // All $FF
+    Ones,
+    // Alternating $55, $AA
+    Stripes,
+    // Pseudo-random bytes from the seed
+    Random,
+}
+
+impl MemoryPattern {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "zero" | "00" => Some(Self::Zero),
+            "ones" | "ff" => Some(Self::Ones),
+            "stripes" | "55aa" => Some(Self::Stripes),
+            "random" => Some(Self::Random),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct MemoryInit {
+    pub pattern: MemoryPattern,
+    // Only used by the random pattern
+    pub seed: u64,
+}
+
+impl MemoryInit {
+    pub fn new(pattern: MemoryPattern, seed: u64) -> Self {
+        Self { pattern, seed }
+    }
+
+    // A random fill without a configured seed gets one from the clock
+    pub fn resolve(pattern: MemoryPattern, seed: Option<u64>) -> Self {
+        let seed = match (pattern, seed) {
+            (_, Some(seed)) => seed,
+            (MemoryPattern::Random, None) => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos() as u64)
+                .unwrap_or(1),
+            _ => 0,
+        };
+        Self { pattern, seed }
+    }
+
+    // Fill `memory`; `stream` picks an independent random sequence
+    pub fn fill(&self, memory: &mut [u8], stream: u64) {
+        match self.pattern {
+            MemoryPattern::Zero => memory.fill(0x00),
+            MemoryPattern::Ones => memory.fill(0xFF),
+            MemoryPattern::Stripes => {
+                for (i, byte) in memory.iter_mut().enumerate() {
+                    *byte = if i % 2 == 0 { 0x55 } else { 0xAA };
+                }
+            }
+            MemoryPattern::Random => {
+                // splitmix64: any seed, including 0, gives a full-period sequence
+                let mut state = self.seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                for chunk in memory.chunks_mut(8) {
+                    state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+                    let mut z = state;
+                    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                    z ^= z >> 31;
+                    chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+                }
+            }
+        }
+    }
+}
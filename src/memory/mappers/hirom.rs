@@ -18,53 +18,20 @@ impl Mapper for HiROMMapper {
         let bank = (address >> 16) & 0xFF;
         let addr = address & 0xFFFF;
         
-        match bank {
-            // Banks $00-$3F: System area
-            0x00..=0x3F => {
-                if addr >= 0x8000 {
-                    // ROM area ($8000-$FFFF)
-                    let rom_offset = (bank << 16) | addr;
-                    if (rom_offset as usize) < self.rom_size {
-                        Some(rom_offset as usize)
-                    } else {
-                        None
-                    }
-                } else {
-                    None // System area
-                }
-            }
-            
-            // Banks $40-$7D: ROM area
-            0x40..=0x7D => {
-                let rom_offset = (bank << 16) | addr;
-                if (rom_offset as usize) < self.rom_size {
-                    Some(rom_offset as usize)
-                } else {
-                    None
-                }
-            }
+        let rom_offset = match bank {
+            // Banks $00-$3F and $80-$BF: upper half of each 64KB bank
+            0x00..=0x3F | 0x80..=0xBF if addr >= 0x8000 => ((bank & 0x3F) << 16) | addr,
             
-            // Banks $80-$BF: ROM mirror
-            0x80..=0xBF => {
-                let rom_offset = ((bank - 0x80) << 16) | addr;
-                if (rom_offset as usize) < self.rom_size {
-                    Some(rom_offset as usize)
-                } else {
-                    None
-                }
-            }
+            // Banks $40-$7D and $C0-$FF: full 64KB banks
+            0x40..=0x7D | 0xC0..=0xFF => ((bank & 0x3F) << 16) | addr,
             
-            // Banks $C0-$FF: ROM area
-            0xC0..=0xFF => {
-                let rom_offset = ((bank - 0x80) << 16) | addr;
-                if (rom_offset as usize) < self.rom_size {
-                    Some(rom_offset as usize)
-                } else {
-                    None
-                }
-            }
-            
-            _ => None,
+            // System area and WRAM
+            _ => return None,
+        };
+        if (rom_offset as usize) < self.rom_size {
+            Some(rom_offset as usize)
+        } else {
+            None
         }
     }
     
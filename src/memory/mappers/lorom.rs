@@ -19,23 +19,13 @@ impl Mapper for LoROMMapper {
         let addr = address & 0xFFFF;
         
         match bank {
-            // Banks $00-$7D: System area
-            0x00..=0x7D => {
-                if addr >= 0x8000 {
-                    // ROM area ($8000-$FFFF)
-                    let rom_offset = ((bank & 0x7F) << 15) | (addr & 0x7FFF);
-                    if (rom_offset as usize) < self.rom_size {
-                        Some(rom_offset as usize)
-                    } else {
-                        None
-                    }
-                } else {
-                    None // System area
-                }
-            }
+            // System area, WRAM and SRAM in the lower halves
+            0x00..=0x3F | 0x80..=0xBF | 0x70..=0x7D | 0xF0..=0xFF if addr < 0x8000 => None,
+            0x7E..=0x7F => None,
             
-            // Banks $80-$FF: ROM mirror
-            0x80..=0xFF => {
+            // 32KB ROM banks; banks $40-$6F and $C0-$EF repeat each bank in
+            // the lower half, and $80-$FF mirror $00-$7F
+            _ => {
                 let rom_offset = ((bank & 0x7F) << 15) | (addr & 0x7FFF);
                 if (rom_offset as usize) < self.rom_size {
                     Some(rom_offset as usize)
@@ -43,8 +33,6 @@ impl Mapper for LoROMMapper {
                     None
                 }
             }
-            
-            _ => None,
         }
     }
     
@@ -16,6 +16,17 @@ pub enum MapperType {
 }
 
 impl MapperType {
+    // Name as given on the command line ("lorom", "hirom", ...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "lorom" => Some(MapperType::LoROM),
+            "hirom" => Some(MapperType::HiROM),
+            "exlorom" => Some(MapperType::ExLoROM),
+            "exhirom" => Some(MapperType::ExHiROM),
+            _ => None,
+        }
+    }
+
     pub fn from_header_byte(byte: u8) -> Self {
         match byte {
             0x20 | 0x30 => MapperType::LoROM,      // LoROM/FastLoROM
@@ -35,6 +46,60 @@ impl MapperType {
     }
 }
 
+/// Kind of cartridge memory a region resolves to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    Rom,
+    Sram,
+}
+
+/// A contiguous range of CPU addresses resolved by a mapper.
+/// Every bank in `banks` maps `start..=end`; the offset advances by the
+/// size of that window for each successive bank.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedRegion {
+    pub kind: RegionKind,
+    pub banks: (u8, u8),
+    pub start: u16,
+    pub end: u16,
+    pub offset: usize,
+}
+
+impl MappedRegion {
+    /// Bytes mapped per bank
+    pub fn window_size(&self) -> usize {
+        (self.end - self.start) as usize + 1
+    }
+    
+    /// Total bytes covered by the region
+    pub fn total_size(&self) -> usize {
+        self.window_size() * ((self.banks.1 - self.banks.0) as usize + 1)
+    }
+}
+
+impl std::fmt::Display for MappedRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            RegionKind::Rom => "ROM",
+            RegionKind::Sram => "SRAM",
+        };
+        write!(
+            f,
+            "${:02X}-${:02X}:${:04X}-${:04X} -> {:<4} ${:06X}-${:06X}",
+            self.banks.0,
+            self.banks.1,
+            self.start,
+            self.end,
+            kind,
+            self.offset,
+            self.offset + self.total_size() - 1,
+        )
+    }
+}
+
+// Granularity used when probing a mapper for its regions
+const REGION_PAGE_SIZE: u32 = 0x1000;
+
 pub trait Mapper: Send + Sync {
     /// Map a CPU address to a ROM offset
     fn map_address(&self, address: u32) -> Option<usize>;
@@ -44,6 +109,66 @@ pub trait Mapper: Send + Sync {
     
     /// Get mapper name
     fn name(&self) -> &'static str;
+    
+    /// Resolve the full 24-bit address space into ROM/SRAM regions
+    fn describe_regions(&self) -> Vec<MappedRegion> {
+        let mut regions: Vec<MappedRegion> = Vec::new();
+        
+        for bank in 0..=0xFFu32 {
+            // Collect linearly mapped windows within this bank
+            let mut bank_regions: Vec<MappedRegion> = Vec::new();
+            for page in 0..(0x10000 / REGION_PAGE_SIZE) {
+                let start = (bank << 16) | (page * REGION_PAGE_SIZE);
+                let end = start + REGION_PAGE_SIZE - 1;
+                
+                // SRAM takes precedence, matching Cartridge::read
+                let probes = [
+                    (RegionKind::Sram, self.map_sram_address(start), self.map_sram_address(end)),
+                    (RegionKind::Rom, self.map_address(start), self.map_address(end)),
+                ];
+                let Some((kind, offset)) = probes.iter().find_map(|&(kind, first, last)| match (first, last) {
+                    (Some(first), Some(last)) if last == first + (REGION_PAGE_SIZE as usize - 1) => Some((kind, first)),
+                    _ => None,
+                }) else {
+                    continue;
+                };
+                
+                let page_start = (start & 0xFFFF) as u16;
+                let page_end = (end & 0xFFFF) as u16;
+                match bank_regions.last_mut() {
+                    Some(prev) if prev.kind == kind
+                        && prev.end.wrapping_add(1) == page_start
+                        && prev.offset + prev.window_size() == offset => {
+                        prev.end = page_end;
+                    }
+                    _ => bank_regions.push(MappedRegion {
+                        kind,
+                        banks: (bank as u8, bank as u8),
+                        start: page_start,
+                        end: page_end,
+                        offset,
+                    }),
+                }
+            }
+            
+            // Merge with a region from the previous bank when it continues linearly
+            for region in bank_regions {
+                let merged = regions.iter_mut().rev().find(|prev| {
+                    prev.kind == region.kind
+                        && prev.banks.1 as u32 + 1 == bank
+                        && prev.start == region.start
+                        && prev.end == region.end
+                        && prev.offset + prev.total_size() == region.offset
+                });
+                match merged {
+                    Some(prev) => prev.banks.1 = bank as u8,
+                    None => regions.push(region),
+                }
+            }
+        }
+        
+        regions
+    }
 }
 
 pub fn create_mapper(mapper_type: MapperType, rom_size: usize, sram_size: usize) -> Result<Box<dyn Mapper>> {
@@ -2,5 +2,9 @@ pub mod bus;
 pub mod dma;
 pub mod mappers;
 pub mod cache;
+pub mod freeze;
+pub mod timing;
+pub mod init;
 
-pub use bus::Bus;
\ No newline at end of file
+pub use bus::Bus;
+pub use init::{MemoryInit, MemoryPattern};
\ No newline at end of file
@@ -0,0 +1,45 @@
+// Master clock cost of CPU bus accesses. The 65C816 cycle length depends on
+// the region being accessed: WRAM and SlowROM take 8 master clocks, I/O
+// registers and FastROM 6, and the old joypad registers 12.
+
+// Master clocks per region
+pub const FAST_CYCLES: u64 = 6;
+pub const SLOW_CYCLES: u64 = 8;
+pub const XSLOW_CYCLES: u64 = 12;
+
+// CPU cycles without a bus access (internal operations)
+pub const IO_CYCLES: u64 = FAST_CYCLES;
+
+// The PPU advances one dot every 4 master cycles
+pub const MASTER_CYCLES_PER_DOT: u64 = 4;
+pub const DOTS_PER_SCANLINE: u16 = 341;
+pub const SCANLINES_PER_FRAME: u16 = 262;
+
+// HVBJOY ($4212) timing, in dots and scanlines: hblank runs from dot 274 to
+// the end of the line, vblank from line 225 (240 with overscan, see
+// Bus::set_vblank_start) to the end of the frame, and the auto-joypad read
+// takes 4224 master cycles from dot 32 of the first vblank line
+pub const HBLANK_START_DOT: u16 = 274;
+pub const VBLANK_START_SCANLINE: u16 = 225;
+pub const AUTO_JOYPAD_START_DOT: u16 = 32;
+pub const AUTO_JOYPAD_DOTS: u32 = 4224 / MASTER_CYCLES_PER_DOT as u32;
+
+// Master clocks for one access to `address`; `fast_rom` is MEMSEL ($420D) bit 0
+pub fn access_cycles(address: u32, fast_rom: bool) -> u64 {
+    let bank = (address >> 16) & 0xFF;
+    let addr = address & 0xFFFF;
+    let rom_cycles = if fast_rom && bank >= 0x80 { FAST_CYCLES } else { SLOW_CYCLES };
+
+    match bank {
+        0x00..=0x3F | 0x80..=0xBF => match addr {
+            0x0000..=0x1FFF => SLOW_CYCLES,
+            0x2000..=0x3FFF => FAST_CYCLES,
+            0x4000..=0x41FF => XSLOW_CYCLES,
+            0x4200..=0x5FFF => FAST_CYCLES,
+            0x6000..=0x7FFF => SLOW_CYCLES,
+            _ => rom_cycles,
+        },
+        0x40..=0x7F => SLOW_CYCLES,
+        _ => rom_cycles,
+    }
+}
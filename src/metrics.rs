@@ -0,0 +1,124 @@
+// Emulation counters and frame-time statistics for frontends. Speed is
+// derived from emulated master cycles against wall time, so it stays
+// accurate under fast-forward, frame skipping or a throttled host.
+use serde::Serialize;
+
+// NTSC master clock (21.477 MHz)
+pub const MASTER_CLOCK_HZ: f64 = 21_477_272.0;
+
+// Frames per second the emulator runs at: 1364 master cycles per scanline,
+// 262 scanlines (60.0985 Hz; the hardware's one short scanline every other
+// frame makes it 60.0988 Hz)
+pub const FRAME_RATE_HZ: f64 = MASTER_CLOCK_HZ / (1364.0 * 262.0);
+
+// Frame-time histogram: 0.5ms buckets covering 0-50ms, plus one overflow bucket
+pub const FRAME_TIME_BUCKET_MS: f64 = 0.5;
+pub const FRAME_TIME_BUCKETS: usize = 101;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Metrics {
+    pub master_cycles: u64,
+    pub instructions: u64,
+    pub frames: u64,
+    pub dma_bytes: u64,
+    // Host time spent in each step_frame, if enabled
+    pub frame_times: Option<FrameTimeHistogram>,
+}
+
+impl Metrics {
+    pub fn emulated_seconds(&self) -> f64 {
+        self.master_cycles as f64 / MASTER_CLOCK_HZ
+    }
+
+    // Percent of real-time speed since an earlier snapshot taken
+    // `wall_seconds` ago (100.0 is full speed)
+    pub fn speed_percent(&self, earlier: &Metrics, wall_seconds: f64) -> f64 {
+        if wall_seconds <= 0.0 {
+            return 0.0;
+        }
+        let emulated = self.master_cycles.saturating_sub(earlier.master_cycles) as f64 / MASTER_CLOCK_HZ;
+        emulated / wall_seconds * 100.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrameTimeHistogram {
+    buckets: Vec<u32>,
+    count: u64,
+    total_ms: f64,
+    min_ms: f64,
+    max_ms: f64,
+}
+
+impl Default for FrameTimeHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameTimeHistogram {
+    pub fn new() -> Self {
+        Self {
+            buckets: vec![0; FRAME_TIME_BUCKETS],
+            count: 0,
+            total_ms: 0.0,
+            min_ms: f64::INFINITY,
+            max_ms: 0.0,
+        }
+    }
+
+    pub fn record(&mut self, ms: f64) {
+        let ms = ms.max(0.0);
+        let bucket = ((ms / FRAME_TIME_BUCKET_MS) as usize).min(FRAME_TIME_BUCKETS - 1);
+        self.buckets[bucket] += 1;
+        self.count += 1;
+        self.total_ms += ms;
+        self.min_ms = self.min_ms.min(ms);
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    // Frame counts per bucket; bucket i covers [i, i+1) * FRAME_TIME_BUCKET_MS
+    // and the last one everything slower
+    pub fn buckets(&self) -> &[u32] {
+        &self.buckets
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn mean_ms(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total_ms / self.count as f64)
+    }
+
+    pub fn min_ms(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min_ms)
+    }
+
+    pub fn max_ms(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max_ms)
+    }
+
+    // Upper edge of the bucket containing the given percentile (0-100)
+    pub fn percentile_ms(&self, percentile: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let target = ((self.count as f64 * percentile.clamp(0.0, 100.0) / 100.0).ceil() as u64).max(1);
+        let mut seen = 0u64;
+        for (i, &frames) in self.buckets.iter().enumerate() {
+            seen += frames as u64;
+            if seen >= target {
+                if i == FRAME_TIME_BUCKETS - 1 {
+                    return Some(self.max_ms);
+                }
+                return Some((i + 1) as f64 * FRAME_TIME_BUCKET_MS);
+            }
+        }
+        Some(self.max_ms)
+    }
+}
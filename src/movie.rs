@@ -0,0 +1,284 @@
+// Recorded controller input, replayed frame by frame from power-on or,
+// for a movie started mid-session, from the save state taken when
+// recording began. A movie stores both pads for every frame plus the title
+// of the ROM it was recorded with, so replays against another game can be
+// caught, and the power-on memory fill so a random one replays identically.
+use crate::emulator::Emulator;
+use crate::input::controller::{
+    BUTTON_A, BUTTON_B, BUTTON_DOWN, BUTTON_L, BUTTON_LEFT, BUTTON_R, BUTTON_RIGHT, BUTTON_SELECT, BUTTON_START,
+    BUTTON_UP, BUTTON_X, BUTTON_Y,
+};
+use crate::input::InputSource;
+use crate::memory::MemoryInit;
+use crate::savestate::SaveState;
+use crate::{EmulatorError, Result};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+
+pub const MOVIE_VERSION: u32 = 3;
+
+// Piano-roll columns: the D-pad as arrows, then "-" for Select and "+"
+// for Start, then the face and shoulder buttons
+pub const BUTTON_COLUMNS: [(u16, char); 12] = [
+    (BUTTON_UP, '^'), (BUTTON_DOWN, 'v'), (BUTTON_LEFT, '<'), (BUTTON_RIGHT, '>'),
+    (BUTTON_SELECT, '-'), (BUTTON_START, '+'),
+    (BUTTON_Y, 'Y'), (BUTTON_B, 'B'), (BUTTON_X, 'X'), (BUTTON_A, 'A'), (BUTTON_L, 'L'), (BUTTON_R, 'R'),
+];
+
+// Fixed-width button readout, "." for buttons that aren't held
+pub fn format_buttons(buttons: u16) -> String {
+    BUTTON_COLUMNS.iter().map(|&(mask, symbol)| if buttons & mask != 0 { symbol } else { '.' }).collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movie {
+    pub version: u32,
+    pub rom_title: String,
+    // Times the recording was rewound and continued
+    pub rerecords: u32,
+    // Power-on memory fill, applied before playback
+    pub memory_init: MemoryInit,
+    // Save state the movie starts from (SaveState::to_bytes), None for
+    // power-on, and the emulator's frame count there
+    start_state: Option<Vec<u8>>,
+    start_frame: u64,
+    // Pad 1 and pad 2 buttons per frame
+    frames: Vec<[u16; 2]>,
+}
+
+impl Movie {
+    pub fn new(rom_title: &str) -> Self {
+        Self {
+            version: MOVIE_VERSION,
+            rom_title: rom_title.trim().to_string(),
+            rerecords: 0,
+            memory_init: MemoryInit::default(),
+            start_state: None,
+            start_frame: 0,
+            frames: Vec::new(),
+        }
+    }
+
+    // Empty movie for the ROM loaded in `emulator`
+    pub fn for_emulator(emulator: &Emulator) -> Self {
+        let mut movie = Self::new(&emulator.get_rom_info().map(|info| info.title).unwrap_or_default());
+        movie.memory_init = emulator.memory_init();
+        movie
+    }
+
+    // Empty movie starting from where `emulator` is now, for recording
+    // from the middle of a session
+    pub fn start_recording(emulator: &mut Emulator) -> Result<Self> {
+        let mut movie = Self::for_emulator(emulator);
+        movie.start_state = Some(emulator.save_state()?.to_bytes()?);
+        movie.start_frame = emulator.get_frame_count();
+        Ok(movie)
+    }
+
+    // Carry on recording from `emulator`, which has loaded a state from
+    // this movie: its final state appends to the movie, an earlier one
+    // drops the frames after it and counts as a rerecord
+    pub fn resume_recording(&mut self, emulator: &Emulator) -> Result<()> {
+        self.check_rom(emulator)?;
+        let frame = emulator.get_frame_count();
+        let offset = frame.checked_sub(self.start_frame).filter(|&offset| offset <= self.frames.len() as u64);
+        let Some(offset) = offset else {
+            return Err(EmulatorError::input(format!(
+                "The game is at frame {}, outside the movie (frames {} to {})",
+                frame,
+                self.start_frame,
+                self.end_frame()
+            )));
+        };
+        if offset < self.frames.len() as u64 {
+            self.frames.truncate(offset as usize);
+            self.rerecords += 1;
+        }
+        Ok(())
+    }
+
+    // Put `emulator` where the movie starts: its start state, or power-on
+    // with the movie's memory fill
+    pub fn start_playback(&self, emulator: &mut Emulator) -> Result<()> {
+        self.check_rom(emulator)?;
+        match &self.start_state {
+            Some(bytes) => emulator.load_state(&SaveState::from_bytes(bytes)?)?,
+            None => {
+                emulator.set_memory_init(self.memory_init);
+                emulator.reset()?;
+            }
+        }
+        Ok(())
+    }
+
+    // Whether the movie starts from a save state rather than power-on
+    pub fn starts_from_state(&self) -> bool {
+        self.start_state.is_some()
+    }
+
+    // Emulator frame counts where the movie starts and where its last
+    // frame ends
+    pub fn start_frame(&self) -> u64 {
+        self.start_frame
+    }
+
+    pub fn end_frame(&self) -> u64 {
+        self.start_frame + self.frames.len() as u64
+    }
+
+    // Whether `other` starts from the same point, so it can replace this one
+    pub fn same_start(&self, other: &Movie) -> bool {
+        self.rom_title == other.rom_title
+            && self.memory_init == other.memory_init
+            && self.start_state == other.start_state
+            && self.start_frame == other.start_frame
+    }
+
+    // Append the pads as they are set for the coming frame
+    pub fn record_frame(&mut self, emulator: &Emulator) {
+        self.frames.push([
+            emulator.input().get_controller_state(0),
+            emulator.input().get_controller_state(1),
+        ]);
+    }
+
+    pub fn push(&mut self, pads: [u16; 2]) {
+        self.frames.push(pads);
+    }
+
+    pub fn frames(&self) -> &[[u16; 2]] {
+        &self.frames
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    // Set one pad's buttons on `frame`, padding the movie with empty frames
+    // when it's past the end. Returns whether the input changed.
+    pub fn set_input(&mut self, frame: usize, player: u8, buttons: u16) -> Result<bool> {
+        if player > 1 {
+            return Err(EmulatorError::input(format!("No controller for player {}", player + 1)));
+        }
+        if frame >= self.frames.len() {
+            if buttons == 0 {
+                return Ok(false);
+            }
+            self.frames.resize(frame + 1, [0; 2]);
+        }
+        let pad = &mut self.frames[frame][player as usize];
+        let changed = *pad != buttons;
+        *pad = buttons;
+        Ok(changed)
+    }
+
+    // Insert `count` empty frames before `frame`
+    pub fn insert_frames(&mut self, frame: usize, count: usize) -> Result<()> {
+        self.check_frame(frame)?;
+        self.frames.splice(frame..frame, std::iter::repeat_n([0; 2], count));
+        Ok(())
+    }
+
+    // Remove `count` frames starting at `frame`; the range may run past the end
+    pub fn delete_frames(&mut self, frame: usize, count: usize) -> Result<()> {
+        self.check_frame(frame)?;
+        let end = frame.saturating_add(count).min(self.frames.len());
+        self.frames.drain(frame..end);
+        Ok(())
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.frames.truncate(len);
+    }
+
+    fn check_frame(&self, frame: usize) -> Result<()> {
+        if frame > self.frames.len() {
+            return Err(EmulatorError::input(format!(
+                "Frame {} is past the end of the movie ({} frames)", frame, self.frames.len()
+            )));
+        }
+        Ok(())
+    }
+
+    // Piano-roll line for `frame`: the frame number and both pads
+    pub fn row(&self, frame: usize) -> Option<String> {
+        let [pad1, pad2] = *self.frames.get(frame)?;
+        Some(format!("{:>7} |{}|{}|", frame, format_buttons(pad1), format_buttons(pad2)))
+    }
+
+    // Error if this movie can't be a continuation of `earlier`: rerecords
+    // only ever go up
+    pub fn check_rerecords(&self, earlier: &Movie) -> Result<()> {
+        if self.rerecords < earlier.rerecords {
+            return Err(EmulatorError::input(format!(
+                "Movie has {} rerecords, fewer than the {} it was edited from", self.rerecords, earlier.rerecords
+            )));
+        }
+        Ok(())
+    }
+
+    // Error if the movie was recorded with a different game
+    pub fn check_rom(&self, emulator: &Emulator) -> Result<()> {
+        let title = emulator.get_rom_info().map(|info| info.title).unwrap_or_default();
+        if !self.rom_title.is_empty() && self.rom_title != title.trim() {
+            return Err(EmulatorError::input(format!(
+                "Movie was recorded with '{}', but '{}' is loaded", self.rom_title, title.trim()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+        bincode::serialize_into(encoder, self)
+            .map_err(|e| EmulatorError::input(format!("Failed to write movie: {}", e)))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let movie: Movie = bincode::deserialize_from(GzDecoder::new(File::open(path)?))
+            .map_err(|e| EmulatorError::input(format!("Failed to read movie: {}", e)))?;
+        if movie.version != MOVIE_VERSION {
+            return Err(EmulatorError::input(format!(
+                "Movie version mismatch: expected {}, got {}", MOVIE_VERSION, movie.version
+            )));
+        }
+        Ok(movie)
+    }
+}
+
+impl InputSource for Movie {
+    fn buttons(&self, frame: u64, player: u8) -> u16 {
+        self.frames
+            .get(frame as usize)
+            .and_then(|pads| pads.get(player as usize))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn frame_count(&self) -> u64 {
+        self.frames.len() as u64
+    }
+}
+
+// Run `frames` frames, setting both pads from `inputs` before each one.
+// Frames are counted from the first one run here.
+pub fn play(emulator: &mut Emulator, inputs: &dyn InputSource, frames: u64, mut record: Option<&mut Movie>) -> Result<()> {
+    for frame in 0..frames {
+        for player in 0..2 {
+            emulator.set_controller_input(player, inputs.buttons(frame, player));
+        }
+        if let Some(movie) = record.as_deref_mut() {
+            movie.record_frame(emulator);
+        }
+        emulator.step_frame()?;
+    }
+    Ok(())
+}
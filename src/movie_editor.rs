@@ -0,0 +1,204 @@
+// Editing a recorded movie frame by frame, the core of a piano-roll TAS
+// editor. The editor keeps a greenzone: save states taken while replaying
+// the movie, so seeking to a frame replays from the closest one instead of
+// from the start. Editing a frame drops the states after it, and editing a
+// frame that was already played counts as a rerecord.
+use crate::emulator::Emulator;
+use crate::input::InputSource;
+use crate::movie::Movie;
+use crate::savestate::SaveState;
+use crate::{EmulatorError, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Frames between greenzone states
+pub const DEFAULT_GREENZONE_INTERVAL: u64 = 10;
+// States kept; a state is roughly 260 KB
+pub const DEFAULT_GREENZONE_CAPACITY: usize = 256;
+
+pub struct MovieEditor {
+    movie: Movie,
+    // State before the keyed frame runs; frame 0 is the movie's start
+    greenzone: BTreeMap<u64, SaveState>,
+    interval: u64,
+    capacity: usize,
+    // Frame the emulator runs next
+    playback: u64,
+    // The emulator ran past an edit and must replay to `playback`
+    needs_seek: bool,
+    // Rerecords the movie must have: the count it was opened with plus
+    // the edits to frames already played since
+    expected_rerecords: u32,
+}
+
+impl MovieEditor {
+    // Put `emulator` at the movie's start (power-on with its memory fill,
+    // or its start state) and start at frame 0
+    pub fn new(movie: Movie, emulator: &mut Emulator) -> Result<Self> {
+        movie.start_playback(emulator)?;
+        let mut greenzone = BTreeMap::new();
+        greenzone.insert(0, emulator.save_state()?);
+        Ok(Self {
+            expected_rerecords: movie.rerecords,
+            movie,
+            greenzone,
+            interval: DEFAULT_GREENZONE_INTERVAL,
+            capacity: DEFAULT_GREENZONE_CAPACITY,
+            playback: 0,
+            needs_seek: false,
+        })
+    }
+
+    pub fn movie(&self) -> &Movie {
+        &self.movie
+    }
+
+    pub fn into_movie(self) -> Movie {
+        self.movie
+    }
+
+    pub fn playback_frame(&self) -> u64 {
+        self.playback
+    }
+
+    pub fn set_greenzone_interval(&mut self, frames: u64) {
+        self.interval = frames.max(1);
+    }
+
+    // At least the start state is always kept
+    pub fn set_greenzone_capacity(&mut self, states: usize) {
+        self.capacity = states.max(1);
+        self.trim_greenzone();
+    }
+
+    // Frames with a cached state, in order
+    pub fn greenzone_frames(&self) -> impl Iterator<Item = u64> + '_ {
+        self.greenzone.keys().copied()
+    }
+
+    // Set one pad's buttons on `frame`, extending the movie if needed
+    pub fn set_input(&mut self, frame: u64, player: u8, buttons: u16) -> Result<()> {
+        if self.movie.set_input(frame as usize, player, buttons)? {
+            self.invalidate_from(frame);
+        }
+        Ok(())
+    }
+
+    pub fn insert_frames(&mut self, frame: u64, count: usize) -> Result<()> {
+        self.movie.insert_frames(frame as usize, count)?;
+        if count > 0 {
+            self.invalidate_from(frame);
+        }
+        Ok(())
+    }
+
+    pub fn delete_frames(&mut self, frame: u64, count: usize) -> Result<()> {
+        let len = self.movie.len();
+        self.movie.delete_frames(frame as usize, count)?;
+        if self.movie.len() != len {
+            self.invalidate_from(frame);
+        }
+        Ok(())
+    }
+
+    // States after `frame` were run with the old input
+    fn invalidate_from(&mut self, frame: u64) {
+        self.greenzone.split_off(&(frame + 1));
+        if frame < self.playback {
+            self.movie.rerecords += 1;
+            self.expected_rerecords += 1;
+            self.needs_seek = true;
+        }
+    }
+
+    // Put the emulator at the start of `frame`, replaying from the closest
+    // greenzone state before it
+    pub fn seek(&mut self, emulator: &mut Emulator, frame: u64) -> Result<()> {
+        // Going forward from where the emulator is needs no state
+        if self.needs_seek || frame < self.playback {
+            let (&start, state) = self.greenzone.range(..=frame).next_back()
+                .ok_or_else(|| EmulatorError::input("Greenzone has no start state"))?;
+            emulator.load_state(state)?;
+            self.playback = start;
+        }
+        self.needs_seek = false;
+        while self.playback < frame {
+            self.run_frame(emulator)?;
+        }
+        Ok(())
+    }
+
+    // Run the next frame of the movie, catching up on edits first
+    pub fn advance(&mut self, emulator: &mut Emulator) -> Result<()> {
+        if self.needs_seek {
+            self.seek(emulator, self.playback)?;
+        }
+        self.run_frame(emulator)
+    }
+
+    fn run_frame(&mut self, emulator: &mut Emulator) -> Result<()> {
+        for player in 0..2 {
+            emulator.set_controller_input(player, self.movie.buttons(self.playback, player));
+        }
+        emulator.step_frame()?;
+        self.playback += 1;
+        if self.playback.is_multiple_of(self.interval) && !self.greenzone.contains_key(&self.playback) {
+            self.greenzone.insert(self.playback, emulator.save_state()?);
+            self.trim_greenzone();
+        }
+        Ok(())
+    }
+
+    // Drop the states farthest from the playback position, never frame 0
+    fn trim_greenzone(&mut self) {
+        while self.greenzone.len() > self.capacity.max(1) {
+            let playback = self.playback;
+            let farthest = self.greenzone.keys().copied().filter(|&frame| frame != 0).max_by_key(|&frame| frame.abs_diff(playback));
+            match farthest {
+                Some(frame) => self.greenzone.remove(&frame),
+                None => break,
+            };
+        }
+    }
+
+    // Swap in another version of the movie, e.g. one edited elsewhere and
+    // reloaded. It must be for the same game and can't have fewer
+    // rerecords; the greenzone is kept up to the first changed frame.
+    pub fn replace_movie(&mut self, movie: Movie) -> Result<()> {
+        if !movie.same_start(&self.movie) {
+            return Err(EmulatorError::input(format!(
+                "Movie for '{}' doesn't start where the one for '{}' does and can't replace it", movie.rom_title, self.movie.rom_title
+            )));
+        }
+        movie.check_rerecords(&self.movie)?;
+        let (old, new) = (self.movie.frames(), movie.frames());
+        let first_change = old.iter().zip(new).position(|(a, b)| a != b)
+            .or_else(|| (old.len() != new.len()).then(|| old.len().min(new.len())));
+        self.expected_rerecords = self.expected_rerecords.max(movie.rerecords);
+        self.movie = movie;
+        if let Some(frame) = first_change {
+            self.invalidate_from(frame as u64);
+        }
+        Ok(())
+    }
+
+    // Error if the rerecord count fell below the edits counted so far, or
+    // the greenzone lost the state everything replays from
+    pub fn verify(&self) -> Result<()> {
+        if self.movie.rerecords < self.expected_rerecords {
+            return Err(EmulatorError::input(format!(
+                "Movie has {} rerecords but {} were counted while editing",
+                self.movie.rerecords, self.expected_rerecords
+            )));
+        }
+        if !self.greenzone.contains_key(&0) {
+            return Err(EmulatorError::input("Greenzone lost its start state"));
+        }
+        Ok(())
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.verify()?;
+        self.movie.save(path)
+    }
+}
@@ -0,0 +1,221 @@
+// Per-game storage layout for saves, states, screenshots and cheats
+use crate::config::PathConfig;
+use crate::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Identifies a game by file name and ROM contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameId {
+    // File name cleaned up for use in directory names
+    pub name: String,
+    // File name as given, which the flat layout has always named files by
+    pub stem: String,
+    pub crc32: u32,
+}
+
+impl GameId {
+    pub fn new(name: &str, rom_data: &[u8]) -> Self {
+        let mut crc = flate2::Crc::new();
+        crc.update(rom_data);
+        Self {
+            name: sanitize_name(name),
+            stem: name.to_string(),
+            crc32: crc.sum(),
+        }
+    }
+
+    // Build an id from a ROM file path and its contents
+    pub fn from_rom_path(rom_path: &Path, rom_data: &[u8]) -> Self {
+        let stem = rom_path.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "game".to_string());
+        Self::new(&stem, rom_data)
+    }
+
+    // Directory name used under the data root
+    pub fn dir_name(&self) -> String {
+        format!("{}-{:08X}", self.name, self.crc32)
+    }
+}
+
+// Replace characters that are awkward in file names
+fn sanitize_name(name: &str) -> String {
+    let cleaned: String = name.trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | '(' | ')' | '[' | ']') { c } else { '_' })
+        .collect();
+    if cleaned.is_empty() {
+        "game".to_string()
+    } else {
+        cleaned
+    }
+}
+
+// Resolved file locations for one game
+#[derive(Debug, Clone)]
+pub struct GamePaths {
+    name: String,
+    sram: PathBuf,
+    states_dir: PathBuf,
+    state_prefix: String,
+    screenshot_dir: PathBuf,
+    cheats: PathBuf,
+    profile: PathBuf,
+}
+
+impl GamePaths {
+    // Pick the per-game or flat layout based on the configuration
+    pub fn new(config: &PathConfig, game: &GameId) -> Self {
+        if config.per_game_dirs {
+            Self::per_game(&config.data_root, game)
+        } else {
+            Self::flat(config, &game.stem)
+        }
+    }
+
+    // Resolve the layout for a game and, with per-game directories enabled,
+    // move any files left in the flat layout into it
+    pub fn prepare(config: &PathConfig, game: &GameId) -> Result<Self> {
+        let paths = Self::new(config, game);
+        if config.per_game_dirs {
+            let moved = paths.migrate_from(&Self::flat(config, &game.stem))?;
+            if !moved.is_empty() {
+                log::info!("Migrated {} file(s) for {}", moved.len(), game.dir_name());
+            }
+        }
+        Ok(paths)
+    }
+
+    // <root>/<name>-<crc>/{<name>.srm, states/, screenshots/, cheats.toml, profile.toml}
+    pub fn per_game(root: &Path, game: &GameId) -> Self {
+        let dir = root.join(game.dir_name());
+        Self {
+            name: game.name.clone(),
+            sram: dir.join(format!("{}.srm", game.name)),
+            states_dir: dir.join("states"),
+            state_prefix: "slot".to_string(),
+            screenshot_dir: dir.join("screenshots"),
+            cheats: dir.join("cheats.toml"),
+            profile: dir.join("profile.toml"),
+        }
+    }
+
+    // Legacy layout: one shared directory per data type, files prefixed by name
+    pub fn flat(config: &PathConfig, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            sram: config.sram_dir.join(format!("{}.srm", name)),
+            states_dir: config.save_state_dir.clone(),
+            state_prefix: format!("{}.st", name),
+            screenshot_dir: config.screenshot_dir.clone(),
+            cheats: config.sram_dir.join(format!("{}.cht", name)),
+            profile: config.sram_dir.join(format!("{}.profile.toml", name)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn sram_path(&self) -> &Path {
+        &self.sram
+    }
+
+    pub fn state_path(&self, slot: u8) -> PathBuf {
+        self.states_dir.join(format!("{}{}", self.state_prefix, slot))
+    }
+
+    // Movie recorded in the frontend, next to the save states
+    pub fn movie_path(&self) -> PathBuf {
+        self.states_dir.join(format!("{}.movie", self.name))
+    }
+
+    pub fn screenshot_dir(&self) -> &Path {
+        &self.screenshot_dir
+    }
+
+    pub fn screenshot_path(&self, timestamp: u128, extension: &str) -> PathBuf {
+        self.screenshot_dir.join(format!("{}-{}.{}", self.name, timestamp, extension))
+    }
+
+    // Whether `file_name` is one of this game's screenshot_path names, and
+    // not that of a game whose name merely starts with this one's
+    fn is_screenshot(&self, file_name: &str) -> bool {
+        let Some(rest) = file_name.strip_prefix(&self.name).and_then(|rest| rest.strip_prefix('-')) else {
+            return false;
+        };
+        match rest.split_once('.') {
+            Some((timestamp, extension)) => {
+                !timestamp.is_empty() && timestamp.bytes().all(|b| b.is_ascii_digit()) && !extension.is_empty()
+            }
+            None => false,
+        }
+    }
+
+    pub fn cheats_path(&self) -> &Path {
+        &self.cheats
+    }
+
+    pub fn profile_path(&self) -> &Path {
+        &self.profile
+    }
+
+    pub fn create_directories(&self) -> Result<()> {
+        if let Some(parent) = self.sram.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::create_dir_all(&self.states_dir)?;
+        fs::create_dir_all(&self.screenshot_dir)?;
+        Ok(())
+    }
+
+    // Move files for this game out of a flat layout. Existing destination
+    // files are never overwritten. Returns the new locations of moved files.
+    pub fn migrate_from(&self, flat: &GamePaths) -> Result<Vec<PathBuf>> {
+        let mut moved = Vec::new();
+        if flat.sram == self.sram && flat.states_dir == self.states_dir {
+            return Ok(moved);
+        }
+
+        self.create_directories()?;
+
+        let mut pairs = vec![
+            (flat.sram.clone(), self.sram.clone()),
+            (flat.cheats.clone(), self.cheats.clone()),
+            (flat.profile.clone(), self.profile.clone()),
+        ];
+        for slot in 0..=9 {
+            pairs.push((flat.state_path(slot), self.state_path(slot)));
+        }
+
+        // Screenshots named "<name>-<timestamp>.<ext>"
+        if let Ok(entries) = fs::read_dir(&flat.screenshot_dir) {
+            for entry in entries.flatten() {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if flat.is_screenshot(&file_name) && entry.path() != self.screenshot_dir.join(&file_name) {
+                    pairs.push((entry.path(), self.screenshot_dir.join(&file_name)));
+                }
+            }
+        }
+
+        for (from, to) in pairs {
+            if from.is_file() && !to.exists() {
+                move_file(&from, &to)?;
+                log::info!("Migrated {:?} -> {:?}", from, to);
+                moved.push(to);
+            }
+        }
+
+        Ok(moved)
+    }
+}
+
+// Rename, falling back to copy + remove across file systems
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::ppu::memory::{Vram, Cgram};
+use crate::ppu::memory::{Vram, VramAddress, Cgram};
 use crate::ppu::registers::PpuRegisters;
 
 // Tile size constants
@@ -36,8 +36,8 @@ impl From<u8> for BgMode {
 // Background layer info
 #[derive(Debug, Clone, Copy)]
 pub struct BackgroundInfo {
-    pub tilemap_base: u16,    // VRAM address of tilemap
-    pub tile_base: u16,       // VRAM address of tiles
+    pub tilemap_base: VramAddress, // VRAM word address of tilemap
+    pub tile_base: VramAddress,    // VRAM word address of tiles
     pub tilemap_size: (u8, u8), // Width/height (0=32, 1=64)
     pub tile_size: bool,      // false=8x8, true=16x16
     pub priority: [bool; 2],  // Priority bits
@@ -51,15 +51,24 @@ pub struct BackgroundRenderer {
     bg2_buffer: Vec<u8>,
     bg3_buffer: Vec<u8>,
     bg4_buffer: Vec<u8>,
+    // Priority bit of the tile under each pixel, per layer
+    bg_priority: [Vec<bool>; 4],
 }
 
 impl BackgroundRenderer {
     pub fn new() -> Self {
+        Self::with_width(256)
+    }
+    
+    // Buffers for `width` columns, which needn't be those of the screen
+    // (see render_columns)
+    pub fn with_width(width: usize) -> Self {
         Self {
-            bg1_buffer: vec![0; 256 * 4], // RGBA buffer
-            bg2_buffer: vec![0; 256 * 4],
-            bg3_buffer: vec![0; 256 * 4],
-            bg4_buffer: vec![0; 256 * 4],
+            bg1_buffer: vec![0; width * 4], // RGBA buffer
+            bg2_buffer: vec![0; width * 4],
+            bg3_buffer: vec![0; width * 4],
+            bg4_buffer: vec![0; width * 4],
+            bg_priority: [vec![false; width], vec![false; width], vec![false; width], vec![false; width]],
         }
     }
     
@@ -73,14 +82,14 @@ impl BackgroundRenderer {
         };
         
         // Extract tilemap base address (bits 2-7 of BGnSC)
-        let tilemap_base = ((sc_reg & 0xFC) as u16) << 9; // * 1KB
+        let tilemap_base = VramAddress::new(((sc_reg & 0xFC) as u16) << 8); // * 1K words
         
         // Extract tile base address
-        let tile_base = if bg_num <= 2 {
+        let tile_base = VramAddress::new(if bg_num <= 2 {
             ((bg_reg & 0x0F) as u16) << 12 // BG1/2 use low nibble
         } else {
             (((bg_reg & 0xF0) >> 4) as u16) << 12 // BG3/4 use high nibble
-        };
+        });
         
         // Extract tilemap size (bits 0-1 of BGnSC)
         let size_bits = sc_reg & 0x03;
@@ -112,54 +121,98 @@ impl BackgroundRenderer {
             tile_base,
             tilemap_size,
             tile_size,
-            priority: [false, false], // TODO: Extract from tilemap entries
+            priority: [false, false], // Per tile; see BackgroundRenderer::pixel
             h_scroll,
             v_scroll,
         }
     }
 
+    // Draw each background of the current mode into its own buffer; the
+    // priority compositor decides which one shows at each pixel
     pub fn render_scanline(
         &mut self,
         vram: &Vram,
         cgram: &Cgram,
         registers: &PpuRegisters,
         scanline: u16,
-    ) -> &[u8] {
+        field: bool,
+    ) {
+        self.render_columns(vram, cgram, registers, scanline, field, 0);
+    }
+    
+    // Draw the buffers' worth of columns from screen column `first_x` on.
+    // Columns off the 256-pixel screen continue the tilemap past its
+    // edges, for the widescreen enhancement.
+    pub fn render_columns(
+        &mut self,
+        vram: &Vram,
+        cgram: &Cgram,
+        registers: &PpuRegisters,
+        scanline: u16,
+        field: bool,
+        first_x: i32,
+    ) {
         let bg_mode = BgMode::from(registers.bgmode);
+        let scanline = Self::field_line(registers, scanline, field);
         
         // Clear buffers
         self.bg1_buffer.fill(0);
         self.bg2_buffer.fill(0);
         self.bg3_buffer.fill(0);
         self.bg4_buffer.fill(0);
+        for priority in &mut self.bg_priority {
+            priority.fill(false);
+        }
+        let [p1, p2, p3, p4] = &mut self.bg_priority;
         
         // Render appropriate backgrounds based on mode
         match bg_mode {
             BgMode::Mode0 => {
                 // 4 backgrounds, 2bpp each
-                Self::render_bg_2bpp(vram, cgram, registers, 1, scanline, &mut self.bg1_buffer);
-                Self::render_bg_2bpp(vram, cgram, registers, 2, scanline, &mut self.bg2_buffer);
-                Self::render_bg_2bpp(vram, cgram, registers, 3, scanline, &mut self.bg3_buffer);
-                Self::render_bg_2bpp(vram, cgram, registers, 4, scanline, &mut self.bg4_buffer);
+                Self::render_bg_2bpp(vram, cgram, registers, 1, (first_x, scanline), &mut self.bg1_buffer, p1);
+                Self::render_bg_2bpp(vram, cgram, registers, 2, (first_x, scanline), &mut self.bg2_buffer, p2);
+                Self::render_bg_2bpp(vram, cgram, registers, 3, (first_x, scanline), &mut self.bg3_buffer, p3);
+                Self::render_bg_2bpp(vram, cgram, registers, 4, (first_x, scanline), &mut self.bg4_buffer, p4);
             }
             BgMode::Mode1 => {
                 // BG1/2: 4bpp, BG3: 2bpp
-                Self::render_bg_4bpp(vram, cgram, registers, 1, scanline, &mut self.bg1_buffer);
-                Self::render_bg_4bpp(vram, cgram, registers, 2, scanline, &mut self.bg2_buffer);
-                Self::render_bg_2bpp(vram, cgram, registers, 3, scanline, &mut self.bg3_buffer);
+                Self::render_bg_4bpp(vram, cgram, registers, 1, (first_x, scanline), &mut self.bg1_buffer, p1);
+                Self::render_bg_4bpp(vram, cgram, registers, 2, (first_x, scanline), &mut self.bg2_buffer, p2);
+                Self::render_bg_2bpp(vram, cgram, registers, 3, (first_x, scanline), &mut self.bg3_buffer, p3);
             }
             BgMode::Mode3 => {
                 // BG1: 8bpp, BG2: 4bpp
-                Self::render_bg_8bpp(vram, cgram, registers, 1, scanline, &mut self.bg1_buffer);
-                Self::render_bg_4bpp(vram, cgram, registers, 2, scanline, &mut self.bg2_buffer);
+                Self::render_bg_8bpp(vram, cgram, registers, 1, (first_x, scanline), &mut self.bg1_buffer, p1);
+                Self::render_bg_4bpp(vram, cgram, registers, 2, (first_x, scanline), &mut self.bg2_buffer, p2);
             }
             _ => {
                 // TODO: Implement other modes
             }
         }
-        
-        // For now, just return BG1 buffer
-        &self.bg1_buffer
+    }
+    
+    // Line of the background image drawn on `scanline`: interlaced modes 5
+    // and 6 have 448 lines and each field shows every other one
+    pub fn field_line(registers: &PpuRegisters, scanline: u16, field: bool) -> u16 {
+        let bg_mode = BgMode::from(registers.bgmode);
+        if registers.is_interlaced() && matches!(bg_mode, BgMode::Mode5 | BgMode::Mode6) {
+            scanline * 2 + field as u16
+        } else {
+            scanline
+        }
+    }
+    
+    // Pixel of background `bg` (0-3) at `x` and the priority bit of its
+    // tile; None where the layer is transparent
+    pub fn pixel(&self, bg: usize, x: usize) -> Option<(&[u8], bool)> {
+        let buffer = match bg {
+            0 => &self.bg1_buffer,
+            1 => &self.bg2_buffer,
+            2 => &self.bg3_buffer,
+            _ => &self.bg4_buffer,
+        };
+        let rgba = &buffer[x * 4..x * 4 + 4];
+        (rgba[3] != 0).then(|| (rgba, self.bg_priority[bg.min(3)][x]))
     }
     
     fn render_bg_2bpp(
@@ -167,8 +220,9 @@ impl BackgroundRenderer {
         cgram: &Cgram,
         registers: &PpuRegisters,
         bg_num: u8,
-        scanline: u16,
+        (first_x, scanline): (i32, u16),
         buffer: &mut [u8],
+        priority: &mut [bool],
     ) {
         let bg_info = Self::get_bg_info(registers, bg_num);
         let y = (scanline as u32 + bg_info.v_scroll as u32) & 0x1FF;
@@ -176,18 +230,18 @@ impl BackgroundRenderer {
         let fine_y = y % TILE_SIZE as u32;
         
         // Render each pixel in the scanline
-        for x in 0..256u16 {
-            let scroll_x = (x as u32 + bg_info.h_scroll as u32) & 0x1FF;
+        for x in 0..priority.len() as i32 {
+            let scroll_x = (first_x + x + bg_info.h_scroll as i32) as u32 & 0x1FF;
             let tile_x = scroll_x / TILE_SIZE as u32;
             let fine_x = scroll_x % TILE_SIZE as u32;
             
             // Calculate tilemap address
             let tilemap_x = tile_x & 31;
             let tilemap_y = tile_y & 31;
-            let tilemap_addr = bg_info.tilemap_base + (tilemap_y * 32 + tilemap_x) as u16 * 2;
+            let tilemap_addr = bg_info.tilemap_base.offset((tilemap_y * 32 + tilemap_x) as u16);
             
             // Read tilemap entry
-            let tilemap_entry = vram.read16(tilemap_addr);
+            let tilemap_entry = vram.read_word(tilemap_addr);
             let tile_num = tilemap_entry & 0x3FF;
             let palette_num = ((tilemap_entry >> 10) & 0x07) as u8;
             let h_flip = (tilemap_entry & 0x4000) != 0;
@@ -197,12 +251,11 @@ impl BackgroundRenderer {
             let pixel_x = if h_flip { 7 - fine_x } else { fine_x };
             let pixel_y = if v_flip { 7 - fine_y } else { fine_y };
             
-            // Read tile data (2bpp = 2 bits per pixel)
-            let tile_addr = bg_info.tile_base + tile_num * 8;
-            let byte_offset = pixel_y * 2; // 2 bytes per row in 2bpp
+            // Read tile data (2bpp = 2 bits per pixel, one word per row)
+            let row = bg_info.tile_base.offset(tile_num * 8 + pixel_y as u16);
             
-            let low_byte = vram.read(tile_addr + byte_offset as u16);
-            let high_byte = vram.read(tile_addr + byte_offset as u16 + 1);
+            let low_byte = vram.read_low(row);
+            let high_byte = vram.read_high(row);
             
             let bit_mask = 0x80 >> pixel_x;
             let low_bit = if (low_byte & bit_mask) != 0 { 1 } else { 0 };
@@ -225,6 +278,7 @@ impl BackgroundRenderer {
             buffer[buffer_offset + 1] = g;
             buffer[buffer_offset + 2] = b;
             buffer[buffer_offset + 3] = 255;
+            priority[x as usize] = (tilemap_entry & 0x2000) != 0;
         }
     }
     
@@ -233,24 +287,25 @@ impl BackgroundRenderer {
         cgram: &Cgram,
         registers: &PpuRegisters,
         bg_num: u8,
-        scanline: u16,
+        (first_x, scanline): (i32, u16),
         buffer: &mut [u8],
+        priority: &mut [bool],
     ) {
         let bg_info = Self::get_bg_info(registers, bg_num);
         let y = (scanline as u32 + bg_info.v_scroll as u32) & 0x1FF;
         let tile_y = y / TILE_SIZE as u32;
         let fine_y = y % TILE_SIZE as u32;
         
-        for x in 0..256u16 {
-            let scroll_x = (x as u32 + bg_info.h_scroll as u32) & 0x1FF;
+        for x in 0..priority.len() as i32 {
+            let scroll_x = (first_x + x + bg_info.h_scroll as i32) as u32 & 0x1FF;
             let tile_x = scroll_x / TILE_SIZE as u32;
             let fine_x = scroll_x % TILE_SIZE as u32;
             
             let tilemap_x = tile_x & 31;
             let tilemap_y = tile_y & 31;
-            let tilemap_addr = bg_info.tilemap_base + (tilemap_y * 32 + tilemap_x) as u16 * 2;
+            let tilemap_addr = bg_info.tilemap_base.offset((tilemap_y * 32 + tilemap_x) as u16);
             
-            let tilemap_entry = vram.read16(tilemap_addr);
+            let tilemap_entry = vram.read_word(tilemap_addr);
             let tile_num = tilemap_entry & 0x3FF;
             let palette_num = ((tilemap_entry >> 10) & 0x07) as u8;
             let h_flip = (tilemap_entry & 0x4000) != 0;
@@ -259,14 +314,13 @@ impl BackgroundRenderer {
             let pixel_x = if h_flip { 7 - fine_x } else { fine_x };
             let pixel_y = if v_flip { 7 - fine_y } else { fine_y };
             
-            // 4bpp = 4 bits per pixel, 4 bytes per row
-            let tile_addr = bg_info.tile_base + tile_num * 16;
-            let byte_offset = pixel_y * 2;
+            // 4bpp = 4 bits per pixel, planes 2-3 8 words after planes 0-1
+            let row = bg_info.tile_base.offset(tile_num * 16 + pixel_y as u16);
             
-            let plane0 = vram.read(tile_addr + byte_offset as u16);
-            let plane1 = vram.read(tile_addr + byte_offset as u16 + 1);
-            let plane2 = vram.read(tile_addr + byte_offset as u16 + 8);
-            let plane3 = vram.read(tile_addr + byte_offset as u16 + 9);
+            let plane0 = vram.read_low(row);
+            let plane1 = vram.read_high(row);
+            let plane2 = vram.read_low(row.offset(8));
+            let plane3 = vram.read_high(row.offset(8));
             
             let bit_mask = 0x80 >> pixel_x;
             let bit0 = if (plane0 & bit_mask) != 0 { 1 } else { 0 };
@@ -288,6 +342,7 @@ impl BackgroundRenderer {
             buffer[buffer_offset + 1] = g;
             buffer[buffer_offset + 2] = b;
             buffer[buffer_offset + 3] = 255;
+            priority[x as usize] = (tilemap_entry & 0x2000) != 0;
         }
     }
     
@@ -296,24 +351,25 @@ impl BackgroundRenderer {
         cgram: &Cgram,
         registers: &PpuRegisters,
         bg_num: u8,
-        scanline: u16,
+        (first_x, scanline): (i32, u16),
         buffer: &mut [u8],
+        priority: &mut [bool],
     ) {
         let bg_info = Self::get_bg_info(registers, bg_num);
         let y = (scanline as u32 + bg_info.v_scroll as u32) & 0x1FF;
         let tile_y = y / TILE_SIZE as u32;
         let fine_y = y % TILE_SIZE as u32;
         
-        for x in 0..256u16 {
-            let scroll_x = (x as u32 + bg_info.h_scroll as u32) & 0x1FF;
+        for x in 0..priority.len() as i32 {
+            let scroll_x = (first_x + x + bg_info.h_scroll as i32) as u32 & 0x1FF;
             let tile_x = scroll_x / TILE_SIZE as u32;
             let fine_x = scroll_x % TILE_SIZE as u32;
             
             let tilemap_x = tile_x & 31;
             let tilemap_y = tile_y & 31;
-            let tilemap_addr = bg_info.tilemap_base + (tilemap_y * 32 + tilemap_x) as u16 * 2;
+            let tilemap_addr = bg_info.tilemap_base.offset((tilemap_y * 32 + tilemap_x) as u16);
             
-            let tilemap_entry = vram.read16(tilemap_addr);
+            let tilemap_entry = vram.read_word(tilemap_addr);
             let tile_num = tilemap_entry & 0x3FF;
             let h_flip = (tilemap_entry & 0x4000) != 0;
             let v_flip = (tilemap_entry & 0x8000) != 0;
@@ -321,15 +377,14 @@ impl BackgroundRenderer {
             let pixel_x = if h_flip { 7 - fine_x } else { fine_x };
             let pixel_y = if v_flip { 7 - fine_y } else { fine_y };
             
-            // 8bpp = 8 bits per pixel, 8 bytes per row
-            let tile_addr = bg_info.tile_base + tile_num * 32;
-            let byte_offset = pixel_y * 4;
+            // 8bpp = 8 bits per pixel, each pair of planes 8 words apart
+            let row = bg_info.tile_base.offset(tile_num * 32 + pixel_y as u16);
             
             // Read all 8 bitplanes
             let mut color_index = 0u8;
             for plane in 0..8 {
-                let plane_offset = (plane / 2) * 8 + (plane % 2);
-                let plane_byte = vram.read(tile_addr + byte_offset as u16 + plane_offset);
+                let pair = row.offset((plane / 2) * 8);
+                let plane_byte = if plane % 2 == 0 { vram.read_low(pair) } else { vram.read_high(pair) };
                 let bit_mask = 0x80 >> pixel_x;
                 if (plane_byte & bit_mask) != 0 {
                     color_index |= 1 << plane;
@@ -348,6 +403,7 @@ impl BackgroundRenderer {
             buffer[buffer_offset + 1] = g;
             buffer[buffer_offset + 2] = b;
             buffer[buffer_offset + 3] = 255;
+            priority[x as usize] = (tilemap_entry & 0x2000) != 0;
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,114 @@
+// Color math: CGWSEL ($2130) and CGADSUB ($2131) add the sub screen, or
+// the fixed color, to the main screen or subtract it, per pixel and only
+// for the layers CGADSUB enables. The color window can clip the main
+// screen to black or keep math off in or outside of it.
+use crate::ppu::registers::PpuRegisters;
+
+// Where a composed pixel came from, which decides whether it takes part
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    // Background 0-3 (BG1-BG4)
+    Bg(usize),
+    // A sprite with OBJ palette 0-7
+    Obj(u8),
+    // No layer covered the pixel
+    Backdrop,
+}
+
+impl Source {
+    // Whether CGADSUB bits 0-5 enable math for this pixel. Sprites using
+    // palettes 0-3 never take part, whatever bit 4 says, so games can keep
+    // opaque sprites over a translucent layer.
+    pub fn takes_part(self, cgadsub: u8) -> bool {
+        match self {
+            Source::Bg(bg) => (cgadsub & (1 << bg)) != 0,
+            Source::Obj(palette) => palette >= 4 && (cgadsub & 0x10) != 0,
+            Source::Backdrop => (cgadsub & 0x20) != 0,
+        }
+    }
+}
+
+// Whether anything on this line can change the composed main screen, so
+// the sub screen needs composing at all
+pub fn is_active(registers: &PpuRegisters) -> bool {
+    (registers.cgadsub & 0x3F) != 0 || (registers.cgwsel & 0xC0) != 0
+}
+
+// One window's test at `x` from its two WOBJSEL bits (bit 0 inverts, bit 1
+// enables); None when the window is off
+fn window(select: u8, left: u8, right: u8, x: usize) -> Option<bool> {
+    let inside = (left as usize..=right as usize).contains(&x);
+    ((select & 0x02) != 0).then_some(inside != ((select & 0x01) != 0))
+}
+
+// The color window at `x`: windows 1 and 2 as WOBJSEL bits 4-7 select
+// them, combined by the WOBJLOG bits 2-3 logic when both are on
+pub fn in_color_window(registers: &PpuRegisters, x: usize) -> bool {
+    let select = registers.wobjsel >> 4;
+    let window1 = window(select & 0x03, registers.wh0, registers.wh1, x);
+    let window2 = window(select >> 2, registers.wh2, registers.wh3, x);
+    match (window1, window2) {
+        (Some(a), Some(b)) => match (registers.wobjlog >> 2) & 0x03 {
+            0 => a || b,
+            1 => a && b,
+            2 => a != b,
+            _ => a == b,
+        },
+        (Some(a), None) | (None, Some(a)) => a,
+        (None, None) => false,
+    }
+}
+
+// CGWSEL's two-bit region fields: never, outside the color window, inside
+// it, always
+fn in_region(mode: u8, in_window: bool) -> bool {
+    match mode & 0x03 {
+        0 => false,
+        1 => !in_window,
+        2 => in_window,
+        _ => true,
+    }
+}
+
+// One 5-bit channel of the result
+fn blend(main: u8, other: u8, subtract: bool, halve: bool) -> u8 {
+    let value = if subtract { main.saturating_sub(other) } else { main + other };
+    if halve { value >> 1 } else { value.min(31) }
+}
+
+// Apply color math to the composed `main` line (RGBA) whose pixels came
+// from `main_sources`. `sub` is the sub screen line with its sources,
+// read when CGWSEL bit 1 selects it; `fixed` is COLDATA's color as RGB.
+pub fn apply(
+    registers: &PpuRegisters,
+    main: &mut [u8],
+    main_sources: &[Source],
+    sub: &[u8],
+    sub_sources: &[Source],
+    fixed: [u8; 3],
+) {
+    let subtract = (registers.cgadsub & 0x80) != 0;
+    let halve = (registers.cgadsub & 0x40) != 0;
+    let use_sub = (registers.cgwsel & 0x02) != 0;
+    for (x, dst) in main.chunks_exact_mut(4).enumerate() {
+        let in_window = in_color_window(registers, x);
+        let black = in_region(registers.cgwsel >> 6, in_window);
+        if black {
+            dst[..3].fill(0);
+        }
+        if !main_sources[x].takes_part(registers.cgadsub) || in_region(registers.cgwsel >> 4, in_window) {
+            continue;
+        }
+        // A transparent sub screen pixel shows the fixed color, and isn't
+        // halved; nor is a main screen pixel clipped to black
+        let (other, halve) = if use_sub && sub_sources[x] != Source::Backdrop {
+            ([sub[x * 4], sub[x * 4 + 1], sub[x * 4 + 2]], halve && !black)
+        } else {
+            (fixed, halve && !black && !use_sub)
+        };
+        for (channel, other) in dst[..3].iter_mut().zip(other) {
+            *channel = blend(*channel >> 3, other >> 3, subtract, halve) << 3;
+        }
+        dst[3] = 255;
+    }
+}
@@ -1,21 +1,92 @@
-use crate::memory::Bus;
+use crate::debug::spans;
+use crate::memory::{Bus, MemoryInit};
 use crate::ppu::registers::PpuRegisters;
 use crate::ppu::renderer::Renderer;
-use crate::ppu::memory::{Vram, Cgram, Oam};
+use crate::ppu::memory::{Vram, VramAddress, Cgram, Oam, SpriteAttributes};
+use crate::ppu::ports::{self, PortAccess};
+use crate::ppu::priority;
+use crate::ppu::color_math::{self, Source};
+use crate::ppu::render_cache::TileCache;
 use crate::ppu::backgrounds::BackgroundRenderer;
 use crate::ppu::sprites::SpriteRenderer;
 use crate::ppu::scrolling::ScrollingEngine;
 use crate::ppu::mode7::Mode7Renderer;
+use crate::ppu::widescreen::WidescreenRenderer;
+use crate::{EmulatorError, Result};
 use log::trace;
+use serde::{Deserialize, Serialize};
 
-const SCREEN_WIDTH: usize = 256;
-const SCREEN_HEIGHT: usize = 224;
-const FRAMEBUFFER_SIZE: usize = SCREEN_WIDTH * SCREEN_HEIGHT * 4; // RGBA
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 224;
+// Picture height with SETINI overscan
+pub const OVERSCAN_HEIGHT: usize = 239;
+// Room for the taller picture; frames are the first 224 or 239 lines
+const FRAMEBUFFER_SIZE: usize = SCREEN_WIDTH * OVERSCAN_HEIGHT * 4; // RGBA
 
 // PPU timing constants
 const DOTS_PER_SCANLINE: u32 = 341;
 const SCANLINES_PER_FRAME: u16 = 262;
 const VBLANK_START_SCANLINE: u16 = 225;
+const OVERSCAN_VBLANK_START_SCANLINE: u16 = 240;
+
+// Dot at which the first visible pixel of a scanline is output
+const FIRST_VISIBLE_DOT: u32 = 22;
+
+// Dot at which H-Blank begins
+const HBLANK_START_DOT: u32 = 274;
+
+// Where the PPU is in the frame (see Ppu::timing)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PpuTiming {
+    pub scanline: u16,
+    pub dot: u32,
+    pub frame: u64,
+    // Interlace field, as in STAT78
+    pub field: bool,
+    pub vblank: bool,
+    // Outside the visible dots of the line, where CGRAM is free
+    pub hblank: bool,
+    // Line vblank starts on in this frame, 225 or 240 with overscan
+    pub vblank_start: u16,
+}
+
+// How the picture is produced. Scanline renders each line when it begins
+// and redraws the rest of it after mid-line writes; Dot outputs one pixel
+// per dot from the state at that dot, which is slower but exact for raster
+// effects that change registers several times within a line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum RenderMode {
+    #[default]
+    Scanline,
+    Dot,
+}
+
+// Renderable layers, used for debug layer toggles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Bg1,
+    Bg2,
+    Bg3,
+    Bg4,
+    Obj,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 5] = [Layer::Bg1, Layer::Bg2, Layer::Bg3, Layer::Bg4, Layer::Obj];
+    
+    // Bit matching the layer's position in TM/TS ($212C/$212D)
+    pub fn mask(self) -> u8 {
+        match self {
+            Layer::Bg1 => 0x01,
+            Layer::Bg2 => 0x02,
+            Layer::Bg3 => 0x04,
+            Layer::Bg4 => 0x08,
+            Layer::Obj => 0x10,
+        }
+    }
+}
 
 pub struct Ppu {
     // PPU state
@@ -31,10 +102,16 @@ pub struct Ppu {
     cgram: Cgram,
     oam: Oam,
     
+    // Decoded tiles, kept exact by routing every VRAM write through
+    // write_vram_byte
+    tile_cache: TileCache,
+    
     // Timing
     dot: u32,           // Current dot (0-340)
     scanline: u16,      // Current scanline (0-261)
     frame: u64,         // Frame counter
+    // SETINI overscan as seen at line 225, where vblank does or doesn't start
+    overscan: bool,
     
     // Frame buffer
     frame_buffer: Vec<u8>,
@@ -43,23 +120,54 @@ pub struct Ppu {
     nmi_pending: bool,
     irq_pending: bool,
     
-    // H/V counters for latching
-    h_counter: u16,
-    v_counter: u16,
-    latch_h: bool,
-    latch_v: bool,
-    
-    // VRAM write buffer (for 16-bit writes)
-    vram_latch: u8,
-    vram_first_write: bool,
-    
     // Temporary scanline buffer for compositing
     scanline_buffer: Vec<u8>,
+    // Where each main screen pixel came from, and the sub screen line
+    // color math reads, with its own sources
+    main_sources: Vec<Source>,
+    sub_buffer: Vec<u8>,
+    sub_sources: Vec<Source>,
+    // Mode 7 BG1 and EXTBG lines, composed into both screens
+    mode7_buffer: Vec<u8>,
+    extbg_buffer: Vec<u8>,
+    
+    // Columns past the picture's sides, when the enhancement is on
+    widescreen: Option<WidescreenRenderer>,
+    
+    // Debug layer toggles (same bit layout as TM); cleared bits are never composited
+    layer_mask: u8,
+    
+    // First pixel of the current line invalidated by a mid-line register
+    // write; the line is redrawn from there before the next write lands
+    redraw_x: Option<usize>,
+    
+    // Drop VRAM/OAM/CGRAM accesses while the PPU is drawing, as the
+    // hardware does (Quirks::ppu_access_restrictions)
+    access_restrictions: bool,
+    
+    // Last value read from a data port; restricted reads return it again
+    // since the PPU's read buffer isn't refilled while it draws
+    read_buffer: u8,
+    
+    // VRAM contents after reset
+    memory_init: MemoryInit,
+    
+    // CGRAM writes from the game are ignored (palette imported for editing)
+    palette_locked: bool,
+    
+    render_mode: RenderMode,
+    
+    // Dot mode: the state changed since scanline_buffer was composed
+    line_dirty: bool,
 }
 
 impl Ppu {
     pub fn new() -> Self {
-        Self {
+        Self::with_memory_init(MemoryInit::default())
+    }
+
+    pub fn with_memory_init(memory_init: MemoryInit) -> Self {
+        let mut ppu = Self {
             registers: PpuRegisters::new(),
             _renderer: Renderer::new(),
             bg_renderer: BackgroundRenderer::new(),
@@ -69,38 +177,68 @@ impl Ppu {
             vram: Vram::new(),
             cgram: Cgram::new(),
             oam: Oam::new(),
+            tile_cache: TileCache::new(),
             dot: 0,
             scanline: 0,
             frame: 0,
+            overscan: false,
             frame_buffer: vec![0; FRAMEBUFFER_SIZE],
             nmi_pending: false,
             irq_pending: false,
-            h_counter: 0,
-            v_counter: 0,
-            latch_h: false,
-            latch_v: false,
-            vram_latch: 0,
-            vram_first_write: true,
             scanline_buffer: vec![0; 256 * 4],
-        }
+            main_sources: vec![Source::Backdrop; 256],
+            sub_buffer: vec![0; 256 * 4],
+            sub_sources: vec![Source::Backdrop; 256],
+            mode7_buffer: vec![0; 256 * 4],
+            extbg_buffer: vec![0; 256 * 4],
+            widescreen: None,
+            layer_mask: 0x1F,
+            redraw_x: None,
+            access_restrictions: false,
+            read_buffer: 0,
+            memory_init,
+            palette_locked: false,
+            render_mode: RenderMode::Scanline,
+            line_dirty: true,
+        };
+        ppu.vram.init(memory_init);
+        ppu
+    }
+
+    pub fn memory_init(&self) -> MemoryInit {
+        self.memory_init
+    }
+
+    // Use on later resets without touching VRAM now
+    pub fn set_memory_init_for_reset(&mut self, memory_init: MemoryInit) {
+        self.memory_init = memory_init;
+    }
+
+    // Refill VRAM now and on every reset
+    pub fn set_memory_init(&mut self, memory_init: MemoryInit) {
+        self.memory_init = memory_init;
+        self.vram.init(memory_init);
+        self.tile_cache.invalidate_all();
     }
 
     pub fn reset(&mut self) {
         self.registers = PpuRegisters::new();
-        self.vram.reset();
+        self.vram.init(self.memory_init);
+        self.tile_cache.invalidate_all();
         self.cgram.reset();
         self.oam.reset();
         self.dot = 0;
         self.scanline = 0;
         self.frame = 0;
+        self.overscan = false;
         self.nmi_pending = false;
         self.irq_pending = false;
-        self.h_counter = 0;
-        self.v_counter = 0;
-        self.latch_h = false;
-        self.latch_v = false;
-        self.vram_latch = 0;
-        self.vram_first_write = true;
+        self.redraw_x = None;
+        self.line_dirty = true;
+        self.read_buffer = 0;
+        if let Some(widescreen) = &mut self.widescreen {
+            *widescreen = WidescreenRenderer::new(widescreen.margin());
+        }
         
         // Clear frame buffer to black
         for pixel in self.frame_buffer.chunks_mut(4) {
@@ -111,30 +249,54 @@ impl Ppu {
         }
     }
 
-    pub fn step(&mut self, bus: &mut Bus) {
-        self.dot += 1;
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    // Takes effect from the next line
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.flush_redraw();
+        self.render_mode = mode;
+        self.line_dirty = true;
+    }
 
-        // Update H/V counters
-        self.h_counter = self.dot as u16;
-        self.v_counter = self.scanline;
+    pub fn step(&mut self, _bus: &mut Bus) {
+        // The pixel of the dot ending now, so writes made during it count
+        if self.render_mode == RenderMode::Dot {
+            self.output_dot();
+        }
+        
+        self.dot += 1;
 
         // Check for H-Blank (dot 274)
-        if self.dot == 274 {
+        if self.dot == HBLANK_START_DOT {
             // H-Blank processing
         }
+        
+        if self.render_mode == RenderMode::Scanline && self.dot == FIRST_VISIBLE_DOT + SCREEN_WIDTH as u32 {
+            // Last visible pixel output; later writes only affect the next line
+            self.flush_redraw();
+        }
 
         // End of scanline
         if self.dot >= DOTS_PER_SCANLINE {
             self.dot = 0;
             self.scanline += 1;
+            if self.scanline == VBLANK_START_SCANLINE {
+                self.overscan = self.registers.is_overscan();
+            }
             
             // Check if we're in visible range
-            if self.scanline < VBLANK_START_SCANLINE {
-                self.render_scanline(bus);
+            if !self.is_in_vblank() {
+                match self.render_mode {
+                    RenderMode::Scanline => self.render_scanline(0),
+                    // Composed when its first pixel is due
+                    RenderMode::Dot => self.line_dirty = true,
+                }
             }
             
             // V-Blank start
-            if self.scanline == VBLANK_START_SCANLINE {
+            if self.scanline == self.vblank_start_scanline() {
                 self.enter_vblank();
             }
             
@@ -147,84 +309,277 @@ impl Ppu {
         }
     }
 
-    fn render_scanline(&mut self, _bus: &mut Bus) {
+    // Whether the current line is in the frame buffer: lines 224-238 only
+    // with overscan, which from line 225 on also kept vblank from starting
+    fn is_picture_line(&self) -> bool {
+        let y = self.scanline as usize;
+        !self.is_in_vblank() && (y < SCREEN_HEIGHT || (self.registers.is_overscan() && y < OVERSCAN_HEIGHT))
+    }
+
+    // Pixel of the current line being output at this dot (SCREEN_WIDTH once
+    // the visible part of the line is over)
+    fn current_x(&self) -> usize {
+        (self.dot.saturating_sub(FIRST_VISIBLE_DOT) as usize).min(SCREEN_WIDTH)
+    }
+    
+    // A write at the current dot changes the rest of the visible line. The
+    // line was drawn with the old state; pixels already output keep it and
+    // the remainder is redrawn once the state stops changing at this dot.
+    fn invalidate_line(&mut self) {
+        if self.render_mode == RenderMode::Dot {
+            self.line_dirty = true;
+            return;
+        }
+        let y = self.scanline as usize;
+        if y == 0 || !self.is_picture_line() {
+            return;
+        }
+        let x = self.current_x();
+        if x >= SCREEN_WIDTH {
+            return;
+        }
+        match self.redraw_x {
+            Some(start) if start < x => {
+                self.flush_redraw();
+                self.redraw_x = Some(x);
+            }
+            Some(_) => {}
+            None => self.redraw_x = Some(x),
+        }
+    }
+    
+    fn flush_redraw(&mut self) {
+        if let Some(start) = self.redraw_x.take() {
+            self.render_scanline(start);
+        }
+    }
+    
+    // Render the current line and output pixels from `start_x` onwards
+    fn render_scanline(&mut self, start_x: usize) {
         // Skip rendering if screen is blanked
         if self.registers.is_screen_blanked() {
             return;
         }
         
+        if !self.is_picture_line() {
+            return;
+        }
+        
+        self.compose_line();
+        self.output_pixels(start_x, SCREEN_WIDTH);
+    }
+    
+    // Dot mode: output the pixel due at this dot, composing the line again
+    // first if anything changed since the last pixel
+    fn output_dot(&mut self) {
         let y = self.scanline as usize;
-        if y >= SCREEN_HEIGHT {
+        if y == 0 || !self.is_picture_line() || self.dot < FIRST_VISIBLE_DOT {
+            return;
+        }
+        let x = (self.dot - FIRST_VISIBLE_DOT) as usize;
+        if x >= SCREEN_WIDTH || self.registers.is_screen_blanked() {
             return;
         }
+        if std::mem::take(&mut self.line_dirty) {
+            self.compose_line();
+        }
+        self.output_pixels(x, x + 1);
+    }
+    
+    // Composite the current line into scanline_buffer
+    fn compose_line(&mut self) {
+        let _span = spans::span("render_scanline", "ppu");
         
         // Check if we're in Mode 7
         let bg_mode = self.registers.get_bg_mode();
+        let main_screen = self.registers.get_main_screen_layers();
         
-        if bg_mode == 7 {
-            // Mode 7 rendering
-            self.mode7.render_scanline(
+        let layers = main_screen & self.layer_mask;
+        // The sub screen is only composed for color math to read
+        let math = color_math::is_active(&self.registers);
+        let sub_layers = if math { self.registers.get_sub_screen_layers() & self.layer_mask } else { 0 };
+        let (r, g, b) = self.cgram.color_to_rgb(self.cgram.read_color(0));
+        let backdrop = [r, g, b, 255];
+        let (r, g, b) = self.cgram.color_to_rgb(self.registers.fixed_color);
+        let fixed = [r, g, b];
+        
+        if ((layers | sub_layers) & 0x10) != 0 {
+            self.sprite_renderer.render_scanline(
                 &self.vram,
                 &self.cgram,
+                &self.oam,
                 &self.registers,
                 self.scanline,
-                &mut self.scanline_buffer,
+                self.field(),
             );
+        }
+        
+        if bg_mode == 7 {
+            // Mode 7 rendering
+            if self.is_layer_enabled(Layer::Bg1) {
+                self.mode7.render_scanline(
+                    &self.vram,
+                    &self.cgram,
+                    &self.registers,
+                    self.scanline,
+                    &mut self.mode7_buffer,
+                );
+            } else {
+                self.mode7_buffer.fill(0);
+            }
             
             // Check for Mode 7 EXTBG (BG2)
-            if self.mode7.is_extbg_enabled(&self.registers) {
-                let mut extbg_buffer = vec![0u8; SCREEN_WIDTH * 4];
+            if self.is_layer_enabled(Layer::Bg2) && self.mode7.is_extbg_enabled(&self.registers) {
                 self.mode7.render_extbg_scanline(
                     &self.vram,
                     &self.cgram,
                     &self.registers,
                     self.scanline,
-                    &mut extbg_buffer,
+                    &mut self.extbg_buffer,
+                );
+            } else {
+                self.extbg_buffer.fill(0);
+            }
+            
+            priority::compose_mode7(
+                layers,
+                &self.mode7_buffer,
+                &self.extbg_buffer,
+                &self.sprite_renderer,
+                backdrop,
+                &mut self.scanline_buffer,
+                &mut self.main_sources,
+            );
+            if math {
+                priority::compose_mode7(
+                    sub_layers,
+                    &self.mode7_buffer,
+                    &self.extbg_buffer,
+                    &self.sprite_renderer,
+                    [fixed[0], fixed[1], fixed[2], 255],
+                    &mut self.sub_buffer,
+                    &mut self.sub_sources,
                 );
-                
-                // Composite EXTBG onto main buffer
-                for x in 0..SCREEN_WIDTH {
-                    let offset = x * 4;
-                    if extbg_buffer[offset + 3] != 0 {
-                        self.scanline_buffer[offset] = extbg_buffer[offset];
-                        self.scanline_buffer[offset + 1] = extbg_buffer[offset + 1];
-                        self.scanline_buffer[offset + 2] = extbg_buffer[offset + 2];
-                        self.scanline_buffer[offset + 3] = extbg_buffer[offset + 3];
-                    }
-                }
             }
         } else {
-            // Normal background rendering
-            let bg_buffer = self.bg_renderer.render_scanline(
+            self.bg_renderer.render_scanline(
                 &self.vram,
                 &self.cgram,
                 &self.registers,
                 self.scanline,
+                self.field(),
             );
-            
-            // Copy background to scanline buffer
-            self.scanline_buffer.copy_from_slice(bg_buffer);
+            let order = priority::layer_order(self.registers.bgmode);
+            priority::compose(
+                order,
+                layers,
+                &self.bg_renderer,
+                &self.sprite_renderer,
+                backdrop,
+                &mut self.scanline_buffer,
+                &mut self.main_sources,
+            );
+            if math {
+                priority::compose(
+                    order,
+                    sub_layers,
+                    &self.bg_renderer,
+                    &self.sprite_renderer,
+                    [fixed[0], fixed[1], fixed[2], 255],
+                    &mut self.sub_buffer,
+                    &mut self.sub_sources,
+                );
+            }
         }
         
-        // Render sprites on top
-        let main_screen = self.registers.get_main_screen_layers();
-        if (main_screen & 0x10) != 0 { // Check if sprites are enabled on main screen
-            self.sprite_renderer.render_scanline(
-                &self.vram,
-                &self.cgram,
-                &self.oam,
+        if math {
+            color_math::apply(
                 &self.registers,
-                self.scanline,
                 &mut self.scanline_buffer,
+                &self.main_sources,
+                &self.sub_buffer,
+                &self.sub_sources,
+                fixed,
             );
         }
         
-        // Copy final scanline to frame buffer with brightness adjustment
-        let frame_offset = y * SCREEN_WIDTH * 4;
+        self.compose_widescreen(layers, backdrop);
+    }
+    
+    // Draw the widescreen columns either side of the current line: the
+    // main screen's backgrounds over the backdrop, without sprites,
+    // windows or color math
+    fn compose_widescreen(&mut self, layers: u8, backdrop: [u8; 4]) {
+        let field = self.field();
+        let Some(widescreen) = self.widescreen.as_mut() else {
+            return;
+        };
+        let factor = self.registers.get_brightness() as f32 / 15.0;
+        for first_x in widescreen.sides() {
+            let origin = (first_x, self.scanline);
+            if self.registers.get_bg_mode() == 7 {
+                if (layers & 0x01) != 0 {
+                    self.mode7.render_columns(
+                        &self.vram,
+                        &self.cgram,
+                        &self.registers,
+                        origin,
+                        &mut widescreen.mode7_buffer,
+                    );
+                } else {
+                    widescreen.mode7_buffer.fill(0);
+                }
+                if (layers & 0x02) != 0 && self.mode7.is_extbg_enabled(&self.registers) {
+                    self.mode7.render_extbg_columns(
+                        &self.vram,
+                        &self.cgram,
+                        &self.registers,
+                        origin,
+                        &mut widescreen.extbg_buffer,
+                    );
+                } else {
+                    widescreen.extbg_buffer.fill(0);
+                }
+                priority::compose_mode7(
+                    layers & 0x03,
+                    &widescreen.mode7_buffer,
+                    &widescreen.extbg_buffer,
+                    &self.sprite_renderer,
+                    backdrop,
+                    &mut widescreen.line,
+                    &mut widescreen.sources,
+                );
+            } else {
+                widescreen.backgrounds.render_columns(
+                    &self.vram,
+                    &self.cgram,
+                    &self.registers,
+                    self.scanline,
+                    field,
+                    first_x,
+                );
+                priority::compose(
+                    priority::layer_order(self.registers.bgmode),
+                    layers & 0x0F,
+                    &widescreen.backgrounds,
+                    &self.sprite_renderer,
+                    backdrop,
+                    &mut widescreen.line,
+                    &mut widescreen.sources,
+                );
+            }
+            widescreen.output_side(self.scanline as usize, first_x, factor);
+        }
+    }
+    
+    // Copy pixels `start_x..end_x` of the composed line to the frame buffer
+    // with brightness adjustment
+    fn output_pixels(&mut self, start_x: usize, end_x: usize) {
+        let frame_offset = self.scanline as usize * SCREEN_WIDTH * 4;
         let brightness = self.registers.get_brightness();
         let factor = brightness as f32 / 15.0;
         
-        for x in 0..SCREEN_WIDTH {
+        for x in start_x..end_x {
             let src_offset = x * 4;
             let dst_offset = frame_offset + src_offset;
             
@@ -233,9 +588,6 @@ impl Ppu {
             self.frame_buffer[dst_offset + 2] = (self.scanline_buffer[src_offset + 2] as f32 * factor) as u8;
             self.frame_buffer[dst_offset + 3] = self.scanline_buffer[src_offset + 3];
         }
-        
-        // TODO: Implement proper layer priority compositing
-        // TODO: Implement sub-screen and color math
     }
 
     fn enter_vblank(&mut self) {
@@ -244,6 +596,7 @@ impl Ppu {
         // Set V-Blank flag and trigger NMI if enabled
         if !self.registers.is_screen_blanked() {
             self.nmi_pending = true;
+            self.registers.reload_oam_address();
         }
     }
 
@@ -252,8 +605,62 @@ impl Ppu {
         // V-Blank period is over
     }
 
+    // Force a layer in or out of compositing, independent of TM/TS
+    pub fn set_layer_enabled(&mut self, layer: Layer, enabled: bool) {
+        if enabled {
+            self.layer_mask |= layer.mask();
+        } else {
+            self.layer_mask &= !layer.mask();
+        }
+    }
+    
+    pub fn is_layer_enabled(&self, layer: Layer) -> bool {
+        (self.layer_mask & layer.mask()) != 0
+    }
+
+    // The picture: SCREEN_WIDTH by frame_height() pixels
     pub fn get_frame_buffer(&self) -> &[u8] {
-        &self.frame_buffer
+        &self.frame_buffer[..SCREEN_WIDTH * self.frame_height() * 4]
+    }
+
+    // Columns drawn past each side of the picture (Enhancements::widescreen);
+    // 0 turns the enhancement off. Takes effect from the next line.
+    pub fn set_widescreen(&mut self, margin: usize) {
+        if margin == self.widescreen_margin() {
+            return;
+        }
+        self.widescreen = (margin > 0).then(|| WidescreenRenderer::new(margin));
+    }
+    
+    pub fn widescreen_margin(&self) -> usize {
+        self.widescreen.as_ref().map_or(0, |widescreen| widescreen.margin())
+    }
+    
+    // Width of get_widescreen_frame_buffer's picture
+    pub fn widescreen_width(&self) -> usize {
+        self.widescreen.as_ref().map_or(SCREEN_WIDTH, |widescreen| widescreen.width())
+    }
+    
+    // The picture with the widescreen columns on both sides, or the
+    // regular one while the enhancement is off
+    pub fn get_widescreen_frame_buffer(&mut self) -> &[u8] {
+        let height = self.frame_height();
+        match self.widescreen.as_mut() {
+            Some(widescreen) => widescreen.frame_buffer(&self.frame_buffer, height),
+            None => &self.frame_buffer[..SCREEN_WIDTH * height * 4],
+        }
+    }
+
+    // 239 lines for frames drawn with SETINI overscan, 224 otherwise. Set
+    // when a frame reaches line 225, so it describes the last finished
+    // frame until then.
+    pub fn frame_height(&self) -> usize {
+        if self.overscan { OVERSCAN_HEIGHT } else { SCREEN_HEIGHT }
+    }
+
+    // First vblank line: 240 with overscan instead of 225
+    pub fn vblank_start_scanline(&self) -> u16 {
+        if self.overscan { OVERSCAN_VBLANK_START_SCANLINE } else { VBLANK_START_SCANLINE }
     }
 
     pub fn nmi_pending(&mut self) -> bool {
@@ -275,101 +682,108 @@ impl Ppu {
     }
 
     // PPU register access
+    pub fn set_access_restrictions(&mut self, enabled: bool) {
+        self.access_restrictions = enabled;
+    }
+    
+    pub fn access_restrictions(&self) -> bool {
+        self.access_restrictions
+    }
+
+    // Bilinear filtering of the Mode 7 plane, an enhancement
+    pub fn set_mode7_bilinear(&mut self, enabled: bool) {
+        self.mode7.bilinear = enabled;
+        self.invalidate_line();
+    }
+    
+    // Whether the PPU is drawing and owns VRAM/OAM, or CGRAM when `cgram`
+    // (which the CPU may also use during H-Blank)
+    pub fn memory_busy(&self, cgram: bool) -> bool {
+        if self.registers.is_screen_blanked() || self.is_in_vblank() {
+            return false;
+        }
+        !(cgram && self.is_in_hblank())
+    }
+    
+    // memory_busy, only enforced with access restrictions enabled
+    fn memory_locked(&self, cgram: bool) -> bool {
+        self.access_restrictions && self.memory_busy(cgram)
+    }
+    
+    // Result of a data port read; the address advances either way
+    fn buffered_read(&mut self, locked: bool, value: u8) -> u8 {
+        if !locked {
+            self.read_buffer = value;
+        }
+        self.read_buffer
+    }
+    
     pub fn read_register(&mut self, address: u16) -> u8 {
         match address {
-            // VRAM data read
-            0x2139 => {
+            // VRAM data read, low and high byte of the word at VMADD; VMAIN
+            // bit 7 picks which of the two advances the address
+            0x2139 | 0x213A => {
+                let locked = self.memory_locked(false);
                 let vram_addr = self.registers.get_vram_address();
-                let value = if self.vram_first_write {
-                    // Read low byte
-                    self.vram.read(vram_addr)
+                let high = address == 0x213A;
+                let value = if high {
+                    self.vram.read_high(vram_addr)
                 } else {
-                    // Read high byte
-                    self.vram.read(vram_addr.wrapping_add(1))
+                    self.vram.read_low(vram_addr)
                 };
                 
-                // Toggle first write flag
-                self.vram_first_write = !self.vram_first_write;
-                
-                // Auto-increment based on VMAIN setting
-                if (!self.vram_first_write && (self.registers.vmain & 0x80) == 0) ||
-                   (self.vram_first_write && (self.registers.vmain & 0x80) != 0) {
+                if high == ((self.registers.vmain & 0x80) != 0) {
                     self.auto_increment_vram();
                 }
                 
-                value
+                self.buffered_read(locked, value)
             }
             
             // CGRAM data read
             0x213B => {
                 let value = self.cgram.read(self.registers.cgadd);
                 self.registers.cgadd = self.registers.cgadd.wrapping_add(1);
-                value
+                self.buffered_read(self.memory_locked(true), value)
             }
             
             // OAM data read
             0x2138 => {
-                let address = self.registers.get_oam_address();
-                let value = self.oam.read(address);
-                
-                // Auto-increment OAM address
-                let new_address = (address + 1) & 0x3FF;
-                self.registers.oamaddl = (new_address & 0xFF) as u8;
-                self.registers.oamaddh = ((new_address >> 8) & 0x01) as u8 | (self.registers.oamaddh & 0x80);
-                
-                value
-            }
-            
-            // Counter latch register
-            0x2137 => {
-                // Latch H/V counters on read
-                self.latch_h = true;
-                self.latch_v = true;
-                self.registers.read(address)
-            }
-            
-            // H counter data
-            0x213C => {
-                if self.latch_h {
-                    self.latch_h = false;
-                    (self.h_counter & 0xFF) as u8
-                } else {
-                    0
-                }
-            }
-            0x213D => {
-                if self.latch_h {
-                    self.latch_h = false;
-                    ((self.h_counter >> 8) & 0x01) as u8
-                } else {
-                    0
-                }
-            }
-            
-            // V counter data
-            0x213E => {
-                if self.latch_v {
-                    self.latch_v = false;
-                    (self.v_counter & 0xFF) as u8
-                } else {
-                    0
-                }
-            }
-            0x213F => {
-                if self.latch_v {
-                    self.latch_v = false;
-                    ((self.v_counter >> 8) & 0x01) as u8
-                } else {
-                    0
-                }
+                let value = self.oam.read(self.registers.get_oam_address());
+                self.registers.increment_oam_address();
+                self.buffered_read(self.memory_locked(false), value)
             }
             
-            // Default register read
+            // Write-only ports read open bus
+            _ if ports::port(address).access == PortAccess::Write => 0,
             _ => self.registers.read(address),
         }
     }
 
+    // What read_register would return, without advancing addresses or
+    // filling the read buffer, for debuggers
+    pub fn peek_register(&self, address: u16) -> u8 {
+        let (locked, value) = match address {
+            0x2139 => (self.memory_locked(false), self.vram.read_low(self.registers.get_vram_address())),
+            0x213A => (self.memory_locked(false), self.vram.read_high(self.registers.get_vram_address())),
+            0x213B => (self.memory_locked(true), self.cgram.read(self.registers.cgadd)),
+            0x2138 => (self.memory_locked(false), self.oam.read(self.registers.get_oam_address())),
+            _ if ports::port(address).access == PortAccess::Write => return 0,
+            _ => return self.registers.read(address),
+        };
+        if locked {
+            self.read_buffer
+        } else {
+            value
+        }
+    }
+
     pub fn write_register(&mut self, address: u16, value: u8) {
+        // Address registers don't change the picture; everything else takes
+        // effect from the current dot
+        if !matches!(address, 0x2102 | 0x2103 | 0x2115..=0x2117 | 0x2121 | 0x2134..) {
+            self.invalidate_line();
+        }
+        
         self.registers.write(address, value);
         
         // Forward to scrolling engine for scroll/window registers
@@ -400,46 +814,40 @@ impl Ppu {
         }
     }
 
+    // VMDATAL and VMDATAH each store their half of the word at VMADD
+    // straight away; VMAIN bit 7 picks which of the two advances the address
     fn write_vram_low(&mut self, value: u8) {
         let address = self.registers.get_vram_address();
         
-        if self.vram_first_write {
-            // First write - store in latch
-            self.vram_latch = value;
-            self.vram_first_write = false;
-        } else {
-            // Second write - write both bytes
-            self.vram.write16(address, (value as u16) << 8 | self.vram_latch as u16);
-            self.vram_first_write = true;
-            
-            // Auto-increment based on VMAIN setting
-            if (self.registers.vmain & 0x80) == 0 {
-                self.auto_increment_vram();
-            }
+        if !self.memory_locked(false) {
+            self.write_vram_byte(address.byte_address(), value);
+        }
+        if (self.registers.vmain & 0x80) == 0 {
+            self.auto_increment_vram();
         }
         
-        trace!("VRAM write low: ${:04X} = ${:02X}", address, value);
+        trace!("VRAM write low: ${:04X} = ${:02X}", address.word(), value);
     }
 
     fn write_vram_high(&mut self, value: u8) {
         let address = self.registers.get_vram_address();
         
-        if self.vram_first_write {
-            // First write - store in latch
-            self.vram_latch = value;
-            self.vram_first_write = false;
-        } else {
-            // Second write - write both bytes
-            self.vram.write16(address, (self.vram_latch as u16) << 8 | value as u16);
-            self.vram_first_write = true;
-            
-            // Auto-increment based on VMAIN setting
-            if (self.registers.vmain & 0x80) != 0 {
-                self.auto_increment_vram();
-            }
+        if !self.memory_locked(false) {
+            self.write_vram_byte(address.byte_address() + 1, value);
+        }
+        if (self.registers.vmain & 0x80) != 0 {
+            self.auto_increment_vram();
         }
         
-        trace!("VRAM write high: ${:04X} = ${:02X}", address, value);
+        trace!("VRAM write high: ${:04X} = ${:02X}", address.word(), value);
+    }
+
+    // Where VRAM changes after power-on: the $2118/$2119 ports (written by
+    // the CPU or by DMA over the B-bus), save states and debugger edits.
+    // `offset` is into the byte view, low byte of each word first.
+    fn write_vram_byte(&mut self, offset: u16, value: u8) {
+        self.vram.write(offset, value);
+        self.tile_cache.invalidate_address(VramAddress::new(offset >> 1));
     }
 
     fn auto_increment_vram(&mut self) {
@@ -451,12 +859,14 @@ impl Ppu {
             _ => 1,
         };
         
-        let new_address = (self.registers.get_vram_address() + increment) & 0x7FFF;
+        let new_address = self.registers.get_vram_address().offset(increment);
         self.registers.set_vram_address(new_address);
     }
 
     fn write_cgram(&mut self, value: u8) {
-        self.cgram.write(self.registers.cgadd, value);
+        if !self.memory_locked(true) && !self.palette_locked {
+            self.cgram.write(self.registers.cgadd, value);
+        }
         trace!("CGRAM write: ${:02X} = ${:02X}", self.registers.cgadd, value);
         
         // Auto-increment CGRAM address
@@ -465,13 +875,11 @@ impl Ppu {
 
     fn write_oam(&mut self, value: u8) {
         let address = self.registers.get_oam_address();
-        self.oam.write(address, value);
+        if !self.memory_locked(false) {
+            self.oam.write(address, value);
+        }
         trace!("OAM write: ${:04X} = ${:02X}", address, value);
-        
-        // Auto-increment OAM address
-        let new_address = (address + 1) & 0x3FF;
-        self.registers.oamaddl = (new_address & 0xFF) as u8;
-        self.registers.oamaddh = ((new_address >> 8) & 0x01) as u8 | (self.registers.oamaddh & 0x80);
+        self.registers.increment_oam_address();
     }
 
     pub fn get_current_scanline(&self) -> u16 {
@@ -483,16 +891,75 @@ impl Ppu {
     }
 
     pub fn is_in_vblank(&self) -> bool {
-        self.scanline >= VBLANK_START_SCANLINE
+        self.scanline >= self.vblank_start_scanline()
     }
 
+    pub fn is_in_hblank(&self) -> bool {
+        self.dot >= HBLANK_START_DOT || self.dot < FIRST_VISIBLE_DOT
+    }
+
+    pub fn timing(&self) -> PpuTiming {
+        PpuTiming {
+            scanline: self.scanline,
+            dot: self.dot,
+            frame: self.frame,
+            field: self.field(),
+            vblank: self.is_in_vblank(),
+            hblank: self.is_in_hblank(),
+            vblank_start: self.vblank_start_scanline(),
+        }
+    }
+
+    // Jump to a point in the current frame, for tests and the debugger to
+    // set up mid-frame scenarios directly. Nothing between the old and new
+    // position happens: lines skipped aren't drawn and entering vblank this
+    // way raises no NMI. The line landed on is redrawn from the new dot.
+    pub fn set_position(&mut self, scanline: u16, dot: u32) -> Result<()> {
+        if scanline >= SCANLINES_PER_FRAME || dot >= DOTS_PER_SCANLINE {
+            return Err(EmulatorError::ppu(format!(
+                "PPU position {}:{} is outside the frame ({} lines of {} dots)",
+                scanline, dot, SCANLINES_PER_FRAME, DOTS_PER_SCANLINE
+            )));
+        }
+        self.redraw_x = None;
+        self.scanline = scanline;
+        self.dot = dot;
+        if scanline >= VBLANK_START_SCANLINE {
+            self.overscan = self.registers.is_overscan();
+        }
+        match self.render_mode {
+            // Line 0 isn't drawn, as in step
+            RenderMode::Scanline => {
+                if scanline > 0 && self.current_x() < SCREEN_WIDTH {
+                    self.render_scanline(self.current_x());
+                }
+            }
+            RenderMode::Dot => self.line_dirty = true,
+        }
+        Ok(())
+    }
+
+    // Interlace field being drawn: false on even frames, true on odd ones.
+    // Read back in STAT78 ($213F) bit 7.
+    pub fn field(&self) -> bool {
+        (self.frame & 1) != 0
+    }
+    
     pub fn get_frame_count(&self) -> u64 {
         self.frame
     }
     
     // Complete PPU save state implementation
     pub fn save_state(&self) -> crate::savestate::PpuState {
-        use crate::savestate::PpuState;
+        use crate::savestate::{PpuLatchState, PpuState};
+        
+        // The H/V counter latch lives on the bus and is filled in by the emulator
+        let mut latches = PpuLatchState {
+            ..PpuLatchState::default()
+        };
+        self.registers.save_latches(&mut latches);
+        self.scrolling.save_latches(&mut latches);
+        self.mode7.save_latches(&mut latches);
         
         PpuState {
             registers: self.get_registers_as_bytes(),
@@ -504,8 +971,10 @@ impl Ppu {
             frame_count: self.frame,
             vblank: self.is_in_vblank(),
             hblank: false, // TODO: Track H-blank state
-            nmi_flag: self.nmi_pending,
+            // RDNMI's flag is kept by the bus; Emulator::save_state fills it in
+            nmi_flag: false,
             irq_flag: self.irq_pending,
+            latches,
         }
     }
     
@@ -516,7 +985,7 @@ impl Ppu {
         // Load memory (this overwrites the internal data)
         if state.vram.len() == 0x10000 {
             for (i, &byte) in state.vram.iter().enumerate() {
-                self.vram.write(i as u16, byte);
+                self.write_vram_byte(i as u16, byte);
             }
         }
         
@@ -540,10 +1009,16 @@ impl Ppu {
         
         // Load timing state
         self.scanline = state.current_scanline;
+        self.overscan = self.registers.is_overscan();
         self.dot = state.current_cycle as u32;
         self.frame = state.frame_count;
-        self.nmi_pending = state.nmi_flag;
         self.irq_pending = state.irq_flag;
+        
+        // Latches last, after the register bytes above
+        self.registers.load_latches(&state.latches);
+        self.scrolling.load_latches(&state.latches);
+        self.mode7.load_latches(&state.latches);
+        self.line_dirty = true;
     }
     
     fn get_registers_as_bytes(&self) -> Vec<u8> {
@@ -567,6 +1042,17 @@ impl Ppu {
         registers[0x16] = self.registers.vmaddl;
         registers[0x17] = self.registers.vmaddh;
         registers[0x22] = self.registers.cgadd;
+        registers[0x25] = self.registers.wobjsel;
+        registers[0x26] = self.registers.wh0;
+        registers[0x27] = self.registers.wh1;
+        registers[0x28] = self.registers.wh2;
+        registers[0x29] = self.registers.wh3;
+        registers[0x2B] = self.registers.wobjlog;
+        registers[0x2C] = self.registers.tm;
+        registers[0x2D] = self.registers.ts;
+        registers[0x30] = self.registers.cgwsel;
+        registers[0x31] = self.registers.cgadsub;
+        registers[0x33] = self.registers.setini;
         
         registers
     }
@@ -589,10 +1075,21 @@ impl Ppu {
             self.registers.vmaddl = registers[0x16];
             self.registers.vmaddh = registers[0x17];
             self.registers.cgadd = registers[0x22];
+            self.registers.wobjsel = registers[0x25];
+            self.registers.wh0 = registers[0x26];
+            self.registers.wh1 = registers[0x27];
+            self.registers.wh2 = registers[0x28];
+            self.registers.wh3 = registers[0x29];
+            self.registers.wobjlog = registers[0x2B];
+            self.registers.tm = registers[0x2C];
+            self.registers.ts = registers[0x2D];
+            self.registers.cgwsel = registers[0x30];
+            self.registers.cgadsub = registers[0x31];
+            self.registers.setini = registers[0x33];
         }
     }
     
-    fn get_complete_oam_data(&self) -> Vec<u8> {
+    pub(crate) fn get_complete_oam_data(&self) -> Vec<u8> {
         // Return both low table (512 bytes) and high table (32 bytes)
         let mut oam_data = Vec::with_capacity(544);
         
@@ -614,6 +1111,21 @@ impl Ppu {
         self.vram.get_data()
     }
     
+    pub fn tile_cache(&self) -> &TileCache {
+        &self.tile_cache
+    }
+    
+    // Tile `tile_index` of the `bpp` (2, 4 or 8) tile data at `base_addr`,
+    // decoded again only when VRAM under it changed
+    pub fn cached_tile(&mut self, tile_index: usize, base_addr: u16, bpp: u8) -> &[u8; 64] {
+        match bpp {
+            2 => self.tile_cache.decode_2bpp_tile(&self.vram, tile_index, base_addr),
+            4 => self.tile_cache.decode_4bpp_tile(&self.vram, tile_index, base_addr),
+            _ => self.tile_cache.decode_8bpp_tile(&self.vram, tile_index, base_addr),
+        }
+        self.tile_cache.get_tile(tile_index)
+    }
+    
     pub fn get_cgram(&self) -> &[u8] {
         self.cgram.get_data()
     }
@@ -621,4 +1133,38 @@ impl Ppu {
     pub fn get_oam(&self) -> &[u8] {
         self.oam.get_data()
     }
+    
+    // Sprite `index` (0-127) as decoded from both OAM tables
+    pub fn sprite(&self, index: u8) -> SpriteAttributes {
+        self.oam.get_sprite(index)
+    }
+    
+    pub fn is_palette_locked(&self) -> bool {
+        self.palette_locked
+    }
+    
+    // Ignore the game's CGRAM writes while locked; set_cgram still works
+    pub fn set_palette_locked(&mut self, locked: bool) {
+        self.palette_locked = locked;
+    }
+    
+    // Overwrite from the start of each memory, e.g. from a debugger dump
+    pub fn set_vram(&mut self, data: &[u8]) {
+        for (i, &byte) in data.iter().take(0x10000).enumerate() {
+            self.write_vram_byte(i as u16, byte);
+        }
+    }
+    
+    pub fn set_cgram(&mut self, data: &[u8]) {
+        // Byte addresses only reach the first half; write whole colors
+        for (index, color) in data.chunks_exact(2).take(256).enumerate() {
+            self.cgram.write_color(index as u8, u16::from_le_bytes([color[0], color[1]]));
+        }
+    }
+    
+    pub fn set_oam(&mut self, data: &[u8]) {
+        for (i, &byte) in data.iter().take(544).enumerate() {
+            self.oam.write(i as u16, byte);
+        }
+    }
 }
\ No newline at end of file
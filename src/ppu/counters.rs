@@ -0,0 +1,123 @@
+// H/V counter latch ($2137, $213C/$213D, $213F). The counters are latched
+// by reading $2137 or by the I/O port pin (WRIO bit 7) going low, and read
+// back one byte at a time through a per-counter flip-flop.
+use crate::savestate::CounterLatchState;
+use std::cell::Cell;
+
+// PPU2 version reported in STAT78 bits 0-3
+const PPU2_VERSION: u8 = 3;
+// STAT78 bit 6: counters latched since the last STAT78 read
+const STAT78_LATCHED: u8 = 0x40;
+// STAT78 bit 7: interlace field (odd frames)
+const STAT78_FIELD: u8 = 0x80;
+
+#[derive(Debug, Default)]
+pub struct CounterLatch {
+    h: Cell<u16>,
+    v: Cell<u16>,
+    latched: Cell<bool>,
+    // Next OPHCT/OPVCT read returns the high byte
+    h_high: Cell<bool>,
+    v_high: Cell<bool>,
+    // Field the PPU is drawing, set by the scheduler with the beam position
+    field: Cell<bool>,
+}
+
+impl CounterLatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&self) {
+        self.h.set(0);
+        self.v.set(0);
+        self.latched.set(false);
+        self.h_high.set(false);
+        self.v_high.set(false);
+        self.field.set(false);
+    }
+
+    pub fn set_field(&self, field: bool) {
+        self.field.set(field);
+    }
+
+    pub fn save_state(&self) -> CounterLatchState {
+        CounterLatchState {
+            h: self.h.get(),
+            v: self.v.get(),
+            latched: self.latched.get(),
+            h_high: self.h_high.get(),
+            v_high: self.v_high.get(),
+        }
+    }
+
+    pub fn load_state(&self, state: &CounterLatchState) {
+        self.h.set(state.h & 0x1FF);
+        self.v.set(state.v & 0x1FF);
+        self.latched.set(state.latched);
+        self.h_high.set(state.h_high);
+        self.v_high.set(state.v_high);
+    }
+
+    pub fn latch(&self, h: u16, v: u16) {
+        self.h.set(h & 0x1FF);
+        self.v.set(v & 0x1FF);
+        self.latched.set(true);
+    }
+
+    // Latched (H, V) position
+    pub fn position(&self) -> (u16, u16) {
+        (self.h.get(), self.v.get())
+    }
+
+    pub fn is_latched(&self) -> bool {
+        self.latched.get()
+    }
+
+    // $213C OPHCT
+    pub fn read_h(&self) -> u8 {
+        read_counter(self.h.get(), &self.h_high)
+    }
+
+    // $213D OPVCT
+    pub fn read_v(&self) -> u8 {
+        read_counter(self.v.get(), &self.v_high)
+    }
+
+    // $213F STAT78; reading resets both flip-flops and the latch flag
+    pub fn read_status(&self) -> u8 {
+        let flag = if self.latched.replace(false) { STAT78_LATCHED } else { 0 };
+        let field = if self.field.get() { STAT78_FIELD } else { 0 };
+        self.h_high.set(false);
+        self.v_high.set(false);
+        field | flag | PPU2_VERSION
+    }
+
+    // The same three reads without flipping or resetting anything, for
+    // debuggers
+    pub fn peek_h(&self) -> u8 {
+        counter_byte(self.h.get(), self.h_high.get())
+    }
+
+    pub fn peek_v(&self) -> u8 {
+        counter_byte(self.v.get(), self.v_high.get())
+    }
+
+    pub fn peek_status(&self) -> u8 {
+        let flag = if self.latched.get() { STAT78_LATCHED } else { 0 };
+        let field = if self.field.get() { STAT78_FIELD } else { 0 };
+        field | flag | PPU2_VERSION
+    }
+}
+
+fn read_counter(value: u16, high: &Cell<bool>) -> u8 {
+    counter_byte(value, high.replace(!high.get()))
+}
+
+fn counter_byte(value: u16, high: bool) -> u8 {
+    if high {
+        (value >> 8) as u8 & 0x01
+    } else {
+        value as u8
+    }
+}
@@ -0,0 +1,49 @@
+// Frame blending for effects made for a CRT's persistence: sprites
+// flickered at 30Hz to look translucent, or shadows drawn every other
+// frame. Each output frame is the average of the newest frame and the one
+// before it. Runs on converted RGBA8888 frames, before overlays.
+#[derive(Debug, Clone, Default)]
+pub struct FrameBlender {
+    enabled: bool,
+    // Last unblended frame
+    previous: Vec<u8>,
+}
+
+impl FrameBlender {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, previous: Vec::new() }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if enabled != self.enabled {
+            self.enabled = enabled;
+            self.previous.clear();
+        }
+    }
+
+    // Forget the previous frame, e.g. after a state load, so the picture
+    // from before the jump doesn't ghost into the next frame
+    pub fn reset(&mut self) {
+        self.previous.clear();
+    }
+
+    // Blend `frame` with the last one in place, keeping an unblended copy
+    pub fn apply(&mut self, frame: &mut [u8]) {
+        if !self.enabled {
+            return;
+        }
+        if self.previous.len() != frame.len() {
+            self.previous = frame.to_vec();
+            return;
+        }
+        for (pixel, previous) in frame.iter_mut().zip(self.previous.iter_mut()) {
+            let current = *pixel;
+            *pixel = (current as u16 + *previous as u16).div_ceil(2) as u8;
+            *previous = current;
+        }
+    }
+}
@@ -1,7 +1,37 @@
 // SNES PPU Memory Components
+use crate::memory::init::{MemoryInit, VRAM_STREAM};
 
-// VRAM - Video RAM (64KB)
-// Used for tiles, tilemaps, and Mode 7 data
+// Word address into VRAM, the unit VMADD and every VRAM base register
+// count in. VRAM is 32K 16-bit words, so only 15 bits are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct VramAddress(u16);
+
+impl VramAddress {
+    pub const WORDS: usize = 0x8000;
+
+    pub const fn new(word: u16) -> Self {
+        Self(word & 0x7FFF)
+    }
+
+    pub const fn word(self) -> u16 {
+        self.0
+    }
+
+    // `words` further on, wrapping at the end of VRAM
+    pub const fn offset(self, words: u16) -> Self {
+        Self::new(self.0.wrapping_add(words))
+    }
+
+    // Offset of the word's low byte in the 64KB byte view
+    pub const fn byte_address(self) -> u16 {
+        self.0 << 1
+    }
+}
+
+// VRAM - Video RAM (32K words)
+// Used for tiles, tilemaps, and Mode 7 data. Each word is stored low byte
+// first, so the byte view (get_data, save states) matches hardware dumps
+// whatever the host's endianness.
 pub struct Vram {
     data: Vec<u8>,
 }
@@ -9,7 +39,7 @@ pub struct Vram {
 impl Vram {
     pub fn new() -> Self {
         Self {
-            data: vec![0; 0x10000], // 64KB
+            data: vec![0; VramAddress::WORDS * 2],
         }
     }
     
@@ -17,23 +47,45 @@ impl Vram {
         self.data.fill(0);
     }
     
-    pub fn read(&self, address: u16) -> u8 {
-        self.data[address as usize]
+    // Power-on contents
+    pub fn init(&mut self, init: MemoryInit) {
+        init.fill(&mut self.data, VRAM_STREAM);
     }
     
-    pub fn write(&mut self, address: u16, value: u8) {
-        self.data[address as usize] = value;
+    pub fn read_word(&self, address: VramAddress) -> u16 {
+        u16::from_le_bytes([self.read_low(address), self.read_high(address)])
     }
     
-    pub fn read16(&self, address: u16) -> u16 {
-        let low = self.read(address) as u16;
-        let high = self.read(address.wrapping_add(1)) as u16;
-        (high << 8) | low
+    pub fn write_word(&mut self, address: VramAddress, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        self.write_low(address, low);
+        self.write_high(address, high);
+    }
+    
+    // Halves of a word, as VMDATAL/VMDATAH and RDVRAML/RDVRAMH see them
+    pub fn read_low(&self, address: VramAddress) -> u8 {
+        self.data[address.byte_address() as usize]
+    }
+    
+    pub fn read_high(&self, address: VramAddress) -> u8 {
+        self.data[address.byte_address() as usize + 1]
+    }
+    
+    pub fn write_low(&mut self, address: VramAddress, value: u8) {
+        self.data[address.byte_address() as usize] = value;
+    }
+    
+    pub fn write_high(&mut self, address: VramAddress, value: u8) {
+        self.data[address.byte_address() as usize + 1] = value;
+    }
+    
+    // Byte view, for dumps and save states
+    pub fn read(&self, offset: u16) -> u8 {
+        self.data[offset as usize]
     }
     
-    pub fn write16(&mut self, address: u16, value: u16) {
-        self.write(address, (value & 0xFF) as u8);
-        self.write(address.wrapping_add(1), (value >> 8) as u8);
+    pub fn write(&mut self, offset: u16, value: u8) {
+        self.data[offset as usize] = value;
     }
     
     pub fn get_data(&self) -> &[u8] {
@@ -126,11 +178,13 @@ impl Oam {
         self.high_table.fill(0);
     }
     
+    // Byte addresses $000-$3FF; the 32-byte high table repeats through
+    // $200-$3FF
     pub fn read(&self, address: u16) -> u8 {
         if address < 512 {
             self.low_table[address as usize]
         } else {
-            self.high_table[(address - 512) as usize]
+            self.high_table[(address & 0x1F) as usize]
         }
     }
     
@@ -138,7 +192,7 @@ impl Oam {
         if address < 512 {
             self.low_table[address as usize] = value;
         } else {
-            self.high_table[(address - 512) as usize] = value;
+            self.high_table[(address & 0x1F) as usize] = value;
         }
     }
     
@@ -7,5 +7,13 @@ pub mod memory;
 pub mod scrolling;
 pub mod mode7;
 pub mod render_cache;
+pub mod priority;
+pub mod color_math;
+pub mod pixel;
+pub mod counters;
+pub mod frame_blend;
+pub mod widescreen;
+pub mod ports;
 
-pub use core::Ppu;
\ No newline at end of file
+pub use core::{Ppu, Layer, PpuTiming, RenderMode};
+pub use memory::VramAddress;
\ No newline at end of file
@@ -1,5 +1,6 @@
-use crate::ppu::memory::{Vram, Cgram};
+use crate::ppu::memory::{Vram, VramAddress, Cgram};
 use crate::ppu::registers::PpuRegisters;
+use crate::savestate::PpuLatchState;
 
 /// Mode 7 transformation matrix and rendering
 pub struct Mode7Renderer {
@@ -13,10 +14,14 @@ pub struct Mode7Renderer {
     pub m7x: i16,  // Center X
     pub m7y: i16,  // Center Y
     
-    // Scroll values
+    // Scroll values, following M7X/M7Y writes. Rendering takes the scroll
+    // from BG1HOFS/BG1VOFS, which Mode 7 shares on hardware.
     pub m7hofs: i16,  // Horizontal scroll
     pub m7vofs: i16,  // Vertical scroll
     
+    // Filter between texels (an enhancement; see Enhancements)
+    pub bilinear: bool,
+    
     // Internal state
     write_toggle: bool,
     prev_value: u8,
@@ -33,15 +38,28 @@ impl Mode7Renderer {
             m7y: 0,
             m7hofs: 0,
             m7vofs: 0,
+            bilinear: false,
             write_toggle: false,
             prev_value: 0,
         }
     }
     
     pub fn reset(&mut self) {
-        *self = Self::new();
+        *self = Self { bilinear: self.bilinear, ..Self::new() };
     }
     
+    pub fn save_latches(&self, state: &mut PpuLatchState) {
+        state.mode7_write_toggle = self.write_toggle;
+        state.mode7_prev_value = self.prev_value;
+        state.mode7_matrix = [self.m7a, self.m7b, self.m7c, self.m7d, self.m7x, self.m7y, self.m7hofs, self.m7vofs];
+    }
+
+    pub fn load_latches(&mut self, state: &PpuLatchState) {
+        self.write_toggle = state.mode7_write_toggle;
+        self.prev_value = state.mode7_prev_value;
+        [self.m7a, self.m7b, self.m7c, self.m7d, self.m7x, self.m7y, self.m7hofs, self.m7vofs] = state.mode7_matrix;
+    }
+
     pub fn write_register(&mut self, address: u16, value: u8) {
         match address {
             0x211B => {
@@ -155,104 +173,137 @@ impl Mode7Renderer {
     ) {
         // Mode 7 uses a 128x128 tilemap at VRAM $0000-$3FFF
         // Tiles are 8x8 pixels, direct color (8bpp) at VRAM $0000-$3FFF
+        self.render_columns(vram, cgram, registers, (0, scanline), buffer);
+    }
+    
+    // Draw the buffer's worth of columns of the line from screen column
+    // `first_x` on; those off the 256-pixel screen carry on with the same
+    // step, for the widescreen enhancement
+    pub fn render_columns(
+        &self,
+        vram: &Vram,
+        cgram: &Cgram,
+        registers: &PpuRegisters,
+        origin: (i32, u16),
+        buffer: &mut [u8],
+    ) {
+        self.draw(vram, cgram, registers, origin, false, buffer);
+    }
+    
+    fn draw(
+        &self,
+        vram: &Vram,
+        cgram: &Cgram,
+        registers: &PpuRegisters,
+        (first_x, scanline): (i32, u16),
+        extbg: bool,
+        buffer: &mut [u8],
+    ) {
+        let ([mut x, mut y], [step_x, step_y]) = self.line_coordinates(registers, scanline);
+        x += first_x * step_x;
+        y += first_x * step_y;
         
-        let screen_y = scanline as i32;
-        
-        for screen_x in 0..256 {
-            // Apply transformation matrix
-            // Transform screen coordinates to texture coordinates
-            let sx = screen_x as i32 - 128;  // Center around screen center
-            let sy = screen_y as i32 - 112;
-            
-            // Apply matrix transformation
-            // [tx]   [m7a m7b] [sx]   [m7x]
-            // [ty] = [m7c m7d] [sy] + [m7y]
-            let tx = ((self.m7a as i32 * sx + self.m7b as i32 * sy) >> 8) + self.m7hofs as i32;
-            let ty = ((self.m7c as i32 * sx + self.m7d as i32 * sy) >> 8) + self.m7vofs as i32;
-            
-            // Get pixel from tilemap
-            let pixel = self.get_mode7_pixel(vram, tx, ty, registers);
-            
-            // Convert to RGB
-            let (r, g, b) = if pixel != 0 {
-                cgram.color_to_rgb(cgram.read_color(pixel))
-            } else {
-                (0, 0, 0)  // Transparent
-            };
-            
-            // Write to buffer
-            let offset = screen_x * 4;
-            buffer[offset] = r;
-            buffer[offset + 1] = g;
-            buffer[offset + 2] = b;
-            buffer[offset + 3] = if pixel != 0 { 255 } else { 0 };
+        // EXTBG reads the same plane, with each texel's bit 7 as its
+        // priority; only high priority pixels are drawn
+        let mask = if extbg { 0x7F } else { 0xFF };
+        for pixel in buffer.chunks_exact_mut(4) {
+            let texel = self.texel(vram, registers, x >> 8, y >> 8).unwrap_or(0);
+            let texel = if extbg && texel & 0x80 == 0 { 0 } else { texel };
+            write_pixel(pixel, self.color(vram, cgram, registers, [x, y], texel, mask));
+            x += step_x;
+            y += step_y;
         }
     }
     
-    fn get_mode7_pixel(&self, vram: &Vram, tx: i32, ty: i32, registers: &PpuRegisters) -> u8 {
-        // Handle wrapping/repeat modes
-        let (tile_x, tile_y, out_of_bounds) = self.handle_mode7_wrapping(tx, ty, registers);
+    // Texture coordinates of the line's first pixel and the step to the
+    // next, in 8.8 fixed point. As on the PPU the products for the line are
+    // truncated to 6 fraction bits and each pixel only adds A and C, so the
+    // fraction carries along the line instead of being dropped per pixel.
+    fn line_coordinates(&self, registers: &PpuRegisters, scanline: u16) -> ([i32; 2], [i32; 2]) {
+        let (a, b, c, d) = (self.m7a as i32, self.m7b as i32, self.m7c as i32, self.m7d as i32);
+        let (center_x, center_y) = (self.m7x as i32, self.m7y as i32);
+        let h = clip(sign_extend_13(registers.bg1hofs) - center_x);
+        let v = clip(sign_extend_13(registers.bg1vofs) - center_y);
         
-        if out_of_bounds {
-            // Return transparent or fixed color based on settings
-            return if registers.m7sel & 0x40 != 0 {
-                // Fill with character 0
-                0
-            } else {
-                // Transparent
-                0
-            };
+        // M7SEL bit 1 flips the plane vertically, bit 0 horizontally
+        let line = if registers.m7sel & 0x02 != 0 { 255 - scanline as i32 } else { scanline as i32 };
+        let mut x = ((a * h) & !63) + ((b * v) & !63) + ((b * line) & !63) + (center_x << 8);
+        let mut y = ((c * h) & !63) + ((d * v) & !63) + ((d * line) & !63) + (center_y << 8);
+        if registers.m7sel & 0x01 != 0 {
+            x += 255 * a;
+            y += 255 * c;
+            return ([x, y], [-a, -c]);
         }
-        
-        // Get tile number from tilemap
-        let tilemap_x = (tile_x / 8) & 0x7F;
-        let tilemap_y = (tile_y / 8) & 0x7F;
-        let tilemap_addr = (tilemap_y * 128 + tilemap_x) * 2;
-        let tile_num = vram.read(tilemap_addr as u16) as u16;
-        
-        // Get pixel within tile
-        let pixel_x = tile_x & 7;
-        let pixel_y = tile_y & 7;
-        
-        // Mode 7 tiles are 8x8, 8bpp (64 bytes per tile)
-        let tile_addr = tile_num * 64 + (pixel_y * 8 + pixel_x) as u16;
-        vram.read(tile_addr)
+        ([x, y], [a, c])
     }
     
-    fn handle_mode7_wrapping(&self, tx: i32, ty: i32, registers: &PpuRegisters) -> (i32, i32, bool) {
-        let repeat_mode = registers.m7sel & 0x03;
-        
-        match repeat_mode {
-            0 => {
-                // Screen repeat
-                let wrapped_x = tx & 0x3FF;  // Wrap at 1024
-                let wrapped_y = ty & 0x3FF;
-                (wrapped_x, wrapped_y, false)
-            }
-            1 => {
-                // Affine texture wrapping (same as screen repeat for now)
-                let wrapped_x = tx & 0x3FF;
-                let wrapped_y = ty & 0x3FF;
-                (wrapped_x, wrapped_y, false)
+    // The 8-bit texel at whole plane coordinates. M7SEL bits 6-7 decide
+    // what lies outside the 1024x1024 plane: more of it (0 and 1), nothing
+    // (2, None) or tile 0 (3).
+    fn texel(&self, vram: &Vram, registers: &PpuRegisters, tx: i32, ty: i32) -> Option<u8> {
+        let outside = !(0..1024).contains(&tx) || !(0..1024).contains(&ty);
+        let tile_num = match registers.m7sel >> 6 {
+            2 if outside => return None,
+            3 if outside => 0,
+            _ => {
+                // Tile number from the tilemap, the low bytes of the first 16K words
+                let tilemap_x = (tx & 0x3FF) >> 3;
+                let tilemap_y = (ty & 0x3FF) >> 3;
+                vram.read_low(VramAddress::new((tilemap_y * 128 + tilemap_x) as u16)) as u16
             }
-            2 => {
-                // Bitmap repeat
-                if tx < 0 || tx >= 1024 || ty < 0 || ty >= 1024 {
-                    (0, 0, true)  // Out of bounds
-                } else {
-                    (tx, ty, false)
-                }
+        };
+        
+        // Mode 7 tiles are 8x8, 8bpp (64 words per tile), in the high bytes
+        let tile_addr = VramAddress::new(tile_num * 64 + ((ty & 7) * 8 + (tx & 7)) as u16);
+        Some(vram.read_high(tile_addr))
+    }
+    
+    // The color shown for `texel` at the point, of which `mask` picks the
+    // color bits; None when they're 0, which is transparent
+    fn color(
+        &self,
+        vram: &Vram,
+        cgram: &Cgram,
+        registers: &PpuRegisters,
+        coordinates: [i32; 2],
+        texel: u8,
+        mask: u8,
+    ) -> Option<(u8, u8, u8)> {
+        let index = texel & mask;
+        if index == 0 {
+            None
+        } else if self.bilinear {
+            Some(self.filtered(vram, cgram, registers, coordinates, mask))
+        } else {
+            Some(cgram.color_to_rgb(cgram.read_color(index)))
+        }
+    }
+    
+    // The color between the four texels around the point, each weighted by
+    // how close the coordinates' fractions put it. Transparent texels don't
+    // count; the texel the point falls in is opaque, so one always does.
+    fn filtered(&self, vram: &Vram, cgram: &Cgram, registers: &PpuRegisters, [x, y]: [i32; 2], mask: u8) -> (u8, u8, u8) {
+        let (fx, fy) = (x & 0xFF, y & 0xFF);
+        let corners = [
+            (0, 0, (256 - fx) * (256 - fy)),
+            (1, 0, fx * (256 - fy)),
+            (0, 1, (256 - fx) * fy),
+            (1, 1, fx * fy),
+        ];
+        let mut sum = [0i32; 3];
+        let mut total = 0;
+        for (dx, dy, weight) in corners {
+            let index = self.texel(vram, registers, (x >> 8) + dx, (y >> 8) + dy).unwrap_or(0) & mask;
+            if index == 0 || weight == 0 {
+                continue;
             }
-            3 => {
-                // Bitmap repeat with fill
-                if tx < 0 || tx >= 1024 || ty < 0 || ty >= 1024 {
-                    (0, 0, true)  // Out of bounds, will be filled
-                } else {
-                    (tx, ty, false)
-                }
+            let (r, g, b) = cgram.color_to_rgb(cgram.read_color(index));
+            for (channel, value) in sum.iter_mut().zip([r, g, b]) {
+                *channel += value as i32 * weight;
             }
-            _ => (tx, ty, false),
+            total += weight;
         }
+        ((sum[0] / total) as u8, (sum[1] / total) as u8, (sum[2] / total) as u8)
     }
     
     /// Check if Mode 7 EXTBG is enabled (for BG2 in Mode 7)
@@ -269,66 +320,35 @@ impl Mode7Renderer {
         scanline: u16,
         buffer: &mut [u8],
     ) {
-        // EXTBG uses the high bit of Mode 7 tiles as priority
-        // Same rendering as Mode 7 but with priority handling
-        
-        let screen_y = scanline as i32;
-        
-        for screen_x in 0..256 {
-            let sx = screen_x as i32 - 128;
-            let sy = screen_y as i32 - 112;
-            
-            let tx = ((self.m7a as i32 * sx + self.m7b as i32 * sy) >> 8) + self.m7hofs as i32;
-            let ty = ((self.m7c as i32 * sx + self.m7d as i32 * sy) >> 8) + self.m7vofs as i32;
-            
-            // Get pixel and priority from tilemap
-            let (pixel, priority) = self.get_mode7_pixel_with_priority(vram, tx, ty, registers);
-            
-            if priority {
-                // High priority pixels for EXTBG
-                let (r, g, b) = if pixel != 0 {
-                    cgram.color_to_rgb(cgram.read_color(pixel))
-                } else {
-                    (0, 0, 0)
-                };
-                
-                let offset = screen_x * 4;
-                buffer[offset] = r;
-                buffer[offset + 1] = g;
-                buffer[offset + 2] = b;
-                buffer[offset + 3] = if pixel != 0 { 255 } else { 0 };
-            }
-        }
+        self.render_extbg_columns(vram, cgram, registers, (0, scanline), buffer);
     }
     
-    fn get_mode7_pixel_with_priority(
+    // EXTBG's version of render_columns
+    pub fn render_extbg_columns(
         &self,
         vram: &Vram,
-        tx: i32,
-        ty: i32,
+        cgram: &Cgram,
         registers: &PpuRegisters,
-    ) -> (u8, bool) {
-        let (tile_x, tile_y, out_of_bounds) = self.handle_mode7_wrapping(tx, ty, registers);
-        
-        if out_of_bounds {
-            return (0, false);
-        }
-        
-        let tilemap_x = (tile_x / 8) & 0x7F;
-        let tilemap_y = (tile_y / 8) & 0x7F;
-        let tilemap_addr = (tilemap_y * 128 + tilemap_x) * 2;
-        
-        // Read both tile number and attributes
-        let tile_data = vram.read16(tilemap_addr as u16);
-        let tile_num = (tile_data & 0xFF) as u16;
-        let priority = (tile_data & 0x8000) != 0;  // Bit 15 is priority in EXTBG
-        
-        let pixel_x = tile_x & 7;
-        let pixel_y = tile_y & 7;
-        
-        let tile_addr = tile_num * 64 + (pixel_y * 8 + pixel_x) as u16;
-        let pixel = vram.read(tile_addr);
-        
-        (pixel, priority)
+        origin: (i32, u16),
+        buffer: &mut [u8],
+    ) {
+        self.draw(vram, cgram, registers, origin, true, buffer);
     }
-}
\ No newline at end of file
+}
+
+fn write_pixel(pixel: &mut [u8], color: Option<(u8, u8, u8)>) {
+    match color {
+        Some((r, g, b)) => pixel.copy_from_slice(&[r, g, b, 255]),
+        None => pixel.fill(0),
+    }
+}
+
+// Sign-extend a 13-bit register value
+fn sign_extend_13(value: u16) -> i32 {
+    ((value as i32) << 19) >> 19
+}
+
+// The PPU keeps scroll minus center to 10 bits plus sign
+fn clip(value: i32) -> i32 {
+    if value & 0x2000 != 0 { value | !0x3FF } else { value & 0x3FF }
+}
@@ -0,0 +1,120 @@
+// Frame buffer pixel format conversion shared by the frontends
+//
+// Conversions write RGBA8888, the layout expected by both wgpu textures and
+// canvas ImageData. The RGB565 path uses SSE2 on x86_64 and falls back to a
+// lookup-table scalar loop everywhere else (including wasm).
+use crate::ppu::core::SCREEN_WIDTH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PixelFormat {
+    Rgb565,
+    Rgba8888,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Rgba8888 => 4,
+        }
+    }
+}
+
+// Format of the buffer returned by Ppu::get_frame_buffer
+pub const FRAME_BUFFER_FORMAT: PixelFormat = PixelFormat::Rgba8888;
+
+// Lines in a frame of `len` bytes: 224, or 239 with overscan
+pub fn frame_lines(format: PixelFormat, len: usize) -> usize {
+    len / (SCREEN_WIDTH * format.bytes_per_pixel())
+}
+
+const fn build_expand_table<const N: usize>(bits: u32) -> [u8; N] {
+    let mut table = [0u8; N];
+    let mut i = 0;
+    while i < N {
+        let v = i as u32;
+        table[i] = ((v << (8 - bits)) | (v >> (2 * bits - 8))) as u8;
+        i += 1;
+    }
+    table
+}
+
+// 5-bit and 6-bit channel expansion to 8 bits
+static EXPAND5: [u8; 32] = build_expand_table::<32>(5);
+static EXPAND6: [u8; 64] = build_expand_table::<64>(6);
+
+// Convert a frame in `format` into RGBA8888. Converts as many whole pixels
+// as fit in both buffers.
+pub fn convert_to_rgba(format: PixelFormat, src: &[u8], dst: &mut [u8]) {
+    match format {
+        PixelFormat::Rgb565 => rgb565_to_rgba(src, dst),
+        PixelFormat::Rgba8888 => {
+            let len = (src.len() / 4).min(dst.len() / 4) * 4;
+            dst[..len].copy_from_slice(&src[..len]);
+        }
+    }
+}
+
+// Convert little-endian RGB565 pixels to RGBA8888
+pub fn rgb565_to_rgba(src: &[u8], dst: &mut [u8]) {
+    let pixels = (src.len() / 2).min(dst.len() / 4);
+    let src = &src[..pixels * 2];
+    let dst = &mut dst[..pixels * 4];
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        // SSE2 is part of the x86_64 baseline, so no runtime detection is needed
+        let simd_pixels = pixels & !7;
+        // SAFETY: both slices hold at least `simd_pixels` pixels
+        unsafe { sse2::rgb565_to_rgba(&src[..simd_pixels * 2], &mut dst[..simd_pixels * 4]) };
+        rgb565_to_rgba_scalar(&src[simd_pixels * 2..], &mut dst[simd_pixels * 4..]);
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    rgb565_to_rgba_scalar(src, dst);
+}
+
+// Portable lookup-table implementation
+pub fn rgb565_to_rgba_scalar(src: &[u8], dst: &mut [u8]) {
+    for (chunk, out) in src.chunks_exact(2).zip(dst.chunks_exact_mut(4)) {
+        let pixel = u16::from_le_bytes([chunk[0], chunk[1]]);
+        out[0] = EXPAND5[(pixel >> 11) as usize & 0x1F];
+        out[1] = EXPAND6[(pixel >> 5) as usize & 0x3F];
+        out[2] = EXPAND5[pixel as usize & 0x1F];
+        out[3] = 255;
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod sse2 {
+    use std::arch::x86_64::*;
+
+    // Processes 8 pixels per iteration; `src.len()` must be a multiple of 16
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn rgb565_to_rgba(src: &[u8], dst: &mut [u8]) {
+        let mask5 = _mm_set1_epi16(0x1F);
+        let mask6 = _mm_set1_epi16(0x3F);
+        let alpha = _mm_set1_epi16(0xFF00u16 as i16);
+
+        for (chunk, out) in src.chunks_exact(16).zip(dst.chunks_exact_mut(32)) {
+            let pixels = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+
+            let r5 = _mm_and_si128(_mm_srli_epi16(pixels, 11), mask5);
+            let g6 = _mm_and_si128(_mm_srli_epi16(pixels, 5), mask6);
+            let b5 = _mm_and_si128(pixels, mask5);
+
+            let r8 = _mm_or_si128(_mm_slli_epi16(r5, 3), _mm_srli_epi16(r5, 2));
+            let g8 = _mm_or_si128(_mm_slli_epi16(g6, 2), _mm_srli_epi16(g6, 4));
+            let b8 = _mm_or_si128(_mm_slli_epi16(b5, 3), _mm_srli_epi16(b5, 2));
+
+            // Interleave into R,G,B,A byte order
+            let rg = _mm_or_si128(r8, _mm_slli_epi16(g8, 8));
+            let ba = _mm_or_si128(b8, alpha);
+            let lo = _mm_unpacklo_epi16(rg, ba);
+            let hi = _mm_unpackhi_epi16(rg, ba);
+
+            _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, lo);
+            _mm_storeu_si128(out.as_mut_ptr().add(16) as *mut __m128i, hi);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// The PPU's ports $2100-$213F, decoded in one table for the CPU bus, DMA
+// and the PPU itself so the three don't drift apart. The CPU sees the ports
+// at $2100-$213F of every system bank ($00-$3F and $80-$BF, the same way
+// in each); DMA reaches them as B bus addresses $00-$3F.
+use crate::debug::graphics::GraphicsMemory;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortAccess {
+    Write,
+    Read,
+}
+
+// Who answers a read: the counter latch and STAT78 live on the bus, which
+// knows the beam position and the I/O pin; everything else is the PPU's
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortOwner {
+    Ppu,
+    Counters,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortInfo {
+    pub name: &'static str,
+    pub access: PortAccess,
+    pub owner: PortOwner,
+}
+
+const fn write(name: &'static str) -> PortInfo {
+    PortInfo { name, access: PortAccess::Write, owner: PortOwner::Ppu }
+}
+
+const fn read(name: &'static str, owner: PortOwner) -> PortInfo {
+    PortInfo { name, access: PortAccess::Read, owner }
+}
+
+pub const FIRST_PORT: u16 = 0x2100;
+pub const LAST_PORT: u16 = 0x213F;
+
+pub const PORTS: [PortInfo; 0x40] = [
+    write("INIDISP"), write("OBSEL"), write("OAMADDL"), write("OAMADDH"),
+    write("OAMDATA"), write("BGMODE"), write("MOSAIC"), write("BG1SC"),
+    write("BG2SC"), write("BG3SC"), write("BG4SC"), write("BG12NBA"),
+    write("BG34NBA"), write("BG1HOFS"), write("BG1VOFS"), write("BG2HOFS"),
+    write("BG2VOFS"), write("BG3HOFS"), write("BG3VOFS"), write("BG4HOFS"),
+    write("BG4VOFS"), write("VMAIN"), write("VMADDL"), write("VMADDH"),
+    write("VMDATAL"), write("VMDATAH"), write("M7SEL"), write("M7A"),
+    write("M7B"), write("M7C"), write("M7D"), write("M7X"),
+    write("M7Y"), write("CGADD"), write("CGDATA"), write("W12SEL"),
+    write("W34SEL"), write("WOBJSEL"), write("WH0"), write("WH1"),
+    write("WH2"), write("WH3"), write("WBGLOG"), write("WOBJLOG"),
+    write("TM"), write("TS"), write("TMW"), write("TSW"),
+    write("CGWSEL"), write("CGADSUB"), write("COLDATA"), write("SETINI"),
+    read("MPYL", PortOwner::Ppu), read("MPYM", PortOwner::Ppu),
+    read("MPYH", PortOwner::Ppu), read("SLHV", PortOwner::Counters),
+    read("RDOAM", PortOwner::Ppu), read("RDVRAML", PortOwner::Ppu),
+    read("RDVRAMH", PortOwner::Ppu), read("RDCGRAM", PortOwner::Ppu),
+    read("OPHCT", PortOwner::Counters), read("OPVCT", PortOwner::Counters),
+    read("STAT77", PortOwner::Ppu), read("STAT78", PortOwner::Counters),
+];
+
+// The $21xx port a CPU address reaches, if any
+pub fn decode(address: u32) -> Option<u16> {
+    let bank = (address >> 16) & 0xFF;
+    let port = (address & 0xFFFF) as u16;
+    let system_bank = matches!(bank, 0x00..=0x3F | 0x80..=0xBF);
+    (system_bank && (FIRST_PORT..=LAST_PORT).contains(&port)).then_some(port)
+}
+
+// The $21xx port a B bus address reaches, if any
+pub fn decode_b_bus(address: u8) -> Option<u16> {
+    (address < 0x40).then_some(FIRST_PORT + address as u16)
+}
+
+// Memory behind a data port, which the CPU may only use in blank
+pub fn data_port_memory(port: u16) -> Option<GraphicsMemory> {
+    match port {
+        0x2104 | 0x2138 => Some(GraphicsMemory::Oam),
+        0x2118 | 0x2119 | 0x2139 | 0x213A => Some(GraphicsMemory::Vram),
+        0x2122 | 0x213B => Some(GraphicsMemory::Cgram),
+        _ => None,
+    }
+}
+
+// Entry for a port in $2100-$213F
+pub fn port(port: u16) -> &'static PortInfo {
+    &PORTS[port.wrapping_sub(FIRST_PORT) as usize & 0x3F]
+}
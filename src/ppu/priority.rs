@@ -0,0 +1,130 @@
+// Front-to-back order of the main screen layers for each BG mode. Every
+// background tile and sprite has a priority; where layers overlap, the
+// first entry in the order with an opaque pixel is the one shown.
+use crate::ppu::backgrounds::BackgroundRenderer;
+use crate::ppu::color_math::Source;
+use crate::ppu::sprites::SpriteRenderer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Plane {
+    // Background 0-3 (BG1-BG4) tiles with the priority bit clear or set
+    Bg(usize, bool),
+    // Sprites of priority 0-3
+    Obj(u8),
+}
+
+use Plane::{Bg, Obj};
+
+const MODE0: [Plane; 12] = [
+    Obj(3), Bg(0, true), Bg(1, true), Obj(2), Bg(0, false), Bg(1, false),
+    Obj(1), Bg(2, true), Bg(3, true), Obj(0), Bg(2, false), Bg(3, false),
+];
+
+const MODE1: [Plane; 10] = [
+    Obj(3), Bg(0, true), Bg(1, true), Obj(2), Bg(0, false), Bg(1, false),
+    Obj(1), Bg(2, true), Obj(0), Bg(2, false),
+];
+
+// BGMODE bit 3 moves BG3's high-priority tiles in front of everything,
+// sprites included; status bars (ALttP, Secret of Mana) are drawn that way
+const MODE1_BG3_HIGH: [Plane; 10] = [
+    Bg(2, true), Obj(3), Bg(0, true), Bg(1, true), Obj(2), Bg(0, false),
+    Bg(1, false), Obj(1), Obj(0), Bg(2, false),
+];
+
+const MODE2_TO_6: [Plane; 8] = [
+    Obj(3), Bg(0, true), Obj(2), Bg(1, true), Obj(1), Bg(0, false), Obj(0), Bg(1, false),
+];
+
+// Mode 7 has no tile priorities: BG1 sits between sprite priorities 0 and
+// 1, and EXTBG (BG2, which only draws texels with bit 7 set) goes in
+// front of BG1 and sprite priority 1 but behind priorities 2 and 3
+const MODE7: [Plane; 6] = [Obj(3), Obj(2), Bg(1, true), Obj(1), Bg(0, false), Obj(0)];
+
+// Order for a BGMODE ($2105) value; Mode 7 is drawn by its own renderer
+// and composed by compose_mode7
+pub fn layer_order(bgmode: u8) -> &'static [Plane] {
+    match bgmode & 0x07 {
+        0 => &MODE0,
+        1 if (bgmode & 0x08) != 0 => &MODE1_BG3_HIGH,
+        1 => &MODE1,
+        _ => &MODE2_TO_6,
+    }
+}
+
+// Pick the front pixel of each column into `output` (RGBA) and where it
+// came from into `sources`; `layers` has TM's (or TS's) layout, and
+// columns no enabled layer covers get `backdrop`
+pub fn compose(
+    order: &[Plane],
+    layers: u8,
+    backgrounds: &BackgroundRenderer,
+    sprites: &SpriteRenderer,
+    backdrop: [u8; 4],
+    output: &mut [u8],
+    sources: &mut [Source],
+) {
+    for (x, (dst, source)) in output.chunks_exact_mut(4).zip(sources.iter_mut()).enumerate() {
+        let front = order.iter().find_map(|&plane| match plane {
+            Bg(bg, high) if (layers & (1 << bg)) != 0 => backgrounds
+                .pixel(bg, x)
+                .filter(|&(_, priority)| priority == high)
+                .map(|(rgba, _)| (rgba, Source::Bg(bg))),
+            Obj(level) if (layers & 0x10) != 0 => sprites
+                .pixel(x)
+                .filter(|&(_, priority)| priority == level)
+                .map(|(rgba, _)| (rgba, Source::Obj(sprites.palette(x)))),
+            _ => None,
+        });
+        match front {
+            Some((rgba, from)) => {
+                dst.copy_from_slice(rgba);
+                *source = from;
+            }
+            None => {
+                dst.copy_from_slice(&backdrop);
+                *source = Source::Backdrop;
+            }
+        }
+    }
+}
+
+fn opaque(line: &[u8], x: usize) -> Option<&[u8]> {
+    let rgba = &line[x * 4..x * 4 + 4];
+    (rgba[3] != 0).then_some(rgba)
+}
+
+// Mode 7's version, with the lines the Mode 7 renderer drew for BG1
+// (`bg1`) and EXTBG (`bg2`) in MODE7's order
+pub fn compose_mode7(
+    layers: u8,
+    bg1: &[u8],
+    bg2: &[u8],
+    sprites: &SpriteRenderer,
+    backdrop: [u8; 4],
+    output: &mut [u8],
+    sources: &mut [Source],
+) {
+    for (x, (dst, source)) in output.chunks_exact_mut(4).zip(sources.iter_mut()).enumerate() {
+        let front = MODE7.iter().find_map(|&plane| match plane {
+            Bg(bg, _) if (layers & (1 << bg)) != 0 => {
+                opaque(if bg == 0 { bg1 } else { bg2 }, x).map(|rgba| (rgba, Source::Bg(bg)))
+            }
+            Obj(level) if (layers & 0x10) != 0 => sprites
+                .pixel(x)
+                .filter(|&(_, priority)| priority == level)
+                .map(|(rgba, _)| (rgba, Source::Obj(sprites.palette(x)))),
+            _ => None,
+        });
+        match front {
+            Some((rgba, from)) => {
+                dst.copy_from_slice(rgba);
+                *source = from;
+            }
+            None => {
+                dst.copy_from_slice(&backdrop);
+                *source = Source::Backdrop;
+            }
+        }
+    }
+}
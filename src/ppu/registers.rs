@@ -1,3 +1,5 @@
+use crate::savestate::PpuLatchState;
+use crate::ppu::memory::VramAddress;
 use std::fmt;
 
 #[derive(Debug, Clone)]
@@ -71,6 +73,14 @@ pub struct PpuRegisters {
     pub ppu2_latch: bool,
     pub cgram_latch: bool,
     pub cgram_data_latch: u8,
+    
+    // OAM byte address the data ports use, reloaded from OAMADD (a word
+    // address) when it is written
+    pub oam_address: u16,
+    // Last byte written to M7B: the multiplier of MPYL-MPYH
+    pub multiplier: u8,
+    // Fixed color (BGR555) COLDATA has built up one channel at a time
+    pub fixed_color: u16,
 }
 
 impl PpuRegisters {
@@ -134,6 +144,10 @@ impl PpuRegisters {
             ppu2_latch: false,
             cgram_latch: false,
             cgram_data_latch: 0,
+            
+            oam_address: 0,
+            multiplier: 0,
+            fixed_color: 0,
         }
     }
 
@@ -141,8 +155,14 @@ impl PpuRegisters {
         match address {
             0x2100 => self.inidisp = value,
             0x2101 => self.obsel = value,
-            0x2102 => self.oamaddl = value,
-            0x2103 => self.oamaddh = value & 0x81, // Only bits 0 and 7 are used
+            0x2102 => {
+                self.oamaddl = value;
+                self.reload_oam_address();
+            }
+            0x2103 => {
+                self.oamaddh = value & 0x81; // Only bits 0 and 7 are used
+                self.reload_oam_address();
+            }
             0x2104 => self.oamdata = value,
             0x2105 => self.bgmode = value,
             0x2106 => self.mosaic = value,
@@ -237,6 +257,7 @@ impl PpuRegisters {
                 self.ppu1_latch = !self.ppu1_latch;
             }
             0x211C => {
+                self.multiplier = value;
                 if self.ppu1_latch {
                     self.m7b = ((value as i16) << 8) | (self.m7b & 0xFF);
                 } else {
@@ -298,18 +319,60 @@ impl PpuRegisters {
             0x212F => self.tsw = value,
             0x2130 => self.cgwsel = value,
             0x2131 => self.cgadsub = value,
-            0x2132 => self.coldata = value,
+            0x2132 => {
+                // Bits 5-7 pick the channels (red, green, blue) the
+                // intensity in bits 0-4 is written to
+                self.coldata = value;
+                for channel in 0..3 {
+                    if value & (0x20 << channel) != 0 {
+                        let shift = channel * 5;
+                        self.fixed_color = (self.fixed_color & !(0x1F << shift)) | (((value & 0x1F) as u16) << shift);
+                    }
+                }
+            }
             0x2133 => self.setini = value,
             
             _ => {} // Other addresses are read-only or unused
         }
     }
 
+    pub fn save_latches(&self, state: &mut PpuLatchState) {
+        state.ppu1_latch = self.ppu1_latch;
+        state.ppu2_latch = self.ppu2_latch;
+        state.cgram_latch = self.cgram_latch;
+        state.cgram_data_latch = self.cgram_data_latch;
+        state.bg_offsets = [
+            self.bg1hofs, self.bg1vofs, self.bg2hofs, self.bg2vofs,
+            self.bg3hofs, self.bg3vofs, self.bg4hofs, self.bg4vofs,
+        ];
+        state.mode7_params = [self.m7a, self.m7b, self.m7c, self.m7d, self.m7x, self.m7y];
+        state.oam_address = self.oam_address;
+        state.multiplier = self.multiplier;
+        state.fixed_color = self.fixed_color;
+    }
+
+    pub fn load_latches(&mut self, state: &PpuLatchState) {
+        self.ppu1_latch = state.ppu1_latch;
+        self.ppu2_latch = state.ppu2_latch;
+        self.cgram_latch = state.cgram_latch;
+        self.cgram_data_latch = state.cgram_data_latch;
+        [
+            self.bg1hofs, self.bg1vofs, self.bg2hofs, self.bg2vofs,
+            self.bg3hofs, self.bg3vofs, self.bg4hofs, self.bg4vofs,
+        ] = state.bg_offsets;
+        [self.m7a, self.m7b, self.m7c, self.m7d, self.m7x, self.m7y] = state.mode7_params;
+        self.oam_address = state.oam_address;
+        self.multiplier = state.multiplier;
+        self.fixed_color = state.fixed_color;
+    }
+
     pub fn read(&self, address: u16) -> u8 {
         match address {
             // Most PPU registers are write-only
             // Only a few registers can be read
-            0x2134..=0x2136 => 0, // Multiplication result (implemented later)
+            0x2134 => self.multiply_result() as u8,
+            0x2135 => (self.multiply_result() >> 8) as u8,
+            0x2136 => (self.multiply_result() >> 16) as u8,
             0x2137 => 0,          // Software latch
             0x2138 => 0,          // OAM data read (implemented later)
             0x2139 => 0,          // VRAM data read low (implemented later)
@@ -347,17 +410,69 @@ impl PpuRegisters {
         }
     }
 
-    pub fn get_vram_address(&self) -> u16 {
-        ((self.vmaddh as u16) << 8) | (self.vmaddl as u16)
+    // VMADD, a word address
+    pub fn get_vram_address(&self) -> VramAddress {
+        VramAddress::new(((self.vmaddh as u16) << 8) | (self.vmaddl as u16))
     }
 
-    pub fn set_vram_address(&mut self, address: u16) {
-        self.vmaddl = (address & 0xFF) as u8;
-        self.vmaddh = ((address >> 8) & 0x7F) as u8;
+    pub fn set_vram_address(&mut self, address: VramAddress) {
+        let [low, high] = address.word().to_le_bytes();
+        self.vmaddl = low;
+        self.vmaddh = high;
     }
 
     pub fn get_oam_address(&self) -> u16 {
-        ((self.oamaddh as u16) << 8) | (self.oamaddl as u16)
+        self.oam_address
+    }
+
+    // Point the data ports back at OAMADD, as writing it does and vblank
+    // does outside forced blank
+    pub fn reload_oam_address(&mut self) {
+        self.oam_address = ((((self.oamaddh & 0x01) as u16) << 8) | self.oamaddl as u16) << 1;
+    }
+
+    // Advance the data ports' OAM address past the byte just accessed
+    pub fn increment_oam_address(&mut self) {
+        self.oam_address = (self.oam_address + 1) & 0x3FF;
+    }
+
+    // MPYL-MPYH: M7A times the last byte written to M7B, both signed, as a
+    // 24-bit two's complement product
+    pub fn multiply_result(&self) -> u32 {
+        (self.m7a as i32 * self.multiplier as i8 as i32) as u32 & 0xFF_FFFF
+    }
+
+    // SETINI bit 0: 480-line output, which modes 5 and 6 render at full
+    // vertical resolution by drawing alternate lines each field
+    pub fn is_interlaced(&self) -> bool {
+        (self.setini & 0x01) != 0
+    }
+
+    // SETINI bit 1: sprites are drawn at half height, alternate lines of
+    // each sprite per field
+    pub fn is_obj_interlaced(&self) -> bool {
+        (self.setini & 0x02) != 0
+    }
+
+    // SETINI bit 2: 239 visible lines instead of 224
+    pub fn is_overscan(&self) -> bool {
+        (self.setini & 0x04) != 0
+    }
+
+    // SETINI bit 3: modes 0-4 output 512 dots, main and sub screen
+    // alternating
+    pub fn is_pseudo_hires(&self) -> bool {
+        (self.setini & 0x08) != 0
+    }
+
+    // SETINI bit 6: Mode 7 BG2 from the colour bit 7 of its pixels
+    pub fn is_extbg(&self) -> bool {
+        (self.setini & 0x40) != 0
+    }
+
+    // SETINI bit 7: external sync, only for superimposing hardware
+    pub fn is_external_sync(&self) -> bool {
+        (self.setini & 0x80) != 0
     }
 
     pub fn get_main_screen_layers(&self) -> u8 {
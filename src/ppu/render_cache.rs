@@ -1,18 +1,28 @@
 // PPU rendering cache for performance optimization
-use crate::ppu::memory::{Vram, Cgram};
+use crate::ppu::memory::{Vram, VramAddress, Cgram};
 
 const TILE_SIZE: usize = 8;
 const TILES_PER_ROW: usize = 32;
 const MAX_TILES: usize = 1024;
 
+// Writes are tracked per 8 words of VRAM, the size of a 2bpp tile and
+// of one pair of bitplanes in the deeper formats
+const BLOCK_WORDS: usize = 8;
+const BLOCKS: usize = VramAddress::WORDS / BLOCK_WORDS;
+
 // Pre-decoded tile data for faster rendering
 #[derive(Clone)]
 pub struct TileCache {
     // Decoded tile pixels (8x8 pixels, 256 colors each)
     tiles: Vec<[u8; 64]>,
-    // Tile dirty flags
-    dirty: Vec<bool>,
-    // Last VRAM version to detect changes
+    // VRAM address and bit depth each entry was decoded from; None when
+    // the entry was never decoded or was invalidated
+    sources: Vec<Option<(VramAddress, u8)>>,
+    // `vram_version` when each entry was decoded
+    decoded_at: Vec<u64>,
+    // `vram_version` of the last write to each block
+    block_written: Vec<u64>,
+    // Bumped on every write, so a write after a decode always compares newer
     vram_version: u64,
 }
 
@@ -20,34 +30,75 @@ impl TileCache {
     pub fn new() -> Self {
         Self {
             tiles: vec![[0; 64]; MAX_TILES],
-            dirty: vec![true; MAX_TILES],
+            sources: vec![None; MAX_TILES],
+            decoded_at: vec![0; MAX_TILES],
+            block_written: vec![0; BLOCKS],
             vram_version: 0,
         }
     }
     
     pub fn invalidate_all(&mut self) {
-        self.dirty.fill(true);
+        self.sources.fill(None);
         self.vram_version += 1;
     }
     
     pub fn invalidate_tile(&mut self, tile_index: usize) {
         if tile_index < MAX_TILES {
-            self.dirty[tile_index] = true;
+            self.sources[tile_index] = None;
         }
     }
     
+    // A word of VRAM changed; stales exactly the entries decoded from it
+    #[inline(always)]
+    pub fn invalidate_address(&mut self, address: VramAddress) {
+        self.vram_version += 1;
+        self.block_written[address.word() as usize / BLOCK_WORDS] = self.vram_version;
+    }
+    
+    // Whether the entry holds the `bpp` tile at `tile_index` from
+    // `base_addr` (a word address, as the base registers give) as VRAM is now
+    pub fn is_current(&self, tile_index: usize, base_addr: u16, bpp: u8) -> bool {
+        if tile_index >= MAX_TILES {
+            return false;
+        }
+        let tile_addr = Self::tile_address(tile_index, base_addr, bpp);
+        if self.sources[tile_index] != Some((tile_addr, bpp)) {
+            return false;
+        }
+        let decoded_at = self.decoded_at[tile_index];
+        (0..bpp as u16 * 4).step_by(BLOCK_WORDS).all(|offset| {
+            let block = tile_addr.offset(offset).word() as usize / BLOCK_WORDS;
+            self.block_written[block] <= decoded_at
+        })
+    }
+    
+    // A tile is 8 rows of `bpp` bitplanes, two planes to a word
+    fn tile_address(tile_index: usize, base_addr: u16, bpp: u8) -> VramAddress {
+        VramAddress::new(base_addr).offset((tile_index as u16).wrapping_mul(bpp as u16 * 4))
+    }
+    
+    // Start decoding an entry: its VRAM address, or None when it is current
+    fn begin_decode(&mut self, tile_index: usize, base_addr: u16, bpp: u8) -> Option<VramAddress> {
+        if tile_index >= MAX_TILES || self.is_current(tile_index, base_addr, bpp) {
+            return None;
+        }
+        let tile_addr = Self::tile_address(tile_index, base_addr, bpp);
+        self.sources[tile_index] = Some((tile_addr, bpp));
+        self.decoded_at[tile_index] = self.vram_version;
+        Some(tile_addr)
+    }
+    
     // Decode a 2bpp tile (2 bits per pixel, 4 colors)
     pub fn decode_2bpp_tile(&mut self, vram: &Vram, tile_index: usize, base_addr: u16) {
-        if tile_index >= MAX_TILES || !self.dirty[tile_index] {
+        let Some(tile_addr) = self.begin_decode(tile_index, base_addr, 2) else {
             return;
-        }
-        
-        let tile_addr = base_addr + (tile_index as u16) * 16;
+        };
         let tile = &mut self.tiles[tile_index];
         
         for y in 0..8 {
-            let low = vram.read(tile_addr + (y * 2) as u16);
-            let high = vram.read(tile_addr + (y * 2 + 1) as u16);
+            let row = tile_addr.offset(y as u16);
+            let low = vram.read_low(row);
+            let high = vram.read_high(row);
             
             for x in 0..8 {
                 let bit = 7 - x;
@@ -55,24 +106,21 @@ impl TileCache {
                 tile[y * 8 + x] = color;
             }
         }
-        
-        self.dirty[tile_index] = false;
     }
     
     // Decode a 4bpp tile (4 bits per pixel, 16 colors)
     pub fn decode_4bpp_tile(&mut self, vram: &Vram, tile_index: usize, base_addr: u16) {
-        if tile_index >= MAX_TILES || !self.dirty[tile_index] {
+        let Some(tile_addr) = self.begin_decode(tile_index, base_addr, 4) else {
             return;
-        }
-        
-        let tile_addr = base_addr + (tile_index as u16) * 32;
+        };
         let tile = &mut self.tiles[tile_index];
         
         for y in 0..8 {
-            let plane0 = vram.read(tile_addr + (y * 2) as u16);
-            let plane1 = vram.read(tile_addr + (y * 2 + 1) as u16);
-            let plane2 = vram.read(tile_addr + (y * 2 + 16) as u16);
-            let plane3 = vram.read(tile_addr + (y * 2 + 17) as u16);
+            let row = tile_addr.offset(y as u16);
+            let plane0 = vram.read_low(row);
+            let plane1 = vram.read_high(row);
+            let plane2 = vram.read_low(row.offset(8));
+            let plane3 = vram.read_high(row.offset(8));
             
             for x in 0..8 {
                 let bit = 7 - x;
@@ -83,27 +131,28 @@ impl TileCache {
                 tile[y * 8 + x] = color;
             }
         }
-        
-        self.dirty[tile_index] = false;
     }
     
     // Decode an 8bpp tile (8 bits per pixel, 256 colors)
     pub fn decode_8bpp_tile(&mut self, vram: &Vram, tile_index: usize, base_addr: u16) {
-        if tile_index >= MAX_TILES || !self.dirty[tile_index] {
+        let Some(tile_addr) = self.begin_decode(tile_index, base_addr, 8) else {
             return;
-        }
-        
-        let tile_addr = base_addr + (tile_index as u16) * 64;
+        };
         let tile = &mut self.tiles[tile_index];
         
         for y in 0..8 {
+            // Planes 0-1, 2-3, 4-5 and 6-7 are 8 words apart
+            let rows = [0, 8, 16, 24].map(|pair| vram.read_word(tile_addr.offset(y as u16 + pair)));
             for x in 0..8 {
-                let byte_offset = y * 8 + x;
-                tile[byte_offset] = vram.read(tile_addr + byte_offset as u16);
+                let bit = 7 - x;
+                let mut color = 0;
+                for (pair, word) in rows.iter().enumerate() {
+                    color |= ((word >> bit) & 1) << (pair * 2);
+                    color |= ((word >> (bit + 8)) & 1) << (pair * 2 + 1);
+                }
+                tile[y * 8 + x] = color as u8;
             }
         }
-        
-        self.dirty[tile_index] = false;
     }
     
     // Get decoded tile data
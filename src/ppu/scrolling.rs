@@ -1,3 +1,4 @@
+use crate::savestate::PpuLatchState;
 
 /// Handles PPU scrolling and window functionality
 pub struct ScrollingEngine {
@@ -224,6 +225,26 @@ impl ScrollingEngine {
         }
     }
     
+    pub fn save_latches(&self, state: &mut PpuLatchState) {
+        state.scroll_prev_write = self.prev_write;
+        state.scroll_write_toggle = self.write_toggle;
+        state.scroll_offsets = [
+            self.bg1_hscroll, self.bg1_vscroll, self.bg2_hscroll, self.bg2_vscroll,
+            self.bg3_hscroll, self.bg3_vscroll, self.bg4_hscroll, self.bg4_vscroll,
+            self.m7_hscroll, self.m7_vscroll,
+        ];
+    }
+
+    pub fn load_latches(&mut self, state: &PpuLatchState) {
+        self.prev_write = state.scroll_prev_write;
+        self.write_toggle = state.scroll_write_toggle;
+        [
+            self.bg1_hscroll, self.bg1_vscroll, self.bg2_hscroll, self.bg2_vscroll,
+            self.bg3_hscroll, self.bg3_vscroll, self.bg4_hscroll, self.bg4_vscroll,
+            self.m7_hscroll, self.m7_vscroll,
+        ] = state.scroll_offsets;
+    }
+
     pub fn get_bg_scroll(&self, bg_num: u8) -> (u16, u16) {
         match bg_num {
             1 => (self.bg1_hscroll, self.bg1_vscroll),
@@ -1,4 +1,4 @@
-use crate::ppu::memory::{Vram, Cgram, Oam, SpriteAttributes};
+use crate::ppu::memory::{Vram, VramAddress, Cgram, Oam, SpriteAttributes};
 use crate::ppu::registers::PpuRegisters;
 
 // SNES sprite sizes
@@ -16,6 +16,24 @@ const SPRITE_SIZE_LARGE: [(u8, u8); 4] = [
     (32, 32), // 3: 16x16, 32x32
 ];
 
+// Small and large sprite sizes (width, height) selected by OBSEL
+pub fn sprite_sizes(obsel: u8) -> ((u8, u8), (u8, u8)) {
+    let size_select = (obsel >> 5) & 0x07;
+    let size_index = match size_select {
+        0 => 0, // 8x8, 16x16
+        1 => 1, // 8x8, 32x32
+        2 => 2, // 8x8, 64x64
+        3 => 3, // 16x16, 32x32
+        4 => 3, // 16x16, 64x64
+        5 => 1, // 32x32, 64x64
+        6 => 3, // 16x32, 32x64 (treated as 16x16, 32x32)
+        7 => 3, // 16x32, 32x32 (treated as 16x16, 32x32)
+        _ => 0,
+    };
+    
+    (SPRITE_SIZE_SMALL[size_index], SPRITE_SIZE_LARGE[size_index])
+}
+
 // Sprite priority table for sprite-to-sprite priority
 #[derive(Debug, Clone, Copy)]
 struct SpritePixel {
@@ -25,11 +43,35 @@ struct SpritePixel {
     sprite_priority: u8, // OAM index for sprite-to-sprite priority
 }
 
+// What every sprite on one scanline is drawn with
+struct SpriteLine {
+    scanline: u16,
+    // The field being drawn with OBJ interlace on; None without it
+    field: Option<bool>,
+    size_small: (u8, u8),
+    size_large: (u8, u8),
+}
+
+impl SpriteLine {
+    fn size(&self, sprite: &SpriteAttributes) -> (u8, u8) {
+        if sprite.size {
+            self.size_large
+        } else {
+            self.size_small
+        }
+    }
+}
+
 pub struct SpriteRenderer {
     // Scanline buffers for each priority level
     priority_buffers: [Vec<Option<SpritePixel>>; 4],
     // Sprite evaluation results for current scanline
     active_sprites: Vec<(u8, SpriteAttributes)>, // (index, attributes)
+    // Composited sprite layer (RGBA), and the priority and OBJ palette of
+    // each pixel
+    output_buffer: Vec<u8>,
+    output_priority: Vec<u8>,
+    output_palette: Vec<u8>,
 }
 
 impl SpriteRenderer {
@@ -42,6 +84,9 @@ impl SpriteRenderer {
                 vec![None; 256],
             ],
             active_sprites: Vec::with_capacity(32), // Max 32 sprites per scanline
+            output_buffer: vec![0; 256 * 4],
+            output_priority: vec![0; 256],
+            output_palette: vec![0; 256],
         }
     }
     
@@ -52,7 +97,7 @@ impl SpriteRenderer {
         oam: &Oam,
         registers: &PpuRegisters,
         scanline: u16,
-        buffer: &mut [u8],
+        field: bool,
     ) {
         // Clear priority buffers
         for buffer in &mut self.priority_buffers {
@@ -62,51 +107,34 @@ impl SpriteRenderer {
         // Get sprite size settings
         let (size_small, size_large) = self.get_sprite_sizes(registers);
         
+        // With OBJ interlace each screen line covers two sprite rows, of
+        // which this field draws the even or odd one
+        let line = SpriteLine {
+            scanline,
+            field: registers.is_obj_interlaced().then_some(field),
+            size_small,
+            size_large,
+        };
+        
         // Evaluate sprites for this scanline
-        self.evaluate_sprites(oam, scanline, size_small, size_large);
+        self.evaluate_sprites(oam, &line);
         
         // Render active sprites
         for i in 0..self.active_sprites.len() {
             let (sprite_index, sprite) = self.active_sprites[i];
-            self.render_sprite(
-                vram,
-                registers,
-                sprite_index,
-                &sprite,
-                scanline,
-                size_small,
-                size_large,
-            );
+            self.render_sprite(vram, registers, sprite_index, &sprite, &line);
         }
         
-        // Composite sprites onto output buffer
-        self.composite_sprites(cgram, buffer);
+        // Resolve the sprite layer for the priority compositor
+        self.composite_sprites(cgram);
     }
     
     fn get_sprite_sizes(&self, registers: &PpuRegisters) -> ((u8, u8), (u8, u8)) {
-        let size_select = (registers.obsel >> 5) & 0x07;
-        let size_index = match size_select {
-            0 => 0, // 8x8, 16x16
-            1 => 1, // 8x8, 32x32
-            2 => 2, // 8x8, 64x64
-            3 => 3, // 16x16, 32x32
-            4 => 3, // 16x16, 64x64
-            5 => 1, // 32x32, 64x64
-            6 => 3, // 16x32, 32x64 (treated as 16x16, 32x32)
-            7 => 3, // 16x32, 32x32 (treated as 16x16, 32x32)
-            _ => 0,
-        };
-        
-        (SPRITE_SIZE_SMALL[size_index], SPRITE_SIZE_LARGE[size_index])
+        sprite_sizes(registers.obsel)
     }
     
-    fn evaluate_sprites(
-        &mut self,
-        oam: &Oam,
-        scanline: u16,
-        size_small: (u8, u8),
-        size_large: (u8, u8),
-    ) {
+    fn evaluate_sprites(&mut self, oam: &Oam, line: &SpriteLine) {
+        let scanline = line.scanline;
         self.active_sprites.clear();
         
         // Check all 128 sprites
@@ -114,15 +142,12 @@ impl SpriteRenderer {
             let sprite = oam.get_sprite(i);
             
             // Get sprite size
-            let (_width, height) = if sprite.size {
-                size_large
-            } else {
-                size_small
-            };
+            let (_width, height) = line.size(&sprite);
             
             // Check if sprite is on this scanline
             let sprite_top = sprite.y as i16;
-            let sprite_bottom = sprite_top + height as i16;
+            let lines = if line.field.is_some() { height / 2 } else { height };
+            let sprite_bottom = sprite_top + lines as i16;
             
             if scanline as i16 >= sprite_top && (scanline as i16) < sprite_bottom {
                 self.active_sprites.push((i, sprite));
@@ -141,19 +166,16 @@ impl SpriteRenderer {
         registers: &PpuRegisters,
         sprite_index: u8,
         sprite: &SpriteAttributes,
-        scanline: u16,
-        size_small: (u8, u8),
-        size_large: (u8, u8),
+        line: &SpriteLine,
     ) {
         // Get sprite size
-        let (width, height) = if sprite.size {
-            size_large
-        } else {
-            size_small
-        };
+        let (width, height) = line.size(sprite);
         
         // Calculate row within sprite
-        let sprite_y = (scanline as i16 - sprite.y as i16) as u16;
+        let mut sprite_y = (line.scanline as i16 - sprite.y as i16) as u16;
+        if let Some(field) = line.field {
+            sprite_y = sprite_y * 2 + field as u16;
+        }
         let row = if sprite.v_flip {
             height as u16 - 1 - sprite_y
         } else {
@@ -161,7 +183,7 @@ impl SpriteRenderer {
         };
         
         // Get name base from OBSEL register
-        let name_base = ((registers.obsel & 0x07) as u16) << 13;
+        let name_base = VramAddress::new(((registers.obsel & 0x07) as u16) << 13); // * 8K words
         
         // Render each pixel in the sprite row
         for col in 0..width {
@@ -195,15 +217,15 @@ impl SpriteRenderer {
             
             let tile_num = sprite.tile + tile_offset as u16;
             
-            // Calculate VRAM address
-            let vram_addr = name_base + (tile_num << 4); // 16 bytes per tile in 4bpp
+            // Calculate VRAM address: 16 words per tile in 4bpp, one per
+            // row for planes 0-1 and 8 further on for planes 2-3
+            let row_addr = name_base.offset((tile_num << 4) + fine_y);
             
             // Read tile data (4bpp)
-            let byte_offset = fine_y * 2;
-            let plane0 = vram.read(vram_addr + byte_offset);
-            let plane1 = vram.read(vram_addr + byte_offset + 1);
-            let plane2 = vram.read(vram_addr + byte_offset + 8);
-            let plane3 = vram.read(vram_addr + byte_offset + 9);
+            let plane0 = vram.read_low(row_addr);
+            let plane1 = vram.read_high(row_addr);
+            let plane2 = vram.read_low(row_addr.offset(8));
+            let plane3 = vram.read_high(row_addr.offset(8));
             
             let bit_mask = 0x80 >> fine_x;
             let bit0 = if (plane0 & bit_mask) != 0 { 1 } else { 0 };
@@ -240,7 +262,10 @@ impl SpriteRenderer {
         }
     }
     
-    fn composite_sprites(&self, cgram: &Cgram, buffer: &mut [u8]) {
+    fn composite_sprites(&mut self, cgram: &Cgram) {
+        let buffer = &mut self.output_buffer;
+        buffer.fill(0);
+        
         // Composite sprites from highest to lowest priority
         for x in 0..256 {
             for priority in (0..4).rev() {
@@ -256,6 +281,8 @@ impl SpriteRenderer {
                     buffer[offset + 1] = g;
                     buffer[offset + 2] = b;
                     buffer[offset + 3] = 255;
+                    self.output_priority[x] = pixel.priority;
+                    self.output_palette[x] = pixel.palette - 8;
                     
                     // Stop after first non-transparent pixel
                     break;
@@ -264,6 +291,19 @@ impl SpriteRenderer {
         }
     }
     
+    // Sprite pixel at `x` and its priority (0-3); None where no sprite is
+    // drawn
+    pub fn pixel(&self, x: usize) -> Option<(&[u8], u8)> {
+        let rgba = &self.output_buffer[x * 4..x * 4 + 4];
+        (rgba[3] != 0).then(|| (rgba, self.output_priority[x]))
+    }
+    
+    // OBJ palette (0-7) of the sprite pixel at `x`; palettes 4-7 are the
+    // ones color math applies to
+    pub fn palette(&self, x: usize) -> u8 {
+        self.output_palette[x]
+    }
+    
     pub fn get_priority_buffer(&self, priority: u8) -> &[Option<SpritePixel>] {
         &self.priority_buffers[priority as usize]
     }
@@ -0,0 +1,75 @@
+// Widescreen enhancement (Enhancements::widescreen): columns drawn past
+// each side of the 256-pixel picture by continuing the backgrounds'
+// tilemaps or the Mode 7 plane. The PPU draws them per line next to the
+// real picture; the frame buffer it outputs to stays 256 pixels wide, so
+// frame hashes, movies and screenshots don't change.
+use crate::ppu::backgrounds::BackgroundRenderer;
+use crate::ppu::color_math::Source;
+use crate::ppu::core::{OVERSCAN_HEIGHT, SCREEN_WIDTH};
+
+pub struct WidescreenRenderer {
+    margin: usize,
+
+    // The wide picture: the PPU's frame with `margin` columns either side
+    frame_buffer: Vec<u8>,
+
+    // One side's worth of each layer, composed into `line`
+    pub(crate) backgrounds: BackgroundRenderer,
+    pub(crate) mode7_buffer: Vec<u8>,
+    pub(crate) extbg_buffer: Vec<u8>,
+    pub(crate) line: Vec<u8>,
+    pub(crate) sources: Vec<Source>,
+}
+
+impl WidescreenRenderer {
+    pub fn new(margin: usize) -> Self {
+        Self {
+            margin,
+            frame_buffer: vec![0; (SCREEN_WIDTH + margin * 2) * OVERSCAN_HEIGHT * 4],
+            backgrounds: BackgroundRenderer::with_width(margin),
+            mode7_buffer: vec![0; margin * 4],
+            extbg_buffer: vec![0; margin * 4],
+            line: vec![0; margin * 4],
+            sources: vec![Source::Backdrop; margin],
+        }
+    }
+
+    // Columns added on each side
+    pub fn margin(&self) -> usize {
+        self.margin
+    }
+
+    pub fn width(&self) -> usize {
+        SCREEN_WIDTH + self.margin * 2
+    }
+
+    // Screen column of the first pixel on each side: left, then right
+    pub fn sides(&self) -> [i32; 2] {
+        [-(self.margin as i32), SCREEN_WIDTH as i32]
+    }
+
+    // Copy the composed side starting at screen column `first_x` into line
+    // `y` of the wide picture, with the brightness `factor`
+    pub(crate) fn output_side(&mut self, y: usize, first_x: i32, factor: f32) {
+        let width = self.width();
+        let start = (y * width + (first_x + self.margin as i32) as usize) * 4;
+        let dst = &mut self.frame_buffer[start..start + self.margin * 4];
+        for (dst, src) in dst.chunks_exact_mut(4).zip(self.line.chunks_exact(4)) {
+            dst[0] = (src[0] as f32 * factor) as u8;
+            dst[1] = (src[1] as f32 * factor) as u8;
+            dst[2] = (src[2] as f32 * factor) as u8;
+            dst[3] = src[3];
+        }
+    }
+
+    // The wide picture of `height` lines, with `frame` (the PPU's, 256
+    // pixels wide) copied between the sides
+    pub fn frame_buffer(&mut self, frame: &[u8], height: usize) -> &[u8] {
+        let width = self.width();
+        for (y, row) in frame.chunks_exact(SCREEN_WIDTH * 4).take(height).enumerate() {
+            let start = (y * width + self.margin) * 4;
+            self.frame_buffer[start..start + row.len()].copy_from_slice(row);
+        }
+        &self.frame_buffer[..width * height * 4]
+    }
+}
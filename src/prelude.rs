@@ -0,0 +1,12 @@
+// Everything needed to embed the emulator, for `use ccsnes::prelude::*`.
+// See embed.rs for what may change between versions. Power-on memory
+// fill and quirks for EmulatorConfig come from `memory::MemoryInit` and
+// `quirks::Quirks`.
+pub use crate::embed::{AudioChunk, Button, EmulatorConfig, Frame, RanCycles, AUDIO_SAMPLE_RATE, FRAME_RATE};
+pub use crate::emulator::Emulator;
+pub use crate::error::EmulatorError;
+pub use crate::input::LivePads;
+pub use crate::ppu::RenderMode;
+pub use crate::ppu::pixel::PixelFormat;
+pub use crate::savestate::SaveState;
+pub use crate::sink::{AudioSink, NullSink, VideoSink};
@@ -0,0 +1,46 @@
+// Per-game settings stored as TOML next to the game's saves
+use crate::enhancements::Enhancements;
+use crate::input::{PeripheralConfig, RumbleConfig};
+use crate::quirks::Quirks;
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameProfile {
+    // Hardware edge cases this game needs
+    pub quirks: Quirks,
+    // Rendering beyond the hardware's, such as filtered Mode 7
+    pub enhancements: Enhancements,
+    // Force feedback strength and memory triggers
+    pub rumble: RumbleConfig,
+    // Mouse or Super Scope instead of a controller
+    pub peripherals: PeripheralConfig,
+    // Named entry in [input.profiles] to switch to while this game runs
+    pub input_profile: Option<String>,
+}
+
+impl GameProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A missing file is the default profile
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+// Opt-in emulation of hardware edge cases. Each toggle reproduces a timing
+// detail of the real console that only a handful of games depend on, so all
+// of them but the DRAM refresh stall are off by default and enabled per game
+// through its profile.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+    // The CPU samples NMI before the last cycle of an instruction, so a
+    // vblank that begins during that cycle is only serviced after the next
+    // instruction
+    pub nmi_vblank_race: bool,
+    
+    // H/V timer IRQs fire one dot after the programmed HTIME
+    pub irq_late_dot: bool,
+    
+    // Halt the CPU for 40 master cycles at dot 134 (master cycle 538) of
+    // every scanline. Every game sees this on hardware, so it's on unless a
+    // profile turns it off.
+    pub dram_refresh_stall: bool,
+    
+    // VRAM and OAM are only accessible during vblank or forced blank, and
+    // CGRAM also during hblank; other accesses are dropped
+    pub ppu_access_restrictions: bool,
+    
+    // Not hardware behavior but boot shortcuts for games that hang on the
+    // APU handshake. Fast boot starts the SPC700 where the IPL ROM waits
+    // for the CPU, with $AA/$BB already in ports 0 and 1.
+    pub apu_fast_boot: bool,
+    
+    // Stand-in for a sound driver: CPU port reads return what the CPU last
+    // wrote to that port (after $AA/$BB), which satisfies the IPL upload
+    // protocol and most driver acknowledgements
+    pub apu_fake_ack: bool,
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            nmi_vblank_race: false,
+            irq_late_dot: false,
+            dram_refresh_stall: true,
+            ppu_access_restrictions: false,
+            apu_fast_boot: false,
+            apu_fake_ack: false,
+        }
+    }
+}
+
+impl Quirks {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+    
+    // Names of the enabled toggles, for logging
+    pub fn enabled(&self) -> Vec<&'static str> {
+        [
+            ("nmi_vblank_race", self.nmi_vblank_race),
+            ("irq_late_dot", self.irq_late_dot),
+            ("dram_refresh_stall", self.dram_refresh_stall),
+            ("ppu_access_restrictions", self.ppu_access_restrictions),
+            ("apu_fast_boot", self.apu_fast_boot),
+            ("apu_fake_ack", self.apu_fake_ack),
+        ]
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect()
+    }
+    
+    // Flip the toggle called `name`; false if there's no such toggle
+    pub fn toggle(&mut self, name: &str) -> bool {
+        let flag = match name {
+            "nmi_vblank_race" => &mut self.nmi_vblank_race,
+            "irq_late_dot" => &mut self.irq_late_dot,
+            "dram_refresh_stall" => &mut self.dram_refresh_stall,
+            "ppu_access_restrictions" => &mut self.ppu_access_restrictions,
+            "apu_fast_boot" => &mut self.apu_fast_boot,
+            "apu_fake_ack" => &mut self.apu_fake_ack,
+            _ => return false,
+        };
+        *flag = !*flag;
+        true
+    }
+}
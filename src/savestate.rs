@@ -1,14 +1,45 @@
 use crate::{Result, EmulatorError};
+use crate::memory::MemoryInit;
+use bincode::Options;
+use serde::de::DeserializeOwned;
 use serde::{Serialize, Deserialize};
+use std::fmt;
 use std::fs::File;
+use std::io::Read;
 use flate2::write::GzEncoder;
 use flate2::read::GzDecoder;
 use flate2::Compression;
 
 // Save state version for compatibility checking
-const SAVE_STATE_VERSION: u32 = 1;
+const SAVE_STATE_VERSION: u32 = 8;
 
-#[derive(Serialize, Deserialize)]
+// Start of every gzip stream. Uncompressed states start with the version
+// number instead, so the two can't be confused.
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+// The binary layout, spelled out rather than left to defaults so states
+// move between native and wasm32 builds: fixed-width little-endian
+// integers, and lengths as u64 whatever the target's pointer width.
+fn codec() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_little_endian()
+        .allow_trailing_bytes()
+}
+
+// Supported save state encodings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateEncoding {
+    // Compact binary, used for save slots and rewind
+    Bincode,
+    // Pretty-printed JSON for inspecting and diffing by hand
+    Json,
+}
+
+// Fields may be added in any release; build one with Emulator::save_state
+// or SaveState::new
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct SaveState {
     // Version info
     pub version: u32,
@@ -30,9 +61,17 @@ pub struct SaveState {
     
     // Emulator state
     pub cycles: u64,
+    
+    // Power-on memory fill the session started from
+    #[serde(default)]
+    pub memory_init: MemoryInit,
+    
+    // Cartridge coprocessors, one chunk per chip (see cartridge::coprocessor)
+    #[serde(default)]
+    pub coprocessors: Vec<CoprocessorState>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CpuState {
     // Registers
     pub a: u16,
@@ -51,20 +90,28 @@ pub struct CpuState {
     pub waiting_for_interrupt: bool,
     pub nmi_pending: bool,
     pub irq_pending: bool,
+    // Level of the NMI input at the last instruction boundary; an NMI is
+    // taken on its rising edge
+    #[serde(default)]
+    pub nmi_line: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PpuState {
     // Registers
+    #[serde(with = "hex_bytes")]
     pub registers: Vec<u8>,
     
     // VRAM
+    #[serde(with = "hex_bytes")]
     pub vram: Vec<u8>,
     
     // CGRAM
+    #[serde(with = "hex_bytes")]
     pub cgram: Vec<u8>,
     
     // OAM
+    #[serde(with = "hex_bytes")]
     pub oam: Vec<u8>,
     
     // Internal state
@@ -73,11 +120,61 @@ pub struct PpuState {
     pub frame_count: u64,
     pub vblank: bool,
     pub hblank: bool,
+    // RDNMI ($4210) bit 7: vblank began and $4210 wasn't read since
     pub nmi_flag: bool,
     pub irq_flag: bool,
+    
+    // Write-twice and read-twice latches
+    pub latches: PpuLatchState,
+}
+
+// Internal PPU latches, so a state saved between the two halves of a 16-bit
+// register access resumes it instead of desyncing rendering. The
+// write-twice registers are stored too, since they hold the first byte.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PpuLatchState {
+    // Register file flip-flops: BGnHOFS/VOFS and M7A-M7Y share PPU1's,
+    // CGDATA has its own byte latch
+    pub ppu1_latch: bool,
+    pub ppu2_latch: bool,
+    pub cgram_latch: bool,
+    pub cgram_data_latch: u8,
+    pub bg_offsets: [u16; 8],
+    pub mode7_params: [i16; 6],
+    
+    // Scrolling engine: previous scroll byte, BG1-4 H/V and M7 H/V offsets
+    pub scroll_prev_write: u8,
+    pub scroll_write_toggle: bool,
+    pub scroll_offsets: [u16; 10],
+    
+    // Mode 7 renderer: toggle, previous byte, M7A-M7D, M7X/Y, M7HOFS/VOFS
+    pub mode7_write_toggle: bool,
+    pub mode7_prev_value: u8,
+    pub mode7_matrix: [i16; 8],
+    
+    // H/V counter latch ($2137, $213C/$213D flip-flops)
+    pub counters: CounterLatchState,
+    
+    // OAM data port address and the M7B byte MPYL-MPYH multiply by
+    #[serde(default)]
+    pub oam_address: u16,
+    #[serde(default)]
+    pub multiplier: u8,
+    // COLDATA's fixed color, written a channel at a time
+    #[serde(default)]
+    pub fixed_color: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CounterLatchState {
+    pub h: u16,
+    pub v: u16,
+    pub latched: bool,
+    pub h_high: bool,
+    pub v_high: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ApuState {
     // SPC700 state
     pub spc700: Spc700State,
@@ -87,9 +184,13 @@ pub struct ApuState {
     
     // Audio buffer
     pub audio_buffer: Vec<f32>,
+    
+    // CPU cycles the APU had yet to catch up on
+    #[serde(default)]
+    pub pending_cycles: u64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Spc700State {
     // Registers
     pub a: u8,
@@ -100,6 +201,7 @@ pub struct Spc700State {
     pub psw: u8,
     
     // Memory
+    #[serde(with = "hex_bytes")]
     pub ram: Vec<u8>,
     
     // I/O state
@@ -114,7 +216,7 @@ pub struct Spc700State {
     pub cycles: u64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DspState {
     pub channels: Vec<ChannelState>,
     pub main_volume_left: u8,
@@ -124,7 +226,7 @@ pub struct DspState {
     pub sample_counter: u32,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ChannelState {
     pub volume_left: u8,
     pub volume_right: u8,
@@ -135,21 +237,31 @@ pub struct ChannelState {
     pub envelope: u16,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MemoryState {
     // Work RAM
+    #[serde(with = "hex_bytes")]
     pub wram: Vec<u8>,
     
     // Cartridge SRAM (if present)
+    #[serde(with = "hex_bytes::option")]
     pub sram: Option<Vec<u8>>,
 }
 
-#[derive(Serialize, Deserialize)]
+// A coprocessor's chunk, as the chip saved it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoprocessorState {
+    pub name: String,
+    #[serde(with = "hex_bytes")]
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DmaState {
     pub channels: Vec<DmaChannelState>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DmaChannelState {
     pub enabled: bool,
     pub hdma_enabled: bool,
@@ -180,6 +292,8 @@ impl SaveState {
             memory: MemoryState::default(),
             dma: DmaState::default(),
             cycles: 0,
+            memory_init: MemoryInit::default(),
+            coprocessors: Vec::new(),
         }
     }
     
@@ -188,51 +302,479 @@ impl SaveState {
         let file = File::create(path)?;
         let encoder = GzEncoder::new(file, Compression::default());
         
-        bincode::serialize_into(encoder, self)
+        codec().serialize_into(encoder, self)
             .map_err(|e| EmulatorError::SaveStateError(format!("Failed to serialize save state: {}", e)))?;
             
         Ok(())
     }
     
-    /// Load the state from a file
+    /// Load the state from a file, compressed or as written by `to_bytes`
     pub fn load_from_file(path: &str) -> Result<Self> {
-        let file = File::open(path)?;
-        let decoder = GzDecoder::new(file);
-        
-        let state: SaveState = bincode::deserialize_from(decoder)
-            .map_err(|e| EmulatorError::SaveStateError(format!("Failed to deserialize save state: {}", e)))?;
-            
-        // Check version compatibility
-        if state.version != SAVE_STATE_VERSION {
-            return Err(EmulatorError::SaveStateError(format!(
-                "Save state version mismatch: expected {}, got {}",
-                SAVE_STATE_VERSION, state.version
-            )));
-        }
-        
-        Ok(state)
+        Self::from_bytes(&std::fs::read(path)?)
     }
     
-    /// Serialize save state to bytes
+    /// Serialize save state to bytes (the same on every target)
     pub fn to_bytes(&self) -> Result<Vec<u8>> {
-        bincode::serialize(self)
+        codec().serialize(self)
             .map_err(|e| EmulatorError::SaveStateError(format!("Failed to serialize save state: {}", e)))
     }
     
-    /// Deserialize save state from bytes
+    /// Deserialize save state from bytes, either from `to_bytes` or a
+    /// gzipped file from `save_to_file`
     pub fn from_bytes(data: &[u8]) -> Result<Self> {
-        let state: SaveState = bincode::deserialize(data)
-            .map_err(|e| EmulatorError::SaveStateError(format!("Failed to deserialize save state: {}", e)))?;
+        let state: SaveState = if data.starts_with(&GZIP_MAGIC) {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(data).read_to_end(&mut decompressed)
+                .map_err(|e| EmulatorError::SaveStateError(format!("Failed to decompress save state: {}", e)))?;
+            codec().deserialize(&decompressed)
+        } else {
+            codec().deserialize(data)
+        }
+        .map_err(|e| EmulatorError::SaveStateError(format!("Failed to deserialize save state: {}", e)))?;
+            
+        state.check_version()
+    }
+    
+    /// Serialize save state to pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| EmulatorError::save_state(format!("Failed to export save state as JSON: {}", e)))
+    }
+    
+    /// Deserialize save state from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        let state: SaveState = serde_json::from_str(json)
+            .map_err(|e| EmulatorError::save_state(format!("Failed to parse save state JSON: {}", e)))?;
             
-        // Check version compatibility
-        if state.version != SAVE_STATE_VERSION {
+        state.check_version()
+    }
+    
+    /// Serialize with the given encoding
+    pub fn encode(&self, encoding: StateEncoding) -> Result<Vec<u8>> {
+        match encoding {
+            StateEncoding::Bincode => self.to_bytes(),
+            StateEncoding::Json => self.to_json().map(String::into_bytes),
+        }
+    }
+    
+    /// Deserialize with the given encoding
+    pub fn decode(data: &[u8], encoding: StateEncoding) -> Result<Self> {
+        match encoding {
+            StateEncoding::Bincode => Self::from_bytes(data),
+            StateEncoding::Json => {
+                let json = std::str::from_utf8(data)
+                    .map_err(|e| EmulatorError::save_state(format!("Save state JSON is not UTF-8: {}", e)))?;
+                Self::from_json(json)
+            }
+        }
+    }
+    
+    // Check version compatibility
+    fn check_version(self) -> Result<Self> {
+        if self.version != SAVE_STATE_VERSION {
             return Err(EmulatorError::SaveStateError(format!(
                 "Save state version mismatch: expected {}, got {}",
-                SAVE_STATE_VERSION, state.version
+                SAVE_STATE_VERSION, self.version
             )));
         }
-        
-        Ok(state)
+        Ok(self)
+    }
+}
+
+// The parts of a state that can be recovered on their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StateSection {
+    Cpu,
+    Ppu,
+    Apu,
+    Memory,
+    Dma,
+    // The cycle counter and the power-on memory fill
+    Timing,
+    Coprocessors,
+}
+
+impl StateSection {
+    // In the order the binary layout stores them
+    pub const ALL: [StateSection; 7] = [
+        StateSection::Cpu,
+        StateSection::Ppu,
+        StateSection::Apu,
+        StateSection::Memory,
+        StateSection::Dma,
+        StateSection::Timing,
+        StateSection::Coprocessors,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StateSection::Cpu => "CPU",
+            StateSection::Ppu => "PPU",
+            StateSection::Apu => "APU",
+            StateSection::Memory => "memory",
+            StateSection::Dma => "DMA",
+            StateSection::Timing => "timing",
+            StateSection::Coprocessors => "coprocessors",
+        }
+    }
+}
+
+impl fmt::Display for StateSection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+// What could be read of a damaged state or one from another version. Each
+// section is there only if it parsed and has the sizes this build expects;
+// the others have their reason in `failures`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartialState {
+    // Version the state claims, when it could be read
+    pub version: Option<u32>,
+    pub cpu: Option<CpuState>,
+    pub ppu: Option<PpuState>,
+    pub apu: Option<ApuState>,
+    pub memory: Option<MemoryState>,
+    pub dma: Option<DmaState>,
+    pub timing: Option<(u64, MemoryInit)>,
+    pub coprocessors: Option<Vec<CoprocessorState>>,
+    pub failures: Vec<(StateSection, String)>,
+}
+
+impl PartialState {
+    // Every section lost for the same reason
+    fn unreadable(reason: &str) -> Self {
+        Self {
+            failures: StateSection::ALL.iter().map(|&section| (section, reason.to_string())).collect(),
+            ..Self::default()
+        }
+    }
+
+    pub fn recovered(&self) -> Vec<StateSection> {
+        StateSection::ALL.into_iter().filter(|&section| self.has(section)).collect()
+    }
+
+    fn has(&self, section: StateSection) -> bool {
+        match section {
+            StateSection::Cpu => self.cpu.is_some(),
+            StateSection::Ppu => self.ppu.is_some(),
+            StateSection::Apu => self.apu.is_some(),
+            StateSection::Memory => self.memory.is_some(),
+            StateSection::Dma => self.dma.is_some(),
+            StateSection::Timing => self.timing.is_some(),
+            StateSection::Coprocessors => self.coprocessors.is_some(),
+        }
+    }
+
+    // The whole state, when nothing was lost and the version matches
+    pub fn into_state(self) -> Option<SaveState> {
+        if self.version != Some(SAVE_STATE_VERSION) {
+            return None;
+        }
+        let (cycles, memory_init) = self.timing?;
+        Some(SaveState {
+            version: SAVE_STATE_VERSION,
+            cpu: self.cpu?,
+            ppu: self.ppu?,
+            apu: self.apu?,
+            memory: self.memory?,
+            dma: self.dma?,
+            cycles,
+            memory_init,
+            coprocessors: self.coprocessors?,
+        })
+    }
+
+    pub fn recovery(&self) -> StateRecovery {
+        StateRecovery { version: self.version, recovered: self.recovered(), reset: self.failures.clone() }
+    }
+}
+
+// Report of a partial load: which sections came from the state and which
+// were reset instead, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateRecovery {
+    pub version: Option<u32>,
+    pub recovered: Vec<StateSection>,
+    pub reset: Vec<(StateSection, String)>,
+}
+
+impl StateRecovery {
+    // Everything loaded from a state of this build's version
+    pub fn is_complete(&self) -> bool {
+        self.reset.is_empty() && self.version == Some(SAVE_STATE_VERSION)
+    }
+
+    // A section's load failed after it was read
+    pub fn fail(&mut self, section: StateSection, reason: String) {
+        self.recovered.retain(|&recovered| recovered != section);
+        self.reset.push((section, reason));
+    }
+}
+
+impl fmt::Display for StateRecovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(version) if version != SAVE_STATE_VERSION => {
+                write!(f, "version {} state (this build writes {}); ", version, SAVE_STATE_VERSION)?
+            }
+            Some(_) => {}
+            None => f.write_str("unknown version; ")?,
+        }
+        if self.recovered.is_empty() {
+            f.write_str("nothing recovered")?;
+        } else {
+            let names: Vec<&str> = self.recovered.iter().map(|section| section.name()).collect();
+            write!(f, "recovered {}", names.join(", "))?;
+        }
+        for (section, reason) in &self.reset {
+            write!(f, "; reset {} ({})", section, reason)?;
+        }
+        Ok(())
+    }
+}
+
+// A section's own check beyond parsing: the buffer sizes the emulator loads
+trait Section: DeserializeOwned {
+    fn check(&self) -> std::result::Result<(), String> {
+        Ok(())
+    }
+}
+
+fn check_len(what: &str, len: usize, expected: usize) -> std::result::Result<(), String> {
+    if len == expected {
+        Ok(())
+    } else {
+        Err(format!("{} is {} bytes, expected {}", what, len, expected))
+    }
+}
+
+impl Section for CpuState {}
+
+impl Section for PpuState {
+    fn check(&self) -> std::result::Result<(), String> {
+        let defaults = PpuState::default();
+        if self.registers.len() < defaults.registers.len() {
+            return check_len("the register file", self.registers.len(), defaults.registers.len());
+        }
+        check_len("VRAM", self.vram.len(), defaults.vram.len())?;
+        check_len("CGRAM", self.cgram.len(), defaults.cgram.len())?;
+        // The high table is optional
+        if self.oam.len() < 0x200 {
+            return check_len("OAM", self.oam.len(), defaults.oam.len());
+        }
+        Ok(())
+    }
+}
+
+impl Section for ApuState {
+    fn check(&self) -> std::result::Result<(), String> {
+        check_len("SPC700 RAM", self.spc700.ram.len(), 0x10000)?;
+        if self.dsp.channels.len() != 8 {
+            return Err(format!("the DSP has {} voices, expected 8", self.dsp.channels.len()));
+        }
+        Ok(())
+    }
+}
+
+impl Section for MemoryState {
+    fn check(&self) -> std::result::Result<(), String> {
+        check_len("WRAM", self.wram.len(), 0x20000)
+    }
+}
+
+impl Section for DmaState {
+    fn check(&self) -> std::result::Result<(), String> {
+        if self.channels.len() != 8 {
+            return Err(format!("{} DMA channels, expected 8", self.channels.len()));
+        }
+        Ok(())
+    }
+}
+
+// Timing: cycles then memory_init, which bincode writes one after the other
+impl Section for (u64, MemoryInit) {}
+impl Section for u64 {}
+impl Section for MemoryInit {}
+impl Section for Vec<CoprocessorState> {}
+
+// Reads the binary layout's sections in order. Nothing marks where a
+// section ends, so once one fails to parse the ones after it are lost too;
+// a section that parses but fails its check leaves the rest readable.
+struct SectionReader<'a> {
+    bytes: &'a [u8],
+    damaged: Option<StateSection>,
+    failures: Vec<(StateSection, String)>,
+}
+
+impl SectionReader<'_> {
+    fn read<T: Section>(&mut self, section: StateSection) -> Option<T> {
+        if let Some(damaged) = self.damaged {
+            self.failures.push((section, format!("comes after the damaged {} section", damaged)));
+            return None;
+        }
+        let value: T = match codec().deserialize_from(&mut self.bytes) {
+            Ok(value) => value,
+            Err(e) => {
+                self.damaged = Some(section);
+                self.failures.push((section, format!("unreadable: {}", e)));
+                return None;
+            }
+        };
+        match value.check() {
+            Ok(()) => Some(value),
+            Err(reason) => {
+                self.failures.push((section, reason));
+                None
+            }
+        }
+    }
+}
+
+fn json_section<T: Section>(json: &serde_json::Value, key: &str, section: StateSection, failures: &mut Vec<(StateSection, String)>) -> Option<T> {
+    let Some(value) = json.get(key) else {
+        failures.push((section, format!("missing '{}'", key)));
+        return None;
+    };
+    let result = T::deserialize(value).map_err(|e| format!("unreadable: {}", e)).and_then(|value| value.check().map(|()| value));
+    match result {
+        Ok(value) => Some(value),
+        Err(reason) => {
+            failures.push((section, reason));
+            None
+        }
+    }
+}
+
+impl SaveState {
+    /// Read as much of a state as possible, for when `decode` fails: the
+    /// sections that parse and check out, and why each other one didn't.
+    /// Versions aren't checked, so older states give what still matches.
+    pub fn recover(data: &[u8], encoding: StateEncoding) -> PartialState {
+        match encoding {
+            StateEncoding::Bincode => Self::recover_bincode(data),
+            StateEncoding::Json => match std::str::from_utf8(data) {
+                Ok(json) => Self::recover_json(json),
+                Err(e) => PartialState::unreadable(&format!("the state JSON is not UTF-8: {}", e)),
+            },
+        }
+    }
+
+    fn recover_bincode(data: &[u8]) -> PartialState {
+        let mut decompressed = Vec::new();
+        let bytes = if data.starts_with(&GZIP_MAGIC) {
+            // A truncated file still gives what came before the cut
+            let _ = GzDecoder::new(data).read_to_end(&mut decompressed);
+            &decompressed[..]
+        } else {
+            data
+        };
+        let mut reader = SectionReader { bytes, damaged: None, failures: Vec::new() };
+        let version = match codec().deserialize_from::<_, u32>(&mut reader.bytes) {
+            Ok(version) => version,
+            Err(_) => return PartialState::unreadable("the state is too short to hold a version"),
+        };
+        PartialState {
+            version: Some(version),
+            cpu: reader.read(StateSection::Cpu),
+            ppu: reader.read(StateSection::Ppu),
+            apu: reader.read(StateSection::Apu),
+            memory: reader.read(StateSection::Memory),
+            dma: reader.read(StateSection::Dma),
+            timing: reader.read(StateSection::Timing),
+            coprocessors: reader.read(StateSection::Coprocessors),
+            failures: reader.failures,
+        }
+    }
+
+    fn recover_json(json: &str) -> PartialState {
+        let json: serde_json::Value = match serde_json::from_str(json) {
+            Ok(json) => json,
+            Err(e) => return PartialState::unreadable(&format!("the state JSON doesn't parse: {}", e)),
+        };
+        let mut failures = Vec::new();
+        let cpu = json_section(&json, "cpu", StateSection::Cpu, &mut failures);
+        let ppu = json_section(&json, "ppu", StateSection::Ppu, &mut failures);
+        let apu = json_section(&json, "apu", StateSection::Apu, &mut failures);
+        let memory = json_section(&json, "memory", StateSection::Memory, &mut failures);
+        let dma = json_section(&json, "dma", StateSection::Dma, &mut failures);
+        // memory_init and coprocessors were added later and default like
+        // the full load's
+        let cycles = json_section::<u64>(&json, "cycles", StateSection::Timing, &mut failures);
+        let memory_init = match json.get("memory_init") {
+            Some(_) => json_section(&json, "memory_init", StateSection::Timing, &mut failures),
+            None => Some(MemoryInit::default()),
+        };
+        let coprocessors = match json.get("coprocessors") {
+            Some(_) => json_section(&json, "coprocessors", StateSection::Coprocessors, &mut failures),
+            None => Some(Vec::new()),
+        };
+        PartialState {
+            version: json.get("version").and_then(serde_json::Value::as_u64).map(|version| version as u32),
+            cpu,
+            ppu,
+            apu,
+            memory,
+            dma,
+            timing: cycles.zip(memory_init),
+            coprocessors,
+            failures,
+        }
+    }
+}
+
+// Byte buffers are written as rows of hex in human-readable formats (one row
+// per line in JSON keeps diffs readable) and as plain bytes in bincode.
+mod hex_bytes {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    const ROW_BYTES: usize = 32;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return bytes.serialize(serializer);
+        }
+        let rows: Vec<String> = bytes.chunks(ROW_BYTES)
+            .map(|row| row.iter().map(|b| format!("{:02x}", b)).collect())
+            .collect();
+        rows.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error> {
+        if !deserializer.is_human_readable() {
+            return Vec::<u8>::deserialize(deserializer);
+        }
+        let rows = Vec::<String>::deserialize(deserializer)?;
+        let mut bytes = Vec::with_capacity(rows.len() * ROW_BYTES);
+        for row in &rows {
+            if row.len() % 2 != 0 {
+                return Err(D::Error::custom(format!("odd-length hex row '{}'", row)));
+            }
+            for i in (0..row.len()).step_by(2) {
+                let byte = row.get(i..i + 2)
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(|| D::Error::custom(format!("invalid hex row '{}'", row)))?;
+                bytes.push(byte);
+            }
+        }
+        Ok(bytes)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        #[derive(Serialize, Deserialize)]
+        struct Wrapper(#[serde(with = "super")] Vec<u8>);
+
+        pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            bytes.as_ref().map(|b| Wrapper(b.clone())).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<Vec<u8>>, D::Error> {
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|w| w.0))
+        }
     }
 }
 
@@ -254,6 +796,7 @@ impl Default for CpuState {
             waiting_for_interrupt: false,
             nmi_pending: false,
             irq_pending: false,
+            nmi_line: false,
         }
     }
 }
@@ -272,6 +815,30 @@ impl Default for PpuState {
             hblank: false,
             nmi_flag: false,
             irq_flag: false,
+            latches: PpuLatchState::default(),
+        }
+    }
+}
+
+impl Default for PpuLatchState {
+    fn default() -> Self {
+        Self {
+            ppu1_latch: false,
+            ppu2_latch: false,
+            cgram_latch: false,
+            cgram_data_latch: 0,
+            bg_offsets: [0; 8],
+            mode7_params: [0; 6],
+            scroll_prev_write: 0,
+            scroll_write_toggle: false,
+            scroll_offsets: [0; 10],
+            mode7_write_toggle: false,
+            mode7_prev_value: 0,
+            mode7_matrix: [0x0100, 0, 0, 0x0100, 0, 0, 0, 0],
+            counters: CounterLatchState::default(),
+            oam_address: 0,
+            multiplier: 0,
+            fixed_color: 0,
         }
     }
 }
@@ -282,6 +849,7 @@ impl Default for ApuState {
             spc700: Spc700State::default(),
             dsp: DspState::default(),
             audio_buffer: Vec::new(),
+            pending_cycles: 0,
         }
     }
 }
@@ -0,0 +1,168 @@
+// Session bundles: everything a game's progress lives in (the battery save,
+// the save state slots, the recorded movie, cheats and the game profile) in
+// one file, for moving it to another machine or keeping a backup.
+//
+// A bundle is an 8-byte magic followed by a gzipped bincode body in the
+// save states' fixed-width little-endian layout, so it reads the same on
+// every target and can't be mistaken for a save state.
+use crate::paths::{GameId, GamePaths};
+use crate::{EmulatorError, Result};
+use bincode::Options;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+const MAGIC: [u8; 8] = *b"CCSNESSB";
+const BUNDLE_VERSION: u32 = 1;
+
+// Save state slots the frontend's hotkeys select
+pub const STATE_SLOTS: u8 = 10;
+
+fn codec() -> impl Options {
+    bincode::DefaultOptions::new().with_fixint_encoding().with_little_endian()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BundleEntry {
+    Sram,
+    State(u8),
+    Movie,
+    Cheats,
+    Profile,
+}
+
+impl BundleEntry {
+    // Every file a bundle can hold, in the order it stores them
+    pub fn all() -> Vec<BundleEntry> {
+        let mut entries = vec![BundleEntry::Sram];
+        entries.extend((0..STATE_SLOTS).map(BundleEntry::State));
+        entries.extend([BundleEntry::Movie, BundleEntry::Cheats, BundleEntry::Profile]);
+        entries
+    }
+
+    pub fn path(self, paths: &GamePaths) -> PathBuf {
+        match self {
+            BundleEntry::Sram => paths.sram_path().to_path_buf(),
+            BundleEntry::State(slot) => paths.state_path(slot),
+            BundleEntry::Movie => paths.movie_path(),
+            BundleEntry::Cheats => paths.cheats_path().to_path_buf(),
+            BundleEntry::Profile => paths.profile_path().to_path_buf(),
+        }
+    }
+}
+
+impl fmt::Display for BundleEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleEntry::Sram => f.write_str("SRAM"),
+            BundleEntry::State(slot) => write!(f, "state slot {}", slot),
+            BundleEntry::Movie => f.write_str("movie"),
+            BundleEntry::Cheats => f.write_str("cheats"),
+            BundleEntry::Profile => f.write_str("profile"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionBundle {
+    pub version: u32,
+    // Game the files were saved for, as GameId names it
+    pub game: String,
+    pub crc32: u32,
+    // Each file as it was on disk; a file the game didn't have is left out
+    pub files: Vec<(BundleEntry, Vec<u8>)>,
+}
+
+// Where restore put each file, and the files it left alone because they
+// were already there
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreReport {
+    pub written: Vec<(BundleEntry, PathBuf)>,
+    pub kept: Vec<(BundleEntry, PathBuf)>,
+}
+
+impl SessionBundle {
+    // Read the game's files from where `paths` keeps them
+    pub fn collect(game: &GameId, paths: &GamePaths) -> Result<Self> {
+        let mut files = Vec::new();
+        for entry in BundleEntry::all() {
+            let path = entry.path(paths);
+            if path.is_file() {
+                files.push((entry, fs::read(&path)?));
+            }
+        }
+        Ok(Self { version: BUNDLE_VERSION, game: game.name.clone(), crc32: game.crc32, files })
+    }
+
+    pub fn get(&self, entry: BundleEntry) -> Option<&[u8]> {
+        self.files.iter().find(|(held, _)| *held == entry).map(|(_, data)| &data[..])
+    }
+
+    // Whether the bundle was made for this ROM (by its contents, not name)
+    pub fn matches(&self, game: &GameId) -> bool {
+        self.crc32 == game.crc32
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut encoder = GzEncoder::new(MAGIC.to_vec(), Compression::default());
+        let body = codec()
+            .serialize(self)
+            .map_err(|e| EmulatorError::save_state(format!("Failed to serialize session bundle: {}", e)))?;
+        encoder.write_all(&body)?;
+        Ok(encoder.finish()?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let body = data
+            .strip_prefix(&MAGIC[..])
+            .ok_or_else(|| EmulatorError::save_state("Not a session bundle (written by export-session)"))?;
+        let mut decompressed = Vec::new();
+        GzDecoder::new(body)
+            .read_to_end(&mut decompressed)
+            .map_err(|e| EmulatorError::save_state(format!("Failed to decompress session bundle: {}", e)))?;
+        let bundle: SessionBundle = codec()
+            .deserialize(&decompressed)
+            .map_err(|e| EmulatorError::save_state(format!("Failed to read session bundle: {}", e)))?;
+        if bundle.version != BUNDLE_VERSION {
+            return Err(EmulatorError::save_state(format!(
+                "Session bundle version {} isn't supported (this build reads {})",
+                bundle.version, BUNDLE_VERSION
+            )));
+        }
+        Ok(bundle)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    // Write the files where `paths` keeps them. Files already there are
+    // kept unless `overwrite` is set.
+    pub fn restore(&self, paths: &GamePaths, overwrite: bool) -> Result<RestoreReport> {
+        paths.create_directories()?;
+        let mut report = RestoreReport::default();
+        for (entry, data) in &self.files {
+            let path = entry.path(paths);
+            if path.exists() && !overwrite {
+                report.kept.push((*entry, path));
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, data)?;
+            report.written.push((*entry, path));
+        }
+        Ok(report)
+    }
+}
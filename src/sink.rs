@@ -0,0 +1,35 @@
+// Where a frontend puts what the emulator produces. The native window and
+// the browser canvas implement these, and so can any other host (SDL, a
+// terminal, a headless video encoder) without reaching into the PPU or
+// APU: Emulator::run_frame_to runs a frame and hands it to both sinks.
+use crate::embed::{AudioChunk, Frame};
+use crate::Result;
+
+pub trait VideoSink {
+    // A finished picture. The frame describes its own size and pixel
+    // format and when it was made; the pixels are only borrowed, so copy
+    // what must outlive the call.
+    fn present_frame(&mut self, frame: &Frame) -> Result<()>;
+}
+
+pub trait AudioSink {
+    // Samples made since the previous chunk. A changed epoch means they
+    // don't follow on from the previous ones (reset, state load).
+    fn queue_audio(&mut self, chunk: &AudioChunk) -> Result<()>;
+}
+
+// Discards everything, for running without video or sound
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NullSink;
+
+impl VideoSink for NullSink {
+    fn present_frame(&mut self, _frame: &Frame) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl AudioSink for NullSink {
+    fn queue_audio(&mut self, _chunk: &AudioChunk) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+// Crash-safe SRAM persistence. Saves are written to a temporary file and
+// renamed over the old one, and carry a CRC32 footer so a damaged file is
+// detected on load instead of silently handed to the game. Writes are
+// debounced until the game has stopped touching SRAM for a number of frames.
+use crate::emulator::Emulator;
+use crate::{EmulatorError, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const FOOTER_MAGIC: &[u8; 4] = b"CCSR";
+const FOOTER_SIZE: usize = 8;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+// Read an SRAM file, verifying and stripping the footer. Files without a
+// footer (older saves or other emulators) are returned unchanged.
+pub fn read_sram(path: &Path) -> Result<Vec<u8>> {
+    let mut data = fs::read(path)?;
+    if data.len() >= FOOTER_SIZE && &data[data.len() - FOOTER_SIZE..data.len() - 4] == FOOTER_MAGIC {
+        let footer = data.split_off(data.len() - FOOTER_SIZE);
+        let stored = u32::from_le_bytes([footer[4], footer[5], footer[6], footer[7]]);
+        if stored != crc32(&data) {
+            return Err(EmulatorError::save_state(format!("SRAM file {:?} is corrupt (checksum mismatch)", path)));
+        }
+    }
+    Ok(data)
+}
+
+// Write SRAM with a checksum footer via temp file + rename
+pub fn write_sram(path: &Path, sram: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp_name = path.as_os_str().to_owned();
+    temp_name.push(".tmp");
+    let temp_path = PathBuf::from(temp_name);
+
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(sram)?;
+    file.write_all(FOOTER_MAGIC)?;
+    file.write_all(&crc32(sram).to_le_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+// Tracks SRAM changes frame by frame and writes the file once the game has
+// been idle for `idle_frames` frames
+pub struct SramSaver {
+    path: PathBuf,
+    idle_frames: u32,
+    last_write_count: u64,
+    // Frames since the last SRAM write, while unsaved changes exist
+    pending: Option<u32>,
+}
+
+impl SramSaver {
+    pub fn new(path: PathBuf, idle_frames: u32, emulator: &Emulator) -> Self {
+        Self {
+            path,
+            idle_frames,
+            last_write_count: emulator.sram_write_count(),
+            pending: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn set_idle_frames(&mut self, idle_frames: u32) {
+        self.idle_frames = idle_frames;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    // Call once per emulated frame; returns true when the file was written
+    pub fn frame(&mut self, emulator: &Emulator) -> Result<bool> {
+        let count = emulator.sram_write_count();
+        if count != self.last_write_count {
+            self.last_write_count = count;
+            self.pending = Some(0);
+            return Ok(false);
+        }
+        match self.pending.as_mut() {
+            Some(idle) if *idle + 1 >= self.idle_frames => self.flush(emulator),
+            Some(idle) => {
+                *idle += 1;
+                Ok(false)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // Write unsaved changes now (on exit, before switching games, ...)
+    pub fn flush(&mut self, emulator: &Emulator) -> Result<bool> {
+        self.last_write_count = emulator.sram_write_count();
+        if self.pending.take().is_none() {
+            return Ok(false);
+        }
+        match emulator.get_sram().filter(|sram| !sram.is_empty()) {
+            Some(sram) => {
+                write_sram(&self.path, &sram)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+// Conversion of save files to and from other emulators and flashcarts,
+// which pad saves to a fixed size, truncate them, or (for some coprocessor
+// boards) store them with the bytes of each 16-bit word swapped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaveFormat {
+    // Plain SRAM bytes, as used by other emulators and flashcarts
+    #[default]
+    Raw,
+    // This emulator's format, with the checksum footer
+    Ccsnes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertOptions {
+    // SRAM size the game expects, from the ROM header
+    pub size: usize,
+    // Swap the bytes of every 16-bit word
+    pub swap_bytes: bool,
+    // Byte used to pad short saves
+    pub fill: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertReport {
+    // Save bytes read, without any footer
+    pub input_len: usize,
+    pub output_len: usize,
+    // The input carried this emulator's checksum footer
+    pub had_footer: bool,
+    // Truncation dropped bytes other than padding
+    pub dropped_data: bool,
+}
+
+// Pad or truncate a save to `options.size`, fixing the byte order if asked.
+// Padding follows the data; byte swapping happens first so a save with an
+// odd byte count keeps its last byte in place.
+pub fn normalize_save(data: &[u8], options: &ConvertOptions) -> (Vec<u8>, ConvertReport) {
+    let mut save = data.to_vec();
+    if options.swap_bytes {
+        for pair in save.chunks_exact_mut(2) {
+            pair.swap(0, 1);
+        }
+    }
+
+    // Flashcarts pad with one value; anything else in the cut-off part is data
+    let dropped_data = save.len() > options.size && {
+        let tail = &save[options.size..];
+        tail.iter().any(|&byte| byte != tail[0])
+    };
+    save.resize(options.size, options.fill);
+
+    let report = ConvertReport { input_len: data.len(), output_len: save.len(), had_footer: false, dropped_data };
+    (save, report)
+}
+
+// Read a save in either format, normalize it and write it as `format`
+pub fn convert_save(input: &Path, output: &Path, options: &ConvertOptions, format: SaveFormat) -> Result<ConvertReport> {
+    let raw_len = fs::metadata(input)?.len() as usize;
+    let data = read_sram(input)?;
+    let (save, mut report) = normalize_save(&data, options);
+    report.had_footer = data.len() != raw_len;
+    match format {
+        SaveFormat::Raw => {
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(output, &save)?;
+        }
+        SaveFormat::Ccsnes => write_sram(output, &save)?,
+    }
+    Ok(report)
+}
@@ -4,20 +4,38 @@ use web_sys::{console, HtmlCanvasElement, ImageData, KeyboardEvent};
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use crate::apu::volume::Volume;
+use crate::cartridge::RomLoader;
+use crate::config::Config;
+use crate::embed::{AudioChunk, Frame};
 use crate::emulator::Emulator;
-use crate::input::controller::{
-    BUTTON_A, BUTTON_B, BUTTON_X, BUTTON_Y,
-    BUTTON_L, BUTTON_R, BUTTON_START, BUTTON_SELECT,
-    BUTTON_UP, BUTTON_DOWN, BUTTON_LEFT, BUTTON_RIGHT
-};
+use crate::error::EmulatorError;
+use crate::ppu::frame_blend::FrameBlender;
+use crate::ppu::pixel;
+use crate::sink::{AudioSink, VideoSink};
+
+mod worker;
+
+pub use worker::WorkerEmulator;
+
+// Bytes copied per step of load_rom_async before yielding to the browser
+const ASYNC_CHUNK_SIZE: u32 = 256 * 1024;
+
+// localStorage entry holding the configuration between visits; also used
+// by web/worker-client.js, which persists on the worker's behalf
+const CONFIG_STORAGE_KEY: &str = "ccsnes.config";
 
 #[wasm_bindgen]
 pub struct WasmEmulator {
     emulator: Rc<RefCell<Emulator>>,
-    ctx: web_sys::CanvasRenderingContext2d,
-    audio_ctx: Option<web_sys::AudioContext>,
-    frame_buffer: Vec<u8>,
-    controller_state: u16,
+    video: CanvasVideo,
+    audio: WebAudio,
+    controller_state: [u16; 2],
+    // Chunked load started with begin_rom_load
+    rom_loader: Option<RomLoader>,
+    // Settings applied with apply_config_json
+    config: Config,
+    fps: FpsCounter,
 }
 
 #[wasm_bindgen]
@@ -25,6 +43,7 @@ impl WasmEmulator {
     #[wasm_bindgen(constructor)]
     pub fn new(canvas_id: &str) -> Result<WasmEmulator, JsValue> {
         // Set panic hook for better error messages
+        #[cfg(feature = "panic-hook")]
         console_error_panic_hook::set_once();
         
         // Get canvas element
@@ -49,8 +68,10 @@ impl WasmEmulator {
         canvas.set_height(224);
         
         // Create emulator
-        let emulator = Emulator::new()
+        let mut emulator = Emulator::new()
             .map_err(|e| JsValue::from_str(&format!("Failed to create emulator: {}", e)))?;
+        let config = saved_config();
+        apply_audio(&config, &mut emulator);
         let emulator = Rc::new(RefCell::new(emulator));
         
         // Try to create audio context (might fail due to browser restrictions)
@@ -58,10 +79,17 @@ impl WasmEmulator {
         
         Ok(WasmEmulator {
             emulator,
-            ctx,
-            audio_ctx,
-            frame_buffer: vec![0; 256 * 224 * 4],
-            controller_state: 0,
+            video: CanvasVideo {
+                canvas,
+                ctx,
+                frame_buffer: vec![0; 256 * 224 * 4],
+                blender: FrameBlender::default(),
+            },
+            audio: WebAudio { _context: audio_ctx },
+            controller_state: [0; 2],
+            rom_loader: None,
+            config,
+            fps: FpsCounter::new(),
         })
     }
     
@@ -71,12 +99,71 @@ impl WasmEmulator {
             .load_rom(rom_data)
             .map_err(|e| JsValue::from_str(&format!("Failed to load ROM: {}", e)))?;
             
-        let title = self.emulator.borrow().get_rom_info()
-            .map(|info| info.title.clone())
-            .unwrap_or_else(|| "Unknown".to_string());
+        Ok(loaded_title(&self.emulator.borrow()))
+    }
+    
+    // Run the built-in self test (color bars, input tester, tone)
+    #[wasm_bindgen]
+    pub fn load_boot_rom(&mut self) -> Result<String, JsValue> {
+        self.load_rom(&crate::boot_rom::build())
+    }
+    
+    // Take the game out, e.g. before returning to a ROM picker; returns
+    // its SRAM so the page can store it
+    #[wasm_bindgen]
+    pub fn unload_rom(&mut self) -> Vec<u8> {
+        self.emulator.borrow_mut().unload_rom().and_then(|cartridge| cartridge.get_sram().map(<[u8]>::to_vec)).unwrap_or_default()
+    }
+    
+    // Load a ROM without blocking the page: the data is copied in chunks,
+    // yielding to the event loop in between. Resolves to the ROM title.
+    #[wasm_bindgen]
+    pub fn load_rom_async(&self, rom_data: js_sys::Uint8Array) -> js_sys::Promise {
+        let emulator = self.emulator.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let length = rom_data.length();
+            let mut loader = RomLoader::with_expected_size(length as usize);
+            let mut offset = 0;
+            while offset < length {
+                let end = (offset + ASYNC_CHUNK_SIZE).min(length);
+                loader.push(&rom_data.subarray(offset, end).to_vec());
+                offset = end;
+                yield_to_browser().await?;
+            }
             
-        console::log_1(&format!("Loaded ROM: {}", title).into());
-        Ok(title)
+            let cartridge = loader.finish()
+                .map_err(|e| JsValue::from_str(&format!("Failed to load ROM: {}", e)))?;
+            emulator.borrow_mut()
+                .load_cartridge(cartridge)
+                .map_err(|e| JsValue::from_str(&format!("Failed to load ROM: {}", e)))?;
+            Ok(JsValue::from_str(&loaded_title(&emulator.borrow())))
+        })
+    }
+    
+    // Chunked loading driven from JavaScript (e.g. while streaming a fetch):
+    // begin_rom_load, push_rom_chunk for each piece, then finish_rom_load
+    #[wasm_bindgen]
+    pub fn begin_rom_load(&mut self, expected_size: usize) {
+        self.rom_loader = Some(RomLoader::with_expected_size(expected_size));
+    }
+    
+    // Returns the fraction of the expected size received so far
+    #[wasm_bindgen]
+    pub fn push_rom_chunk(&mut self, chunk: &[u8]) -> Result<f64, JsValue> {
+        let loader = self.rom_loader.as_mut().ok_or("No ROM load in progress")?;
+        loader.push(chunk);
+        Ok(loader.progress())
+    }
+    
+    #[wasm_bindgen]
+    pub fn finish_rom_load(&mut self) -> Result<String, JsValue> {
+        let loader = self.rom_loader.take().ok_or("No ROM load in progress")?;
+        let cartridge = loader.finish()
+            .map_err(|e| JsValue::from_str(&format!("Failed to load ROM: {}", e)))?;
+        self.emulator.borrow_mut()
+            .load_cartridge(cartridge)
+            .map_err(|e| JsValue::from_str(&format!("Failed to load ROM: {}", e)))?;
+        Ok(loaded_title(&self.emulator.borrow()))
     }
     
     #[wasm_bindgen]
@@ -87,69 +174,92 @@ impl WasmEmulator {
     
     #[wasm_bindgen]
     pub fn run_frame(&mut self) -> Result<(), JsValue> {
-        // Run one frame
-        self.emulator.borrow_mut().step_frame()
+        // Run one frame; Instant is unavailable here, so time it with Date
+        let started = js_sys::Date::now();
+        let mut emulator = self.emulator.borrow_mut();
+        emulator.step_frame()
             .map_err(|e| JsValue::from_str(&format!("Emulation error: {}", e)))?;
+        let now = js_sys::Date::now();
+        emulator.record_frame_time(now - started);
+        self.fps.update(now, emulator.get_frame_count());
         
-        // Get frame buffer and render
-        self.render_frame()?;
-        
-        // Process audio if available
-        if let Some(ref audio_ctx) = self.audio_ctx {
-            self.process_audio(audio_ctx)?;
-        }
-        
-        Ok(())
+        self.video.blender.set_enabled(self.config.video.frame_blend);
+        self.video.present_frame(&emulator.frame()).map_err(to_js)?;
+        self.audio.queue_audio(&emulator.take_audio()).map_err(to_js)
     }
     
     #[wasm_bindgen]
     pub fn handle_key_down(&mut self, event: &KeyboardEvent) {
-        let button = match event.key().as_str() {
-            "ArrowUp" => Some(BUTTON_UP),
-            "ArrowDown" => Some(BUTTON_DOWN),
-            "ArrowLeft" => Some(BUTTON_LEFT),
-            "ArrowRight" => Some(BUTTON_RIGHT),
-            "z" | "Z" => Some(BUTTON_A),
-            "x" | "X" => Some(BUTTON_B),
-            "a" | "A" => Some(BUTTON_X),
-            "s" | "S" => Some(BUTTON_Y),
-            "q" | "Q" => Some(BUTTON_L),
-            "w" | "W" => Some(BUTTON_R),
-            "Enter" => Some(BUTTON_START),
-            "Shift" => Some(BUTTON_SELECT),
-            _ => None,
-        };
-        
-        if let Some(button) = button {
-            self.controller_state |= button;
-            self.emulator.borrow_mut().set_controller_input(0, self.controller_state);
-        }
+        self.update_keys(event, true);
     }
     
     #[wasm_bindgen]
     pub fn handle_key_up(&mut self, event: &KeyboardEvent) {
-        let button = match event.key().as_str() {
-            "ArrowUp" => Some(BUTTON_UP),
-            "ArrowDown" => Some(BUTTON_DOWN),
-            "ArrowLeft" => Some(BUTTON_LEFT),
-            "ArrowRight" => Some(BUTTON_RIGHT),
-            "z" | "Z" => Some(BUTTON_A),
-            "x" | "X" => Some(BUTTON_B),
-            "a" | "A" => Some(BUTTON_X),
-            "s" | "S" => Some(BUTTON_Y),
-            "q" | "Q" => Some(BUTTON_L),
-            "w" | "W" => Some(BUTTON_R),
-            "Enter" => Some(BUTTON_START),
-            "Shift" => Some(BUTTON_SELECT),
-            _ => None,
-        };
-        
-        if let Some(button) = button {
-            self.controller_state &= !button;
-            self.emulator.borrow_mut().set_controller_input(0, self.controller_state);
+        self.update_keys(event, false);
+    }
+    
+    // Apply a partial JSON configuration, e.g. {"input": {"player1": {"a": "K"}}},
+    // without restarting, and save it to localStorage. Returns the names of
+    // changed settings that only take effect after a reset.
+    #[wasm_bindgen]
+    pub fn apply_config_json(&mut self, json: &str) -> Result<js_sys::Array, JsValue> {
+        let mut config = self.config.merge_json(json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to apply config: {}", e)))?;
+        // The active input profile isn't serialized; keep it if it still exists
+        let _ = config.input.set_active_profile(self.config.input.active_profile());
+        let reset_required = self.set_config(config);
+        Ok(reset_required.into_iter().map(JsValue::from_str).collect())
+    }
+    
+    // Bind a browser key (KeyboardEvent.key or .code) to a controller
+    // button ("a", "start", "up") of player 0, or 1 when given. Saved to
+    // localStorage with the rest of the configuration.
+    #[wasm_bindgen]
+    pub fn set_key_binding(&mut self, js_key: &str, snes_button: &str, player: Option<u8>) -> Result<(), JsValue> {
+        let mut config = self.config.clone();
+        config.input.set_key_binding(player.unwrap_or(0), snes_button, &binding_key_name(js_key))
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.set_config(config);
+        Ok(())
+    }
+    
+    // Switch to a named [input.profiles] entry, or back to the base
+    // bindings with no name
+    #[wasm_bindgen]
+    pub fn set_input_profile(&mut self, name: Option<String>) -> Result<(), JsValue> {
+        let mut config = self.config.clone();
+        config.input.set_active_profile(name.as_deref())
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.set_config(config);
+        Ok(())
+    }
+    
+    #[wasm_bindgen]
+    pub fn input_profile(&self) -> Option<String> {
+        self.config.input.active_profile().map(str::to_string)
+    }
+    
+    // Key bindings of both controllers: {"player1": {"up": "Up", ...}, "player2": {...}}
+    #[wasm_bindgen]
+    pub fn get_bindings_json(&self) -> String {
+        bindings_json(&self.config)
+    }
+    
+    // Restore the default configuration and forget the saved one
+    #[wasm_bindgen]
+    pub fn reset_config(&mut self) {
+        self.set_config(Config::default());
+        if let Some(storage) = local_storage() {
+            let _ = storage.remove_item(CONFIG_STORAGE_KEY);
         }
     }
     
+    // Current configuration as JSON
+    #[wasm_bindgen]
+    pub fn config_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.config).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+    
     #[wasm_bindgen]
     pub fn save_state(&self) -> Result<Vec<u8>, JsValue> {
         use crate::savestate::SaveState;
@@ -176,51 +286,263 @@ impl WasmEmulator {
     
     #[wasm_bindgen]
     pub fn get_fps(&self) -> f64 {
-        60.0 // TODO: Implement actual FPS calculation
+        self.fps.fps()
     }
     
-    fn render_frame(&mut self) -> Result<(), JsValue> {
-        let emulator = self.emulator.borrow();
-        let frame = emulator.get_frame_buffer();
-        
-        // Convert RGB565 to RGBA8888
-        for (i, chunk) in frame.chunks_exact(2).enumerate() {
-            if i >= 256 * 224 {
-                break;
-            }
-            
-            let pixel = u16::from_le_bytes([chunk[0], chunk[1]]);
-            let r = ((pixel >> 11) & 0x1F) as u8;
-            let g = ((pixel >> 5) & 0x3F) as u8;
-            let b = (pixel & 0x1F) as u8;
-            
-            // Expand to 8-bit
-            let idx = i * 4;
-            self.frame_buffer[idx] = (r << 3) | (r >> 2);
-            self.frame_buffer[idx + 1] = (g << 2) | (g >> 4);
-            self.frame_buffer[idx + 2] = (b << 3) | (b >> 2);
-            self.frame_buffer[idx + 3] = 255;
+    // Output volume from 0.0 to 2.0, 1.0 being the DSP's own level; saved
+    // to localStorage as audio.master_volume
+    #[wasm_bindgen]
+    pub fn set_volume(&mut self, volume: f32) {
+        let mut config = self.config.clone();
+        config.audio.master_volume = Volume::new(volume).percent();
+        self.set_config(config);
+    }
+    
+    #[wasm_bindgen]
+    pub fn volume(&self) -> f32 {
+        self.emulator.borrow().volume()
+    }
+    
+    #[wasm_bindgen]
+    pub fn set_muted(&mut self, muted: bool) {
+        let mut config = self.config.clone();
+        config.audio.muted = muted;
+        self.set_config(config);
+    }
+    
+    #[wasm_bindgen]
+    pub fn is_muted(&self) -> bool {
+        self.emulator.borrow().is_muted()
+    }
+    
+    // Emulator::metrics as JSON (cycles, instructions, frames, DMA bytes
+    // and the frame-time histogram when enabled)
+    #[wasm_bindgen]
+    pub fn metrics_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.emulator.borrow().metrics()).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+    
+    #[wasm_bindgen]
+    pub fn set_frame_timing(&mut self, enabled: bool) {
+        self.emulator.borrow_mut().set_frame_timing(enabled);
+    }
+    
+    // [strong, weak] motor levels for `port`, for the page to pass to
+    // the Gamepad API's vibrationActuator
+    #[wasm_bindgen]
+    pub fn rumble_level(&self, port: u8) -> Vec<f32> {
+        let (strong, weak) = self.emulator.borrow().input().rumble().level(port);
+        vec![strong, weak]
+    }
+    
+    // Switch to `config` and save it, releasing held buttons when the
+    // bindings changed. Returns settings that need a reset.
+    fn set_config(&mut self, config: Config) -> Vec<&'static str> {
+        let reset_required = self.config.reset_required(&config);
+        if self.config.changes(&config).input {
+            self.controller_state = [0; 2];
+            let mut emulator = self.emulator.borrow_mut();
+            emulator.set_controller_input(0, 0);
+            emulator.set_controller_input(1, 0);
+        }
+        self.config = config;
+        apply_audio(&self.config, &mut self.emulator.borrow_mut());
+        save_config(&self.config);
+        reset_required
+    }
+    
+    fn update_keys(&mut self, event: &KeyboardEvent, pressed: bool) {
+        let name = key_name(&event.key(), &event.code());
+        update_controllers(&self.config, &mut self.controller_state, &mut self.emulator.borrow_mut(), &name, pressed);
+    }
+}
+
+// The page's canvas. Its height follows the frames' (224 or 239 lines);
+// the page's CSS letterboxes it.
+struct CanvasVideo {
+    canvas: HtmlCanvasElement,
+    ctx: web_sys::CanvasRenderingContext2d,
+    frame_buffer: Vec<u8>,
+    // Follows config.video.frame_blend
+    blender: FrameBlender,
+}
+
+impl VideoSink for CanvasVideo {
+    fn present_frame(&mut self, frame: &Frame) -> crate::Result<()> {
+        // Convert to RGBA8888 for the canvas
+        let lines = convert_frame(frame, &mut self.frame_buffer);
+        if self.canvas.height() != lines {
+            self.canvas.set_height(lines);
         }
+        self.blender.apply(&mut self.frame_buffer);
         
-        // Create ImageData
         let image_data = ImageData::new_with_u8_clamped_array(
             wasm_bindgen::Clamped(&self.frame_buffer),
-            256,
-        )?;
-        
-        // Draw to canvas
-        self.ctx.put_image_data(&image_data, 0.0, 0.0)?;
-        
-        Ok(())
+            frame.width as u32,
+        ).map_err(from_js)?;
+        self.ctx.put_image_data(&image_data, 0.0, 0.0).map_err(from_js)
     }
-    
-    fn process_audio(&self, _audio_ctx: &web_sys::AudioContext) -> Result<(), JsValue> {
-        // TODO: Implement audio processing
-        // For now, just return Ok
+}
+
+// Sound on the page. Playback through the AudioContext isn't written yet
+// (the worker build hands samples to an AudioWorklet instead), so chunks
+// are dropped.
+struct WebAudio {
+    // None when the browser refused to create one
+    _context: Option<web_sys::AudioContext>,
+}
+
+impl AudioSink for WebAudio {
+    fn queue_audio(&mut self, _chunk: &AudioChunk) -> crate::Result<()> {
         Ok(())
     }
 }
 
+// Browser key (KeyboardEvent.key and .code) to the key names used by
+// config bindings ("Up", "Return", "RShift", "Z")
+fn key_name(key: &str, code: &str) -> String {
+    match key {
+        "ArrowUp" => "Up".to_string(),
+        "ArrowDown" => "Down".to_string(),
+        "ArrowLeft" => "Left".to_string(),
+        "ArrowRight" => "Right".to_string(),
+        "Enter" => "Return".to_string(),
+        " " => "Space".to_string(),
+        "Shift" if code == "ShiftLeft" => "LShift".to_string(),
+        "Shift" => "RShift".to_string(),
+        "Control" if code == "ControlLeft" => "LCtrl".to_string(),
+        "Control" => "RCtrl".to_string(),
+        key => key.to_uppercase(),
+    }
+}
+
+// Binding name for a key given either as KeyboardEvent.key ("ArrowUp", "z")
+// or as KeyboardEvent.code ("KeyZ", "ShiftLeft")
+fn binding_key_name(js_key: &str) -> String {
+    match js_key {
+        "ShiftLeft" => "LShift".to_string(),
+        "ShiftRight" => "RShift".to_string(),
+        "ControlLeft" => "LCtrl".to_string(),
+        "ControlRight" => "RCtrl".to_string(),
+        code if code.len() == 4 && code.starts_with("Key") => code[3..].to_string(),
+        code if code.len() == 6 && code.starts_with("Digit") => code[5..].to_string(),
+        key => key_name(key, ""),
+    }
+}
+
+fn bindings_json(config: &Config) -> String {
+    serde_json::json!({
+        "player1": config.input.player1,
+        "player2": config.input.player2,
+    })
+    .to_string()
+}
+
+// The configured volume, output filter and DSP mixing settings
+// Convert a frame to RGBA8888 in `rgba`, sized to its 224 or 239 lines;
+// returns the line count for the canvas
+fn convert_frame(frame: &Frame, rgba: &mut Vec<u8>) -> u32 {
+    rgba.resize(frame.width * frame.height * 4, 0);
+    pixel::convert_to_rgba(frame.format, frame.pixels, rgba);
+    frame.height as u32
+}
+
+// Errors across the sink traits, which use the crate's error type
+fn from_js(error: JsValue) -> EmulatorError {
+    EmulatorError::video(format!("{:?}", error))
+}
+
+fn to_js(error: EmulatorError) -> JsValue {
+    JsValue::from_str(&error.to_string())
+}
+
+fn apply_audio(config: &Config, emulator: &mut Emulator) {
+    let volume = Volume::from_config(&config.audio);
+    emulator.set_volume(volume.level());
+    emulator.set_muted(volume.is_muted());
+    emulator.apply_audio_config(&config.audio);
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+// Configuration saved by an earlier visit, or the defaults
+fn saved_config() -> Config {
+    local_storage()
+        .and_then(|storage| storage.get_item(CONFIG_STORAGE_KEY).ok().flatten())
+        .and_then(|json| Config::default().merge_json(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(config: &Config) {
+    if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(config)) {
+        let _ = storage.set_item(CONFIG_STORAGE_KEY, &json);
+    }
+}
+
+// Press or release the buttons bound to a key on each port
+fn update_controllers(config: &Config, state: &mut [u16; 2], emulator: &mut Emulator, key: &str, pressed: bool) {
+    let buttons = config.input.buttons_for_key(key);
+    if buttons == [0; 2] {
+        return;
+    }
+    for (port, button) in buttons.into_iter().enumerate() {
+        if pressed {
+            state[port] |= button;
+        } else {
+            state[port] &= !button;
+        }
+        emulator.set_controller_input(port as u8, state[port]);
+    }
+}
+
+// Frames per second over windows of about a second
+struct FpsCounter {
+    // Timestamp (ms) and frame count at the start of the current window
+    window: (f64, u64),
+    fps: f64,
+}
+
+impl FpsCounter {
+    fn new() -> Self {
+        Self { window: (js_sys::Date::now(), 0), fps: 0.0 }
+    }
+
+    fn update(&mut self, now: f64, frames: u64) {
+        let (start, start_frames) = self.window;
+        if now - start >= 1000.0 || frames < start_frames {
+            self.fps = frames.saturating_sub(start_frames) as f64 * 1000.0 / (now - start).max(1.0);
+            self.window = (now, frames);
+        }
+    }
+
+    fn fps(&self) -> f64 {
+        self.fps
+    }
+}
+
+fn loaded_title(emulator: &Emulator) -> String {
+    let title = emulator.get_rom_info()
+        .map(|info| info.title.clone())
+        .unwrap_or_else(|| "Unknown".to_string());
+    console::log_1(&format!("Loaded ROM: {}", title).into());
+    title
+}
+
+// Let the browser render and handle events before continuing
+async fn yield_to_browser() -> Result<(), JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let scheduled = web_sys::window()
+            .map(|window| window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, 0).is_ok())
+            .unwrap_or(false);
+        if !scheduled {
+            let _ = resolve.call0(&JsValue::NULL);
+        }
+    });
+    wasm_bindgen_futures::JsFuture::from(promise).await.map(|_| ())
+}
+
 // Module initialization
 #[wasm_bindgen(start)]
 pub fn main() {
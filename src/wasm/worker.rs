@@ -0,0 +1,243 @@
+// Emulator for a dedicated Web Worker. The page transfers its canvas with
+// transferControlToOffscreen() and talks to the worker through messages
+// (see web/worker.js and web/worker-client.js); there is no DOM here, so
+// frames go to an OffscreenCanvas and audio samples are handed back to be
+// posted to an AudioWorklet on the main thread.
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{ImageData, OffscreenCanvas, OffscreenCanvasRenderingContext2d};
+
+use super::{apply_audio, binding_key_name, bindings_json, convert_frame, from_js, key_name, loaded_title, to_js, update_controllers, FpsCounter};
+use crate::apu::volume::Volume;
+use crate::config::Config;
+use crate::embed::Frame;
+use crate::emulator::Emulator;
+use crate::sink::VideoSink;
+use crate::savestate::SaveState;
+
+#[wasm_bindgen]
+pub struct WorkerEmulator {
+    emulator: Emulator,
+    video: OffscreenVideo,
+    controller_state: [u16; 2],
+    config: Config,
+    fps: FpsCounter,
+}
+
+#[wasm_bindgen]
+impl WorkerEmulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: OffscreenCanvas) -> Result<WorkerEmulator, JsValue> {
+        #[cfg(feature = "panic-hook")]
+        console_error_panic_hook::set_once();
+
+        canvas.set_width(256);
+        canvas.set_height(224);
+        let ctx = canvas
+            .get_context("2d")?
+            .ok_or("Failed to get 2D context")?
+            .dyn_into::<OffscreenCanvasRenderingContext2d>()?;
+
+        let mut emulator = Emulator::new()
+            .map_err(|e| JsValue::from_str(&format!("Failed to create emulator: {}", e)))?;
+        // worker-client.js sends the saved configuration afterwards; until
+        // then the defaults apply, low-pass filter included
+        let config = Config::default();
+        apply_audio(&config, &mut emulator);
+
+        Ok(WorkerEmulator {
+            emulator,
+            video: OffscreenVideo { canvas, ctx, frame_buffer: vec![0; 256 * 224 * 4] },
+            controller_state: [0; 2],
+            config,
+            fps: FpsCounter::new(),
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn load_rom(&mut self, rom_data: &[u8]) -> Result<String, JsValue> {
+        self.emulator
+            .load_rom(rom_data)
+            .map_err(|e| JsValue::from_str(&format!("Failed to load ROM: {}", e)))?;
+        Ok(loaded_title(&self.emulator))
+    }
+
+    // Run the built-in self test (color bars, input tester, tone)
+    #[wasm_bindgen]
+    pub fn load_boot_rom(&mut self) -> Result<String, JsValue> {
+        self.load_rom(&crate::boot_rom::build())
+    }
+
+    #[wasm_bindgen]
+    pub fn reset(&mut self) {
+        let _ = self.emulator.reset();
+    }
+
+    // Run and draw one frame
+    #[wasm_bindgen]
+    pub fn run_frame(&mut self) -> Result<(), JsValue> {
+        let started = js_sys::Date::now();
+        self.emulator.step_frame()
+            .map_err(|e| JsValue::from_str(&format!("Emulation error: {}", e)))?;
+        let now = js_sys::Date::now();
+        self.emulator.record_frame_time(now - started);
+        self.fps.update(now, self.emulator.get_frame_count());
+
+        self.video.present_frame(&self.emulator.frame()).map_err(to_js)
+    }
+
+    // Interleaved stereo samples (32 kHz) produced since the last call
+    #[wasm_bindgen]
+    pub fn take_audio_samples(&mut self) -> Vec<f32> {
+        self.emulator.get_audio_samples()
+    }
+
+    // Same as WasmEmulator::set_volume; worker-client.js saves the change
+    #[wasm_bindgen]
+    pub fn set_volume(&mut self, volume: f32) {
+        let mut config = self.config.clone();
+        config.audio.master_volume = Volume::new(volume).percent();
+        self.set_config(config);
+    }
+
+    #[wasm_bindgen]
+    pub fn set_muted(&mut self, muted: bool) {
+        let mut config = self.config.clone();
+        config.audio.muted = muted;
+        self.set_config(config);
+    }
+
+    // Key events are forwarded by the page as KeyboardEvent.key and .code
+    #[wasm_bindgen]
+    pub fn set_key(&mut self, key: &str, code: &str, pressed: bool) {
+        update_controllers(&self.config, &mut self.controller_state, &mut self.emulator, &key_name(key, code), pressed);
+    }
+
+    // Set a port's buttons directly, e.g. from the Gamepad API
+    #[wasm_bindgen]
+    pub fn set_controller(&mut self, port: u8, buttons: u16) {
+        self.emulator.set_controller_input(port, buttons);
+    }
+
+    // Same as WasmEmulator::apply_config_json, but without localStorage
+    // (workers have none); worker-client.js saves the configuration
+    #[wasm_bindgen]
+    pub fn apply_config_json(&mut self, json: &str) -> Result<js_sys::Array, JsValue> {
+        let mut config = self.config.merge_json(json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to apply config: {}", e)))?;
+        // The active input profile isn't serialized; keep it if it still exists
+        let _ = config.input.set_active_profile(self.config.input.active_profile());
+        let reset_required = self.set_config(config);
+        Ok(reset_required.into_iter().map(JsValue::from_str).collect())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_key_binding(&mut self, js_key: &str, snes_button: &str, player: Option<u8>) -> Result<(), JsValue> {
+        let mut config = self.config.clone();
+        config.input.set_key_binding(player.unwrap_or(0), snes_button, &binding_key_name(js_key))
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.set_config(config);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_input_profile(&mut self, name: Option<String>) -> Result<(), JsValue> {
+        let mut config = self.config.clone();
+        config.input.set_active_profile(name.as_deref())
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        self.set_config(config);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn input_profile(&self) -> Option<String> {
+        self.config.input.active_profile().map(str::to_string)
+    }
+
+    #[wasm_bindgen]
+    pub fn get_bindings_json(&self) -> String {
+        bindings_json(&self.config)
+    }
+
+    #[wasm_bindgen]
+    pub fn reset_config(&mut self) {
+        self.set_config(Config::default());
+    }
+
+    #[wasm_bindgen]
+    pub fn config_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.config).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    #[wasm_bindgen]
+    pub fn save_state(&self) -> Result<Vec<u8>, JsValue> {
+        let state = self.emulator
+            .save_state()
+            .map_err(|e| JsValue::from_str(&format!("Failed to save state: {}", e)))?;
+        SaveState::to_bytes(&state)
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize state: {}", e)))
+    }
+
+    #[wasm_bindgen]
+    pub fn load_state(&mut self, state_data: &[u8]) -> Result<(), JsValue> {
+        let state = SaveState::from_bytes(state_data)
+            .map_err(|e| JsValue::from_str(&format!("Failed to deserialize state: {}", e)))?;
+        self.emulator
+            .load_state(&state)
+            .map_err(|e| JsValue::from_str(&format!("Failed to load state: {}", e)))
+    }
+
+    #[wasm_bindgen]
+    pub fn get_fps(&self) -> f64 {
+        self.fps.fps()
+    }
+
+    #[wasm_bindgen]
+    pub fn metrics_json(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.emulator.metrics()).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    #[wasm_bindgen]
+    pub fn set_frame_timing(&mut self, enabled: bool) {
+        self.emulator.set_frame_timing(enabled);
+    }
+
+    #[wasm_bindgen]
+    pub fn rumble_level(&self, port: u8) -> Vec<f32> {
+        let (strong, weak) = self.emulator.input().rumble().level(port);
+        vec![strong, weak]
+    }
+}
+
+impl WorkerEmulator {
+    fn set_config(&mut self, config: Config) -> Vec<&'static str> {
+        let reset_required = self.config.reset_required(&config);
+        if self.config.changes(&config).input {
+            self.controller_state = [0; 2];
+            self.emulator.set_controller_input(0, 0);
+            self.emulator.set_controller_input(1, 0);
+        }
+        self.config = config;
+        apply_audio(&self.config, &mut self.emulator);
+        reset_required
+    }
+}
+
+// The transferred canvas, resized to the frames' height like WasmEmulator's
+struct OffscreenVideo {
+    canvas: OffscreenCanvas,
+    ctx: OffscreenCanvasRenderingContext2d,
+    frame_buffer: Vec<u8>,
+}
+
+impl VideoSink for OffscreenVideo {
+    fn present_frame(&mut self, frame: &Frame) -> crate::Result<()> {
+        let lines = convert_frame(frame, &mut self.frame_buffer);
+        if self.canvas.height() != lines {
+            self.canvas.set_height(lines);
+        }
+        let image_data = ImageData::new_with_u8_clamped_array(wasm_bindgen::Clamped(&self.frame_buffer), frame.width as u32)
+            .map_err(from_js)?;
+        self.ctx.put_image_data(&image_data, 0.0, 0.0).map_err(from_js)
+    }
+}
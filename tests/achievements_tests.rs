@@ -0,0 +1,92 @@
+use ccsnes::achievements::{AchievementHook, HookEvent, MemoryPeek, MemoryPoll};
+use ccsnes::memory::Bus;
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+use std::sync::{Arc, Mutex};
+
+// LoROM idling with BRA *
+fn idle_rom() -> Vec<u8> {
+    LoRom::new("ACHIEVEMENT TEST", &[0x80, 0xFE]).at(0x8100, &[0xDE, 0xAD, 0xBE, 0xEF]).build()
+}
+
+#[derive(Default)]
+struct Log {
+    frames: Vec<(u64, Vec<u8>, Vec<u8>)>,
+    events: Vec<HookEvent>,
+    rom_bytes: [u8; 4],
+}
+
+struct Recorder(Arc<Mutex<Log>>);
+
+impl AchievementHook for Recorder {
+    fn addresses(&self) -> Vec<u32> {
+        vec![0x7E0020, 0x7E0021, 0x000022]
+    }
+
+    fn on_frame(&mut self, frame: u64, poll: &MemoryPoll, memory: &MemoryPeek) {
+        let mut log = self.0.lock().unwrap();
+        log.frames.push((frame, poll.values().to_vec(), poll.previous().to_vec()));
+        memory.read_block(0x008100, &mut log.rom_bytes);
+    }
+
+    fn on_event(&mut self, event: HookEvent) {
+        self.0.lock().unwrap().events.push(event);
+    }
+}
+
+#[test]
+fn test_memory_poll_reads_in_batches() {
+    let mut bus = Bus::new();
+    bus.write8(0x7E1000, 0x34);
+    bus.write8(0x7E1001, 0x12);
+    let mut poll = MemoryPoll::new(vec![0x7E1000, 0x7E1001, 0x002137]);
+    poll.prime(&bus);
+    assert_eq!(poll.value16(0), Some(0x1234));
+    // I/O registers are never touched
+    assert_eq!(poll.value(2), Some(0));
+    assert_eq!(poll.changed().count(), 0);
+
+    bus.write8(0x7E1001, 0x56);
+    poll.poll(&bus);
+    assert_eq!(poll.changed().collect::<Vec<_>>(), vec![1]);
+    assert_eq!(poll.previous()[1], 0x12);
+    assert_eq!(poll.value(3), None);
+}
+
+#[test]
+fn test_achievement_hook_sees_frames_and_events() {
+    let log = Arc::new(Mutex::new(Log::default()));
+    let mut emulator = Emulator::new().unwrap();
+    emulator.set_achievement_hook(Box::new(Recorder(log.clone())));
+    emulator.load_rom(&idle_rom()).unwrap();
+    assert_eq!(log.lock().unwrap().events, vec![HookEvent::Reset, HookEvent::RomLoaded]);
+
+    emulator.write8(0x7E0020, 5);
+    emulator.step_frame().unwrap();
+    emulator.write8(0x7E0021, 9);
+    emulator.write8(0x7E0022, 1);
+    emulator.step_frame().unwrap();
+    {
+        let log = log.lock().unwrap();
+        assert_eq!(log.frames.len(), 2);
+        assert_eq!(log.frames[0].1, vec![5, 0, 0]);
+        assert_eq!(log.frames[0].2, vec![0, 0, 0]);
+        // $000022 mirrors $7E0022
+        assert_eq!(log.frames[1].1, vec![5, 9, 1]);
+        assert_eq!(log.frames[1].2, vec![5, 0, 0]);
+        assert!(log.frames[1].0 > log.frames[0].0);
+        assert_eq!(log.rom_bytes, [0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+
+    let state = emulator.save_state().unwrap();
+    emulator.load_state(&state).unwrap();
+    assert_eq!(log.lock().unwrap().events.last(), Some(&HookEvent::StateLoaded));
+
+    // Switching games reports the old one leaving first
+    emulator.load_rom(&idle_rom()).unwrap();
+    assert_eq!(log.lock().unwrap().events[3..], [HookEvent::RomUnloaded, HookEvent::Reset, HookEvent::RomLoaded]);
+
+    assert!(emulator.take_achievement_hook().is_some());
+    emulator.step_frame().unwrap();
+    assert_eq!(log.lock().unwrap().frames.len(), 2);
+}
@@ -1,4 +1,14 @@
+use ccsnes::apu::dsp::Interpolation;
+use ccsnes::apu::filter::{LowPass, DEFAULT_CUTOFF_HZ};
+use ccsnes::apu::reconcile::{AudioReconciler, FADE_FRAMES};
+use ccsnes::apu::spc_file::{self, SpcTags, SPC_FILE_SIZE};
+use ccsnes::apu::volume::{Volume, MAX_VOLUME};
 use ccsnes::apu::Apu;
+use ccsnes::config::AudioConfig;
+use ccsnes::debug::apu_ports::{PortFilter, PortSide};
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+use std::collections::VecDeque;
 
 #[test]
 fn test_apu_communication_ports() {
@@ -44,4 +54,426 @@ fn test_apu_dsp_register_access() {
     // The APU generates samples at 32kHz, so we may need more steps
     // to get samples in the buffer
     assert!(samples.is_empty() || samples.len() > 0);
-}
\ No newline at end of file
+}
+fn stereo(values: impl Iterator<Item = f32>) -> Vec<f32> {
+    values.flat_map(|v| [v, v]).collect()
+}
+
+#[test]
+fn test_reconciler_passes_continuous_audio_through() {
+    let mut reconciler = AudioReconciler::new();
+    let mut queue = VecDeque::new();
+    for block in 0..3 {
+        let samples = stereo((0..100).map(|i| (block * 100 + i) as f32));
+        reconciler.queue(&mut queue, &samples, 0);
+    }
+    // One frame of delay carries across blocks, nothing is lost or repeated
+    let expected = stereo(std::iter::once(0.0).chain((0..299).map(|i| i as f32)));
+    assert_eq!(queue.len(), expected.len());
+    assert!(queue.iter().zip(&expected).all(|(a, b)| (a - b).abs() < 1e-4));
+}
+
+#[test]
+fn test_reconciler_corrects_queue_level() {
+    let mut reconciler = AudioReconciler::new();
+    let samples = stereo((0..1000).map(|_| 0.25));
+
+    // Over-full queues get fewer frames, under-full ones more
+    let mut queue: VecDeque<f32> = vec![0.0; 8000].into();
+    reconciler.queue(&mut queue, &samples, 4000);
+    let added = queue.len() - 8000;
+    assert!(added < samples.len() && added >= samples.len() - 20, "{} samples", added);
+
+    let mut reconciler = AudioReconciler::new();
+    let mut queue = VecDeque::new();
+    reconciler.queue(&mut queue, &samples, 4000);
+    assert!(queue.len() > samples.len());
+}
+
+#[test]
+fn test_reconciler_crossfades_discontinuity() {
+    let mut reconciler = AudioReconciler::new();
+    let mut queue = VecDeque::new();
+    reconciler.queue(&mut queue, &stereo((0..512).map(|_| 1.0)), 0);
+    let before = queue.len();
+
+    reconciler.discontinuity();
+    reconciler.queue(&mut queue, &stereo((0..512).map(|_| -1.0)), 0);
+    assert_eq!(queue.len(), before + (512 - FADE_FRAMES) * 2);
+    // (skipping the initial frame, which starts from silence)
+    let samples: Vec<f32> = queue.iter().skip(2).copied().collect();
+    let largest_step = samples.windows(3).map(|w| (w[2] - w[0]).abs()).fold(0.0, f32::max);
+    assert!(largest_step < 2.0 / FADE_FRAMES as f32 + 1e-3, "step {}", largest_step);
+    assert_eq!(*queue.back().unwrap(), -1.0);
+
+    // With nothing queued, the new audio fades in from silence
+    let mut queue = VecDeque::new();
+    reconciler.discontinuity();
+    reconciler.queue(&mut queue, &stereo((0..512).map(|_| 1.0)), 0);
+    assert!(queue[0] < 0.01);
+    assert_eq!(queue.len(), 1024);
+}
+
+#[test]
+fn test_load_state_starts_new_audio_epoch() {
+    let mut emulator = Emulator::new().unwrap();
+    let epoch = emulator.audio_epoch();
+    let state = emulator.save_state().unwrap();
+    emulator.load_state(&state).unwrap();
+    assert_eq!(emulator.audio_epoch(), epoch + 1);
+    assert!(emulator.get_audio_samples().is_empty());
+}
+
+#[test]
+fn test_apu_catch_up_matches_lockstep() {
+    let mut lockstep = Apu::new();
+    let mut batched = Apu::new();
+    for cycles in [3u32, 7, 2, 95, 4, 300, 6] {
+        for _ in 0..cycles {
+            lockstep.step();
+        }
+        batched.schedule(cycles);
+        assert!(batched.pending_cycles() < ccsnes::apu::MAX_PENDING_CYCLES);
+    }
+    batched.catch_up();
+    assert_eq!(batched.pending_cycles(), 0);
+    assert_eq!(batched.save_state(), lockstep.save_state());
+}
+
+#[test]
+fn test_apu_port_access_catches_up() {
+    let mut apu = Apu::new();
+    apu.schedule(10);
+    assert_eq!(apu.pending_cycles(), 10);
+    apu.read_port(0);
+    assert_eq!(apu.pending_cycles(), 0);
+
+    apu.schedule(10);
+    apu.write_port(0, 0x55);
+    assert_eq!(apu.pending_cycles(), 0);
+}
+
+#[test]
+fn test_apu_clock_ratio_scales_schedule() {
+    let mut apu = Apu::new();
+    apu.set_max_pending(u64::MAX);
+    assert_eq!(apu.clock_ratio(), 1.0);
+    apu.schedule(10);
+    assert_eq!(apu.pending_cycles(), 10);
+
+    // Fractions of a cycle carry over to the next batch
+    apu.catch_up();
+    apu.set_clock_ratio(1.5).unwrap();
+    apu.schedule(3);
+    assert_eq!(apu.pending_cycles(), 4);
+    apu.schedule(1);
+    assert_eq!(apu.pending_cycles(), 6);
+    apu.catch_up();
+    apu.set_clock_ratio(0.75).unwrap();
+    for _ in 0..100 {
+        apu.schedule(1);
+    }
+    assert_eq!(apu.pending_cycles(), 75);
+
+    // Kept across resets; out-of-range ratios are refused
+    apu.reset();
+    assert_eq!(apu.clock_ratio(), 0.75);
+    for ratio in [0.0, 0.49, 2.5, f64::NAN] {
+        assert!(apu.set_clock_ratio(ratio).is_err(), "{}", ratio);
+    }
+    assert_eq!(apu.clock_ratio(), 0.75);
+}
+
+#[test]
+fn test_apu_clock_ratio_changes_sound_timing() {
+    use ccsnes::embed::EmulatorConfig;
+
+    let run = |ratio: f64| {
+        let mut emulator = EmulatorConfig::new().apu_clock_ratio(ratio).build().unwrap();
+        emulator.load_rom(&ccsnes::boot_rom::build()).unwrap();
+        emulator.step_frame().unwrap();
+        (emulator.take_audio().samples.len(), emulator.apu().spc700().cycles())
+    };
+    let (stock_samples, stock_cycles) = run(1.0);
+    let (fast_samples, fast_cycles) = run(1.25);
+    assert!(fast_cycles > stock_cycles, "{} vs {}", fast_cycles, stock_cycles);
+    assert!(fast_samples > stock_samples, "{} vs {}", fast_samples, stock_samples);
+    assert!(EmulatorConfig::new().apu_clock_ratio(3.0).build().is_err());
+}
+
+// LoROM polling APU port 0: LDA $2140; STA $0000; BRA loop
+fn port_polling_rom() -> Vec<u8> {
+    LoRom::new("APU PORT TEST", &[0xAD, 0x40, 0x21, 0x8D, 0x00, 0x00, 0x80, 0xF8]).build()
+}
+
+#[test]
+fn test_emulator_apu_batches_match_lockstep() {
+    let mut batched = Emulator::new().unwrap();
+    batched.load_rom(&port_polling_rom()).unwrap();
+    let mut lockstep = Emulator::new().unwrap();
+    lockstep.load_rom(&port_polling_rom()).unwrap();
+    lockstep.apu_mut().set_max_pending(1);
+
+    for _ in 0..3 {
+        batched.step_frame().unwrap();
+        lockstep.step_frame().unwrap();
+        // Every frame ends with the APU caught up
+        assert_eq!(batched.apu().pending_cycles(), 0);
+        assert_eq!(batched.apu().save_state(), lockstep.apu().save_state());
+        assert_eq!(batched.save_state().unwrap().memory, lockstep.save_state().unwrap().memory);
+    }
+}
+
+#[test]
+fn test_apu_port_filter_parse() {
+    assert_eq!(PortFilter::parse("").unwrap(), PortFilter::default());
+    let filter = PortFilter::parse("0, 1, spc, write").unwrap();
+    assert_eq!(filter.ports, 0x03);
+    assert!(filter.matches(PortSide::Spc, true, 1));
+    assert!(!filter.matches(PortSide::Cpu, true, 1));
+    assert!(!filter.matches(PortSide::Spc, false, 0));
+    assert!(!filter.matches(PortSide::Spc, true, 2));
+    assert!(PortFilter::parse("4").is_err());
+    assert!(PortFilter::parse("dsp").is_err());
+}
+
+#[test]
+fn test_apu_port_trace_records_cpu_accesses() {
+    let mut apu = Apu::new();
+    apu.write_port(0, 0x11);
+    assert!(apu.port_trace().is_empty());
+
+    apu.port_trace_mut().set_enabled(true);
+    apu.write_port(1, 0xCC);
+    for _ in 0..5 {
+        apu.read_port(2);
+    }
+    let entries: Vec<_> = apu.port_trace().entries().cloned().collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!((entries[0].side, entries[0].write, entries[0].port, entries[0].value), (PortSide::Cpu, true, 1, 0xCC));
+    // The polling reads collapse into one entry
+    assert_eq!((entries[1].write, entries[1].port, entries[1].repeats), (false, 2, 4));
+    assert!(apu.port_trace().dump().contains("write $2141 = $CC"));
+
+    apu.port_trace_mut().set_max_entries(1);
+    assert_eq!(apu.port_trace().len(), 1);
+    assert_eq!(apu.port_trace().dropped(), 1);
+}
+
+#[test]
+fn test_apu_port_trace_sees_both_sides() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&port_polling_rom()).unwrap();
+    emulator.apu_mut().port_trace_mut().set_enabled(true);
+    emulator.step_frame().unwrap();
+
+    let trace = emulator.apu().port_trace();
+    let cpu_read = trace.entries().find(|entry| entry.side == PortSide::Cpu).unwrap();
+    assert!(!cpu_read.write);
+    assert_eq!(cpu_read.port, 0);
+    // The CPU side carries the 24-bit address of the LDA
+    assert_eq!(cpu_read.pc, 0x008000);
+    assert!(trace.entries().any(|entry| entry.side == PortSide::Spc));
+}
+
+#[test]
+fn test_dsp_registers_written_through_f2_f3() {
+    let mut apu = Apu::new();
+    // Main volume left, then channel 1's pitch high byte
+    apu.spc700_mut().write8(0x00F2, 0x0C);
+    apu.spc700_mut().write8(0x00F3, 0x7F);
+    apu.spc700_mut().write8(0x00F2, 0x13);
+    apu.spc700_mut().write8(0x00F3, 0x10);
+    apu.step();
+
+    let registers = apu.dsp().registers();
+    assert_eq!(registers[0x0C], 0x7F);
+    assert_eq!(registers[0x13], 0x10);
+    assert_eq!(apu.spc700().dsp_address(), 0x13);
+    assert_eq!(apu.spc700().read8(0x00F3), 0x10);
+
+    // States keep the decoded registers
+    let mut loaded = Apu::new();
+    loaded.load_state(&apu.save_state());
+    assert_eq!(loaded.dsp().registers()[0x0C], 0x7F);
+    assert_eq!(loaded.dsp().registers()[0x13], 0x10);
+}
+
+#[test]
+fn test_spc_export_layout() {
+    let mut apu = Apu::new();
+    apu.spc700_mut().write8(0x0200, 0xAB);
+    apu.spc700_mut().write8(0x00FA, 0x40);
+    apu.spc700_mut().write8(0x00F2, 0x5D);
+    apu.spc700_mut().write8(0x00F3, 0x02);
+    apu.step();
+    let registers = apu.spc700().registers();
+    let tags = SpcTags { song_title: "Opening".to_string(), ..SpcTags::for_game("  SPC TEST  ") };
+    let file = spc_file::export(&apu, &tags);
+
+    assert_eq!(file.len(), SPC_FILE_SIZE);
+    assert_eq!(&file[..33], b"SNES-SPC700 Sound File Data v0.30");
+    assert_eq!(file[0x21..0x25], [26, 26, 26, 30]);
+    assert_eq!(file[0x25..0x27], registers.pc.to_le_bytes());
+    assert_eq!(file[0x27..0x2C], [registers.a, registers.x, registers.y, registers.psw, registers.sp]);
+
+    // ID666 text tags, zero padded
+    assert_eq!(&file[0x2E..0x35], b"Opening");
+    assert_eq!(file[0x35], 0);
+    assert_eq!(&file[0x4E..0x56], b"SPC TEST");
+    assert_eq!(file[0x56], 0);
+    assert_eq!(&file[0xA9..0xAC], b"180");
+    assert_eq!(&file[0xAC..0xB1], b"10000");
+
+    // RAM with the I/O registers in place, then the DSP registers
+    assert_eq!(file[0x100 + 0x0200], 0xAB);
+    assert_eq!(file[0x100 + 0xF1], 0x80);
+    assert_eq!(file[0x100 + 0xF2], 0x5D);
+    assert_eq!(file[0x100 + 0xFA], 0x40);
+    assert_eq!(file[0x10100 + 0x5D], 0x02);
+    assert_eq!(file[0x101C0..], apu.spc700().ram()[0xFFC0..]);
+}
+
+#[test]
+fn test_emulator_spc_export_uses_rom_title() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&port_polling_rom()).unwrap();
+    emulator.step_frame().unwrap();
+    let file = emulator.export_spc();
+    assert_eq!(&file[0x4E..0x5B], b"APU PORT TEST");
+    assert_eq!(file[0x5B], 0);
+}
+#[test]
+fn test_volume_scales_and_clips_samples() {
+    let mut samples = vec![0.25, -0.5, 0.75];
+    Volume::new(0.5).apply(&mut samples);
+    assert_eq!(samples, vec![0.125, -0.25, 0.375]);
+
+    let mut samples = vec![0.25, -0.75];
+    Volume::new(MAX_VOLUME).apply(&mut samples);
+    assert_eq!(samples, vec![0.5, -1.0]);
+
+    // Out of range levels are clamped
+    assert_eq!(Volume::new(5.0).level(), MAX_VOLUME);
+    assert_eq!(Volume::new(-1.0).level(), 0.0);
+    assert_eq!(Volume::new(f32::NAN).level(), 0.0);
+}
+
+#[test]
+fn test_mute_keeps_the_level() {
+    let mut volume = Volume::new(1.5);
+    volume.set_muted(true);
+    let mut samples = vec![0.5, -0.5];
+    volume.apply(&mut samples);
+    assert_eq!(samples, vec![0.0, 0.0]);
+    assert_eq!(volume.percent(), 150);
+
+    volume.set_muted(false);
+    assert_eq!(volume.gain(), 1.5);
+}
+
+#[test]
+fn test_emulator_volume() {
+    let mut emulator = Emulator::new().unwrap();
+    assert_eq!(emulator.volume(), 1.0);
+    assert!(!emulator.is_muted());
+
+    emulator.set_volume(3.0);
+    emulator.set_muted(true);
+    emulator.reset().unwrap();
+    assert_eq!(emulator.volume(), MAX_VOLUME);
+    assert!(emulator.is_muted());
+}
+
+#[test]
+fn test_low_pass_passes_dc_and_cuts_highs() {
+    let mut filter = LowPass::new(DEFAULT_CUTOFF_HZ);
+    let dc = (0..200).map(|_| filter.process(0.5)).last().unwrap();
+    assert!((dc - 0.5).abs() < 0.001);
+
+    // A tone at the Nyquist frequency comes out quieter, more so with a
+    // lower cutoff
+    let nyquist_peak = |cutoff_hz| {
+        let mut filter = LowPass::new(cutoff_hz);
+        (0..200)
+            .map(|i| filter.process(if i % 2 == 0 { 1.0 } else { -1.0 }))
+            .skip(100)
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+    };
+    let peak = nyquist_peak(DEFAULT_CUTOFF_HZ);
+    assert!(peak < 0.7, "peak {}", peak);
+    assert!(nyquist_peak(2_000) < peak / 2.0);
+
+    assert_eq!(LowPass::new(100_000).cutoff_hz(), 16_000);
+    assert_eq!(LowPass::new(0).cutoff_hz(), 20);
+}
+
+#[test]
+fn test_apu_output_filter_setting() {
+    let mut apu = Apu::new();
+    assert_eq!(apu.output_filter(), None);
+    apu.set_output_filter(Some(5_000));
+    assert_eq!(apu.output_filter(), Some(5_000));
+    apu.reset();
+    assert_eq!(apu.output_filter(), Some(5_000));
+    apu.set_output_filter(None);
+    assert_eq!(apu.output_filter(), None);
+}
+
+#[test]
+fn test_dsp_interpolation() {
+    let mut apu = Apu::new();
+    let dsp = apu.dsp_mut();
+    assert_eq!(dsp.interpolation(), Interpolation::Gaussian);
+
+    // Gaussian weights sum to one and lean on the nearest samples
+    let flat = [1000; 4];
+    assert_eq!(dsp.interpolate(0, flat, 0), 1000);
+    assert_eq!(dsp.interpolate(0, flat, 2048), 1000);
+    let impulse = dsp.interpolate(0, [0, 1000, 0, 0], 0);
+    assert!(impulse > 500 && impulse < 1000, "impulse {}", impulse);
+
+    dsp.set_interpolation(Interpolation::Linear);
+    assert_eq!(dsp.interpolate(0, [0, 1000, 0, 0], 0), 1000);
+    assert_eq!(dsp.interpolate(0, [0, 0, 1000, 2000], 1024), 250);
+}
+
+#[test]
+fn test_dsp_voice_gain() {
+    let mut apu = Apu::new();
+    let dsp = apu.dsp_mut();
+    dsp.set_interpolation(Interpolation::Linear);
+    assert_eq!(dsp.voice_gain(3), 1.0);
+
+    dsp.set_voice_gain(3, 0.5);
+    assert_eq!(dsp.interpolate(3, [0, 1000, 0, 0], 0), 500);
+    assert_eq!(dsp.interpolate(2, [0, 1000, 0, 0], 0), 1000);
+
+    dsp.set_voice_gain(3, 5.0);
+    assert_eq!(dsp.voice_gain(3), 2.0);
+    dsp.set_voice_gain(3, f32::NAN);
+    assert_eq!(dsp.voice_gain(3), 0.0);
+    dsp.set_voice_gain(8, 0.5);
+    assert_eq!(dsp.voice_gain(8), 0.0);
+}
+
+#[test]
+fn test_emulator_applies_audio_config() {
+    let mut emulator = Emulator::new().unwrap();
+    let mut config = AudioConfig::default();
+    assert!(config.low_pass_filter);
+    assert_eq!(config.low_pass_cutoff_hz, DEFAULT_CUTOFF_HZ);
+    assert!(config.gaussian_interpolation);
+
+    config.low_pass_cutoff_hz = 6_000;
+    config.gaussian_interpolation = false;
+    config.voice_gain[7] = 0.0;
+    emulator.apply_audio_config(&config);
+    assert_eq!(emulator.apu().output_filter(), Some(6_000));
+    assert_eq!(emulator.apu().dsp().interpolation(), Interpolation::Linear);
+    assert_eq!(emulator.apu().dsp().voice_gain(7), 0.0);
+
+    config.low_pass_filter = false;
+    emulator.apply_audio_config(&config);
+    assert_eq!(emulator.apu().output_filter(), None);
+}
@@ -0,0 +1,67 @@
+use ccsnes::boot_rom;
+use ccsnes::prelude::Button;
+use ccsnes::Emulator;
+
+fn self_test(frames: usize) -> Emulator {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.set_lint(true);
+    emulator.load_rom(&boot_rom::build()).unwrap();
+    for _ in 0..frames {
+        emulator.step_frame().unwrap();
+    }
+    emulator
+}
+
+// Attribute byte of the sprite for the nth button in the ROM's table
+fn button_attributes(emulator: &Emulator, index: usize) -> u8 {
+    emulator.ppu().get_oam()[index * 4 + 3]
+}
+
+#[test]
+fn test_boot_rom_header() {
+    let emulator = self_test(0);
+    assert!(boot_rom::is_loaded(&emulator));
+    assert!(emulator.rom_warnings().is_empty(), "{:?}", emulator.rom_warnings());
+}
+
+#[test]
+fn test_boot_rom_sets_up_video() {
+    let emulator = self_test(10);
+    let cgram = emulator.ppu().get_cgram();
+    // Color 1 of BG palette 1 is the yellow bar
+    assert_eq!(u16::from_le_bytes([cgram[10], cgram[11]]), 0x03FF);
+    // Screen on, BG1 and sprites on the main screen
+    assert_eq!(emulator.ppu().registers.inidisp, 0x0F);
+    assert_eq!(emulator.ppu().registers.tm, 0x11);
+    // and the bars are drawn
+    let yellow = emulator.ppu().get_frame_buffer().chunks_exact(4).filter(|pixel| pixel[..3] == [248, 248, 0]).count();
+    assert!(yellow > 0);
+}
+
+#[test]
+fn test_boot_rom_input_tester() {
+    let mut emulator = self_test(10);
+    // Index 10 is the A button
+    assert_eq!(button_attributes(&emulator, 10), 0x30);
+    emulator.set_buttons(0, &[Button::A]);
+    emulator.step_frame().unwrap();
+    emulator.step_frame().unwrap();
+    assert_eq!(button_attributes(&emulator, 10), 0x32);
+    assert_eq!(button_attributes(&emulator, 11), 0x30);
+}
+
+#[test]
+fn test_boot_rom_runs_without_sound_upload() {
+    // An APU that never finishes the IPL handshake leaves the test silent
+    // but running: the main loop keeps bumping the frame counter
+    let mut emulator = self_test(10);
+    let frame = emulator.bus().peek8(0x000004);
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.bus().peek8(0x000004), frame.wrapping_add(1));
+}
+
+#[test]
+fn test_boot_rom_is_lint_clean() {
+    let emulator = self_test(120);
+    assert!(emulator.lints().is_empty(), "{:?}", emulator.lints());
+}
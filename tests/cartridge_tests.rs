@@ -1,5 +1,9 @@
-use ccsnes::cartridge::{Cartridge, CartridgeHeader};
-use ccsnes::memory::mappers::MapperType;
+use ccsnes::cartridge::integrity::{self, IntegrityWarning, RomDatabase};
+use ccsnes::cartridge::verify::verify_mapper;
+use ccsnes::cartridge::{Cartridge, CartridgeHeader, RomImage, RomLoader};
+use ccsnes::emulator::Emulator;
+use ccsnes::memory::mappers::{create_mapper, MapperType, MappedRegion, RegionKind};
+use ccsnes_test_support::{fresh_dir, LoRom};
 
 #[test]
 fn test_lorom_header_detection() {
@@ -191,4 +195,395 @@ fn test_sram_access() {
     let sram_data = cartridge.save_sram();
     assert_eq!(sram_data[0], 0x42);
     assert_eq!(sram_data[1], 0x43);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_lorom_describe_regions() {
+    // 64KB LoROM with 8KB SRAM
+    let mapper = create_mapper(MapperType::LoROM, 0x10000, 0x2000).unwrap();
+    let regions = mapper.describe_regions();
+    
+    // Banks $00-$01 map linearly to the first 64KB
+    assert_eq!(regions[0], MappedRegion {
+        kind: RegionKind::Rom,
+        banks: (0x00, 0x01),
+        start: 0x8000,
+        end: 0xFFFF,
+        offset: 0,
+    });
+    
+    // SRAM appears at $70:0000-$1FFF
+    let sram = regions.iter().find(|r| r.kind == RegionKind::Sram).unwrap();
+    assert_eq!(sram.banks, (0x70, 0x70));
+    assert_eq!((sram.start, sram.end), (0x0000, 0x1FFF));
+    assert_eq!(sram.offset, 0);
+    
+    // Mirror at $80-$81
+    assert!(regions.iter().any(|r| r.kind == RegionKind::Rom && r.banks == (0x80, 0x81) && r.offset == 0));
+}
+
+#[test]
+fn test_hirom_describe_regions() {
+    // 128KB HiROM without SRAM
+    let mapper = create_mapper(MapperType::HiROM, 0x20000, 0).unwrap();
+    let regions = mapper.describe_regions();
+    
+    assert!(regions.iter().all(|r| r.kind == RegionKind::Rom));
+    
+    // Bank $00 exposes the upper half of the first 64KB bank
+    let bank0 = &regions[0];
+    assert_eq!(bank0.banks, (0x00, 0x00));
+    assert_eq!((bank0.start, bank0.end), (0x8000, 0xFFFF));
+    assert_eq!(bank0.offset, 0x8000);
+    assert_eq!(bank0.total_size(), 0x8000);
+    assert_eq!(bank0.to_string(), "$00-$00:$8000-$FFFF -> ROM  $008000-$00FFFF");
+}
+
+// LoROM image with a valid checksum, and the same image behind a copier header
+fn checksummed_lorom(title: &str) -> (Vec<u8>, Vec<u8>) {
+    let program: Vec<u8> = (0..0x7FC0).map(|i| (i * 7) as u8).collect();
+    let image = LoRom::new(title, &program).checksummed().build();
+
+    let mut rom = vec![0xEE; 512];
+    rom.extend_from_slice(&image);
+    (image, rom)
+}
+
+#[test]
+fn test_incremental_rom_loader() {
+    let (image, rom) = checksummed_lorom("CHUNKED LOAD");
+    let sum = CartridgeHeader::byte_sum(&image) as u16;
+
+    let mut loader = RomLoader::with_expected_size(rom.len());
+    assert_eq!(loader.progress(), 0.0);
+    // Uneven chunks, the first one ending inside the copier header
+    for chunk in rom.chunks(300) {
+        loader.push(chunk);
+    }
+    assert_eq!(loader.len(), rom.len());
+    assert_eq!(loader.progress(), 1.0);
+
+    let chunked = loader.finish().unwrap();
+    let direct = Cartridge::load(&rom).unwrap();
+    assert_eq!(chunked.rom_data, image);
+    assert_eq!(chunked.rom_data, direct.rom_data);
+    assert_eq!(chunked.header.title, "CHUNKED LOAD");
+    assert_eq!(chunked.header.checksum, direct.header.checksum);
+    assert_eq!(CartridgeHeader::byte_sum(&chunked.rom_data) as u16, sum);
+}
+
+#[test]
+fn test_memory_mapped_rom() {
+    let (image, rom) = checksummed_lorom("MAPPED LOAD");
+    let dir = fresh_dir("ccsnes_memory_mapped_rom");
+    let path = dir.join("game.sfc");
+    std::fs::write(&path, &rom).unwrap();
+
+    let mapped = RomImage::map(&path).unwrap();
+    assert!(mapped.is_mapped() && mapped.has_copier_header());
+    assert_eq!(mapped.len(), rom.len());
+
+    // The cartridge skips the copier header in place and reads the map
+    let cartridge = Cartridge::from_rom_image_as(mapped.clone(), None).unwrap();
+    let direct = Cartridge::load(&rom).unwrap();
+    assert!(cartridge.rom_data.is_mapped());
+    assert_eq!(cartridge.rom_data, image);
+    assert_eq!(cartridge.header.title, "MAPPED LOAD");
+    assert_eq!(cartridge.header.checksum, direct.header.checksum);
+    assert_eq!(cartridge.read(0x008123), image[0x123]);
+
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom_image(mapped, None).unwrap();
+    assert_eq!(emulator.get_rom_info().unwrap().title, "MAPPED LOAD");
+
+    // Owned images behave the same
+    let owned = Cartridge::from_rom_image_as(RomImage::from(rom), None).unwrap();
+    assert!(!owned.rom_data.is_mapped());
+    assert_eq!(owned.rom_data, cartridge.rom_data);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn bps_number(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte | 0x80);
+            return;
+        }
+        out.push(byte);
+        value -= 1;
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+#[test]
+fn test_ips_patch() {
+    use ccsnes::cartridge::patch::{apply_patch, PatchFormat};
+
+    let rom = vec![0u8; 16];
+    let mut ips = b"PATCH".to_vec();
+    ips.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]);   // 2 bytes at $02
+    ips.extend_from_slice(&[0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x03, 0xCC]); // RLE x3 at $08
+    ips.extend_from_slice(&[0x00, 0x00, 0x12, 0x00, 0x01, 0xDD]);   // grows the ROM
+    ips.extend_from_slice(b"EOF");
+
+    assert_eq!(PatchFormat::detect(&ips), Some(PatchFormat::Ips));
+    let patched = apply_patch(&rom, &ips).unwrap();
+    assert_eq!(patched.len(), 0x13);
+    assert_eq!(&patched[0x02..0x04], &[0xAA, 0xBB]);
+    assert_eq!(&patched[0x08..0x0B], &[0xCC; 3]);
+    assert_eq!(patched[0x12], 0xDD);
+
+    // Truncated records are rejected
+    assert!(apply_patch(&rom, &ips[..ips.len() - 5]).is_err());
+    assert!(apply_patch(&rom, b"NOT A PATCH").is_err());
+}
+
+#[test]
+fn test_bps_patch() {
+    use ccsnes::cartridge::patch::apply_patch;
+
+    let rom: Vec<u8> = (0..16).collect();
+    let mut expected = rom.clone();
+    expected[4..6].copy_from_slice(&[0xEE, 0xFF]);
+    expected.extend_from_slice(&[0xEE, 0xFF, 0x06, 0x07]);
+
+    let mut bps = b"BPS1".to_vec();
+    bps_number(&mut bps, rom.len());
+    bps_number(&mut bps, expected.len());
+    bps_number(&mut bps, 0);
+    bps_number(&mut bps, (4 - 1) << 2);         // SourceRead 4
+    bps_number(&mut bps, ((2 - 1) << 2) | 1);   // TargetRead 2
+    bps.extend_from_slice(&[0xEE, 0xFF]);
+    bps_number(&mut bps, ((10 - 1) << 2) | 2);  // SourceCopy 10 from +6
+    bps_number(&mut bps, 6 << 1);
+    bps_number(&mut bps, ((4 - 1) << 2) | 3);   // TargetCopy 4 from +4
+    bps_number(&mut bps, 4 << 1);
+    bps.extend_from_slice(&crc32(&rom).to_le_bytes());
+    bps.extend_from_slice(&crc32(&expected).to_le_bytes());
+    let patch_crc = crc32(&bps);
+    bps.extend_from_slice(&patch_crc.to_le_bytes());
+
+    assert_eq!(apply_patch(&rom, &bps).unwrap(), expected);
+
+    // Wrong source ROM
+    let mut other = rom.clone();
+    other[0] = 0x80;
+    assert!(apply_patch(&other, &bps).is_err());
+}
+
+// Footer for a patch against `rom`, so only the actions are malformed
+fn bps_footer(mut bps: Vec<u8>, rom: &[u8]) -> Vec<u8> {
+    bps.extend_from_slice(&crc32(rom).to_le_bytes());
+    bps.extend_from_slice(&crc32(&[]).to_le_bytes());
+    let patch_crc = crc32(&bps);
+    bps.extend_from_slice(&patch_crc.to_le_bytes());
+    bps
+}
+
+#[test]
+fn test_bps_patch_overflow() {
+    use ccsnes::cartridge::patch::apply_patch;
+
+    let rom: Vec<u8> = (0..16).collect();
+    // A varint that never fits in usize
+    let mut bps = b"BPS1".to_vec();
+    bps.extend_from_slice(&[0x7F; 12]);
+    bps.push(0xFF);
+    let error = apply_patch(&rom, &bps_footer(bps, &rom)).unwrap_err();
+    assert!(error.to_string().contains("truncated"), "{}", error);
+
+    // Metadata running past the end of usize
+    let mut bps = b"BPS1".to_vec();
+    bps_number(&mut bps, rom.len());
+    bps_number(&mut bps, 4);
+    bps_number(&mut bps, usize::MAX);
+    assert!(apply_patch(&rom, &bps_footer(bps, &rom)).is_err());
+
+    // A TargetCopy that would keep repeating its own output
+    let mut bps = b"BPS1".to_vec();
+    bps_number(&mut bps, rom.len());
+    bps_number(&mut bps, 4);
+    bps_number(&mut bps, 0);
+    bps_number(&mut bps, 1);                    // TargetRead 1
+    bps.push(0xAA);
+    bps_number(&mut bps, ((1 << 40) << 2) | 3); // TargetCopy 2^40 + 1 from +0
+    bps_number(&mut bps, 0);
+    assert!(apply_patch(&rom, &bps_footer(bps, &rom)).is_err());
+}
+
+
+// 64KB image with a header and reset vector in the given layout's place
+// and a valid checksum
+fn mapper_test_rom(header: usize, mapper_byte: u8) -> Vec<u8> {
+    let mut rom = vec![0u8; 0x10000];
+    // SEI at the reset target, $8000 in bank 0 for either layout
+    let entry = if header == 0x7FC0 { 0 } else { 0x8000 };
+    rom[entry] = 0x78;
+    rom[header..header + 21].copy_from_slice(b"MAPPER CHECK         ");
+    rom[header + 0x15] = mapper_byte;
+    rom[header + 0x17] = 6;
+    rom[header + 0x3C..header + 0x3E].copy_from_slice(&0x8000u16.to_le_bytes());
+    rom[header + 0x1C..header + 0x20].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00]);
+    let sum = CartridgeHeader::byte_sum(&rom) as u16;
+    rom[header + 0x1C..header + 0x1E].copy_from_slice(&(!sum).to_le_bytes());
+    rom[header + 0x1E..header + 0x20].copy_from_slice(&sum.to_le_bytes());
+    rom
+}
+
+#[test]
+fn test_verify_mapper_scores() {
+    let report = verify_mapper(&mapper_test_rom(0x7FC0, 0x20), None);
+    assert_eq!(report.chosen, Some(MapperType::LoROM));
+    assert_eq!(report.best, Some(MapperType::LoROM));
+    assert!(!report.is_suspicious());
+    let lorom = report.candidate(MapperType::LoROM).unwrap();
+    assert!(lorom.header_plausible && lorom.mapper_byte_matches && lorom.checksum_matches);
+    assert_eq!(lorom.reset_opcode, Some(0x78));
+    assert!(lorom.score > report.candidate(MapperType::HiROM).unwrap().score);
+
+    let report = verify_mapper(&mapper_test_rom(0xFFC0, 0x21), None);
+    assert_eq!(report.best, Some(MapperType::HiROM));
+    assert_eq!(report.chosen, Some(MapperType::HiROM));
+    assert!(report.to_string().contains("Best match: HiROM"));
+}
+
+#[test]
+fn test_verify_mapper_flags_wrong_mapper_byte() {
+    // A HiROM image whose header claims LoROM
+    let report = verify_mapper(&mapper_test_rom(0xFFC0, 0x20), None);
+    assert_eq!(report.best, Some(MapperType::HiROM));
+    assert_eq!(report.chosen, Some(MapperType::LoROM));
+    assert!(report.is_suspicious());
+    assert!(report.to_string().contains("--force-mapper hirom"));
+
+    // Forcing the mapper reads the header from its location
+    let report = verify_mapper(&mapper_test_rom(0xFFC0, 0x20), Some(MapperType::HiROM));
+    assert_eq!(report.chosen, Some(MapperType::HiROM));
+    assert!(!report.is_suspicious());
+    assert!(report.to_string().contains("forced"));
+}
+
+#[test]
+fn test_force_mapper() {
+    // Read as LoROM, this image's header is the zeros at $7FC0
+    let rom = mapper_test_rom(0xFFC0, 0x20);
+    assert!(Cartridge::load(&rom).is_err());
+
+    let cartridge = Cartridge::load_as(&rom, Some(MapperType::HiROM)).unwrap();
+    assert_eq!(cartridge.get_mapper_type(), MapperType::HiROM);
+    assert_eq!(cartridge.mapper.name(), "HiROM");
+    assert_eq!(cartridge.get_title(), "MAPPER CHECK");
+    // Reset vector through the HiROM mapping
+    assert_eq!(cartridge.read(0x00FFFC), 0x00);
+    assert_eq!(cartridge.read(0x00FFFD), 0x80);
+
+    assert_eq!(MapperType::from_name("HiROM"), Some(MapperType::HiROM));
+    assert_eq!(MapperType::from_name("bogus"), None);
+}
+
+fn integrity_warnings(rom: &[u8]) -> Vec<IntegrityWarning> {
+    integrity::check_cartridge(&Cartridge::load(rom).unwrap())
+}
+
+#[test]
+fn test_integrity_checks_header_checksum() {
+    let (image, _) = checksummed_lorom("INTEGRITY TEST");
+    assert!(integrity_warnings(&image).is_empty());
+
+    let mut modified = image.clone();
+    modified[0x1000] ^= 0x01;
+    let warnings = integrity_warnings(&modified);
+    assert_eq!(warnings.len(), 1);
+    assert!(matches!(warnings[0], IntegrityWarning::ChecksumMismatch { .. }));
+    assert!(warnings[0].to_string().contains("bad dump"));
+
+    // Complement edited, with another byte moved so the sum still matches
+    let mut damaged = image.clone();
+    damaged[0x7FDC] = damaged[0x7FDC].wrapping_add(1);
+    damaged[0x1001] -= 1;
+    assert_eq!(
+        integrity_warnings(&damaged),
+        vec![IntegrityWarning::ChecksumComplement {
+            checksum: u16::from_le_bytes([damaged[0x7FDE], damaged[0x7FDF]]),
+            complement: u16::from_le_bytes([damaged[0x7FDC], damaged[0x7FDD]]),
+        }]
+    );
+}
+
+#[test]
+fn test_integrity_checks_rom_size() {
+    let (image, _) = checksummed_lorom("INTEGRITY SIZE");
+
+    // The header declares 128 KB
+    let mut underdump = image.clone();
+    underdump[0x7FC0 + 0x17] = 7;
+    assert!(integrity_warnings(&underdump).contains(&IntegrityWarning::Underdump { header_size: 0x20000, file_size: 0x8000 }));
+
+    let mut overdump = image.clone();
+    overdump.extend_from_slice(&[0xFF; 0x4000]);
+    let warnings = integrity_warnings(&overdump);
+    assert!(warnings.contains(&IntegrityWarning::Overdump { header_size: 0x8000, file_size: 0xC000 }));
+    assert!(warnings[warnings.len() - 1].to_string().contains("overdump"));
+}
+
+#[test]
+fn test_mirrored_sum() {
+    let rom: Vec<u8> = (0..0x18000).map(|i| (i % 251) as u8).collect();
+    let head = CartridgeHeader::byte_sum(&rom[..0x10000]);
+    let tail = CartridgeHeader::byte_sum(&rom[0x10000..]);
+    assert_eq!(integrity::mirrored_sum(&rom), head + tail * 2);
+    assert_eq!(integrity::mirrored_sum(&rom[..0x10000]), head);
+}
+
+#[test]
+fn test_rom_database() {
+    let (image, _) = checksummed_lorom("DATABASE TEST");
+    let mut other = image.clone();
+    other[0] ^= 0xFF;
+    let dat = format!(
+        "<?xml version=\"1.0\"?>\n<datafile>\n\
+         <game name=\"Test &amp; Game (USA)\">\n<rom name=\"Test &amp; Game (USA).sfc\" size=\"{}\" crc=\"{:08x}\" md5=\"x\"/>\n</game>\n\
+         <game name=\"Test Game [b1]\">\n<rom name=\"Test Game [b1].sfc\" size=\"{}\" crc=\"{:08X}\"/>\n</game>\n</datafile>\n",
+        image.len(),
+        crc32(&image),
+        other.len(),
+        crc32(&other)
+    );
+    let database = RomDatabase::parse(&dat);
+    assert_eq!(database.len(), 2);
+    assert_eq!(database.find(crc32(&image), image.len()).unwrap().name, "Test & Game (USA).sfc");
+
+    assert_eq!(database.check(&image), None);
+    assert_eq!(
+        database.check(&other),
+        Some(IntegrityWarning::MarkedInDatabase { name: "Test Game [b1].sfc".to_string() })
+    );
+    let mut unknown = image.clone();
+    unknown[1] ^= 0xFF;
+    assert_eq!(database.check(&unknown), Some(IntegrityWarning::NotInDatabase { crc32: crc32(&unknown) }));
+
+    // Without a database there is nothing to compare with
+    assert_eq!(RomDatabase::default().check(&unknown), None);
+    assert!(RomDatabase::load("/nonexistent/snes.dat").unwrap().is_empty());
+}
+
+#[test]
+fn test_emulator_reports_rom_warnings() {
+    let (image, _) = checksummed_lorom("EMULATOR WARNINGS");
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&image).unwrap();
+    assert!(emulator.rom_warnings().is_empty());
+
+    let mut modified = image.clone();
+    modified[0x2000] ^= 0x80;
+    emulator.load_rom(&modified).unwrap();
+    assert!(matches!(emulator.rom_warnings(), [IntegrityWarning::ChecksumMismatch { .. }]));
+}
@@ -0,0 +1,90 @@
+use ccsnes::cheats::{CheatCode, CheatList};
+use ccsnes::memory::freeze::FreezeTarget;
+use ccsnes::memory::Bus;
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM with 8KB SRAM that idles in a loop
+fn sram_rom() -> Vec<u8> {
+    LoRom::new("CHEAT TEST", &[0x80, 0xFE]).sram(3).build()
+}
+
+#[test]
+fn test_freeze_survives_writes_through_mirrors() {
+    let mut bus = Bus::new();
+    bus.freeze(0x7E0010, 0x09).unwrap();
+    assert_eq!(bus.read8(0x000010), 0x09);
+
+    // Low RAM mirror and direct bank $7E both hit the same byte
+    bus.write8(0x000010, 0x00);
+    assert_eq!(bus.read8(0x7E0010), 0x09);
+    bus.write8(0x800010, 0x01);
+    bus.write8(0x7E0010, 0x02);
+    assert_eq!(bus.read8(0x7E0010), 0x09);
+
+    // Neighbouring bytes are unaffected
+    bus.write8(0x7E0011, 0x55);
+    assert_eq!(bus.read8(0x7E0011), 0x55);
+
+    // Replacing a freeze keeps a single entry
+    bus.freeze(0x000010, 0x03).unwrap();
+    assert_eq!(bus.freezes().len(), 1);
+    assert_eq!(bus.freezes()[0].target, FreezeTarget::Wram(0x10));
+
+    assert!(bus.unfreeze(0x7E0010));
+    bus.write8(0x7E0010, 0x04);
+    assert_eq!(bus.read8(0x7E0010), 0x04);
+
+    // ROM and I/O cannot be frozen
+    assert!(bus.freeze(0x002100, 0).is_err());
+    assert!(bus.freeze(0x008000, 0).is_err());
+}
+
+#[test]
+fn test_sram_freeze_and_cheat_list() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&sram_rom()).unwrap();
+
+    let mut cheats = CheatList::new();
+    cheats.add("Infinite lives", "7E0DBF09").unwrap();
+    cheats.add("Max gold", "70:0004:63").unwrap();
+    cheats.add("Disabled", "7E000001").unwrap();
+    assert!(cheats.set_enabled("Disabled", false));
+    assert!(cheats.add("Broken", "7E00").is_err());
+    assert_eq!(emulator.apply_cheats(&cheats).unwrap(), 2);
+
+    emulator.write8(0x700004, 0x00);
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.get_sram().unwrap()[4], 0x63);
+    assert_eq!(emulator.read8(0x7E0DBF), 0x09);
+
+    let dir = std::env::temp_dir().join(format!("ccsnes_cheats_{}", std::process::id()));
+    let path = dir.join("cheats.toml");
+    cheats.save(&path).unwrap();
+    let loaded = CheatList::load(&path).unwrap();
+    assert_eq!(loaded, cheats);
+    assert!(CheatList::load(path.with_file_name("missing.toml")).unwrap().cheats.is_empty());
+    let _ = std::fs::remove_dir_all(&dir);
+
+    assert_eq!(CheatCode::parse("7e0dbf09").unwrap(), CheatCode { address: 0x7E0DBF, value: 0x09 });
+}
+
+#[test]
+#[cfg(feature = "debugger")]
+fn test_lock_watch() {
+    use ccsnes::debug::{Debugger, WatchFormat, WatchSize};
+
+    let mut emulator = Emulator::new().unwrap();
+    let mut debugger = Debugger::new();
+    emulator.write16(0x7E0100, 0x1234);
+    debugger.add_watch("hp".to_string(), 0x7E0100, WatchSize::Word, WatchFormat::Hex);
+
+    debugger.lock_watch(&mut emulator, "hp").unwrap();
+    emulator.write16(0x7E0100, 0);
+    assert_eq!(emulator.read16(0x7E0100), 0x1234);
+    assert_eq!(debugger.format_freezes(emulator.bus()).lines().next().unwrap(), "$7E0100 = $34  (WRAM $00100)");
+
+    debugger.unlock_watch(&mut emulator, "hp").unwrap();
+    assert!(emulator.bus().freezes().is_empty());
+    assert!(debugger.lock_watch(&mut emulator, "missing").is_err());
+}
@@ -0,0 +1,270 @@
+use ccsnes::config::{AudioConfig, Config, ConfigWatcher};
+use ccsnes::input::conflicts::{ButtonRef, InputConflict};
+use ccsnes::input::HotkeyAction;
+use ccsnes::input::controller::{BUTTON_A, BUTTON_L, BUTTON_R, BUTTON_START};
+use ccsnes::profile::GameProfile;
+use ccsnes_test_support::fresh_dir;
+
+#[test]
+fn test_config_round_trip() {
+    let mut config = Config::default();
+    config.audio.backend = Some("ALSA".to_string());
+    config.audio.device = Some("default".to_string());
+    config.audio.latency_ms = 32;
+    
+    // Unique per process: tests/mod.rs runs this test too
+    let path = std::env::temp_dir().join(format!("ccsnes_config_round_trip_{}.toml", std::process::id()));
+    config.save_to_file(&path).expect("Failed to save config");
+    let loaded = Config::load_from_file(&path).expect("Failed to load config");
+    
+    assert_eq!(loaded.audio.backend.as_deref(), Some("ALSA"));
+    assert_eq!(loaded.audio.device.as_deref(), Some("default"));
+    assert_eq!(loaded.audio.latency_ms, 32);
+    assert!(!loaded.audio.exclusive_mode);
+    
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_audio_config_defaults_for_missing_fields() {
+    // Audio section written before backend/latency settings existed
+    let audio: AudioConfig = toml::from_str(
+        "master_volume = 50\nsample_rate = 44100\nbuffer_size = 1024\nenabled = true\nlow_pass_filter = false\n"
+    ).expect("Failed to parse audio config");
+    
+    assert_eq!(audio.master_volume, 50);
+    assert_eq!(audio.backend, None);
+    assert_eq!(audio.device, None);
+    assert_eq!(audio.latency_ms, AudioConfig::default().latency_ms);
+}
+
+#[test]
+fn test_config_merge_json_and_changes() {
+    let config = Config::default();
+    let updated = config
+        .merge_json(r#"{"audio": {"latency_ms": 32}, "video": {"scale": 3}, "input": {"player2": {"a": "N"}}}"#)
+        .expect("Failed to merge config");
+    
+    assert_eq!(updated.audio.latency_ms, 32);
+    assert_eq!(updated.audio.sample_rate, config.audio.sample_rate);
+    assert_eq!(updated.input.buttons_for_key("n"), [0, BUTTON_A]);
+    assert_eq!(updated.input.buttons_for_key("Return"), [BUTTON_START, 0]);
+    
+    let changes = config.changes(&updated);
+    assert!(changes.audio && changes.video && changes.input);
+    assert!(!changes.hotkeys && !changes.emulation && !changes.paths && !changes.debug);
+    assert_eq!(config.reset_required(&updated), vec!["video.scale"]);
+    assert!(config.changes(&config).is_empty());
+    
+    assert!(config.merge_json(r#"{"audio": {"no_such_setting": 1}}"#).is_err());
+    assert!(config.merge_json(r#"{"video": {"scale": "big"}}"#).is_err());
+}
+
+#[test]
+fn test_config_watcher_reports_updates() {
+    let dir = fresh_dir("ccsnes_config_watcher");
+    let path = dir.join("config.toml");
+    let mut config = Config::default();
+    config.save_to_file(&path).expect("Failed to save config");
+    
+    let mut watcher = ConfigWatcher::new(&path).expect("Failed to watch config");
+    assert!(watcher.poll().expect("Poll failed").is_none());
+    
+    config.hotkeys.quit = "F10".to_string();
+    config.emulation.region = ccsnes::config::Region::PAL;
+    config.save_to_file(&path).expect("Failed to save config");
+    // Make sure the change is visible even on coarse-grained file systems
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(2);
+    std::fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+    
+    let update = watcher.poll().expect("Poll failed").expect("No update reported");
+    assert_eq!(update.config.hotkeys.quit, "F10");
+    assert!(update.changes.hotkeys && update.changes.emulation && !update.changes.video);
+    assert_eq!(update.reset_required, vec!["emulation.region"]);
+    assert!(watcher.poll().expect("Poll failed").is_none());
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_set_key_binding() {
+    let mut config = Config::default();
+    config.input.set_key_binding(0, "L", "O").unwrap();
+    config.input.set_key_binding(1, "start", "M").unwrap();
+    assert_eq!(config.input.player1.l, "O");
+    assert_eq!(config.input.buttons_for_key("o"), [BUTTON_L, 0]);
+    assert_eq!(config.input.buttons_for_key("M"), [0, BUTTON_START]);
+    
+    assert!(config.input.set_key_binding(0, "turbo", "T").is_err());
+    assert!(config.input.set_key_binding(2, "a", "T").is_err());
+    
+    // Bindings survive the JSON round trip used for browser storage
+    let json = serde_json::to_string(&config).unwrap();
+    let restored = Config::default().merge_json(&json).unwrap();
+    assert_eq!(restored.input, config.input);
+}
+
+
+const PROFILES_TOML: &str = r#"
+[input.profiles.shoulder_swap.player1]
+l = "W"
+r = "Q"
+
+[input.profiles.two_player.player2]
+a = "N"
+"#;
+
+fn config_with_profiles() -> Config {
+    let toml = toml::to_string(&Config::default()).unwrap() + PROFILES_TOML;
+    toml::from_str(&toml).expect("Failed to parse profiles")
+}
+
+#[test]
+fn test_input_profile_overrides_bindings() {
+    let mut config = config_with_profiles();
+    assert_eq!(config.input.active_profile(), None);
+    assert_eq!(config.input.buttons_for_key("Q"), [BUTTON_L, 0]);
+    
+    config.input.set_active_profile(Some("shoulder_swap")).unwrap();
+    assert_eq!(config.input.active_profile(), Some("shoulder_swap"));
+    assert_eq!(config.input.buttons_for_key("Q"), [BUTTON_R, 0]);
+    assert_eq!(config.input.buttons_for_key("W"), [BUTTON_L, 0]);
+    // Bindings the profile doesn't mention stay as they were
+    assert_eq!(config.input.buttons_for_key("X"), [BUTTON_A, 0]);
+    assert_eq!(config.input.player1.l, "Q");
+    
+    // Changing a base binding keeps the profile's overrides on top
+    config.input.set_key_binding(0, "start", "Space").unwrap();
+    assert_eq!(config.input.buttons_for_key("Space"), [BUTTON_START, 0]);
+    assert_eq!(config.input.buttons_for_key("Q"), [BUTTON_R, 0]);
+    
+    config.input.set_active_profile(None).unwrap();
+    assert_eq!(config.input.buttons_for_key("Q"), [BUTTON_L, 0]);
+}
+
+#[test]
+fn test_input_profile_errors() {
+    let mut config = config_with_profiles();
+    assert!(config.input.set_active_profile(Some("missing")).is_err());
+    assert_eq!(config.input.active_profile(), None);
+    
+    config.input.profiles.get_mut("two_player").unwrap().player2.insert("turbo".to_string(), "T".to_string());
+    let err = config.input.set_active_profile(Some("two_player")).unwrap_err();
+    assert!(err.to_string().contains("two_player"));
+    assert_eq!(config.input.active_profile(), None);
+}
+
+#[test]
+fn test_cycle_input_profiles() {
+    let mut config = config_with_profiles();
+    assert_eq!(config.input.cycle_profile().unwrap(), Some("shoulder_swap"));
+    assert_eq!(config.input.cycle_profile().unwrap(), Some("two_player"));
+    assert_eq!(config.input.buttons_for_key("N"), [0, BUTTON_A]);
+    assert_eq!(config.input.cycle_profile().unwrap(), None);
+    assert_eq!(config.input.cycle_profile().unwrap(), Some("shoulder_swap"));
+    
+    assert_eq!(Config::default().input.cycle_profile().unwrap(), None);
+}
+
+#[test]
+fn test_game_profile_selects_input_profile() {
+    let profile: GameProfile = toml::from_str("input_profile = \"shoulder_swap\"\n").unwrap();
+    assert_eq!(profile.input_profile.as_deref(), Some("shoulder_swap"));
+    assert_eq!(GameProfile::new().input_profile, None);
+    
+    let mut config = config_with_profiles();
+    config.input.set_active_profile(profile.input_profile.as_deref()).unwrap();
+    assert_eq!(config.input.buttons_for_key("W"), [BUTTON_L, 0]);
+}
+#[test]
+fn test_config_watcher_save_is_not_reported_back() {
+    let dir = fresh_dir("ccsnes_config_watcher_save");
+    let path = dir.join("config.toml");
+    Config::default().save_to_file(&path).expect("Failed to save config");
+    let mut watcher = ConfigWatcher::new(&path).expect("Failed to watch config");
+
+    let mut config = watcher.current().clone();
+    config.audio.master_volume = 150;
+    config.audio.muted = true;
+    watcher.save(config).expect("Failed to save config");
+    assert!(watcher.poll().expect("Poll failed").is_none());
+
+    let loaded = Config::load_from_file(&path).expect("Failed to load config");
+    let volume = ccsnes::apu::volume::Volume::from_config(&loaded.audio);
+    assert_eq!(volume.level(), 1.5);
+    assert!(volume.is_muted());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn button(player: u8, button: &'static str, profile: Option<&str>) -> ButtonRef {
+    ButtonRef { player, button, profile: profile.map(str::to_string) }
+}
+
+#[test]
+fn test_default_bindings_are_valid() {
+    let config = config_with_profiles();
+    assert_eq!(config.input.validate(&config.hotkeys), vec![]);
+}
+
+#[test]
+fn test_validate_duplicate_and_unreachable_buttons() {
+    let mut config = Config::default();
+    // Player 2's B is already on F
+    config.input.player1.select = "f".to_string();
+    config.input.player2.start = String::new();
+    config.input.player2.l = "Numpad1".to_string();
+
+    let conflicts = config.input.validate(&config.hotkeys);
+    assert_eq!(conflicts, vec![
+        InputConflict::DuplicateBinding { key: "F".to_string(), first: button(0, "select", None), second: button(1, "b", None) },
+        InputConflict::UnreachableButton { button: button(1, "l", None), key: "Numpad1".to_string() },
+        InputConflict::UnreachableButton { button: button(1, "start", None), key: String::new() },
+    ]);
+    assert!(conflicts[1].to_string().contains("unknown key 'Numpad1'"));
+    assert!(conflicts[2].to_string().starts_with("player 2 start has no key"));
+}
+
+#[test]
+fn test_validate_hotkey_conflicts() {
+    let mut config = config_with_profiles();
+    config.hotkeys.screenshot = "Q".to_string();
+    config.hotkeys.reset = "f5".to_string();
+    config.hotkeys.toggle_obj = "Hyper+5".to_string();
+    // Modifiers keep a hotkey off the plain key
+    config.hotkeys.quit = "Shift+Z".to_string();
+
+    let conflicts = config.input.validate(&config.hotkeys);
+    assert_eq!(conflicts.len(), 4);
+    assert_eq!(conflicts[0], InputConflict::DuplicateHotkey {
+        binding: "f5".to_string(),
+        first: HotkeyAction::SaveState,
+        second: HotkeyAction::Reset,
+    });
+    assert!(matches!(&conflicts[1], InputConflict::InvalidHotkey { action: HotkeyAction::ToggleObj, .. }));
+    assert_eq!(conflicts[2], InputConflict::HotkeyConflict {
+        button: button(0, "l", None),
+        key: "Q".to_string(),
+        action: HotkeyAction::Screenshot,
+    });
+    // The profile moving R onto Q has its own conflict; the base one isn't repeated
+    assert_eq!(conflicts[3], InputConflict::HotkeyConflict {
+        button: button(0, "r", Some("shoulder_swap")),
+        key: "Q".to_string(),
+        action: HotkeyAction::Screenshot,
+    });
+    assert_eq!(
+        conflicts[3].to_string(),
+        "player 1 r (profile 'shoulder_swap') is bound to 'Q', which the screenshot hotkey takes first; rebind one of them"
+    );
+}
+
+#[test]
+fn test_validate_bad_profile() {
+    let mut config = config_with_profiles();
+    config.input.profiles.get_mut("two_player").unwrap().player2.insert("turbo".to_string(), "T".to_string());
+    let conflicts = config.input.validate(&config.hotkeys);
+    assert_eq!(conflicts.len(), 1);
+    assert!(matches!(&conflicts[0], InputConflict::InvalidProfile { profile, .. } if profile == "two_player"));
+    assert!(conflicts[0].to_string().contains("'turbo'"));
+}
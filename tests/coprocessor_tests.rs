@@ -0,0 +1,186 @@
+use ccsnes::cartridge::coprocessor::{BusWindow, Coprocessor};
+use ccsnes::savestate::{SaveState, StateEncoding, StateSection};
+use ccsnes::{EmulatorError, Emulator, Result};
+use ccsnes_test_support::LoRom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// A timer chip at $3000-$3001: $3000 is a latch, reading $3001 gives and
+// acknowledges the IRQ, raised every `period` master cycles
+struct TimerChip {
+    name: &'static str,
+    period: u64,
+    elapsed: u64,
+    latch: u8,
+    irq: bool,
+    ran: Arc<AtomicU64>,
+}
+
+impl TimerChip {
+    fn new(name: &'static str, period: u64) -> Self {
+        Self { name, period, elapsed: 0, latch: 0, irq: false, ran: Arc::new(AtomicU64::new(0)) }
+    }
+}
+
+impl Coprocessor for TimerChip {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn windows(&self) -> Vec<BusWindow> {
+        BusWindow::system(0x3000..=0x3001).to_vec()
+    }
+
+    fn read(&mut self, address: u32) -> u8 {
+        match address & 0xFFFF {
+            0x3000 => self.latch,
+            _ => (std::mem::take(&mut self.irq) as u8) << 7,
+        }
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        if address & 0xFFFF == 0x3000 {
+            self.latch = value;
+        }
+    }
+
+    fn run(&mut self, master_cycles: u64) {
+        self.ran.fetch_add(master_cycles, Ordering::Relaxed);
+        self.elapsed += master_cycles;
+        if self.elapsed >= self.period {
+            self.elapsed %= self.period;
+            self.irq = true;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.irq
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0;
+        self.latch = 0;
+        self.irq = false;
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        let mut data = vec![self.latch, self.irq as u8];
+        data.extend_from_slice(&self.elapsed.to_le_bytes());
+        data
+    }
+
+    fn load_state(&mut self, data: &[u8]) -> Result<()> {
+        if data.len() != 10 {
+            return Err(EmulatorError::save_state("timer chip state is 10 bytes"));
+        }
+        self.latch = data[0];
+        self.irq = data[1] != 0;
+        self.elapsed = u64::from_le_bytes(data[2..10].try_into().unwrap());
+        Ok(())
+    }
+}
+
+// LoROM running CLI then BRA $; the IRQ handler does INC $10, LDA $3001, RTI
+fn irq_rom() -> Vec<u8> {
+    LoRom::new("COPROCESSOR TEST", &[0x58, 0x80, 0xFE])
+        .at(0x8010, &[0xE6, 0x10, 0xAD, 0x01, 0x30, 0x40])
+        .vector(0xFFFE, 0x8010)
+        .build()
+}
+
+#[test]
+fn test_coprocessor_window_takes_accesses_before_the_cartridge() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&irq_rom()).unwrap();
+    emulator.attach_coprocessor(Box::new(TimerChip::new("timer", 1 << 40))).unwrap();
+    assert_eq!(emulator.coprocessors().names(), vec!["timer".to_string()]);
+
+    emulator.write8(0x003000, 0x5A);
+    assert_eq!(emulator.read8(0x003000), 0x5A);
+    assert_eq!(emulator.read8(0x803000), 0x5A);
+    assert!(emulator.coprocessors().claims(0xBF3001));
+    // Outside the window the cartridge answers as before
+    assert!(!emulator.coprocessors().claims(0x403000));
+    assert_eq!(emulator.read8(0x008010), 0xE6);
+
+    // Names key the save state chunks, so they can't repeat
+    assert!(emulator.attach_coprocessor(Box::new(TimerChip::new("timer", 1))).is_err());
+
+    // The chip belongs to the cartridge and comes out with it
+    emulator.reset().unwrap();
+    assert_eq!(emulator.read8(0x003000), 0x00);
+    emulator.unload_rom();
+    assert!(emulator.coprocessors().is_empty());
+}
+
+#[test]
+fn test_coprocessor_is_clocked_and_raises_irqs() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&irq_rom()).unwrap();
+    let chip = TimerChip::new("timer", 50_000);
+    let ran = chip.ran.clone();
+    emulator.attach_coprocessor(Box::new(chip)).unwrap();
+
+    let start = emulator.cycles;
+    emulator.step_frame().unwrap();
+    // Every master cycle of the frame reaches the chip, DMA and HDMA included
+    assert_eq!(ran.load(Ordering::Relaxed), emulator.cycles - start);
+
+    // The handler acknowledges each IRQ, so it runs once per period
+    let irqs = emulator.read8(0x7E0010) as u64;
+    let expected = (emulator.cycles - start) / 50_000;
+    assert!((expected - 1..=expected).contains(&irqs), "{} IRQs, expected about {}", irqs, expected);
+}
+
+#[test]
+fn test_coprocessor_state_is_saved_and_checked() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&irq_rom()).unwrap();
+    emulator.attach_coprocessor(Box::new(TimerChip::new("timer", 1 << 40))).unwrap();
+    emulator.write8(0x003000, 0x42);
+    let state = emulator.save_state().unwrap();
+    assert_eq!(state.coprocessors.len(), 1);
+    assert_eq!(state.coprocessors[0].name, "timer");
+
+    // Round-trips through both encodings
+    for encoding in [StateEncoding::Bincode, StateEncoding::Json] {
+        let decoded = SaveState::decode(&state.encode(encoding).unwrap(), encoding).unwrap();
+        assert_eq!(decoded, state);
+    }
+
+    emulator.write8(0x003000, 0x00);
+    emulator.load_state(&state).unwrap();
+    assert_eq!(emulator.read8(0x003000), 0x42);
+
+    // Without the chip the state is refused, and nothing changes
+    let mut other = Emulator::new().unwrap();
+    other.load_rom(&irq_rom()).unwrap();
+    let before = other.save_state().unwrap();
+    let error = other.load_state(&state).unwrap_err().to_string();
+    assert!(error.contains("'timer' coprocessor, which isn't attached"), "{}", error);
+    assert_eq!(other.save_state().unwrap(), before);
+
+    // With the chip but a state saved without it, likewise
+    other.attach_coprocessor(Box::new(TimerChip::new("timer", 1 << 40))).unwrap();
+    let error = other.load_state(&before).unwrap_err().to_string();
+    assert!(error.contains("nothing for the 'timer' coprocessor"), "{}", error);
+}
+
+#[test]
+fn test_partial_load_resets_mismatched_coprocessors() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&irq_rom()).unwrap();
+    emulator.attach_coprocessor(Box::new(TimerChip::new("timer", 1 << 40))).unwrap();
+    emulator.write8(0x003000, 0x42);
+    let mut state = emulator.save_state().unwrap();
+    state.cpu.a = 0x2468;
+    state.coprocessors[0].data.truncate(3);
+
+    let partial = SaveState::recover(&state.to_bytes().unwrap(), StateEncoding::Bincode);
+    assert!(partial.coprocessors.is_some());
+    let recovery = emulator.load_partial_state(&partial).unwrap();
+    assert_eq!(recovery.reset.len(), 1);
+    assert_eq!(recovery.reset[0].0, StateSection::Coprocessors);
+    assert_eq!(emulator.save_state().unwrap().cpu.a, 0x2468);
+    assert_eq!(emulator.read8(0x003000), 0x00);
+}
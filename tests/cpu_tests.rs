@@ -1,4 +1,4 @@
-use ccsnes::cpu::Cpu;
+use ccsnes::cpu::{Bus65816, Cpu, IrqSource};
 use ccsnes::memory::Bus;
 
 #[test]
@@ -296,4 +296,176 @@ fn test_block_move() {
     
     // Should continue to next instruction now
     assert_eq!(cpu.get_registers().pc, 0x8003);
-}
\ No newline at end of file
+}
+#[test]
+fn test_disasm_iter_slice() {
+    use ccsnes::cpu::disasm::DisasmIter;
+
+    let code = [
+        0xC2, 0x30,             // REP #$30
+        0xA9, 0x34, 0x12,       // LDA #$1234
+        0xA2, 0x00, 0x00,       // LDX #$0000
+        0xE2, 0x20,             // SEP #$20
+        0xA9, 0x01,             // LDA #$01
+        0x9D, 0x00, 0x21,       // STA $2100,X
+        0xD0, 0xFB,             // BNE $00800C
+        0x22, 0x56, 0x34, 0x12, // JSL $123456
+        0x54, 0x7E, 0x7F,       // MVN $7F,$7E
+        0xB7, 0x10,             // LDA [$10],Y
+    ];
+    let listing: Vec<_> = DisasmIter::from_slice(&code, 0x8000, 0x8000).collect();
+    let text: Vec<String> = listing.iter().map(|i| format!("{} {}", i.mnemonic(), i.operand_text())).collect();
+    assert_eq!(text, [
+        "REP #$30",
+        "LDA #$1234",
+        "LDX #$0000",
+        "SEP #$20",
+        "LDA #$01",
+        "STA $2100,X",
+        "BNE $00800C",
+        "JSL $123456",
+        "MVN $7F,$7E",
+        "LDA [$10],Y",
+    ]);
+
+    assert_eq!(listing[1].len(), 3);
+    assert_eq!(listing[1].cycles, 2);
+    assert_eq!(listing[6].target, Some(0x800C));
+    assert_eq!(listing[7].target, Some(0x123456));
+    assert_eq!(listing[5].target, None);
+    assert_eq!(listing[2].to_string(), "$008005: A2 00 00    LDX #$0000");
+
+    // An instruction cut off by the end of the slice is not yielded
+    assert_eq!(DisasmIter::from_slice(&code[..4], 0x8000, 0x8000).count(), 1);
+}
+
+#[test]
+#[cfg(feature = "debugger")]
+fn test_disasm_iter_bus() {
+    use ccsnes::cpu::disasm::DisasmIter;
+    use ccsnes::debug::Debugger;
+
+    let mut bus = Bus::new();
+    for (i, byte) in [0x4C, 0x00, 0x90, 0x80, 0x80, 0x00].iter().enumerate() {
+        bus.write8(0x0100 + i as u32, *byte);
+    }
+    let listing: Vec<_> = DisasmIter::from_bus(&bus, 0x0100).take(3).collect();
+    assert_eq!(listing[0].target, Some(0x9000));
+    // BRA -128 from $0105 lands at $0085; BRK carries a signature byte
+    assert_eq!(listing[1].target, Some(0x0085));
+    assert_eq!(listing[2].len(), 2);
+
+    // 16-bit widths from the status register
+    bus.write8(0x0200, 0xA9);
+    let wide = DisasmIter::from_bus(&bus, 0x0200).with_status(0x00, false).next().unwrap();
+    assert_eq!(wide.len(), 3);
+    let narrow = DisasmIter::from_bus(&bus, 0x0200).with_status(0x00, true).next().unwrap();
+    assert_eq!(narrow.len(), 2);
+
+    let debugger = Debugger::new();
+    let text = debugger.disassemble(&bus, 0x0100, 2);
+    assert_eq!(text.lines().collect::<Vec<_>>(), [
+        "$000100: 4C 00 90    JMP $9000",
+        "$000103: 80 80       BRA $000085",
+    ]);
+}
+
+// Flat 16MB of memory that records every access, for testing the core
+// without the SNES memory map
+struct MockBus {
+    memory: Vec<u8>,
+    log: Vec<(char, u32, u8)>,
+}
+
+impl MockBus {
+    fn with_program(address: u32, code: &[u8]) -> Self {
+        let mut memory = vec![0; 0x1000000];
+        memory[address as usize..address as usize + code.len()].copy_from_slice(code);
+        memory[0xFFFC..0xFFFE].copy_from_slice(&(address as u16).to_le_bytes());
+        Self { memory, log: Vec::new() }
+    }
+}
+
+impl Bus65816 for MockBus {
+    fn read(&mut self, address: u32) -> u8 {
+        let value = self.memory[(address & 0xFFFFFF) as usize];
+        self.log.push(('r', address & 0xFFFFFF, value));
+        value
+    }
+
+    fn write(&mut self, address: u32, value: u8) {
+        self.memory[(address & 0xFFFFFF) as usize] = value;
+        self.log.push(('w', address & 0xFFFFFF, value));
+    }
+}
+
+#[test]
+fn test_cpu_runs_on_a_mock_bus() {
+    // LDA #$5A; STA $2000 (an I/O register on the SNES, plain memory here)
+    let mut bus = MockBus::with_program(0x8000, &[0xA9, 0x5A, 0x8D, 0x00, 0x20]);
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut bus).unwrap();
+    bus.log.clear();
+
+    cpu.step(&mut bus).unwrap();
+    cpu.step(&mut bus).unwrap();
+    assert_eq!(bus.memory[0x2000], 0x5A);
+    assert_eq!(
+        bus.log[..5],
+        [('r', 0x8000, 0xA9), ('r', 0x8001, 0x5A), ('r', 0x8002, 0x8D), ('r', 0x8003, 0x00), ('r', 0x8004, 0x20)]
+    );
+    assert_eq!(bus.log.last(), Some(&('w', 0x2000, 0x5A)));
+}
+
+#[test]
+fn test_lda_immediate_flags_for_every_value() {
+    for value in 0..=0xFFu8 {
+        let mut bus = MockBus::with_program(0x8000, &[0xA9, value]);
+        let mut cpu = Cpu::new();
+        cpu.reset(&mut bus).unwrap();
+        cpu.step(&mut bus).unwrap();
+        let registers = cpu.get_registers();
+        assert_eq!(registers.get_a(), value as u16);
+        assert_eq!(registers.zero(), value == 0, "Z for ${:02X}", value);
+        assert_eq!(registers.negative(), value & 0x80 != 0, "N for ${:02X}", value);
+    }
+}
+
+#[test]
+fn test_asserted_interrupts_on_a_mock_bus() {
+    // CLI, then NOPs; the IRQ handler is at $9000 and the NMI's at $A000
+    let mut bus = MockBus::with_program(0x8000, &[0x58, 0xEA, 0xEA]);
+    bus.memory[0xFFFE..0x10000].copy_from_slice(&0x9000u16.to_le_bytes());
+    bus.memory[0xFFFA..0xFFFC].copy_from_slice(&0xA000u16.to_le_bytes());
+    let mut cpu = Cpu::new();
+    cpu.reset(&mut bus).unwrap();
+    
+    // Held while interrupts are disabled, taken once CLI clears I
+    cpu.assert_irq(IrqSource::Cartridge);
+    assert!(cpu.is_irq_asserted(IrqSource::Cartridge));
+    assert!(!cpu.service_asserted(&mut bus).unwrap());
+    cpu.step(&mut bus).unwrap();
+    bus.log.clear();
+    assert!(cpu.service_asserted(&mut bus).unwrap());
+    assert_eq!(cpu.registers.pc, 0x9000);
+    // The entry shows on the bus: PC and P pushed, then the vector read
+    assert_eq!(
+        bus.log,
+        [('w', 0x01FF, 0x80), ('w', 0x01FE, 0x01), ('w', 0x01FD, 0x20), ('r', 0xFFFE, 0x00), ('r', 0xFFFF, 0x90)]
+    );
+    // The handler has I set, so the held line waits
+    assert!(!cpu.service_asserted(&mut bus).unwrap());
+    cpu.release_irq(IrqSource::Cartridge);
+    assert!(!cpu.is_irq_asserted(IrqSource::Cartridge));
+    
+    // An NMI is taken once, I or not
+    cpu.assert_nmi();
+    assert!(cpu.service_asserted(&mut bus).unwrap());
+    assert_eq!(cpu.registers.pc, 0xA000);
+    assert!(!cpu.service_asserted(&mut bus).unwrap());
+    
+    // Reset drops every asserted line
+    cpu.assert_irq(IrqSource::Timer);
+    cpu.reset(&mut bus).unwrap();
+    assert!(!cpu.is_irq_asserted(IrqSource::Timer));
+}
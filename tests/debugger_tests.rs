@@ -0,0 +1,467 @@
+#![cfg(feature = "debugger")]
+
+use ccsnes::debug::frame_diff::{compare_frames, highlight, run_against_dump, FrameDump, FRAME_BYTES};
+use ccsnes::debug::spc700::{decode, disassemble, instruction_length};
+use ccsnes::debug::interrupts::{InterruptSource, InterruptTracker};
+use ccsnes::debug::{DebugContext, Debugger, InterruptEvent, InterruptKind, WatchFormat, WatchHistory, WatchSize};
+use ccsnes::memory::Bus;
+use ccsnes::ppu::Ppu;
+use ccsnes::Emulator;
+use ccsnes_test_support::{fresh_dir, LoRom};
+
+#[test]
+fn test_spc700_disassembler() {
+    let mut ram = vec![0u8; 0x10000];
+    ram[0x0200..0x020E].copy_from_slice(&[
+        0xCD, 0xEF,             // MOV X, #$EF
+        0x8F, 0xAA, 0xF4,       // MOV $F4, #$AA
+        0xD0, 0xFC,             // BNE $0203
+        0x3F, 0x34, 0x12,       // CALL $1234
+        0xAA, 0x05, 0x60,       // MOV1 C, $0005.3
+        0x6F,                   // RET
+    ]);
+
+    let listing: Vec<String> = disassemble(&ram, 0x0200, 6).into_iter().map(|i| i.text).collect();
+    assert_eq!(listing, [
+        "MOV X, #$EF",
+        "MOV $F4, #$AA",
+        "BNE $0203",
+        "CALL $1234",
+        "MOV1 C, $0005.3",
+        "RET",
+    ]);
+
+    let instruction = decode(&ram, 0x0202);
+    assert_eq!(instruction.bytes, [0x8F, 0xAA, 0xF4]);
+    assert_eq!(instruction.to_string(), "$0202: 8F AA F4  MOV $F4, #$AA");
+
+    assert_eq!(instruction_length(0x00), 1);
+    assert_eq!(instruction_length(0xE4), 2);
+    assert_eq!(instruction_length(0x2E), 3);
+
+    // Decoding wraps at the end of APU RAM
+    ram[0xFFFF] = 0xE8;
+    ram[0x0000] = 0x42;
+    assert_eq!(decode(&ram, 0xFFFF).text, "MOV A, #$42");
+}
+
+#[test]
+fn test_debugger_spc700_context() {
+    let mut emulator = Emulator::new().unwrap();
+    let mut debugger = Debugger::new();
+    debugger.enabled = true;
+    assert_eq!(debugger.context(), DebugContext::Cpu);
+
+    // SPC700 breakpoints are independent of CPU breakpoints
+    debugger.spc_breakpoints.add_pc_breakpoint(0xFFC0);
+    assert!(debugger.should_break_spc(emulator.apu().spc700()));
+    assert!(!debugger.should_break(&emulator.cpu));
+
+    debugger.set_context(DebugContext::Spc700);
+    assert!(debugger.registers(&emulator).starts_with("A:00 X:00 Y:00 SP:FF PC:FFC0"));
+
+    // Single-step runs one instruction of the IPL boot code
+    debugger.step(&mut emulator).unwrap();
+    assert_eq!(emulator.apu().spc700().pc(), 0xFFC2);
+    assert_eq!(emulator.apu().spc700().registers().x, 0xEF);
+    assert!(!debugger.should_break_spc(emulator.apu().spc700()));
+
+    let listing = debugger.disassemble_spc(emulator.apu().spc700(), 0xFFC0, 2);
+    assert_eq!(listing, "$FFC0: CD EF     MOV X, #$EF\n$FFC2: BD        MOV SP, X\n");
+
+    let dump = debugger.memory_dump_spc(emulator.apu().spc700(), 0xFFF0, 32);
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("$FFF0: "));
+    assert!(lines[1].starts_with("$0000: "));
+}
+
+// LoROM that idles in a loop: BRA *
+fn idle_rom() -> Vec<u8> {
+    ccsnes_test_support::idle_rom("FRAME DIFF TEST")
+}
+
+#[test]
+fn test_compare_and_highlight_frames() {
+    let a = vec![0x40u8; FRAME_BYTES];
+    let mut b = a.clone();
+    assert_eq!(compare_frames(0, &a, &b), None);
+
+    // Alpha is ignored
+    b[3] = 0;
+    assert_eq!(compare_frames(0, &a, &b), None);
+
+    let pixel = |x: usize, y: usize| (y * 256 + x) * 4;
+    b[pixel(10, 5)] = 0xFF;
+    b[pixel(3, 9) + 2] = 0xFF;
+    let diff = compare_frames(7, &a, &b).unwrap();
+    assert_eq!(diff.frame, 7);
+    assert_eq!(diff.pixels, 2);
+    assert_eq!(diff.first, (10, 5));
+    assert_eq!(diff.bounds, (3, 5, 10, 9));
+
+    let image = highlight(&a, &b);
+    assert_eq!(&image[pixel(10, 5)..pixel(10, 5) + 4], &[0xFF, 0x00, 0xFF, 0xFF]);
+    assert_ne!(&image[0..3], &[0xFF, 0x00, 0xFF]);
+
+    // The extra lines of an overscan frame all differ
+    let mut tall = a.clone();
+    tall.resize(256 * 239 * 4, 0x40);
+    let diff = compare_frames(0, &a, &tall).unwrap();
+    assert_eq!(diff.pixels, 256 * 15);
+    assert_eq!(diff.first, (0, 224));
+}
+
+#[test]
+fn test_frame_dump_divergence() {
+    let dir = fresh_dir("ccsnes_frame_dump");
+    let path = dir.join("dump.ccfd");
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&idle_rom()).unwrap();
+
+    let dump = FrameDump::record(&mut emulator, 3).unwrap();
+    dump.save(&path).unwrap();
+    let loaded = FrameDump::load(&path).unwrap();
+    assert_eq!(loaded, dump);
+
+    // A fresh instance reproduces the recording exactly
+    let mut replay = Emulator::new().unwrap();
+    replay.load_rom(&idle_rom()).unwrap();
+    assert_eq!(run_against_dump(&mut replay, &loaded).unwrap(), None);
+
+    let mut frames: Vec<Vec<u8>> = loaded.frames().to_vec();
+    frames[1][0] ^= 0xFF;
+    let mut tampered = FrameDump::new();
+    for frame in &frames {
+        tampered.push(frame).unwrap();
+    }
+    assert!(tampered.push(&[0; 4]).is_err());
+
+    let mut replay = Emulator::new().unwrap();
+    replay.load_rom(&idle_rom()).unwrap();
+    let diff = run_against_dump(&mut replay, &tampered).unwrap().unwrap();
+    assert_eq!(diff.frame, 1);
+    assert_eq!(diff.first, (0, 0));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_dump_and_restore_graphics_memory() {
+    let dir = fresh_dir("ccsnes_graphics_dump");
+    let mut debugger = Debugger::new();
+    let mut emulator = Emulator::new().unwrap();
+
+    let vram: Vec<u8> = (0..0x10000).map(|i| (i * 7) as u8).collect();
+    let cgram: Vec<u8> = (0..0x200).map(|i| (i ^ 0x5A) as u8).collect();
+    let oam: Vec<u8> = (0..544).map(|i| (i * 3) as u8).collect();
+    emulator.ppu_mut().set_vram(&vram);
+    emulator.ppu_mut().set_cgram(&cgram);
+    emulator.ppu_mut().set_oam(&oam);
+
+    for memory in ["vram", "cgram", "oam"] {
+        let path = dir.join(format!("{}.bin", memory));
+        let message = debugger.execute_command(&mut emulator, &format!("dump{} {}", memory, path.display())).unwrap();
+        assert!(message.starts_with("Wrote"), "{}", message);
+    }
+    assert_eq!(std::fs::read(dir.join("vram.bin")).unwrap(), vram);
+    assert_eq!(std::fs::read(dir.join("cgram.bin")).unwrap(), cgram);
+    assert_eq!(std::fs::read(dir.join("oam.bin")).unwrap(), oam);
+
+    let mut fresh = Emulator::new().unwrap();
+    for memory in ["vram", "cgram", "oam"] {
+        let path = dir.join(format!("{}.bin", memory));
+        debugger.execute_command(&mut fresh, &format!("restore{} {}", memory, path.display())).unwrap();
+    }
+    assert_eq!(fresh.ppu().get_vram(), &vram[..]);
+    assert_eq!(fresh.ppu().get_cgram(), &cgram[..]);
+    assert_eq!(fresh.save_state().unwrap().ppu.oam, oam);
+
+    // Wrong sizes, missing files and unknown commands are errors
+    std::fs::write(dir.join("short.bin"), [0; 16]).unwrap();
+    let short = format!("restorevram {}", dir.join("short.bin").display());
+    assert!(debugger.execute_command(&mut fresh, &short).is_err());
+    assert!(debugger.execute_command(&mut fresh, "dumpvram").is_err());
+    assert!(debugger.execute_command(&mut fresh, "dumpwram x.bin").is_err());
+}
+
+#[test]
+fn test_palette_export_import() {
+    use ccsnes::debug::palette;
+
+    let dir = fresh_dir("ccsnes_palette");
+    let mut debugger = Debugger::new();
+    let mut emulator = Emulator::new().unwrap();
+    let cgram: Vec<u8> = (0..256u16).flat_map(|i| (i.wrapping_mul(0x111) & 0x7FFF).to_le_bytes()).collect();
+    emulator.ppu_mut().set_cgram(&cgram);
+
+    // BGR555 survives the trip through 8-bit RGB
+    assert_eq!(palette::to_rgb(0x7FFF), [0xFF, 0xFF, 0xFF]);
+    assert_eq!(palette::to_rgb(0x001F), [0xFF, 0, 0]);
+    let pal = dir.join("game.pal");
+    debugger.execute_command(&mut emulator, &format!("exportpal {}", pal.display())).unwrap();
+    assert_eq!(std::fs::read(&pal).unwrap().len(), 768);
+
+    let png = std::fs::read({
+        let path = dir.join("game.png");
+        debugger.execute_command(&mut emulator, &format!("exportpal {}", path.display())).unwrap();
+        path
+    })
+    .unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(u32::from_be_bytes([png[16], png[17], png[18], png[19]]), palette::SWATCH_SIZE as u32);
+
+    let mut fresh = Emulator::new().unwrap();
+    let message = debugger.execute_command(&mut fresh, &format!("importpal {} lock", pal.display())).unwrap();
+    assert!(message.contains("256 colors"), "{}", message);
+    assert_eq!(fresh.ppu().get_cgram(), &cgram[..]);
+
+    // Locked: the game's CGRAM writes are dropped until unlocked
+    fresh.ppu_mut().write_register(0x2121, 0);
+    fresh.ppu_mut().write_register(0x2122, 0x12);
+    assert_eq!(fresh.ppu().get_cgram()[0], cgram[0]);
+    debugger.execute_command(&mut fresh, "unlockpal").unwrap();
+    assert!(!fresh.ppu().is_palette_locked());
+
+    // JASC-PAL text replaces just the colors it lists
+    let jasc = palette::parse(b"JASC-PAL\r\n0100\r\n2\r\n255 0 0\r\n0 0 255\r\n").unwrap();
+    assert_eq!(jasc, vec![0x1F, 0x00, 0x00, 0x7C]);
+    assert!(palette::parse(b"JASC-PAL\n0100\n1\n1 2\n").is_err());
+    assert!(palette::parse(&[0; 1000]).is_err());
+}
+
+#[test]
+fn test_watch_history_ring_buffer() {
+    let mut history = WatchHistory::new(3);
+    for frame in 1..=5 {
+        history.push(frame, frame as u32 * 10);
+    }
+    assert_eq!(history.values(), vec![30, 40, 50]);
+    assert_eq!(history.range(), Some((30, 50)));
+    assert_eq!(history.since(4).map(|sample| sample.value).collect::<Vec<_>>(), vec![50]);
+    assert_eq!(history.sparkline(8), "▁▄█");
+    assert_eq!(history.sparkline(2), "▄█");
+
+    // Going back in time (a loaded state) starts over
+    history.push(2, 7);
+    assert_eq!(history.values(), vec![7]);
+
+    history.set_capacity(1);
+    history.push(3, 8);
+    assert_eq!(history.values(), vec![8]);
+}
+
+#[test]
+fn test_record_watches_per_frame() {
+    let mut emulator = Emulator::new().unwrap();
+    let mut debugger = Debugger::new();
+    debugger.add_watch("x".to_string(), 0x7E0010, WatchSize::Word, WatchFormat::Decimal);
+    debugger.set_history_frames(4);
+    for frame in 0..6u64 {
+        emulator.write16(0x7E0010, 0x0100 + frame as u16);
+        debugger.record_watches(emulator.bus(), frame);
+    }
+
+    let history = debugger.watch_history("x").unwrap();
+    assert_eq!(history.capacity(), 4);
+    assert_eq!(history.values(), vec![0x0102, 0x0103, 0x0104, 0x0105]);
+    assert_eq!(history.latest().unwrap().frame, 5);
+
+    let text = debugger.execute_command(&mut emulator, "history x 2").unwrap();
+    assert_eq!(text, "x: 4 frames, $0102-$0105, $0105 at frame 5\n▅█");
+    assert!(debugger.execute_command(&mut emulator, "history missing").is_err());
+
+    debugger.clear_watch_history();
+    assert!(debugger.watch_history("x").unwrap().is_empty());
+    assert!(debugger.watch_history("y").is_none());
+}
+
+#[test]
+fn test_debugger_reads_leave_io_registers_alone() {
+    let mut ppu = Ppu::new();
+    let mut bus = Bus::new();
+    unsafe { bus.connect_ppu(&mut ppu) };
+    let mut debugger = Debugger::new();
+    bus.set_nmi_flag(true);
+    bus.set_beam_position(0x1A5, 0x020);
+    // A high-to-low WRIO transition latches the counters
+    bus.write8(0x4201, 0x00);
+
+    debugger.add_watch("rdnmi".to_string(), 0x4210, WatchSize::Byte, WatchFormat::Hex);
+    debugger.add_watch("ophct".to_string(), 0x213C, WatchSize::Word, WatchFormat::Hex);
+    debugger.record_watches(&bus, 0);
+    assert_eq!(debugger.watch_history("rdnmi").unwrap().values(), vec![0x82]);
+    assert!(debugger.memory_dump(&bus, 0x2130, 0x10).contains("A5 20"));
+    assert_eq!(debugger.search_memory(&bus, &[0x82], 0x4210, 0x4210), vec![0x4210]);
+
+    // The vblank NMI is still pending, the latch still set and the
+    // OPHCT flip-flop still on the low byte
+    assert!(bus.nmi_flag());
+    assert!(bus.counter_latch().is_latched());
+    assert_eq!(bus.read8(0x213C), 0xA5);
+    assert_eq!(bus.read8(0x213C), 0x01);
+}
+
+#[test]
+fn test_export_tile_sheet() {
+    use ccsnes::debug::sheets::{tile_sheet, TileLayer};
+
+    let dir = fresh_dir("ccsnes_tile_sheet");
+    let mut debugger = Debugger::new();
+    let mut emulator = Emulator::new().unwrap();
+    // Mode 1, BG1 tiles at word 0; tile 1 (4bpp, words 16-31) has color 1
+    // at its top left pixel, and palette 1 makes that red
+    emulator.ppu_mut().write_register(0x2105, 0x01);
+    emulator.ppu_mut().write_register(0x210B, 0x00);
+    let mut vram = vec![0; 0x10000];
+    vram[32] = 0x80;
+    emulator.ppu_mut().set_vram(&vram);
+    let mut cgram = vec![0; 0x200];
+    cgram[34] = 0x1F;
+    emulator.ppu_mut().set_cgram(&cgram);
+
+    let sheet = tile_sheet(emulator.ppu(), TileLayer::Bg(1), 1).unwrap();
+    assert_eq!((sheet.width, sheet.height), (159, 577));
+    // Row labels take 14 pixels, then each tile is 8 pixels and a grid line
+    assert_eq!(sheet.pixel(24, 1), [0xFF, 0, 0, 0xFF]);
+    assert_eq!(sheet.pixel(25, 1)[3], 0, "color 0 is transparent");
+    assert_eq!(sheet.pixel(14, 0), [0x30, 0x30, 0x30, 0xFF]);
+
+    let path = dir.join("bg1.png");
+    let message = debugger.execute_command(&mut emulator, &format!("exporttiles bg1 {} 1", path.display())).unwrap();
+    assert!(message.contains("1024 BG1 tiles"), "{}", message);
+    assert_eq!(&std::fs::read(&path).unwrap()[..8], b"\x89PNG\r\n\x1a\n");
+
+    // Mode 1 has no BG4; palettes go to 7
+    assert!(debugger.execute_command(&mut emulator, &format!("exporttiles bg4 {}", path.display())).is_err());
+    assert!(debugger.execute_command(&mut emulator, &format!("exporttiles bg1 {} 8", path.display())).is_err());
+    assert!(debugger.execute_command(&mut emulator, "exporttiles bg9 x.png").is_err());
+    let obj = tile_sheet(emulator.ppu(), TileLayer::Obj, 0).unwrap();
+    assert_eq!(obj.height, 32 * 9 + 1);
+}
+
+#[test]
+fn test_export_sprite_overlay() {
+    use ccsnes::debug::sheets::{sprite_overlay, visible_sprites};
+
+    let dir = fresh_dir("ccsnes_sprite_overlay");
+    let mut debugger = Debugger::new();
+    let mut emulator = Emulator::new().unwrap();
+    // Every sprite parked below the picture except 0, an 8x8 priority 3
+    // sprite at (16, 32), and 1, which wraps from line 252 to the top
+    let mut oam = vec![0; 544];
+    for sprite in oam[..512].chunks_exact_mut(4) {
+        sprite[1] = 240;
+    }
+    oam[..4].copy_from_slice(&[16, 32, 0x05, 0x30]);
+    oam[4..8].copy_from_slice(&[100, 252, 0x06, 0x00]);
+    emulator.ppu_mut().set_oam(&oam);
+
+    let placed = visible_sprites(emulator.ppu());
+    assert_eq!(placed.len(), 2);
+    assert_eq!((placed[0].x, placed[0].y, placed[0].tile, placed[0].priority), (16, 32, 5, 3));
+    assert_eq!((placed[1].index, placed[1].y), (1, -4));
+
+    let overlay = sprite_overlay(emulator.ppu());
+    assert_eq!((overlay.width, overlay.height), (256, emulator.ppu().frame_height()));
+    assert_eq!(overlay.pixel(16, 32), [0xFF, 0x40, 0x40, 0xFF]);
+    assert_eq!(overlay.pixel(20, 32), [0xFF, 0x40, 0x40, 0xFF]);
+
+    let path = dir.join("sprites.png");
+    let message = debugger.execute_command(&mut emulator, &format!("exportsprites {}", path.display())).unwrap();
+    assert!(message.contains("2 visible sprites"), "{}", message);
+    assert!(message.contains("$00 at (16, 32) 8x8 tile $005 palette 0 priority 3"), "{}", message);
+    assert_eq!(&std::fs::read(&path).unwrap()[..8], b"\x89PNG\r\n\x1a\n");
+}
+
+// LoROM that enables the NMI, runs BRK #$12 and COP #$34 and then loops;
+// each handler is a bare RTI except the NMI's, which counts in $10
+fn interrupt_rom() -> Vec<u8> {
+    LoRom::new("INTERRUPT TEST", &[0x58, 0xA9, 0x80, 0x8D, 0x00, 0x42, 0x00, 0x12, 0x02, 0x34, 0x80, 0xFE])
+        .at(0x8020, &[0xE6, 0x10, 0x40])
+        .at(0x8030, &[0x40])
+        .at(0x8040, &[0x40])
+        .vector(0xFFF4, 0x8040)
+        .vector(0xFFFA, 0x8020)
+        .vector(0xFFFE, 0x8030)
+        .build()
+}
+
+fn run_to_interrupt_break(debugger: &mut Debugger, emulator: &mut Emulator) -> InterruptEvent {
+    for _ in 0..200_000 {
+        emulator.step().unwrap();
+        if let Some(event) = debugger.check_interrupts(emulator) {
+            return event;
+        }
+    }
+    panic!("no interrupt break");
+}
+
+#[test]
+fn test_break_on_interrupt_entry_and_exit() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&interrupt_rom()).unwrap();
+    let mut debugger = Debugger::new();
+    debugger.enabled = true;
+    
+    assert_eq!(debugger.execute_command(&mut emulator, "breakon").unwrap(), "Not breaking on interrupts");
+    assert!(debugger.execute_command(&mut emulator, "breakon vblank").is_err());
+    let message = debugger.execute_command(&mut emulator, "breakon rti nmi brk").unwrap();
+    assert_eq!(message, "Breaking on NMI, BRK, RTI");
+    assert!(emulator.interrupt_tracker().is_some());
+    
+    let brk = run_to_interrupt_break(&mut debugger, &mut emulator);
+    assert_eq!((brk.kind, brk.source), (InterruptKind::Brk, InterruptSource::Signature(0x12)));
+    assert_eq!((brk.pc, brk.target), (0x008006, 0x008030));
+    assert!(debugger.should_break(&emulator.cpu));
+    debugger.handle_break(&emulator.cpu, emulator.bus());
+    
+    let rti = run_to_interrupt_break(&mut debugger, &mut emulator);
+    assert_eq!(rti.source, InterruptSource::Return(Some(InterruptKind::Brk)));
+    assert_eq!((rti.pc, rti.target), (0x008030, 0x008008));
+    
+    // COP isn't chosen, but the RTI out of its handler is
+    let rti = run_to_interrupt_break(&mut debugger, &mut emulator);
+    assert_eq!(rti.source, InterruptSource::Return(Some(InterruptKind::Cop)));
+    assert_eq!(rti.to_string(), "RTI (from COP) at $008040, returning to $00800A (frame 0, line 0)");
+    
+    let nmi = run_to_interrupt_break(&mut debugger, &mut emulator);
+    assert_eq!((nmi.kind, nmi.source, nmi.target), (InterruptKind::Nmi, InterruptSource::Vblank, 0x008020));
+    assert_eq!(emulator.interrupt_tracker().unwrap().handlers(), [InterruptKind::Nmi]);
+    assert!(nmi.to_string().starts_with("NMI (vblank) at $00800A, handler $008020"), "{}", nmi);
+    let rti = run_to_interrupt_break(&mut debugger, &mut emulator);
+    assert_eq!(rti.source, InterruptSource::Return(Some(InterruptKind::Nmi)));
+    assert_eq!(emulator.read8(0x7E0010), 1);
+    
+    // Turning every kind off stops the tracking too
+    assert_eq!(debugger.execute_command(&mut emulator, "breakon off rti").unwrap(), "Breaking on NMI, BRK");
+    assert_eq!(debugger.execute_command(&mut emulator, "breakon off").unwrap(), "Not breaking on interrupts");
+    assert!(emulator.interrupt_tracker().is_none());
+    assert!(emulator.take_interrupts().is_empty());
+}
+
+#[test]
+fn test_irq_sources_are_annotated() {
+    assert_eq!(
+        InterruptSource::Irq { timer: true, ppu: false, cartridge: true }.to_string(),
+        "H/V timer, cartridge"
+    );
+    assert_eq!(InterruptSource::Return(None).to_string(), "from an unseen handler");
+    assert_eq!(InterruptKind::from_name("cop"), Some(InterruptKind::Cop));
+    
+    // Nested handlers pair with their RTIs innermost first
+    let mut tracker = InterruptTracker::new();
+    tracker.entered(InterruptKind::Nmi, InterruptSource::Vblank, 0x8000, 0x9000, 1, 225);
+    tracker.entered(InterruptKind::Brk, InterruptSource::Signature(0), 0x9000, 0xA000, 1, 225);
+    tracker.returned(0xA000, 0x9002, 1, 226);
+    tracker.returned(0x9010, 0x8000, 1, 226);
+    tracker.returned(0x8010, 0x8020, 1, 227);
+    let sources: Vec<InterruptSource> = tracker.take_events().into_iter().map(|event| event.source).collect();
+    assert_eq!(sources[2..], [
+        InterruptSource::Return(Some(InterruptKind::Brk)),
+        InterruptSource::Return(Some(InterruptKind::Nmi)),
+        InterruptSource::Return(None),
+    ]);
+    assert!(tracker.take_events().is_empty());
+}
@@ -159,4 +159,244 @@ fn test_multiple_dma_channels() {
     
     assert!(cycles > 16); // Should be more than single channel
     assert_eq!(dma.read_register(0x420B), 0x00);
-}
\ No newline at end of file
+}
+#[test]
+fn test_dma_log_records_transfers() {
+    use ccsnes::debug::TransferKind;
+
+    let mut dma = DmaController::new();
+    let mut bus = Bus::new();
+    let mut ppu = Ppu::new();
+
+    dma.write_register(0x4320, 0x01); // Two registers mode
+    dma.write_register(0x4321, 0x18); // B address = $2118
+    dma.write_register(0x4324, 0x7E); // A bank
+    let start_transfer = |dma: &mut DmaController| {
+        dma.write_register(0x4322, 0x00);
+        dma.write_register(0x4323, 0x20); // A address = $7E:2000
+        dma.write_register(0x4325, 0x00);
+        dma.write_register(0x4326, 0x08); // 2048 bytes
+        dma.write_register(0x420B, 0x04);
+    };
+
+    // Nothing is recorded until the log is enabled
+    start_transfer(&mut dma);
+    dma.execute_dma(&mut bus, &mut ppu);
+    dma.log_mut().end_frame();
+    assert!(dma.log().transfers().is_empty());
+
+    dma.log_mut().set_enabled(true);
+    start_transfer(&mut dma);
+    dma.execute_dma(&mut bus, &mut ppu);
+    assert_eq!(dma.log().current().len(), 1);
+
+    dma.log_mut().end_frame();
+    assert!(dma.log().current().is_empty());
+    let transfer = &dma.log().transfers()[0];
+    assert_eq!(transfer.kind, TransferKind::Dma);
+    assert_eq!(transfer.channel, 2);
+    assert_eq!(transfer.source, 0x7E2000);
+    assert_eq!(transfer.size, 2048);
+    assert_eq!(transfer.to_string(), "line   0  DMA  ch2 mode 1  $7E:2000 -> $2118  2048 bytes");
+
+    let dump = dma.log().dump();
+    assert!(dump.ends_with("1 transfers, 2048 bytes\n"));
+
+    let timeline = dma.log().timeline();
+    let rows: Vec<&str> = timeline.lines().collect();
+    assert_eq!(rows.len(), 9);
+    assert!(rows[3].starts_with("ch2  D."));
+    assert!(rows[1].starts_with("ch0  .."));
+}
+
+// Copy `data` from $7E:1000 through $2118/$2119 to `vram_address` with DMA mode 1
+fn dma_to_vram(dma: &mut DmaController, bus: &mut Bus, ppu: &mut Ppu, vram_address: u16, data: &[u8]) {
+    for (i, &byte) in data.iter().enumerate() {
+        bus.write8(0x1000 + i as u32, byte);
+    }
+    ppu.write_register(0x2115, 0x80);
+    ppu.write_register(0x2116, vram_address as u8);
+    ppu.write_register(0x2117, (vram_address >> 8) as u8);
+    dma.write_register(0x4300, 0x01); // Two registers, $2118/$2119
+    dma.write_register(0x4301, 0x18);
+    dma.write_register(0x4302, 0x00);
+    dma.write_register(0x4303, 0x10);
+    dma.write_register(0x4304, 0x7E);
+    dma.write_register(0x4305, data.len() as u8);
+    dma.write_register(0x4306, (data.len() >> 8) as u8);
+    dma.write_register(0x420B, 0x01);
+    dma.execute_dma(bus, ppu);
+}
+
+// Mode 0, BG1 tile data at $1000 and an all-zero tilemap at $0000, color 3 red
+fn tile_test_ppu() -> Ppu {
+    let mut ppu = Ppu::new();
+    ppu.write_register(0x2100, 0x0F);
+    ppu.write_register(0x2105, 0x00);
+    ppu.write_register(0x2107, 0x00);
+    ppu.write_register(0x210B, 0x01);
+    ppu.write_register(0x212C, 0x01);
+    ppu.write_register(0x2121, 0x06);
+    ppu.write_register(0x2122, 0x1F);
+    ppu.write_register(0x2122, 0x00);
+    ppu
+}
+
+fn run_frame(ppu: &mut Ppu, bus: &mut Bus) {
+    let frame = ppu.get_frame_count();
+    while ppu.get_frame_count() == frame {
+        ppu.step(bus);
+    }
+}
+
+fn pixel(ppu: &Ppu, x: usize, y: usize) -> [u8; 3] {
+    let offset = (y * 256 + x) * 4;
+    let frame = ppu.get_frame_buffer();
+    [frame[offset], frame[offset + 1], frame[offset + 2]]
+}
+
+#[test]
+fn test_dma_tile_upload_shows_next_frame() {
+    let mut dma = DmaController::new();
+    let mut bus = Bus::new();
+    let mut ppu = tile_test_ppu();
+
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 8, 8), [0, 0, 0]);
+
+    // Uploaded during vblank, drawn by the frame after
+    dma_to_vram(&mut dma, &mut bus, &mut ppu, 0x1000, &[0xFF; 32]);
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 8, 8), [248, 0, 0]);
+    assert_eq!(ppu.cached_tile(0, 0x1000, 2), &[3; 64]);
+
+    dma_to_vram(&mut dma, &mut bus, &mut ppu, 0x1000, &[0x00; 32]);
+    run_frame(&mut ppu, &mut bus);
+    assert_eq!(pixel(&ppu, 8, 8), [0, 0, 0]);
+    assert_eq!(ppu.cached_tile(0, 0x1000, 2), &[0; 64]);
+}
+
+#[test]
+fn test_dma_invalidates_only_written_tiles() {
+    let mut dma = DmaController::new();
+    let mut bus = Bus::new();
+    let mut ppu = Ppu::new();
+
+    dma_to_vram(&mut dma, &mut bus, &mut ppu, 0x1000, &[0xFF; 64]);
+    ppu.cached_tile(0, 0x1000, 2);
+    ppu.cached_tile(1, 0x1000, 2);
+    ppu.cached_tile(2, 0x1000, 2);
+    assert!(ppu.tile_cache().is_current(0, 0x1000, 2));
+
+    // Tile 1 alone is rewritten, 8 words in
+    dma_to_vram(&mut dma, &mut bus, &mut ppu, 0x1008, &[0x00; 16]);
+    assert!(ppu.tile_cache().is_current(0, 0x1000, 2));
+    assert!(!ppu.tile_cache().is_current(1, 0x1000, 2));
+    assert!(ppu.tile_cache().is_current(2, 0x1000, 2));
+    assert_eq!(ppu.cached_tile(1, 0x1000, 2)[0], 0);
+
+    // A 4bpp view of the same bytes is a different entry
+    assert!(!ppu.tile_cache().is_current(0, 0x1000, 4));
+}
+
+#[test]
+fn test_cpu_port_and_state_writes_invalidate_tiles() {
+    let mut ppu = Ppu::new();
+    ppu.cached_tile(0, 0x2000, 4);
+    assert!(ppu.tile_cache().is_current(0, 0x2000, 4));
+
+    // The second bitplane pair, 8 words in
+    ppu.write_register(0x2115, 0x80);
+    ppu.write_register(0x2116, 0x08);
+    ppu.write_register(0x2117, 0x20);
+    ppu.write_register(0x2118, 0xFF);
+    ppu.write_register(0x2119, 0xFF);
+    assert!(!ppu.tile_cache().is_current(0, 0x2000, 4));
+    assert_eq!(ppu.cached_tile(0, 0x2000, 4)[0], 0x0C);
+
+    let state = ppu.save_state();
+    let mut restored = Ppu::new();
+    restored.cached_tile(0, 0x2000, 4);
+    restored.load_state(&state);
+    assert!(!restored.tile_cache().is_current(0, 0x2000, 4));
+    assert_eq!(restored.cached_tile(0, 0x2000, 4)[0], 0x0C);
+}
+
+// One channel 0 transfer of `size` bytes between `a_address` and $21xx
+fn dma_channel0(dma: &mut DmaController, bus: &mut Bus, ppu: &mut Ppu, control: u8, b_address: u8, a_address: u32, size: u16) {
+    dma.write_register(0x4300, control);
+    dma.write_register(0x4301, b_address);
+    dma.write_register(0x4302, a_address as u8);
+    dma.write_register(0x4303, (a_address >> 8) as u8);
+    dma.write_register(0x4304, (a_address >> 16) as u8);
+    dma.write_register(0x4305, size as u8);
+    dma.write_register(0x4306, (size >> 8) as u8);
+    dma.write_register(0x420B, 0x01);
+    dma.execute_dma(bus, ppu);
+}
+
+#[test]
+fn test_dma_b_address_wraps_within_page() {
+    let mut dma = DmaController::new();
+    let mut bus = Bus::new();
+    let mut ppu = Ppu::new();
+    bus.write8(0x1000, 0xAA);
+    bus.write8(0x1001, 0x0F);
+
+    // Mode 1 from $21FF: the second byte goes to $2100, not $2200
+    dma_channel0(&mut dma, &mut bus, &mut ppu, 0x01, 0xFF, 0x7E1000, 2);
+    assert_eq!(ppu.registers.inidisp, 0x0F);
+}
+
+#[test]
+fn test_dma_apu_mirrors_and_unmapped_b_addresses() {
+    let mut dma = DmaController::new();
+    let mut bus = Bus::new();
+    let mut ppu = Ppu::new();
+    bus.write8(0x1000, 0x11);
+    bus.write8(0x1001, 0x22);
+
+    // $217F is a mirror of APU port 3; $2180 isn't mapped
+    dma_channel0(&mut dma, &mut bus, &mut ppu, 0x01, 0x7F, 0x7E1000, 2);
+    assert_eq!(bus.read8(0x2143), 0x11);
+
+    // Reading an unmapped B address stores open bus
+    bus.write8(0x1000, 0x55);
+    dma_channel0(&mut dma, &mut bus, &mut ppu, 0x80, 0x90, 0x7E1000, 1);
+    assert_eq!(bus.read8(0x1000), 0x00);
+}
+
+#[test]
+fn test_dma_a_address_wraps_within_bank() {
+    let mut dma = DmaController::new();
+    let mut bus = Bus::new();
+    let mut ppu = Ppu::new();
+    bus.write8(0x7EFFFF, 0x12);
+    bus.write8(0x7E0000, 0x34);
+    bus.write8(0x7F0000, 0x99);
+
+    // The second byte comes from $7E:0000, not $7F:0000
+    dma_channel0(&mut dma, &mut bus, &mut ppu, 0x00, 0x00, 0x7EFFFF, 2);
+    assert_eq!(ppu.registers.inidisp, 0x34);
+    assert_eq!(dma.read_register(0x4302), 0x01);
+    assert_eq!(dma.read_register(0x4303), 0x00);
+    assert_eq!(dma.read_register(0x4304), 0x7E);
+
+    // Decrementing from $0000 wraps to $FFFF in the same bank
+    dma_channel0(&mut dma, &mut bus, &mut ppu, 0x10, 0x00, 0x7E0000, 2);
+    assert_eq!(ppu.registers.inidisp, 0x12);
+    assert_eq!(dma.read_register(0x4302), 0xFE);
+    assert_eq!(dma.read_register(0x4303), 0xFF);
+    assert_eq!(dma.read_register(0x4304), 0x7E);
+}
+
+#[test]
+fn test_dma_a_bus_cannot_reach_registers() {
+    let mut dma = DmaController::new();
+    let mut bus = Bus::new();
+    let mut ppu = Ppu::new();
+    bus.write8(0x2140, 0x77);
+
+    dma_channel0(&mut dma, &mut bus, &mut ppu, 0x00, 0x00, 0x002140, 1);
+    assert_eq!(ppu.registers.inidisp, 0x00);
+}
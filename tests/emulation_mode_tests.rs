@@ -0,0 +1,329 @@
+// Emulation-mode behavior of the 65816: XCE transitions, the forced M/X
+// flags, the B flag in pushed status bytes and interrupt vector selection.
+use ccsnes::cpu::Cpu;
+use ccsnes::memory::Bus;
+
+const NATIVE_COP: u16 = 0x9000;
+const NATIVE_BRK: u16 = 0x9100;
+const NATIVE_NMI: u16 = 0x9200;
+const NATIVE_IRQ: u16 = 0x9300;
+const EMU_COP: u16 = 0x9400;
+const EMU_NMI: u16 = 0x9500;
+const EMU_IRQ_BRK: u16 = 0x9600;
+
+// CPU reset into emulation mode with `code` at $8000 and a distinct handler
+// address behind every vector
+fn setup(code: &[u8]) -> (Cpu, Bus) {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    for (i, &byte) in code.iter().enumerate() {
+        bus.write8(0x8000 + i as u32, byte);
+    }
+    bus.write16(0xFFFC, 0x8000);
+    bus.write16(0xFFE4, NATIVE_COP);
+    bus.write16(0xFFE6, NATIVE_BRK);
+    bus.write16(0xFFEA, NATIVE_NMI);
+    bus.write16(0xFFEE, NATIVE_IRQ);
+    bus.write16(0xFFF4, EMU_COP);
+    bus.write16(0xFFFA, EMU_NMI);
+    bus.write16(0xFFFE, EMU_IRQ_BRK);
+    cpu.reset(&mut bus).unwrap();
+    (cpu, bus)
+}
+
+fn run(cpu: &mut Cpu, bus: &mut Bus, instructions: usize) {
+    for _ in 0..instructions {
+        cpu.step(bus).unwrap();
+    }
+}
+
+// CLC; XCE
+const TO_NATIVE: [u8; 2] = [0x18, 0xFB];
+
+#[test]
+fn test_xce_enters_native_mode() {
+    let (mut cpu, mut bus) = setup(&[0x18, 0xFB, 0xC2, 0x30]); // CLC; XCE; REP #$30
+    run(&mut cpu, &mut bus, 2);
+
+    let regs = cpu.get_registers();
+    assert!(!regs.emulation_mode);
+    // The old emulation flag lands in carry
+    assert!(regs.carry());
+    // M and X stay set until cleared explicitly
+    assert!(regs.memory_width());
+    assert!(regs.index_width());
+
+    run(&mut cpu, &mut bus, 1);
+    assert!(!cpu.get_registers().memory_width());
+    assert!(!cpu.get_registers().index_width());
+}
+
+#[test]
+fn test_xce_in_native_mode_with_carry_clear_stays_native() {
+    let (mut cpu, mut bus) = setup(&[0x18, 0xFB, 0x18, 0xFB]); // CLC; XCE; CLC; XCE
+    run(&mut cpu, &mut bus, 4);
+    assert!(!cpu.get_registers().emulation_mode);
+    assert!(!cpu.get_registers().carry());
+}
+
+#[test]
+fn test_xce_back_to_emulation_forces_widths_and_stack_page() {
+    let mut code = TO_NATIVE.to_vec();
+    code.extend_from_slice(&[
+        0xC2, 0x30, // REP #$30
+        0xA2, 0x34, 0x12, // LDX #$1234
+        0xA0, 0x78, 0x56, // LDY #$5678
+        0xA9, 0x80, 0x05, // LDA #$0580
+        0x1B, // TCS
+        0x38, // SEC
+        0xFB, // XCE
+    ]);
+    let (mut cpu, mut bus) = setup(&code);
+    run(&mut cpu, &mut bus, 9);
+
+    let regs = cpu.get_registers();
+    assert!(regs.emulation_mode);
+    assert!(!regs.carry());
+    assert!(regs.memory_width());
+    assert!(regs.index_width());
+    // Index high bytes are cleared, the stack moves to page 1
+    assert_eq!(regs.x, 0x0034);
+    assert_eq!(regs.y, 0x0078);
+    assert_eq!(regs.s, 0x0180);
+    // The accumulator's high byte (B) survives
+    assert_eq!(regs.a, 0x0580);
+}
+
+#[test]
+fn test_rep_cannot_clear_widths_in_emulation_mode() {
+    let (mut cpu, mut bus) = setup(&[0xC2, 0x31]); // REP #$31
+    cpu.get_registers_mut().set_carry(true);
+    run(&mut cpu, &mut bus, 1);
+
+    let regs = cpu.get_registers();
+    assert!(regs.memory_width());
+    assert!(regs.index_width());
+    // Other bits are still cleared
+    assert!(!regs.carry());
+}
+
+#[test]
+fn test_plp_forces_widths_in_emulation_mode() {
+    let (mut cpu, mut bus) = setup(&[0x28]); // PLP
+    cpu.get_registers_mut().push_8(&mut bus, 0x00);
+    run(&mut cpu, &mut bus, 1);
+
+    let regs = cpu.get_registers();
+    assert!(regs.memory_width());
+    assert!(regs.index_width());
+    assert!(!regs.irq_disable());
+}
+
+#[test]
+fn test_plp_in_native_mode_loads_widths() {
+    let mut code = TO_NATIVE.to_vec();
+    code.push(0x28); // PLP
+    let (mut cpu, mut bus) = setup(&code);
+    run(&mut cpu, &mut bus, 2);
+    cpu.get_registers_mut().push_8(&mut bus, 0x00);
+    run(&mut cpu, &mut bus, 1);
+
+    assert!(!cpu.get_registers().memory_width());
+    assert!(!cpu.get_registers().index_width());
+}
+
+#[test]
+fn test_sep_index_width_clears_index_high_bytes() {
+    let mut code = TO_NATIVE.to_vec();
+    code.extend_from_slice(&[
+        0xC2, 0x10, // REP #$10
+        0xA2, 0x34, 0x12, // LDX #$1234
+        0xE2, 0x10, // SEP #$10
+    ]);
+    let (mut cpu, mut bus) = setup(&code);
+    run(&mut cpu, &mut bus, 5);
+    assert_eq!(cpu.get_registers().x, 0x0034);
+}
+
+#[test]
+fn test_index_immediates_follow_x_flag() {
+    let mut code = TO_NATIVE.to_vec();
+    code.extend_from_slice(&[
+        0xC2, 0x10, // REP #$10 (A stays 8-bit)
+        0xA0, 0xCD, 0xAB, // LDY #$ABCD
+        0xA9, 0x12, // LDA #$12
+    ]);
+    let (mut cpu, mut bus) = setup(&code);
+    run(&mut cpu, &mut bus, 5);
+    assert_eq!(cpu.get_registers().y, 0xABCD);
+    assert_eq!(cpu.get_registers().get_a(), 0x12);
+    assert_eq!(cpu.get_registers().pc, 0x8009);
+}
+
+#[test]
+fn test_emulation_stack_wraps_within_page_one() {
+    let (mut cpu, mut bus) = setup(&[0x48]); // PHA
+    {
+        let regs = cpu.get_registers_mut();
+        regs.s = 0x0100;
+        regs.set_a(0x42);
+    }
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!(cpu.get_registers().s, 0x01FF);
+    assert_eq!(bus.read8(0x0100), 0x42);
+}
+
+#[test]
+fn test_php_in_emulation_mode_pushes_b_set() {
+    let (mut cpu, mut bus) = setup(&[0x08]); // PHP
+    run(&mut cpu, &mut bus, 1);
+    let pushed = bus.read8(0x01FF);
+    assert_eq!(pushed & 0x30, 0x30);
+}
+
+#[test]
+fn test_brk_in_emulation_mode() {
+    let (mut cpu, mut bus) = setup(&[0xEA, 0x00, 0x00]); // NOP; BRK #$00
+    cpu.get_registers_mut().set_decimal(true);
+    run(&mut cpu, &mut bus, 2);
+
+    let regs = cpu.get_registers();
+    assert_eq!(regs.pc, EMU_IRQ_BRK as u32);
+    assert!(regs.irq_disable());
+    assert!(!regs.decimal());
+    // Three bytes: PC after the signature byte, then P with B set
+    assert_eq!(regs.s, 0x01FC);
+    assert_eq!(bus.read16(0x01FE), 0x8003);
+    let pushed = bus.read8(0x01FD);
+    assert_ne!(pushed & 0x10, 0);
+    assert_ne!(pushed & 0x08, 0);
+}
+
+#[test]
+fn test_brk_in_native_mode_keeps_pushed_index_width() {
+    let mut code = TO_NATIVE.to_vec();
+    code.extend_from_slice(&[0xC2, 0x30, 0x00, 0x00]); // REP #$30; BRK #$00
+    let (mut cpu, mut bus) = setup(&code);
+    run(&mut cpu, &mut bus, 4);
+
+    let regs = cpu.get_registers();
+    assert_eq!(regs.pc, NATIVE_BRK as u32);
+    // Four bytes: program bank, PC, P
+    assert_eq!(regs.s, 0x01FB);
+    assert_eq!(bus.read8(0x01FF), 0x00);
+    assert_eq!(bus.read16(0x01FD), 0x8006);
+    // Bit 4 is the X flag in native mode, not B
+    assert_eq!(bus.read8(0x01FC) & 0x30, 0x00);
+}
+
+#[test]
+fn test_cop_vectors() {
+    let (mut cpu, mut bus) = setup(&[0x02, 0x00]); // COP #$00
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!(cpu.get_registers().pc, EMU_COP as u32);
+    assert_eq!(cpu.get_registers().s, 0x01FC);
+    // Returns past the signature byte
+    assert_eq!(bus.read16(0x01FE), 0x8002);
+
+    let mut code = TO_NATIVE.to_vec();
+    code.extend_from_slice(&[0x02, 0x00]);
+    let (mut cpu, mut bus) = setup(&code);
+    cpu.get_registers_mut().set_decimal(true);
+    run(&mut cpu, &mut bus, 3);
+    assert_eq!(cpu.get_registers().pc, NATIVE_COP as u32);
+    assert_eq!(cpu.get_registers().s, 0x01FB);
+    assert!(cpu.get_registers().irq_disable());
+    assert!(!cpu.get_registers().decimal());
+}
+
+#[test]
+fn test_nmi_in_emulation_mode_pushes_b_clear() {
+    let (mut cpu, mut bus) = setup(&[]);
+    cpu.get_registers_mut().set_decimal(true);
+    cpu.trigger_nmi(&mut bus).unwrap();
+
+    let regs = cpu.get_registers();
+    assert_eq!(regs.pc, EMU_NMI as u32);
+    assert_eq!(regs.s, 0x01FC);
+    assert!(!regs.decimal());
+    assert_eq!(bus.read16(0x01FE), 0x8000);
+    let pushed = bus.read8(0x01FD);
+    // B clear tells the shared IRQ/BRK handler this was hardware
+    assert_eq!(pushed & 0x10, 0);
+    assert_ne!(pushed & 0x08, 0);
+}
+
+#[test]
+fn test_irq_in_emulation_mode_shares_brk_vector_with_b_clear() {
+    let (mut cpu, mut bus) = setup(&[]);
+    cpu.get_registers_mut().set_irq_disable(false);
+    cpu.trigger_irq(&mut bus).unwrap();
+
+    assert_eq!(cpu.get_registers().pc, EMU_IRQ_BRK as u32);
+    assert_eq!(bus.read8(0x01FD) & 0x10, 0);
+    assert!(cpu.get_registers().irq_disable());
+}
+
+#[test]
+fn test_irq_is_masked_by_i_flag() {
+    let (mut cpu, mut bus) = setup(&[]);
+    assert!(cpu.get_registers().irq_disable());
+    cpu.trigger_irq(&mut bus).unwrap();
+    assert_eq!(cpu.get_registers().pc, 0x8000);
+    assert_eq!(cpu.get_registers().s, 0x01FF);
+}
+
+#[test]
+fn test_native_interrupt_vectors_push_program_bank() {
+    let (mut cpu, mut bus) = setup(&TO_NATIVE);
+    run(&mut cpu, &mut bus, 2);
+    cpu.get_registers_mut().pc = 0x12_8000;
+    cpu.trigger_nmi(&mut bus).unwrap();
+    assert_eq!(cpu.get_registers().pc, NATIVE_NMI as u32);
+    assert_eq!(cpu.get_registers().s, 0x01FB);
+    assert_eq!(bus.read8(0x01FF), 0x12);
+
+    let (mut cpu, mut bus) = setup(&TO_NATIVE);
+    run(&mut cpu, &mut bus, 2);
+    cpu.get_registers_mut().set_irq_disable(false);
+    cpu.trigger_irq(&mut bus).unwrap();
+    assert_eq!(cpu.get_registers().pc, NATIVE_IRQ as u32);
+    assert_eq!(cpu.get_registers().s, 0x01FB);
+}
+
+#[test]
+fn test_rti_in_emulation_mode_forces_widths() {
+    let (mut cpu, mut bus) = setup(&[0x40]); // RTI
+    {
+        let regs = cpu.get_registers_mut();
+        regs.push_16(&mut bus, 0x8123);
+        regs.push_8(&mut bus, 0x00);
+    }
+    run(&mut cpu, &mut bus, 1);
+
+    let regs = cpu.get_registers();
+    assert_eq!(regs.pc, 0x8123);
+    assert_eq!(regs.s, 0x01FF);
+    assert!(regs.memory_width());
+    assert!(regs.index_width());
+}
+
+#[test]
+fn test_rti_in_native_mode_pops_program_bank() {
+    let mut code = TO_NATIVE.to_vec();
+    code.push(0x40); // RTI
+    let (mut cpu, mut bus) = setup(&code);
+    run(&mut cpu, &mut bus, 2);
+    {
+        let regs = cpu.get_registers_mut();
+        regs.push_8(&mut bus, 0x05);
+        regs.push_16(&mut bus, 0x9ABC);
+        regs.push_8(&mut bus, 0x00);
+    }
+    run(&mut cpu, &mut bus, 1);
+
+    let regs = cpu.get_registers();
+    assert_eq!(regs.pc, 0x05_9ABC);
+    assert!(!regs.memory_width());
+    assert!(!regs.index_width());
+}
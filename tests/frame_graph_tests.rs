@@ -0,0 +1,46 @@
+use ccsnes::frontend::native::frame_graph::{
+    FrameSample, FrameTimeGraph, AUDIO_COLOR, EMULATION_COLOR, FRAME_BUDGET_MS, GRAPH_HEIGHT, GRAPH_X, GRAPH_Y,
+    HISTORY_FRAMES, RENDER_COLOR,
+};
+
+fn pixel(frame: &[u8], x: i32, y: i32) -> [u8; 4] {
+    let offset = (y as usize * 256 + x as usize) * 4;
+    frame[offset..offset + 4].try_into().unwrap()
+}
+
+#[test]
+fn test_frame_graph_history() {
+    let mut graph = FrameTimeGraph::new();
+    for i in 0..HISTORY_FRAMES + 20 {
+        graph.push(FrameSample { emulation_ms: i as f32, ..Default::default() });
+    }
+    assert_eq!(graph.samples().count(), HISTORY_FRAMES);
+    assert_eq!(graph.samples().next().unwrap().emulation_ms, 20.0);
+}
+
+#[test]
+fn test_frame_graph_draw() {
+    let mut graph = FrameTimeGraph::new();
+    // Emulation takes half the frame budget, rendering a quarter
+    graph.push(FrameSample {
+        emulation_ms: FRAME_BUDGET_MS / 2.0,
+        render_ms: FRAME_BUDGET_MS / 4.0,
+        audio_fill: 1.0,
+    });
+    let mut frame = vec![0x11; 256 * 224 * 4];
+    graph.draw(&mut frame);
+
+    // The only sample is in the rightmost column
+    let x = GRAPH_X + HISTORY_FRAMES as i32 - 1;
+    let bottom = GRAPH_Y + GRAPH_HEIGHT as i32;
+    assert_eq!(pixel(&frame, x, bottom - 1), EMULATION_COLOR);
+    // The budget is half the graph height and emulation a quarter of it
+    assert_eq!(pixel(&frame, x, bottom - GRAPH_HEIGHT as i32 / 4), EMULATION_COLOR);
+    assert_eq!(pixel(&frame, x, bottom - GRAPH_HEIGHT as i32 / 4 - 2), RENDER_COLOR);
+    // A full audio queue is marked at the top
+    assert_eq!(pixel(&frame, x, GRAPH_Y), AUDIO_COLOR);
+
+    // Columns without samples stay background, and nothing outside the box is touched
+    assert_eq!(pixel(&frame, GRAPH_X, bottom - 1), [0, 0, 0, 0xFF]);
+    assert_eq!(pixel(&frame, 10, 100), [0x11; 4]);
+}
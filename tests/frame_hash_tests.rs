@@ -0,0 +1,76 @@
+use ccsnes::boot_rom;
+use ccsnes::frame_hash::{hash_audio, hash_frame, sample_to_pcm, FrameHasher, FNV_OFFSET_BASIS};
+use ccsnes::Emulator;
+
+fn boot(frames: usize) -> Emulator {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&boot_rom::build()).unwrap();
+    for _ in 0..frames {
+        emulator.step_frame().unwrap();
+    }
+    emulator
+}
+
+#[test]
+fn test_hash_algorithm_is_fixed() {
+    // FNV-1a reference vectors; if these change, every recorded hash breaks
+    let fnv = |bytes: &[u8]| {
+        let mut hasher = FrameHasher::new();
+        hasher.update(bytes);
+        hasher.finish()
+    };
+    assert_eq!(fnv(b""), FNV_OFFSET_BASIS);
+    assert_eq!(fnv(b"a"), 0xAF63_DC4C_8601_EC8C);
+    assert_eq!(fnv(b"foobar"), 0x8594_4171_F739_67E8);
+
+    // Dimensions come first, then the pixels
+    assert_eq!(hash_frame(2, 1, &[1, 2, 3, 4, 5, 6, 7, 8]), 0xF86D_D2CD_636C_CF86);
+    assert_ne!(hash_frame(1, 2, &[1, 2, 3, 4, 5, 6, 7, 8]), hash_frame(2, 1, &[1, 2, 3, 4, 5, 6, 7, 8]));
+
+    // Samples hash as rounded, clamped 16-bit PCM
+    assert_eq!(sample_to_pcm(-0.5), -16384);
+    assert_eq!(sample_to_pcm(2.0), i16::MAX);
+    assert_eq!(hash_audio(&[1.0, -0.5, 0.0]), 0x0852_8E8B_A5B5_46D3);
+}
+
+#[test]
+fn test_runs_hash_alike() {
+    let mut first = boot(0);
+    let mut second = boot(0);
+    // Volume and how often the frontend takes samples don't count
+    second.set_volume(0.5);
+    for frame in 0..30 {
+        first.step_frame().unwrap();
+        second.step_frame().unwrap();
+        if frame % 3 == 0 {
+            second.take_audio();
+        }
+        assert_eq!(first.frame_hash(), second.frame_hash(), "frame {}", frame);
+        assert_eq!(first.audio_hash(), second.audio_hash(), "frame {}", frame);
+    }
+    let frame = first.frame();
+    assert_eq!(first.frame_hash(), hash_frame(frame.width, frame.height, frame.pixels));
+    // The self test draws a picture and generates samples every frame
+    assert_ne!(first.frame_hash(), boot(0).frame_hash());
+    assert_ne!(first.audio_hash(), FNV_OFFSET_BASIS);
+}
+
+#[test]
+fn test_audio_hash_follows_state_loads() {
+    let mut emulator = boot(10);
+    let state = emulator.save_state().unwrap();
+    let hashes: Vec<u64> = (0..5)
+        .map(|_| {
+            emulator.step_frame().unwrap();
+            emulator.audio_hash()
+        })
+        .collect();
+
+    // A loaded state starts a new frame's hash
+    emulator.load_state(&state).unwrap();
+    assert_eq!(emulator.audio_hash(), FNV_OFFSET_BASIS);
+    for expected in hashes {
+        emulator.step_frame().unwrap();
+        assert_eq!(emulator.audio_hash(), expected);
+    }
+}
@@ -0,0 +1,119 @@
+use ccsnes::debug::heatmap::{AccessCounts, Heatmap, HeatmapSpace, HotAddress};
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM looping over INC $10; LDA $20; STA $2118; BRA
+fn busy_rom() -> Vec<u8> {
+    LoRom::new("HEATMAP TEST", &[0xE6, 0x10, 0xA5, 0x20, 0x8D, 0x18, 0x21, 0x80, 0xF7]).build()
+}
+
+#[test]
+fn test_access_counts() {
+    let mut counts = AccessCounts::new(64);
+    for _ in 0..3 {
+        counts.read(5);
+    }
+    counts.write(5);
+    counts.write(40);
+    counts.read(999);
+    
+    assert_eq!(counts.touched(), 2);
+    assert_eq!(counts.buckets(16), vec![(3, 1), (0, 0), (0, 1), (0, 0)]);
+    assert_eq!(
+        counts.hottest(8),
+        vec![HotAddress { address: 5, reads: 3, writes: 1 }, HotAddress { address: 40, reads: 0, writes: 1 }]
+    );
+    assert_eq!(counts.hottest(1).len(), 1);
+}
+
+#[test]
+fn test_heatmap_windows() {
+    let mut heatmap = Heatmap::new(2, false, 10);
+    heatmap.wram_write(0x100);
+    heatmap.vram_write(0x20);
+    heatmap.end_frame(11);
+    // Until a window closes, the open one is the latest
+    assert_eq!(heatmap.latest().frames, 1);
+    heatmap.end_frame(12);
+    heatmap.wram_read(0x200);
+    
+    let closed = heatmap.latest();
+    assert_eq!((closed.first_frame, closed.frames), (10, 2));
+    assert_eq!(closed.wram.writes()[0x100], 1);
+    assert_eq!(closed.wram.reads()[0x200], 0);
+    assert!(closed.counts(HeatmapSpace::Vram).is_none());
+    assert_eq!(heatmap.current().first_frame, 12);
+    assert_eq!(heatmap.current().wram.reads()[0x200], 1);
+    
+    heatmap.clear(20);
+    assert_eq!(heatmap.latest().wram.touched(), 0);
+    
+    // A window of 0 frames keeps counting
+    let mut heatmap = Heatmap::new(0, true, 0);
+    for frame in 1..=1000 {
+        heatmap.vram_read(0x10);
+        heatmap.end_frame(frame);
+    }
+    assert_eq!(heatmap.latest().frames, 1000);
+    assert_eq!(heatmap.latest().counts(HeatmapSpace::Vram).unwrap().reads()[0x10], 1000);
+}
+
+#[test]
+fn test_emulator_counts_wram_and_vram_accesses() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&busy_rom()).unwrap();
+    assert!(emulator.heatmap().is_none());
+    emulator.start_heatmap(2, true);
+    emulator.step_frame().unwrap();
+    emulator.step_frame().unwrap();
+    // Peeks aren't the game's accesses
+    emulator.bus().peek8(0x7E0030);
+    
+    let heatmap = emulator.heatmap().unwrap();
+    let window = heatmap.latest();
+    assert_eq!(window.frames, 2);
+    let hottest = window.wram.hottest(2);
+    assert_eq!(hottest[0].address, 0x10);
+    assert!(hottest[0].writes > 100);
+    assert_eq!(hottest[0].reads, hottest[0].writes);
+    assert_eq!(hottest[1], HotAddress { address: 0x20, reads: hottest[0].reads, writes: 0 });
+    assert_eq!(window.wram.touched(), 2);
+    
+    // One VRAM low byte per loop
+    let vram = window.counts(HeatmapSpace::Vram).unwrap();
+    let total: u64 = vram.writes().iter().map(|&count| count as u64).sum();
+    assert_eq!(total, hottest[0].writes as u64);
+    assert!(vram.writes().iter().skip(1).step_by(2).all(|&count| count == 0));
+    drop(heatmap);
+    
+    emulator.stop_heatmap();
+    assert!(emulator.heatmap().is_none());
+}
+
+#[test]
+#[cfg(feature = "debugger")]
+fn test_heatmap_debugger_command() {
+    use ccsnes::debug::Debugger;
+
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&busy_rom()).unwrap();
+    let mut debugger = Debugger::new();
+    
+    assert!(debugger.execute_command(&mut emulator, "heatmap").is_err());
+    let message = debugger.execute_command(&mut emulator, "heatmap on 0").unwrap();
+    assert_eq!(message, "Counting WRAM accesses until cleared");
+    emulator.step_frame().unwrap();
+    
+    let report = debugger.execute_command(&mut emulator, "heatmap wram 2").unwrap();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], "1 frames from frame 0: 2 WRAM bytes used");
+    assert!(lines[1].starts_with("  $7E0010  "), "{}", lines[1]);
+    assert!(lines[2].starts_with("  $7E0020  "), "{}", lines[2]);
+    assert!(debugger.execute_command(&mut emulator, "heatmap vram").is_err());
+    
+    debugger.execute_command(&mut emulator, "heatmap clear").unwrap();
+    assert_eq!(emulator.heatmap().unwrap().latest().wram.touched(), 0);
+    assert!(debugger.execute_command(&mut emulator, "heatmap on 60 vram").unwrap().contains("WRAM and VRAM"));
+    debugger.execute_command(&mut emulator, "heatmap off").unwrap();
+    assert!(emulator.heatmap().is_none());
+}
@@ -0,0 +1,77 @@
+use ccsnes::hot_slots::HotSlots;
+use ccsnes::savestate::SaveState;
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM that counts frames in WRAM: INC $10; BRA -5
+fn counting_rom() -> Vec<u8> {
+    LoRom::new("HOT SLOTS TEST", &[0xEE, 0x10, 0x00, 0x80, 0xFB]).build()
+}
+
+fn state(cycles: u64) -> SaveState {
+    let mut state = SaveState::new();
+    state.cycles = cycles;
+    state
+}
+
+#[test]
+fn test_hot_slot_save_load() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&counting_rom()).unwrap();
+    emulator.step_frame().unwrap();
+
+    let mut slots = HotSlots::new(2);
+    slots.save(3, &emulator).unwrap();
+    let saved = emulator.save_state().unwrap();
+    for _ in 0..5 {
+        emulator.step_frame().unwrap();
+    }
+    assert_ne!(emulator.save_state().unwrap().cycles, saved.cycles);
+
+    assert!(slots.load(3, &mut emulator).unwrap());
+    let loaded = emulator.save_state().unwrap();
+    assert_eq!(loaded.cycles, saved.cycles);
+    assert_eq!(loaded.memory.wram, saved.memory.wram);
+
+    // Slots never saved aren't in memory
+    assert!(!slots.load(4, &mut emulator).unwrap());
+}
+
+#[test]
+fn test_hot_slots_evict_least_recent() {
+    let mut slots = HotSlots::new(2);
+    slots.insert(0, state(0));
+    slots.insert(1, state(1));
+    // Saving slot 0 again makes slot 1 the oldest
+    slots.insert(0, state(10));
+    slots.insert(2, state(2));
+    assert_eq!(slots.slots(), vec![0, 2]);
+    assert_eq!(slots.get(0).unwrap().cycles, 10);
+
+    slots.set_capacity(1);
+    assert_eq!(slots.slots(), vec![2]);
+}
+
+#[test]
+fn test_pinned_hot_slots() {
+    let mut slots = HotSlots::new(1);
+    assert!(!slots.pin(5, true), "nothing to pin yet");
+
+    slots.insert(5, state(5));
+    assert!(slots.pin(5, true));
+    assert!(slots.is_pinned(5));
+
+    // Pinned slots don't count against capacity and survive any number of saves
+    for slot in 0..4 {
+        slots.insert(slot, state(slot as u64));
+    }
+    assert_eq!(slots.slots(), vec![3, 5]);
+    assert_eq!(slots.get(5).unwrap().cycles, 5);
+
+    slots.set_capacity(0);
+    assert_eq!(slots.slots(), vec![5]);
+
+    // Unpinning makes it evictable again
+    slots.pin(5, false);
+    assert!(slots.is_empty());
+}
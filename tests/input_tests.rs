@@ -0,0 +1,404 @@
+use ccsnes::config::HotkeyConfig;
+use ccsnes::input::controller::{BUTTON_A, BUTTON_B, BUTTON_L, BUTTON_SELECT, BUTTON_START};
+use ccsnes::input::hotkeys::{Binding, HotkeyAction, HotkeyManager, Modifiers};
+use ccsnes::input::peripheral::known_peripheral;
+use ccsnes::input::{
+    Input, LatencyTracker, MouseSettings, Peripheral, PeripheralConfig, Rumble, RumbleConfig, RumbleEffect, RumbleTrigger,
+    ScopeSettings, SuperScope,
+};
+use ccsnes::profile::GameProfile;
+use ccsnes::Emulator;
+use ccsnes_test_support::{idle_rom, LoRom};
+
+#[test]
+fn test_binding_parse() {
+    assert_eq!(
+        Binding::parse("F5").unwrap(),
+        Binding::Key { key: "f5".to_string(), modifiers: Modifiers::default() }
+    );
+    assert_eq!(
+        Binding::parse("Ctrl+Shift+R").unwrap(),
+        Binding::Key {
+            key: "r".to_string(),
+            modifiers: Modifiers { ctrl: true, shift: true, alt: false },
+        }
+    );
+    assert_eq!(
+        Binding::parse("Pad:Select+L").unwrap(),
+        Binding::Pad { buttons: BUTTON_SELECT | BUTTON_L }
+    );
+    
+    assert!(Binding::parse("").is_err());
+    assert!(Binding::parse("Hyper+R").is_err());
+    assert!(Binding::parse("Pad:Select+Turbo").is_err());
+}
+
+#[test]
+fn test_default_hotkeys() {
+    let manager = HotkeyManager::from_config(&HotkeyConfig::default()).unwrap();
+    
+    assert_eq!(manager.match_key("F5", Modifiers::default()), Some(HotkeyAction::SaveState));
+    assert_eq!(manager.match_key("escape", Modifiers::default()), Some(HotkeyAction::Quit));
+    assert_eq!(manager.match_key("1", Modifiers::default()), Some(HotkeyAction::ToggleBg1));
+    
+    // Reset requires Ctrl
+    assert_eq!(manager.match_key("R", Modifiers::default()), None);
+    let ctrl = Modifiers { ctrl: true, ..Modifiers::default() };
+    assert_eq!(manager.match_key("R", ctrl), Some(HotkeyAction::Reset));
+    
+    // Game keys are not claimed
+    assert_eq!(manager.match_key("Z", Modifiers::default()), None);
+}
+
+#[test]
+fn test_unbound_actions() {
+    let config = HotkeyConfig { quit: String::new(), ..HotkeyConfig::default() };
+    
+    let manager = HotkeyManager::from_config(&config).unwrap();
+    assert_eq!(manager.match_key("Escape", Modifiers::default()), None);
+    assert!(manager.bindings().iter().all(|(_, action)| *action != HotkeyAction::Quit));
+}
+
+#[test]
+fn test_pad_combo_edges() {
+    let config = HotkeyConfig { fast_forward: "Pad:Select+L".to_string(), ..HotkeyConfig::default() };
+    let mut manager = HotkeyManager::from_config(&config).unwrap();
+    
+    // Partial combo does nothing
+    assert!(manager.poll_pad(BUTTON_SELECT).is_empty());
+    
+    // Completing the combo fires once
+    assert_eq!(manager.poll_pad(BUTTON_SELECT | BUTTON_L), vec![(HotkeyAction::FastForward, true)]);
+    assert!(manager.poll_pad(BUTTON_SELECT | BUTTON_L | BUTTON_START).is_empty());
+    
+    // Releasing reports the release
+    assert_eq!(manager.poll_pad(BUTTON_L), vec![(HotkeyAction::FastForward, false)]);
+    assert!(HotkeyAction::FastForward.is_held());
+    assert!(!HotkeyAction::SaveState.is_held());
+}
+
+#[test]
+fn test_latency_tracker() {
+    let mut tracker = LatencyTracker::new();
+    assert!(tracker.stats().is_none());
+
+    // Input during frame 10, latched at frame 11, shown when frame 11 is presented
+    tracker.input_event(10, 100.0);
+    tracker.presented(10, 105.0);
+    tracker.latched(11, 110.0);
+    tracker.presented(10, 112.0);
+    assert!(tracker.stats().is_none());
+    tracker.presented(11, 130.0);
+
+    // Two inputs latched by the same read
+    tracker.input_event(11, 140.0);
+    tracker.input_event(11, 145.0);
+    tracker.latched(12, 150.0);
+    tracker.presented(12, 160.0);
+
+    let samples: Vec<_> = tracker.samples().copied().collect();
+    assert_eq!(samples.len(), 3);
+    assert_eq!(samples[0].input_to_latch_ms, 10.0);
+    assert_eq!(samples[0].latch_to_present_ms, 20.0);
+    assert_eq!(samples[0].frames_to_latch, 1);
+
+    let stats = tracker.stats().unwrap();
+    assert_eq!(stats.samples, 3);
+    assert_eq!(stats.min_ms, 15.0);
+    assert_eq!(stats.max_ms, 30.0);
+    assert_eq!(stats.avg_ms, 65.0 / 3.0);
+    assert_eq!(stats.p95_ms, 30.0);
+
+    tracker.reset();
+    assert!(tracker.stats().is_none());
+}
+
+#[test]
+fn test_auto_joypad_read_latches_controllers() {
+    // LDA #$01; STA $4200; BRA * (auto-joypad read enabled, no NMI)
+    let rom = LoRom::new("JOYPAD TEST", &[0xA9, 0x01, 0x8D, 0x00, 0x42, 0x80, 0xFE]).build();
+
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&rom).unwrap();
+    emulator.set_controller_input(0, BUTTON_A | BUTTON_START);
+    emulator.step_frame().unwrap();
+    emulator.step_frame().unwrap();
+
+    assert!(emulator.joypad_latch_count() > 0);
+    assert_eq!(emulator.read8(0x4218), 0x80);
+    assert_eq!(emulator.read8(0x4219), 0x10);
+    assert_eq!(emulator.read8(0x421A), 0x00);
+    assert_eq!(emulator.read8(0x421F), 0x00);
+}
+
+// LoROM that stores 1 to $7E:0010 and spins
+fn rumble_rom() -> Vec<u8> {
+    LoRom::new("RUMBLE TEST", &[0xA9, 0x01, 0x8D, 0x10, 0x00, 0x80, 0xFE]).build()
+}
+
+#[test]
+fn test_rumble_effects_count_down() {
+    let mut rumble = Rumble::new();
+    rumble.request(0, RumbleEffect { strong: 0.8, weak: 0.4, frames: 2 });
+    rumble.request(1, RumbleEffect { strong: 1.0, weak: 1.0, frames: 0 });
+    rumble.request(5, RumbleEffect { strong: 1.0, weak: 1.0, frames: 1 });
+    assert_eq!(rumble.level(0), (0.8, 0.4));
+
+    rumble.end_frame();
+    assert_eq!(rumble.level(0), (0.8, 0.4));
+    rumble.end_frame();
+    assert_eq!(rumble.level(0), (0.0, 0.0));
+    // Untimed effects play until stopped
+    assert_eq!(rumble.level(1), (1.0, 1.0));
+    rumble.stop(1);
+    assert_eq!(rumble.level(1), (0.0, 0.0));
+
+    // Strength scales, and disabling silences everything
+    rumble.set_config(RumbleConfig { strength: 0.5, ..RumbleConfig::default() });
+    rumble.request(0, RumbleEffect { strong: 1.0, weak: 0.5, frames: 0 });
+    assert_eq!(rumble.level(0), (0.5, 0.25));
+    rumble.set_config(RumbleConfig { enabled: false, ..RumbleConfig::default() });
+    assert_eq!(rumble.level(0), (0.0, 0.0));
+}
+
+#[test]
+fn test_rumble_trigger_from_profile() {
+    let profile: GameProfile = toml::from_str(
+        "[rumble]\nstrength = 0.5\n\n[[rumble.triggers]]\naddress = 0x7E0010\nvalue = 1\nframes = 3\n",
+    )
+    .unwrap();
+    assert_eq!(profile.rumble.triggers[0], RumbleTrigger { address: 0x7E0010, value: 1, frames: 3, ..RumbleTrigger::default() });
+
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&rumble_rom()).unwrap();
+    emulator.input_mut().rumble_mut().set_config(profile.rumble);
+
+    // Fires once when the value appears, then runs for its duration
+    for _ in 0..3 {
+        emulator.step_frame().unwrap();
+        assert_eq!(emulator.input().rumble().level(0), (0.5, 0.5));
+        assert_eq!(emulator.input().rumble().level(1), (0.0, 0.0));
+    }
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.input().rumble().level(0), (0.0, 0.0));
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.input().rumble().level(0), (0.0, 0.0));
+}
+
+fn read_serial(input: &mut Input, port: u8, bits: usize) -> u32 {
+    (0..bits).fold(0, |value, _| value << 1 | input.read_controller(port) as u32)
+}
+
+#[test]
+fn test_mouse_report() {
+    let mut input = Input::new();
+    input.attach(0, Peripheral::Mouse);
+    let mouse = input.mouse_mut(0).unwrap();
+    mouse.move_by(5, -3);
+    mouse.set_buttons(true, false);
+
+    input.strobe_controllers(true);
+    input.strobe_controllers(false);
+    // Left button, sensitivity 0, ID 0001; up 3; right 5
+    assert_eq!(read_serial(&mut input, 0, 32), 0x0041_8305);
+
+    // Motion is consumed by the read; the auto-joypad read takes the first
+    // 16 bits and leaves the rest for the serial port
+    input.mouse_mut(0).unwrap().move_by(-200, 0);
+    assert_eq!(input.auto_read(0), 0x0041);
+    assert_eq!(read_serial(&mut input, 0, 16), 0x00FF);
+
+    // Reading while strobed cycles the sensitivity
+    input.strobe_controllers(true);
+    input.read_controller(0);
+    input.strobe_controllers(false);
+    assert_eq!(input.mouse_mut(0).unwrap().sensitivity(), 1);
+}
+
+#[test]
+fn test_mouse_detected_from_sensitivity_cycling() {
+    let mut input = Input::new();
+    for _ in 0..3 {
+        input.strobe_controllers(true);
+        input.read_controller(0);
+        input.strobe_controllers(false);
+    }
+    input.end_frame(false, false);
+    assert_eq!(input.device(0), Peripheral::Mouse);
+    assert_eq!(input.device(1), Peripheral::Gamepad);
+    assert_eq!(input.take_auto_attached(), vec![(0, Peripheral::Mouse)]);
+    assert!(input.take_auto_attached().is_empty());
+
+    // A device set in the profile is never switched
+    let config: ccsnes::input::PeripheralConfig = toml::from_str("port1 = \"gamepad\"").unwrap();
+    input.configure_peripherals(&config, "SOME GAME");
+    for _ in 0..3 {
+        input.strobe_controllers(true);
+        input.read_controller(0);
+        input.strobe_controllers(false);
+    }
+    input.end_frame(false, false);
+    assert_eq!(input.device(0), Peripheral::Gamepad);
+}
+
+#[test]
+fn test_known_peripherals() {
+    assert_eq!(known_peripheral("MARIO PAINT          "), Some((0, Peripheral::Mouse)));
+    assert_eq!(known_peripheral("YOSHI'S SAFARI"), Some((1, Peripheral::SuperScope)));
+    assert_eq!(known_peripheral("SUPER MARIOWORLD"), None);
+
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&idle_rom("MARIO PAINT")).unwrap();
+    assert_eq!(emulator.input().device(0), Peripheral::Mouse);
+
+    let profile: GameProfile = toml::from_str("[peripherals]\nport1 = \"gamepad\"\nport2 = \"superscope\"\n").unwrap();
+    emulator.set_peripherals(&profile.peripherals);
+    assert_eq!(emulator.input().device(0), Peripheral::Gamepad);
+    assert_eq!(emulator.input().device(1), Peripheral::SuperScope);
+}
+
+#[test]
+fn test_super_scope_latches_counters() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&idle_rom("SCOPE TEST")).unwrap();
+    emulator.input_mut().attach(1, Peripheral::SuperScope);
+
+    // Off screen: no latch, and the report says so
+    emulator.step_frame().unwrap();
+    assert!(!emulator.bus().counter_latch().is_latched());
+    assert_eq!(emulator.input_mut().auto_read(1), 0x02FF);
+
+    emulator.input_mut().scope_mut().aim(Some((100, 50)));
+    emulator.input_mut().scope_mut().set_buttons(true, false, false, false);
+    emulator.step_frame().unwrap();
+    assert!(emulator.bus().counter_latch().is_latched());
+    let (h, v) = emulator.bus().counter_latch().position();
+    assert_eq!(v, 51);
+    assert!((122..140).contains(&h), "latched at dot {}", h);
+    assert_eq!(emulator.input_mut().auto_read(1), 0x80FF);
+}
+
+#[test]
+fn test_super_scope_detected_from_latch_polling() {
+    // LDA $213F; BRA loop: waits for a counter latch it never makes itself
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&LoRom::new("SCOPE POLL TEST", &[0xAD, 0x3F, 0x21, 0x80, 0xFB]).build()).unwrap();
+    for _ in 0..29 {
+        emulator.step_frame().unwrap();
+    }
+    assert_eq!(emulator.input().device(1), Peripheral::Gamepad);
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.input().device(1), Peripheral::SuperScope);
+    assert_eq!(emulator.input_mut().take_auto_attached(), vec![(1, Peripheral::SuperScope)]);
+}
+
+#[test]
+fn test_super_scope_calibration() {
+    let mut scope = SuperScope::default();
+    scope.aim(Some((100, 50)));
+    let uncalibrated = scope.beam_target().unwrap();
+
+    // The game drew the shot 3 dots right and a line low of the target
+    scope.calibrate((100, 50), (103, 49));
+    assert_eq!(scope.settings(), ScopeSettings { dot_offset: -3, line_offset: 1 });
+    assert_eq!(scope.beam_target(), Some((uncalibrated.0 - 3, uncalibrated.1 + 1)));
+
+    let mut restored = SuperScope::default();
+    restored.apply_settings(&scope.settings());
+    restored.aim(Some((100, 50)));
+    assert_eq!(restored.beam_target(), scope.beam_target());
+}
+
+#[test]
+fn test_peripheral_settings_round_trip_through_profile() {
+    let profile: GameProfile = toml::from_str(
+        "[peripherals]\nport1 = \"mouse\"\nport2 = \"superscope\"\n\
+         [peripherals.mouse]\nsensitivity = 2\n[peripherals.scope]\ndot_offset = -4\nline_offset = 2\n",
+    )
+    .unwrap();
+    let mut input = Input::new();
+    input.configure_peripherals(&profile.peripherals, "SOME GAME");
+    assert_eq!(input.mouse_mut(0).unwrap().sensitivity(), 2);
+    assert_eq!(input.scope_mut().settings(), ScopeSettings { dot_offset: -4, line_offset: 2 });
+
+    // The game cycles the sensitivity, which is what gets stored
+    input.strobe_controllers(true);
+    input.read_controller(0);
+    input.strobe_controllers(false);
+    let mut config = profile.peripherals.clone();
+    input.store_peripheral_settings(&mut config);
+    assert_eq!(config.mouse, MouseSettings { sensitivity: 0 });
+    assert_eq!(config.scope, profile.peripherals.scope);
+
+    // Devices not plugged in keep their stored settings
+    let pads = PeripheralConfig { port1: Some(Peripheral::Gamepad), port2: Some(Peripheral::Gamepad), ..config.clone() };
+    input.configure_peripherals(&pads, "SOME GAME");
+    input.mouse_mut(0).unwrap().apply_settings(&MouseSettings { sensitivity: 1 });
+    let mut unchanged = config.clone();
+    input.store_peripheral_settings(&mut unchanged);
+    assert_eq!(unchanged, config);
+}
+
+// Run until the scanline is reached, one instruction at a time
+fn run_to_scanline(emulator: &mut Emulator, scanline: u16) {
+    while emulator.ppu().get_current_scanline() != scanline {
+        emulator.step().unwrap();
+    }
+}
+
+#[test]
+fn test_live_pads_latch_at_the_auto_joypad_read() {
+    // LDA #$01; STA $4200 (auto-joypad read on); loop
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&LoRom::new("LIVE PADS", &[0xA9, 0x01, 0x8D, 0x00, 0x42, 0x80, 0xFE]).build()).unwrap();
+    emulator.set_controller_input(0, BUTTON_B);
+    let pads = emulator.live_pads();
+    assert_eq!(pads.get(0), BUTTON_B);
+    emulator.step_frame().unwrap();
+
+    // A change in the middle of the frame is what the next read latches,
+    // without the frontend calling into the emulator
+    run_to_scanline(&mut emulator, 100);
+    pads.set(0, BUTTON_A);
+    let latches = emulator.joypad_latch_count();
+    run_to_scanline(&mut emulator, 230);
+    assert_eq!(emulator.joypad_latch_count(), latches + 1);
+    assert_eq!(emulator.read8(0x4218), 0x80);
+    assert_eq!(emulator.input().get_controller_state(0), BUTTON_A);
+
+    // Changes after the read wait for the next one
+    pads.set(0, BUTTON_B);
+    run_to_scanline(&mut emulator, 240);
+    assert_eq!(emulator.read8(0x4219), 0x00);
+    assert_eq!(emulator.read8(0x4218), 0x80);
+
+    // set_controller_input still works and updates the shared state
+    emulator.set_controller_input(1, BUTTON_L);
+    assert_eq!(pads.get(1), BUTTON_L);
+    emulator.detach_live_pads();
+    pads.set(0, 0);
+    emulator.input_mut().strobe_controllers(true);
+    emulator.input_mut().strobe_controllers(false);
+    assert_eq!(emulator.input().get_controller_state(0), BUTTON_A);
+}
+
+#[test]
+fn test_live_pads_latch_at_manual_strobe() {
+    let mut input = Input::new();
+    let pads = ccsnes::input::LivePads::new();
+    input.set_live_pads(Some(pads.clone()));
+
+    pads.set(0, BUTTON_START);
+    input.strobe_controllers(true);
+    pads.set(0, BUTTON_SELECT);
+    input.strobe_controllers(false);
+    assert_eq!(read_serial(&mut input, 0, 4), 0b0010);
+
+    // A stray write of 0 mid-read doesn't restart the shift
+    pads.set(0, BUTTON_B);
+    input.strobe_controllers(false);
+    assert_eq!(read_serial(&mut input, 0, 12), 0);
+    input.strobe_controllers(true);
+    input.strobe_controllers(false);
+    assert_eq!(read_serial(&mut input, 0, 1), 1);
+}
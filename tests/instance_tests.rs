@@ -0,0 +1,99 @@
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM with 8KB SRAM running: LDA #value; STA $700000; BRA *
+fn sram_writer_rom(value: u8) -> Vec<u8> {
+    LoRom::new("INSTANCE TEST", &[0xA9, value, 0x8F, 0x00, 0x00, 0x70, 0x80, 0xFE]).sram(3).build()
+}
+
+fn loaded_emulator(value: u8) -> Emulator {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&sram_writer_rom(value)).unwrap();
+    emulator
+}
+
+#[test]
+fn test_emulator_is_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Emulator>();
+}
+
+#[test]
+fn test_instances_survive_moves() {
+    // Both emulators are moved after load_rom, so the bus must not point at
+    // their old locations
+    let mut emulators = vec![loaded_emulator(0x42), loaded_emulator(0x17)];
+    for emulator in &mut emulators {
+        emulator.step_frame().unwrap();
+    }
+
+    assert_eq!(emulators[0].get_sram().unwrap()[0], 0x42);
+    assert_eq!(emulators[1].get_sram().unwrap()[0], 0x17);
+}
+
+#[test]
+fn test_instances_run_on_threads() {
+    let handles: Vec<_> = [0x11u8, 0x22, 0x33]
+        .into_iter()
+        .map(|value| {
+            let emulator = loaded_emulator(value);
+            std::thread::spawn(move || {
+                let mut emulator = emulator;
+                emulator.step_frame().unwrap();
+                (value, emulator.get_sram().unwrap()[0], emulator.get_cycle_count())
+            })
+        })
+        .collect();
+
+    let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    for (value, sram, _) in &results {
+        assert_eq!(value, sram);
+    }
+    // Identical programs take identical time regardless of the thread
+    assert!(results.windows(2).all(|w| w[0].2 == w[1].2));
+}
+
+#[test]
+fn test_switching_games_leaves_nothing_behind() {
+    let mut emulator = loaded_emulator(0x42);
+    emulator.step_frame().unwrap();
+    emulator.freeze(0x7E0010, 0x99).unwrap();
+    emulator.write8(0x4200, 0x81);
+
+    let other = LoRom::new("OTHER", &[0xA9, 0x17, 0x8F, 0x00, 0x00, 0x70, 0x80, 0xFE]).sram(3).build();
+    emulator.load_rom(&other).unwrap();
+    assert!(emulator.get_rom_info().unwrap().title.starts_with("OTHER"));
+    assert!(emulator.bus().freezes().is_empty());
+    assert_eq!(emulator.read8(0x4200), 0);
+    assert_eq!(emulator.get_frame_count(), 0);
+
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.get_sram().unwrap()[0], 0x17);
+}
+
+#[test]
+fn test_unload_rom() {
+    let sram_seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let mut emulator = loaded_emulator(0x42);
+    let seen = sram_seen.clone();
+    emulator.on_sram_write(move |sram| *seen.lock().unwrap() = sram.to_vec());
+
+    // Mid-frame, so only unloading hands the write to the callback
+    for _ in 0..4 {
+        emulator.step().unwrap();
+    }
+    assert!(sram_seen.lock().unwrap().is_empty());
+    let cartridge = emulator.unload_rom().expect("a cartridge was in");
+    assert_eq!(cartridge.get_sram().unwrap()[0], 0x42);
+    assert_eq!(sram_seen.lock().unwrap()[0], 0x42);
+
+    assert!(emulator.get_rom_info().is_none());
+    assert!(emulator.unload_rom().is_none());
+    // Powered off: frames don't run
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.get_frame_count(), 0);
+
+    emulator.load_rom(&sram_writer_rom(0x17)).unwrap();
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.get_sram().unwrap()[0], 0x17);
+}
@@ -0,0 +1,84 @@
+use ccsnes::frontend::native::browser::{BrowserAction, LibraryBrowser, VISIBLE_ROWS};
+use ccsnes::frontend::native::osd;
+use ccsnes::library::RomLibrary;
+use ccsnes_test_support::{fresh_dir, LoRom};
+use std::path::Path;
+
+// Minimal LoROM image with a title and region code
+fn write_rom(path: &Path, title: &str, region: u8) {
+    std::fs::write(path, LoRom::new(title, &[]).region(region).build()).unwrap();
+}
+
+#[test]
+fn test_library_scan_and_cache() {
+    let dir = fresh_dir("ccsnes_library_scan");
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    write_rom(&dir.join("b.sfc"), "ZETA QUEST", 0x01);
+    write_rom(&dir.join("sub").join("a.SMC"), "ALPHA RACER", 0x00);
+    std::fs::write(dir.join("notes.txt"), b"not a rom").unwrap();
+    let cache = dir.join("cache").join("library.toml");
+
+    let library = RomLibrary::scan(&dir, Some(&cache)).unwrap();
+    assert_eq!(library.len(), 2);
+    assert_eq!(library.cache_hits(), 0);
+    assert_eq!(library.entries()[0].title, "ALPHA RACER");
+    assert_eq!(library.entries()[0].region, "Japan");
+    assert_eq!(library.entries()[1].title, "ZETA QUEST");
+    assert_eq!(library.entries()[1].region, "USA");
+    assert!(cache.exists());
+
+    // Second scan is served from the cache
+    let library = RomLibrary::scan(&dir, Some(&cache)).unwrap();
+    assert_eq!(library.len(), 2);
+    assert_eq!(library.cache_hits(), 2);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_library_browser_navigation() {
+    let dir = fresh_dir("ccsnes_library_browser");
+    for i in 0..VISIBLE_ROWS + 5 {
+        write_rom(&dir.join(format!("{:02}.sfc", i)), &format!("GAME {:02}", i), 0x01);
+    }
+
+    let mut browser = LibraryBrowser::new(RomLibrary::scan(&dir, None).unwrap());
+    assert_eq!(browser.handle_key("Up"), BrowserAction::None);
+    assert_eq!(browser.selected(), 0);
+
+    browser.handle_key("Down");
+    browser.handle_key("Down");
+    assert_eq!(browser.selected(), 2);
+    assert_eq!(browser.handle_key("Return"), BrowserAction::Launch(dir.join("02.sfc")));
+
+    browser.handle_key("End");
+    assert_eq!(browser.selected(), VISIBLE_ROWS + 4);
+    browser.handle_key("PageUp");
+    assert_eq!(browser.selected(), 4);
+    browser.handle_key("Home");
+    assert_eq!(browser.selected(), 0);
+    assert_eq!(browser.handle_key("Escape"), BrowserAction::Quit);
+
+    let frame = browser.render();
+    assert_eq!(frame.len(), 256 * 224 * 4);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_osd_text_clipping() {
+    let mut frame = vec![0u8; 256 * 224 * 4];
+    let white = [0xFF; 4];
+
+    let end = osd::draw_text(&mut frame, 0, 0, "I", white);
+    assert_eq!(end, osd::CHAR_ADVANCE as i32);
+    // Top row of "I" is columns 1-3
+    assert_eq!(&frame[0..4], &[0, 0, 0, 0]);
+    assert_eq!(&frame[4..8], &white);
+    assert_eq!(osd::text_width("AB"), 11);
+
+    // Drawing off the edges must not panic
+    osd::draw_text(&mut frame, 250, 220, "CLIPPED", white);
+    osd::draw_text(&mut frame, -10, -3, "x", white);
+    osd::fill_rect(&mut frame, 200, 200, 100, 100, white);
+}
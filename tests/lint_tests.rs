@@ -0,0 +1,94 @@
+use ccsnes::debug::lint::{Lint, LintKind};
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM running `code` from $8000
+fn program_rom(code: &[u8]) -> Vec<u8> {
+    LoRom::new("LINT TEST", code).build()
+}
+
+fn run_linted(code: &[u8], steps: usize) -> Vec<Lint> {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.set_lint(true);
+    emulator.load_rom(&program_rom(code)).unwrap();
+    for _ in 0..steps {
+        emulator.step().unwrap();
+    }
+    emulator.lints()
+}
+
+#[test]
+fn test_lint_off_by_default() {
+    let mut emulator = Emulator::new().unwrap();
+    // LDA $1000; STA $9000; BRA *
+    emulator.load_rom(&program_rom(&[0xAD, 0x00, 0x10, 0x8D, 0x00, 0x90, 0x80, 0xFE])).unwrap();
+    for _ in 0..10 {
+        emulator.step().unwrap();
+    }
+    assert!(!emulator.lint_enabled());
+    assert!(emulator.lints().is_empty());
+}
+
+#[test]
+fn test_lint_uninitialized_wram_read() {
+    // LDA $1000; STA $1100; LDA $1100; BRA -5 (back to the second LDA)
+    let lints = run_linted(&[0xAD, 0x00, 0x10, 0x8D, 0x00, 0x11, 0xAD, 0x00, 0x11, 0x80, 0xFB], 20);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].kind, LintKind::UninitializedRead);
+    assert_eq!(lints[0].address, 0x001000);
+    assert_eq!(lints[0].pc, 0x008000);
+}
+
+#[test]
+fn test_lint_reported_once_per_instruction() {
+    // LDA $1000; LDA $1001; BRA -8
+    let lints = run_linted(&[0xAD, 0x00, 0x10, 0xAD, 0x01, 0x10, 0x80, 0xF8], 30);
+    let pcs: Vec<u32> = lints.iter().map(|lint| lint.pc).collect();
+    assert_eq!(pcs, vec![0x008000, 0x008003]);
+}
+
+#[test]
+fn test_lint_rom_write() {
+    // STA $9000; BRA *
+    let lints = run_linted(&[0x8D, 0x00, 0x90, 0x80, 0xFE], 5);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].kind, LintKind::RomWrite);
+    assert_eq!(lints[0].address, 0x009000);
+}
+
+#[test]
+fn test_lint_video_memory_outside_blank() {
+    // LDA #$0F; STA $2100 (screen on); STA $2118; BRA *
+    let lints = run_linted(&[0xA9, 0x0F, 0x8D, 0x00, 0x21, 0x8D, 0x18, 0x21, 0x80, 0xFE], 5);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].kind, LintKind::VideoMemoryWhileDrawing);
+    assert_eq!(lints[0].address, 0x2118);
+    assert!(lints[0].to_string().contains("VMDATAL"));
+
+    // The same writes in forced blank are fine
+    let lints = run_linted(&[0xA9, 0x80, 0x8D, 0x00, 0x21, 0x8D, 0x18, 0x21, 0x80, 0xFE], 5);
+    assert!(lints.is_empty());
+}
+
+#[test]
+fn test_lint_stack_overflow() {
+    // CLC; XCE; REP #$10; LDX #$0050; TXS; BRA *
+    let lints = run_linted(&[0x18, 0xFB, 0xC2, 0x10, 0xA2, 0x50, 0x00, 0x9A, 0x80, 0xFE], 8);
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].kind, LintKind::StackOverflow);
+    assert_eq!(lints[0].address, 0x0050);
+    assert_eq!(lints[0].pc, 0x008007);
+}
+
+#[test]
+fn test_lint_unserviced_irq() {
+    // LDA #$10; STA $4200 (H timer IRQ, I flag still set); BRA *
+    let mut emulator = Emulator::new().unwrap();
+    emulator.set_lint(true);
+    emulator.load_rom(&program_rom(&[0xA9, 0x10, 0x8D, 0x00, 0x42, 0x80, 0xFE])).unwrap();
+    emulator.step_frame().unwrap();
+    let lints = emulator.lints();
+    assert_eq!(lints.len(), 1);
+    assert_eq!(lints[0].kind, LintKind::UnservicedIrq);
+    assert_eq!(lints[0].pc, 0x008005);
+}
@@ -0,0 +1,233 @@
+use ccsnes::cartridge::Cartridge;
+use ccsnes::memory::mappers::MapperType;
+use ccsnes::memory::{Bus, MemoryInit, MemoryPattern};
+use ccsnes::movie::Movie;
+use ccsnes::Emulator;
+
+// Deterministic xorshift, so failures reproduce
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 16) as u32
+    }
+
+    fn address(&mut self) -> u32 {
+        self.next() & 0xFFFFFF
+    }
+}
+
+// Distinct bytes across banks, so a wrong bank or offset shows up
+fn rom_byte(offset: usize) -> u8 {
+    (offset ^ (offset >> 8) ^ (offset >> 16).wrapping_mul(0x35)) as u8
+}
+
+fn cartridge(mapper: MapperType) -> Cartridge {
+    let (size, header, mapper_byte, size_byte) = match mapper {
+        MapperType::LoROM => (0x400000, 0x7FC0, 0x20, 12),
+        _ => (0x20000, 0xFFC0, 0x21, 7),
+    };
+    let mut rom: Vec<u8> = (0..size).map(rom_byte).collect();
+    rom[header..header + 21].copy_from_slice(b"MEMORY TEST          ");
+    rom[header + 0x15] = mapper_byte;
+    rom[header + 0x16] = 0x02;
+    rom[header + 0x17] = size_byte;
+    rom[header + 0x18] = 3; // 8KB SRAM
+    rom[header + 0x19] = 0x01;
+    rom[header + 0x1C..header + 0x20].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00]);
+    let cartridge = Cartridge::load(&rom).unwrap();
+    assert_eq!(cartridge.header.mapper_type, mapper);
+    cartridge
+}
+
+// Read and write every address in the 24-bit space, then hammer random
+// ones (with the upper byte set, which must be ignored)
+fn sweep(bus: &mut Bus) {
+    for address in 0..0x1000000 {
+        bus.read8(address);
+    }
+    for address in 0..0x1000000u32 {
+        bus.write8(address, address as u8);
+    }
+    // Multi-byte accesses at the top of the space wrap instead of overflowing
+    bus.read16(0xFFFFFFFF);
+    bus.read24(0xFFFFFFFE);
+    bus.write16(0xFFFFFFFF, 0x1234);
+    let mut rng = Rng(0x2677);
+    for _ in 0..100_000 {
+        let address = rng.next() | 0xFF000000;
+        bus.write8(address, rng.next() as u8);
+        bus.read8(address);
+        bus.read16(address);
+        bus.write16(address, rng.next() as u16);
+    }
+}
+
+#[test]
+fn test_bus_sweep_without_cartridge() {
+    let mut bus = Bus::new();
+    sweep(&mut bus);
+}
+
+#[test]
+fn test_bus_sweep_with_cartridges() {
+    for mapper in [MapperType::LoROM, MapperType::HiROM] {
+        let mut cartridge = cartridge(mapper);
+        let mut bus = Bus::new();
+        unsafe { bus.install_cartridge(&mut cartridge) };
+        sweep(&mut bus);
+    }
+}
+
+#[test]
+fn test_wram_mirrors() {
+    let mut bus = Bus::new();
+    let mut rng = Rng(7);
+    for _ in 0..10_000 {
+        // Low RAM appears in the first 8KB of every system bank
+        let offset = rng.next() & 0x1FFF;
+        let bank = match rng.next() % 2 {
+            0 => rng.next() % 0x40,
+            _ => 0x80 + rng.next() % 0x40,
+        };
+        let value = rng.next() as u8;
+        bus.write8(bank << 16 | offset, value);
+        assert_eq!(bus.read8(0x7E0000 | offset), value, "${:02X}:{:04X}", bank, offset);
+
+        // Bank $7F is the second 64KB
+        let address = 0x7F0000 | (rng.next() & 0xFFFF);
+        bus.write8(address, value);
+        assert_eq!(bus.read8(address), value);
+    }
+    bus.write8(0x7E0000, 0x11);
+    bus.write8(0x7F0000, 0x22);
+    assert_eq!(bus.read8(0x000000), 0x11);
+    // Addresses wrap to 24 bits
+    assert_eq!(bus.read8(0xFF7F0000), 0x22);
+}
+
+// Banks $80-$FF mirror $00-$7F everywhere but WRAM
+fn assert_high_banks_mirror(bus: &mut Bus) {
+    let mut rng = Rng(0x80);
+    for _ in 0..50_000 {
+        let address = rng.address() & 0x7FFFFF;
+        if (0x7E..=0x7F).contains(&(address >> 16)) {
+            continue;
+        }
+        assert_eq!(bus.read8(address), bus.read8(address | 0x800000), "${:06X}", address);
+    }
+}
+
+#[test]
+fn test_lorom_mirrors() {
+    let mut cartridge = cartridge(MapperType::LoROM);
+    let mut bus = Bus::new();
+    unsafe { bus.install_cartridge(&mut cartridge) };
+
+    // 32KB banks in the upper half of each bank
+    assert_eq!(bus.read8(0x008000), rom_byte(0));
+    assert_eq!(bus.read8(0x01FFFF), rom_byte(0xFFFF));
+    assert_eq!(bus.read8(0x078123), rom_byte(0x38123));
+    // Banks $40-$6F show the same bank in both halves
+    assert_eq!(bus.read8(0x400123), rom_byte(0x200123));
+    assert_eq!(bus.read8(0x408123), rom_byte(0x200123));
+    assert_eq!(bus.read8(0x878123), rom_byte(0x38123));
+
+    // SRAM and its mirror at $F0
+    bus.write8(0x700010, 0x5A);
+    assert_eq!(bus.read8(0xF00010), 0x5A);
+    bus.write8(0xF01FFF, 0xA5);
+    assert_eq!(bus.read8(0x701FFF), 0xA5);
+
+    assert_high_banks_mirror(&mut bus);
+}
+
+#[test]
+fn test_hirom_mirrors() {
+    let mut cartridge = cartridge(MapperType::HiROM);
+    let mut bus = Bus::new();
+    unsafe { bus.install_cartridge(&mut cartridge) };
+
+    // 64KB banks from $40 and $C0, upper halves also in the system banks
+    assert_eq!(bus.read8(0x400000), rom_byte(0));
+    assert_eq!(bus.read8(0xC1ABCD), rom_byte(0x1ABCD));
+    assert_eq!(bus.read8(0x018000), bus.read8(0x418000));
+    assert_eq!(bus.read8(0x81FFFF), bus.read8(0xC1FFFF));
+
+    // SRAM at $6000-$7FFF of banks $20-$3F and $A0-$BF
+    bus.write8(0x206000, 0x5A);
+    assert_eq!(bus.read8(0xA06000), 0x5A);
+    bus.write8(0xA07FFF, 0xA5);
+    assert_eq!(bus.read8(0x207FFF), 0xA5);
+
+    assert_high_banks_mirror(&mut bus);
+}
+
+// Smallest bootable LoROM: BRA *
+fn idle_rom() -> Vec<u8> {
+    ccsnes_test_support::idle_rom("MEMORY INIT TEST")
+}
+
+#[test]
+fn test_memory_init_patterns() {
+    let mut memory = [0u8; 9];
+    MemoryInit::new(MemoryPattern::Ones, 0).fill(&mut memory, 0);
+    assert_eq!(memory, [0xFF; 9]);
+    MemoryInit::new(MemoryPattern::Stripes, 0).fill(&mut memory, 0);
+    assert_eq!(&memory[..4], &[0x55, 0xAA, 0x55, 0xAA]);
+    MemoryInit::new(MemoryPattern::Zero, 0).fill(&mut memory, 0);
+    assert_eq!(memory, [0; 9]);
+
+    // Random fills depend only on the seed and stream
+    let fill = |seed, stream| {
+        let mut memory = vec![0u8; 256];
+        MemoryInit::new(MemoryPattern::Random, seed).fill(&mut memory, stream);
+        memory
+    };
+    assert_eq!(fill(7, 1), fill(7, 1));
+    assert_ne!(fill(7, 1), fill(8, 1));
+    assert_ne!(fill(7, 1), fill(7, 2));
+    assert!(fill(0, 0).iter().any(|&byte| byte != 0));
+
+    assert_eq!(MemoryInit::resolve(MemoryPattern::Random, Some(42)).seed, 42);
+    assert_eq!(MemoryInit::resolve(MemoryPattern::Zero, None).seed, 0);
+}
+
+#[test]
+fn test_emulator_power_on_memory() {
+    let init = MemoryInit::new(MemoryPattern::Random, 1234);
+    let mut emulator = Emulator::with_memory_init(init).unwrap();
+    emulator.load_rom(&idle_rom()).unwrap();
+    let state = emulator.save_state().unwrap();
+    assert_eq!(state.memory_init, init);
+
+    // Another power-on with the same seed starts from identical memory
+    let mut replay = Emulator::new().unwrap();
+    replay.load_rom(&idle_rom()).unwrap();
+    replay.set_memory_init(init);
+    let replay_state = replay.save_state().unwrap();
+    assert_eq!(replay_state.memory.wram, state.memory.wram);
+    assert_eq!(replay_state.ppu.vram, state.ppu.vram);
+    assert!(state.memory.wram.iter().any(|&byte| byte != 0));
+    assert_ne!(&state.memory.wram[..0x100], &state.ppu.vram[..0x100]);
+
+    // Resets keep WRAM but refill VRAM
+    let mut ones = Emulator::with_memory_init(MemoryInit::new(MemoryPattern::Ones, 0)).unwrap();
+    ones.load_rom(&idle_rom()).unwrap();
+    ones.write8(0x7E0100, 0x12);
+    ones.reset().unwrap();
+    assert_eq!(ones.read8(0x7E0100), 0x12);
+    assert_eq!(ones.read8(0x7E0101), 0xFF);
+    assert!(ones.save_state().unwrap().ppu.vram.iter().all(|&byte| byte == 0xFF));
+}
+
+#[test]
+fn test_movie_records_memory_init() {
+    let init = MemoryInit::new(MemoryPattern::Stripes, 0);
+    let mut emulator = Emulator::with_memory_init(init).unwrap();
+    emulator.load_rom(&idle_rom()).unwrap();
+    assert_eq!(Movie::for_emulator(&emulator).memory_init, init);
+}
\ No newline at end of file
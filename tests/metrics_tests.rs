@@ -0,0 +1,87 @@
+use ccsnes::metrics::{FrameTimeHistogram, Metrics, FRAME_TIME_BUCKETS, MASTER_CLOCK_HZ};
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM running `code` from reset
+fn rom_with_code(code: &[u8]) -> Vec<u8> {
+    LoRom::new("METRICS TEST", code).build()
+}
+
+fn snapshot(master_cycles: u64) -> Metrics {
+    Metrics { master_cycles, instructions: 0, frames: 0, dma_bytes: 0, frame_times: None }
+}
+
+#[test]
+fn test_metrics_count_cycles_instructions_and_frames() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&rom_with_code(&[0x80, 0xFE])).unwrap();
+    emulator.step_frame().unwrap();
+    emulator.step_frame().unwrap();
+
+    let metrics = emulator.metrics();
+    assert_eq!(metrics.master_cycles, emulator.get_cycle_count());
+    assert_eq!(metrics.frames, 2);
+    assert!(metrics.instructions > 1000, "{} instructions", metrics.instructions);
+    assert_eq!(metrics.dma_bytes, 0);
+    assert!(metrics.frame_times.is_none());
+
+    emulator.reset().unwrap();
+    assert_eq!(emulator.metrics().instructions, 0);
+}
+
+#[test]
+fn test_metrics_count_dma_bytes() {
+    // Copy 16 bytes from $00:8000 to VMDATAL, then spin
+    let code = [
+        0xA9, 0x00, 0x8D, 0x00, 0x43, // DMAP0 = 0
+        0xA9, 0x18, 0x8D, 0x01, 0x43, // BBAD0 = $18
+        0xA9, 0x00, 0x8D, 0x02, 0x43, // A1T0 = $8000
+        0xA9, 0x80, 0x8D, 0x03, 0x43,
+        0xA9, 0x00, 0x8D, 0x04, 0x43, // A1B0 = $00
+        0xA9, 0x10, 0x8D, 0x05, 0x43, // DAS0 = 16
+        0xA9, 0x00, 0x8D, 0x06, 0x43,
+        0xA9, 0x01, 0x8D, 0x0B, 0x42, // MDMAEN = channel 0
+        0x80, 0xFE,
+    ];
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&rom_with_code(&code)).unwrap();
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.metrics().dma_bytes, 16);
+}
+
+#[test]
+fn test_frame_timing_histogram() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&rom_with_code(&[0x80, 0xFE])).unwrap();
+    emulator.set_frame_timing(true);
+    emulator.step_frame().unwrap();
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.metrics().frame_times.unwrap().count(), 2);
+
+    emulator.set_frame_timing(false);
+    emulator.step_frame().unwrap();
+    assert!(emulator.metrics().frame_times.is_none());
+
+    let mut histogram = FrameTimeHistogram::new();
+    assert_eq!(histogram.percentile_ms(50.0), None);
+    for ms in [10.2, 16.1, 16.4, 16.6, 100.0] {
+        histogram.record(ms);
+    }
+    assert_eq!(histogram.count(), 5);
+    assert_eq!(histogram.min_ms(), Some(10.2));
+    assert_eq!(histogram.max_ms(), Some(100.0));
+    assert_eq!(histogram.buckets()[32], 2);
+    assert_eq!(histogram.buckets()[FRAME_TIME_BUCKETS - 1], 1);
+    assert_eq!(histogram.percentile_ms(50.0), Some(16.5));
+    assert_eq!(histogram.percentile_ms(100.0), Some(100.0));
+}
+
+#[test]
+fn test_speed_percent() {
+    let before = snapshot(1_000);
+    let now = snapshot(1_000 + MASTER_CLOCK_HZ as u64);
+    assert!((now.speed_percent(&before, 1.0) - 100.0).abs() < 0.001);
+    assert!((now.speed_percent(&before, 2.0) - 50.0).abs() < 0.001);
+    assert_eq!(now.speed_percent(&before, 0.0), 0.0);
+    assert!((now.emulated_seconds() - 1.0).abs() < 0.001);
+}
@@ -4,4 +4,38 @@ mod dma_tests;
 mod cartridge_tests;
 mod mode7_tests;
 mod apu_tests;
-mod savestate_tests;
\ No newline at end of file
+mod savestate_tests;
+mod pixel_tests;
+mod config_tests;
+mod input_tests;
+mod paths_tests;
+mod library_tests;
+mod instance_tests;
+mod debugger_tests;
+mod cheats_tests;
+mod sram_tests;
+mod quirks_tests;
+mod timing_tests;
+mod metrics_tests;
+mod movie_tests;
+mod spans_tests;
+mod memory_tests;
+mod hot_slots_tests;
+mod frame_graph_tests;
+mod achievements_tests;
+mod emulation_mode_tests;
+mod wdm_tests;
+mod pacing_tests;
+mod remote_input_tests;
+mod rom_watch_tests;
+mod tool_server_tests;
+mod prelude_tests;
+mod pause_menu_tests;
+mod video_tests;
+mod lint_tests;
+mod boot_rom_tests;
+mod watchdog_tests;
+mod frame_hash_tests;
+mod heatmap_tests;
+mod coprocessor_tests;
+mod session_tests;
\ No newline at end of file
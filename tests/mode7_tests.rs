@@ -1,6 +1,9 @@
 use ccsnes::ppu::mode7::Mode7Renderer;
-use ccsnes::ppu::memory::{Vram, Cgram};
+use ccsnes::ppu::memory::{Vram, VramAddress, Cgram};
 use ccsnes::ppu::registers::PpuRegisters;
+use ccsnes::enhancements::Enhancements;
+use ccsnes::profile::GameProfile;
+use ccsnes::Emulator;
 
 #[test]
 fn test_mode7_identity_matrix() {
@@ -101,13 +104,13 @@ fn test_mode7_pixel_calculation() {
     let mut registers = PpuRegisters::new();
     let mut mode7 = Mode7Renderer::new();
     
-    // Set up a simple tilemap entry at position (0,0)
-    vram.write(0x0000, 0x0001);  // Tile 1 at tilemap position 0,0
+    // Set up a simple tilemap entry at position (0,0), in the low byte
+    vram.write_low(VramAddress::new(0x0000), 0x01);  // Tile 1 at tilemap position 0,0
     
-    // Write some pixel data for tile 1
-    // Mode 7 tiles are 8x8, 8bpp (64 bytes per tile)
+    // Write some pixel data for tile 1, in the high bytes
+    // Mode 7 tiles are 8x8, 8bpp (64 words per tile)
     for i in 0..64 {
-        vram.write(64 + i, i as u8);  // Tile 1 starts at offset 64
+        vram.write_high(VramAddress::new(64 + i), i as u8);  // Tile 1 starts at word 64
     }
     
     // Create a buffer for rendering
@@ -121,10 +124,128 @@ fn test_mode7_pixel_calculation() {
     mode7.m7hofs = 0;
     mode7.m7vofs = 0;
     
-    // Render a scanline
-    mode7.render_scanline(&vram, &cgram, &registers, 112, &mut buffer);
+    // Render a scanline; unscrolled, line 0 is the plane's top row
+    mode7.render_scanline(&vram, &cgram, &registers, 0, &mut buffer);
     
     // Check that we got some non-zero pixels
     let non_zero_pixels = buffer.iter().filter(|&&x| x != 0).count();
     assert!(non_zero_pixels > 0);
-}
\ No newline at end of file
+}
+
+// Tile 1 at the plane's top left, each row of it texels 1-8, with color i
+// red i; the rest of the plane is tile 0
+fn plane() -> (Vram, Cgram) {
+    let mut vram = Vram::new();
+    let mut cgram = Cgram::new();
+    vram.write_low(VramAddress::new(0x0000), 0x01);
+    for i in 0..64 {
+        vram.write_high(VramAddress::new(64 + i), (i % 8) as u8 + 1);
+    }
+    for i in 0..32 {
+        cgram.write_color(i, i as u16);
+    }
+    (vram, cgram)
+}
+
+fn red(buffer: &[u8], x: usize) -> u8 {
+    buffer[x * 4]
+}
+
+#[test]
+fn test_mode7_truncates_like_the_ppu() {
+    let (vram, cgram) = plane();
+    let registers = PpuRegisters::new();
+    let mut mode7 = Mode7Renderer::new();
+    let mut buffer = vec![0u8; 256 * 4];
+
+    // A step of 1.5 texels: the fraction carries from pixel to pixel
+    mode7.m7a = 0x0180;
+    mode7.render_scanline(&vram, &cgram, &registers, 0, &mut buffer);
+    let texels: Vec<u8> = (0..6).map(|x| red(&buffer, x) >> 3).collect();
+    assert_eq!(texels, vec![1, 2, 4, 5, 7, 8]);
+
+    // B times the line keeps 6 fraction bits: $7F becomes $40, so pixel 1
+    // at 0.625 + 0.25 is still in texel 0 (0.625 + 0.496 would be texel 1)
+    mode7.m7a = 0x00A0;
+    mode7.m7b = 0x007F;
+    mode7.render_scanline(&vram, &cgram, &registers, 1, &mut buffer);
+    let texels: Vec<u8> = (0..3).map(|x| red(&buffer, x) >> 3).collect();
+    assert_eq!(texels, vec![1, 1, 2]);
+}
+
+#[test]
+fn test_mode7_flip_and_screen_over() {
+    let (mut vram, cgram) = plane();
+    let mut registers = PpuRegisters::new();
+    let mode7 = Mode7Renderer::new();
+    let mut buffer = vec![0u8; 256 * 4];
+
+    // M7SEL bit 0 mirrors the line
+    registers.m7sel = 0x01;
+    mode7.render_scanline(&vram, &cgram, &registers, 0, &mut buffer);
+    assert_eq!(red(&buffer, 255) >> 3, 1);
+    assert_eq!(red(&buffer, 248) >> 3, 8);
+    assert_eq!(buffer[3], 0);
+
+    // Scrolled 8 pixels left of the plane: nothing there with M7SEL $80,
+    // tile 0 with $C0
+    for i in 0..64 {
+        vram.write_high(VramAddress::new(i), 9);
+    }
+    registers.bg1hofs = 0x1FF8;
+    registers.m7sel = 0x80;
+    mode7.render_scanline(&vram, &cgram, &registers, 0, &mut buffer);
+    assert!((0..8).all(|x| buffer[x * 4 + 3] == 0));
+    assert_eq!(red(&buffer, 8) >> 3, 1);
+    registers.m7sel = 0xC0;
+    mode7.render_scanline(&vram, &cgram, &registers, 0, &mut buffer);
+    assert!((0..8).all(|x| red(&buffer, x) >> 3 == 9 && buffer[x * 4 + 3] == 255));
+}
+
+#[test]
+fn test_mode7_bilinear_filtering() {
+    let (vram, cgram) = plane();
+    let registers = PpuRegisters::new();
+    let mut mode7 = Mode7Renderer::new();
+    let mut buffer = vec![0u8; 256 * 4];
+
+    // Half a texel per pixel: odd pixels sit halfway between two texels
+    mode7.m7a = 0x0080;
+    mode7.render_scanline(&vram, &cgram, &registers, 0, &mut buffer);
+    assert_eq!(red(&buffer, 1), 8);
+
+    mode7.bilinear = true;
+    mode7.render_scanline(&vram, &cgram, &registers, 0, &mut buffer);
+    assert_eq!(red(&buffer, 0), 8);
+    assert_eq!(red(&buffer, 1), 12);
+    // Transparent texels don't darken the edge, and what's opaque stays so
+    assert_eq!(red(&buffer, 15), 64);
+    assert_eq!(buffer[16 * 4 + 3], 0);
+}
+
+#[test]
+fn test_game_profile_enables_mode7_bilinear() {
+    let profile: GameProfile = toml::from_str("[enhancements]\nmode7_bilinear = true\n").unwrap();
+    assert!(profile.enhancements.mode7_bilinear);
+    assert_eq!(profile.enhancements.enabled(), vec!["mode7_bilinear"]);
+    assert!(GameProfile::new().enhancements.is_empty());
+
+    let mut emulator = Emulator::new().unwrap();
+    emulator.set_enhancements(profile.enhancements);
+    assert_eq!(emulator.enhancements(), Enhancements { mode7_bilinear: true, ..Enhancements::default() });
+}
+
+#[test]
+fn test_mode7_columns_past_the_screen() {
+    let (vram, cgram) = plane();
+    let mut registers = PpuRegisters::new();
+    let mode7 = Mode7Renderer::new();
+    let mut buffer = vec![0u8; 8 * 4];
+
+    // The 8 columns left of the screen, scrolled so they show the plane's
+    // first texels, which the screen's column 0 would show unscrolled
+    registers.bg1hofs = 8;
+    mode7.render_columns(&vram, &cgram, &registers, (-8, 0), &mut buffer);
+    let texels: Vec<u8> = (0..8).map(|x| red(&buffer, x) >> 3).collect();
+    assert_eq!(texels, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+}
@@ -0,0 +1,303 @@
+use ccsnes::input::controller::{BUTTON_A, BUTTON_B, BUTTON_RIGHT, BUTTON_START};
+use ccsnes::input::{InputScript, InputSource};
+use ccsnes::movie::{self, Movie};
+use ccsnes::movie_editor::MovieEditor;
+use ccsnes::Emulator;
+use ccsnes_test_support::{fresh_dir, LoRom};
+
+// LoROM with the auto-joypad read enabled, so pad 1 is latched into $4218
+// every frame: LDA #$01; STA $4200; BRA *
+fn pad_echo_rom() -> Vec<u8> {
+    LoRom::new("MOVIE TEST", &[0xA9, 0x01, 0x8D, 0x00, 0x42, 0x80, 0xFE]).build()
+}
+
+#[test]
+fn test_input_script_parse() {
+    let script = InputScript::parse(
+        "# title screen\n\
+         60: start\n\
+         \n\
+         120-180: right+b   # run\n\
+         150: A\n\
+         200: p2 a\n\
+         240: none\n",
+    )
+    .unwrap();
+    
+    assert_eq!(script.buttons(59, 0), 0);
+    assert_eq!(script.buttons(60, 0), BUTTON_START);
+    assert_eq!(script.buttons(120, 0), BUTTON_RIGHT | BUTTON_B);
+    assert_eq!(script.buttons(150, 0), BUTTON_RIGHT | BUTTON_B | BUTTON_A);
+    assert_eq!(script.buttons(181, 0), 0);
+    assert_eq!(script.buttons(200, 0), 0);
+    assert_eq!(script.buttons(200, 1), BUTTON_A);
+    assert_eq!(script.frame_count(), 241);
+}
+
+#[test]
+fn test_input_script_errors() {
+    for (text, message) in [
+        ("10 start", "line 1: expected 'frame: buttons'"),
+        ("\nx: start", "line 2: invalid frame 'x'"),
+        ("20-10: a", "line 1: frame range 20-10 ends before it starts"),
+        ("5: a+turbo", "line 1: unknown button 'turbo'"),
+    ] {
+        let error = InputScript::parse(text).unwrap_err().to_string();
+        assert!(error.contains(message), "{}", error);
+    }
+}
+
+#[test]
+fn test_movie_round_trip() {
+    let dir = fresh_dir("ccsnes_movie_round_trip");
+    let path = dir.join("test.movie");
+    let mut movie = Movie::new("MOVIE TEST");
+    movie.push([BUTTON_A, 0]);
+    movie.push([0, BUTTON_START]);
+    movie.rerecords = 3;
+    movie.save(&path).unwrap();
+    
+    let loaded = Movie::load(&path).unwrap();
+    assert_eq!(loaded, movie);
+    assert_eq!(loaded.buttons(0, 0), BUTTON_A);
+    assert_eq!(loaded.buttons(1, 1), BUTTON_START);
+    assert_eq!(loaded.buttons(5, 0), 0);
+    assert_eq!(loaded.frame_count(), 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_movie_checks_rom() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&pad_echo_rom()).unwrap();
+    assert!(Movie::new("MOVIE TEST").check_rom(&emulator).is_ok());
+    assert!(Movie::new("OTHER GAME").check_rom(&emulator).is_err());
+}
+
+#[test]
+fn test_play_script_and_replay_movie() {
+    let mut script = InputScript::new();
+    script.hold(2, 3, 0, BUTTON_A);
+    
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&pad_echo_rom()).unwrap();
+    let mut recording = Movie::for_emulator(&emulator);
+    movie::play(&mut emulator, &script, 3, Some(&mut recording)).unwrap();
+    assert_eq!(recording.len(), 3);
+    assert_eq!(recording.frames()[2], [BUTTON_A, 0]);
+    assert_eq!(emulator.read8(0x4218), BUTTON_A as u8);
+    let scripted = emulator.get_video_buffer().to_vec();
+    
+    // Replaying the recording from power-on gives the same run
+    let mut replay = Emulator::new().unwrap();
+    replay.load_rom(&pad_echo_rom()).unwrap();
+    movie::play(&mut replay, &recording, recording.frame_count(), None).unwrap();
+    assert_eq!(replay.read8(0x4218), BUTTON_A as u8);
+    assert_eq!(replay.get_video_buffer(), &scripted[..]);
+}
+
+
+#[test]
+fn test_movie_editing() {
+    let mut movie = Movie::new("MOVIE TEST");
+    assert!(movie.set_input(2, 0, BUTTON_A).unwrap());
+    assert_eq!(movie.frames(), &[[0, 0], [0, 0], [BUTTON_A, 0]]);
+    assert!(!movie.set_input(2, 0, BUTTON_A).unwrap());
+    // Clearing a frame past the end doesn't grow the movie
+    assert!(!movie.set_input(10, 1, 0).unwrap());
+    assert!(movie.set_input(0, 2, BUTTON_A).is_err());
+    
+    movie.insert_frames(1, 2).unwrap();
+    assert_eq!(movie.len(), 5);
+    assert_eq!(movie.buttons(4, 0), BUTTON_A);
+    movie.delete_frames(0, 4).unwrap();
+    assert_eq!(movie.frames(), &[[BUTTON_A, 0]]);
+    assert!(movie.insert_frames(3, 1).is_err());
+    // Deleting past the end stops at the end
+    movie.delete_frames(0, 100).unwrap();
+    assert!(movie.is_empty());
+    
+    assert_eq!(movie::format_buttons(BUTTON_RIGHT | BUTTON_START | BUTTON_B), "...>.+.B....");
+    movie.push([BUTTON_A, BUTTON_B]);
+    assert_eq!(movie.row(0).unwrap(), "      0 |.........A..|.......B....|");
+    assert_eq!(movie.row(1), None);
+}
+
+#[test]
+fn test_movie_editor_greenzone_replays_edits() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&pad_echo_rom()).unwrap();
+    let mut movie = Movie::for_emulator(&emulator);
+    for _ in 0..30 {
+        movie.push([BUTTON_B, 0]);
+    }
+    movie.rerecords = 4;
+    
+    let mut editor = MovieEditor::new(movie, &mut emulator).unwrap();
+    editor.set_greenzone_interval(5);
+    editor.seek(&mut emulator, 20).unwrap();
+    assert_eq!(editor.playback_frame(), 20);
+    assert_eq!(editor.greenzone_frames().collect::<Vec<_>>(), vec![0, 5, 10, 15, 20]);
+    assert_eq!(emulator.read8(0x4219), (BUTTON_B >> 8) as u8);
+    
+    // Ahead of playback: no rerecord, and states up to the frame stay
+    editor.set_input(25, 0, BUTTON_A).unwrap();
+    assert_eq!(editor.movie().rerecords, 4);
+    assert_eq!(editor.greenzone_frames().last(), Some(20));
+    
+    // Rewriting a frame that was played drops the states after it and
+    // replays to where playback was
+    editor.set_input(12, 0, BUTTON_A).unwrap();
+    assert_eq!(editor.movie().rerecords, 5);
+    assert_eq!(editor.greenzone_frames().collect::<Vec<_>>(), vec![0, 5, 10]);
+    editor.advance(&mut emulator).unwrap();
+    assert_eq!(editor.playback_frame(), 21);
+    assert_eq!(editor.greenzone_frames().collect::<Vec<_>>(), vec![0, 5, 10, 15, 20]);
+    
+    // Seeking back lands on the edited input, the same as a full replay
+    editor.seek(&mut emulator, 13).unwrap();
+    assert_eq!(emulator.read8(0x4218), BUTTON_A as u8);
+    let edited = emulator.get_video_buffer().to_vec();
+    let mut replay = Emulator::new().unwrap();
+    replay.load_rom(&pad_echo_rom()).unwrap();
+    movie::play(&mut replay, editor.movie(), 13, None).unwrap();
+    assert_eq!(replay.get_video_buffer(), &edited[..]);
+    
+    editor.verify().unwrap();
+    let movie = editor.into_movie();
+    assert_eq!(movie.buttons(12, 0), BUTTON_A);
+    assert_eq!(movie.buttons(25, 0), BUTTON_A);
+}
+
+#[test]
+fn test_movie_editor_rerecord_checks() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&pad_echo_rom()).unwrap();
+    let mut movie = Movie::for_emulator(&emulator);
+    movie.push([0, 0]);
+    movie.push([0, 0]);
+    movie.rerecords = 2;
+    
+    let mut editor = MovieEditor::new(movie.clone(), &mut emulator).unwrap();
+    editor.seek(&mut emulator, 2).unwrap();
+    editor.insert_frames(0, 1).unwrap();
+    editor.delete_frames(0, 1).unwrap();
+    assert_eq!(editor.movie().rerecords, 4);
+    
+    // A reloaded copy with fewer rerecords, or for another game, is refused
+    assert!(editor.replace_movie(movie.clone()).is_err());
+    let mut other = movie.clone();
+    other.rom_title = "OTHER GAME".to_string();
+    other.rerecords = 10;
+    assert!(editor.replace_movie(other).is_err());
+    
+    let mut reloaded = editor.movie().clone();
+    reloaded.set_input(1, 1, BUTTON_START).unwrap();
+    editor.replace_movie(reloaded).unwrap();
+    assert_eq!(editor.movie().rerecords, 5);
+    assert_eq!(editor.movie().buttons(1, 1), BUTTON_START);
+    editor.verify().unwrap();
+    
+    let later = editor.movie().clone();
+    assert!(movie.check_rerecords(&later).is_err());
+    assert!(later.check_rerecords(&movie).is_ok());
+}
+
+#[test]
+fn test_record_from_mid_session() {
+    let mut script = InputScript::new();
+    script.hold(0, 9, 0, BUTTON_B);
+    script.hold(6, 9, 0, BUTTON_A);
+
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&pad_echo_rom()).unwrap();
+    movie::play(&mut emulator, &script, 4, None).unwrap();
+    let mut recording = Movie::start_recording(&mut emulator).unwrap();
+    assert!(recording.starts_from_state());
+    assert_eq!(recording.start_frame(), emulator.get_frame_count());
+    for frame in 4..10 {
+        emulator.set_controller_input(0, script.buttons(frame, 0));
+        recording.record_frame(&emulator);
+        emulator.step_frame().unwrap();
+    }
+    assert_eq!(recording.len(), 6);
+    assert_eq!(recording.end_frame(), emulator.get_frame_count());
+    let recorded = emulator.audio_hash();
+
+    // Replaying starts from the anchored state, not power-on, and survives a save
+    let dir = fresh_dir("ccsnes_anchored_movie");
+    let path = dir.join("test.movie");
+    recording.save(&path).unwrap();
+    let loaded = Movie::load(&path).unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+    assert_eq!(loaded, recording);
+
+    let mut replay = Emulator::new().unwrap();
+    replay.load_rom(&pad_echo_rom()).unwrap();
+    loaded.start_playback(&mut replay).unwrap();
+    assert_eq!(replay.get_frame_count(), loaded.start_frame());
+    let mut replayed = Movie::start_recording(&mut replay).unwrap();
+    movie::play(&mut replay, &loaded, loaded.frame_count(), Some(&mut replayed)).unwrap();
+    assert_eq!(replay.get_frame_count(), loaded.end_frame());
+    assert_eq!(replayed.frames(), loaded.frames());
+    assert_eq!(replay.audio_hash(), recorded);
+}
+
+#[test]
+fn test_resume_recording_appends_and_truncates() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&pad_echo_rom()).unwrap();
+    let mut recording = Movie::start_recording(&mut emulator).unwrap();
+    let mut states = Vec::new();
+    for _ in 0..6 {
+        states.push(emulator.save_state().unwrap());
+        emulator.set_controller_input(0, BUTTON_A);
+        recording.record_frame(&emulator);
+        emulator.step_frame().unwrap();
+    }
+    let end_state = emulator.save_state().unwrap();
+
+    // Loading the final state carries on where the movie ends
+    emulator.load_state(&end_state).unwrap();
+    recording.resume_recording(&emulator).unwrap();
+    assert_eq!(recording.len(), 6);
+    assert_eq!(recording.rerecords, 0);
+    recording.record_frame(&emulator);
+    emulator.step_frame().unwrap();
+    assert_eq!(recording.len(), 7);
+
+    // An earlier state drops the frames after it as a rerecord
+    emulator.load_state(&states[2]).unwrap();
+    recording.resume_recording(&emulator).unwrap();
+    assert_eq!(recording.len(), 2);
+    assert_eq!(recording.rerecords, 1);
+
+    // A state before the movie started isn't part of it
+    let mut other = Emulator::new().unwrap();
+    other.load_rom(&pad_echo_rom()).unwrap();
+    movie::play(&mut other, &InputScript::new(), 3, None).unwrap();
+    let mut late = Movie::start_recording(&mut other).unwrap();
+    let error = late.resume_recording(&emulator).unwrap_err().to_string();
+    assert!(error.contains("outside the movie"), "{}", error);
+}
+
+#[test]
+fn test_movie_editor_on_anchored_movie() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&pad_echo_rom()).unwrap();
+    movie::play(&mut emulator, &InputScript::new(), 3, None).unwrap();
+    let mut recording = Movie::start_recording(&mut emulator).unwrap();
+    for _ in 0..4 {
+        recording.record_frame(&emulator);
+        emulator.step_frame().unwrap();
+    }
+    recording.set_input(3, 0, BUTTON_A).unwrap();
+
+    let mut editor = MovieEditor::new(recording.clone(), &mut emulator).unwrap();
+    editor.seek(&mut emulator, 4).unwrap();
+    assert_eq!(emulator.get_frame_count(), recording.end_frame());
+    assert_eq!(emulator.read8(0x4218), BUTTON_A as u8);
+
+    // A power-on movie doesn't start where this one does
+    assert!(editor.replace_movie(Movie::for_emulator(&emulator)).is_err());
+}
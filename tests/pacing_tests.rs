@@ -0,0 +1,126 @@
+use ccsnes::config::{Config, SyncMode};
+use ccsnes::frontend::native::pacing::{self, Pacer, AUDIO_STALL_FRAMES};
+use std::time::{Duration, Instant};
+
+const FRAME: Duration = Duration::from_micros(16_639);
+
+#[test]
+fn test_video_sync_waits_for_the_frame_time() {
+    let mut pacer = Pacer::new(SyncMode::Video, FRAME);
+    let start = Instant::now();
+
+    assert!(pacer.frame_due(start + FRAME, Some(0.0)));
+    // A drained audio queue doesn't pull frames early
+    assert!(!pacer.frame_due(start + FRAME + FRAME / 2, Some(0.0)));
+    assert!(pacer.frame_due(start + FRAME * 2, Some(1.0)));
+}
+
+#[test]
+fn test_audio_sync_follows_the_queue() {
+    let mut pacer = Pacer::new(SyncMode::Audio, FRAME);
+    let start = Instant::now();
+    assert!(pacer.frame_due(start, Some(0.2)));
+    assert!(!pacer.frame_due(start + FRAME, Some(0.8)));
+    assert!(pacer.frame_due(start + FRAME, Some(0.4)));
+}
+
+#[test]
+fn test_audio_sync_falls_back_to_the_timer() {
+    let mut pacer = Pacer::new(SyncMode::Audio, FRAME);
+    let start = Instant::now();
+    assert!(pacer.frame_due(start + FRAME, Some(0.0)));
+
+    // A queue that never drains still gets a frame every few frame times
+    let stalled = start + FRAME;
+    assert!(!pacer.frame_due(stalled + FRAME * (AUDIO_STALL_FRAMES - 1), Some(1.0)));
+    assert!(pacer.frame_due(stalled + FRAME * AUDIO_STALL_FRAMES, Some(1.0)));
+
+    // Without a device it paces like sync-to-video
+    let lost = stalled + FRAME * AUDIO_STALL_FRAMES;
+    assert!(!pacer.frame_due(lost + FRAME / 2, None));
+    assert!(pacer.frame_due(lost + FRAME, None));
+}
+
+#[test]
+fn test_free_run_never_waits() {
+    let mut pacer = Pacer::new(SyncMode::Free, FRAME);
+    let start = Instant::now();
+    for _ in 0..4 {
+        assert!(pacer.frame_due(start, Some(1.0)));
+    }
+    assert!(!pacer.wants_vsync());
+
+    pacer.set_mode(SyncMode::Video);
+    assert_eq!(pacer.mode(), SyncMode::Video);
+    assert!(pacer.wants_vsync());
+}
+
+#[test]
+fn test_sync_mode_cycles_and_parses() {
+    assert_eq!(SyncMode::Video.next(), SyncMode::Audio);
+    assert_eq!(SyncMode::Audio.next(), SyncMode::Free);
+    assert_eq!(SyncMode::Free.next(), SyncMode::Video);
+
+    assert_eq!(Config::default().emulation.sync_mode, SyncMode::Video);
+    let mut config = Config::default();
+    config.emulation.sync_mode = SyncMode::Audio;
+    let text = toml::to_string(&config).unwrap();
+    assert!(text.contains("sync_mode = \"audio\""));
+    let parsed: Config = toml::from_str(&text).unwrap();
+    assert_eq!(parsed.emulation.sync_mode, SyncMode::Audio);
+}
+
+#[test]
+fn test_video_sync_keeps_to_its_schedule() {
+    let mut pacer = Pacer::new(SyncMode::Video, FRAME);
+    let start = Instant::now();
+
+    // Waking late doesn't push the following deadlines back
+    assert!(pacer.frame_due(start + FRAME + FRAME / 4, None));
+    assert!(!pacer.frame_due(start + FRAME * 2 - FRAME / 8, None));
+    assert!(pacer.frame_due(start + FRAME * 2, None));
+
+    // Far behind, it starts a new schedule instead of racing to catch up
+    let stalled = start + FRAME * 10;
+    assert!(pacer.frame_due(stalled, None));
+    assert!(!pacer.frame_due(stalled + FRAME / 2, None));
+    assert!(pacer.frame_due(stalled + FRAME, None));
+}
+
+#[test]
+fn test_video_sync_starts_frames_ahead_of_their_present() {
+    let mut pacer = Pacer::new(SyncMode::Video, FRAME);
+    let start = Instant::now();
+    let took = Duration::from_millis(3);
+    let mut deadline = start + FRAME;
+    for _ in 0..64 {
+        let started = deadline.checked_sub(pacer.lead()).unwrap();
+        assert!(pacer.frame_due(started, None));
+        pacer.presented(started + took);
+        deadline += FRAME;
+    }
+    // Frames now start about as long before the deadline as they take
+    let lead = pacer.lead();
+    assert!(lead > Duration::from_micros(2_900) && lead <= took, "{:?}", lead);
+    // The schedule started when the pacer was made, just before `start`
+    let wake = pacer.wake_at().unwrap();
+    let expected = deadline - lead - pacing::WAKE_MARGIN;
+    assert!(wake.max(expected) - wake.min(expected) < Duration::from_millis(1));
+
+    // The lead is capped, so a blocking present can't start frames too early
+    for _ in 0..64 {
+        let now = deadline - pacer.lead();
+        assert!(pacer.frame_due(now, None));
+        pacer.presented(now + FRAME);
+        deadline += FRAME;
+    }
+    assert_eq!(pacer.lead(), FRAME / 4);
+}
+
+#[test]
+fn test_only_video_sync_sleeps() {
+    assert!(Pacer::new(SyncMode::Video, FRAME).wake_at().is_some());
+    assert!(Pacer::new(SyncMode::Audio, FRAME).wake_at().is_none());
+    assert!(Pacer::new(SyncMode::Free, FRAME).wake_at().is_none());
+    assert!((ccsnes::metrics::FRAME_RATE_HZ - 60.0988).abs() < 0.001);
+}
@@ -0,0 +1,103 @@
+use ccsnes::config::PathConfig;
+use ccsnes::paths::{GameId, GamePaths};
+use ccsnes_test_support::fresh_dir;
+use std::path::{Path, PathBuf};
+
+fn test_config(root: &Path) -> PathConfig {
+    PathConfig {
+        rom_dir: root.join("roms"),
+        save_state_dir: root.join("states"),
+        sram_dir: root.join("saves"),
+        screenshot_dir: root.join("screenshots"),
+        bios_dir: root.join("bios"),
+        data_root: root.join("games"),
+        per_game_dirs: true,
+        library_cache: root.join("library.toml"),
+        rom_database: root.join("snes.dat"),
+    }
+}
+
+#[test]
+fn test_game_id_dir_name() {
+    let id = GameId::new("Super Game", b"123456789");
+    // CRC32 of "123456789"
+    assert_eq!(id.crc32, 0xCBF43926);
+    assert_eq!(id.dir_name(), "Super Game-CBF43926");
+
+    let id = GameId::from_rom_path(Path::new("/roms/Bad:Name?.sfc"), &[]);
+    assert_eq!(id.name, "Bad_Name_");
+    assert_eq!(id.stem, "Bad:Name?");
+}
+
+#[test]
+fn test_per_game_and_flat_layouts() {
+    let root = PathBuf::from("/data");
+    let config = test_config(&root);
+    let id = GameId::new("game", b"123456789");
+
+    let paths = GamePaths::new(&config, &id);
+    let dir = root.join("games").join("game-CBF43926");
+    assert_eq!(paths.sram_path(), dir.join("game.srm"));
+    assert_eq!(paths.state_path(3), dir.join("states").join("slot3"));
+    assert_eq!(paths.screenshot_path(42, "ppm"), dir.join("screenshots").join("game-42.ppm"));
+    assert_eq!(paths.cheats_path(), dir.join("cheats.toml"));
+
+    let config = PathConfig { per_game_dirs: false, ..config };
+    let paths = GamePaths::new(&config, &id);
+    assert_eq!(paths.sram_path(), root.join("saves").join("game.srm"));
+    assert_eq!(paths.state_path(3), root.join("states").join("game.st3"));
+    assert_eq!(paths.screenshot_path(42, "ppm"), root.join("screenshots").join("game-42.ppm"));
+
+    // The flat layout keeps the file name as it was, so saves made before
+    // per-game directories existed are still found
+    let id = GameId::from_rom_path(Path::new("/roms/Game (U) [!].sfc"), b"123456789");
+    let paths = GamePaths::new(&config, &id);
+    assert_eq!(paths.sram_path(), root.join("saves").join("Game (U) [!].srm"));
+    assert_eq!(paths.state_path(0), root.join("states").join("Game (U) [!].st0"));
+}
+
+#[test]
+fn test_migrate_from_flat_layout() {
+    let root = fresh_dir("ccsnes_paths_migrate");
+    let config = test_config(&root);
+    let id = GameId::new("game", b"rom");
+
+    let flat = GamePaths::flat(&config, &id.name);
+    let per_game = GamePaths::per_game(&config.data_root, &id);
+    flat.create_directories().unwrap();
+    per_game.create_directories().unwrap();
+
+    std::fs::write(flat.sram_path(), b"old sram").unwrap();
+    std::fs::write(flat.state_path(0), b"state 0").unwrap();
+    std::fs::write(flat.state_path(1), b"old state 1").unwrap();
+    std::fs::write(flat.screenshot_path(7, "ppm"), b"shot").unwrap();
+    std::fs::write(flat.screenshot_dir().join("other-7.ppm"), b"other game").unwrap();
+    // A game whose name starts with this one's keeps its screenshots
+    std::fs::write(flat.screenshot_dir().join("game-kart-7.ppm"), b"game kart").unwrap();
+
+    // Already present in the new layout, must be kept
+    std::fs::write(per_game.state_path(1), b"new state 1").unwrap();
+
+    let moved = per_game.migrate_from(&flat).unwrap();
+    assert_eq!(moved.len(), 3);
+
+    assert_eq!(std::fs::read(per_game.sram_path()).unwrap(), b"old sram");
+    assert_eq!(std::fs::read(per_game.state_path(0)).unwrap(), b"state 0");
+    assert_eq!(std::fs::read(per_game.state_path(1)).unwrap(), b"new state 1");
+    assert_eq!(std::fs::read(per_game.screenshot_path(7, "ppm")).unwrap(), b"shot");
+    assert!(!flat.sram_path().exists());
+    assert!(flat.state_path(1).exists());
+    assert!(flat.screenshot_dir().join("other-7.ppm").exists());
+    assert!(flat.screenshot_dir().join("game-kart-7.ppm").exists());
+
+    // Nothing left to move on the second run
+    assert!(per_game.migrate_from(&flat).unwrap().is_empty());
+
+    // prepare finds flat files under the file name as it was
+    let id = GameId::from_rom_path(Path::new("/roms/Game (U) [!].sfc"), b"rom");
+    std::fs::write(config.sram_dir.join("Game (U) [!].srm"), b"bang sram").unwrap();
+    let paths = GamePaths::prepare(&config, &id).unwrap();
+    assert_eq!(std::fs::read(paths.sram_path()).unwrap(), b"bang sram");
+
+    let _ = std::fs::remove_dir_all(&root);
+}
@@ -0,0 +1,74 @@
+use ccsnes::config::HotkeyConfig;
+use ccsnes::frontend::native::pause_menu::{MenuAction, MenuItem, PauseMenu, ITEMS};
+use ccsnes::frontend::native::STATE_SLOTS;
+use ccsnes::input::hotkeys::{HotkeyAction, HotkeyManager, Modifiers};
+
+fn select(menu: &mut PauseMenu, item: MenuItem) {
+    while menu.selected() != item {
+        menu.handle_key("Down");
+    }
+}
+
+#[test]
+fn test_pause_menu_navigation_wraps() {
+    let mut menu = PauseMenu::new(0, false, 80);
+    assert_eq!(menu.selected(), MenuItem::Resume);
+    menu.handle_key("Up");
+    assert_eq!(menu.selected(), MenuItem::Quit);
+    for _ in 0..ITEMS.len() {
+        menu.handle_key("Down");
+    }
+    assert_eq!(menu.selected(), MenuItem::Quit);
+    assert_eq!(menu.handle_key("Return"), MenuAction::Quit);
+    assert_eq!(menu.handle_key("Escape"), MenuAction::Resume);
+}
+
+#[test]
+fn test_pause_menu_state_slots() {
+    let mut menu = PauseMenu::new(3, false, 80);
+    select(&mut menu, MenuItem::SaveState);
+    assert_eq!(menu.handle_key("Right"), MenuAction::None);
+    assert_eq!(menu.handle_key("Return"), MenuAction::SaveState(4));
+
+    // Load shares the slot, and it wraps both ways
+    select(&mut menu, MenuItem::LoadState);
+    for _ in 0..5 {
+        menu.handle_key("Left");
+    }
+    assert_eq!(menu.slot(), STATE_SLOTS - 1);
+    assert_eq!(menu.handle_key("Return"), MenuAction::LoadState(STATE_SLOTS - 1));
+}
+
+#[test]
+fn test_pause_menu_settings() {
+    let mut menu = PauseMenu::new(0, false, 195);
+    select(&mut menu, MenuItem::Filter);
+    assert_eq!(menu.handle_key("Return"), MenuAction::SetFrameBlend(true));
+    assert_eq!(menu.handle_key("Left"), MenuAction::SetFrameBlend(false));
+
+    select(&mut menu, MenuItem::Volume);
+    assert_eq!(menu.handle_key("Right"), MenuAction::SetVolume(200));
+    assert_eq!(menu.handle_key("Left"), MenuAction::SetVolume(190));
+    for _ in 0..20 {
+        menu.handle_key("Left");
+    }
+    assert_eq!(menu.volume(), 0);
+    assert_eq!(menu.handle_key("Return"), MenuAction::None);
+}
+
+#[test]
+fn test_pause_menu_draws_over_the_frame() {
+    let menu = PauseMenu::new(0, false, 80);
+    let mut frame = vec![0xFF; 256 * 224 * 4];
+    menu.draw(&mut frame);
+    // The game behind it is darkened, alpha untouched
+    assert_eq!(&frame[..4], &[0x55, 0x55, 0x55, 0xFF]);
+    let center = (112 * 256 + 128) * 4;
+    assert_ne!(&frame[center..center + 4], &[0xFF, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn test_pause_menu_hotkey_default() {
+    let manager = HotkeyManager::from_config(&HotkeyConfig::default()).unwrap();
+    assert_eq!(manager.match_key("P", Modifiers::default()), Some(HotkeyAction::PauseMenu));
+}
@@ -0,0 +1,52 @@
+use ccsnes::ppu::pixel::{self, PixelFormat};
+
+#[test]
+fn test_rgb565_matches_scalar_for_all_values() {
+    // Every possible RGB565 value, plus an odd tail to exercise the remainder path
+    let mut src = Vec::with_capacity(0x10003 * 2);
+    for value in 0..=0xFFFFu16 {
+        src.extend_from_slice(&value.to_le_bytes());
+    }
+    src.extend_from_slice(&[0x1F, 0xF8, 0xE0, 0x07, 0x00, 0x00]);
+    
+    let mut fast = vec![0u8; src.len() * 2];
+    let mut reference = vec![0u8; src.len() * 2];
+    pixel::rgb565_to_rgba(&src, &mut fast);
+    pixel::rgb565_to_rgba_scalar(&src, &mut reference);
+    
+    assert_eq!(fast, reference);
+}
+
+#[test]
+fn test_rgb565_channel_expansion() {
+    // White, red, green, blue
+    let src = [0xFF, 0xFF, 0x00, 0xF8, 0xE0, 0x07, 0x1F, 0x00];
+    let mut dst = [0u8; 16];
+    pixel::convert_to_rgba(PixelFormat::Rgb565, &src, &mut dst);
+    
+    assert_eq!(&dst[0..4], &[255, 255, 255, 255]);
+    assert_eq!(&dst[4..8], &[255, 0, 0, 255]);
+    assert_eq!(&dst[8..12], &[0, 255, 0, 255]);
+    assert_eq!(&dst[12..16], &[0, 0, 255, 255]);
+}
+
+#[test]
+fn test_rgba_passthrough() {
+    let src = [1, 2, 3, 4, 5, 6, 7, 8];
+    let mut dst = [0u8; 8];
+    pixel::convert_to_rgba(PixelFormat::Rgba8888, &src, &mut dst);
+    
+    assert_eq!(dst, src);
+    assert_eq!(PixelFormat::Rgba8888.bytes_per_pixel(), 4);
+    assert_eq!(PixelFormat::Rgb565.bytes_per_pixel(), 2);
+}
+
+#[test]
+fn test_conversion_clamps_to_destination() {
+    // Destination only has room for one pixel
+    let src = [0xFF, 0xFF, 0xFF, 0xFF];
+    let mut dst = [0u8; 6];
+    pixel::rgb565_to_rgba(&src, &mut dst);
+    
+    assert_eq!(dst, [255, 255, 255, 255, 0, 0]);
+}
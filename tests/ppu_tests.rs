@@ -1,5 +1,9 @@
-use ccsnes::ppu::Ppu;
+use ccsnes::ppu::{Ppu, Layer, PpuTiming, RenderMode, VramAddress};
 use ccsnes::memory::Bus;
+use ccsnes::ppu::frame_blend::FrameBlender;
+use ccsnes::enhancements::{self, Enhancements};
+use ccsnes::profile::GameProfile;
+use ccsnes::Emulator;
 
 #[test]
 fn test_ppu_reset() {
@@ -170,4 +174,974 @@ fn test_nmi_generation() {
     
     // Should NOT have NMI pending when screen is blanked
     assert!(!ppu.nmi_pending());
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_layer_toggles() {
+    let mut ppu = Ppu::new();
+    
+    // All layers are enabled by default
+    for layer in Layer::ALL {
+        assert!(ppu.is_layer_enabled(layer));
+    }
+    
+    ppu.set_layer_enabled(Layer::Bg3, false);
+    ppu.set_layer_enabled(Layer::Obj, false);
+    assert!(ppu.is_layer_enabled(Layer::Bg1));
+    assert!(!ppu.is_layer_enabled(Layer::Bg3));
+    assert!(!ppu.is_layer_enabled(Layer::Obj));
+    
+    ppu.set_layer_enabled(Layer::Bg3, true);
+    assert!(ppu.is_layer_enabled(Layer::Bg3));
+}
+
+#[test]
+fn test_disabled_layer_not_composited() {
+    let mut ppu = Ppu::new();
+    let mut bus = Bus::new();
+    
+    // Mode 0, BG1 tilemap at $0000, BG1 tiles at $1000, BG1 on main screen
+    ppu.write_register(0x2100, 0x0F); // INIDISP - full brightness
+    ppu.write_register(0x2105, 0x00); // BGMODE
+    ppu.write_register(0x2107, 0x00); // BG1SC
+    ppu.write_register(0x210B, 0x01); // BG12NBA
+    ppu.write_register(0x212C, 0x01); // TM
+    
+    // Fill tile 0 with solid pixels
+    ppu.write_register(0x2115, 0x80); // VMAIN - increment after high byte
+    ppu.write_register(0x2116, 0x00);
+    ppu.write_register(0x2117, 0x10);
+    for _ in 0..16 {
+        ppu.write_register(0x2118, 0xFF);
+        ppu.write_register(0x2119, 0xFF);
+    }
+    
+    // Palette 0 colors 1-3 = red
+    ppu.write_register(0x2121, 0x02);
+    for _ in 0..3 {
+        ppu.write_register(0x2122, 0x1F);
+        ppu.write_register(0x2122, 0x00);
+    }
+    
+    // Render scanline 1 with BG1 enabled
+    for _ in 0..341 {
+        ppu.step(&mut bus);
+    }
+    let row1 = 256 * 4;
+    assert_ne!(ppu.get_frame_buffer()[row1], 0);
+    
+    // Render scanline 2 with BG1 forced off
+    ppu.set_layer_enabled(Layer::Bg1, false);
+    for _ in 0..341 {
+        ppu.step(&mut bus);
+    }
+    let row2 = 2 * 256 * 4;
+    assert_eq!(ppu.get_frame_buffer()[row2], 0);
+}
+
+#[test]
+fn test_counter_latch_via_2137() {
+    let mut bus = Bus::new();
+    assert_eq!(bus.read8(0x4213), 0xFF);
+    
+    bus.set_beam_position(0x123, 0x045);
+    bus.read8(0x2137);
+    assert_eq!(bus.read8(0x213C), 0x23);
+    assert_eq!(bus.read8(0x213C), 0x01);
+    assert_eq!(bus.read8(0x213D), 0x45);
+    
+    // STAT78 reports the latch once and resets the flip-flops
+    assert_eq!(bus.read8(0x213F) & 0x40, 0x40);
+    assert_eq!(bus.read8(0x213F) & 0x40, 0);
+    assert_eq!(bus.read8(0x213D), 0x45);
+    assert_eq!(bus.read8(0x213D), 0x00);
+}
+
+#[test]
+fn test_counter_latch_via_io_pin() {
+    let mut bus = Bus::new();
+    
+    // With pin 7 low, $2137 reads do not latch
+    bus.write8(0x4201, 0x7F);
+    assert!(bus.counter_latch().is_latched());
+    bus.read8(0x213F);
+    bus.set_beam_position(10, 20);
+    bus.read8(0x2137);
+    assert!(!bus.counter_latch().is_latched());
+    assert_eq!(bus.read8(0x4213), 0x7F);
+    
+    // A high-to-low transition latches
+    bus.write8(0x4201, 0xFF);
+    assert!(!bus.counter_latch().is_latched());
+    bus.set_beam_position(200, 100);
+    bus.write8(0x4201, 0x00);
+    assert_eq!(bus.counter_latch().position(), (200, 100));
+    bus.write8(0x4201, 0xFF);
+    bus.read8(0x213F);
+    
+    // So does a device pulling the pin low (light gun)
+    bus.set_beam_position(64, 32);
+    bus.set_io_pins_low(0x80);
+    assert_eq!(bus.read8(0x4213), 0x7F);
+    assert!(bus.counter_latch().is_latched());
+    assert_eq!(bus.counter_latch().position(), (64, 32));
+}
+
+// Mode 0 with BG1 filled by tile 0 drawn in color 3 (red)
+fn solid_bg1_ppu() -> Ppu {
+    let mut ppu = Ppu::new();
+    ppu.write_register(0x2100, 0x0F); // INIDISP - full brightness
+    ppu.write_register(0x2105, 0x00); // BGMODE
+    ppu.write_register(0x2107, 0x00); // BG1SC
+    ppu.write_register(0x210B, 0x01); // BG12NBA
+    ppu.write_register(0x212C, 0x01); // TM
+    
+    // Tile 0 with every pixel in color 3
+    ppu.write_register(0x2115, 0x80);
+    ppu.write_register(0x2116, 0x00);
+    ppu.write_register(0x2117, 0x10);
+    for _ in 0..16 {
+        ppu.write_register(0x2118, 0xFF);
+        ppu.write_register(0x2119, 0xFF);
+    }
+    
+    ppu.write_register(0x2121, 0x06); // color 3 (CGRAM byte address)
+    ppu.write_register(0x2122, 0x1F);
+    ppu.write_register(0x2122, 0x00);
+    ppu
+}
+
+fn set_color3_green(ppu: &mut Ppu) {
+    ppu.write_register(0x2121, 0x06); // color 3 (CGRAM byte address)
+    ppu.write_register(0x2122, 0xE0);
+    ppu.write_register(0x2122, 0x03);
+}
+
+fn pixel(ppu: &Ppu, x: usize, y: usize) -> [u8; 3] {
+    let offset = (y * 256 + x) * 4;
+    let frame = ppu.get_frame_buffer();
+    [frame[offset], frame[offset + 1], frame[offset + 2]]
+}
+
+#[test]
+fn test_mid_scanline_palette_write() {
+    let mut ppu = solid_bg1_ppu();
+    let mut bus = Bus::new();
+    
+    // Start of scanline 1, then on to pixel 128 (dot 22 is the first pixel)
+    for _ in 0..341 + 22 + 128 {
+        ppu.step(&mut bus);
+    }
+    set_color3_green(&mut ppu);
+    for _ in 0..200 {
+        ppu.step(&mut bus);
+    }
+    
+    // Pixels already output keep the old color, the rest use the new one
+    assert!(pixel(&ppu, 0, 1)[0] > 0 && pixel(&ppu, 0, 1)[1] == 0);
+    assert!(pixel(&ppu, 127, 1)[0] > 0);
+    assert!(pixel(&ppu, 128, 1)[1] > 0 && pixel(&ppu, 128, 1)[0] == 0);
+    assert!(pixel(&ppu, 255, 1)[1] > 0);
+}
+
+#[test]
+fn test_set_position_mid_frame() {
+    let mut ppu = solid_bg1_ppu();
+    let mut bus = Bus::new();
+    
+    // Straight to the first pixel of line 100, then a write at pixel 128
+    ppu.set_position(100, 22).unwrap();
+    step_dots(&mut ppu, &mut bus, 128);
+    set_color3_green(&mut ppu);
+    step_dots(&mut ppu, &mut bus, 200);
+    assert!(pixel(&ppu, 127, 100)[0] > 0 && pixel(&ppu, 127, 100)[1] == 0);
+    assert!(pixel(&ppu, 128, 100)[1] > 0 && pixel(&ppu, 128, 100)[0] == 0);
+    
+    let timing = ppu.timing();
+    assert_eq!(timing, PpuTiming {
+        scanline: 101,
+        dot: 22 + 328 - 341,
+        frame: 0,
+        field: false,
+        vblank: false,
+        hblank: true,
+        vblank_start: 225,
+    });
+    
+    ppu.set_position(261, 300).unwrap();
+    assert!(ppu.timing().vblank && ppu.timing().hblank);
+    step_dots(&mut ppu, &mut bus, 41);
+    assert_eq!((ppu.timing().scanline, ppu.timing().frame, ppu.timing().field), (0, 1, true));
+    
+    assert!(ppu.set_position(262, 0).is_err());
+    assert!(ppu.set_position(0, 341).is_err());
+}
+
+#[test]
+fn test_hblank_write_affects_next_scanline() {
+    let mut ppu = solid_bg1_ppu();
+    let mut bus = Bus::new();
+    
+    // Write during H-Blank of scanline 1
+    for _ in 0..341 + 300 {
+        ppu.step(&mut bus);
+    }
+    set_color3_green(&mut ppu);
+    for _ in 0..341 {
+        ppu.step(&mut bus);
+    }
+    
+    assert!(pixel(&ppu, 200, 1)[0] > 0 && pixel(&ppu, 200, 1)[1] == 0);
+    assert!(pixel(&ppu, 0, 2)[1] > 0 && pixel(&ppu, 0, 2)[0] == 0);
+}
+
+fn step_dots(ppu: &mut Ppu, bus: &mut Bus, dots: usize) {
+    for _ in 0..dots {
+        ppu.step(bus);
+    }
+}
+
+#[test]
+fn test_dot_renderer_applies_each_write_at_its_dot() {
+    let mut ppu = solid_bg1_ppu();
+    ppu.set_render_mode(RenderMode::Dot);
+    let mut bus = Bus::new();
+    
+    // Green from pixel 64, red again from pixel 192, dimmed from pixel 224
+    step_dots(&mut ppu, &mut bus, 341 + 22 + 64);
+    set_color3_green(&mut ppu);
+    step_dots(&mut ppu, &mut bus, 128);
+    ppu.write_register(0x2121, 0x06);
+    ppu.write_register(0x2122, 0x1F);
+    ppu.write_register(0x2122, 0x00);
+    step_dots(&mut ppu, &mut bus, 32);
+    ppu.write_register(0x2100, 0x07);
+    step_dots(&mut ppu, &mut bus, 341);
+    
+    assert!(pixel(&ppu, 63, 1)[0] > 0 && pixel(&ppu, 63, 1)[1] == 0);
+    assert!(pixel(&ppu, 64, 1)[1] > 0 && pixel(&ppu, 64, 1)[0] == 0);
+    assert!(pixel(&ppu, 191, 1)[1] > 0);
+    assert!(pixel(&ppu, 192, 1)[0] > 0 && pixel(&ppu, 192, 1)[1] == 0);
+    let (bright, dim) = (pixel(&ppu, 223, 1)[0], pixel(&ppu, 224, 1)[0]);
+    assert!(dim > 0 && dim < bright);
+    // Scanline 2 starts with the last state
+    assert_eq!(pixel(&ppu, 0, 2), pixel(&ppu, 224, 1));
+}
+
+#[test]
+fn test_dot_renderer_matches_scanline_renderer() {
+    let mut bus = Bus::new();
+    let mut scanline = solid_bg1_ppu();
+    let mut dot = solid_bg1_ppu();
+    dot.set_render_mode(RenderMode::Dot);
+    assert_eq!(dot.render_mode(), RenderMode::Dot);
+    
+    for ppu in [&mut scanline, &mut dot] {
+        // A write in the middle of scanline 3
+        step_dots(ppu, &mut bus, 3 * 341 + 150);
+        set_color3_green(ppu);
+        step_dots(ppu, &mut bus, 262 * 341);
+    }
+    assert_eq!(scanline.get_frame_buffer(), dot.get_frame_buffer());
+}
+
+#[test]
+fn test_counter_latch_at_access_dot() {
+    let mut bus = Bus::new();
+    bus.set_beam_position(100, 20);
+    
+    // By default the latch uses the instruction's start position
+    bus.take_access_timing();
+    bus.read8(0x0000);
+    bus.read8(0x2137);
+    assert_eq!(bus.counter_latch().position(), (100, 20));
+    
+    // With dot rendering it adds the dots the accesses so far took: 8
+    // master cycles for WRAM and 6 for $2137
+    bus.set_latch_at_access(true);
+    bus.take_access_timing();
+    bus.read8(0x0000);
+    bus.read8(0x2137);
+    assert_eq!(bus.counter_latch().position(), (103, 20));
+    
+    // Wrapping into the next scanline
+    bus.set_beam_position(340, 261);
+    bus.take_access_timing();
+    bus.read8(0x0000);
+    bus.read8(0x2137);
+    assert_eq!(bus.counter_latch().position(), (2, 0));
+}
+
+#[test]
+fn test_frame_blend_averages_consecutive_frames() {
+    let mut blender = FrameBlender::new(true);
+    
+    // First frame passes through
+    let mut frame = vec![200, 100, 0, 255];
+    blender.apply(&mut frame);
+    assert_eq!(frame, vec![200, 100, 0, 255]);
+    
+    // A sprite flickered off: half its brightness remains
+    let mut frame = vec![0, 100, 50, 255];
+    blender.apply(&mut frame);
+    assert_eq!(frame, vec![100, 100, 25, 255]);
+    
+    // Blended with the unblended previous frame, not the blended output
+    let mut frame = vec![200, 100, 0, 255];
+    blender.apply(&mut frame);
+    assert_eq!(frame, vec![100, 100, 25, 255]);
+    
+    // Disabled: frames are untouched and toggling back on starts fresh
+    blender.set_enabled(false);
+    let mut frame = vec![10, 20, 30, 255];
+    blender.apply(&mut frame);
+    assert_eq!(frame, vec![10, 20, 30, 255]);
+    blender.set_enabled(true);
+    let mut frame = vec![40, 40, 40, 255];
+    blender.apply(&mut frame);
+    assert_eq!(frame, vec![40, 40, 40, 255]);
+    
+    blender.reset();
+    let mut frame = vec![0, 0, 0, 255];
+    blender.apply(&mut frame);
+    assert_eq!(frame, vec![0, 0, 0, 255]);
+}
+
+// Mode 1 with BG3 filled by a red tile (priority bit `bg3_high`) and an
+// 8x8 green sprite of `sprite_priority` at (16, 16)
+fn bg3_and_sprite_ppu(bgmode: u8, bg3_high: bool, sprite_priority: u8) -> Ppu {
+    let mut ppu = Ppu::new();
+    let mut vram = vec![0; 0x10000];
+    let entry: u16 = 0x0001 | if bg3_high { 0x2000 } else { 0 };
+    for cell in vram[0x0800..0x1000].chunks_exact_mut(2) {
+        cell.copy_from_slice(&entry.to_le_bytes());
+    }
+    vram[0x4010..0x4020].fill(0xFF); // BG3 tile 1, color 3
+    vram[0x0040..0x0060].fill(0xFF); // sprite tile 2, color 15
+    ppu.set_vram(&vram);
+    
+    let mut oam = vec![0; 544];
+    for sprite in oam[..512].chunks_exact_mut(4) {
+        sprite[1] = 0xF0; // below the screen
+    }
+    oam[..4].copy_from_slice(&[16, 16, 2, sprite_priority << 4]);
+    ppu.set_oam(&oam);
+    
+    let mut cgram = vec![0; 0x200];
+    cgram[3 * 2..3 * 2 + 2].copy_from_slice(&0x001Fu16.to_le_bytes());
+    cgram[143 * 2..143 * 2 + 2].copy_from_slice(&0x03E0u16.to_le_bytes());
+    ppu.set_cgram(&cgram);
+    
+    ppu.write_register(0x2100, 0x0F);
+    ppu.write_register(0x2101, 0x00); // OBSEL - 8x8 sprites at $0000
+    ppu.write_register(0x2105, bgmode);
+    ppu.write_register(0x2109, 0x04); // BG3SC - tilemap at word $0400
+    ppu.write_register(0x210C, 0x20); // BG34NBA - BG3 tiles at word $2000
+    ppu.write_register(0x212C, 0x14); // TM - BG3 and sprites
+    ppu
+}
+
+fn rendered_pixel(mut ppu: Ppu, x: usize, y: usize) -> [u8; 3] {
+    let mut bus = Bus::new();
+    let frame = ppu.get_frame_count();
+    while ppu.get_frame_count() == frame {
+        ppu.step(&mut bus);
+    }
+    pixel(&ppu, x, y)
+}
+
+const RED: [u8; 3] = [248, 0, 0];
+const GREEN: [u8; 3] = [0, 248, 0];
+
+#[test]
+fn test_mode1_bg3_priority_puts_bg3_above_sprites() {
+    // BGMODE bit 3: high-priority BG3 tiles cover even priority 3 sprites
+    assert_eq!(rendered_pixel(bg3_and_sprite_ppu(0x09, true, 3), 20, 20), RED);
+    
+    // Without it, sprites of any priority are in front of BG3
+    assert_eq!(rendered_pixel(bg3_and_sprite_ppu(0x01, true, 3), 20, 20), GREEN);
+    assert_eq!(rendered_pixel(bg3_and_sprite_ppu(0x01, true, 1), 20, 20), GREEN);
+    
+    // The bit only promotes tiles that have their own priority bit set
+    assert_eq!(rendered_pixel(bg3_and_sprite_ppu(0x09, false, 0), 20, 20), GREEN);
+    
+    // Away from the sprite BG3 shows either way
+    assert_eq!(rendered_pixel(bg3_and_sprite_ppu(0x01, true, 3), 40, 40), RED);
+}
+
+#[test]
+fn test_mode1_priority_0_sprite_behind_high_bg3() {
+    assert_eq!(rendered_pixel(bg3_and_sprite_ppu(0x01, true, 0), 20, 20), RED);
+    assert_eq!(rendered_pixel(bg3_and_sprite_ppu(0x01, false, 0), 20, 20), GREEN);
+}
+
+// Mode 7 with the whole plane in tile 0, every texel `texel`, and the
+// green sprite of bg3_and_sprite_ppu at (16, 16). Texel $81 is red on BG1
+// and blue on EXTBG, which drops bit 7; texel $02 is red.
+fn mode7_and_sprite_ppu(setini: u8, texel: u8, sprite_priority: u8) -> Ppu {
+    let mut ppu = Ppu::new();
+    let mut vram = vec![0; 0x10000];
+    for word in 0..64 {
+        vram[word * 2 + 1] = texel; // tile 0 in the high bytes
+    }
+    vram[0x4040..0x4060].fill(0xFF); // sprite tile 2 at word $2000, color 15
+    ppu.set_vram(&vram);
+    
+    let mut oam = vec![0; 544];
+    for sprite in oam[..512].chunks_exact_mut(4) {
+        sprite[1] = 0xF0;
+    }
+    oam[..4].copy_from_slice(&[16, 16, 2, sprite_priority << 4]);
+    ppu.set_oam(&oam);
+    
+    let mut cgram = vec![0; 0x200];
+    for (color, rgb) in [(0x81, 0x001Fu16), (0x01, 0x7C00), (0x02, 0x001F), (143, 0x03E0)] {
+        cgram[color * 2..color * 2 + 2].copy_from_slice(&rgb.to_le_bytes());
+    }
+    ppu.set_cgram(&cgram);
+    
+    ppu.write_register(0x2100, 0x0F);
+    ppu.write_register(0x2101, 0x01); // OBSEL - 8x8 sprites at word $2000
+    ppu.write_register(0x2105, 0x07);
+    ppu.write_register(0x212C, 0x13); // TM - BG1, BG2 and sprites
+    ppu.write_register(0x2133, setini);
+    ppu
+}
+
+#[test]
+fn test_mode7_extbg_priority() {
+    // High-priority EXTBG pixels cover sprites of priority 0 and 1...
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x40, 0x81, 0), 20, 20), BLUE);
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x40, 0x81, 1), 20, 20), BLUE);
+    // ...but not 2 and 3
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x40, 0x81, 2), 20, 20), GREEN);
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x40, 0x81, 3), 20, 20), GREEN);
+    
+    // Low-priority texels leave EXTBG empty, and BG1 only covers priority 0
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x40, 0x02, 0), 20, 20), RED);
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x40, 0x02, 1), 20, 20), GREEN);
+    
+    // Without EXTBG the same texels are BG1's
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x00, 0x81, 0), 20, 20), RED);
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x00, 0x81, 1), 20, 20), GREEN);
+    assert_eq!(rendered_pixel(mode7_and_sprite_ppu(0x40, 0x81, 0), 40, 40), BLUE);
+}
+
+#[test]
+fn test_layer_toggles_uncover_the_next_layer_by_priority() {
+    let hidden = |layers: &[Layer]| {
+        let mut ppu = mode7_and_sprite_ppu(0x40, 0x81, 0);
+        for &layer in layers {
+            ppu.set_layer_enabled(layer, false);
+        }
+        rendered_pixel(ppu, 20, 20)
+    };
+    
+    assert_eq!(hidden(&[]), BLUE);
+    assert_eq!(hidden(&[Layer::Bg2]), RED);
+    assert_eq!(hidden(&[Layer::Bg2, Layer::Bg1]), GREEN);
+    assert_eq!(hidden(&[Layer::Bg2, Layer::Bg1, Layer::Obj]), BLACK);
+    
+    // Hiding the sprite doesn't change what covers it
+    assert_eq!(hidden(&[Layer::Obj]), BLUE);
+}
+
+#[test]
+fn test_priority_order_per_mode() {
+    use ccsnes::ppu::priority::{layer_order, Plane};
+    
+    assert_eq!(layer_order(0x01)[0], Plane::Obj(3));
+    assert_eq!(layer_order(0x09)[0], Plane::Bg(2, true));
+    assert_eq!(layer_order(0x09).len(), layer_order(0x01).len());
+    // Bit 3 means nothing outside Mode 1
+    assert_eq!(layer_order(0x08), layer_order(0x00));
+    assert_eq!(layer_order(0x0B), layer_order(0x03));
+}
+
+// An 8x8 sprite at (16, 16) whose rows 0-7 come out in colors
+// 5, 10, 5, 10, 1, 2, 1, 2 (128 + color in CGRAM)
+fn interlace_sprite_ppu(setini: u8) -> Ppu {
+    let mut ppu = Ppu::new();
+    let mut vram = vec![0; 0x10000];
+    // Tile 2 is bytes $40-$5F: planes 0-1 a word per row, then planes 2-3
+    for row in 0..8 {
+        vram[0x40 + row * 2 + row % 2] = 0xFF;
+        if row < 4 {
+            vram[0x50 + row * 2 + row % 2] = 0xFF;
+        }
+    }
+    ppu.set_vram(&vram);
+    
+    let mut oam = vec![0; 544];
+    for sprite in oam[..512].chunks_exact_mut(4) {
+        sprite[1] = 0xF0;
+    }
+    oam[..4].copy_from_slice(&[16, 16, 2, 0x30]);
+    ppu.set_oam(&oam);
+    
+    let mut cgram = vec![0; 0x200];
+    for (color, rgb) in [(133, 0x001Fu16), (138, 0x03E0), (129, 0x7C00), (130, 0x7FFF)] {
+        cgram[color * 2..color * 2 + 2].copy_from_slice(&rgb.to_le_bytes());
+    }
+    ppu.set_cgram(&cgram);
+    
+    ppu.write_register(0x2100, 0x0F);
+    ppu.write_register(0x2105, 0x01);
+    ppu.write_register(0x212C, 0x10);
+    ppu.write_register(0x2133, setini);
+    ppu
+}
+
+fn render_frames(ppu: &mut Ppu, frames: u64) {
+    let mut bus = Bus::new();
+    let end = ppu.get_frame_count() + frames;
+    while ppu.get_frame_count() < end {
+        ppu.step(&mut bus);
+    }
+}
+
+const BLUE: [u8; 3] = [0, 0, 248];
+const BLACK: [u8; 3] = [0, 0, 0];
+
+#[test]
+fn test_obj_interlace_draws_alternate_rows_per_field() {
+    let mut ppu = interlace_sprite_ppu(0x02);
+    
+    // Frame 0 is the even field: rows 0, 2, 4, 6 on four lines
+    render_frames(&mut ppu, 1);
+    assert_eq!(pixel(&ppu, 20, 16), RED);
+    assert_eq!(pixel(&ppu, 20, 17), RED);
+    assert_eq!(pixel(&ppu, 20, 18), BLUE);
+    assert_eq!(pixel(&ppu, 20, 20), BLACK);
+    
+    // Frame 1 is the odd field: rows 1, 3, 5, 7
+    render_frames(&mut ppu, 1);
+    assert_eq!(pixel(&ppu, 20, 16), GREEN);
+    assert_eq!(pixel(&ppu, 20, 18), [248, 248, 248]);
+    assert_eq!(pixel(&ppu, 20, 20), BLACK);
+}
+
+#[test]
+fn test_sprites_without_obj_interlace_use_every_row() {
+    let mut ppu = interlace_sprite_ppu(0x00);
+    for _ in 0..2 {
+        render_frames(&mut ppu, 1);
+        assert_eq!(pixel(&ppu, 20, 16), RED);
+        assert_eq!(pixel(&ppu, 20, 17), GREEN);
+        assert_eq!(pixel(&ppu, 20, 20), BLUE);
+        assert_eq!(pixel(&ppu, 20, 23), [248, 248, 248]);
+    }
+}
+
+#[test]
+fn test_field_alternates_and_reads_back_in_stat78() {
+    let mut ppu = Ppu::new();
+    assert!(!ppu.field());
+    render_frames(&mut ppu, 1);
+    assert!(ppu.field());
+    render_frames(&mut ppu, 1);
+    assert!(!ppu.field());
+    
+    let mut bus = Bus::new();
+    assert_eq!(bus.read8(0x213F) & 0x80, 0);
+    bus.set_field(true);
+    assert_eq!(bus.read8(0x213F) & 0x80, 0x80);
+    // Still the PPU2 version in the low bits
+    assert_eq!(bus.read8(0x213F) & 0x0F, 3);
+}
+
+#[test]
+fn test_interlaced_hires_backgrounds_use_field_lines() {
+    use ccsnes::ppu::backgrounds::BackgroundRenderer;
+    use ccsnes::ppu::registers::PpuRegisters;
+    
+    let mut registers = PpuRegisters::new();
+    registers.write(0x2105, 0x05);
+    assert_eq!(BackgroundRenderer::field_line(&registers, 10, true), 10);
+    registers.write(0x2133, 0x01);
+    assert_eq!(BackgroundRenderer::field_line(&registers, 10, false), 20);
+    assert_eq!(BackgroundRenderer::field_line(&registers, 10, true), 21);
+    
+    // Other modes ignore interlace
+    registers.write(0x2105, 0x01);
+    assert_eq!(BackgroundRenderer::field_line(&registers, 10, true), 10);
+}
+
+#[test]
+fn test_ppu_ports_decode_in_every_system_bank() {
+    use ccsnes::ppu::ports::{self, PortAccess};
+    
+    for bank in (0x00..=0x3F).chain(0x80..=0xBF) {
+        assert_eq!(ports::decode(bank << 16 | 0x2118), Some(0x2118));
+    }
+    assert_eq!(ports::decode(0x402118), None);
+    assert_eq!(ports::decode(0x7E2118), None);
+    assert_eq!(ports::decode(0x002140), None);
+    assert_eq!(ports::decode_b_bus(0x3F), Some(0x213F));
+    assert_eq!(ports::decode_b_bus(0x40), None);
+    assert_eq!(ports::port(0x2133).name, "SETINI");
+    assert_eq!(ports::port(0x2134).access, PortAccess::Read);
+    
+    let mut ppu = Ppu::new();
+    let mut bus = Bus::new();
+    unsafe { bus.connect_ppu(&mut ppu) };
+    // M7A low and high through different banks, M7B from a high mirror
+    bus.write8(0x00211B, 0x34);
+    bus.write8(0x3F211B, 0x12);
+    bus.write8(0xBF211C, 0x02);
+    assert_eq!(bus.read8(0x002134), 0x68);
+    assert_eq!(bus.read8(0x802135), 0x24);
+    assert_eq!(bus.read8(0xBF2136), 0x00);
+    // Zero is written through too
+    bus.write8(0x802102, 0x00);
+    bus.write8(0x802102, 0x01);
+    bus.write8(0x002102, 0x00);
+    bus.write8(0x002104, 0xAB);
+    assert_eq!(ppu.get_oam()[0], 0xAB);
+}
+
+#[test]
+fn test_ppu_multiply_is_signed() {
+    let mut ppu = Ppu::new();
+    // M7A = -2, M7B = 3
+    ppu.write_register(0x211B, 0xFE);
+    ppu.write_register(0x211B, 0xFF);
+    ppu.write_register(0x211C, 0x03);
+    let product = ppu.read_register(0x2134) as u32
+        | (ppu.read_register(0x2135) as u32) << 8
+        | (ppu.read_register(0x2136) as u32) << 16;
+    assert_eq!(product, 0xFF_FFFA);
+    
+    // M7B byte $80 is -128
+    ppu.write_register(0x211C, 0x80);
+    assert_eq!(ppu.read_register(0x2134), 0x00);
+    assert_eq!(ppu.read_register(0x2135), 0x01);
+    assert_eq!(ppu.read_register(0x2136), 0x00);
+}
+
+#[test]
+fn test_oam_address_is_a_word_address() {
+    let mut ppu = Ppu::new();
+    ppu.write_register(0x2102, 0x01);
+    ppu.write_register(0x2103, 0x00);
+    ppu.write_register(0x2104, 0x11);
+    assert_eq!(ppu.get_oam()[2], 0x11);
+    
+    // OAMADDH bit 0 selects the high table
+    ppu.write_register(0x2102, 0x00);
+    ppu.write_register(0x2103, 0x01);
+    ppu.write_register(0x2104, 0x22);
+    assert_eq!(ppu.get_oam()[0], 0);
+    ppu.write_register(0x2102, 0x00);
+    assert_eq!(ppu.read_register(0x2138), 0x22);
+    // $220 and up repeat the 32-byte high table
+    ppu.write_register(0x2102, 0x10);
+    assert_eq!(ppu.read_register(0x2138), 0x22);
+    
+    // Reads advance the same address
+    ppu.write_register(0x2102, 0x01);
+    ppu.write_register(0x2103, 0x00);
+    assert_eq!(ppu.read_register(0x2138), 0x11);
+    ppu.write_register(0x2104, 0x44);
+    assert_eq!(ppu.get_oam()[3], 0x44);
+    // Write-only ports read open bus
+    assert_eq!(ppu.read_register(0x2104), 0);
+}
+
+#[test]
+fn test_oam_address_reloads_at_vblank() {
+    let mut ppu = Ppu::new();
+    let mut bus = Bus::new();
+    ppu.write_register(0x2100, 0x0F);
+    ppu.write_register(0x2102, 0x04);
+    ppu.write_register(0x2104, 0x55);
+    for _ in 0..341 * 226 {
+        ppu.step(&mut bus);
+    }
+    ppu.write_register(0x2104, 0x66);
+    assert_eq!(&ppu.get_oam()[8..10], &[0x66, 0]);
+}
+
+#[test]
+fn test_setini_bits() {
+    use ccsnes::ppu::registers::PpuRegisters;
+    
+    let mut registers = PpuRegisters::new();
+    registers.write(0x2133, 0x06);
+    assert!(!registers.is_interlaced());
+    assert!(registers.is_obj_interlaced());
+    assert!(registers.is_overscan());
+    assert!(!registers.is_pseudo_hires());
+    registers.write(0x2133, 0xC8);
+    assert!(registers.is_pseudo_hires());
+    assert!(registers.is_extbg());
+    assert!(registers.is_external_sync());
+    assert!(!registers.is_overscan());
+}
+
+#[test]
+fn test_overscan_draws_239_lines() {
+    let mut ppu = solid_bg1_ppu();
+    let mut bus = Bus::new();
+    assert_eq!(ppu.frame_height(), 224);
+    assert_eq!(ppu.get_frame_buffer().len(), 256 * 224 * 4);
+    
+    ppu.write_register(0x2133, 0x04);
+    step_dots(&mut ppu, &mut bus, 341 * 225);
+    assert!(!ppu.is_in_vblank());
+    assert_eq!(ppu.vblank_start_scanline(), 240);
+    step_dots(&mut ppu, &mut bus, 341 * 15);
+    assert_eq!(ppu.get_current_scanline(), 240);
+    assert!(ppu.is_in_vblank());
+    assert_eq!(ppu.frame_height(), 239);
+    assert_eq!(ppu.get_frame_buffer().len(), 256 * 239 * 4);
+    assert!(pixel(&ppu, 10, 238)[0] > 0);
+    
+    // Switching back takes effect at the next frame's line 225
+    ppu.write_register(0x2133, 0x00);
+    render_frames(&mut ppu, 1);
+    assert_eq!(ppu.frame_height(), 239);
+    step_dots(&mut ppu, &mut bus, 341 * 225);
+    assert!(ppu.is_in_vblank());
+    assert_eq!(ppu.frame_height(), 224);
+    assert_eq!(ppu.get_frame_buffer().len(), 256 * 224 * 4);
+}
+
+fn set_vram_address(ppu: &mut Ppu, address: u16) {
+    ppu.write_register(0x2116, address as u8);
+    ppu.write_register(0x2117, (address >> 8) as u8);
+}
+
+#[test]
+fn test_vram_ports_are_word_addressed() {
+    let mut ppu = Ppu::new();
+    ppu.write_register(0x2115, 0x80);
+    set_vram_address(&mut ppu, 0x1234);
+    ppu.write_register(0x2118, 0xCD);
+    ppu.write_register(0x2119, 0xAB);
+    // Word $1234 is bytes $2468-$2469, low byte first
+    assert_eq!(&ppu.get_vram()[0x2468..0x246A], &[0xCD, 0xAB]);
+    assert_eq!(ppu.registers.get_vram_address(), VramAddress::new(0x1235));
+
+    // Either half can come first
+    set_vram_address(&mut ppu, 0x0010);
+    ppu.write_register(0x2119, 0x56);
+    set_vram_address(&mut ppu, 0x0010);
+    ppu.write_register(0x2118, 0x78);
+    assert_eq!(&ppu.get_vram()[0x20..0x22], &[0x78, 0x56]);
+
+    // VMADD bit 15 doesn't exist
+    set_vram_address(&mut ppu, 0xFFFF);
+    assert_eq!(ppu.registers.get_vram_address().word(), 0x7FFF);
+}
+
+#[test]
+fn test_vram_increment_modes() {
+    for (vmain, step) in [(0x80, 1), (0x81, 32), (0x82, 128), (0x83, 128)] {
+        let mut ppu = Ppu::new();
+        ppu.write_register(0x2115, vmain);
+        set_vram_address(&mut ppu, 0x0100);
+        for value in [0x1111u16, 0x2222] {
+            ppu.write_register(0x2118, value as u8);
+            ppu.write_register(0x2119, (value >> 8) as u8);
+        }
+        let second = (0x0100 + step) * 2;
+        assert_eq!(&ppu.get_vram()[0x200..0x202], &[0x11, 0x11], "VMAIN ${:02X}", vmain);
+        assert_eq!(&ppu.get_vram()[second..second + 2], &[0x22, 0x22], "VMAIN ${:02X}", vmain);
+        assert_eq!(ppu.registers.get_vram_address().word() as usize, 0x0100 + step * 2);
+    }
+
+    // The address wraps at the end of the 32K words
+    let mut ppu = Ppu::new();
+    ppu.write_register(0x2115, 0x80);
+    set_vram_address(&mut ppu, 0x7FFF);
+    for _ in 0..2 {
+        ppu.write_register(0x2118, 0xEE);
+        ppu.write_register(0x2119, 0xEE);
+    }
+    assert_eq!(&ppu.get_vram()[0xFFFE..], &[0xEE, 0xEE]);
+    assert_eq!(&ppu.get_vram()[..2], &[0xEE, 0xEE]);
+}
+
+#[test]
+fn test_vram_increment_on_low_byte() {
+    // VMAIN bit 7 clear: $2118 advances, so low bytes alone fill a run
+    // of words (as for Mode 7 tilemaps) and $2119 leaves the address
+    let mut ppu = Ppu::new();
+    ppu.write_register(0x2115, 0x00);
+    set_vram_address(&mut ppu, 0x0000);
+    for value in 1..=3 {
+        ppu.write_register(0x2118, value);
+    }
+    assert_eq!(&ppu.get_vram()[..6], &[1, 0, 2, 0, 3, 0]);
+    ppu.write_register(0x2119, 0x44);
+    ppu.write_register(0x2119, 0x55);
+    assert_eq!(&ppu.get_vram()[6..8], &[0, 0x55]);
+    assert_eq!(ppu.registers.get_vram_address().word(), 3);
+}
+
+#[test]
+fn test_vram_reads_follow_increment_mode() {
+    let mut ppu = Ppu::new();
+    ppu.write_register(0x2115, 0x80);
+    set_vram_address(&mut ppu, 0x0400);
+    for value in [0xA1B2u16, 0xC3D4] {
+        ppu.write_register(0x2118, value as u8);
+        ppu.write_register(0x2119, (value >> 8) as u8);
+    }
+
+    // Increment on high: low then high byte of each word in turn
+    set_vram_address(&mut ppu, 0x0400);
+    let bytes: Vec<u8> = [0x2139, 0x213A, 0x2139, 0x213A].iter().map(|&port| ppu.read_register(port)).collect();
+    assert_eq!(bytes, vec![0xB2, 0xA1, 0xD4, 0xC3]);
+
+    // Increment on low: the low bytes alone
+    ppu.write_register(0x2115, 0x00);
+    set_vram_address(&mut ppu, 0x0400);
+    assert_eq!(ppu.read_register(0x2139), 0xB2);
+    assert_eq!(ppu.read_register(0x2139), 0xD4);
+}
+
+// bg3_and_sprite_ppu's picture with the sprite in OBJ palette `palette`
+// (also green) and the color math registers set
+fn color_math_ppu(palette: u8, tm: u8, ts: u8, cgwsel: u8, cgadsub: u8) -> Ppu {
+    let mut ppu = bg3_and_sprite_ppu(0x01, false, 3);
+    let mut oam = ppu.get_oam().to_vec();
+    oam[3] = 0x30 | (palette << 1);
+    ppu.set_oam(&oam);
+    let mut cgram = ppu.get_cgram().to_vec();
+    let color = (128 + palette as usize * 16 + 15) * 2;
+    cgram[color..color + 2].copy_from_slice(&0x03E0u16.to_le_bytes());
+    ppu.set_cgram(&cgram);
+    
+    ppu.write_register(0x212C, tm);
+    ppu.write_register(0x212D, ts);
+    ppu.write_register(0x2130, cgwsel);
+    ppu.write_register(0x2131, cgadsub);
+    ppu.write_register(0x2132, 0x9F); // COLDATA - blue 31
+    ppu
+}
+
+#[test]
+fn test_color_math_only_applies_to_enabled_layers() {
+    // CGADSUB bit 2: BG3 gets the fixed color added, the sprite doesn't
+    let ppu = color_math_ppu(4, 0x14, 0x00, 0x00, 0x04);
+    assert_eq!(rendered_pixel(ppu, 40, 40), [248, 0, 248]);
+    let ppu = color_math_ppu(4, 0x14, 0x00, 0x00, 0x04);
+    assert_eq!(rendered_pixel(ppu, 20, 20), GREEN);
+    
+    // Bit 5 is the backdrop's: CGRAM color 0, black here, turns blue
+    let ppu = color_math_ppu(4, 0x10, 0x00, 0x00, 0x20);
+    assert_eq!(rendered_pixel(ppu, 40, 40), BLUE);
+    let ppu = color_math_ppu(4, 0x10, 0x00, 0x00, 0x04);
+    assert_eq!(rendered_pixel(ppu, 40, 40), BLACK);
+}
+
+#[test]
+fn test_sprites_in_palettes_0_to_3_skip_color_math() {
+    for palette in 0..8 {
+        let ppu = color_math_ppu(palette, 0x14, 0x00, 0x00, 0x10);
+        let expected = if palette < 4 { GREEN } else { [0, 248, 248] };
+        assert_eq!(rendered_pixel(ppu, 20, 20), expected, "palette {}", palette);
+    }
+}
+
+#[test]
+fn test_color_math_halves_with_the_sub_screen() {
+    // Sub screen BG3 (red) added to the sprite and the backdrop, halved
+    let ppu = color_math_ppu(4, 0x10, 0x04, 0x02, 0x70);
+    assert_eq!(rendered_pixel(ppu, 20, 20), [120, 120, 0]);
+    let ppu = color_math_ppu(4, 0x10, 0x04, 0x02, 0x70);
+    assert_eq!(rendered_pixel(ppu, 40, 40), [120, 0, 0]);
+    
+    // Where the sub screen is transparent the fixed color shows through,
+    // and isn't halved
+    let ppu = color_math_ppu(4, 0x10, 0x00, 0x02, 0x70);
+    assert_eq!(rendered_pixel(ppu, 40, 40), BLUE);
+    
+    // Subtracting: red minus red
+    let ppu = color_math_ppu(4, 0x04, 0x04, 0x02, 0x84);
+    assert_eq!(rendered_pixel(ppu, 40, 40), BLACK);
+}
+
+#[test]
+fn test_color_window_limits_color_math() {
+    // Window 1 over columns 0-31 is the color window; CGWSEL bits 4-5 = 1
+    // keep math off outside it
+    let window = |mut ppu: Ppu| {
+        ppu.write_register(0x2125, 0x20); // WOBJSEL - window 1 for color
+        ppu.write_register(0x2126, 0);
+        ppu.write_register(0x2127, 31);
+        ppu
+    };
+    let ppu = window(color_math_ppu(4, 0x04, 0x00, 0x10, 0x04));
+    assert_eq!(rendered_pixel(ppu, 20, 40), [248, 0, 248]);
+    let ppu = window(color_math_ppu(4, 0x04, 0x00, 0x10, 0x04));
+    assert_eq!(rendered_pixel(ppu, 40, 40), RED);
+    
+    // Bits 6-7 = 2 clip the main screen to black inside it, even where
+    // no layer takes part
+    let ppu = window(color_math_ppu(4, 0x04, 0x00, 0x80, 0x00));
+    assert_eq!(rendered_pixel(ppu, 20, 40), BLACK);
+    let ppu = window(color_math_ppu(4, 0x04, 0x00, 0x80, 0x00));
+    assert_eq!(rendered_pixel(ppu, 40, 40), RED);
+}
+
+#[test]
+fn test_widescreen_continues_the_tilemap_past_the_sides() {
+    let mut ppu = solid_bg1_ppu();
+    // Tilemap column 31 holds tile 1, every pixel color 1 (green)
+    ppu.write_register(0x2116, 0x1F);
+    ppu.write_register(0x2117, 0x00);
+    ppu.write_register(0x2118, 0x01);
+    ppu.write_register(0x2119, 0x00);
+    ppu.write_register(0x2116, 0x08);
+    ppu.write_register(0x2117, 0x10);
+    for _ in 0..8 {
+        ppu.write_register(0x2118, 0xFF);
+        ppu.write_register(0x2119, 0x00);
+    }
+    ppu.write_register(0x2121, 0x02); // color 1 (CGRAM byte address)
+    ppu.write_register(0x2122, 0xE0);
+    ppu.write_register(0x2122, 0x03);
+    
+    ppu.set_widescreen(8);
+    render_frames(&mut ppu, 1);
+    assert_eq!(ppu.widescreen_width(), 272);
+    let regular = ppu.get_frame_buffer().to_vec();
+    let wide = ppu.get_widescreen_frame_buffer().to_vec();
+    assert_eq!(wide.len(), 272 * 224 * 4);
+    
+    // On the first tile row, left of the screen is column 31 wrapped
+    // around and right of it column 0 again; the 256 pixels between are
+    // the regular picture
+    let wide_pixel = |x: usize, y: usize| {
+        let offset = (y * 272 + x) * 4;
+        [wide[offset], wide[offset + 1], wide[offset + 2]]
+    };
+    assert_eq!(wide_pixel(0, 4), GREEN);
+    assert_eq!(wide_pixel(7, 4), GREEN);
+    assert_eq!(wide_pixel(264, 4), RED);
+    assert_eq!(&wide[(4 * 272 + 8) * 4..(4 * 272 + 264) * 4], &regular[4 * 256 * 4..5 * 256 * 4]);
+    assert_eq!(pixel(&ppu, 255, 4), GREEN);
+    
+    ppu.set_widescreen(0);
+    assert_eq!(ppu.get_widescreen_frame_buffer().len(), 256 * 224 * 4);
+}
+
+#[test]
+fn test_widescreen_enhancement_from_profile() {
+    let profile: GameProfile = toml::from_str("[enhancements]\nwidescreen = 100\n").unwrap();
+    assert_eq!(profile.enhancements.widescreen_margin(), 64);
+    assert_eq!(profile.enhancements.enabled(), vec!["widescreen"]);
+    assert!(enhancements::widescreen_compatible("SUPER MARIOKART"));
+    assert!(!enhancements::widescreen_compatible("TEST ROM"));
+    
+    // Only the widescreen frame gets wider
+    let mut emulator = Emulator::new().unwrap();
+    emulator.set_enhancements(profile.enhancements);
+    assert_eq!(emulator.frame().width, 256);
+    assert_eq!(emulator.widescreen_frame().width, 384);
+    emulator.set_enhancements(Enhancements::default());
+    assert_eq!(emulator.widescreen_frame().width, 256);
+}
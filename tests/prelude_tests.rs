@@ -0,0 +1,161 @@
+use ccsnes::prelude::*;
+
+// LoROM spinning on BRA *
+fn idle_rom() -> Vec<u8> {
+    ccsnes_test_support::idle_rom("PRELUDE TEST")
+}
+
+fn running_emulator() -> Emulator {
+    let mut emulator = EmulatorConfig::new().render_mode(RenderMode::Dot).build().unwrap();
+    emulator.load_rom(&idle_rom()).unwrap();
+    emulator
+}
+
+#[test]
+fn test_config_builder_applies_options() {
+    let emulator = EmulatorConfig::new().render_mode(RenderMode::Dot).frame_timing(true).build().unwrap();
+    assert_eq!(emulator.render_mode(), RenderMode::Dot);
+    assert!(emulator.metrics().frame_times.is_some());
+
+    let emulator = EmulatorConfig::default().build().unwrap();
+    assert_eq!(emulator.render_mode(), RenderMode::Scanline);
+}
+
+#[test]
+fn test_frame_describes_its_format() {
+    let mut emulator = running_emulator();
+    emulator.step_frame().unwrap();
+
+    let frame = emulator.frame();
+    assert_eq!((frame.width, frame.height), (256, 224));
+    assert_eq!(frame.pitch(), 1024);
+    assert_eq!(frame.pixels.len(), frame.pitch() * frame.height);
+    assert_eq!(frame.number, 1);
+    assert!(frame.pixel(255, 223).is_some());
+    assert_eq!(frame.pixel(256, 0), None);
+}
+
+#[test]
+fn test_audio_chunks_drain_and_carry_epoch() {
+    let mut emulator = running_emulator();
+    emulator.step_frame().unwrap();
+
+    let chunk = emulator.take_audio();
+    assert_eq!(chunk.sample_rate, AUDIO_SAMPLE_RATE);
+    assert_eq!(chunk.channels, 1);
+    assert!(!chunk.samples.is_empty());
+    assert_eq!(chunk.frames(), chunk.samples.len());
+    assert!(emulator.take_audio().samples.is_empty());
+
+    let epoch = chunk.epoch;
+    emulator.reset().unwrap();
+    assert_ne!(emulator.take_audio().epoch, epoch);
+}
+
+#[test]
+fn test_buttons_map_to_pad_bits() {
+    assert_eq!(Button::pad_bits(&[]), 0);
+    assert_eq!(Button::pad_bits(&[Button::B, Button::A]), 0x8080);
+    assert_eq!(Button::pad_bits(&Button::ALL), 0xFFF0);
+
+    let mut emulator = running_emulator();
+    emulator.set_buttons(0, &[Button::Start]);
+    emulator.step_frame().unwrap();
+}
+
+#[test]
+fn test_save_state_round_trip() {
+    let mut emulator = running_emulator();
+    emulator.step_frame().unwrap();
+    let state: SaveState = emulator.save_state().unwrap();
+
+    emulator.step_frame().unwrap();
+    emulator.load_state(&state).unwrap();
+    assert_eq!(emulator.frame().number, 1);
+
+    let error: EmulatorError = emulator.load_state_from_file("/nonexistent/state.sav").unwrap_err();
+    assert!(!error.to_string().is_empty());
+}
+
+#[test]
+fn test_run_cycles_keeps_to_the_budget() {
+    let mut emulator = running_emulator();
+    let mut total = 0;
+    for _ in 0..100 {
+        let ran: RanCycles = emulator.run_cycles(1000).unwrap();
+        assert!(!ran.frame_completed);
+        assert!(ran.cycles <= 1000 + 100, "ran {} cycles", ran.cycles);
+        total += ran.cycles;
+    }
+    // Each overrun comes off the next budget, so only the last one remains
+    assert!((100 * 1000..100 * 1000 + 100).contains(&total), "ran {} cycles", total);
+    assert_eq!(emulator.metrics().master_cycles, total);
+}
+
+#[test]
+fn test_run_cycles_stops_at_frame_end() {
+    let mut emulator = running_emulator();
+    let mut ran = emulator.run_cycles(u64::MAX).unwrap();
+    assert!(ran.frame_completed);
+    assert_eq!(emulator.frame().number, 1);
+    assert!(ran.cycles < 1364 * 263, "ran {} cycles", ran.cycles);
+
+    let mut frames = 0;
+    for _ in 0..2000 {
+        ran = emulator.run_cycles(500).unwrap();
+        frames += ran.frame_completed as u64;
+    }
+    // 1,000,000 cycles is a little under 3 NTSC frames
+    assert_eq!(frames, 2);
+    assert_eq!(emulator.frame().number, 3);
+
+    emulator.pause();
+    assert_eq!(emulator.run_cycles(500).unwrap().cycles, 0);
+}
+
+// What a frontend's sinks were given
+#[derive(Default)]
+struct Recorder {
+    frames: Vec<(u64, PixelFormat, usize, f64)>,
+    samples: usize,
+    epochs: Vec<u64>,
+}
+
+impl VideoSink for Recorder {
+    fn present_frame(&mut self, frame: &Frame) -> ccsnes::Result<()> {
+        assert_eq!(frame.pixels.len(), frame.pitch() * frame.height);
+        self.frames.push((frame.number, frame.format, frame.height, frame.timestamp));
+        Ok(())
+    }
+}
+
+impl AudioSink for Recorder {
+    fn queue_audio(&mut self, chunk: &AudioChunk) -> ccsnes::Result<()> {
+        self.samples += chunk.samples.len();
+        self.epochs.push(chunk.epoch);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_run_frame_to_feeds_sinks() {
+    let mut emulator = running_emulator();
+    let mut video = Recorder::default();
+    let mut audio = Recorder::default();
+    for _ in 0..2 {
+        emulator.run_frame_to(&mut video, &mut audio).unwrap();
+    }
+
+    let numbers: Vec<u64> = video.frames.iter().map(|frame| frame.0).collect();
+    assert_eq!(numbers, vec![1, 2]);
+    assert!(video.frames.iter().all(|frame| frame.1 == PixelFormat::Rgba8888 && frame.2 == 224));
+    // A frame's worth of emulated time apart
+    let elapsed = video.frames[1].3 - video.frames[0].3;
+    assert!((elapsed - 1.0 / FRAME_RATE).abs() < 1e-4, "{}", elapsed);
+    assert!(audio.samples > 0);
+    assert_eq!(audio.epochs.len(), 2);
+
+    // The null sink takes anything
+    emulator.run_frame_to(&mut NullSink, &mut NullSink).unwrap();
+    assert_eq!(emulator.frame().number, 3);
+}
@@ -0,0 +1,239 @@
+use ccsnes::memory::Bus;
+use ccsnes::ppu::Ppu;
+use ccsnes::profile::GameProfile;
+use ccsnes::quirks::Quirks;
+use ccsnes::Emulator;
+use ccsnes_test_support::{fresh_dir, LoRom};
+
+// LoROM that enables the H timer IRQ at dot $20 and counts IRQs in $0010:
+//   LDA #$20; STA $4207; STZ $4208; LDA #$10; STA $4200; CLI; BRA *
+//   IRQ at $8020: INC $0010; RTI
+fn irq_rom() -> Vec<u8> {
+    LoRom::new("QUIRKS TEST", &[
+        0xA9, 0x20, 0x8D, 0x07, 0x42, 0x9C, 0x08, 0x42,
+        0xA9, 0x10, 0x8D, 0x00, 0x42, 0x58, 0x80, 0xFE, 0xEA,
+    ])
+    .at(0x8020, &[0xEE, 0x10, 0x00, 0x40])
+    .vector(0xFFFE, 0x8020)
+    .build()
+}
+
+fn run_cycles(emulator: &mut Emulator, cycles: u64) {
+    while emulator.get_cycle_count() < cycles {
+        emulator.step().unwrap();
+    }
+}
+
+#[test]
+fn test_timer_irq_fires_each_scanline() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&irq_rom()).unwrap();
+    run_cycles(&mut emulator, 8000);
+    
+    let count = emulator.read8(0x0010);
+    let lines = emulator.ppu().get_current_scanline() as u8;
+    assert!(count > 0, "no IRQ was serviced");
+    assert!(count <= lines + 1, "{} IRQs in {} scanlines", count, lines);
+}
+
+#[test]
+fn test_dram_refresh_stall_slows_cpu() {
+    let mut plain = Emulator::new().unwrap();
+    plain.load_rom(&irq_rom()).unwrap();
+    plain.set_quirks(Quirks { dram_refresh_stall: false, ..Quirks::default() });
+    let mut stalled = Emulator::new().unwrap();
+    stalled.load_rom(&irq_rom()).unwrap();
+    assert!(stalled.quirks().dram_refresh_stall);
+    
+    // The stall takes its cycles from the CPU: the same time runs fewer
+    // instructions
+    let mut steps = [0; 2];
+    for (emulator, steps) in [&mut plain, &mut stalled].into_iter().zip(&mut steps) {
+        while emulator.get_cycle_count() < 20000 {
+            emulator.step().unwrap();
+            *steps += 1;
+        }
+    }
+    assert!(steps[1] < steps[0], "{:?}", steps);
+}
+
+#[test]
+fn test_game_profile_round_trip() {
+    let dir = fresh_dir("ccsnes_profile");
+    let path = dir.join("profile.toml");
+    assert_eq!(GameProfile::load(&path).unwrap(), GameProfile::new());
+    
+    let mut profile = GameProfile::new();
+    profile.quirks.nmi_vblank_race = true;
+    profile.quirks.irq_late_dot = true;
+    profile.save(&path).unwrap();
+    
+    let loaded = GameProfile::load(&path).unwrap();
+    assert_eq!(loaded, profile);
+    assert_eq!(loaded.quirks.enabled(), vec!["nmi_vblank_race", "irq_late_dot", "dram_refresh_stall"]);
+    
+    // Unlisted toggles stay off
+    let partial: GameProfile = toml::from_str("[quirks]\nirq_late_dot = true\n").unwrap();
+    assert!(partial.quirks.irq_late_dot && !partial.quirks.nmi_vblank_race);
+}
+
+#[test]
+fn test_toggle_quirk_by_name() {
+    let mut quirks = Quirks::default();
+    assert!(quirks.toggle("irq_late_dot"));
+    assert!(quirks.toggle("dram_refresh_stall"));
+    assert_eq!(quirks.enabled(), vec!["irq_late_dot"]);
+    
+    assert!(!quirks.toggle("no_such_quirk"));
+    assert!(quirks.toggle("irq_late_dot"));
+    assert!(quirks.enabled().is_empty());
+}
+
+fn step_dots(ppu: &mut Ppu, dots: u32) {
+    let mut bus = Bus::new();
+    for _ in 0..dots {
+        ppu.step(&mut bus);
+    }
+}
+
+fn write_vram_word(ppu: &mut Ppu, address: u16, value: u16) {
+    ppu.write_register(0x2115, 0x80);
+    ppu.write_register(0x2116, address as u8);
+    ppu.write_register(0x2117, (address >> 8) as u8);
+    ppu.write_register(0x2118, value as u8);
+    ppu.write_register(0x2119, (value >> 8) as u8);
+}
+
+#[test]
+fn test_vram_oam_writes_dropped_during_display() {
+    let mut ppu = Ppu::new();
+    ppu.set_access_restrictions(true);
+    ppu.write_register(0x2100, 0x0F); // display on
+    step_dots(&mut ppu, 341 * 10);
+    
+    write_vram_word(&mut ppu, 0x0100, 0xBEEF); // word $0100 is byte $0200
+    assert_eq!(&ppu.get_vram()[0x200..0x202], &[0, 0]);
+    
+    ppu.write_register(0x2102, 0x08); // word $08 is byte $10
+    ppu.write_register(0x2103, 0x00);
+    ppu.write_register(0x2104, 0x55);
+    ppu.write_register(0x2104, 0x66);
+    assert_eq!(&ppu.get_oam()[0x10..0x12], &[0, 0]);
+    
+    // Forced blank opens both again
+    ppu.write_register(0x2100, 0x8F);
+    write_vram_word(&mut ppu, 0x0100, 0xBEEF);
+    assert_ne!(&ppu.get_vram()[0x200..0x202], &[0, 0]);
+    // The dropped writes still advanced the OAM address
+    ppu.write_register(0x2104, 0x77);
+    assert_eq!(ppu.get_oam()[0x12], 0x77);
+}
+
+#[test]
+fn test_vram_writes_allowed_in_vblank() {
+    let mut ppu = Ppu::new();
+    ppu.set_access_restrictions(true);
+    ppu.write_register(0x2100, 0x0F);
+    step_dots(&mut ppu, 341 * 230);
+    assert!(ppu.is_in_vblank());
+    
+    write_vram_word(&mut ppu, 0x0200, 0x1234);
+    assert_eq!(&ppu.get_vram()[0x400..0x402], &[0x34, 0x12]);
+}
+
+#[test]
+fn test_cgram_writes_allowed_in_hblank() {
+    let mut ppu = Ppu::new();
+    ppu.set_access_restrictions(true);
+    ppu.write_register(0x2100, 0x0F);
+    
+    // Mid-line: dropped, but the address still advances
+    step_dots(&mut ppu, 341 * 10 + 100);
+    ppu.write_register(0x2121, 0x00);
+    ppu.write_register(0x2122, 0x1F);
+    assert_eq!(ppu.get_cgram()[0], 0);
+    
+    // H-Blank: lands at the advanced address
+    step_dots(&mut ppu, 200);
+    ppu.write_register(0x2122, 0x7C);
+    assert_eq!(ppu.get_cgram()[1], 0x7C);
+}
+
+#[test]
+fn test_restricted_reads_return_stale_value() {
+    let mut ppu = Ppu::new();
+    ppu.set_access_restrictions(true);
+    ppu.write_register(0x2100, 0x8F);
+    write_vram_word(&mut ppu, 0x0000, 0xAA11);
+    write_vram_word(&mut ppu, 0x0010, 0xBB22);
+    
+    // Read VRAM $0000 in forced blank to fill the read buffer
+    ppu.write_register(0x2115, 0x00);
+    ppu.write_register(0x2116, 0x00);
+    ppu.write_register(0x2117, 0x00);
+    let buffered = ppu.read_register(0x2139);
+    assert_ne!(buffered, 0);
+    
+    // During display the buffered value comes back instead of $0010
+    ppu.write_register(0x2100, 0x0F);
+    step_dots(&mut ppu, 341 * 10);
+    ppu.write_register(0x2116, 0x10);
+    ppu.write_register(0x2117, 0x00);
+    assert_eq!(ppu.read_register(0x2139), buffered);
+}
+
+#[test]
+fn test_access_restrictions_off_by_default() {
+    let mut emulator = Emulator::new().unwrap();
+    assert!(!emulator.ppu().access_restrictions());
+    
+    emulator.set_quirks(Quirks { ppu_access_restrictions: true, ..Quirks::default() });
+    assert!(emulator.ppu().access_restrictions());
+    assert_eq!(emulator.quirks().enabled(), vec!["dram_refresh_stall", "ppu_access_restrictions"]);
+    
+    // Without the quirk, writes during display land as before
+    let mut ppu = Ppu::new();
+    ppu.write_register(0x2100, 0x0F);
+    step_dots(&mut ppu, 341 * 10);
+    write_vram_word(&mut ppu, 0x0100, 0xBEEF);
+    assert_ne!(&ppu.get_vram()[0x200..0x202], &[0, 0]);
+}
+
+// LoROM waiting for the APU: LDA #$CC; STA $2141; loop: LDA $2140;
+// CMP #$AA; BNE loop; LDA $2141; CMP #$CC; BNE loop; INC $0010; BRA *
+fn apu_wait_rom() -> Vec<u8> {
+    LoRom::new("APU BOOT TEST", &[
+        0xA9, 0xCC, 0x8D, 0x41, 0x21,
+        0xAD, 0x40, 0x21, 0xC9, 0xAA, 0xD0, 0xF9,
+        0xAD, 0x41, 0x21, 0xC9, 0xCC, 0xD0, 0xF2,
+        0xEE, 0x10, 0x00,
+        0x80, 0xFE,
+    ])
+    .build()
+}
+
+#[test]
+fn test_apu_fast_boot_and_fake_ack() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&apu_wait_rom()).unwrap();
+    emulator.set_quirks(Quirks { apu_fast_boot: true, apu_fake_ack: true, ..Quirks::default() });
+    // The IPL's announcement is visible before the APU runs at all
+    assert_eq!(emulator.apu().spc700().pc(), 0xFFCF);
+    assert_eq!(emulator.apu().spc700().read_port(0), 0xAA);
+    run_cycles(&mut emulator, 2000);
+    assert_eq!(emulator.read8(0x0010), 1);
+
+    // Fast boot survives resets; the ack echoes CPU writes
+    emulator.reset().unwrap();
+    assert_eq!(emulator.apu().spc700().pc(), 0xFFCF);
+    assert_eq!(emulator.apu_mut().read_port(1), 0xBB);
+    emulator.apu_mut().write_port(1, 0x42);
+    assert_eq!(emulator.apu_mut().read_port(1), 0x42);
+
+    // Without the ack, the SPC700 never writes $CC to port 1
+    let mut plain = Emulator::new().unwrap();
+    plain.load_rom(&apu_wait_rom()).unwrap();
+    plain.set_quirks(Quirks { apu_fast_boot: true, ..Quirks::default() });
+    run_cycles(&mut plain, 2000);
+    assert_eq!(plain.read8(0x0010), 0);
+}
@@ -0,0 +1,75 @@
+use ccsnes::input::controller::{BUTTON_A, BUTTON_B, BUTTON_RIGHT, BUTTON_START};
+use ccsnes::input::remote::{parse_buttons, RemoteEvent, RemoteInput};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+// Poll until `done` holds, collecting events; the socket is nonblocking
+fn poll_until(remote: &mut RemoteInput, mut done: impl FnMut(&RemoteInput) -> bool) -> Vec<RemoteEvent> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut events = Vec::new();
+    while !done(remote) {
+        assert!(Instant::now() < deadline, "timed out waiting for remote input");
+        events.extend(remote.poll());
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    events
+}
+
+#[test]
+fn test_remote_buttons_parse() {
+    assert_eq!(parse_buttons("a+b+right"), Ok(BUTTON_A | BUTTON_B | BUTTON_RIGHT));
+    assert_eq!(parse_buttons("Start A"), Ok(BUTTON_START | BUTTON_A));
+    assert_eq!(parse_buttons("none"), Ok(0));
+    assert_eq!(parse_buttons(""), Ok(0));
+    assert_eq!(parse_buttons("0x8080"), Ok(0x8080));
+    assert!(parse_buttons("a+turbo").is_err());
+    assert!(parse_buttons("0xZZ").is_err());
+}
+
+#[test]
+fn test_remote_client_drives_buttons() {
+    let mut remote = RemoteInput::bind("127.0.0.1:0", 1).unwrap();
+    let address = remote.local_addr().unwrap();
+    assert_eq!(remote.player(), 1);
+    assert!(remote.poll().is_empty());
+
+    let mut client = TcpStream::connect(address).unwrap();
+    let events = poll_until(&mut remote, |remote| remote.peer().is_some());
+    assert!(matches!(events[..], [RemoteEvent::Connected(_)]));
+
+    client.write_all(b"a+start\n").unwrap();
+    poll_until(&mut remote, |remote| remote.buttons() == BUTTON_A | BUTTON_START);
+
+    // A bad line is answered and changes nothing
+    client.write_all(b"jump\n0x").unwrap();
+    let mut reply = String::new();
+    let mut reader = BufReader::new(client.try_clone().unwrap());
+    while reply.is_empty() {
+        remote.poll();
+        client.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        let _ = reader.read_line(&mut reply);
+    }
+    assert!(reply.starts_with("error: unknown button 'jump'"));
+    assert_eq!(remote.buttons(), BUTTON_A | BUTTON_START);
+    // A line split across writes applies once complete
+    client.write_all(b"8000\n").unwrap();
+    poll_until(&mut remote, |remote| remote.buttons() == BUTTON_B);
+
+    // A second client is turned away
+    let second = TcpStream::connect(address).unwrap();
+    let mut busy = String::new();
+    let events = poll_until(&mut remote, |_| {
+        second.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        let _ = BufReader::new(&second).read_line(&mut busy);
+        busy == "busy\n"
+    });
+    assert!(events.iter().any(|event| matches!(event, RemoteEvent::Refused(_))));
+
+    // Disconnecting releases the buttons
+    drop(client);
+    drop(reader);
+    let events = poll_until(&mut remote, |remote| remote.peer().is_none());
+    assert!(events.iter().any(|event| matches!(event, RemoteEvent::Disconnected(_))));
+    assert_eq!(remote.buttons(), 0);
+}
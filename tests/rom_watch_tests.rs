@@ -0,0 +1,36 @@
+use ccsnes::frontend::native::rom_watch::{RomWatcher, POLL_INTERVAL};
+use ccsnes_test_support::fresh_dir;
+use std::time::Instant;
+
+#[test]
+fn test_rom_watcher_waits_for_the_file_to_settle() {
+    let dir = fresh_dir("ccsnes_rom_watch");
+    let path = dir.join("game.sfc");
+    std::fs::write(&path, [0; 16]).unwrap();
+
+    let start = Instant::now();
+    let mut watcher = RomWatcher::new(&path);
+    assert!(!watcher.poll(start));
+
+    // A rebuild is seen on one poll and reported on the next if unchanged
+    std::fs::write(&path, [1; 32]).unwrap();
+    assert!(!watcher.poll(start + POLL_INTERVAL / 2), "polled before the interval");
+    assert!(!watcher.poll(start + POLL_INTERVAL));
+    assert!(watcher.poll(start + POLL_INTERVAL * 2));
+    assert!(!watcher.poll(start + POLL_INTERVAL * 3));
+
+    // Still being written: each poll sees another size, then it settles
+    std::fs::write(&path, [2; 48]).unwrap();
+    assert!(!watcher.poll(start + POLL_INTERVAL * 4));
+    std::fs::write(&path, [2; 64]).unwrap();
+    assert!(!watcher.poll(start + POLL_INTERVAL * 5));
+    assert!(watcher.poll(start + POLL_INTERVAL * 6));
+
+    // Deleted mid-build: nothing until it is back
+    std::fs::remove_file(&path).unwrap();
+    assert!(!watcher.poll(start + POLL_INTERVAL * 7));
+    assert!(!watcher.poll(start + POLL_INTERVAL * 8));
+    std::fs::write(&path, [3; 80]).unwrap();
+    assert!(!watcher.poll(start + POLL_INTERVAL * 9));
+    assert!(watcher.poll(start + POLL_INTERVAL * 10));
+}
@@ -1,5 +1,6 @@
-use ccsnes::savestate::SaveState;
+use ccsnes::savestate::{CounterLatchState, SaveState, StateEncoding, StateSection};
 use ccsnes::emulator::Emulator;
+use ccsnes_test_support::idle_rom;
 use std::fs;
 
 #[test]
@@ -136,4 +137,251 @@ fn test_compressed_save_state() {
     
     // Clean up
     let _ = fs::remove_file(test_path);
-}
\ No newline at end of file
+}
+#[test]
+fn test_save_state_json_round_trip() {
+    let mut state = SaveState::new();
+    state.cpu.a = 0x1234;
+    state.memory.wram[0x100] = 0xAB;
+    state.memory.sram = Some(vec![1, 2, 3]);
+    state.ppu.vram[0xFFFF] = 0x5A;
+    
+    let json = state.to_json().expect("Failed to export JSON");
+    // Byte buffers are written as hex rows rather than one number per line
+    assert!(json.contains("\"ab"));
+    assert!(json.contains("\"010203\""));
+    
+    let loaded = SaveState::from_json(&json).expect("Failed to import JSON");
+    assert!(loaded == state);
+    
+    // Bincode stays compact and decodes to the same state
+    let bytes = state.encode(StateEncoding::Bincode).unwrap();
+    assert!(bytes.len() < json.len() / 2);
+    assert!(SaveState::decode(&bytes, StateEncoding::Bincode).unwrap() == state);
+    assert!(SaveState::decode(json.as_bytes(), StateEncoding::Json).unwrap() == state);
+}
+
+#[test]
+fn test_save_state_json_rejects_bad_hex() {
+    let json = SaveState::new().to_json().unwrap().replacen("\"00", "\"zz", 1);
+    assert!(SaveState::from_json(&json).is_err());
+}
+
+#[test]
+fn test_save_state_keeps_ppu_latches() {
+    use ccsnes::ppu::Ppu;
+
+    // Save between the two halves of VRAM, BG scroll and Mode 7 writes;
+    // VRAM takes each half as it comes, the others latch the first byte
+    let mut original = Ppu::new();
+    original.write_register(0x2118, 0x34);
+    original.write_register(0x210D, 0x78);
+    original.write_register(0x211B, 0xCD);
+    let state = original.save_state();
+    assert_eq!(state.vram[0], 0x34);
+    assert_eq!(state.latches.scroll_prev_write, 0x78);
+
+    let mut restored = Ppu::new();
+    restored.load_state(&state);
+    for ppu in [&mut original, &mut restored] {
+        ppu.write_register(0x2119, 0x12);
+        ppu.write_register(0x210D, 0x01);
+        ppu.write_register(0x211B, 0xAB);
+    }
+    assert_eq!(&restored.get_vram()[..2], &original.get_vram()[..2]);
+    assert_eq!(restored.registers.bg1hofs, original.registers.bg1hofs);
+    assert_eq!(restored.registers.m7a, original.registers.m7a);
+    assert_eq!(restored.save_state().latches, original.save_state().latches);
+
+    // The H/V counter flip-flop survives too: the next OPHCT read is the high byte
+    let mut emulator = Emulator::new().unwrap();
+    let mut state = emulator.save_state().unwrap();
+    state.ppu.latches.counters = CounterLatchState { h: 0x1A5, v: 0x020, latched: true, h_high: false, v_high: false };
+    emulator.load_state(&state).unwrap();
+    assert_eq!(emulator.read8(0x213C), 0xA5);
+    let bytes = emulator.save_state().unwrap().to_bytes().unwrap();
+
+    let mut other = Emulator::new().unwrap();
+    other.load_state(&SaveState::from_bytes(&bytes).unwrap()).unwrap();
+    assert_eq!(other.read8(0x213C), 0x01);
+    assert_eq!(other.bus().counter_latch().position(), (0x1A5, 0x020));
+}
+
+#[test]
+fn test_save_state_keeps_nmi_flag_and_line() {
+    // Saved after vblank began but before the game read $4210, and after
+    // the CPU saw the NMI line rise
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&idle_rom("NMI STATE TEST")).unwrap();
+    while !emulator.ppu().is_in_vblank() {
+        emulator.step().unwrap();
+    }
+    let mut state = emulator.save_state().unwrap();
+    assert!(state.ppu.nmi_flag);
+    state.cpu.nmi_line = true;
+    let bytes = state.to_bytes().unwrap();
+
+    // The flag is still there for the game to read, and the line stays
+    // high so the same NMI isn't taken twice
+    let mut other = Emulator::new().unwrap();
+    other.load_state(&SaveState::from_bytes(&bytes).unwrap()).unwrap();
+    assert!(other.save_state().unwrap().cpu.nmi_line);
+    assert_eq!(other.read8(0x4210) & 0x80, 0x80);
+    assert!(!other.bus().nmi_flag());
+}
+
+// A state with distinctive values in fields of each integer width
+fn sample_state() -> SaveState {
+    let mut state = SaveState::new();
+    state.cpu.a = 0x1234;
+    state.cpu.pc = 0x8000;
+    state.ppu.frame_count = 0x0102_0304_0506_0708;
+    state.apu.audio_buffer = vec![0.5, -0.25];
+    state.memory.wram[0x1FFFF] = 0xAB;
+    state.cycles = 0x1122_3344_5566_7788;
+    state
+}
+
+#[test]
+fn test_state_bytes_are_little_endian_and_fixed_width() {
+    let bytes = sample_state().to_bytes().unwrap();
+    // The layout earlier releases wrote with bincode's defaults
+    assert_eq!(bytes, bincode::serialize(&sample_state()).unwrap());
+    
+    // Version, then the CPU registers in declaration order
+    assert_eq!(bytes[0..4], 8u32.to_le_bytes());
+    assert_eq!(bytes[4..6], [0x34, 0x12]);
+    // a, x, y, s, d, db, pb come before pc
+    assert_eq!(bytes[16..18], [0x00, 0x80]);
+    // The CPU state is 21 bytes; the PPU register file's length follows
+    // as a u64 on every target, 32-bit ones included
+    let registers = SaveState::new().ppu.registers.len() as u64;
+    assert_eq!(bytes[25..33], registers.to_le_bytes());
+    // cycles, then memory_init: the pattern as a u32 variant index and the
+    // seed, then the number of coprocessor chunks
+    let end = bytes.len();
+    assert_eq!(bytes[end - 28..end - 20], 0x1122_3344_5566_7788u64.to_le_bytes());
+    assert_eq!(bytes[end - 20..end - 16], 0u32.to_le_bytes());
+    assert_eq!(bytes[end - 8..end], 0u64.to_le_bytes());
+}
+
+#[test]
+fn test_state_bytes_match_across_targets() {
+    // Checked on native and wasm32 alike: any change to this digest means
+    // states from one build stop loading in the other (or in older
+    // builds), so bump SAVE_STATE_VERSION along with it
+    let bytes = sample_state().to_bytes().unwrap();
+    let mut crc = flate2::Crc::new();
+    crc.update(&bytes);
+    assert_eq!((bytes.len(), crc.sum()), (263772, 4190637348));
+}
+
+#[test]
+fn test_browser_and_desktop_states_load_on_either() {
+    let state = sample_state();
+    
+    // A download from the web build is the raw bytes
+    let browser_path = "/tmp/test_savestate_browser.state";
+    fs::write(browser_path, state.to_bytes().unwrap()).unwrap();
+    assert_eq!(SaveState::load_from_file(browser_path).unwrap(), state);
+    
+    // Desktop save files are gzipped; the web build's from_bytes takes them
+    let desktop_path = "/tmp/test_savestate_desktop.state";
+    state.save_to_file(desktop_path).unwrap();
+    assert_eq!(SaveState::from_bytes(&fs::read(desktop_path).unwrap()).unwrap(), state);
+    
+    let _ = fs::remove_file(browser_path);
+    let _ = fs::remove_file(desktop_path);
+}
+
+#[test]
+fn test_recover_whole_state() {
+    let state = sample_state();
+    let partial = SaveState::recover(&state.to_bytes().unwrap(), StateEncoding::Bincode);
+    assert!(partial.failures.is_empty());
+    assert!(partial.recovery().is_complete());
+    assert_eq!(partial.recovered(), StateSection::ALL.to_vec());
+    assert_eq!(partial.into_state(), Some(state));
+}
+
+#[test]
+fn test_recover_skips_sections_with_wrong_sizes() {
+    let mut state = sample_state();
+    state.memory.wram = vec![0; 4];
+    let partial = SaveState::recover(&state.to_bytes().unwrap(), StateEncoding::Bincode);
+    // The section parsed, so the ones after it are still found
+    assert!(partial.memory.is_none());
+    assert_eq!(partial.failures, vec![(StateSection::Memory, "WRAM is 4 bytes, expected 131072".to_string())]);
+    assert_eq!(partial.cpu.as_ref().unwrap().a, 0x1234);
+    assert_eq!(partial.timing.unwrap().0, 0x1122_3344_5566_7788);
+    assert!(partial.dma.is_some());
+    assert!(partial.into_state().is_none());
+}
+
+#[test]
+fn test_recover_truncated_state() {
+    let state = sample_state();
+    let bytes = state.to_bytes().unwrap();
+    let before_memory = 4
+        + bincode::serialize(&state.cpu).unwrap().len()
+        + bincode::serialize(&state.ppu).unwrap().len()
+        + bincode::serialize(&state.apu).unwrap().len();
+    
+    let partial = SaveState::recover(&bytes[..before_memory + 100], StateEncoding::Bincode);
+    assert_eq!(partial.recovered(), vec![StateSection::Cpu, StateSection::Ppu, StateSection::Apu]);
+    let reasons: Vec<_> = partial.failures.iter().map(|(section, reason)| (*section, reason.as_str())).collect();
+    assert_eq!(reasons[0].0, StateSection::Memory);
+    assert!(reasons[0].1.starts_with("unreadable"), "{}", reasons[0].1);
+    assert_eq!(reasons[1], (StateSection::Dma, "comes after the damaged memory section"));
+    assert_eq!(reasons[2], (StateSection::Timing, "comes after the damaged memory section"));
+    
+    // Cut-off gzip files give the same, and too little gives nothing
+    let path = "/tmp/test_savestate_truncated.state";
+    state.save_to_file(path).unwrap();
+    let compressed = fs::read(path).unwrap();
+    let _ = fs::remove_file(path);
+    let partial = SaveState::recover(&compressed[..compressed.len() - 8], StateEncoding::Bincode);
+    assert!(partial.cpu.is_some());
+    assert!(SaveState::recover(&bytes[..2], StateEncoding::Bincode).recovered().is_empty());
+}
+
+#[test]
+fn test_recover_json_sections_independently() {
+    let mut json: serde_json::Value = serde_json::from_str(&sample_state().to_json().unwrap()).unwrap();
+    json["apu"]["spc700"] = serde_json::Value::String("broken".into());
+    json["version"] = 4.into();
+    let partial = SaveState::recover(json.to_string().as_bytes(), StateEncoding::Json);
+    assert_eq!(partial.version, Some(4));
+    assert_eq!(partial.failures.len(), 1);
+    assert_eq!(partial.failures[0].0, StateSection::Apu);
+    assert!(partial.memory.is_some() && partial.dma.is_some() && partial.timing.is_some());
+    
+    let report = partial.recovery().to_string();
+    assert!(report.starts_with("version 4 state (this build writes"), "{}", report);
+    assert!(report.contains("recovered CPU, PPU, memory, DMA, timing, coprocessors; reset APU (unreadable"), "{}", report);
+}
+
+#[test]
+fn test_emulator_loads_partial_state() {
+    let emulator = Emulator::new().unwrap();
+    let mut state = emulator.save_state().unwrap();
+    state.cpu.a = 0x4321;
+    state.memory.wram[0x10] = 0x77;
+    state.apu.spc700.ram.truncate(16);
+    state.apu.spc700.pc = 0x1234;
+    
+    let mut other = Emulator::new().unwrap();
+    let partial = SaveState::recover(&state.to_bytes().unwrap(), StateEncoding::Bincode);
+    let recovery = other.load_partial_state(&partial).unwrap();
+    assert!(!recovery.is_complete());
+    assert_eq!(recovery.reset[0].0, StateSection::Apu);
+    assert_eq!(other.save_state().unwrap().cpu.a, 0x4321);
+    assert_eq!(other.read8(0x7E0010), 0x77);
+    // The APU was reset rather than loaded
+    assert_eq!(other.save_state().unwrap().apu.spc700.pc, 0xFFC0);
+    
+    // Garbage can't be loaded at all
+    let garbage = SaveState::recover(&[0xFF; 3], StateEncoding::Bincode);
+    let error = other.load_partial_state(&garbage).unwrap_err().to_string();
+    assert!(error.contains("Nothing in the save state could be read"), "{}", error);
+}
@@ -0,0 +1,93 @@
+use ccsnes::paths::{GameId, GamePaths};
+use ccsnes::session::{BundleEntry, SessionBundle};
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_root(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("ccsnes_session_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    root
+}
+
+#[test]
+fn test_session_bundle_round_trip() {
+    let game = GameId::new("Session Game", b"rom contents");
+    let from = GamePaths::per_game(&temp_root("from"), &game);
+    from.create_directories().unwrap();
+    fs::write(from.sram_path(), [1, 2, 3]).unwrap();
+    fs::write(from.state_path(0), b"state zero").unwrap();
+    fs::write(from.state_path(9), b"state nine").unwrap();
+    fs::write(from.cheats_path(), "cheats").unwrap();
+    fs::write(from.profile_path(), "profile").unwrap();
+    
+    let bundle = SessionBundle::collect(&game, &from).unwrap();
+    let entries: Vec<BundleEntry> = bundle.files.iter().map(|(entry, _)| *entry).collect();
+    assert_eq!(entries, [
+        BundleEntry::Sram,
+        BundleEntry::State(0),
+        BundleEntry::State(9),
+        BundleEntry::Cheats,
+        BundleEntry::Profile,
+    ]);
+    assert!(bundle.matches(&game));
+    assert!(!bundle.matches(&GameId::new("Session Game", b"patched rom")));
+    
+    let file = temp_root("bundle");
+    bundle.save(&file).unwrap();
+    let loaded = SessionBundle::load(&file).unwrap();
+    assert_eq!(loaded, bundle);
+    assert_eq!(loaded.get(BundleEntry::State(9)), Some(&b"state nine"[..]));
+    let _ = fs::remove_file(&file);
+    
+    // Restoring into a flat layout puts each file where that layout keeps it
+    let root = temp_root("to");
+    let config = ccsnes::config::PathConfig {
+        sram_dir: root.join("saves"),
+        save_state_dir: root.join("states"),
+        screenshot_dir: root.join("shots"),
+        ..Default::default()
+    };
+    let to = GamePaths::flat(&config, &game.name);
+    let report = loaded.restore(&to, false).unwrap();
+    assert_eq!(report.written.len(), 5);
+    assert!(report.kept.is_empty());
+    assert_eq!(fs::read(to.sram_path()).unwrap(), [1, 2, 3]);
+    assert_eq!(fs::read(to.state_path(0)).unwrap(), b"state zero");
+    assert_eq!(fs::read_to_string(to.profile_path()).unwrap(), "profile");
+    
+    let _ = fs::remove_dir_all(temp_root("from"));
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_session_restore_keeps_existing_files() {
+    let game = GameId::new("Keep", b"rom");
+    let root = temp_root("keep");
+    let paths = GamePaths::per_game(&root, &game);
+    paths.create_directories().unwrap();
+    fs::write(paths.sram_path(), [9]).unwrap();
+    
+    let bundle = SessionBundle {
+        files: vec![(BundleEntry::Sram, vec![1]), (BundleEntry::Movie, vec![2])],
+        ..SessionBundle::collect(&game, &paths).unwrap()
+    };
+    let report = bundle.restore(&paths, false).unwrap();
+    assert_eq!(report.kept, vec![(BundleEntry::Sram, paths.sram_path().to_path_buf())]);
+    assert_eq!(report.written, vec![(BundleEntry::Movie, paths.movie_path())]);
+    assert_eq!(fs::read(paths.sram_path()).unwrap(), [9]);
+    
+    bundle.restore(&paths, true).unwrap();
+    assert_eq!(fs::read(paths.sram_path()).unwrap(), [1]);
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_session_bundle_rejects_other_files() {
+    let error = SessionBundle::from_bytes(b"\x1F\x8Bnot a bundle").unwrap_err().to_string();
+    assert!(error.contains("Not a session bundle"), "{}", error);
+    
+    let bundle = SessionBundle { version: 1, game: "x".into(), crc32: 0, files: Vec::new() };
+    let bytes = bundle.to_bytes().unwrap();
+    let error = SessionBundle::from_bytes(&bytes[..bytes.len() / 2]).unwrap_err().to_string();
+    assert!(error.contains("session bundle"), "{}", error);
+}
@@ -0,0 +1,61 @@
+use ccsnes::debug::spans::{self, SpanEvent, Trace};
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM that forces blank off and spins: LDA #$0F; STA $2100; BRA *
+fn spin_rom() -> Vec<u8> {
+    LoRom::new("SPANS TEST", &[0xA9, 0x0F, 0x8D, 0x00, 0x21, 0x80, 0xFE]).build()
+}
+
+#[test]
+fn test_chrome_trace_json() {
+    let trace = Trace {
+        events: vec![
+            SpanEvent { name: "frame", category: "emulator", ts: 0.0, dur: 16.5 },
+            SpanEvent { name: "dma", category: "dma", ts: 2.0, dur: 1.25 },
+        ],
+        dropped: 0,
+    };
+    let json: serde_json::Value = serde_json::from_str(&trace.to_chrome_json().unwrap()).unwrap();
+    let events = json["traceEvents"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1]["name"], "dma");
+    assert_eq!(events[1]["cat"], "dma");
+    assert_eq!(events[1]["ph"], "X");
+    assert_eq!(events[1]["ts"], 2.0);
+    assert_eq!(events[1]["dur"], 1.25);
+    assert!(events[1]["pid"].is_number() && events[1]["tid"].is_number());
+}
+
+#[test]
+fn test_stop_without_recording() {
+    assert!(spans::stop_recording().is_none());
+    drop(spans::span("unrecorded", "test"));
+    assert!(spans::stop_recording().is_none());
+}
+
+#[test]
+fn test_frame_spans() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&spin_rom()).unwrap();
+
+    spans::start_recording();
+    emulator.step_frame().unwrap();
+    let trace = spans::stop_recording();
+
+    if !spans::available() {
+        assert!(trace.is_none());
+        return;
+    }
+    let trace = trace.unwrap();
+    let count = |name: &str| trace.events.iter().filter(|event| event.name == name).count();
+    assert_eq!(count("frame"), 1);
+    assert!(count("cpu") >= 200, "expected a CPU batch per scanline");
+    assert!(count("render_scanline") >= 200);
+
+    // Scanline spans lie within the frame span
+    let frame = trace.events.iter().find(|event| event.name == "frame").unwrap();
+    for event in trace.events.iter().filter(|event| event.name == "render_scanline") {
+        assert!(event.ts >= frame.ts && event.ts + event.dur <= frame.ts + frame.dur + 1.0);
+    }
+}
@@ -0,0 +1,126 @@
+use ccsnes::sram::{convert_save, normalize_save, read_sram, write_sram, ConvertOptions, SaveFormat, SramSaver};
+use ccsnes::Emulator;
+use ccsnes_test_support::{fresh_dir, LoRom};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// LoROM with 8KB SRAM running: LDA #$5A; STA $700000; BRA *
+fn sram_writer_rom() -> Vec<u8> {
+    LoRom::new("SRAM TEST", &[0xA9, 0x5A, 0x8F, 0x00, 0x00, 0x70, 0x80, 0xFE]).sram(3).build()
+}
+
+#[test]
+fn test_sram_file_integrity() {
+    let dir = fresh_dir("ccsnes_sram_integrity");
+    let path = dir.join("game.srm");
+    let sram: Vec<u8> = (0..2048).map(|i| i as u8).collect();
+
+    write_sram(&path, &sram).unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 2048 + 8);
+    assert!(!dir.join("game.srm.tmp").exists());
+    assert_eq!(read_sram(&path).unwrap(), sram);
+
+    // A flipped byte is detected
+    let mut data = std::fs::read(&path).unwrap();
+    data[100] ^= 0xFF;
+    std::fs::write(&path, &data).unwrap();
+    assert!(read_sram(&path).is_err());
+
+    // Plain files without a footer load unchanged
+    std::fs::write(&path, &sram).unwrap();
+    assert_eq!(read_sram(&path).unwrap(), sram);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_sram_saver_debounce_and_event() {
+    let dir = fresh_dir("ccsnes_sram_saver");
+    let path = dir.join("game.srm");
+
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&sram_writer_rom()).unwrap();
+    let events = Arc::new(AtomicUsize::new(0));
+    let counter = events.clone();
+    emulator.on_sram_write(move |sram| {
+        assert_eq!(sram[0], 0x5A);
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+
+    let mut saver = SramSaver::new(path.clone(), 3, &emulator);
+    emulator.step_frame().unwrap();
+    assert!(!saver.frame(&emulator).unwrap());
+    assert!(saver.is_dirty());
+    assert_eq!(events.load(Ordering::SeqCst), 1);
+
+    // Written only after three idle frames
+    for _ in 0..2 {
+        emulator.step_frame().unwrap();
+        assert!(!saver.frame(&emulator).unwrap());
+        assert!(!path.exists());
+    }
+    emulator.step_frame().unwrap();
+    assert!(saver.frame(&emulator).unwrap());
+    assert!(!saver.is_dirty());
+    assert_eq!(read_sram(&path).unwrap()[0], 0x5A);
+    assert_eq!(events.load(Ordering::SeqCst), 1);
+
+    // Nothing left to flush
+    assert!(!saver.flush(&emulator).unwrap());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_normalize_pads_and_truncates() {
+    let options = ConvertOptions { size: 8, swap_bytes: false, fill: 0xFF };
+    let (save, report) = normalize_save(&[1, 2, 3], &options);
+    assert_eq!(save, vec![1, 2, 3, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    assert_eq!((report.input_len, report.output_len), (3, 8));
+    assert!(!report.dropped_data);
+
+    // Cutting off uniform padding loses nothing
+    let mut padded = vec![7; 4];
+    padded.extend_from_slice(&[0; 12]);
+    let options = ConvertOptions { size: 4, swap_bytes: false, fill: 0 };
+    let (save, report) = normalize_save(&padded, &options);
+    assert_eq!(save, vec![7; 4]);
+    assert!(!report.dropped_data);
+
+    // Cutting off anything else is reported
+    let (_, report) = normalize_save(&[1, 2, 3, 4, 5, 6], &options);
+    assert!(report.dropped_data);
+}
+
+#[test]
+fn test_normalize_swaps_bytes() {
+    let options = ConvertOptions { size: 6, swap_bytes: true, fill: 0 };
+    let (save, _) = normalize_save(&[1, 2, 3, 4, 5], &options);
+    assert_eq!(save, vec![2, 1, 4, 3, 5, 0]);
+}
+
+#[test]
+fn test_convert_save_round_trip() {
+    let dir = fresh_dir("ccsnes_sram_convert");
+    let original: Vec<u8> = (0..0x800).map(|i| i as u8).collect();
+    let native = dir.join("game.srm");
+    write_sram(&native, &original).unwrap();
+
+    // Export: footer stripped, padded to the flashcart's 8KB
+    let exported = dir.join("export/game.sav");
+    let options = ConvertOptions { size: 0x2000, swap_bytes: false, fill: 0 };
+    let report = convert_save(&native, &exported, &options, SaveFormat::Raw).unwrap();
+    assert!(report.had_footer);
+    assert_eq!(report.input_len, 0x800);
+    let raw = std::fs::read(&exported).unwrap();
+    assert_eq!(raw.len(), 0x2000);
+    assert_eq!(&raw[..0x800], &original[..]);
+
+    // Import: trimmed back to the game's 2KB in this emulator's format
+    let imported = dir.join("imported.srm");
+    let options = ConvertOptions { size: 0x800, swap_bytes: false, fill: 0 };
+    let report = convert_save(&exported, &imported, &options, SaveFormat::Ccsnes).unwrap();
+    assert!(!report.had_footer);
+    assert!(!report.dropped_data);
+    assert_eq!(read_sram(&imported).unwrap(), original);
+}
\ No newline at end of file
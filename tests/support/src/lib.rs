@@ -0,0 +1,84 @@
+// Test ROMs and scratch directories shared by the integration tests (the
+// ccsnes-test-support dev-dependency)
+use std::path::PathBuf;
+
+const HEADER: usize = 0x7FC0;
+
+// 32KB LoROM image, mapped at $8000-$FFFF of bank 0. The reset vector points
+// at the program, which starts at $8000; the header's checksum is left blank
+// ($0000 with complement $FFFF) unless `checksummed` is asked for.
+pub struct LoRom {
+    rom: Vec<u8>,
+    checksummed: bool,
+}
+
+impl LoRom {
+    pub fn new(title: &str, program: &[u8]) -> Self {
+        let mut rom = vec![0; 0x8000];
+        rom[..program.len()].copy_from_slice(program);
+        let mut name = [b' '; 21];
+        name[..title.len()].copy_from_slice(title.as_bytes());
+        rom[HEADER..HEADER + 21].copy_from_slice(&name);
+        rom[HEADER + 0x15] = 0x20;
+        rom[HEADER + 0x17] = 5;
+        rom[HEADER + 0x1C] = 0xFF;
+        rom[HEADER + 0x1D] = 0xFF;
+        Self { rom, checksummed: false }.vector(0xFFFC, 0x8000)
+    }
+
+    // `bytes` at `address` in $8000-$FFFF, e.g. an interrupt handler
+    pub fn at(mut self, address: u16, bytes: &[u8]) -> Self {
+        let offset = (address - 0x8000) as usize;
+        self.rom[offset..offset + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    // Point the vector at `vector` ($FFE4-$FFFF) to `target`
+    pub fn vector(self, vector: u16, target: u16) -> Self {
+        self.at(vector, &target.to_le_bytes())
+    }
+
+    // SRAM of 2^size KB (3 for 8KB)
+    pub fn sram(mut self, size: u8) -> Self {
+        self.rom[HEADER + 0x18] = size;
+        self
+    }
+
+    pub fn region(mut self, region: u8) -> Self {
+        self.rom[HEADER + 0x19] = region;
+        self
+    }
+
+    // Fill in a checksum and complement that match the image
+    pub fn checksummed(mut self) -> Self {
+        self.checksummed = true;
+        self
+    }
+
+    pub fn build(mut self) -> Vec<u8> {
+        if self.checksummed {
+            self.rom[HEADER + 0x1C..HEADER + 0x20].copy_from_slice(&[0xFF, 0xFF, 0x00, 0x00]);
+            // Same sum as CartridgeHeader::byte_sum, which this crate can't
+            // reach without depending on ccsnes
+            let sum = self.rom.iter().fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+            self.rom[HEADER + 0x1C..HEADER + 0x1E].copy_from_slice(&(!sum).to_le_bytes());
+            self.rom[HEADER + 0x1E..HEADER + 0x20].copy_from_slice(&sum.to_le_bytes());
+        }
+        self.rom
+    }
+}
+
+// LoROM spinning on BRA * from reset
+pub fn idle_rom(title: &str) -> Vec<u8> {
+    LoRom::new(title, &[0x80, 0xFE]).build()
+}
+
+// Empty directory for one test under the temp dir. The process id keeps
+// concurrent runs apart, including tests/mod.rs running the same test as
+// the test's own binary.
+pub fn fresh_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
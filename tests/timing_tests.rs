@@ -0,0 +1,215 @@
+use ccsnes::memory::timing::{access_cycles, FAST_CYCLES, SLOW_CYCLES, XSLOW_CYCLES};
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM running BRA * from reset
+fn idle_rom() -> Vec<u8> {
+    ccsnes_test_support::idle_rom("TIMING TEST")
+}
+
+#[test]
+fn test_access_cycles_by_region() {
+    assert_eq!(access_cycles(0x000123, false), SLOW_CYCLES);
+    assert_eq!(access_cycles(0x7E8000, false), SLOW_CYCLES);
+    assert_eq!(access_cycles(0x002118, false), FAST_CYCLES);
+    assert_eq!(access_cycles(0x004016, false), XSLOW_CYCLES);
+    assert_eq!(access_cycles(0x004300, false), FAST_CYCLES);
+    assert_eq!(access_cycles(0x708000, false), SLOW_CYCLES);
+
+    // FastROM only applies to the upper banks
+    assert_eq!(access_cycles(0x008000, true), SLOW_CYCLES);
+    assert_eq!(access_cycles(0x808000, true), FAST_CYCLES);
+    assert_eq!(access_cycles(0xC00000, true), FAST_CYCLES);
+    assert_eq!(access_cycles(0xC00000, false), SLOW_CYCLES);
+    assert_eq!(access_cycles(0x800123, true), SLOW_CYCLES);
+}
+
+#[test]
+fn test_frame_matches_hardware_cycle_count() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&idle_rom()).unwrap();
+    emulator.step_frame().unwrap();
+
+    // One frame of master cycles moves the PPU through exactly one frame
+    let cycles = emulator.get_cycle_count();
+    assert!((1364 * 262..1364 * 262 + 64).contains(&cycles), "{} cycles", cycles);
+    assert_eq!(emulator.ppu().get_frame_count(), 1);
+    assert_eq!(emulator.ppu().get_current_scanline(), 0);
+}
+
+#[test]
+fn test_dram_refresh_stall_each_scanline() {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&idle_rom()).unwrap();
+
+    // BRA * is two SlowROM fetches and one internal cycle
+    let per_instruction = 2 * SLOW_CYCLES + FAST_CYCLES;
+    let mut instructions = 0;
+    while emulator.ppu().get_current_scanline() < 10 {
+        emulator.step().unwrap();
+        instructions += 1;
+    }
+
+    // Everything beyond the instructions themselves is refresh time
+    let stalled = emulator.get_cycle_count() - instructions * per_instruction;
+    assert_eq!(stalled % 40, 0);
+    assert_eq!(stalled / 40, 10);
+}
+
+// LoROM running `main` from reset, with an NMI handler at $8010 that counts
+// NMIs in $0010 (INC $0010; then `handler`; RTI)
+fn nmi_rom(main: &[u8], handler: &[u8]) -> Vec<u8> {
+    let mut nmi = vec![0xEE, 0x10, 0x00];
+    nmi.extend_from_slice(handler);
+    nmi.push(0x40);
+    // Native and emulation NMI vectors
+    LoRom::new("TIMING TEST", main)
+        .at(0x8010, &nmi)
+        .vector(0xFFEA, 0x8010)
+        .vector(0xFFFA, 0x8010)
+        .build()
+}
+
+fn nmi_emulator(main: &[u8], handler: &[u8]) -> Emulator {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&nmi_rom(main, handler)).unwrap();
+    emulator
+}
+
+fn run_until(emulator: &mut Emulator, done: impl Fn(&Emulator) -> bool) {
+    while !done(emulator) {
+        emulator.step().unwrap();
+    }
+}
+
+// LDA #$80; STA $4200; BRA *
+const ENABLE_NMI: [u8; 7] = [0xA9, 0x80, 0x8D, 0x00, 0x42, 0x80, 0xFE];
+
+#[test]
+fn test_one_nmi_per_vblank() {
+    // Whether or not the handler acknowledges it in $4210
+    for handler in [&[0xAD, 0x10, 0x42][..], &[]] {
+        let mut emulator = nmi_emulator(&ENABLE_NMI, handler);
+        for _ in 0..3 {
+            emulator.step_frame().unwrap();
+        }
+        assert_eq!(emulator.read8(0x0010), 3);
+    }
+}
+
+#[test]
+fn test_no_nmi_unless_enabled() {
+    let mut emulator = nmi_emulator(&[0x80, 0xFE], &[]);
+    for _ in 0..3 {
+        emulator.step_frame().unwrap();
+    }
+    assert_eq!(emulator.read8(0x0010), 0);
+}
+
+#[test]
+fn test_rdnmi_set_in_vblank_and_cleared_by_read() {
+    let mut emulator = nmi_emulator(&[0x80, 0xFE], &[]);
+    assert_eq!(emulator.read8(0x4210), 0x02);
+    
+    run_until(&mut emulator, |e| e.ppu().is_in_vblank());
+    assert_eq!(emulator.read8(0x4210), 0x82);
+    assert_eq!(emulator.read8(0x4210), 0x02);
+    
+    // Cleared at the end of vblank even if never read
+    run_until(&mut emulator, |e| !e.ppu().is_in_vblank());
+    run_until(&mut emulator, |e| e.ppu().is_in_vblank());
+    run_until(&mut emulator, |e| !e.ppu().is_in_vblank());
+    assert_eq!(emulator.read8(0x4210), 0x02);
+}
+
+#[test]
+fn test_enabling_nmi_mid_vblank() {
+    // The flag is still set: the NMI is taken right away
+    let mut emulator = nmi_emulator(&[0x80, 0xFE], &[]);
+    run_until(&mut emulator, |e| e.ppu().get_current_scanline() == 240);
+    emulator.write8(0x4200, 0x80);
+    emulator.step().unwrap();
+    emulator.step().unwrap();
+    assert_eq!(emulator.read8(0x0010), 1);
+    
+    // The flag was read first: nothing until the next vblank
+    let mut emulator = nmi_emulator(&[0x80, 0xFE], &[]);
+    run_until(&mut emulator, |e| e.ppu().get_current_scanline() == 240);
+    emulator.read8(0x4210);
+    emulator.write8(0x4200, 0x80);
+    run_until(&mut emulator, |e| e.ppu().get_current_scanline() == 200);
+    assert_eq!(emulator.read8(0x0010), 0);
+    run_until(&mut emulator, |e| e.ppu().get_current_scanline() == 230);
+    assert_eq!(emulator.read8(0x0010), 1);
+}
+
+#[test]
+fn test_polling_rdnmi_then_enabling_skips_current_vblank() {
+    // -: LDA $4210; BPL -; LDA #$80; STA $4200; BRA *
+    let main = [0xAD, 0x10, 0x42, 0x10, 0xFB, 0xA9, 0x80, 0x8D, 0x00, 0x42, 0x80, 0xFE];
+    let mut emulator = nmi_emulator(&main, &[]);
+    emulator.step_frame().unwrap();
+    run_until(&mut emulator, |e| e.ppu().get_current_scanline() == 200);
+    assert_eq!(emulator.read8(0x0010), 0);
+    run_until(&mut emulator, |e| e.ppu().get_current_scanline() == 230);
+    assert_eq!(emulator.read8(0x0010), 1);
+}
+
+fn hvbjoy_at(bus: &mut ccsnes::memory::Bus, h: u16, v: u16) -> u8 {
+    bus.set_beam_position(h, v);
+    bus.take_access_timing();
+    bus.read8(0x4212)
+}
+
+#[test]
+fn test_hvbjoy_blank_flags_follow_the_beam() {
+    let mut bus = ccsnes::memory::Bus::new();
+    assert_eq!(hvbjoy_at(&mut bus, 100, 10), 0x00);
+    assert_eq!(hvbjoy_at(&mut bus, 300, 10), 0x40);
+    assert_eq!(hvbjoy_at(&mut bus, 100, 224), 0x00);
+    assert_eq!(hvbjoy_at(&mut bus, 100, 225), 0x80);
+    assert_eq!(hvbjoy_at(&mut bus, 300, 261), 0xC0);
+
+    // The read itself takes a dot
+    assert_eq!(hvbjoy_at(&mut bus, 272, 10), 0x00);
+    assert_eq!(hvbjoy_at(&mut bus, 273, 10), 0x40);
+}
+
+#[test]
+fn test_hvbjoy_vblank_starts_later_with_overscan() {
+    let mut bus = ccsnes::memory::Bus::new();
+    bus.set_vblank_start(240);
+    assert_eq!(hvbjoy_at(&mut bus, 100, 225), 0x00);
+    assert_eq!(hvbjoy_at(&mut bus, 100, 240), 0x80);
+    
+    bus.write8(0x4200, 0x01);
+    assert_eq!(hvbjoy_at(&mut bus, 40, 225) & 0x01, 0);
+    assert_eq!(hvbjoy_at(&mut bus, 40, 240) & 0x01, 1);
+}
+
+#[test]
+fn test_hvbjoy_auto_joypad_busy() {
+    let mut bus = ccsnes::memory::Bus::new();
+    // Only while the read is enabled
+    assert_eq!(hvbjoy_at(&mut bus, 100, 225) & 0x01, 0);
+
+    bus.write8(0x4200, 0x01);
+    assert_eq!(hvbjoy_at(&mut bus, 20, 225) & 0x01, 0);
+    assert_eq!(hvbjoy_at(&mut bus, 40, 225) & 0x01, 1);
+    assert_eq!(hvbjoy_at(&mut bus, 100, 227) & 0x01, 1);
+    // 1056 dots after (225, 32)
+    assert_eq!(hvbjoy_at(&mut bus, 60, 228) & 0x01, 1);
+    assert_eq!(hvbjoy_at(&mut bus, 70, 228) & 0x01, 0);
+    assert_eq!(hvbjoy_at(&mut bus, 40, 10) & 0x01, 0);
+}
+
+#[test]
+fn test_polling_hvbjoy_waits_for_vblank() {
+    // -: LDA $4212; BPL -; LDA $2137; LDA $213D; STA $0010; BRA *
+    let main = [
+        0xAD, 0x12, 0x42, 0x10, 0xFB, 0xAD, 0x37, 0x21, 0xAD, 0x3D, 0x21, 0x8D, 0x10, 0x00, 0x80, 0xFE,
+    ];
+    let mut emulator = nmi_emulator(&main, &[]);
+    emulator.step_frame().unwrap();
+    assert_eq!(emulator.read8(0x0010), 225);
+}
@@ -0,0 +1,215 @@
+#![cfg(feature = "tool-server")]
+
+use ccsnes::debug::tool_server::ToolServer;
+use ccsnes::Emulator;
+use ccsnes_test_support::{fresh_dir, LoRom};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+// LoROM spinning on BRA
+fn idle_rom() -> Vec<u8> {
+    ccsnes_test_support::idle_rom("TOOL SERVER TEST")
+}
+
+struct Tool {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Tool {
+    fn connect(server: &ToolServer) -> Self {
+        let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_millis(5))).unwrap();
+        Self { reader: BufReader::new(stream.try_clone().unwrap()), stream }
+    }
+
+    // Next message from the server, polling it meanwhile
+    fn receive(&mut self, server: &mut ToolServer, emulator: &mut Emulator) -> Value {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut line = String::new();
+        loop {
+            assert!(Instant::now() < deadline, "no reply from the tool server");
+            server.poll(emulator);
+            if self.reader.read_line(&mut line).is_ok() && line.ends_with('\n') {
+                return serde_json::from_str(&line).unwrap();
+            }
+        }
+    }
+
+    fn call(&mut self, server: &mut ToolServer, emulator: &mut Emulator, method: &str, params: Value) -> Value {
+        let request = json!({ "jsonrpc": "2.0", "id": 7, "method": method, "params": params });
+        writeln!(self.stream, "{}", request).unwrap();
+        let response = self.receive(server, emulator);
+        assert_eq!(response["id"], 7);
+        response
+    }
+}
+
+fn setup() -> (ToolServer, Emulator, Tool) {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&idle_rom()).unwrap();
+    let server = ToolServer::bind("127.0.0.1:0").unwrap();
+    let tool = Tool::connect(&server);
+    (server, emulator, tool)
+}
+
+#[test]
+fn test_tool_server_peek_poke() {
+    let (mut server, mut emulator, mut tool) = setup();
+
+    let response = tool.call(&mut server, &mut emulator, "poke", json!({ "address": "0x7E0010", "bytes": [1, 2, 3] }));
+    assert_eq!(response["result"]["written"], 3);
+    assert_eq!(server.client_count(), 1);
+    // The low WRAM mirror in bank $00
+    let response = tool.call(&mut server, &mut emulator, "peek", json!({ "address": "$000010", "length": 3 }));
+    assert_eq!(response["result"]["bytes"], json!([1, 2, 3]));
+    assert_eq!(response["result"]["address"], 0x10);
+
+    tool.call(&mut server, &mut emulator, "poke", json!({ "address": 0x20, "bytes": [0x7C], "space": "cgram" }));
+    assert_eq!(emulator.ppu().get_cgram()[0x20], 0x7C);
+    tool.call(&mut server, &mut emulator, "poke", json!({ "address": 0x300, "bytes": [0x55], "space": "aram" }));
+    let response = tool.call(&mut server, &mut emulator, "peek", json!({ "address": 0x300, "space": "aram" }));
+    assert_eq!(response["result"]["bytes"], json!([0x55]));
+
+    // Errors keep the connection usable
+    let response = tool.call(&mut server, &mut emulator, "poke", json!({ "address": 0, "bytes": [256] }));
+    assert_eq!(response["error"]["code"], -32602);
+    let response = tool.call(&mut server, &mut emulator, "peek", json!({ "address": 0x10000, "space": "vram" }));
+    assert_eq!(response["error"]["code"], -32602);
+    let response = tool.call(&mut server, &mut emulator, "jump", json!({}));
+    assert_eq!(response["error"]["code"], -32601);
+    writeln!(tool.stream, "not json").unwrap();
+    assert_eq!(tool.receive(&mut server, &mut emulator)["error"]["code"], -32700);
+
+    let response = tool.call(&mut server, &mut emulator, "status", Value::Null);
+    assert_eq!(response["result"]["title"], "TOOL SERVER TEST");
+}
+
+#[test]
+fn test_tool_server_registers_and_states() {
+    let (mut server, mut emulator, mut tool) = setup();
+    emulator.step_frame().unwrap();
+
+    let response = tool.call(&mut server, &mut emulator, "hashes", Value::Null);
+    assert_eq!(response["result"]["frame_hash"], format!("{:016x}", emulator.frame_hash()));
+    assert_eq!(response["result"]["audio_hash"], format!("{:016x}", emulator.audio_hash()));
+
+    let response = tool.call(&mut server, &mut emulator, "registers", Value::Null);
+    assert_eq!(response["result"]["cpu"]["pc"], emulator.cpu.registers.pc);
+    assert_eq!(response["result"]["spc700"]["pc"], emulator.apu().spc700().pc());
+
+    let response = tool.call(&mut server, &mut emulator, "load_state", Value::Null);
+    assert!(response["error"]["message"].as_str().unwrap().contains("no state"));
+
+    emulator.write8(0x7E0000, 0x11);
+    tool.call(&mut server, &mut emulator, "save_state", Value::Null);
+    emulator.write8(0x7E0000, 0x22);
+    let response = tool.call(&mut server, &mut emulator, "load_state", Value::Null);
+    assert_eq!(response["result"]["loaded"], true);
+    assert_eq!(emulator.bus().peek8(0x7E0000), 0x11);
+
+    let path = fresh_dir("ccsnes_tool_server").join("test.state");
+    let path = path.to_string_lossy();
+    tool.call(&mut server, &mut emulator, "save_state", json!({ "path": path }));
+    emulator.write8(0x7E0000, 0x33);
+    tool.call(&mut server, &mut emulator, "load_state", json!({ "path": path }));
+    assert_eq!(emulator.bus().peek8(0x7E0000), 0x11);
+}
+
+#[test]
+fn test_tool_server_frame_notifications() {
+    let (mut server, mut emulator, mut tool) = setup();
+    let response = tool.call(&mut server, &mut emulator, "subscribe", json!({ "watch": [{ "address": "0x7E0100", "length": 2 }] }));
+    assert_eq!(response["result"]["subscribed"], true);
+
+    emulator.write8(0x7E0100, 0xAB);
+    emulator.step_frame().unwrap();
+    server.end_frame(&emulator);
+    let frame = tool.receive(&mut server, &mut emulator);
+    assert_eq!(frame["method"], "frame");
+    assert_eq!(frame["params"]["frame"], emulator.get_frame_count());
+    assert_eq!(frame["params"]["watch"], json!([[0xAB, 0]]));
+    assert_eq!(frame["params"]["discontinuity"], false);
+
+    emulator.reset().unwrap();
+    emulator.step_frame().unwrap();
+    server.end_frame(&emulator);
+    assert_eq!(tool.receive(&mut server, &mut emulator)["params"]["discontinuity"], true);
+
+    // Requests without an id get no reply; unsubscribing stops the frames
+    writeln!(tool.stream, "{}", json!({ "jsonrpc": "2.0", "method": "unsubscribe" })).unwrap();
+    server.poll(&mut emulator);
+    let response = tool.call(&mut server, &mut emulator, "status", Value::Null);
+    assert!(response["result"].is_object());
+    server.end_frame(&emulator);
+    let response = tool.call(&mut server, &mut emulator, "status", Value::Null);
+    assert!(response.get("method").is_none());
+}
+
+#[test]
+fn test_tool_server_heatmap() {
+    let (mut server, mut emulator, mut tool) = setup();
+    let response = tool.call(&mut server, &mut emulator, "heatmap", Value::Null);
+    assert!(response["error"]["message"].as_str().unwrap().contains("heatmap_start"));
+
+    let response = tool.call(&mut server, &mut emulator, "heatmap_start", json!({ "window": 1 }));
+    assert_eq!(response["result"], json!({ "window": 1, "vram": false }));
+    emulator.write8(0x7E0123, 1);
+    emulator.step_frame().unwrap();
+
+    let response = tool.call(&mut server, &mut emulator, "heatmap", json!({ "bucket": 256, "top": 4 }));
+    let result = &response["result"];
+    assert_eq!(result["space"], "WRAM");
+    assert_eq!(result["frames"], 1);
+    assert_eq!(result["writes"].as_array().unwrap().len(), 0x200);
+    assert_eq!(result["writes"][1], 1);
+    assert_eq!(result["hottest"][0], json!({ "address": 0x123, "reads": 0, "writes": 1 }));
+
+    let response = tool.call(&mut server, &mut emulator, "heatmap", json!({ "space": "vram" }));
+    assert_eq!(response["error"]["code"], -32000);
+    let response = tool.call(&mut server, &mut emulator, "heatmap", json!({ "bucket": 0 }));
+    assert_eq!(response["error"]["code"], -32602);
+    tool.call(&mut server, &mut emulator, "heatmap_stop", Value::Null);
+    assert!(emulator.heatmap().is_none());
+}
+
+#[test]
+fn test_tool_server_asserts_interrupts_and_reports_them() {
+    // NMI handler at $8010: INC $10, RTI
+    let rom = LoRom::new("TOOL SERVER TEST", &[0x80, 0xFE])
+        .at(0x8010, &[0xE6, 0x10, 0x40])
+        .vector(0xFFFA, 0x8010)
+        .build();
+    let mut emulator = Emulator::new().unwrap();
+    emulator.load_rom(&rom).unwrap();
+    let mut server = ToolServer::bind("127.0.0.1:0").unwrap();
+    let mut tool = Tool::connect(&server);
+
+    let response = tool.call(&mut server, &mut emulator, "interrupts", Value::Null);
+    assert_eq!(response["result"]["interrupts"], json!([]));
+    let response = tool.call(&mut server, &mut emulator, "assert_nmi", Value::Null);
+    assert_eq!(response["result"]["asserted"], "nmi");
+    for _ in 0..4 {
+        emulator.step().unwrap();
+    }
+    assert_eq!(emulator.read8(0x7E0010), 1);
+
+    let response = tool.call(&mut server, &mut emulator, "interrupts", Value::Null);
+    let events = response["result"]["interrupts"].as_array().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!((events[0]["kind"].as_str(), events[0]["target"].as_u64()), (Some("NMI"), Some(0x8010)));
+    assert_eq!(events[1]["source"], "from NMI");
+
+    // IRQs wait on I, which reset left set
+    let response = tool.call(&mut server, &mut emulator, "assert_irq", json!({ "source": "ppu" }));
+    assert_eq!(response["result"]["asserted"], true);
+    emulator.step().unwrap();
+    let response = tool.call(&mut server, &mut emulator, "interrupts", Value::Null);
+    assert_eq!(response["result"]["interrupts"], json!([]));
+    let response = tool.call(&mut server, &mut emulator, "release_irq", json!({ "source": "ppu" }));
+    assert_eq!(response["result"]["asserted"], false);
+    let response = tool.call(&mut server, &mut emulator, "assert_irq", json!({ "source": "dsp" }));
+    assert_eq!(response["error"]["code"], -32602);
+}
@@ -0,0 +1,35 @@
+use ccsnes::frontend::native::video::Letterbox;
+
+#[test]
+fn test_letterbox_fills_a_matching_window() {
+    let view = Letterbox::fit((768, 672), (256, 224));
+    assert_eq!(view, Letterbox { x: 0.0, y: 0.0, width: 768.0, height: 672.0 });
+}
+
+#[test]
+fn test_letterbox_keeps_overscan_frames_square() {
+    // 239 lines in a window made for 224: bars at the sides
+    let view = Letterbox::fit((512, 478), (256, 239));
+    assert_eq!((view.width, view.height), (512.0, 478.0));
+    let view = Letterbox::fit((512, 448), (256, 239));
+    assert_eq!(view.height, 448.0);
+    assert!(view.width < 512.0 && view.x > 0.0);
+    assert_eq!(view.x * 2.0 + view.width, 512.0);
+    
+    // A wide window gets bars at the sides, a tall one above and below
+    let view = Letterbox::fit((1000, 224), (256, 224));
+    assert_eq!((view.x, view.y, view.width), (372.0, 0.0, 256.0));
+    let view = Letterbox::fit((256, 500), (256, 224));
+    assert_eq!((view.y, view.height), (138.0, 224.0));
+}
+
+#[test]
+fn test_letterbox_maps_window_positions_to_frame_pixels() {
+    let view = Letterbox::fit((512, 448), (256, 224));
+    assert_eq!(view.to_frame((0.0, 0.0), (256, 224)), Some((0, 0)));
+    assert_eq!(view.to_frame((511.0, 447.0), (256, 224)), Some((255, 223)));
+    
+    let view = Letterbox::fit((1000, 448), (256, 224));
+    assert_eq!(view.to_frame((100.0, 10.0), (256, 224)), None);
+    assert_eq!(view.to_frame((500.0, 224.0), (256, 224)), Some((128, 112)));
+}
@@ -0,0 +1,125 @@
+use ccsnes::debug::watchdog::{DesyncWatchdog, DEFAULT_DESYNC_FRAMES};
+use ccsnes::Emulator;
+use ccsnes_test_support::LoRom;
+
+// LoROM running `code` from $8000
+fn program_rom(code: &[u8]) -> Vec<u8> {
+    LoRom::new("WATCHDOG TEST", code).build()
+}
+
+// LDA $2140; CMP #$12; BNE -7: waits for a value the APU never sends
+const HANDSHAKE_LOOP: [u8; 7] = [0xAD, 0x40, 0x21, 0xC9, 0x12, 0xD0, 0xF9];
+
+fn run(code: &[u8], threshold: Option<u32>, frames: usize) -> Emulator {
+    let mut emulator = Emulator::new().unwrap();
+    emulator.set_desync_watchdog(threshold);
+    emulator.load_rom(&program_rom(code)).unwrap();
+    for _ in 0..frames {
+        emulator.step_frame().unwrap();
+    }
+    emulator
+}
+
+#[test]
+fn test_watchdog_on_by_default() {
+    let emulator = Emulator::new().unwrap();
+    assert_eq!(emulator.desync_watchdog(), Some(DEFAULT_DESYNC_FRAMES));
+}
+
+#[test]
+fn test_watchdog_reports_stuck_handshake() {
+    let emulator = run(&HANDSHAKE_LOOP, Some(10), 8);
+    assert!(emulator.desync().is_none());
+
+    let emulator = run(&HANDSHAKE_LOOP, Some(10), 20);
+    let report = *emulator.desync().expect("desync reported");
+    assert_eq!(report.cpu_pc, 0x008000);
+    assert_eq!(report.port, 0);
+    assert_eq!(report.frames, 10);
+    // The SPC700 sits in the IPL ROM waiting for the CPU's $CC
+    assert!(report.spc_pc >= 0xFFC0);
+    assert_eq!(report.apu_to_cpu, emulator.apu().peek_ports().1);
+    assert!(report.to_string().contains("polled $2140 for 10 frames"));
+}
+
+#[test]
+fn test_watchdog_ignores_other_loops() {
+    // BRA *
+    let emulator = run(&[0x80, 0xFE], Some(10), 20);
+    assert!(emulator.desync().is_none());
+}
+
+#[test]
+fn test_watchdog_off() {
+    let emulator = run(&HANDSHAKE_LOOP, None, 20);
+    assert_eq!(emulator.desync_watchdog(), None);
+    assert!(emulator.desync().is_none());
+}
+
+#[test]
+fn test_watchdog_clears_when_ports_move() {
+    let mut emulator = run(&HANDSHAKE_LOOP, Some(10), 20);
+    assert!(emulator.desync().is_some());
+    emulator.apu_mut().write_port(1, 0x5A);
+    emulator.step_frame().unwrap();
+    assert!(emulator.desync().is_none());
+
+    // Reset starts the count over
+    let mut emulator = run(&HANDSHAKE_LOOP, Some(10), 20);
+    emulator.reset().unwrap();
+    assert!(emulator.desync().is_none());
+}
+
+#[test]
+fn test_watchdog_fires_once_per_stall() {
+    let mut watchdog = DesyncWatchdog::new(2);
+    let ports = ([0; 4], [0xAA, 0xBB, 0, 0]);
+    let mut fired = Vec::new();
+    for frame in 0..6 {
+        for _ in 0..100 {
+            watchdog.cpu_polled(0x808000, 1);
+        }
+        fired.push(watchdog.end_frame(frame, ports, 0xFFC9).is_some());
+    }
+    // The first frame only records the ports
+    assert_eq!(fired, [false, false, true, false, false, false]);
+    let report = watchdog.report().unwrap();
+    assert_eq!((report.frame, report.cpu_pc, report.port, report.spc_pc), (2, 0x808000, 1, 0xFFC9));
+
+    // A frame without polling ends the stall
+    assert!(watchdog.end_frame(6, ports, 0xFFC9).is_none());
+    assert!(watchdog.report().is_none());
+}
+
+#[test]
+#[cfg(feature = "debugger")]
+fn test_debugger_breaks_on_desync() {
+    use ccsnes::debug::Debugger;
+
+    let emulator = run(&HANDSHAKE_LOOP, Some(10), 20);
+    let mut debugger = Debugger::new();
+    debugger.enabled = true;
+    assert!(debugger.check_desync(&emulator).is_none());
+
+    debugger.break_on_desync = true;
+    assert!(debugger.check_desync(&emulator).is_some());
+    assert!(debugger.break_on_next);
+    debugger.break_on_next = false;
+    // The same stall breaks once
+    assert!(debugger.check_desync(&emulator).is_none());
+    assert!(!debugger.break_on_next);
+}
+
+#[test]
+#[cfg(feature = "debugger")]
+fn test_desync_command() {
+    use ccsnes::debug::Debugger;
+
+    let mut emulator = run(&HANDSHAKE_LOOP, Some(10), 20);
+    let mut debugger = Debugger::new();
+    let text = debugger.execute_command(&mut emulator, "desync").unwrap();
+    assert!(text.starts_with("CPU at $008000"));
+
+    let mut emulator = run(&[0x80, 0xFE], Some(10), 2);
+    assert_eq!(debugger.execute_command(&mut emulator, "desync").unwrap(), "No CPU/APU desync detected");
+}
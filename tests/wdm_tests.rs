@@ -0,0 +1,118 @@
+use ccsnes::cpu::wdm::{WDM_ASSERT_EQ, WDM_FAIL, WDM_PASS};
+use ccsnes::cpu::{Bus65816, Cpu, CpuRegisters, TestOutcome};
+use ccsnes::memory::Bus;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+fn setup(code: &[u8]) -> (Cpu, Bus) {
+    let mut cpu = Cpu::new();
+    let mut bus = Bus::new();
+    for (i, &byte) in code.iter().enumerate() {
+        bus.write8(0x8000 + i as u32, byte);
+    }
+    bus.write16(0xFFFC, 0x8000);
+    cpu.reset(&mut bus).unwrap();
+    (cpu, bus)
+}
+
+fn run(cpu: &mut Cpu, bus: &mut Bus, instructions: usize) {
+    for _ in 0..instructions {
+        cpu.step(bus).unwrap();
+    }
+}
+
+#[test]
+fn test_wdm_is_a_two_byte_no_op_by_default() {
+    let (mut cpu, mut bus) = setup(&[0x42, 0x07, 0xA9, 0x55]); // WDM #$07; LDA #$55
+    cpu.wdm_mut().set_test_reports(true);
+    run(&mut cpu, &mut bus, 2);
+
+    assert_eq!(cpu.get_registers().get_a(), 0x55);
+    assert_eq!(cpu.get_registers().pc, 0x8004);
+    assert!(cpu.wdm().test_reports().is_empty());
+    assert_eq!(cpu.wdm().tests_passed(), None);
+}
+
+#[test]
+fn test_wdm_handler_runs_for_its_operand() {
+    let (mut cpu, mut bus) = setup(&[0x42, 0x10, 0x42, 0x11, 0x42, 0x10]);
+    let calls = Arc::new(AtomicU32::new(0));
+    let counter = calls.clone();
+    cpu.wdm_mut().set_handler(
+        0x10,
+        Box::new(move |operand: u8, regs: &mut CpuRegisters, bus: &mut dyn Bus65816| {
+            assert_eq!(operand, 0x10);
+            counter.fetch_add(1, Ordering::SeqCst);
+            // Handlers can drive the machine, e.g. to signal back to the ROM
+            regs.set_a(0x99);
+            bus.write(0x0000, 0x42);
+        }),
+    );
+    run(&mut cpu, &mut bus, 3);
+
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+    assert_eq!(cpu.get_registers().get_a(), 0x99);
+    assert_eq!(bus.read8(0x0000), 0x42);
+
+    assert!(cpu.wdm_mut().remove_handler(0x10).is_some());
+    assert!(!cpu.wdm().has_handler(0x10));
+}
+
+#[test]
+fn test_wdm_test_reports() {
+    let code = [
+        0x42, WDM_PASS,
+        0xA9, 0x05, // LDA #$05
+        0xA2, 0x05, // LDX #$05
+        0x42, WDM_ASSERT_EQ,
+        0xE8, // INX
+        0x42, WDM_ASSERT_EQ,
+        0x42, WDM_FAIL,
+    ];
+    let (mut cpu, mut bus) = setup(&code);
+    // Off by default: nothing is recorded
+    run(&mut cpu, &mut bus, 1);
+    assert!(cpu.wdm().test_reports().is_empty());
+
+    let (mut cpu, mut bus) = setup(&code);
+    cpu.wdm_mut().set_test_reports(true);
+    run(&mut cpu, &mut bus, 7);
+
+    let outcomes: Vec<_> = cpu.wdm().test_reports().iter().map(|report| report.outcome).collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            TestOutcome::Pass,
+            TestOutcome::Pass,
+            TestOutcome::NotEqual { a: 5, x: 6 },
+            TestOutcome::Fail { code: 5 },
+        ]
+    );
+    assert_eq!(cpu.wdm().test_reports()[2].pc, 0x8009);
+    assert_eq!(cpu.wdm().tests_passed(), Some(false));
+
+    assert_eq!(cpu.wdm_mut().take_test_reports().len(), 4);
+    assert!(cpu.wdm().test_reports().is_empty());
+}
+
+#[test]
+fn test_wdm_handler_overrides_test_operand() {
+    let (mut cpu, mut bus) = setup(&[0x42, WDM_FAIL]);
+    cpu.wdm_mut().set_test_reports(true);
+    cpu.wdm_mut().set_handler(WDM_FAIL, Box::new(|_: u8, _: &mut CpuRegisters, _: &mut dyn Bus65816| {}));
+    run(&mut cpu, &mut bus, 1);
+    assert!(cpu.wdm().test_reports().is_empty());
+}
+
+#[test]
+fn test_wdm_handlers_survive_reset() {
+    let (mut cpu, mut bus) = setup(&[0x42, 0x01]);
+    let calls = Arc::new(AtomicU32::new(0));
+    let counter = calls.clone();
+    cpu.wdm_mut().set_handler(0x01, Box::new(move |_: u8, _: &mut CpuRegisters, _: &mut dyn Bus65816| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    }));
+    cpu.reset(&mut bus).unwrap();
+    run(&mut cpu, &mut bus, 1);
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+}
@@ -64,17 +64,19 @@ async function initEmulator() {
     }
 }
 
-// Load ROM file
+// Load ROM file, or the built-in self test without one
 function loadROM(arrayBuffer) {
     try {
-        const romData = new Uint8Array(arrayBuffer);
-        
         if (emulator) {
             emulator.free();
         }
         
         emulator = new WasmEmulator('screen');
-        emulator.load_rom(romData);
+        if (arrayBuffer) {
+            emulator.load_rom(new Uint8Array(arrayBuffer));
+        } else {
+            emulator.load_boot_rom();
+        }
         
         isPaused = false;
         updateControlStates();
@@ -216,6 +218,12 @@ document.addEventListener('DOMContentLoaded', async () => {
         }
     });
     
+    document.getElementById('self-test-btn').addEventListener('click', () => {
+        if (loadROM(null)) {
+            document.getElementById('rom-status').textContent = 'Self test';
+        }
+    });
+    
     // Control buttons
     document.getElementById('play-pause-btn').addEventListener('click', () => {
         if (isPaused) {
@@ -0,0 +1,46 @@
+// AudioWorklet playing the samples posted by the emulator worker. Samples
+// arrive as interleaved stereo Float32Arrays through the node's port and
+// are kept in a ring buffer; underruns play silence.
+const CAPACITY = 32000; // one second of stereo frames at 32 kHz
+
+class CcsnesAudioProcessor extends AudioWorkletProcessor {
+    constructor() {
+        super();
+        this.buffer = new Float32Array(CAPACITY * 2);
+        this.read = 0;
+        this.write = 0;
+        this.length = 0;
+        this.port.onmessage = ({ data }) => this.push(data);
+    }
+
+    push(samples) {
+        for (let i = 0; i < samples.length; i++) {
+            // Drop the oldest sample when full so latency stays bounded
+            if (this.length === this.buffer.length) {
+                this.read = (this.read + 1) % this.buffer.length;
+                this.length--;
+            }
+            this.buffer[this.write] = samples[i];
+            this.write = (this.write + 1) % this.buffer.length;
+            this.length++;
+        }
+    }
+
+    process(_inputs, outputs) {
+        const [left, right = left] = outputs[0];
+        for (let i = 0; i < left.length; i++) {
+            if (this.length >= 2) {
+                left[i] = this.buffer[this.read];
+                right[i] = this.buffer[(this.read + 1) % this.buffer.length];
+                this.read = (this.read + 2) % this.buffer.length;
+                this.length -= 2;
+            } else {
+                left[i] = 0;
+                right[i] = 0;
+            }
+        }
+        return true;
+    }
+}
+
+registerProcessor('ccsnes-audio', CcsnesAudioProcessor);
@@ -0,0 +1,192 @@
+// Main-thread handle for an emulator running in web/worker.js. Methods
+// mirror WasmEmulator but return Promises, so the page stays responsive
+// while the worker emulates, draws to the transferred canvas and streams
+// audio to an AudioWorklet. Workers have no localStorage, so the
+// configuration is saved here, under the same key WasmEmulator uses.
+//
+//   const emulator = await WorkerEmulator.create('screen');
+//   await emulator.load_rom(romBytes);
+//   await emulator.start();
+const CONFIG_STORAGE_KEY = 'ccsnes.config';
+
+function saveConfig(config) {
+    localStorage.setItem(CONFIG_STORAGE_KEY, JSON.stringify(config));
+}
+
+export class WorkerEmulator {
+    constructor(worker) {
+        this.worker = worker;
+        this.nextId = 0;
+        this.pending = new Map();
+        this.audioNode = null;
+        this.fps = 0;
+        this.rumble = [[0, 0], [0, 0]];
+        // Called with worker errors that happen outside a request
+        this.onerror = (error) => console.error('Emulation error:', error);
+        worker.onmessage = ({ data }) => this.receive(data);
+    }
+
+    static async create(canvasId, config = null) {
+        const canvas = document.getElementById(canvasId);
+        if (!canvas) {
+            throw new Error('Canvas not found');
+        }
+        const offscreen = canvas.transferControlToOffscreen();
+        const worker = new Worker(new URL('./worker.js', import.meta.url), { type: 'module' });
+        const emulator = new WorkerEmulator(worker);
+        await emulator.request('init', { canvas: offscreen }, [offscreen]);
+        const saved = localStorage.getItem(CONFIG_STORAGE_KEY);
+        if (saved) {
+            await emulator.request('applyConfig', { config: JSON.parse(saved) });
+        }
+        if (config) {
+            await emulator.apply_config_json(JSON.stringify(config));
+        }
+        return emulator;
+    }
+
+    request(type, args = {}, transfer = []) {
+        const id = this.nextId++;
+        return new Promise((resolve, reject) => {
+            this.pending.set(id, { resolve, reject });
+            this.worker.postMessage({ id, type, ...args }, transfer);
+        });
+    }
+
+    receive(data) {
+        if (data.event === 'audio') {
+            if (this.audioNode) {
+                this.audioNode.port.postMessage(data.samples, [data.samples.buffer]);
+            }
+        } else if (data.event === 'stats') {
+            this.fps = data.fps;
+            this.rumble = data.rumble;
+        } else if (data.event === 'error') {
+            this.onerror(data.error);
+        } else {
+            const request = this.pending.get(data.id);
+            if (request) {
+                this.pending.delete(data.id);
+                data.ok ? request.resolve(data.result) : request.reject(new Error(data.error));
+            }
+        }
+    }
+
+    // Route audio to the speakers; call from a user gesture so the
+    // AudioContext is allowed to start
+    async enable_audio() {
+        if (this.audioNode) {
+            return;
+        }
+        const context = new AudioContext({ sampleRate: 32000 });
+        await context.audioWorklet.addModule(new URL('./audio-worklet.js', import.meta.url));
+        this.audioNode = new AudioWorkletNode(context, 'ccsnes-audio', { outputChannelCount: [2] });
+        this.audioNode.connect(context.destination);
+    }
+
+    load_rom(romData) {
+        const data = romData.slice().buffer;
+        return this.request('loadRom', { data }, [data]);
+    }
+
+    load_boot_rom() {
+        return this.request('loadBootRom');
+    }
+
+    start() {
+        return this.request('start');
+    }
+
+    pause() {
+        return this.request('pause');
+    }
+
+    reset() {
+        return this.request('reset');
+    }
+
+    run_frame() {
+        return this.request('runFrame');
+    }
+
+    handle_key_down(event) {
+        return this.request('key', { key: event.key, code: event.code, pressed: true });
+    }
+
+    handle_key_up(event) {
+        return this.request('key', { key: event.key, code: event.code, pressed: false });
+    }
+
+    set_controller(port, buttons) {
+        return this.request('setController', { port, buttons });
+    }
+
+    // Takes the same partial configuration as WasmEmulator.apply_config_json
+    async apply_config_json(json) {
+        const resetRequired = await this.request('applyConfig', { config: JSON.parse(json) });
+        saveConfig(await this.request('config'));
+        return resetRequired;
+    }
+
+    // Bind a KeyboardEvent.key or .code to a button ('a', 'start', 'up')
+    // of player 0 (default) or 1
+    async set_key_binding(jsKey, snesButton, player = 0) {
+        saveConfig(await this.request('setKeyBinding', { key: jsKey, button: snesButton, player }));
+    }
+
+    // 0.0 to 2.0, 1.0 being the emulator's own level
+    async set_volume(volume) {
+        saveConfig(await this.request('setVolume', { volume }));
+    }
+
+    async set_muted(muted) {
+        saveConfig(await this.request('setMuted', { muted }));
+    }
+
+    async get_bindings_json() {
+        return JSON.stringify(await this.request('bindings'));
+    }
+
+    async reset_config() {
+        await this.request('resetConfig');
+        localStorage.removeItem(CONFIG_STORAGE_KEY);
+    }
+
+    async config_json() {
+        return JSON.stringify(await this.request('config'));
+    }
+
+    save_state() {
+        return this.request('saveState');
+    }
+
+    load_state(stateData) {
+        const data = stateData.slice().buffer;
+        return this.request('loadState', { data }, [data]);
+    }
+
+    // Updated by the worker once per second
+    get_fps() {
+        return this.fps;
+    }
+
+    rumble_level(port) {
+        return this.rumble[port] || [0, 0];
+    }
+
+    async metrics_json() {
+        return JSON.stringify(await this.request('metrics'));
+    }
+
+    set_frame_timing(enabled) {
+        return this.request('setFrameTiming', { enabled });
+    }
+
+    terminate() {
+        this.worker.terminate();
+        for (const request of this.pending.values()) {
+            request.reject(new Error('Worker terminated'));
+        }
+        this.pending.clear();
+    }
+}
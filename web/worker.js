@@ -0,0 +1,155 @@
+// CCSNES Web Worker: runs the emulation loop off the main thread.
+//
+// Requests are {id, type, ...args}; every request gets one reply
+// {id, ok: true, result} or {id, ok: false, error}. The worker also posts
+// unsolicited events: {event: 'audio', samples} with interleaved stereo
+// Float32Array samples and {event: 'stats', fps, rumble} once per second.
+import init, { WorkerEmulator } from '../pkg/ccsnes.js';
+
+const FRAME_MS = 1000 / 60;
+
+let emulator = null;
+let running = false;
+let nextFrame = 0;
+let lastStats = 0;
+
+const handlers = {
+    async init({ canvas, config }) {
+        await init();
+        emulator = new WorkerEmulator(canvas);
+        if (config) {
+            emulator.apply_config_json(JSON.stringify(config));
+        }
+    },
+    loadRom({ data }) {
+        return emulator.load_rom(new Uint8Array(data));
+    },
+    loadBootRom() {
+        return emulator.load_boot_rom();
+    },
+    start() {
+        if (!running) {
+            running = true;
+            nextFrame = performance.now();
+            tick();
+        }
+    },
+    pause() {
+        running = false;
+    },
+    reset() {
+        emulator.reset();
+    },
+    runFrame() {
+        emulator.run_frame();
+        postAudio();
+    },
+    key({ key, code, pressed }) {
+        emulator.set_key(key, code, pressed);
+    },
+    setController({ port, buttons }) {
+        emulator.set_controller(port, buttons);
+    },
+    applyConfig({ config }) {
+        return Array.from(emulator.apply_config_json(JSON.stringify(config)));
+    },
+    config() {
+        return JSON.parse(emulator.config_json());
+    },
+    setKeyBinding({ key, button, player }) {
+        emulator.set_key_binding(key, button, player);
+        return JSON.parse(emulator.config_json());
+    },
+    setVolume({ volume }) {
+        emulator.set_volume(volume);
+        return JSON.parse(emulator.config_json());
+    },
+    setMuted({ muted }) {
+        emulator.set_muted(muted);
+        return JSON.parse(emulator.config_json());
+    },
+    bindings() {
+        return JSON.parse(emulator.get_bindings_json());
+    },
+    resetConfig() {
+        emulator.reset_config();
+    },
+    saveState() {
+        const state = emulator.save_state();
+        return transfer(state, [state.buffer]);
+    },
+    loadState({ data }) {
+        emulator.load_state(new Uint8Array(data));
+    },
+    metrics() {
+        return JSON.parse(emulator.metrics_json());
+    },
+    setFrameTiming({ enabled }) {
+        emulator.set_frame_timing(enabled);
+    },
+};
+
+// Marks a result whose buffers should be transferred instead of copied
+function transfer(value, buffers) {
+    return { transfer: true, value, buffers };
+}
+
+function postAudio() {
+    const samples = emulator.take_audio_samples();
+    if (samples.length > 0) {
+        self.postMessage({ event: 'audio', samples }, [samples.buffer]);
+    }
+}
+
+// Drift-corrected 60 Hz loop; frames are skipped rather than queued when
+// the worker falls behind
+function tick() {
+    if (!running) {
+        return;
+    }
+    try {
+        emulator.run_frame();
+        postAudio();
+    } catch (error) {
+        running = false;
+        self.postMessage({ event: 'error', error: String(error) });
+        return;
+    }
+
+    const now = performance.now();
+    if (now - lastStats >= 1000) {
+        lastStats = now;
+        self.postMessage({
+            event: 'stats',
+            fps: emulator.get_fps(),
+            rumble: [Array.from(emulator.rumble_level(0)), Array.from(emulator.rumble_level(1))],
+        });
+    }
+
+    nextFrame += FRAME_MS;
+    if (nextFrame < now - FRAME_MS) {
+        nextFrame = now;
+    }
+    setTimeout(tick, Math.max(0, nextFrame - now));
+}
+
+self.onmessage = async ({ data }) => {
+    const { id, type, ...args } = data;
+    const handler = handlers[type];
+    try {
+        if (!handler) {
+            throw new Error(`Unknown request: ${type}`);
+        }
+        if (!emulator && type !== 'init') {
+            throw new Error('Emulator not initialized');
+        }
+        const result = await handler(args);
+        if (result && result.transfer) {
+            self.postMessage({ id, ok: true, result: result.value }, result.buffers);
+        } else {
+            self.postMessage({ id, ok: true, result });
+        }
+    } catch (error) {
+        self.postMessage({ id, ok: false, error: String(error) });
+    }
+};